@@ -1,18 +1,18 @@
-/// Comprehensive Performance Benchmark Suite
-/// Tests all key components of the matching engine based on system design principles:
-/// 1. OrderBook matching (core latency)
-/// 2. Memory allocation patterns (free list efficiency)
-/// 3. Network framing overhead (LengthDelimitedCodec)
-/// 4. Serialization costs (serde_json)
-/// 5. Price level lookups (BTreeMap performance)
+//! Comprehensive Performance Benchmark Suite
+//! Tests all key components of the matching engine based on system design principles:
+//! 1. OrderBook matching (core latency)
+//! 2. Memory allocation patterns (free list efficiency)
+//! 3. Network framing overhead (LengthDelimitedCodec)
+//! 4. Serialization costs (serde_json)
+//! 5. Price level lookups (BTreeMap performance)
 
 use criterion::{black_box, criterion_group, criterion_main, BatchSize, Criterion, Throughput, BenchmarkId};
 use matching_engine::orderbook::OrderBook;
 use matching_engine::protocol::{NewOrderRequest, OrderType, TradeNotification, OrderConfirmation};
 
-/// ============================================================================
-/// 1. CORE MATCHING PERFORMANCE
-/// ============================================================================
+// ============================================================================
+// 1. CORE MATCHING PERFORMANCE
+// ============================================================================
 
 /// Benchmark: Single order add (no matching)
 /// Tests: OrderNode allocation + Vec.push + BTreeMap insertion
@@ -22,7 +22,7 @@ fn bench_order_add_no_match(c: &mut Criterion) {
 
     group.bench_function("single_order_add", |b| {
         b.iter_batched(
-            || OrderBook::new(),
+            OrderBook::new,
             |mut book| {
                 let order = NewOrderRequest {
                     user_id: 1,
@@ -31,7 +31,7 @@ fn bench_order_add_no_match(c: &mut Criterion) {
                     price: black_box(50000),
                     quantity: black_box(100),
                 };
-                book.match_order(order);
+                book.match_order(order).unwrap();
             },
             BatchSize::SmallInput,
         );
@@ -57,7 +57,7 @@ fn bench_full_match(c: &mut Criterion) {
                     order_type: OrderType::Sell,
                     price: 50000,
                     quantity: 100,
-                });
+                }).unwrap();
                 book
             },
             |mut book| {
@@ -68,7 +68,7 @@ fn bench_full_match(c: &mut Criterion) {
                     price: black_box(50000),
                     quantity: black_box(100),
                 };
-                book.match_order(buy_order);
+                book.match_order(buy_order).unwrap();
             },
             BatchSize::SmallInput,
         );
@@ -93,7 +93,7 @@ fn bench_partial_match(c: &mut Criterion) {
                     order_type: OrderType::Sell,
                     price: 50000,
                     quantity: 100,
-                });
+                }).unwrap();
                 book
             },
             |mut book| {
@@ -104,7 +104,7 @@ fn bench_partial_match(c: &mut Criterion) {
                     price: black_box(50000),
                     quantity: black_box(50), // Partial
                 };
-                book.match_order(buy_order);
+                book.match_order(buy_order).unwrap();
             },
             BatchSize::SmallInput,
         );
@@ -113,9 +113,9 @@ fn bench_partial_match(c: &mut Criterion) {
     group.finish();
 }
 
-/// ============================================================================
-/// 2. MEMORY POOL EFFICIENCY (Free List)
-/// ============================================================================
+// ============================================================================
+// 2. MEMORY POOL EFFICIENCY (Free List)
+// ============================================================================
 
 /// Benchmark: Order lifecycle - add → remove → reuse
 /// Tests: free list effectiveness
@@ -124,7 +124,7 @@ fn bench_memory_pool_reuse(c: &mut Criterion) {
 
     group.bench_function("add_remove_add_sequence", |b| {
         b.iter_batched(
-            || OrderBook::new(),
+            OrderBook::new,
             |mut book| {
                 // Add order 1
                 let order1 = NewOrderRequest {
@@ -134,7 +134,7 @@ fn bench_memory_pool_reuse(c: &mut Criterion) {
                     price: 50000,
                     quantity: 100,
                 };
-                let (_trades1, _) = book.match_order(order1);
+                let (_trades1, _) = book.match_order(order1).unwrap();
 
                 // Remove order (via complete match)
                 let order2 = NewOrderRequest {
@@ -144,7 +144,7 @@ fn bench_memory_pool_reuse(c: &mut Criterion) {
                     price: 49999,
                     quantity: 100,
                 };
-                let (_trades2, _) = book.match_order(order2);
+                let (_trades2, _) = book.match_order(order2).unwrap();
 
                 // Add order 3 - should reuse freed slot
                 let order3 = NewOrderRequest {
@@ -154,7 +154,7 @@ fn bench_memory_pool_reuse(c: &mut Criterion) {
                     price: 51000,
                     quantity: 50,
                 };
-                book.match_order(order3);
+                book.match_order(order3).unwrap();
             },
             BatchSize::SmallInput,
         );
@@ -163,9 +163,9 @@ fn bench_memory_pool_reuse(c: &mut Criterion) {
     group.finish();
 }
 
-/// ============================================================================
-/// 3. PRICE LEVEL LOOKUP PERFORMANCE (BTreeMap)
-/// ============================================================================
+// ============================================================================
+// 3. PRICE LEVEL LOOKUP PERFORMANCE (BTreeMap)
+// ============================================================================
 
 /// Benchmark: Lookup time with varying depth
 fn bench_price_level_lookup(c: &mut Criterion) {
@@ -189,7 +189,7 @@ fn bench_price_level_lookup(c: &mut Criterion) {
                                 order_type: OrderType::Sell,
                                 price: 50000 + (i as u64),
                                 quantity: 100,
-                            });
+                            }).unwrap();
                         }
                         book
                     },
@@ -202,7 +202,7 @@ fn bench_price_level_lookup(c: &mut Criterion) {
                             price: black_box(50000 + num_levels as u64),
                             quantity: black_box(1000),
                         };
-                        book.match_order(buy_order);
+                        book.match_order(buy_order).unwrap();
                     },
                     BatchSize::SmallInput,
                 );
@@ -213,9 +213,9 @@ fn bench_price_level_lookup(c: &mut Criterion) {
     group.finish();
 }
 
-/// ============================================================================
-/// 4. MULTIPLE ORDERS AT SAME PRICE (Linked List Traversal)
-/// ============================================================================
+// ============================================================================
+// 4. MULTIPLE ORDERS AT SAME PRICE (Linked List Traversal)
+// ============================================================================
 
 /// Benchmark: FIFO matching at single price level
 fn bench_fifo_order_queue(c: &mut Criterion) {
@@ -239,7 +239,7 @@ fn bench_fifo_order_queue(c: &mut Criterion) {
                                 order_type: OrderType::Sell,
                                 price: 50000,
                                 quantity: 100,
-                            });
+                            }).unwrap();
                         }
                         book
                     },
@@ -252,7 +252,7 @@ fn bench_fifo_order_queue(c: &mut Criterion) {
                             price: 50000,
                             quantity: black_box((queue_depth * 100) as u64),
                         };
-                        book.match_order(buy_order);
+                        book.match_order(buy_order).unwrap();
                     },
                     BatchSize::SmallInput,
                 );
@@ -263,9 +263,9 @@ fn bench_fifo_order_queue(c: &mut Criterion) {
     group.finish();
 }
 
-/// ============================================================================
-/// 5. ALLOCATION & DEALLOCATION COST
-/// ============================================================================
+// ============================================================================
+// 5. ALLOCATION & DEALLOCATION COST
+// ============================================================================
 
 /// Benchmark: TradeNotification allocation
 /// Tests: Vec<TradeNotification> growth cost
@@ -284,7 +284,7 @@ fn bench_trade_allocation(c: &mut Criterion) {
                     for i in 0..num_trades {
                         trades.push(TradeNotification {
                             trade_id: i as u64,
-                            symbol: "BTC/USD".to_string(),
+                            symbol: "BTC/USD".into(),
                             matched_price: 50000,
                             matched_quantity: 100,
                             buyer_user_id: 1,
@@ -292,6 +292,7 @@ fn bench_trade_allocation(c: &mut Criterion) {
                             seller_user_id: 2,
                             seller_order_id: 2,
                             timestamp: 0,
+                            is_block_trade: false,
                         });
                     }
                     black_box(trades);
@@ -303,9 +304,9 @@ fn bench_trade_allocation(c: &mut Criterion) {
     group.finish();
 }
 
-/// ============================================================================
-/// 6. SERIALIZATION COST (serde_json)
-/// ============================================================================
+// ============================================================================
+// 6. SERIALIZATION COST (serde_json)
+// ============================================================================
 
 /// Benchmark: JSON serialization of messages
 fn bench_json_serialization(c: &mut Criterion) {
@@ -315,7 +316,7 @@ fn bench_json_serialization(c: &mut Criterion) {
     group.bench_function("trade_notification_serialize", |b| {
         let trade = TradeNotification {
             trade_id: 1,
-            symbol: "BTC/USD".to_string(),
+            symbol: "BTC/USD".into(),
             matched_price: 50000,
             matched_quantity: 100,
             buyer_user_id: 1,
@@ -323,6 +324,7 @@ fn bench_json_serialization(c: &mut Criterion) {
             seller_user_id: 2,
             seller_order_id: 2,
             timestamp: 1234567890,
+            is_block_trade: false,
         };
 
         b.iter(|| {
@@ -344,9 +346,9 @@ fn bench_json_serialization(c: &mut Criterion) {
     group.finish();
 }
 
-/// ============================================================================
-/// 7. WORST-CASE SCENARIOS
-/// ============================================================================
+// ============================================================================
+// 7. WORST-CASE SCENARIOS
+// ============================================================================
 
 /// Benchmark: Worst-case price crossing (many matches)
 fn bench_worst_case_crossing(c: &mut Criterion) {
@@ -365,7 +367,7 @@ fn bench_worst_case_crossing(c: &mut Criterion) {
                         order_type: OrderType::Sell,
                         price: 50000 + i as u64,
                         quantity: 10,
-                    });
+                    }).unwrap();
                 }
                 book
             },
@@ -378,7 +380,7 @@ fn bench_worst_case_crossing(c: &mut Criterion) {
                     price: black_box(51000),
                     quantity: black_box(10000),
                 };
-                book.match_order(big_buy);
+                book.match_order(big_buy).unwrap();
             },
             BatchSize::SmallInput,
         );
@@ -1,13 +1,15 @@
-/// End-to-End Network Performance Benchmark
-/// 测试真实网络延迟，包括系统调用、内核处理等隐藏成本
-///
-/// 这个基准测试暴露当前内存中基准的缺陷
+//! End-to-End Network Performance Benchmark
+//! 测试真实网络延迟，包括系统调用、内核处理等隐藏成本
+//!
+//! 这个基准测试暴露当前内存中基准的缺陷
 
-use criterion::{black_box, criterion_group, criterion_main, Criterion, BenchmarkId};
+use criterion::{black_box, criterion_group, criterion_main, Criterion, BenchmarkId, Throughput};
 use std::io::{Read, Write};
 use std::net::{TcpListener, TcpStream};
 use std::thread;
+use matching_engine::engine::{EngineCommand, EngineOutput, MatchingEngine, OutputBatching};
 use matching_engine::protocol::{NewOrderRequest, OrderType};
+use tokio::sync::mpsc;
 
 /// 启动简单的TCP回显服务器
 fn start_echo_server(port: u16) -> thread::JoinHandle<()> {
@@ -15,26 +17,21 @@ fn start_echo_server(port: u16) -> thread::JoinHandle<()> {
         let listener = TcpListener::bind(format!("127.0.0.1:{}", port))
             .expect("无法绑定服务器");
 
-        for stream in listener.incoming() {
-            match stream {
-                Ok(mut stream) => {
-                    // 为每个连接创建一个新线程来处理
-                    thread::spawn(move || {
-                        let mut buffer = [0; 1024];
-                        // 在单个连接上循环处理多个请求
-                        while let Ok(n) = stream.read(&mut buffer) {
-                            if n == 0 {
-                                break; // 客户端关闭了连接
-                            }
-                            // 回显数据
-                            if stream.write_all(&buffer[..n]).is_err() {
-                                break; // 写入失败，客户端可能已断开
-                            }
-                        }
-                    });
+        for mut stream in listener.incoming().flatten() {
+            // 为每个连接创建一个新线程来处理
+            thread::spawn(move || {
+                let mut buffer = [0; 1024];
+                // 在单个连接上循环处理多个请求
+                while let Ok(n) = stream.read(&mut buffer) {
+                    if n == 0 {
+                        break; // 客户端关闭了连接
+                    }
+                    // 回显数据
+                    if stream.write_all(&buffer[..n]).is_err() {
+                        break; // 写入失败，客户端可能已断开
+                    }
                 }
-                Err(_) => {}
-            }
+            });
         }
     })
 }
@@ -45,37 +42,32 @@ fn start_matching_server(port: u16) -> thread::JoinHandle<()> {
         let listener = TcpListener::bind(format!("127.0.0.1:{}", port))
             .expect("无法绑定服务器");
 
-        for stream in listener.incoming() {
-            match stream {
-                Ok(mut stream) => {
-                    // 为每个连接创建一个新线程来处理
-                    thread::spawn(move || {
-                        let mut buffer = [0; 1024];
-                        // 在单个连接上循环处理多个请求
-                        while let Ok(n) = stream.read(&mut buffer) {
-                            if n == 0 {
-                                break; // 客户端关闭了连接
-                            }
-
-                            // 模拟JSON反序列化 + 匹配 + 序列化
-                            let _data = String::from_utf8_lossy(&buffer[..n]);
-
-                            // 模拟核心匹配逻辑 (~100 ns)
-                            let mut sum = 0u64;
-                            for i in 0..100 {
-                                sum = sum.wrapping_add(i);
-                            }
-
-                            // 模拟响应序列化
-                            let response = format!("{{\"result\":{}}}\n", sum);
-                            if stream.write_all(response.as_bytes()).is_err() {
-                                break; // 写入失败
-                            }
-                        }
-                    });
+        for mut stream in listener.incoming().flatten() {
+            // 为每个连接创建一个新线程来处理
+            thread::spawn(move || {
+                let mut buffer = [0; 1024];
+                // 在单个连接上循环处理多个请求
+                while let Ok(n) = stream.read(&mut buffer) {
+                    if n == 0 {
+                        break; // 客户端关闭了连接
+                    }
+
+                    // 模拟JSON反序列化 + 匹配 + 序列化
+                    let _data = String::from_utf8_lossy(&buffer[..n]);
+
+                    // 模拟核心匹配逻辑 (~100 ns)
+                    let mut sum = 0u64;
+                    for i in 0..100 {
+                        sum = sum.wrapping_add(i);
+                    }
+
+                    // 模拟响应序列化
+                    let response = format!("{{\"result\":{}}}\n", sum);
+                    if stream.write_all(response.as_bytes()).is_err() {
+                        break; // 写入失败
+                    }
                 }
-                Err(_) => {}
-            }
+            });
         }
     })
 }
@@ -169,7 +161,8 @@ fn bench_connection_reuse(c: &mut Criterion) {
 
             client.write_all(b"test").expect("写入失败");
             let mut buffer = [0; 1024];
-            client.read(&mut buffer).expect("读取失败");
+            let n = client.read(&mut buffer).expect("读取失败");
+            black_box(n);
             // 连接在这里关闭 (RAII)
         });
     });
@@ -245,6 +238,62 @@ fn bench_persistent_connection_throughput(c: &mut Criterion) {
     group.finish();
 }
 
+/// 基准: 引擎输出逐条发送 vs 批量发送
+/// 引擎线程反复挂出 10 档卖单再用一笔买单扫掉，输出由另一线程上的 tokio 任务接收（相当于网络层的广播任务）。
+/// 逐条发送时每笔成交都可能唤醒一次接收任务，批量发送时每条命令最多一次
+fn bench_engine_output_batching(c: &mut Criterion) {
+    const SWEEPS: u64 = 1_000;
+    const DEPTH: u64 = 10;
+    let mut group = c.benchmark_group("E2E - Engine Output Batching");
+    // 每轮 DEPTH 条挂单和 1 条扫单
+    group.throughput(Throughput::Elements(SWEEPS * (DEPTH + 1)));
+    let runtime = tokio::runtime::Builder::new_multi_thread().worker_threads(1).enable_all().build().unwrap();
+
+    for (name, batching) in [("unbatched", None), ("batched", Some(OutputBatching::default()))] {
+        group.bench_function(name, |b| {
+            b.iter(|| {
+                let (_commands, command_receiver) = mpsc::unbounded_channel();
+                let (output_sender, mut outputs) = mpsc::unbounded_channel();
+                let mut engine = MatchingEngine::new(command_receiver, output_sender);
+                if let Some(batching) = batching {
+                    engine = engine.with_output_batching(batching);
+                }
+                let consumer = runtime.spawn(async move {
+                    let mut trades = 0;
+                    while let Some(output) = outputs.recv().await {
+                        match output {
+                            EngineOutput::Batch(batch) => {
+                                trades += batch.iter().filter(|output| matches!(output, EngineOutput::Trade(_))).count() as u64
+                            }
+                            EngineOutput::Trade(_) => trades += 1,
+                            _ => {}
+                        }
+                    }
+                    trades
+                });
+                let engine_thread = thread::spawn(move || {
+                    let order = |user_id, order_type, price, quantity| {
+                        EngineCommand::NewOrder(NewOrderRequest { user_id, symbol: "BTC/USD".to_string(), order_type, price, quantity })
+                    };
+                    for _ in 0..SWEEPS {
+                        for level in 0..DEPTH {
+                            engine.handle_command(order(1, OrderType::Sell, 100 + level, 1));
+                            engine.flush_outputs();
+                        }
+                        engine.handle_command(order(2, OrderType::Buy, 100 + DEPTH, DEPTH));
+                        engine.flush_outputs();
+                    }
+                    // 引擎在这里释放，输出通道随之关闭
+                });
+                engine_thread.join().unwrap();
+                assert_eq!(runtime.block_on(consumer).unwrap(), SWEEPS * DEPTH);
+            });
+        });
+    }
+
+    group.finish();
+}
+
 criterion_group!(
     name = benches;
     config = Criterion::default().sample_size(10).measurement_time(std::time::Duration::from_secs(10));
@@ -253,7 +302,8 @@ criterion_group!(
         bench_application_processing,
         bench_connection_reuse,
         bench_syscall_overhead,
-        bench_persistent_connection_throughput
+        bench_persistent_connection_throughput,
+        bench_engine_output_batching
 );
 
 criterion_main!(benches);
@@ -1,14 +1,14 @@
-/// Network Layer Performance Benchmarks
-/// Tests the zero-copy networking stack impact on total latency
+//! Network Layer Performance Benchmarks
+//! Tests the zero-copy networking stack impact on total latency
 
 use criterion::{black_box, criterion_group, criterion_main, Criterion, Throughput};
-use matching_engine::protocol::{NewOrderRequest, OrderType, TradeNotification};
-use serde_json;
+use matching_engine::protocol::{ClientMessage, NewOrderRequest, OrderType, TradeNotification};
+use matching_engine::sbe::{NewOrderView, SbeNewOrder};
 use bytes::{BytesMut, BufMut};
 
-/// ============================================================================
-/// 1. JSON SERIALIZATION COST
-/// ============================================================================
+// ============================================================================
+// 1. JSON SERIALIZATION COST
+// ============================================================================
 
 fn bench_json_encode_order_request(c: &mut Criterion) {
     let mut group = c.benchmark_group("Network - JSON Encode");
@@ -53,7 +53,7 @@ fn bench_json_encode_trade_notification(c: &mut Criterion) {
     group.bench_function("trade_notification", |b| {
         let trade = TradeNotification {
             trade_id: 1,
-            symbol: "BTC/USD".to_string(),
+            symbol: "BTC/USD".into(),
             matched_price: 50000,
             matched_quantity: 100,
             buyer_user_id: 1,
@@ -61,6 +61,7 @@ fn bench_json_encode_trade_notification(c: &mut Criterion) {
             seller_user_id: 2,
             seller_order_id: 102,
             timestamp: 1234567890123,
+            is_block_trade: false,
         };
 
         b.iter(|| {
@@ -71,9 +72,9 @@ fn bench_json_encode_trade_notification(c: &mut Criterion) {
     group.finish();
 }
 
-/// ============================================================================
-/// 2. BYTESMUT BUFFER OPERATIONS
-/// ============================================================================
+// ============================================================================
+// 2. BYTESMUT BUFFER OPERATIONS
+// ============================================================================
 
 fn bench_bytesmut_push(c: &mut Criterion) {
     let mut group = c.benchmark_group("Network - BytesMut Push");
@@ -83,7 +84,7 @@ fn bench_bytesmut_push(c: &mut Criterion) {
         let data = vec![0u8; 100];
         b.iter(|| {
             let mut buf = BytesMut::with_capacity(1024);
-            buf.extend_from_slice(&black_box(&data));
+            buf.extend_from_slice(black_box(&data));
             black_box(buf);
         });
     });
@@ -113,9 +114,9 @@ fn bench_bytesmut_framing(c: &mut Criterion) {
     group.finish();
 }
 
-/// ============================================================================
-/// 3. COMBINED ENCODE/DECODE PIPELINE
-/// ============================================================================
+// ============================================================================
+// 3. COMBINED ENCODE/DECODE PIPELINE
+// ============================================================================
 
 fn bench_full_request_pipeline(c: &mut Criterion) {
     let mut group = c.benchmark_group("Network - Full Request Pipeline");
@@ -152,7 +153,7 @@ fn bench_full_response_pipeline(c: &mut Criterion) {
     group.bench_function("trade_to_json_to_bytes", |b| {
         let trade = TradeNotification {
             trade_id: 1,
-            symbol: "BTC/USD".to_string(),
+            symbol: "BTC/USD".into(),
             matched_price: 50000,
             matched_quantity: 100,
             buyer_user_id: 1,
@@ -160,6 +161,7 @@ fn bench_full_response_pipeline(c: &mut Criterion) {
             seller_user_id: 2,
             seller_order_id: 102,
             timestamp: 1234567890123,
+            is_block_trade: false,
         };
 
         b.iter(|| {
@@ -179,9 +181,9 @@ fn bench_full_response_pipeline(c: &mut Criterion) {
     group.finish();
 }
 
-/// ============================================================================
-/// 4. BROADCAST CHANNEL SIMULATION
-/// ============================================================================
+// ============================================================================
+// 4. BROADCAST CHANNEL SIMULATION
+// ============================================================================
 
 fn bench_broadcast_string_clone(c: &mut Criterion) {
     let mut group = c.benchmark_group("Network - Broadcast Clone");
@@ -198,6 +200,44 @@ fn bench_broadcast_string_clone(c: &mut Criterion) {
     group.finish();
 }
 
+// ============================================================================
+// 5. BINARY DECODE: BINCODE vs SBE
+// ============================================================================
+
+fn bench_binary_decode_order_request(c: &mut Criterion) {
+    let mut group = c.benchmark_group("Network - Binary Decode");
+
+    let order = NewOrderRequest {
+        user_id: 12345,
+        symbol: "BTC/USD".to_string(),
+        order_type: OrderType::Buy,
+        price: 50000,
+        quantity: 100,
+    };
+    let config = bincode::config::standard();
+    let bincode_bytes = bincode::encode_to_vec(ClientMessage::NewOrder(order.clone()), config).unwrap();
+    let mut sbe_bytes = [0u8; SbeNewOrder::ENCODED_LEN];
+    SbeNewOrder::from_request(&order).unwrap().encode(&mut sbe_bytes).unwrap();
+
+    group.bench_function("bincode_new_order", |b| {
+        b.iter(|| {
+            let (message, _): (ClientMessage, usize) = bincode::decode_from_slice(black_box(&bincode_bytes), config).unwrap();
+            black_box(message);
+        });
+    });
+    group.bench_function("sbe_new_order", |b| {
+        b.iter(|| black_box(SbeNewOrder::decode(black_box(&sbe_bytes)).unwrap()));
+    });
+    group.bench_function("sbe_new_order_view", |b| {
+        b.iter(|| {
+            let view = NewOrderView::new(black_box(&sbe_bytes)).unwrap();
+            black_box((view.user_id(), view.symbol(), view.price(), view.quantity()));
+        });
+    });
+
+    group.finish();
+}
+
 // Criterion Setup
 
 criterion_group!(
@@ -211,7 +251,8 @@ criterion_group!(
         bench_bytesmut_framing,
         bench_full_request_pipeline,
         bench_full_response_pipeline,
-        bench_broadcast_string_clone
+        bench_broadcast_string_clone,
+        bench_binary_decode_order_request
 );
 
 criterion_main!(benches);
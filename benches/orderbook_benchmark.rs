@@ -19,7 +19,7 @@ fn realistic_match_benchmark(c: &mut Criterion) {
             order_type: OrderType::Sell,
             price: 50000 + i as u64,
             quantity: 10,
-        });
+        }).unwrap();
     }
 
     group.bench_function("1-to-1 Match in a cloned book with 1000 levels", |b| {
@@ -38,7 +38,7 @@ fn realistic_match_benchmark(c: &mut Criterion) {
             },
             // 3. Measured Routine: 实际的撮合操作
             |(mut orderbook, order)| {
-                orderbook.match_order(black_box(order));
+                orderbook.match_order(black_box(order)).unwrap();
             },
             BatchSize::SmallInput,
         );
@@ -47,5 +47,40 @@ fn realistic_match_benchmark(c: &mut Criterion) {
     group.finish();
 }
 
-criterion_group!(benches, realistic_match_benchmark);
+// 撤单路径的吞吐：按打散的顺序撤掉一个有 10000 笔挂单的订单簿中的全部订单
+fn cancel_benchmark(c: &mut Criterion) {
+    let mut group = c.benchmark_group("OrderBook Cancel");
+
+    let book_size = 10_000u64;
+    let mut master_orderbook = OrderBook::new();
+    for i in 0..book_size {
+        master_orderbook.match_order(NewOrderRequest {
+            user_id: i + 1,
+            symbol: "BTC/USD".to_string(),
+            order_type: OrderType::Sell,
+            price: 50000 + i % 100,
+            quantity: 10,
+        }).unwrap();
+    }
+    // 订单号从 1 开始连续分配，乘以与 book_size 互素的数得到一个排列
+    let cancel_order: Vec<u64> = (0..book_size).map(|i| i * 7919 % book_size + 1).collect();
+
+    group.throughput(criterion::Throughput::Elements(book_size));
+    group.bench_function("Cancel 10000 resting orders", |b| {
+        b.iter_batched(
+            || master_orderbook.clone(),
+            |mut orderbook| {
+                for &order_id in &cancel_order {
+                    orderbook.cancel_order(black_box(order_id)).unwrap();
+                }
+                orderbook
+            },
+            BatchSize::LargeInput,
+        );
+    });
+
+    group.finish();
+}
+
+criterion_group!(benches, realistic_match_benchmark, cancel_benchmark);
 criterion_main!(benches);
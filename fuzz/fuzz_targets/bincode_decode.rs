@@ -0,0 +1,20 @@
+#![no_main]
+// 任意字节作为帧内容交给 BincodeCodec 解码：不能 panic，也不能按长度前缀无限制地分配内存；
+// 解码成功的消息重新编码后必须能解码回相同的内容
+use libfuzzer_sys::fuzz_target;
+use matching_engine::codec::{BincodeCodec, Codec};
+
+fuzz_target!(|data: &[u8]| {
+    let codec = BincodeCodec;
+    // 协议类型没有实现 PartialEq，用 Debug 输出比较
+    if let Ok(message) = codec.decode_client(data) {
+        let encoded = codec.encode_client(&message).expect("解码成功的消息必须能重新编码");
+        let decoded = codec.decode_client(&encoded).expect("重新编码的消息必须能解码");
+        assert_eq!(format!("{:?}", message), format!("{:?}", decoded));
+    }
+    if let Ok(message) = codec.decode_server(data) {
+        let encoded = codec.encode_server(&message).expect("解码成功的消息必须能重新编码");
+        let decoded = codec.decode_server(&encoded).expect("重新编码的消息必须能解码");
+        assert_eq!(format!("{:?}", message), format!("{:?}", decoded));
+    }
+});
@@ -0,0 +1,17 @@
+#![no_main]
+// 模拟服务器的读路径：任意字节流经过 LengthDelimitedCodec 分帧，再把每帧交给 BincodeCodec 解码。
+// 分帧器和解码器都不能 panic，交给解码器的帧不能超过单帧上限
+use bytes::BytesMut;
+use libfuzzer_sys::fuzz_target;
+use matching_engine::codec::{BincodeCodec, Codec, DEFAULT_MAX_FRAME_LEN};
+use tokio_util::codec::{Decoder, LengthDelimitedCodec};
+
+fuzz_target!(|data: &[u8]| {
+    let mut framer = LengthDelimitedCodec::new();
+    let mut buffer = BytesMut::from(data);
+    // 帧长度超限时分帧器返回错误，服务器随即断开连接
+    while let Ok(Some(frame)) = framer.decode(&mut buffer) {
+        assert!(frame.len() <= DEFAULT_MAX_FRAME_LEN);
+        let _ = BincodeCodec.decode_client(&frame);
+    }
+});
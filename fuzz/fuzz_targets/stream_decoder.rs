@@ -0,0 +1,44 @@
+#![no_main]
+// 把任意字节按第一个字节决定的块大小分多次送入 StreamDecoder，模拟一帧跨多次 recv 的情况。
+// 分块送入与一次性送入必须解出相同的消息序列，缓冲区不能超过单帧上限
+use libfuzzer_sys::fuzz_target;
+use matching_engine::codec::{BincodeCodec, ClientStreamDecoder, CodecError, FRAME_HEADER_LEN};
+
+// 用较小的帧上限，让长度超限的分支也能被覆盖到
+const MAX_FRAME_LEN: usize = 64 * 1024;
+
+fuzz_target!(|data: &[u8]| {
+    let Some((&chunk, stream)) = data.split_first() else { return };
+    let chunk = chunk as usize + 1;
+
+    let mut whole = ClientStreamDecoder::new(BincodeCodec).with_max_frame_len(MAX_FRAME_LEN);
+    whole.extend(stream);
+    let expected = drain(&mut whole);
+
+    let mut chunked = ClientStreamDecoder::new(BincodeCodec).with_max_frame_len(MAX_FRAME_LEN);
+    let mut actual = Vec::new();
+    for piece in stream.chunks(chunk) {
+        chunked.extend(piece);
+        actual.extend(drain(&mut chunked));
+        assert!(chunked.buffered_len() < FRAME_HEADER_LEN + MAX_FRAME_LEN + chunk);
+    }
+    assert_eq!(until_too_long(&expected), until_too_long(&actual));
+});
+
+// 帧长度超限后缓冲区被清空，之后的解析结果取决于当时已缓冲的字节，只比较在此之前的部分
+fn until_too_long(messages: &[String]) -> &[String] {
+    let end = messages.iter().position(|m| m.starts_with("Err(FrameTooLong")).map_or(messages.len(), |i| i + 1);
+    &messages[..end]
+}
+
+fn drain(decoder: &mut ClientStreamDecoder<BincodeCodec>) -> Vec<String> {
+    let mut messages = Vec::new();
+    while let Some(result) = decoder.next_message() {
+        let stop = matches!(result, Err(CodecError::FrameTooLong(_)));
+        messages.push(format!("{:?}", result));
+        if stop {
+            break;
+        }
+    }
+    messages
+}
@@ -0,0 +1,67 @@
+// 主动订单与同一价格层级上多个挂单成交时的数量分配算法
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum AllocationPolicy {
+    // 严格的时间优先
+    #[default]
+    Fifo,
+    // 按挂单剩余数量占该价格层级总量的比例分配
+    ProRata,
+    // 最早的挂单优先成交，剩余数量再对其他挂单按比例分配
+    TopOrderProRata,
+}
+
+// 将 incoming 数量分配给按时间顺序排列的挂单（resting 为各挂单的剩余数量），
+// 返回每个挂单的成交数量；分配总量为 min(incoming, 挂单总量)
+pub fn allocate(policy: AllocationPolicy, resting: &[u64], incoming: u64) -> Vec<u64> {
+    let mut fills = Vec::with_capacity(resting.len());
+    allocate_into(policy, resting, incoming, &mut fills);
+    fills
+}
+
+// 与 allocate 相同，结果写入调用方复用的缓冲区（先清空），撮合路径上不再为每个价格层级分配内存
+pub fn allocate_into(policy: AllocationPolicy, resting: &[u64], incoming: u64, fills: &mut Vec<u64>) {
+    fills.clear();
+    match policy {
+        AllocationPolicy::Fifo => fifo(resting, incoming, fills),
+        AllocationPolicy::ProRata => pro_rata(resting, incoming, fills),
+        AllocationPolicy::TopOrderProRata => {
+            let Some((&top, rest)) = resting.split_first() else {
+                return;
+            };
+            let top_fill = top.min(incoming);
+            fills.push(top_fill);
+            pro_rata(rest, incoming - top_fill, fills);
+        }
+    }
+}
+
+fn fifo(resting: &[u64], incoming: u64, fills: &mut Vec<u64>) {
+    let mut remaining = incoming;
+    fills.extend(resting.iter().map(|&quantity| {
+        let fill = quantity.min(remaining);
+        remaining -= fill;
+        fill
+    }));
+}
+
+// 按比例向下取整分配，取整产生的余量按时间顺序逐手分配。结果追加在 fills 末尾
+fn pro_rata(resting: &[u64], incoming: u64, fills: &mut Vec<u64>) {
+    let total: u64 = resting.iter().sum();
+    if incoming >= total {
+        fills.extend_from_slice(resting);
+        return;
+    }
+    let start = fills.len();
+    fills.extend(resting.iter().map(|&quantity| (quantity as u128 * incoming as u128 / total as u128) as u64));
+    // incoming < total 时每个挂单的取整结果都严格小于其数量，且余量小于挂单个数，一轮即可分完
+    let mut leftover = incoming - fills[start..].iter().sum::<u64>();
+    for (fill, &quantity) in fills[start..].iter_mut().zip(resting) {
+        if leftover == 0 {
+            break;
+        }
+        if *fill < quantity {
+            *fill += 1;
+            leftover -= 1;
+        }
+    }
+}
@@ -0,0 +1,52 @@
+use crate::protocol::DepthLevel;
+
+// 集合竞价的撮合结果
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct AuctionResult {
+    // 统一成交价
+    pub price: u64,
+    // 在该价格下可成交的数量
+    pub volume: u64,
+}
+
+// 计算集合竞价的均衡价格。
+// bids 按价格从高到低、asks 按价格从低到高排列（与 OrderBook::depth 一致）。
+// 依次选择：成交量最大 → 未成交量（买卖不平衡）最小 → 最接近参考价 → 价格较低者。
+pub fn equilibrium_price(bids: &[DepthLevel], asks: &[DepthLevel], reference_price: Option<u64>) -> Option<AuctionResult> {
+    let mut candidates: Vec<u64> = bids.iter().chain(asks.iter()).map(|level| level.price).collect();
+    candidates.sort_unstable();
+    candidates.dedup();
+
+    let mut best: Option<(AuctionResult, u64)> = None;
+    for price in candidates {
+        // 愿意以不低于该价格买入的总量，以及愿意以不高于该价格卖出的总量
+        let demand: u64 = bids.iter().filter(|level| level.price >= price).map(|level| level.quantity).sum();
+        let supply: u64 = asks.iter().filter(|level| level.price <= price).map(|level| level.quantity).sum();
+        let volume = demand.min(supply);
+        if volume == 0 {
+            continue;
+        }
+        let imbalance = demand.abs_diff(supply);
+        let candidate = AuctionResult { price, volume };
+
+        let better = match best {
+            None => true,
+            Some((current, current_imbalance)) => {
+                if volume != current.volume {
+                    volume > current.volume
+                } else if imbalance != current_imbalance {
+                    imbalance < current_imbalance
+                } else if let Some(reference) = reference_price {
+                    // 候选价格按升序遍历，距离相同时保留较低的价格
+                    price.abs_diff(reference) < current.price.abs_diff(reference)
+                } else {
+                    false
+                }
+            }
+        };
+        if better {
+            best = Some((candidate, imbalance));
+        }
+    }
+    best.map(|(result, _)| result)
+}
@@ -0,0 +1,417 @@
+// 监管审计轨迹：记录每个订单事件（客户端的下单、撤单、改单请求，引擎的确认、执行回报、撤单回报和拒绝），
+// 带微秒时间戳、用户、会话（连接编号）和客户端 IP，按事件的 UTC 日期写入每日一个的 CSV 文件
+// audit-YYYY-MM-DD.csv，供长期保存；并可按订单号查询。
+// 与命令日志（恢复用）分开：事件经通道交给后台线程写入，网络层和引擎线程不等待磁盘。
+// 引擎产生的事件不带连接信息，按用户最近一次提交请求的会话和 IP 填写
+use crate::engine::{EngineCommand, EngineOutput};
+use crate::protocol::OrderType;
+use std::collections::HashMap;
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, BufRead, BufReader, BufWriter, Read, Write};
+use std::net::{IpAddr, TcpListener, TcpStream};
+use std::path::{Path, PathBuf};
+use std::sync::mpsc as std_mpsc;
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
+
+const CSV_HEADER: &str = "timestamp_us,event,order_id,user_id,session_id,ip,symbol,side,price,quantity,detail";
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct AuditEvent {
+    // Unix 微秒
+    pub timestamp_us: u64,
+    pub event: String,
+    // 新订单请求在引擎分配订单号之前为 None
+    pub order_id: Option<u64>,
+    pub user_id: u64,
+    pub session_id: Option<u64>,
+    pub ip: Option<IpAddr>,
+    pub symbol: String,
+    pub side: Option<OrderType>,
+    pub price: Option<u64>,
+    pub quantity: Option<u64>,
+    pub detail: String,
+}
+
+impl AuditEvent {
+    fn new(event: &str, user_id: u64, symbol: &str) -> Self {
+        AuditEvent {
+            timestamp_us: now_micros(),
+            event: event.to_string(),
+            user_id,
+            symbol: symbol.to_string(),
+            ..AuditEvent::default()
+        }
+    }
+
+    pub fn to_csv(&self) -> String {
+        let optional = |value: Option<u64>| value.map(|value| value.to_string()).unwrap_or_default();
+        let side = match self.side {
+            Some(OrderType::Buy) => "buy",
+            Some(OrderType::Sell) => "sell",
+            None => "",
+        };
+        // 字段中不会出现逗号：合约名和说明中的逗号替换为分号
+        format!(
+            "{},{},{},{},{},{},{},{},{},{},{}",
+            self.timestamp_us,
+            self.event,
+            optional(self.order_id),
+            self.user_id,
+            optional(self.session_id),
+            self.ip.map(|ip| ip.to_string()).unwrap_or_default(),
+            self.symbol.replace(',', ";"),
+            side,
+            optional(self.price),
+            optional(self.quantity),
+            self.detail.replace(',', ";")
+        )
+    }
+
+    pub fn parse_csv(line: &str) -> Result<Self, String> {
+        let fields: Vec<&str> = line.split(',').collect();
+        if fields.len() != 11 {
+            return Err(format!("审计记录应有 11 列: {}", line));
+        }
+        let number = |field: &str| field.parse::<u64>().map_err(|_| format!("无效的数字: {}", field));
+        let optional = |field: &str| if field.is_empty() { Ok(None) } else { number(field).map(Some) };
+        Ok(AuditEvent {
+            timestamp_us: number(fields[0])?,
+            event: fields[1].to_string(),
+            order_id: optional(fields[2])?,
+            user_id: number(fields[3])?,
+            session_id: optional(fields[4])?,
+            ip: match fields[5] {
+                "" => None,
+                ip => Some(ip.parse().map_err(|_| format!("无效的 IP: {}", ip))?),
+            },
+            symbol: fields[6].to_string(),
+            side: match fields[7] {
+                "buy" => Some(OrderType::Buy),
+                "sell" => Some(OrderType::Sell),
+                "" => None,
+                other => return Err(format!("无效的方向: {}", other)),
+            },
+            price: optional(fields[8])?,
+            quantity: optional(fields[9])?,
+            detail: fields[10].to_string(),
+        })
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct AuditConfig {
+    // 每日 CSV 文件所在的目录，不存在时创建
+    pub dir: PathBuf,
+}
+
+enum AuditMessage {
+    Event(AuditEvent),
+    // 写完之前的全部事件并刷到文件后回复
+    Flush(std_mpsc::Sender<()>),
+}
+
+// 审计轨迹的写入端，可在网络层的多个任务间共享
+#[derive(Debug)]
+pub struct AuditTrail {
+    sender: std_mpsc::Sender<AuditMessage>,
+}
+
+impl AuditTrail {
+    // 启动后台写入线程
+    pub fn spawn(config: AuditConfig) -> io::Result<Self> {
+        fs::create_dir_all(&config.dir)?;
+        let (sender, receiver) = std_mpsc::channel();
+        thread::spawn(move || {
+            let mut writer = DailyWriter { dir: config.dir, date: String::new(), file: None };
+            if let Err(e) = writer.run(receiver) {
+                eprintln!("写入审计轨迹失败: {}", e);
+            }
+        });
+        Ok(AuditTrail { sender })
+    }
+
+    fn send(&self, event: AuditEvent) {
+        // 写入线程退出后丢弃事件，错误已在线程中报告
+        let _ = self.sender.send(AuditMessage::Event(event));
+    }
+
+    // 记录客户端通过某个连接提交的订单请求；查询类命令不是订单事件，不记录
+    pub fn record_request(&self, session_id: u64, ip: Option<IpAddr>, command: &EngineCommand) {
+        let events = match command {
+            EngineCommand::Request { command, .. }
+            | EngineCommand::Direct { command, .. }
+            | EngineCommand::Received { command, .. }
+            | EngineCommand::Traced { command, .. } => return self.record_request(session_id, ip, command),
+            EngineCommand::NewOrder(request) | EngineCommand::ReduceOnlyOrder(request) => {
+                vec![new_order_event(request, command.kind())]
+            }
+            EngineCommand::TimedOrder(request) => vec![new_order_event(&request.order, command.kind())],
+            EngineCommand::OcoOrder(request) => {
+                vec![new_order_event(&request.first, command.kind()), new_order_event(&request.second, command.kind())]
+            }
+            EngineCommand::CancelOrder(request) => {
+                let mut event = AuditEvent::new("cancel_request", request.user_id, &request.symbol);
+                event.order_id = Some(request.order_id);
+                vec![event]
+            }
+            EngineCommand::AmendOrder(request) => {
+                let mut event = AuditEvent::new("amend_request", request.user_id, &request.symbol);
+                event.order_id = Some(request.order_id);
+                event.price = Some(request.new_price);
+                event.quantity = Some(request.new_quantity);
+                vec![event]
+            }
+            EngineCommand::SpreadOrder(request) => {
+                let mut event = AuditEvent::new("new_order", request.user_id, &request.spread);
+                event.side = Some(request.order_type);
+                event.quantity = Some(request.quantity);
+                event.detail = format!("spread_order price={}", request.price);
+                vec![event]
+            }
+            EngineCommand::BasketOrder(request) => request
+                .legs
+                .iter()
+                .map(|leg| {
+                    let mut event = AuditEvent::new("new_order", request.user_id, &leg.symbol);
+                    event.side = Some(leg.order_type);
+                    event.price = Some(leg.price);
+                    event.quantity = Some(leg.quantity);
+                    event.detail = "basket_order".to_string();
+                    event
+                })
+                .collect(),
+            EngineCommand::BlockTrade(request) => {
+                let mut event = AuditEvent::new("block_trade_request", request.buyer_user_id, &request.symbol);
+                event.price = Some(request.price);
+                event.quantity = Some(request.quantity);
+                event.detail = format!("seller={}", request.seller_user_id);
+                vec![event]
+            }
+            EngineCommand::CancelUserOrders(user_id) => vec![AuditEvent::new("cancel_all_request", *user_id, "")],
+            _ => return,
+        };
+        for mut event in events {
+            event.session_id = Some(session_id);
+            event.ip = ip;
+            self.send(event);
+        }
+    }
+
+    // 记录引擎输出中的订单事件：挂单确认、执行回报、撤单回报和拒绝
+    pub fn record_output(&self, output: &EngineOutput) {
+        let event = match output {
+            EngineOutput::Response { output, .. } | EngineOutput::Direct { output, .. } => {
+                return self.record_output(output)
+            }
+            EngineOutput::Confirmation(confirmation) => {
+                let mut event = AuditEvent::new("accepted", confirmation.user_id, "");
+                event.order_id = Some(confirmation.order_id);
+                event
+            }
+            EngineOutput::ExecutionReport(report) => {
+                let mut event = AuditEvent::new("execution", report.user_id, &report.symbol);
+                event.order_id = Some(report.order_id);
+                event.side = Some(report.order_type);
+                event.price = Some(report.last_price);
+                event.quantity = Some(report.last_quantity);
+                event.detail = format!(
+                    "status={:?} trade_id={} cumulative={} leaves={}",
+                    report.status, report.trade_id, report.cumulative_quantity, report.leaves_quantity
+                );
+                event
+            }
+            EngineOutput::CancelAck(ack) => {
+                let mut event = AuditEvent::new("cancel_ack", ack.user_id, &ack.symbol);
+                event.order_id = Some(ack.order_id);
+                event.quantity = Some(ack.cancelled_quantity);
+                event.detail = format!("status={:?}", ack.status);
+                event
+            }
+            EngineOutput::Reject(reject) => {
+                let mut event = AuditEvent::new("reject", reject.user_id, &reject.symbol);
+                event.detail = format!("reason={:?}", reject.reason);
+                event
+            }
+            _ => return,
+        };
+        self.send(event);
+    }
+
+    // 等待之前记录的事件全部写入文件
+    pub fn flush(&self) {
+        let (reply, done) = std_mpsc::channel();
+        if self.sender.send(AuditMessage::Flush(reply)).is_ok() {
+            let _ = done.recv();
+        }
+    }
+}
+
+fn new_order_event(request: &crate::protocol::NewOrderRequest, kind: &str) -> AuditEvent {
+    let mut event = AuditEvent::new("new_order", request.user_id, &request.symbol);
+    event.side = Some(request.order_type);
+    event.price = Some(request.price);
+    event.quantity = Some(request.quantity);
+    event.detail = kind.to_string();
+    event
+}
+
+// 按事件日期轮换文件的写入器，只在写入线程中使用
+struct DailyWriter {
+    dir: PathBuf,
+    date: String,
+    file: Option<BufWriter<File>>,
+}
+
+impl DailyWriter {
+    fn run(&mut self, receiver: std_mpsc::Receiver<AuditMessage>) -> io::Result<()> {
+        // 用户最近一次提交请求的 (会话, IP)
+        let mut connections: HashMap<u64, (Option<u64>, Option<IpAddr>)> = HashMap::new();
+        while let Ok(message) = receiver.recv() {
+            // 取完通道中已有的事件再刷新，突发时合并写入
+            for message in std::iter::once(message).chain(receiver.try_iter()) {
+                match message {
+                    AuditMessage::Event(mut event) => {
+                        if event.session_id.is_some() {
+                            connections.insert(event.user_id, (event.session_id, event.ip));
+                        } else if let Some(&(session_id, ip)) = connections.get(&event.user_id) {
+                            (event.session_id, event.ip) = (session_id, ip);
+                        }
+                        self.write(&event)?;
+                    }
+                    AuditMessage::Flush(reply) => {
+                        self.flush()?;
+                        let _ = reply.send(());
+                    }
+                }
+            }
+            self.flush()?;
+        }
+        self.flush()
+    }
+
+    fn write(&mut self, event: &AuditEvent) -> io::Result<()> {
+        let date = utc_date(event.timestamp_us / 1_000_000);
+        if self.file.is_none() || date != self.date {
+            self.flush()?;
+            let path = self.dir.join(format!("audit-{}.csv", date));
+            let is_new = !path.exists();
+            let mut file = BufWriter::new(OpenOptions::new().create(true).append(true).open(&path)?);
+            if is_new {
+                writeln!(file, "{}", CSV_HEADER)?;
+            }
+            self.file = Some(file);
+            self.date = date;
+        }
+        let file = self.file.as_mut().expect("已打开当日文件");
+        writeln!(file, "{}", event.to_csv())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        match self.file.as_mut() {
+            Some(file) => file.flush(),
+            None => Ok(()),
+        }
+    }
+}
+
+// 按时间顺序查询某个订单的全部审计事件，扫描目录中所有的每日文件
+pub fn query_order(dir: &Path, order_id: u64) -> io::Result<Vec<AuditEvent>> {
+    let mut paths: Vec<PathBuf> = fs::read_dir(dir)?
+        .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+        .filter(|path| {
+            path.file_name()
+                .and_then(|name| name.to_str())
+                .is_some_and(|name| name.starts_with("audit-") && name.ends_with(".csv"))
+        })
+        .collect();
+    // 文件名中的日期按字典序即时间顺序
+    paths.sort();
+    let mut events = Vec::new();
+    for path in paths {
+        for line in BufReader::new(File::open(&path)?).lines() {
+            let line = line?;
+            if line.is_empty() || line == CSV_HEADER {
+                continue;
+            }
+            let event = AuditEvent::parse_csv(&line)
+                .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, format!("{}: {}", path.display(), e)))?;
+            if event.order_id == Some(order_id) {
+                events.push(event);
+            }
+        }
+    }
+    Ok(events)
+}
+
+// 在独立线程中提供按订单号查询的 HTTP 接口：GET /orders/<order_id> 返回 CSV（带表头）
+pub fn spawn_query_server(listener: TcpListener, dir: PathBuf) -> JoinHandle<()> {
+    thread::spawn(move || {
+        for stream in listener.incoming() {
+            let Ok(stream) = stream else { continue };
+            if let Err(e) = serve_query(stream, &dir) {
+                eprintln!("处理审计查询请求失败: {}", e);
+            }
+        }
+    })
+}
+
+fn serve_query(mut stream: TcpStream, dir: &Path) -> io::Result<()> {
+    stream.set_read_timeout(Some(Duration::from_secs(5)))?;
+    // 只需要请求行，读到请求头结束或缓冲区满为止
+    let mut request = [0u8; 4096];
+    let mut len = 0;
+    while len < request.len() && !request[..len].windows(4).any(|w| w == b"\r\n\r\n") {
+        let n = stream.read(&mut request[len..])?;
+        if n == 0 {
+            break;
+        }
+        len += n;
+    }
+    let request = String::from_utf8_lossy(&request[..len]);
+    let mut parts = request.split_whitespace();
+    let order_id = match (parts.next(), parts.next()) {
+        (Some("GET"), Some(path)) => path.strip_prefix("/orders/").and_then(|id| id.parse::<u64>().ok()),
+        _ => None,
+    };
+    let (status, body) = match order_id {
+        Some(order_id) => match query_order(dir, order_id) {
+            Ok(events) => {
+                let mut body = format!("{}\n", CSV_HEADER);
+                for event in events {
+                    body.push_str(&event.to_csv());
+                    body.push('\n');
+                }
+                ("200 OK", body)
+            }
+            Err(e) => ("500 Internal Server Error", format!("{}\n", e)),
+        },
+        None => ("404 Not Found", "not found\n".to_string()),
+    };
+    write!(
+        stream,
+        "HTTP/1.1 {}\r\nContent-Type: text/csv\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+        status,
+        body.len(),
+        body
+    )?;
+    stream.flush()
+}
+
+fn now_micros() -> u64 {
+    std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH).map_or(0, |elapsed| elapsed.as_micros() as u64)
+}
+
+// Unix 秒对应的 UTC 日期 YYYY-MM-DD（公历换算）
+pub fn utc_date(unix_secs: u64) -> String {
+    let days = (unix_secs / 86_400) as i64 + 719_468;
+    let era = days.div_euclid(146_097);
+    let day_of_era = days.rem_euclid(146_097);
+    let year_of_era = (day_of_era - day_of_era / 1_460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let month_index = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * month_index + 2) / 5 + 1;
+    let month = if month_index < 10 { month_index + 3 } else { month_index - 9 };
+    let year = year_of_era + era * 400 + i64::from(month <= 2);
+    format!("{:04}-{:02}-{:02}", year, month, day)
+}
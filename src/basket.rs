@@ -0,0 +1,30 @@
+// 篮子订单：跨合约的多条腿要么全部成交，要么整单拒绝。
+// 引擎在单线程上顺序处理命令，一条篮子订单分两个阶段：先按各腿订单簿的现有深度核对
+// 每条腿能否在限价内全部成交（预留），全部满足后再逐条提交腿订单（提交）。
+// 两个阶段在同一次命令处理中完成，中间不会有其他订单改变订单簿
+use crate::protocol::{BasketOrderRequest, DepthLevel, OrderType, RejectReason};
+use std::collections::HashSet;
+
+// 检查篮子本身的结构：至少一条腿，同一合约不能出现在多条腿中
+pub fn check_legs(request: &BasketOrderRequest) -> Result<(), RejectReason> {
+    if request.legs.is_empty() {
+        return Err(RejectReason::InvalidBasket);
+    }
+    let mut symbols = HashSet::with_capacity(request.legs.len());
+    if !request.legs.iter().all(|leg| symbols.insert(leg.symbol.as_str())) {
+        return Err(RejectReason::InvalidBasket);
+    }
+    Ok(())
+}
+
+// levels 为腿订单要吃的对手盘，按由优到劣排列；返回限价内可以成交的总数量
+pub fn fillable_within(order_type: OrderType, limit_price: u64, levels: &[DepthLevel]) -> u64 {
+    levels
+        .iter()
+        .take_while(|level| match order_type {
+            OrderType::Buy => level.price <= limit_price,
+            OrderType::Sell => level.price >= limit_price,
+        })
+        .map(|level| level.quantity)
+        .sum()
+}
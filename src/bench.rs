@@ -0,0 +1,132 @@
+// 内置的撮合基准测试（matching-engine bench）：在当前线程直接驱动撮合引擎，
+// 不经过网络和命令通道，用于在部署前验证硬件的吞吐量和撮合延迟
+use crate::engine::{EngineCommand, EngineOutput, MatchingEngine};
+use crate::network;
+use crate::protocol::ClientMessage;
+use crate::workload::{WorkloadGenerator, WorkloadProfile};
+use hdrhistogram::Histogram;
+use std::time::{Duration, Instant};
+use tokio::sync::mpsc;
+
+// 延迟直方图的上限（纳秒）和精度
+const MAX_LATENCY_NANOS: u64 = 10_000_000_000;
+const LATENCY_SIGFIG: u8 = 3;
+
+// 基准测试参数。负载沿用 WorkloadProfile（connections 为模拟的用户数，rate 不生效），
+// 另外支持 tick=<最小变动价位>，应用到所有合约的订单簿
+#[derive(Debug, Clone)]
+pub struct BenchConfig {
+    pub profile: WorkloadProfile,
+    pub tick_size: Option<u64>,
+}
+
+impl BenchConfig {
+    // 解析 "key=value" 参数，例如 duration=5 symbols=BTC/USD,ETH/USD cancel=0.2 tick=5
+    pub fn parse<'a>(args: impl IntoIterator<Item = &'a str>) -> Result<Self, String> {
+        let mut tick_size = None;
+        let mut workload = Vec::new();
+        for arg in args {
+            match arg.split_once('=') {
+                Some(("tick", value)) => {
+                    let tick: u64 = value.parse().map_err(|_| format!("无效的参数值: {}", arg))?;
+                    if tick == 0 {
+                        return Err("最小变动价位必须大于 0".to_string());
+                    }
+                    tick_size = Some(tick);
+                }
+                _ => workload.push(arg),
+            }
+        }
+        let profile = WorkloadProfile::parse(workload)?;
+        if profile.connections == 0 {
+            return Err("至少需要一个用户".to_string());
+        }
+        Ok(BenchConfig { profile, tick_size })
+    }
+}
+
+pub struct BenchReport {
+    pub commands: u64,
+    pub trades: u64,
+    pub rejects: u64,
+    pub elapsed: Duration,
+    // 每条命令从交给引擎到输出全部取出的耗时（纳秒）
+    pub latencies: Histogram<u64>,
+}
+
+impl BenchReport {
+    pub fn ops_per_sec(&self) -> f64 {
+        self.commands as f64 / self.elapsed.as_secs_f64()
+    }
+
+    pub fn print(&self) {
+        println!("命令数: {} ({:.0} 笔/秒)", self.commands, self.ops_per_sec());
+        println!("成交数: {} ({:.0} 笔/秒)", self.trades, self.trades as f64 / self.elapsed.as_secs_f64());
+        println!("拒绝数: {}", self.rejects);
+        println!("撮合延迟:");
+        for (label, quantile) in [("p50", 0.5), ("p99", 0.99), ("p999", 0.999)] {
+            println!("  {:<5} {:>10.2} µs", label, self.latencies.value_at_quantile(quantile) as f64 / 1000.0);
+        }
+        println!("  max   {:>10.2} µs", self.latencies.max() as f64 / 1000.0);
+    }
+}
+
+// 按配置的时长持续生成请求并交给引擎处理；configure 可以应用与服务器相同的引擎设置
+pub fn run(config: &BenchConfig, configure: impl FnOnce(MatchingEngine) -> MatchingEngine) -> BenchReport {
+    let (_commands, command_receiver) = mpsc::unbounded_channel();
+    let (output_sender, mut outputs) = mpsc::unbounded_channel();
+    let mut engine = configure(MatchingEngine::new(command_receiver, output_sender));
+    if let Some(tick_size) = config.tick_size {
+        for symbol in &config.profile.symbols {
+            engine = engine.with_tick_size(symbol, tick_size);
+        }
+    }
+
+    let mut generators: Vec<WorkloadGenerator> = (1..=config.profile.connections as u64)
+        .map(|user_id| WorkloadGenerator::new(config.profile.clone(), user_id))
+        .collect();
+    let mut latencies = Histogram::new_with_bounds(1, MAX_LATENCY_NANOS, LATENCY_SIGFIG).expect("直方图参数有效");
+    let (mut commands, mut trades, mut rejects) = (0u64, 0u64, 0u64);
+    let mut received = Vec::new();
+
+    let started = Instant::now();
+    let deadline = started + config.profile.duration;
+    let mut now = started;
+    while now < deadline {
+        let user = commands as usize % generators.len();
+        let command = match generators[user].next_message(now) {
+            ClientMessage::NewOrder(request) => EngineCommand::NewOrder(request),
+            ClientMessage::CancelOrder(request) => EngineCommand::CancelOrder(request),
+            ClientMessage::AmendOrder(request) => EngineCommand::AmendOrder(request),
+            other => unreachable!("负载生成器不会生成 {:?}", other),
+        };
+
+        let sent = Instant::now();
+        engine.handle_command(command);
+        engine.flush_outputs();
+        while let Ok(output) = outputs.try_recv() {
+            match output {
+                EngineOutput::Batch(batch) => received.extend(batch),
+                output => received.push(output),
+            }
+        }
+        now = Instant::now();
+        latencies.saturating_record((now - sent).as_nanos() as u64);
+        commands += 1;
+
+        // 回报交给各用户的生成器，用于跟踪挂单以生成撤单和改单
+        for output in received.drain(..) {
+            match output {
+                EngineOutput::Trade(_) => trades += 1,
+                EngineOutput::Reject(_) => rejects += 1,
+                _ => {}
+            }
+            let message = network::server_message(output);
+            for generator in generators.iter_mut() {
+                generator.on_server_message(&message, now);
+            }
+        }
+    }
+
+    BenchReport { commands, trades, rejects, elapsed: started.elapsed(), latencies }
+}
@@ -0,0 +1,42 @@
+use matching_engine::book_analysis;
+use std::fs::File;
+use std::io::BufReader;
+
+// 用法: book_analyzer <深度快照.ndjson> [symbol=BTC/USD] [levels=10]
+// 读取 MATCHING_ENGINE_BOOK_EXPORT 导出的文件，分析每个合约最新的一份快照。
+// 存在交叉的订单簿时以非零状态码退出，便于在巡检脚本中使用
+fn main() {
+    let mut path = None;
+    let mut symbol = None;
+    let mut levels = 10;
+    for arg in std::env::args().skip(1) {
+        match arg.split_once('=') {
+            Some(("symbol", value)) => symbol = Some(value.to_string()),
+            Some(("levels", value)) => {
+                levels = value.parse().unwrap_or_else(|_| panic!("无效的档位数: {}", value));
+            }
+            Some(_) => panic!("未知参数: {}", arg),
+            None => path = Some(arg),
+        }
+    }
+    let Some(path) = path else {
+        eprintln!("用法: book_analyzer <深度快照.ndjson> [symbol=X] [levels=N]");
+        std::process::exit(2);
+    };
+
+    let file = File::open(&path).unwrap_or_else(|e| panic!("无法打开 {}: {}", path, e));
+    let snapshots = book_analysis::latest_snapshots(BufReader::new(file)).unwrap_or_else(|e| panic!("{}: {}", path, e));
+
+    let mut crossed = false;
+    let mut total_bytes = 0;
+    for snapshot in snapshots.values().filter(|s| symbol.as_ref().is_none_or(|symbol| &s.symbol == symbol)) {
+        print!("{}", book_analysis::render_report(snapshot, levels));
+        println!();
+        crossed |= book_analysis::is_crossed(snapshot);
+        total_bytes += book_analysis::estimate_memory(snapshot);
+    }
+    println!("合计估算内存 {:.1} KiB", total_bytes as f64 / 1024.0);
+    if crossed {
+        std::process::exit(1);
+    }
+}
@@ -1,158 +1,265 @@
+use bytes::Bytes;
+use futures::stream::SplitSink;
 use futures::{SinkExt, StreamExt};
-use matching_engine::protocol::{ClientMessage, NewOrderRequest, OrderType, ServerMessage};
-use rand::Rng;
+use hdrhistogram::Histogram;
+use matching_engine::load_script::{LoadScript, MarketView, ScriptAction};
+use matching_engine::protocol::{CancelOrderRequest, ClientMessage, NewOrderRequest, ServerMessage};
+use matching_engine::workload::{WorkloadGenerator, WorkloadProfile};
+use parking_lot::Mutex;
 use std::net::SocketAddr;
 use std::sync::atomic::{AtomicU64, Ordering};
 use std::sync::Arc;
-use std::time::{Duration, Instant};
+use std::time::Instant;
 use tokio::net::TcpStream;
-use tokio::sync::mpsc;
 use tokio_util::codec::{Framed, LengthDelimitedCodec};
 use bincode::config;
 
 // --- 配置 ---
-const NUM_CLIENTS: u32 = 8; // 模拟的并发客户端数量
-const TEST_DURATION: Duration = Duration::from_secs(10); // 测试持续时间
 const SERVER_ADDR: &str = "127.0.0.1:8080";
+const SYMBOL: &str = "BTC/USD";
+// 延迟直方图的上限（纳秒）和精度
+const MAX_LATENCY_NANOS: u64 = 60_000_000_000;
+const LATENCY_SIGFIG: u8 = 3;
 
+// 用法: load_generator [场景脚本.rhai] [key=value ...]
+// 参数见 WorkloadProfile，例如 connections=16 rate=2000 symbols=BTC/USD,ETH/USD cancel=0.2 replace=0.1。
+// 指定脚本时由脚本决定每个连接的请求，只使用 connections 和 duration 参数
 #[tokio::main]
 async fn main() {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let (scripts, settings): (Vec<&str>, Vec<&str>) =
+        args.iter().map(String::as_str).partition(|arg| arg.ends_with(".rhai"));
+    let profile = WorkloadProfile::parse(settings).unwrap_or_else(|e| panic!("{}", e));
+
     println!("启动吞吐量测试...");
-    println!("模拟客户端数量: {}", NUM_CLIENTS);
-    println!("测试持续时间: {:?}", TEST_DURATION);
+    println!("模拟客户端数量: {}", profile.connections);
+    println!("测试持续时间: {:?}", profile.duration);
+
+    let script = scripts.first().map(|path| {
+        let source = std::fs::read_to_string(path).unwrap_or_else(|e| panic!("无法读取脚本 {}: {}", path, e));
+        println!("场景脚本: {}", path);
+        Arc::new(LoadScript::compile(&source).unwrap_or_else(|e| panic!("{}", e)))
+    });
+    if script.is_none() {
+        println!("合约: {}", profile.symbols.join(", "));
+        if profile.rate > 0.0 {
+            println!("每连接平均速率: {} 笔/秒（泊松到达）", profile.rate);
+        } else {
+            println!("每连接速率: 不限速");
+        }
+        println!("撤单比例: {}，改单比例: {}", profile.cancel_ratio, profile.replace_ratio);
+    }
 
     let trade_counter = Arc::new(AtomicU64::new(0));
-    let (latency_tx, mut latency_rx) = mpsc::channel(NUM_CLIENTS as usize * 100);
+    let request_counter = Arc::new(AtomicU64::new(0));
+    let deadline = Instant::now() + profile.duration;
 
     let mut handles = Vec::new();
-
-    for i in 0..NUM_CLIENTS {
+    for i in 0..profile.connections {
         let trade_counter = trade_counter.clone();
-        let latency_tx = latency_tx.clone();
-        let handle = tokio::spawn(async move {
-            run_client(i, trade_counter, latency_tx).await;
-        });
-        handles.push(handle);
+        let request_counter = request_counter.clone();
+        let script = script.clone();
+        let profile = profile.clone();
+        handles.push(tokio::spawn(async move {
+            run_client(i, profile, deadline, trade_counter, request_counter, script).await
+        }));
     }
 
-    // 等待测试结束
-    tokio::time::sleep(TEST_DURATION).await;
+    // 等待所有连接结束，合并各连接的延迟直方图
+    let mut latencies = new_histogram();
+    for handle in handles {
+        if let Ok(histogram) = handle.await {
+            latencies.add(&histogram).expect("直方图参数一致");
+        }
+    }
 
     // 测试结束，计算结果
+    let seconds = profile.duration.as_secs_f64();
     let total_trades = trade_counter.load(Ordering::Relaxed);
-    let throughput = total_trades as f64 / TEST_DURATION.as_secs_f64();
-
-    // 收集并计算平均延迟
-    let mut latencies = Vec::new();
-    while let Ok(latency) = latency_rx.try_recv() {
-        latencies.push(latency);
-    }
-    let avg_latency = if !latencies.is_empty() {
-        latencies.iter().sum::<u128>() as f64 / latencies.len() as f64
-    } else {
-        0.0
-    };
+    let total_requests = request_counter.load(Ordering::Relaxed);
 
     println!("\n--- 测试结果 ---");
+    println!("总请求数: {} ({:.2} 笔/秒)", total_requests, total_requests as f64 / seconds);
     println!("总撮合交易数: {}", total_trades);
-    println!("吞吐量 (TPS): {:.2}", throughput);
-    println!("平均端到端延迟: {:.2} µs", avg_latency / 1000.0);
+    println!("吞吐量 (TPS): {:.2}", total_trades as f64 / seconds);
+    if latencies.is_empty() {
+        println!("没有延迟样本");
+    } else {
+        println!("应答延迟 (样本数 {}):", latencies.len());
+        for quantile in [0.5, 0.9, 0.99, 0.999] {
+            let micros = latencies.value_at_quantile(quantile) as f64 / 1000.0;
+            println!("  p{:<5} {:>10.2} µs", quantile * 100.0, micros);
+        }
+        println!("  max    {:>10.2} µs", latencies.max() as f64 / 1000.0);
+        println!("  mean   {:>10.2} µs", latencies.mean() / 1000.0);
+    }
 
-    // 可以在这里中止所有任务，但为了简单起见，我们直接退出进程
+    // 连接上可能还有未读完的回报，直接退出进程
     std::process::exit(0);
 }
 
-
+fn new_histogram() -> Histogram<u64> {
+    Histogram::new_with_bounds(1, MAX_LATENCY_NANOS, LATENCY_SIGFIG).expect("直方图参数有效")
+}
 
 async fn run_client(
     client_id: u32,
+    profile: WorkloadProfile,
+    deadline: Instant,
     trade_counter: Arc<AtomicU64>,
-    latency_tx: mpsc::Sender<u128>,
-) {
+    request_counter: Arc<AtomicU64>,
+    script: Option<Arc<LoadScript>>,
+) -> Histogram<u64> {
     let addr: SocketAddr = SERVER_ADDR.parse().unwrap();
     let stream = match TcpStream::connect(addr).await {
         Ok(s) => s,
         Err(e) => {
             eprintln!("[客户端 {}] 连接失败: {}", client_id, e);
-            return;
+            return new_histogram();
         }
     };
 
     let framed = Framed::new(stream, LengthDelimitedCodec::new());
     let (mut writer, mut reader) = framed.split();
 
-    let (order_time_tx, mut order_time_rx) = mpsc::channel::<(u64, Instant)>(1000);
     let config = config::standard();
+    let user_id = client_id as u64;
+    // 由服务器回报维护的行情和挂单状态，供场景脚本使用
+    let view = Arc::new(Mutex::new(MarketView::default()));
+    let reader_view = view.clone();
+    let generator = Arc::new(Mutex::new(WorkloadGenerator::new(profile, user_id)));
+    let reader_generator = generator.clone();
 
-    // 监听服务器响应的任务
-    tokio::spawn(async move {
-        let mut sent_orders = std::collections::HashMap::new();
+    // 监听服务器响应的任务，结束时返回本连接的应答延迟直方图
+    let reader_handle = tokio::spawn(async move {
+        let mut latencies = new_histogram();
         loop {
             tokio::select! {
-                Some((order_id, time)) = order_time_rx.recv() => {
-                    sent_orders.insert(order_id, time);
-                }
-                Some(Ok(buf)) = reader.next() => {
-                    match bincode::decode_from_slice(&buf, config) {
-                        Ok((decoded, _len)) => {
-                            match decoded {
-                                ServerMessage::Trade(trade) => {
-                                    trade_counter.fetch_add(1, Ordering::Relaxed);
-                                    // 估算延迟
-                                    if let Some(start_time) = sent_orders.get(&trade.buyer_order_id).or_else(|| sent_orders.get(&trade.seller_order_id)) {
-                                        let latency = start_time.elapsed().as_nanos();
-                                        let _ = latency_tx.send(latency).await;
-                                    }
-                                }
-                                ServerMessage::Confirmation(_conf) => {
-                                    // 可以在这里处理挂单确认的延迟
-                                }
-                            }
-                        }
+                _ = tokio::time::sleep_until(deadline.into()) => break,
+                frame = reader.next() => {
+                    let Some(Ok(buf)) = frame else { break };
+                    let decoded: ServerMessage = match bincode::decode_from_slice(&buf, config) {
+                        Ok((decoded, _len)) => decoded,
                         Err(e) => {
                             eprintln!("Bincode decoding error in load_generator: {:?}", e);
+                            continue;
+                        }
+                    };
+                    let now = Instant::now();
+                    if let Some(latency) = reader_generator.lock().on_server_message(&decoded, now) {
+                        latencies.saturating_record(latency.as_nanos() as u64);
+                    }
+                    match decoded {
+                        ServerMessage::Trade(trade) => {
+                            // 成交会推送给所有连接，只由买方所在的连接计数
+                            if trade.buyer_user_id == user_id {
+                                trade_counter.fetch_add(1, Ordering::Relaxed);
+                            }
+                            reader_view.lock().last_price = Some(trade.matched_price);
+                        }
+                        ServerMessage::Confirmation(conf) if conf.user_id == user_id => {
+                            reader_view.lock().open_orders.push(conf.order_id);
+                        }
+                        ServerMessage::CancelAck(ack) if ack.user_id == user_id => {
+                            reader_view.lock().open_orders.retain(|&id| id != ack.order_id);
                         }
+                        _ => {}
                     }
                 }
-                else => break,
             }
         }
+        latencies
     });
 
-    // 发送订单的任务
-    let mut order_id_counter: u64 = (client_id as u64) << 32;
+    match script {
+        Some(script) => run_script(client_id, &script, &view, &mut writer, deadline, &request_counter).await,
+        None => run_workload(&generator, &mut writer, deadline, &request_counter).await,
+    }
+
+    reader_handle.await.unwrap_or_else(|_| new_histogram())
+}
+
+type Writer = SplitSink<Framed<TcpStream, LengthDelimitedCodec>, Bytes>;
+
+// 按负载模型发送请求，直到测试结束或连接断开
+async fn run_workload(
+    generator: &Mutex<WorkloadGenerator>,
+    writer: &mut Writer,
+    deadline: Instant,
+    request_counter: &AtomicU64,
+) {
+    let config = config::standard();
+    // 按累计的计划发送时间等待，而不是逐笔 sleep，避免定时器精度拉低高速率下的实际速率
+    let mut next_send = Instant::now();
     loop {
-        order_id_counter += 1;
-        let (order, order_id) = {
-            let mut rng = rand::thread_rng();
-            let order_type = if rng.gen::<bool>() { OrderType::Buy } else { OrderType::Sell };
-            let price = match order_type {
-                OrderType::Buy => rng.gen_range(49990..=50000),
-                OrderType::Sell => rng.gen_range(50000..=50010),
-            };
-            let order = NewOrderRequest {
-                user_id: client_id as u64,
-                symbol: "BTC/USD".to_string(),
-                order_type,
-                price,
-                quantity: rng.gen_range(1..=5),
-            };
-            (order, order_id_counter)
-        };
+        next_send += generator.lock().next_delay();
+        if next_send >= deadline {
+            return;
+        }
+        if next_send > Instant::now() {
+            tokio::time::sleep_until(next_send.into()).await;
+        }
+        let message = generator.lock().next_message(Instant::now());
+        let encoded_msg = bincode::encode_to_vec(message, config).expect("消息编码失败");
+        if writer.send(encoded_msg.into()).await.is_err() {
+            return; // 连接断开
+        }
+        request_counter.fetch_add(1, Ordering::Relaxed);
+    }
+}
 
-        let client_message = ClientMessage::NewOrder(order);
-        match bincode::encode_to_vec(client_message, config) {
-            Ok(encoded_msg) => {
-                if writer.send(encoded_msg.into()).await.is_ok() {
-                    // 记录发送时间，用于计算延迟
-                    let _ = order_time_tx.send((order_id, Instant::now())).await;
-                } else {
-                    break; // 连接断开
-                }
-            }
+// 由脚本驱动的场景
+async fn run_script(
+    client_id: u32,
+    script: &LoadScript,
+    view: &Mutex<MarketView>,
+    writer: &mut Writer,
+    deadline: Instant,
+    request_counter: &AtomicU64,
+) {
+    let config = config::standard();
+    let mut tick = 0;
+    while Instant::now() < deadline {
+        let snapshot = MarketView { tick, ..view.lock().clone() };
+        tick += 1;
+        let actions = match script.next_actions(client_id, &snapshot) {
+            Ok(actions) => actions,
             Err(e) => {
-                eprintln!("Bincode encoding error in load_generator: {:?}", e);
+                eprintln!("[客户端 {}] {}", client_id, e);
+                return;
+            }
+        };
+        if actions.is_empty() {
+            tokio::task::yield_now().await;
+        }
+        for action in actions {
+            let message = match action {
+                ScriptAction::Order { order_type, price, quantity } => ClientMessage::NewOrder(NewOrderRequest {
+                    user_id: client_id as u64,
+                    symbol: SYMBOL.to_string(),
+                    order_type,
+                    price,
+                    quantity,
+                }),
+                ScriptAction::Cancel { order_id } => {
+                    // 先从本地状态中移除，避免脚本在撤单回报到达前重复撤单
+                    view.lock().open_orders.retain(|&id| id != order_id);
+                    ClientMessage::CancelOrder(CancelOrderRequest {
+                        user_id: client_id as u64,
+                        symbol: SYMBOL.to_string(),
+                        order_id,
+                    })
+                }
+                ScriptAction::Sleep(duration) => {
+                    tokio::time::sleep(duration).await;
+                    continue;
+                }
+            };
+            let encoded_msg = bincode::encode_to_vec(message, config).expect("消息编码失败");
+            if writer.send(encoded_msg.into()).await.is_err() {
+                return; // 连接断开
             }
+            request_counter.fetch_add(1, Ordering::Relaxed);
         }
     }
 }
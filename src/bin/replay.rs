@@ -0,0 +1,40 @@
+use matching_engine::replay::{self, Journal, ReplayConfig};
+
+// 用法: replay <记录文件.csv> [speed=N]
+// speed=1 按原始节奏回放，speed=10 为十倍速，默认 speed=0 尽快回放。
+// 回放结果与记录的成交不一致时以非零状态码退出，可用于回归测试
+fn main() {
+    let mut path = None;
+    let mut config = ReplayConfig::default();
+    for arg in std::env::args().skip(1) {
+        match arg.split_once('=') {
+            Some(("speed", value)) => {
+                config.speed = value.parse().unwrap_or_else(|_| panic!("无效的回放速度: {}", value));
+            }
+            Some(_) => panic!("未知参数: {}", arg),
+            None => path = Some(arg),
+        }
+    }
+    let Some(path) = path else {
+        eprintln!("用法: replay <记录文件.csv> [speed=N]");
+        std::process::exit(2);
+    };
+
+    let content = std::fs::read_to_string(&path).unwrap_or_else(|e| panic!("无法读取记录文件 {}: {}", path, e));
+    let journal = Journal::parse(&content).unwrap_or_else(|e| panic!("{}: {}", path, e));
+    println!("回放 {}: {} 条事件，速度 {}", path, journal.entries.len(), config.speed);
+
+    let report = replay::replay(&journal, config);
+    println!("命令数: {}", report.commands);
+    println!("成交数: {}", report.trades.len());
+    println!("耗时: {:?} ({:.0} 条/秒)", report.elapsed, report.commands_per_sec());
+
+    if report.is_consistent() {
+        println!("回放结果与记录一致");
+    } else {
+        for mismatch in &report.mismatches {
+            eprintln!("{}", mismatch);
+        }
+        std::process::exit(1);
+    }
+}
@@ -0,0 +1,36 @@
+use matching_engine::verify::{self, VerifyConfig};
+
+// 用法: verify <恢复目录> [clock=logical]
+// 用命令日志重新撮合，与输出日志（MATCHING_ENGINE_OUTPUT_JOURNAL=1 时记录）逐条比对成交，
+// 不一致时以非零状态码退出。clock=logical 表示记录时使用了逻辑时钟，时间戳也参与比对
+fn main() {
+    let mut dir = None;
+    let mut config = VerifyConfig::default();
+    for arg in std::env::args().skip(1) {
+        match arg.split_once('=') {
+            Some(("clock", "logical")) => config.logical_clock = true,
+            Some(("clock", "system")) => config.logical_clock = false,
+            Some(_) => panic!("未知参数: {}", arg),
+            None => dir = Some(arg),
+        }
+    }
+    let Some(dir) = dir else {
+        eprintln!("用法: verify <恢复目录> [clock=logical]");
+        std::process::exit(2);
+    };
+
+    let report = verify::verify(dir.as_ref(), config).unwrap_or_else(|e| panic!("{}: {}", dir, e));
+    println!("命令数: {}", report.commands);
+    println!("比对的命令数: {}", report.verified);
+    println!("没有输出记录的命令数: {}", report.unrecorded);
+    println!("成交数: {}", report.trades);
+
+    if report.is_consistent() {
+        println!("重新撮合的结果与输出日志一致");
+    } else {
+        for mismatch in &report.mismatches {
+            eprintln!("{}", mismatch);
+        }
+        std::process::exit(1);
+    }
+}
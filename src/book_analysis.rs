@@ -0,0 +1,93 @@
+use crate::orderbook::{NodeHandle, OrderNode};
+use crate::protocol::{DepthLevel, DepthSnapshot, OrderType};
+use std::collections::BTreeMap;
+use std::fmt::Write as _;
+use std::io::BufRead;
+use std::mem::size_of;
+
+// 每笔挂单占用的内存：节点池中的 OrderNode 加上订单号索引中的一项
+pub const ORDER_BYTES: usize = size_of::<OrderNode>() + size_of::<(u64, NodeHandle)>();
+// 每个价格档位占用的内存：价格加上队列的头尾指针
+pub const LEVEL_BYTES: usize = size_of::<u64>() + 2 * size_of::<Option<NodeHandle>>();
+
+// 按挂单数和档位数估算订单簿的内存占用（字节）。
+// 只计算数据本身，不含 BTreeMap 节点、分配器开销和节点池中的空闲槽位，是一个下限
+pub fn estimate_memory(snapshot: &DepthSnapshot) -> u64 {
+    snapshot.resting_orders * ORDER_BYTES as u64 + (snapshot.bid_levels + snapshot.ask_levels) * LEVEL_BYTES as u64
+}
+
+// 买一价不低于卖一价。连续竞价中撮合后不应出现，集合竞价期间则是正常现象
+pub fn is_crossed(snapshot: &DepthSnapshot) -> bool {
+    matches!((snapshot.best_bid, snapshot.best_ask), (Some(bid), Some(ask)) if bid >= ask)
+}
+
+// 读取 book_export 导出的 ndjson 文件，返回每个合约最新的一份快照
+pub fn latest_snapshots<R: BufRead>(reader: R) -> Result<BTreeMap<String, DepthSnapshot>, String> {
+    let mut latest: BTreeMap<String, DepthSnapshot> = BTreeMap::new();
+    for (index, line) in reader.lines().enumerate() {
+        let line = line.map_err(|e| format!("读取失败: {}", e))?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        let snapshot: DepthSnapshot =
+            serde_json::from_str(&line).map_err(|e| format!("第 {} 行不是有效的深度快照: {}", index + 1, e))?;
+        match latest.get(&snapshot.symbol) {
+            Some(existing) if existing.timestamp > snapshot.timestamp => {}
+            _ => {
+                latest.insert(snapshot.symbol.clone(), snapshot);
+            }
+        }
+    }
+    Ok(latest)
+}
+
+// 生成一个合约的分析报告：深度阶梯、每档订单数、最大挂单、交叉检测和内存估算
+pub fn render_report(snapshot: &DepthSnapshot, levels: usize) -> String {
+    let mut out = String::new();
+    let _ = writeln!(out, "== {} @ {} ==", snapshot.symbol, snapshot.timestamp);
+    let _ = writeln!(out, "{:>12} {:>12} {:>8}", "价格", "数量", "订单数");
+    // 卖盘从高到低打印在上方，买盘从高到低打印在下方
+    let asks: Vec<&DepthLevel> = snapshot.asks.iter().take(levels).collect();
+    for level in asks.iter().rev() {
+        let _ = writeln!(out, "{:>12} {:>12} {:>8}  卖", level.price, level.quantity, level.order_count);
+    }
+    let _ = writeln!(out, "{:-^36}", spread_label(snapshot));
+    for level in snapshot.bids.iter().take(levels) {
+        let _ = writeln!(out, "{:>12} {:>12} {:>8}  买", level.price, level.quantity, level.order_count);
+    }
+
+    if is_crossed(snapshot) {
+        let _ = writeln!(out, "警告: 订单簿交叉 (买一 {:?} >= 卖一 {:?})", snapshot.best_bid, snapshot.best_ask);
+    }
+    let _ = writeln!(
+        out,
+        "挂单 {} 笔，买盘 {} 档，卖盘 {} 档，估算内存 {:.1} KiB",
+        snapshot.resting_orders,
+        snapshot.bid_levels,
+        snapshot.ask_levels,
+        estimate_memory(snapshot) as f64 / 1024.0
+    );
+    if !snapshot.largest_orders.is_empty() {
+        let _ = writeln!(out, "最大挂单:");
+        for order in &snapshot.largest_orders {
+            let side = match order.order_type {
+                OrderType::Buy => "买",
+                OrderType::Sell => "卖",
+            };
+            let _ = writeln!(
+                out,
+                "  #{:<10} 用户 {:<8} {} {} @ {}",
+                order.order_id, order.user_id, side, order.quantity, order.price
+            );
+        }
+    }
+    out
+}
+
+fn spread_label(snapshot: &DepthSnapshot) -> String {
+    match (snapshot.best_bid, snapshot.best_ask) {
+        (Some(bid), Some(ask)) if ask >= bid => format!(" 价差 {} ", ask - bid),
+        (Some(_), Some(_)) => " 交叉 ".to_string(),
+        _ => " 单边 ".to_string(),
+    }
+}
@@ -0,0 +1,80 @@
+use crate::engine::EngineCommand;
+use crate::protocol::DepthSnapshot;
+use std::fs::OpenOptions;
+use std::io::{BufWriter, Write};
+use std::path::PathBuf;
+use std::sync::mpsc as std_mpsc;
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
+use tokio::sync::mpsc::UnboundedSender;
+
+// 订单簿深度导出配置
+#[derive(Debug, Clone)]
+pub struct BookExportConfig {
+    // 追加写入的 ndjson 文件路径，每行一个 DepthSnapshot
+    pub path: PathBuf,
+    pub interval: Duration,
+    // 每侧导出的档位数
+    pub depth: usize,
+    // 每个合约附带的最大挂单笔数，为 0 时不导出；需要在撮合线程上遍历整个订单簿
+    pub largest_orders: usize,
+}
+
+// 启动后台线程：按固定间隔向引擎请求深度快照，并以 ndjson 格式追加到文件中，
+// 方便简单的看板直接读取，而不必实现二进制行情协议
+pub fn spawn_book_exporter(
+    command_sender: UnboundedSender<EngineCommand>,
+    config: BookExportConfig,
+) -> std::io::Result<JoinHandle<()>> {
+    let file = OpenOptions::new().create(true).append(true).open(&config.path)?;
+    let handle = thread::spawn(move || {
+        let mut writer = BufWriter::new(file);
+        loop {
+            thread::sleep(config.interval);
+            if export_snapshots(&command_sender, &config, &mut writer).is_none() {
+                // 引擎已关闭
+                break;
+            }
+        }
+    });
+    Ok(handle)
+}
+
+// 立即导出一次，用于关闭服务器前保存最终的订单簿状态；返回写入的快照数，引擎已关闭时为 0
+pub fn export_once(command_sender: &UnboundedSender<EngineCommand>, config: &BookExportConfig) -> std::io::Result<usize> {
+    let file = OpenOptions::new().create(true).append(true).open(&config.path)?;
+    let mut writer = BufWriter::new(file);
+    Ok(export_snapshots(command_sender, config, &mut writer).unwrap_or(0))
+}
+
+// 请求一次快照并写入，返回快照数；引擎已关闭时返回 None
+fn export_snapshots<W: Write>(
+    command_sender: &UnboundedSender<EngineCommand>,
+    config: &BookExportConfig,
+    writer: &mut W,
+) -> Option<usize> {
+    let (reply_tx, reply_rx) = std_mpsc::channel();
+    let command = EngineCommand::SnapshotDepth {
+        depth: config.depth,
+        largest_orders: config.largest_orders,
+        reply: reply_tx,
+    };
+    command_sender.send(command).ok()?;
+    let mut count = 0;
+    // 引擎处理完命令后会释放 reply_tx，迭代随之结束
+    for snapshot in reply_rx {
+        count += 1;
+        if let Err(e) = write_snapshot(writer, &snapshot) {
+            eprintln!("写入深度快照失败: {}", e);
+        }
+    }
+    if let Err(e) = writer.flush() {
+        eprintln!("刷新深度快照文件失败: {}", e);
+    }
+    Some(count)
+}
+
+fn write_snapshot<W: Write>(writer: &mut W, snapshot: &DepthSnapshot) -> std::io::Result<()> {
+    serde_json::to_writer(&mut *writer, snapshot)?;
+    writer.write_all(b"\n")
+}
@@ -0,0 +1,47 @@
+use serde::{Deserialize, Serialize};
+
+// 涨跌停价格带触发后的处理方式
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum BreachPolicy {
+    // 拒绝会在价格带之外成交的订单
+    Reject,
+    // 暂停该合约的撮合，直到通过 Resume 命令恢复
+    Halt,
+}
+
+// 每日涨跌停价格带：以参考价（通常为前一交易日结算价）为中心
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PriceBand {
+    pub reference_price: u64,
+    // 涨停幅度，单位为基点
+    pub limit_up_bps: u64,
+    // 跌停幅度，单位为基点
+    pub limit_down_bps: u64,
+    pub policy: BreachPolicy,
+}
+
+impl PriceBand {
+    // 返回允许成交的价格区间 (跌停价, 涨停价)，两端均包含在内
+    // 极端的参考价或幅度不会溢出，价格带在 0 和 u64::MAX 处截断
+    pub fn bounds(&self) -> (u64, u64) {
+        let offset = |bps: u64| u64::try_from(self.reference_price as u128 * bps as u128 / 10_000).unwrap_or(u64::MAX);
+        let (up, down) = (offset(self.limit_up_bps), offset(self.limit_down_bps));
+        (self.reference_price.saturating_sub(down), self.reference_price.saturating_add(up))
+    }
+}
+
+// 价格保护：以外部行情源的最新标记价格为中心，拒绝限价偏离过大的订单，防止误操作的订单挂入订单簿。
+// 尚未收到标记价格的合约不做检查
+#[derive(Debug, Clone, Copy)]
+pub struct PriceCollar {
+    // 允许的最大偏离幅度，单位为基点
+    pub collar_bps: u64,
+}
+
+impl PriceCollar {
+    // 返回允许的限价区间 (下限, 上限)，两端均包含在内
+    pub fn bounds(&self, mark_price: u64) -> (u64, u64) {
+        let deviation = mark_price * self.collar_bps / 10_000;
+        (mark_price.saturating_sub(deviation), mark_price + deviation)
+    }
+}
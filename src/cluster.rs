@@ -0,0 +1,696 @@
+// Raft 集群模式：交易命令先追加到 Raft 日志，由多数派节点确认提交后，各节点按日志顺序送入各自的撮合引擎，
+// 确定性的撮合保证所有节点得到相同的订单簿、订单号和成交号。领导者宕机后剩余的多数派选出新领导者继续服务，
+// 落后太多或新加入的节点通过快照（即恢复快照中的挂单、持仓和 ID 状态）追上。
+// RaftNode 只是状态机：由调用方按固定节拍调用 tick、把收到的消息交给 step、把 take_messages 的消息发给对端，
+// 与网络传输无关。服务进程不会启动集群节点，网络层的命令仍直接送入引擎，嵌入方需要自己提供节点间的传输，
+// 并把客户端命令经领导者的 propose 提交。
+// 用 open 打开的节点把任期、投票、日志和快照写入目录并 fsync 之后才产生投票和应答，
+// 重启后不会在同一任期投两次票，也不会丢掉已经应答过的条目；new 创建的节点只保存在内存中，用于测试和模拟
+use crate::engine::{EngineCommand, MatchingEngine};
+use crate::recovery::{self, LoggedCommand, RecoveryError, Snapshot};
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+use std::fs::{self, File, OpenOptions};
+use std::io;
+use std::path::{Path, PathBuf};
+
+const STATE_FILE: &str = "raft-state.json";
+const LOG_FILE: &str = "raft-log.ndjson";
+const SNAPSHOT_FILE: &str = "raft-snapshot.json";
+
+pub type NodeId = u64;
+
+#[derive(Debug, Clone)]
+pub struct ClusterConfig {
+    pub id: NodeId,
+    // 其他节点，不含自己
+    pub peers: Vec<NodeId>,
+    // 跟随者超过 election_ticks 到两倍之间的随机节拍数收不到领导者的消息时发起选举
+    pub election_ticks: u32,
+    // 领导者发送心跳的间隔节拍数，应远小于 election_ticks
+    pub heartbeat_ticks: u32,
+    // 单条 AppendEntries 最多携带的日志条数
+    pub max_entries_per_message: usize,
+    // 已应用的日志超过这个条数时生成快照并截断日志
+    pub snapshot_threshold: usize,
+}
+
+impl ClusterConfig {
+    pub fn new(id: NodeId, peers: Vec<NodeId>) -> Self {
+        ClusterConfig { id, peers, election_ticks: 10, heartbeat_ticks: 2, max_entries_per_message: 256, snapshot_threshold: 10_000 }
+    }
+}
+
+// 日志条目；command 为 None 的是领导者当选时追加的空条目，用于提交之前任期的条目
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Entry {
+    pub term: u64,
+    pub command: Option<LoggedCommand>,
+}
+
+// 快照及其覆盖的最后一条日志
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ClusterSnapshot {
+    pub index: u64,
+    pub term: u64,
+    pub state: Snapshot,
+}
+
+// 节点之间的消息，所有消息都携带发送者的任期
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum Message {
+    RequestVote { term: u64, last_log_index: u64, last_log_term: u64 },
+    Vote { term: u64, granted: bool },
+    AppendEntries { term: u64, prev_log_index: u64, prev_log_term: u64, entries: Vec<Entry>, leader_commit: u64 },
+    // 失败时 match_index 是跟随者建议的重试位置
+    AppendResponse { term: u64, success: bool, match_index: u64 },
+    // 快照包含整个订单簿，装箱后其他消息不必按快照的大小分配
+    InstallSnapshot { term: u64, snapshot: Box<ClusterSnapshot> },
+}
+
+impl Message {
+    fn term(&self) -> u64 {
+        match self {
+            Message::RequestVote { term, .. }
+            | Message::Vote { term, .. }
+            | Message::AppendEntries { term, .. }
+            | Message::AppendResponse { term, .. }
+            | Message::InstallSnapshot { term, .. } => *term,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Role {
+    Follower,
+    Candidate,
+    Leader,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ClusterError {
+    // 本节点不是领导者，leader 为已知的领导者
+    NotLeader { leader: Option<NodeId> },
+    // 命令不会改变订单簿（查询、管理命令），或暂不支持经日志复制
+    Unsupported(&'static str),
+    // 领导者的引擎按限流拒绝了命令，拒绝回报已经发出，命令没有写入日志
+    Throttled,
+}
+
+impl fmt::Display for ClusterError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ClusterError::NotLeader { leader: Some(leader) } => write!(f, "不是领导者，请发往节点 {}", leader),
+            ClusterError::NotLeader { leader: None } => write!(f, "不是领导者，正在选举"),
+            ClusterError::Unsupported(kind) => write!(f, "集群模式不支持命令 {}", kind),
+            ClusterError::Throttled => write!(f, "命令被限流"),
+        }
+    }
+}
+
+impl std::error::Error for ClusterError {}
+
+// 必须在应答之前落盘的任期和投票
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct HardState {
+    term: u64,
+    voted_for: Option<NodeId>,
+}
+
+// Raft 日志文件的一行；索引不大于之前记录的条目表示冲突后被覆盖，加载时截去原有的同索引及之后的条目
+#[derive(Debug, Serialize, Deserialize)]
+struct LogEntryRecord {
+    index: u64,
+    entry: Entry,
+}
+
+// 节点的持久化目录，文件格式与恢复模块的命令日志相同：每行一条 JSON，崩溃留下的半行在打开时截去
+struct RaftStorage {
+    dir: PathBuf,
+    log: File,
+}
+
+impl RaftStorage {
+    fn open(dir: &Path) -> Result<(Self, HardState, Option<ClusterSnapshot>, Vec<Entry>), RecoveryError> {
+        fs::create_dir_all(dir)?;
+        let state = recovery::read_json::<HardState>(&dir.join(STATE_FILE))?.unwrap_or_default();
+        let snapshot = recovery::read_json::<ClusterSnapshot>(&dir.join(SNAPSHOT_FILE))?;
+        let snapshot_index = snapshot.as_ref().map_or(0, |snapshot| snapshot.index);
+
+        let path = dir.join(LOG_FILE);
+        let (records, complete_len, file_len) = recovery::read_log::<LogEntryRecord>(&path)?;
+        let mut log = Vec::new();
+        for LogEntryRecord { index, entry } in records {
+            if index <= snapshot_index {
+                continue;
+            }
+            let position = (index - snapshot_index - 1) as usize;
+            if position > log.len() {
+                return Err(RecoveryError::Gap { expected: snapshot_index + log.len() as u64 + 1, found: index });
+            }
+            log.truncate(position);
+            log.push(entry);
+        }
+        if complete_len < file_len {
+            OpenOptions::new().write(true).open(&path)?.set_len(complete_len)?;
+        }
+        let file = OpenOptions::new().create(true).append(true).open(&path)?;
+        Ok((RaftStorage { dir: dir.to_path_buf(), log: file }, state, snapshot, log))
+    }
+
+    fn save_state(&self, term: u64, voted_for: Option<NodeId>) -> io::Result<()> {
+        recovery::write_json_atomic(&self.dir.join(STATE_FILE), &HardState { term, voted_for })
+    }
+
+    // 追加从 first_index 开始的条目，全部写入后 fsync 一次
+    fn append(&mut self, first_index: u64, entries: &[Entry]) -> io::Result<()> {
+        for (index, entry) in (first_index..).zip(entries) {
+            recovery::append_line(&mut self.log, &LogEntryRecord { index, entry: entry.clone() }, false)?;
+        }
+        self.log.sync_data()
+    }
+
+    // 写入新快照，再用快照之后的条目重写日志文件；两步都是先写临时文件再改名，中途崩溃时旧日志中
+    // 已被快照覆盖的条目在加载时跳过
+    fn save_snapshot(&mut self, snapshot: &ClusterSnapshot, log: &[Entry]) -> io::Result<()> {
+        recovery::write_json_atomic(&self.dir.join(SNAPSHOT_FILE), snapshot)?;
+        let path = self.dir.join(LOG_FILE);
+        let temporary = path.with_extension("ndjson.tmp");
+        let mut file = File::create(&temporary)?;
+        for (index, entry) in (snapshot.index + 1..).zip(log) {
+            recovery::append_line(&mut file, &LogEntryRecord { index, entry: entry.clone() }, false)?;
+        }
+        file.sync_all()?;
+        fs::rename(&temporary, &path)?;
+        self.log = OpenOptions::new().append(true).open(&path)?;
+        Ok(())
+    }
+}
+
+pub struct RaftNode {
+    config: ClusterConfig,
+    term: u64,
+    voted_for: Option<NodeId>,
+    role: Role,
+    leader: Option<NodeId>,
+    // 快照之后的日志，log[i] 的索引为 snapshot_index + 1 + i
+    log: Vec<Entry>,
+    snapshot: Option<ClusterSnapshot>,
+    // 收到领导者的快照、尚未交给引擎安装
+    installed: Option<ClusterSnapshot>,
+    commit_index: u64,
+    applied_index: u64,
+    votes: HashSet<NodeId>,
+    next_index: HashMap<NodeId, u64>,
+    match_index: HashMap<NodeId, u64>,
+    election_elapsed: u32,
+    election_timeout: u32,
+    heartbeat_elapsed: u32,
+    rng: StdRng,
+    outbox: Vec<(NodeId, Message)>,
+    storage: Option<RaftStorage>,
+}
+
+impl RaftNode {
+    pub fn new(config: ClusterConfig) -> Self {
+        // 选举超时的随机化按节点号播种，同样的消息顺序总是得到同样的选举结果
+        let mut rng = StdRng::seed_from_u64(config.id);
+        let election_timeout = rng.gen_range(config.election_ticks..config.election_ticks * 2);
+        RaftNode {
+            config,
+            term: 0,
+            voted_for: None,
+            role: Role::Follower,
+            leader: None,
+            log: Vec::new(),
+            snapshot: None,
+            installed: None,
+            commit_index: 0,
+            applied_index: 0,
+            votes: HashSet::new(),
+            next_index: HashMap::new(),
+            match_index: HashMap::new(),
+            election_elapsed: 0,
+            election_timeout,
+            heartbeat_elapsed: 0,
+            rng,
+            outbox: Vec::new(),
+            storage: None,
+        }
+    }
+
+    // 从目录加载持久化的任期、投票、快照和日志，之后的状态变化都先写入目录。
+    // 提交位置不持久化，从快照开始，由领导者的下一条消息（或单节点自己当选后）重新确定
+    pub fn open(config: ClusterConfig, dir: &Path) -> Result<Self, RecoveryError> {
+        let (storage, state, snapshot, log) = RaftStorage::open(dir)?;
+        let mut node = RaftNode::new(config);
+        let snapshot_index = snapshot.as_ref().map_or(0, |snapshot| snapshot.index);
+        node.term = state.term;
+        node.voted_for = state.voted_for;
+        node.log = log;
+        node.commit_index = snapshot_index;
+        node.applied_index = snapshot_index;
+        node.installed = snapshot.clone();
+        node.snapshot = snapshot;
+        node.storage = Some(storage);
+        Ok(node)
+    }
+
+    pub fn id(&self) -> NodeId {
+        self.config.id
+    }
+
+    pub fn term(&self) -> u64 {
+        self.term
+    }
+
+    pub fn role(&self) -> Role {
+        self.role
+    }
+
+    pub fn leader(&self) -> Option<NodeId> {
+        self.leader
+    }
+
+    pub fn commit_index(&self) -> u64 {
+        self.commit_index
+    }
+
+    pub fn last_index(&self) -> u64 {
+        self.snapshot_index() + self.log.len() as u64
+    }
+
+    // 快照之后保留的日志条数
+    pub fn log_len(&self) -> usize {
+        self.log.len()
+    }
+
+    fn snapshot_index(&self) -> u64 {
+        self.snapshot.as_ref().map_or(0, |snapshot| snapshot.index)
+    }
+
+    // 日志条目的任期；已被快照截去的条目返回 None
+    fn term_at(&self, index: u64) -> Option<u64> {
+        let snapshot_index = self.snapshot_index();
+        match index {
+            0 => Some(0),
+            _ if index == snapshot_index => self.snapshot.as_ref().map(|snapshot| snapshot.term),
+            _ if index < snapshot_index => None,
+            _ => self.log.get((index - snapshot_index - 1) as usize).map(|entry| entry.term),
+        }
+    }
+
+    fn last_term(&self) -> u64 {
+        self.term_at(self.last_index()).unwrap_or(0)
+    }
+
+    fn quorum(&self) -> usize {
+        let members = self.config.peers.len() + 1;
+        members / 2 + 1
+    }
+
+    // 写入失败时节点已无法保证投票和应答的承诺，与引擎的命令日志一样直接终止
+    fn persist_state(&mut self) {
+        if let Some(storage) = &self.storage {
+            if let Err(e) = storage.save_state(self.term, self.voted_for) {
+                panic!("写入 Raft 任期和投票失败: {}", e);
+            }
+        }
+    }
+
+    // 持久化索引 first_index 及之后的全部条目
+    fn persist_entries(&mut self, first_index: u64) {
+        let start = (first_index - self.snapshot_index() - 1) as usize;
+        if let Some(storage) = &mut self.storage {
+            if let Err(e) = storage.append(first_index, &self.log[start..]) {
+                panic!("写入 Raft 日志失败: {}", e);
+            }
+        }
+    }
+
+    fn persist_snapshot(&mut self) {
+        if let (Some(storage), Some(snapshot)) = (&mut self.storage, &self.snapshot) {
+            if let Err(e) = storage.save_snapshot(snapshot, &self.log) {
+                panic!("写入 Raft 快照失败: {}", e);
+            }
+        }
+    }
+
+    // 推进一个节拍：领导者按间隔发送心跳，其他节点在选举超时后发起选举
+    pub fn tick(&mut self) {
+        if self.role == Role::Leader {
+            self.heartbeat_elapsed += 1;
+            if self.heartbeat_elapsed >= self.config.heartbeat_ticks {
+                self.heartbeat_elapsed = 0;
+                self.broadcast_append();
+            }
+            return;
+        }
+        self.election_elapsed += 1;
+        if self.election_elapsed >= self.election_timeout {
+            self.start_election();
+        }
+    }
+
+    // 领导者追加一条命令，返回其日志索引；提交后才会送入撮合
+    pub fn propose(&mut self, command: LoggedCommand) -> Result<u64, ClusterError> {
+        if self.role != Role::Leader {
+            return Err(ClusterError::NotLeader { leader: self.leader });
+        }
+        self.log.push(Entry { term: self.term, command: Some(command) });
+        let index = self.last_index();
+        // 领导者自己也计入多数派，条目落盘后才能算作已复制
+        self.persist_entries(index);
+        self.advance_commit();
+        for peer in self.config.peers.clone() {
+            // 只向已经跟上的跟随者立即发送，落后的跟随者等应答或心跳时再补发
+            if self.next_index.get(&peer) == Some(&index) {
+                self.send_append(peer);
+            }
+        }
+        Ok(index)
+    }
+
+    pub fn step(&mut self, from: NodeId, message: Message) {
+        if message.term() > self.term {
+            let leader = matches!(message, Message::AppendEntries { .. } | Message::InstallSnapshot { .. }).then_some(from);
+            self.become_follower(message.term(), leader);
+        }
+        match message {
+            Message::RequestVote { term, last_log_index, last_log_term } => {
+                let up_to_date = (last_log_term, last_log_index) >= (self.last_term(), self.last_index());
+                let granted = term == self.term && self.voted_for.is_none_or(|voted| voted == from) && up_to_date;
+                if granted {
+                    self.voted_for = Some(from);
+                    self.election_elapsed = 0;
+                    self.persist_state();
+                }
+                self.outbox.push((from, Message::Vote { term: self.term, granted }));
+            }
+            Message::Vote { term, granted } => {
+                if self.role == Role::Candidate && term == self.term && granted {
+                    self.votes.insert(from);
+                    if self.votes.len() >= self.quorum() {
+                        self.become_leader();
+                    }
+                }
+            }
+            Message::AppendEntries { term, prev_log_index, prev_log_term, entries, leader_commit } => {
+                let response = self.append_entries(from, term, prev_log_index, prev_log_term, entries, leader_commit);
+                self.outbox.push((from, response));
+            }
+            Message::AppendResponse { term, success, match_index } => {
+                if self.role != Role::Leader || term != self.term {
+                    return;
+                }
+                if success {
+                    let matched = self.match_index.entry(from).or_default();
+                    *matched = (*matched).max(match_index);
+                    self.next_index.insert(from, *matched + 1);
+                    self.advance_commit();
+                    if self.next_index[&from] <= self.last_index() {
+                        self.send_append(from);
+                    }
+                } else {
+                    let next = self.next_index.get(&from).copied().unwrap_or(1);
+                    self.next_index.insert(from, next.min(match_index + 1).max(1));
+                    self.send_append(from);
+                }
+            }
+            Message::InstallSnapshot { term, snapshot } => {
+                if term < self.term {
+                    self.outbox.push((from, Message::AppendResponse { term: self.term, success: false, match_index: 0 }));
+                    return;
+                }
+                self.accept_leader(from);
+                let index = snapshot.index;
+                if index > self.commit_index {
+                    // 快照之后的日志可能与领导者不一致，全部丢弃，由领导者补发
+                    self.log.clear();
+                    self.commit_index = index;
+                    self.applied_index = index;
+                    self.snapshot = Some((*snapshot).clone());
+                    self.installed = Some(*snapshot);
+                    self.persist_snapshot();
+                }
+                let match_index = index.max(self.commit_index);
+                self.outbox.push((from, Message::AppendResponse { term: self.term, success: true, match_index }));
+            }
+        }
+    }
+
+    fn append_entries(
+        &mut self,
+        from: NodeId,
+        term: u64,
+        prev_log_index: u64,
+        prev_log_term: u64,
+        entries: Vec<Entry>,
+        leader_commit: u64,
+    ) -> Message {
+        let reject = |node: &Self, hint: u64| Message::AppendResponse { term: node.term, success: false, match_index: hint };
+        if term < self.term {
+            return reject(self, 0);
+        }
+        self.accept_leader(from);
+        if prev_log_index > self.last_index() {
+            return reject(self, self.last_index());
+        }
+        // 已提交的条目一定一致；被快照截去的前缀按已提交处理
+        let snapshot_index = self.snapshot_index();
+        if prev_log_index >= snapshot_index && self.term_at(prev_log_index) != Some(prev_log_term) {
+            return reject(self, prev_log_index.saturating_sub(1).min(self.commit_index.max(snapshot_index)));
+        }
+        let mut index = prev_log_index;
+        // 第一条新写入的条目，之后的条目都是新写入的
+        let mut first_new = None;
+        for entry in entries {
+            index += 1;
+            if index <= snapshot_index {
+                continue;
+            }
+            match self.term_at(index) {
+                Some(existing) if existing == entry.term => continue,
+                Some(_) => self.log.truncate((index - snapshot_index - 1) as usize),
+                None => {}
+            }
+            self.log.push(entry);
+            first_new.get_or_insert(index);
+        }
+        if let Some(first_new) = first_new {
+            self.persist_entries(first_new);
+        }
+        if leader_commit > self.commit_index {
+            self.commit_index = leader_commit.min(index);
+        }
+        Message::AppendResponse { term: self.term, success: true, match_index: index }
+    }
+
+    fn accept_leader(&mut self, leader: NodeId) {
+        self.role = Role::Follower;
+        self.leader = Some(leader);
+        self.election_elapsed = 0;
+    }
+
+    fn become_follower(&mut self, term: u64, leader: Option<NodeId>) {
+        self.term = term;
+        self.voted_for = None;
+        self.role = Role::Follower;
+        self.leader = leader;
+        self.reset_election_timer();
+        self.persist_state();
+    }
+
+    fn reset_election_timer(&mut self) {
+        self.election_elapsed = 0;
+        self.election_timeout = self.rng.gen_range(self.config.election_ticks..self.config.election_ticks * 2);
+    }
+
+    fn start_election(&mut self) {
+        self.term += 1;
+        self.role = Role::Candidate;
+        self.voted_for = Some(self.config.id);
+        self.leader = None;
+        self.votes = HashSet::from([self.config.id]);
+        self.reset_election_timer();
+        self.persist_state();
+        if self.votes.len() >= self.quorum() {
+            self.become_leader();
+            return;
+        }
+        let (last_log_index, last_log_term) = (self.last_index(), self.last_term());
+        for &peer in &self.config.peers {
+            self.outbox.push((peer, Message::RequestVote { term: self.term, last_log_index, last_log_term }));
+        }
+    }
+
+    fn become_leader(&mut self) {
+        self.role = Role::Leader;
+        self.leader = Some(self.config.id);
+        self.heartbeat_elapsed = 0;
+        let next = self.last_index() + 1;
+        self.next_index = self.config.peers.iter().map(|&peer| (peer, next)).collect();
+        self.match_index = self.config.peers.iter().map(|&peer| (peer, 0)).collect();
+        // 只有当前任期的条目能按多数派提交，空条目让之前任期遗留的条目随之提交
+        self.log.push(Entry { term: self.term, command: None });
+        self.persist_entries(self.last_index());
+        self.advance_commit();
+        self.broadcast_append();
+    }
+
+    fn broadcast_append(&mut self) {
+        for peer in self.config.peers.clone() {
+            self.send_append(peer);
+        }
+    }
+
+    fn send_append(&mut self, peer: NodeId) {
+        let next = self.next_index.get(&peer).copied().unwrap_or(1);
+        let message = match self.term_at(next - 1) {
+            // 需要的日志已被快照截去
+            None => match &self.snapshot {
+                Some(snapshot) => Message::InstallSnapshot { term: self.term, snapshot: Box::new(snapshot.clone()) },
+                None => return,
+            },
+            Some(prev_log_term) => {
+                let start = (next - self.snapshot_index() - 1) as usize;
+                let end = self.log.len().min(start + self.config.max_entries_per_message);
+                Message::AppendEntries {
+                    term: self.term,
+                    prev_log_index: next - 1,
+                    prev_log_term,
+                    entries: self.log[start..end].to_vec(),
+                    leader_commit: self.commit_index,
+                }
+            }
+        };
+        self.outbox.push((peer, message));
+    }
+
+    // 多数派（含领导者自己）都已复制、且属于当前任期的最大索引即可提交
+    fn advance_commit(&mut self) {
+        for index in (self.commit_index + 1..=self.last_index()).rev() {
+            if self.term_at(index) != Some(self.term) {
+                break;
+            }
+            let replicated = 1 + self.match_index.values().filter(|&&matched| matched >= index).count();
+            if replicated >= self.quorum() {
+                self.commit_index = index;
+                break;
+            }
+        }
+    }
+
+    // 待发送的消息
+    pub fn take_messages(&mut self) -> Vec<(NodeId, Message)> {
+        std::mem::take(&mut self.outbox)
+    }
+
+    // 从领导者收到、需要替换引擎状态的快照
+    pub fn take_installed_snapshot(&mut self) -> Option<ClusterSnapshot> {
+        self.installed.take()
+    }
+
+    // 已提交但尚未应用的条目 (索引, 条目)
+    pub fn take_committed(&mut self) -> Vec<(u64, Entry)> {
+        let snapshot_index = self.snapshot_index();
+        let committed = (self.applied_index + 1..=self.commit_index)
+            .map(|index| (index, self.log[(index - snapshot_index - 1) as usize].clone()))
+            .collect();
+        self.applied_index = self.commit_index;
+        committed
+    }
+
+    // 用已应用到 index 的引擎状态生成快照，截去 index 及之前的日志
+    pub fn compact(&mut self, index: u64, state: Snapshot) {
+        assert!(index <= self.applied_index, "只能截去已应用的日志");
+        let Some(term) = self.term_at(index) else {
+            return;
+        };
+        let snapshot_index = self.snapshot_index();
+        self.log.drain(..(index - snapshot_index) as usize);
+        self.snapshot = Some(ClusterSnapshot { index, term, state });
+        self.persist_snapshot();
+    }
+}
+
+// 集群中的一个节点：Raft 状态机加上按提交顺序应用日志的撮合引擎。
+// 领导者引擎的回报发给客户端，跟随者引擎的回报由调用方丢弃
+pub struct ClusterNode {
+    raft: RaftNode,
+    engine: MatchingEngine,
+}
+
+impl ClusterNode {
+    pub fn new(config: ClusterConfig, engine: MatchingEngine) -> Self {
+        ClusterNode { raft: RaftNode::new(config), engine }
+    }
+
+    // 从目录恢复节点；引擎应是新建的，持久化的快照在第一次应用时装入，之后的条目等重新提交后再应用
+    pub fn open(config: ClusterConfig, dir: &Path, engine: MatchingEngine) -> Result<Self, RecoveryError> {
+        let mut node = ClusterNode { raft: RaftNode::open(config, dir)?, engine };
+        node.apply();
+        Ok(node)
+    }
+
+    pub fn raft(&self) -> &RaftNode {
+        &self.raft
+    }
+
+    pub fn engine(&self) -> &MatchingEngine {
+        &self.engine
+    }
+
+    pub fn is_leader(&self) -> bool {
+        self.raft.role() == Role::Leader
+    }
+
+    // 提交一条交易命令；返回的日志索引被多数派确认后命令才送入撮合。
+    // 限流按真实时间判断，只由领导者在追加到日志之前检查一次，各节点应用已提交的命令时不再检查
+    pub fn propose(&mut self, command: &EngineCommand) -> Result<u64, ClusterError> {
+        let logged = LoggedCommand::from_command(command).ok_or(ClusterError::Unsupported(command.kind()))?;
+        if !self.is_leader() {
+            return Err(ClusterError::NotLeader { leader: self.raft.leader() });
+        }
+        if !self.engine.admit(command) {
+            return Err(ClusterError::Throttled);
+        }
+        let index = self.raft.propose(logged)?;
+        self.apply();
+        Ok(index)
+    }
+
+    pub fn tick(&mut self) {
+        self.raft.tick();
+        self.apply();
+    }
+
+    pub fn step(&mut self, from: NodeId, message: Message) {
+        self.raft.step(from, message);
+        self.apply();
+    }
+
+    pub fn take_messages(&mut self) -> Vec<(NodeId, Message)> {
+        self.raft.take_messages()
+    }
+
+    // 安装收到的快照，按顺序应用新提交的命令，日志过长时生成快照
+    fn apply(&mut self) {
+        if let Some(snapshot) = self.raft.take_installed_snapshot() {
+            self.engine.restore(&snapshot.state);
+        }
+        for (_, entry) in self.raft.take_committed() {
+            if let Some(command) = entry.command {
+                self.engine.apply_logged(command);
+            }
+        }
+        if self.raft.log_len() > self.raft.config.snapshot_threshold {
+            let index = self.raft.commit_index();
+            let state = Snapshot { seq: index, ..self.engine.checkpoint() };
+            self.raft.compact(index, state);
+        }
+    }
+}
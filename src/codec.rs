@@ -0,0 +1,180 @@
+use crate::protocol::{ClientMessage, ServerMessage};
+use bincode::config;
+use bytes::{Buf, BufMut, BytesMut};
+use std::fmt;
+use std::marker::PhantomData;
+
+// 帧长度前缀的字节数，与 LengthDelimitedCodec 的默认设置一致（4 字节大端）
+pub const FRAME_HEADER_LEN: usize = 4;
+// 单帧最大长度，与 LengthDelimitedCodec 的默认值一致
+pub const DEFAULT_MAX_FRAME_LEN: usize = 8 * 1024 * 1024;
+// 解码一条消息时允许为 Vec、String 预先分配的内存上限（字节）。
+// bincode 按长度前缀预先分配容器，十几个字节的恶意帧就能声明上 PB 的 Vec，
+// 不设上限时分配失败会直接中止整个进程
+pub const DECODE_ALLOCATION_LIMIT: usize = DEFAULT_MAX_FRAME_LEN;
+
+// 编解码错误
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CodecError {
+    Encode(String),
+    Decode(String),
+    // 帧长度超过上限，流已无法继续解析
+    FrameTooLong(usize),
+}
+
+impl fmt::Display for CodecError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CodecError::Encode(message) => write!(f, "编码失败: {}", message),
+            CodecError::Decode(message) => write!(f, "解码失败: {}", message),
+            CodecError::FrameTooLong(len) => write!(f, "帧长度 {} 超过上限", len),
+        }
+    }
+}
+
+impl std::error::Error for CodecError {}
+
+// 协议消息的线上编码格式，分帧由 LengthDelimitedCodec 负责，这里只处理帧内的字节
+pub trait Codec {
+    fn encode_client(&self, message: &ClientMessage) -> Result<Vec<u8>, CodecError>;
+    fn decode_client(&self, bytes: &[u8]) -> Result<ClientMessage, CodecError>;
+    fn encode_server(&self, message: &ServerMessage) -> Result<Vec<u8>, CodecError>;
+    fn decode_server(&self, bytes: &[u8]) -> Result<ServerMessage, CodecError>;
+}
+
+// 服务器默认使用的 bincode 编码
+#[derive(Debug, Clone, Copy, Default)]
+pub struct BincodeCodec;
+
+impl Codec for BincodeCodec {
+    fn encode_client(&self, message: &ClientMessage) -> Result<Vec<u8>, CodecError> {
+        bincode::encode_to_vec(message, config::standard()).map_err(|e| CodecError::Encode(e.to_string()))
+    }
+
+    fn decode_client(&self, bytes: &[u8]) -> Result<ClientMessage, CodecError> {
+        decode(bytes)
+    }
+
+    fn encode_server(&self, message: &ServerMessage) -> Result<Vec<u8>, CodecError> {
+        bincode::encode_to_vec(message, config::standard()).map_err(|e| CodecError::Encode(e.to_string()))
+    }
+
+    fn decode_server(&self, bytes: &[u8]) -> Result<ServerMessage, CodecError> {
+        decode(bytes)
+    }
+}
+
+// 解码不可信输入时使用的 bincode 配置，线上格式与 config::standard() 相同
+pub fn decode_config() -> impl config::Config {
+    config::standard().with_limit::<DECODE_ALLOCATION_LIMIT>()
+}
+
+fn decode<T: bincode::Decode<()>>(bytes: &[u8]) -> Result<T, CodecError> {
+    bincode::decode_from_slice(bytes, decode_config())
+        .map(|(message, _)| message)
+        .map_err(|e| CodecError::Decode(e.to_string()))
+}
+
+// 给帧内字节加上长度前缀，得到可以直接写入 TCP 流的字节
+pub fn encode_frame(payload: &[u8], dst: &mut BytesMut) {
+    dst.reserve(FRAME_HEADER_LEN + payload.len());
+    dst.put_u32(payload.len() as u32);
+    dst.put_slice(payload);
+}
+
+// 有状态的流式解码器，供不使用 tokio Framed 的客户端（同步 socket、FFI 网关等）使用。
+// 每次 recv 得到的字节通过 extend 追加进来，一帧可以跨多次 recv，一次 recv 也可以包含多帧；
+// 不完整的帧留在缓冲区中等待后续字节
+#[derive(Debug)]
+pub struct StreamDecoder<C, M> {
+    codec: C,
+    buffer: BytesMut,
+    max_frame_len: usize,
+    _message: PhantomData<fn() -> M>,
+}
+
+// 解码服务器发来的消息（客户端使用）
+pub type ServerStreamDecoder<C> = StreamDecoder<C, ServerMessage>;
+// 解码客户端发来的消息（服务器、回放工具使用）
+pub type ClientStreamDecoder<C> = StreamDecoder<C, ClientMessage>;
+
+// 解码器按方向选择 Codec 中对应的解码方法
+pub trait DecodeWith<C: Codec>: Sized {
+    fn decode_with(codec: &C, bytes: &[u8]) -> Result<Self, CodecError>;
+}
+
+impl<C: Codec> DecodeWith<C> for ServerMessage {
+    fn decode_with(codec: &C, bytes: &[u8]) -> Result<Self, CodecError> {
+        codec.decode_server(bytes)
+    }
+}
+
+impl<C: Codec> DecodeWith<C> for ClientMessage {
+    fn decode_with(codec: &C, bytes: &[u8]) -> Result<Self, CodecError> {
+        codec.decode_client(bytes)
+    }
+}
+
+impl<C: Codec, M: DecodeWith<C>> StreamDecoder<C, M> {
+    pub fn new(codec: C) -> Self {
+        StreamDecoder {
+            codec,
+            buffer: BytesMut::new(),
+            max_frame_len: DEFAULT_MAX_FRAME_LEN,
+            _message: PhantomData,
+        }
+    }
+
+    pub fn with_max_frame_len(mut self, max_frame_len: usize) -> Self {
+        self.max_frame_len = max_frame_len;
+        self
+    }
+
+    // 追加一次 recv 读到的字节
+    pub fn extend(&mut self, bytes: &[u8]) {
+        self.buffer.extend_from_slice(bytes);
+    }
+
+    // 缓冲区中尚未组成完整帧的字节数
+    pub fn buffered_len(&self) -> usize {
+        self.buffer.len()
+    }
+
+    // 取出下一条完整的消息，缓冲区中没有完整帧时返回 None。
+    // 帧内容解码失败时只丢弃该帧，后续帧仍可继续解析；帧长度超限时清空缓冲区
+    pub fn next_message(&mut self) -> Option<Result<M, CodecError>> {
+        if self.buffer.len() < FRAME_HEADER_LEN {
+            return None;
+        }
+        let len = u32::from_be_bytes(self.buffer[..FRAME_HEADER_LEN].try_into().unwrap()) as usize;
+        if len > self.max_frame_len {
+            self.buffer.clear();
+            return Some(Err(CodecError::FrameTooLong(len)));
+        }
+        if self.buffer.len() < FRAME_HEADER_LEN + len {
+            // 提前为剩余部分预留空间，避免后续多次扩容
+            self.buffer.reserve(FRAME_HEADER_LEN + len - self.buffer.len());
+            return None;
+        }
+        self.buffer.advance(FRAME_HEADER_LEN);
+        let frame = self.buffer.split_to(len);
+        Some(M::decode_with(&self.codec, &frame))
+    }
+
+    // 依次取出缓冲区中所有完整的消息
+    pub fn messages(&mut self) -> Messages<'_, C, M> {
+        Messages { decoder: self }
+    }
+}
+
+pub struct Messages<'a, C, M> {
+    decoder: &'a mut StreamDecoder<C, M>,
+}
+
+impl<C: Codec, M: DecodeWith<C>> Iterator for Messages<'_, C, M> {
+    type Item = Result<M, CodecError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.decoder.next_message()
+    }
+}
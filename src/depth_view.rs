@@ -0,0 +1,118 @@
+// 不暂停撮合的一致深度视图：引擎线程每处理 every_commands 条命令、以及命令队列排空时，
+// 在两条命令之间为所有合约生成前 depth 档深度，发布为一个整体；查询线程读取最近发布的视图，
+// 不向引擎发送命令，也不等待引擎生成快照。
+// 双缓冲：两个槽位轮流写入，发布时先写入非当前槽位再切换下标。读取方只在克隆 Arc 的瞬间持有所在槽位的锁，
+// 引擎写入的是另一个槽位，两边几乎不会争用同一把锁
+use crate::protocol::DepthSnapshot;
+use parking_lot::Mutex;
+use std::collections::BTreeMap;
+use std::io::{self, Read, Write};
+use std::net::{TcpListener, TcpStream};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
+
+#[derive(Debug, Clone, Copy)]
+pub struct DepthViewConfig {
+    // 每个合约发布的深度档数
+    pub depth: usize,
+    // 队列一直不空时，每处理这么多条命令发布一次
+    pub every_commands: u64,
+}
+
+impl Default for DepthViewConfig {
+    fn default() -> Self {
+        DepthViewConfig { depth: 10, every_commands: 1_000 }
+    }
+}
+
+// 某次发布时所有合约的深度，各合约处于同一条命令之后的状态
+#[derive(Debug, Clone, Default)]
+pub struct BookView {
+    // 发布次数，从 1 开始；尚未发布时为 0
+    pub version: u64,
+    // 生成视图前引擎已处理的命令数
+    pub commands: u64,
+    pub books: BTreeMap<String, DepthSnapshot>,
+}
+
+impl BookView {
+    pub fn get(&self, symbol: &str) -> Option<&DepthSnapshot> {
+        self.books.get(symbol)
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct DepthView {
+    slots: [Mutex<Arc<BookView>>; 2],
+    current: AtomicUsize,
+}
+
+impl DepthView {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    // 只有引擎线程发布
+    pub fn publish(&self, view: BookView) {
+        let next = 1 - self.current.load(Ordering::Acquire);
+        *self.slots[next].lock() = Arc::new(view);
+        self.current.store(next, Ordering::Release);
+    }
+
+    // 最近发布的视图，持有期间不影响引擎继续发布
+    pub fn load(&self) -> Arc<BookView> {
+        self.slots[self.current.load(Ordering::Acquire)].lock().clone()
+    }
+}
+
+// 在独立线程中提供深度查询的 HTTP 接口：GET /depth/<symbol> 返回该合约最近发布的深度（JSON），
+// 合约名中的 '/' 写作 %2F 或直接保留均可
+pub fn spawn_query_server(listener: TcpListener, view: Arc<DepthView>) -> JoinHandle<()> {
+    thread::spawn(move || {
+        for stream in listener.incoming() {
+            let Ok(stream) = stream else { continue };
+            if let Err(e) = serve_query(stream, &view) {
+                eprintln!("处理深度查询请求失败: {}", e);
+            }
+        }
+    })
+}
+
+fn serve_query(mut stream: TcpStream, view: &DepthView) -> io::Result<()> {
+    stream.set_read_timeout(Some(Duration::from_secs(5)))?;
+    // 只需要请求行，读到请求头结束或缓冲区满为止
+    let mut request = [0u8; 4096];
+    let mut len = 0;
+    while len < request.len() && !request[..len].windows(4).any(|w| w == b"\r\n\r\n") {
+        let n = stream.read(&mut request[len..])?;
+        if n == 0 {
+            break;
+        }
+        len += n;
+    }
+    let request = String::from_utf8_lossy(&request[..len]);
+    let mut parts = request.split_whitespace();
+    let symbol = match (parts.next(), parts.next()) {
+        (Some("GET"), Some(path)) => path.strip_prefix("/depth/").map(|symbol| symbol.replace("%2F", "/")),
+        _ => None,
+    };
+    let view = view.load();
+    let (status, body) = match symbol.as_deref().and_then(|symbol| view.get(symbol)) {
+        Some(snapshot) => match serde_json::to_string(snapshot) {
+            Ok(json) => ("200 OK", json),
+            Err(e) => ("500 Internal Server Error", e.to_string()),
+        },
+        None => ("404 Not Found", "not found".to_string()),
+    };
+    write!(
+        stream,
+        "HTTP/1.1 {}\r\nContent-Type: application/json\r\nContent-Length: {}\r\nX-View-Version: {}\r\nConnection: close\r\n\r\n{}",
+        status,
+        body.len(),
+        view.version,
+        body
+    )?;
+    stream.flush()
+}
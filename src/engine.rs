@@ -1,25 +1,432 @@
-use crate::orderbook::OrderBook;
-use crate::protocol::{CancelOrderRequest, NewOrderRequest, OrderConfirmation, TradeNotification};
+use crate::allocation::AllocationPolicy;
+use crate::auction;
+use crate::basket;
+use crate::circuit_breaker::{BreachPolicy, PriceBand, PriceCollar};
+use crate::depth_view::{BookView, DepthView, DepthViewConfig};
+use crate::error::EngineError;
+use crate::fault::{self, FaultAction, FaultPoint};
+use crate::feature_flags::{Feature, FeatureFlags};
+use crate::id::IdGenerator;
+use crate::latency_budget::Breadcrumb;
+use crate::market_data::{MarketData, DEFAULT_CANDLE_HISTORY};
+use crate::metrics::{EngineMetrics, SymbolMetrics};
+use crate::orderbook::{BookEvent, MemoryUsage, OrderBook};
+use crate::position::{PositionLimits, PositionTracker};
+use crate::protocol::{
+    AmendOrderRequest, BasketOrderRequest, BlockTradeRequest, CancelAck, CancelOrderRequest, CancelStatus, DepthLevel,
+    DepthSnapshot, ExecutionReport, FillEstimate, FillEstimateRequest, ImpliedQuote, MarkPrice, MarketDataQuery,
+    MarketDataSnapshot, NewOrderRequest, OcoOrderRequest, OrderConfirmation, OrderReject, OrderStatus, OrderType,
+    PositionQuery, PositionReport, RejectReason, SpreadOrderRequest, TimeInForce, TimedOrderRequest, TradeNotification,
+    TradingPhase, TradingStatus,
+};
+use crate::priority_lanes::{LaneWeights, PriorityLanes};
+use crate::rate_limiter::{RateLimitConfig, RateLimiter};
+use crate::rx_timestamp::unix_nanos;
+use crate::recent_cancels::RecentCancels;
+use crate::replica::L3Message;
+use crate::recovery::{
+    CommandLog, LoggedCommand, OutputLog, OutputRecord, Snapshot, SnapshotMarket, SnapshotOcoLink, SnapshotOrder,
+    SnapshotPosition, SnapshotReduceOnly, SnapshotTimer,
+};
+use crate::sequencer::Sequencer;
+use crate::spread::{self, LegFill, SpreadDefinition};
+use crate::symbols::{SymbolId, SymbolPool, SymbolPoolStats};
+use crate::surveillance::{MarketEvent, Surveillance, SurveillanceRule, TradeMeter};
+use crate::timer_wheel::TimerWheel;
+use std::cell::RefCell;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::sync::atomic::Ordering;
+use std::sync::{mpsc as std_mpsc, Arc};
+use std::time::{Duration, Instant};
 use tokio::sync::mpsc::{UnboundedReceiver, UnboundedSender};
 
-// 定义引擎可以接收的命令
+// 定义引擎可以接收的命令，所有功能都经由 MatchingEngine::run 中同一个分发循环处理
 pub enum EngineCommand {
     NewOrder(NewOrderRequest),
     CancelOrder(CancelOrderRequest),
+    AmendOrder(AmendOrderRequest),
+    BlockTrade(BlockTradeRequest),
+    QueryPosition(PositionQuery),
+    EstimateFill(FillEstimateRequest),
+    QueryMarketData(MarketDataQuery),
+    SpreadOrder(SpreadOrderRequest),
+    BasketOrder(BasketOrderRequest),
+    OcoOrder(OcoOrderRequest),
+    TimedOrder(TimedOrderRequest),
+    ReduceOnlyOrder(NewOrderRequest),
+    // 撤销某个用户在所有合约上的挂单（例如连接断开时），逐笔发送撤单回报
+    CancelUserOrders(u64),
+    // 为每个合约生成前 depth 档深度快照，通过 reply 逐个发回；
+    // largest_orders 大于 0 时附带数量最大的若干笔挂单，需要遍历整个订单簿
+    SnapshotDepth {
+        depth: usize,
+        largest_orders: usize,
+        reply: std_mpsc::Sender<DepthSnapshot>,
+    },
+    Control(ControlCommand),
+    // 带请求 ID 的命令：处理 command 时产生的应答包装为 EngineOutput::Response 回显该 ID，
+    // connection_id 是发出请求的连接，网络层只把应答发给该连接（与面包屑的连接编号相同）
+    Request { request_id: u64, connection_id: u64, command: Box<EngineCommand> },
+    // 未登录连接发出的命令：用户 ID 由客户端自报，处理 command 时产生的应答包装为 EngineOutput::Direct，
+    // 只发给该连接，不按用户 ID 分发
+    Direct { connection_id: u64, command: Box<EngineCommand> },
+    // 带接收时间戳的命令：received_at_ns 是网络层从套接字取得的内核或网卡接收时间（Unix 纳秒），
+    // 主动方的执行回报携带该时间，处理完成时记录从线上到撮合完成的耗时
+    Received { received_at_ns: u64, command: Box<EngineCommand> },
+    // 开启延迟预算时网络层附带的面包屑：处理完 command 后记下撮合完成的时刻，
+    // 在本条命令的全部输出之后以 EngineOutput::Traced 送回网络层
+    Traced { breadcrumb: Box<Breadcrumb>, command: Box<EngineCommand> },
+}
+
+impl EngineCommand {
+    // 命令类型，用作跟踪 span 的字段
+    pub fn kind(&self) -> &'static str {
+        match self {
+            EngineCommand::NewOrder(_) => "new_order",
+            EngineCommand::CancelOrder(_) => "cancel_order",
+            EngineCommand::AmendOrder(_) => "amend_order",
+            EngineCommand::BlockTrade(_) => "block_trade",
+            EngineCommand::QueryPosition(_) => "query_position",
+            EngineCommand::EstimateFill(_) => "estimate_fill",
+            EngineCommand::QueryMarketData(_) => "query_market_data",
+            EngineCommand::SpreadOrder(_) => "spread_order",
+            EngineCommand::BasketOrder(_) => "basket_order",
+            EngineCommand::OcoOrder(_) => "oco_order",
+            EngineCommand::TimedOrder(_) => "timed_order",
+            EngineCommand::ReduceOnlyOrder(_) => "reduce_only_order",
+            EngineCommand::CancelUserOrders(_) => "cancel_user_orders",
+            EngineCommand::SnapshotDepth { .. } => "snapshot_depth",
+            EngineCommand::Control(_) => "control",
+            EngineCommand::Request { command, .. } => command.kind(),
+            EngineCommand::Direct { command, .. } => command.kind(),
+            EngineCommand::Received { command, .. } => command.kind(),
+            EngineCommand::Traced { command, .. } => command.kind(),
+        }
+    }
+
+    // 发出命令的用户；管理命令、深度快照和双方协商的大宗交易不属于某个用户
+    pub fn user_id(&self) -> Option<u64> {
+        match self {
+            EngineCommand::NewOrder(request) | EngineCommand::ReduceOnlyOrder(request) => Some(request.user_id),
+            EngineCommand::CancelOrder(request) => Some(request.user_id),
+            EngineCommand::AmendOrder(request) => Some(request.user_id),
+            EngineCommand::QueryPosition(query) => Some(query.user_id),
+            EngineCommand::EstimateFill(request) => Some(request.user_id),
+            EngineCommand::QueryMarketData(query) => Some(query.user_id),
+            EngineCommand::SpreadOrder(request) => Some(request.user_id),
+            EngineCommand::BasketOrder(request) => Some(request.user_id),
+            EngineCommand::OcoOrder(request) => Some(request.first.user_id),
+            EngineCommand::TimedOrder(request) => Some(request.order.user_id),
+            EngineCommand::CancelUserOrders(user_id) => Some(*user_id),
+            EngineCommand::BlockTrade(_) | EngineCommand::SnapshotDepth { .. } | EngineCommand::Control(_) => None,
+            EngineCommand::Request { command, .. }
+            | EngineCommand::Direct { command, .. }
+            | EngineCommand::Received { command, .. }
+            | EngineCommand::Traced { command, .. } => command.user_id(),
+        }
+    }
+}
+
+// 管理类命令，不来自交易客户端
+pub enum ControlCommand {
+    // 设置（或每日更新）某个合约的涨跌停价格带
+    SetPriceBand(String, PriceBand),
+    // 人工暂停某个合约的撮合
+    Halt(String),
+    // 恢复被暂停合约的撮合
+    Resume(String),
+    // 合约进入集合竞价阶段（开盘/收盘竞价）
+    StartAuction(String),
+    // 结束集合竞价：以均衡价格撮合后转入连续竞价
+    EndAuction(String),
+    // 外部行情源采集到的标记价格和指数价格，用于价格保护并广播给客户端
+    UpdateMarkPrice { symbol: String, mark_price: u64, index_price: u64 },
+    // 合约到期：暂停撮合，以到期原因撤销全部挂单并移除订单簿，之后不再接受该合约的订单
+    Expire(String),
+    // 推进引擎时钟到给定的 Unix 毫秒时间戳，撤销期间到期的 GTD 订单
+    AdvanceTime(u64),
+    // 停止接收新命令，处理完通道中已有的命令后退出主循环
+    Drain,
+    // 请求引擎运行统计
+    StatsRequest(std_mpsc::Sender<EngineStats>),
+    // 日终：取走当日全部成交，交由结算导出处理
+    EndOfDay(std_mpsc::Sender<Vec<TradeNotification>>),
+    // 运行时切换功能开关；symbol 为 None 时修改全局设置
+    SetFeature {
+        feature: Feature,
+        symbol: Option<String>,
+        enabled: bool,
+    },
+    // 注册成交监控规则，同名规则会被替换
+    AddSurveillanceRule(SurveillanceRule),
+    RemoveSurveillanceRule(String),
+    // 回收合约名驻留池中已没有订单簿的合约并释放多余容量，回复回收的合约数
+    ShrinkSymbolPool(std_mpsc::Sender<usize>),
+    // 生成恢复快照（挂单、持仓和 ID 生成器状态），由检查点线程写入磁盘
+    Checkpoint(std_mpsc::Sender<Snapshot>),
+}
+
+// 单个合约的统计信息
+#[derive(Debug, Clone)]
+pub struct MarketStats {
+    pub symbol: String,
+    pub symbol_id: SymbolId,
+    pub phase: TradingPhase,
+    pub resting_orders: usize,
+    pub best_bid: Option<u64>,
+    pub best_ask: Option<u64>,
+}
+
+// 引擎运行统计
+#[derive(Debug, Clone)]
+pub struct EngineStats {
+    pub orders_accepted: u64,
+    pub orders_throttled: u64,
+    pub trades_executed: u64,
+    pub markets: Vec<MarketStats>,
+    pub symbol_pool: SymbolPoolStats,
 }
 
 // 定义引擎的输出结果
+#[derive(Debug)]
 pub enum EngineOutput {
     Trade(TradeNotification),
     Confirmation(OrderConfirmation),
+    Reject(OrderReject),
+    Position(PositionReport),
+    TradingStatus(TradingStatus),
+    CancelAck(CancelAck),
+    FillEstimate(FillEstimate),
+    ExecutionReport(ExecutionReport),
+    MarketData(MarketDataSnapshot),
+    MarkPrice(MarkPrice),
+    ImpliedQuote(ImpliedQuote),
+    // 对带请求 ID 命令的应答，只发给发出请求的连接
+    Response { request_id: u64, connection_id: u64, output: Box<EngineOutput> },
+    // 对未登录连接发出的命令的应答，只发给该连接
+    Direct { connection_id: u64, output: Box<EngineOutput> },
+    // 带面包屑的命令已处理完毕，不发送给客户端
+    Traced(Box<Breadcrumb>),
+    // 开启批量发送时，按发送顺序合在一起的多条输出，见 with_output_batching
+    Batch(Vec<EngineOutput>),
+}
+
+// 最近撤单记录的容量
+const RECENT_CANCELS_CAPACITY: usize = 65_536;
+
+// 大宗交易的校验规则
+#[derive(Debug, Clone, Copy)]
+pub struct BlockTradeRules {
+    // 最小申报数量
+    pub min_quantity: u64,
+    // 相对最新成交价允许的最大偏离，单位为基点 (1bp = 0.01%)
+    pub max_price_deviation_bps: u64,
+}
+
+impl Default for BlockTradeRules {
+    fn default() -> Self {
+        BlockTradeRules {
+            min_quantity: 1_000,
+            max_price_deviation_bps: 1_000, // 10%
+        }
+    }
+}
+
+// 输出的批量发送策略：一条命令产生的成交、回报等输出先缓冲，合成一条 EngineOutput::Batch 发送，
+// 接收方每批只被唤醒一次，而不是每笔成交一次
+#[derive(Debug, Clone, Copy)]
+pub struct OutputBatching {
+    // 缓冲的输出达到这么多条时立即发送
+    pub max_outputs: usize,
+    // 为 true 时等命令队列排空才发送，积压期间多条命令的输出合成一批；为 false 时每条命令处理完发送一次
+    pub until_idle: bool,
+}
+
+impl Default for OutputBatching {
+    fn default() -> Self {
+        OutputBatching { max_outputs: 1_024, until_idle: false }
+    }
+}
+
+// 引擎的输出端，未开启批量发送时直接写入输出通道
+struct OutputSink {
+    sender: UnboundedSender<EngineOutput>,
+    batching: Option<OutputBatching>,
+    buffer: RefCell<Vec<EngineOutput>>,
+}
+
+// 发送失败只说明输出通道已关闭，不带回未送出的输出
+impl OutputSink {
+    fn send(&self, output: EngineOutput) -> Result<(), ()> {
+        let Some(batching) = self.batching else {
+            return self.sender.send(output).map_err(drop);
+        };
+        let full = {
+            let mut buffer = self.buffer.borrow_mut();
+            buffer.push(output);
+            buffer.len() >= batching.max_outputs
+        };
+        if full {
+            self.flush()
+        } else {
+            Ok(())
+        }
+    }
+
+    // 发送缓冲中的输出；只有一条时不包成批
+    fn flush(&self) -> Result<(), ()> {
+        let mut buffer = self.buffer.borrow_mut();
+        let sent = match buffer.len() {
+            0 => return Ok(()),
+            1 => self.sender.send(buffer.pop().expect("缓冲中有一条输出")),
+            _ => self.sender.send(EngineOutput::Batch(std::mem::take(&mut *buffer))),
+        };
+        sent.map_err(drop)
+    }
+}
+
+// 订单簿节点池的回收策略：空闲槽位同时超过数量下限和所占比例时压缩节点池，
+// 避免长时间运行后因撤单和成交留下的空闲槽位占用内存
+#[derive(Debug, Clone, Copy)]
+pub struct ReclaimPolicy {
+    pub min_free_slots: usize,
+    // 空闲槽位占节点池的百分比
+    pub max_free_percent: usize,
+}
+
+impl Default for ReclaimPolicy {
+    fn default() -> Self {
+        ReclaimPolicy {
+            min_free_slots: 65_536,
+            max_free_percent: 50,
+        }
+    }
+}
+
+impl ReclaimPolicy {
+    fn should_compact(&self, book: &OrderBook) -> bool {
+        let free = book.free_slots();
+        free >= self.min_free_slots && free * 100 >= book.pool_slots() * self.max_free_percent
+    }
+}
+
+// 单个合约的订单簿及其交易状态
+#[derive(Default)]
+struct Market {
+    book: OrderBook,
+    // 最新成交价，作为大宗交易价格校验的参考价
+    last_trade_price: Option<u64>,
+    // 暂停或集合竞价期间订单直接挂入订单簿，不进行撮合
+    phase: TradingPhase,
+    // 配置的分配算法，实际生效与否取决于功能开关
+    allocation_policy: AllocationPolicy,
+    // 成交速率计量器，与 Surveillance 中登记的是同一个实例
+    meter: Arc<TradeMeter>,
+    // 本合约的计数和撮合耗时，与 EngineMetrics 中登记的是同一个实例
+    metrics: Arc<SymbolMetrics>,
+    // SymbolPool 分配的合约 ID
+    symbol_id: SymbolId,
+}
+
+impl Market {
+    // 更新节点池占用和内存占用的指标
+    fn record_occupancy(&self) {
+        let usage = self.book.memory_usage();
+        self.metrics.pool_slots.store(usage.pool_slots as u64, Ordering::Relaxed);
+        self.metrics.resting_orders.store(usage.resting_orders as u64, Ordering::Relaxed);
+        self.metrics.memory_bytes.store(usage.bytes as u64, Ordering::Relaxed);
+    }
 }
 
 // 撮合引擎
 pub struct MatchingEngine {
-    orderbook: OrderBook,
+    // 每个合约拥有独立的订单簿
+    markets: HashMap<String, Market>,
+    // 驻留的合约名和合约 ID，订单簿和成交回报共享其中的合约名
+    symbols: SymbolPool,
     command_receiver: UnboundedReceiver<EngineCommand>,
-    output_sender: UnboundedSender<EngineOutput>,
-    next_trade_id: u64,
+    output_sender: OutputSink,
+    // 订单号生成器和成交定序组件，由所有合约的订单簿共享
+    order_ids: Arc<IdGenerator>,
+    sequencer: Arc<Sequencer>,
+    block_trade_rules: BlockTradeRules,
+    price_bands: HashMap<String, PriceBand>,
+    // 按用户限流，未配置时不限流
+    rate_limiter: Option<RateLimiter>,
+    // 开启时命令先按类型分到优先车道和普通车道，再按权重取出处理
+    lanes: Option<PriorityLanes>,
+    metrics: Arc<EngineMetrics>,
+    positions: PositionTracker,
+    recent_cancels: RecentCancels,
+    feature_flags: FeatureFlags,
+    // 当日成交记录，日终时交给结算导出后清空
+    trade_log: Vec<TradeNotification>,
+    reclaim_policy: ReclaimPolicy,
+    surveillance: Arc<Surveillance>,
+    market_data: MarketData,
+    // 已到期的合约
+    expired: HashSet<String>,
+    // 各合约最新的标记价格，以及基于它的价格保护（未配置时不检查）
+    mark_prices: HashMap<String, MarkPrice>,
+    price_collar: Option<PriceCollar>,
+    // 价差合约定义，以及最近一次广播的隐含报价
+    spreads: BTreeMap<String, SpreadDefinition>,
+    implied_quotes: HashMap<String, ImpliedQuote>,
+    // OCO 订单的关联，按仍在订单簿中的订单号索引
+    oco_links: HashMap<u64, OcoLink>,
+    // GTD 订单的到期定时器 (合约, 订单号)，时间轮的当前时刻即引擎时钟
+    good_till_date: TimerWheel<(String, u64)>,
+    // GTD 订单的到期时间，按订单号索引，改单时沿用；与定时器一起在触发时移除
+    good_till: HashMap<u64, u64>,
+    // 挂在订单簿中的只减仓订单，按 (用户, 合约) 分组、按下单先后排列；已成交或撤销的订单在下次检查时移除
+    reduce_only: HashMap<(u64, String), Vec<u64>>,
+    // 本次命令中持仓发生变化、需要重新检查只减仓订单的 (用户, 合约)
+    reduce_only_dirty: Vec<(u64, String)>,
+    // 正在处理的带请求 ID 或接收时间戳的命令
+    request: Option<ActiveRequest>,
+    // 崩溃恢复的命令日志，由 recovery::recover 接上；为 None 时不记录
+    command_log: Option<CommandLog>,
+    // 最后写入命令日志的时钟。没有定时器到期的节拍不写日志，GTD 订单按时钟检查有效期之前补写
+    logged_clock_ms: u64,
+    // 每条写入命令日志的命令处理完后，记录其产生的成交
+    output_log: Option<OutputLog>,
+    // 市场行为检测线程的事件通道，为 None 时不发送
+    surveillance_tap: Option<std_mpsc::Sender<MarketEvent>>,
+    // 发布一致深度视图的配置和共享视图，为 None 时不发布
+    depth_view: Option<(DepthViewConfig, Arc<DepthView>)>,
+    // 已处理的命令数，以及上次发布深度视图时的命令数
+    commands_handled: u64,
+    depth_view_published_at: u64,
+    // 逐笔（L3）行情流的发送端和发出校验和的命令间隔，为 None 时不发送
+    l3_feed: Option<(std_mpsc::Sender<L3Message>, u64)>,
+    l3_seq: u64,
+}
+
+// 正在处理的请求；taker 是新订单或改单作为主动方时的买卖方向，
+// 该方向的执行回报属于这个请求，对手方的回报则不属于
+#[derive(Debug, Clone, Copy, Default)]
+struct ActiveRequest {
+    // 请求 ID 和发出请求的连接
+    request_id: Option<(u64, u64)>,
+    // 发出命令的未登录连接
+    connection_id: Option<u64>,
+    received_at_ns: Option<u64>,
+    taker: Option<OrderType>,
+}
+
+// OCO 订单的关联。sibling 是另一条仍在订单簿中的订单 (合约, 订单号)，
+// 任何一条成交后另一条被撤销、sibling 清空，关联 ID 仍随剩余部分的执行回报回显
+#[derive(Debug, Clone)]
+struct OcoLink {
+    link_id: u64,
+    sibling: Option<(String, u64)>,
+}
+
+// 一笔新订单进入订单簿的结果：resting 为挂单的订单号，traded 表示提交时是否有成交
+#[derive(Debug, Clone, Copy, Default)]
+struct Placement {
+    resting: Option<u64>,
+    traded: bool,
 }
 
 impl MatchingEngine {
@@ -28,49 +435,1951 @@ impl MatchingEngine {
         output_sender: UnboundedSender<EngineOutput>,
     ) -> Self {
         MatchingEngine {
-            orderbook: OrderBook::new(),
+            markets: HashMap::new(),
+            symbols: SymbolPool::new(),
             command_receiver,
-            output_sender,
-            next_trade_id: 1,
+            output_sender: OutputSink { sender: output_sender, batching: None, buffer: RefCell::new(Vec::new()) },
+            order_ids: Arc::new(IdGenerator::sequential()),
+            sequencer: Arc::new(Sequencer::default()),
+            block_trade_rules: BlockTradeRules::default(),
+            price_bands: HashMap::new(),
+            rate_limiter: None,
+            lanes: None,
+            metrics: Arc::new(EngineMetrics::new()),
+            positions: PositionTracker::default(),
+            recent_cancels: RecentCancels::new(RECENT_CANCELS_CAPACITY),
+            feature_flags: FeatureFlags::new(),
+            trade_log: Vec::new(),
+            reclaim_policy: ReclaimPolicy::default(),
+            surveillance: Arc::new(Surveillance::new()),
+            market_data: MarketData::default(),
+            expired: HashSet::new(),
+            mark_prices: HashMap::new(),
+            price_collar: None,
+            spreads: BTreeMap::new(),
+            implied_quotes: HashMap::new(),
+            oco_links: HashMap::new(),
+            good_till_date: TimerWheel::new(0),
+            good_till: HashMap::new(),
+            reduce_only: HashMap::new(),
+            reduce_only_dirty: Vec::new(),
+            request: None,
+            command_log: None,
+            logged_clock_ms: 0,
+            output_log: None,
+            surveillance_tap: None,
+            depth_view: None,
+            commands_handled: 0,
+            depth_view_published_at: 0,
+            l3_feed: None,
+            l3_seq: 0,
         }
     }
 
+    // 替换默认的大宗交易校验规则
+    pub fn with_block_trade_rules(mut self, rules: BlockTradeRules) -> Self {
+        self.block_trade_rules = rules;
+        self
+    }
+
+    // 启用按用户的下单限流
+    pub fn with_rate_limit(mut self, config: RateLimitConfig) -> Self {
+        self.rate_limiter = Some(RateLimiter::new(config));
+        self
+    }
+
+    // 启用优先车道：撤单、改单优先于新订单处理，两条车道按权重轮流服务
+    pub fn with_priority_lanes(mut self, weights: LaneWeights) -> Self {
+        self.lanes = Some(PriorityLanes::new(weights));
+        self
+    }
+
+    // 启用持仓限额检查
+    pub fn with_position_limits(mut self, limits: PositionLimits) -> Self {
+        self.positions = PositionTracker::new(limits);
+        self
+    }
+
+    // 为合约配置同一价格层级上的成交分配算法（默认时间优先）
+    pub fn with_allocation_policy(mut self, symbol: &str, policy: AllocationPolicy) -> Self {
+        self.configured_market(symbol).allocation_policy = policy;
+        self.apply_feature_flags();
+        self
+    }
+
+    // 使用给定的功能开关配置，之后可以通过 ControlCommand::SetFeature 在运行时调整
+    pub fn with_feature_flags(mut self, flags: FeatureFlags) -> Self {
+        self.feature_flags = flags;
+        self.apply_feature_flags();
+        self
+    }
+
+    // 为合约配置最小变动价位（默认为 1）
+    pub fn with_tick_size(mut self, symbol: &str, tick_size: u64) -> Self {
+        self.configured_market(symbol).book.set_tick_size(tick_size);
+        self
+    }
+
+    // 限制合约名驻留池中的合约数。池满时回收最久未使用、已没有订单簿的合约，
+    // 仍没有空位时新合约的订单被拒绝（SymbolLimitReached）
+    pub fn with_symbol_limit(mut self, limit: usize) -> Self {
+        self.symbols.set_capacity_limit(Some(limit));
+        self
+    }
+
+    // 设置某个合约的涨跌停价格带，运行期间可以通过 ControlCommand::SetPriceBand 更新
+    pub fn with_price_band(mut self, symbol: &str, band: PriceBand) -> Self {
+        self.price_bands.insert(symbol.to_string(), band);
+        self
+    }
+
+    // 启用基于标记价格的价格保护，标记价格通过 ControlCommand::UpdateMarkPrice 更新
+    pub fn with_price_collar(mut self, collar: PriceCollar) -> Self {
+        self.price_collar = Some(collar);
+        self
+    }
+
+    // 定义一个价差合约，两腿按普通合约交易
+    pub fn with_spread(mut self, definition: SpreadDefinition) -> Self {
+        self.spreads.insert(definition.symbol.clone(), definition);
+        self
+    }
+
+    // 替换默认的节点池回收策略
+    pub fn with_reclaim_policy(mut self, policy: ReclaimPolicy) -> Self {
+        self.reclaim_policy = policy;
+        self
+    }
+
+    // 替换默认的 K 线周期（1 分钟），之前汇总的行情会被清空
+    pub fn with_candle_intervals(mut self, intervals: &[Duration]) -> Self {
+        self.market_data = MarketData::new(intervals, DEFAULT_CANDLE_HISTORY);
+        self
+    }
+
+    // 使用给定的 ID 生成器分配订单号和成交号（例如带分片号的雪花 ID），
+    // 多个引擎实例之间的 ID 也不会重复
+    pub fn with_id_generator(mut self, ids: Arc<IdGenerator>) -> Self {
+        self.sequencer = Arc::new(Sequencer::new(ids.clone()));
+        for market in self.markets.values_mut() {
+            market.book.set_id_generator(ids.clone());
+            market.book.set_sequencer(self.sequencer.clone());
+        }
+        self.order_ids = ids;
+        self
+    }
+
+    // 使用逻辑时钟为成交和快照打时间戳（每次取时间前进 1 纳秒），
+    // 相同的命令序列总是产生完全相同的输出，供确定性仿真和回归测试使用
+    pub fn with_logical_clock(mut self) -> Self {
+        self.sequencer = Arc::new(Sequencer::logical(self.order_ids.clone()));
+        for market in self.markets.values_mut() {
+            market.book.set_sequencer(self.sequencer.clone());
+        }
+        self
+    }
+
+    // 处理会改变挂单的命令之前先写入命令日志，通常由 recovery::recover 在重放完成后调用
+    pub fn with_command_log(mut self, log: CommandLog) -> Self {
+        self.command_log = Some(log);
+        self.logged_clock_ms = self.good_till_date.now();
+        self
+    }
+
+    // 记录每条日志命令产生的成交，只在接上命令日志后生效，通常由 recovery::recover 调用
+    pub fn with_output_log(mut self, log: OutputLog) -> Self {
+        self.output_log = Some(log);
+        self
+    }
+
+    // 把处理过的每个订单事件和成交发给市场行为检测线程，见 surveillance::spawn_market_detectors
+    pub fn with_surveillance_tap(mut self, tap: std_mpsc::Sender<MarketEvent>) -> Self {
+        self.surveillance_tap = Some(tap);
+        self
+    }
+
+    // 批量发送输出，见 OutputBatching。run 和 run_async 按策略发送缓冲的输出；
+    // 直接调用 handle_command 的调用方需自行调用 flush_outputs
+    pub fn with_output_batching(mut self, batching: OutputBatching) -> Self {
+        assert!(batching.max_outputs > 0, "批量发送的输出数必须大于 0");
+        self.output_sender.batching = Some(batching);
+        self
+    }
+
+    // 发送缓冲中的全部输出
+    pub fn flush_outputs(&self) {
+        if self.output_sender.flush().is_err() {
+            eprintln!("输出通道已关闭，无法发送批量输出");
+        }
+    }
+
+    // 定期发布所有合约的一致深度视图，查询线程通过 depth_view() 读取而不暂停撮合，见 depth_view 模块
+    pub fn with_depth_view(mut self, config: DepthViewConfig) -> Self {
+        self.depth_view = Some((config, Arc::new(DepthView::new())));
+        self.publish_depth_view();
+        self
+    }
+
+    pub fn depth_view(&self) -> Option<Arc<DepthView>> {
+        self.depth_view.as_ref().map(|(_, view)| view.clone())
+    }
+
+    // 把订单簿的逐笔变化发给副本订单簿（见 replica 模块），每处理 checksum_every 条命令附上各合约的校验和。
+    // 接上时先按时间优先的顺序发出已有挂单，副本从当前状态开始
+    pub fn with_l3_feed(mut self, feed: std_mpsc::Sender<L3Message>, checksum_every: u64) -> Self {
+        assert!(checksum_every > 0, "校验和间隔必须大于 0");
+        self.l3_feed = Some((feed, checksum_every));
+        let mut symbols: Vec<String> = self.markets.keys().cloned().collect();
+        symbols.sort();
+        for symbol in &symbols {
+            let book = &mut self.markets.get_mut(symbol).expect("已有的合约").book;
+            book.enable_book_events();
+            for node in book.queued_orders() {
+                self.send_l3(L3Message::Event {
+                    seq: 0,
+                    symbol: symbol.clone(),
+                    event: BookEvent::Added {
+                        order_id: node.order_id,
+                        user_id: node.user_id,
+                        order_type: node.order_type,
+                        price: node.price,
+                        quantity: node.quantity,
+                    },
+                });
+            }
+        }
+        self.send_l3_checksums();
+        self
+    }
+
+    // 返回引擎指标的共享句柄，可以在其他线程中读取
+    pub fn metrics(&self) -> Arc<EngineMetrics> {
+        self.metrics.clone()
+    }
+
+    // 各合约订单簿的内存占用，按合约名排序；引擎运行时通过指标中的 memory_bytes 读取
+    pub fn memory_usage(&self) -> BTreeMap<String, MemoryUsage> {
+        self.markets.iter().map(|(symbol, market)| (symbol.clone(), market.book.memory_usage())).collect()
+    }
+
+    // 返回成交计量器和监控规则的共享句柄，规则由 spawn_surveillance_monitor 在引擎线程之外评估
+    pub fn surveillance(&self) -> Arc<Surveillance> {
+        self.surveillance.clone()
+    }
+
     // 引擎的主事件循环
     pub fn run(&mut self) {
         println!("撮合引擎启动...");
-        while let Some(command) = self.command_receiver.blocking_recv() {
-            match command {
-                EngineCommand::NewOrder(request) => {
-                    let (trades, confirmation_opt) = self.orderbook.match_order(request);
-
-                    for mut trade in trades {
-                        trade.trade_id = self.next_trade_id;
-                        trade.timestamp = std::time::SystemTime::now()
-                            .duration_since(std::time::UNIX_EPOCH)
-                            .unwrap_or_default()
-                            .as_nanos() as u64;
-                        self.next_trade_id += 1;
-                        // 将成交结果发送出去
-                        if self.output_sender.send(EngineOutput::Trade(trade)).is_err() {
-                            eprintln!("输出通道已关闭，无法发送成交回报");
-                        }
+        while let Some(command) = self.next_command() {
+            // 注入的引擎停顿：命令在通道中积压，用于检验背压
+            if let Some(stall) = self.before_command() {
+                std::thread::sleep(stall);
+            }
+            self.handle_command(command);
+            self.after_command();
+        }
+        self.flush_outputs();
+        println!("撮合引擎关闭。");
+    }
+
+    // 异步的主事件循环，作为 tokio 任务运行，不需要独占一个线程。等待命令时让出所在的工作线程；
+    // 命令连续到达时每条命令消耗一次协作调度预算，预算用尽后让出，同一线程上的网络任务不会被饿死。
+    // 命令的处理本身是同步的（包括日志写入），对延迟敏感的部署仍应使用 run 和专用线程
+    pub async fn run_async(&mut self) {
+        println!("撮合引擎启动...");
+        while let Some(command) = self.next_command_async().await {
+            if let Some(stall) = self.before_command() {
+                tokio::time::sleep(stall).await;
+            }
+            self.handle_command(command);
+            self.after_command();
+            tokio::task::consume_budget().await;
+        }
+        self.flush_outputs();
+        println!("撮合引擎关闭。");
+    }
+
+    // 取下一条命令。开启优先车道时先把通道中已到达的命令分到两条车道，再按权重挑选
+    fn next_command(&mut self) -> Option<EngineCommand> {
+        let Some(lanes) = self.lanes.as_mut() else {
+            return self.command_receiver.blocking_recv();
+        };
+        while let Ok(command) = self.command_receiver.try_recv() {
+            lanes.push(command);
+        }
+        lanes.pop().or_else(|| self.command_receiver.blocking_recv())
+    }
+
+    async fn next_command_async(&mut self) -> Option<EngineCommand> {
+        let Some(lanes) = self.lanes.as_mut() else {
+            return self.command_receiver.recv().await;
+        };
+        while let Ok(command) = self.command_receiver.try_recv() {
+            lanes.push(command);
+        }
+        match lanes.pop() {
+            Some(command) => Some(command),
+            None => self.command_receiver.recv().await,
+        }
+    }
+
+    // 记录队列深度，返回注入的引擎停顿
+    fn before_command(&self) -> Option<Duration> {
+        let queued = self.command_receiver.len() + self.lanes.as_ref().map_or(0, PriorityLanes::len);
+        self.metrics.command_queue_depth.store(queued as u64, Ordering::Relaxed);
+        match fault::inject(FaultPoint::EngineCommand) {
+            Some(FaultAction::Delay(stall)) => Some(stall),
+            _ => None,
+        }
+    }
+
+    // 队列排空时发布最新状态，空闲期间查询到的视图不落后；按批量策略发送缓冲的输出
+    fn after_command(&mut self) {
+        let idle = self.command_receiver.is_empty() && self.lanes.as_ref().is_none_or(PriorityLanes::is_empty);
+        if self.depth_view.is_some() && self.depth_view_published_at != self.commands_handled && idle {
+            self.publish_depth_view();
+        }
+        if self.output_sender.batching.is_some_and(|batching| idle || !batching.until_idle) {
+            self.flush_outputs();
+        }
+    }
+
+    // 处理一条命令，输出写入输出通道。确定性仿真直接在当前线程逐条调用，不经过命令通道
+    pub fn handle_command(&mut self, command: EngineCommand) {
+        if let EngineCommand::Traced { mut breadcrumb, command } = command {
+            self.handle_command(*command);
+            breadcrumb.matched = Some(Instant::now());
+            if self.output_sender.send(EngineOutput::Traced(breadcrumb)).is_err() {
+                eprintln!("输出通道已关闭，无法送回面包屑");
+            }
+            return;
+        }
+        if let EngineCommand::Received { received_at_ns, command } = command {
+            self.request.get_or_insert_with(ActiveRequest::default).received_at_ns = Some(received_at_ns);
+            self.handle_command(*command);
+            self.request = None;
+            self.metrics.record_wire_latency(Duration::from_nanos(unix_nanos().saturating_sub(received_at_ns)));
+            return;
+        }
+        if let EngineCommand::Request { request_id, connection_id, command } = command {
+            self.request.get_or_insert_with(ActiveRequest::default).request_id = Some((request_id, connection_id));
+            self.handle_command(*command);
+            self.request = None;
+            return;
+        }
+        if let EngineCommand::Direct { connection_id, command } = command {
+            self.request.get_or_insert_with(ActiveRequest::default).connection_id = Some(connection_id);
+            self.handle_command(*command);
+            self.request = None;
+            return;
+        }
+        if !self.admit(&command) {
+            return;
+        }
+        self.execute(command);
+    }
+
+    // 应用已写入日志的命令，供恢复重放、备机和集群节点调用。限流按真实时间判断，重放时结果不同；
+    // 日志中只有记录时通过了限流的命令，这里不再检查
+    pub fn apply_logged(&mut self, command: LoggedCommand) {
+        self.execute(command.into_command());
+    }
+
+    fn execute(&mut self, command: EngineCommand) {
+        // 撮合阶段的耗时；没有订阅 debug 级别时创建 span 只是一次原子读
+        let _span = tracing::debug_span!("match", command = command.kind()).entered();
+        // 先写日志再处理。写入失败时无法保证重启后恢复已确认的订单，引擎直接停止
+        let mut logged_seq = None;
+        if self.command_log.is_some() {
+            match LoggedCommand::from_command(&command) {
+                // 时钟节拍由 advance_time 在有定时器到期时写入，每个节拍都写会让空闲的引擎每秒写上百条日志
+                Some(LoggedCommand::AdvanceTime(_)) | None => {}
+                Some(logged) => {
+                    let checks_clock = matches!(
+                        &logged,
+                        LoggedCommand::TimedOrder(TimedOrderRequest { time_in_force: TimeInForce::GoodTillDate { .. }, .. })
+                    );
+                    if checks_clock && self.good_till_date.now() > self.logged_clock_ms {
+                        self.log_clock();
                     }
+                    logged_seq = self.log_command(logged);
+                }
+            }
+        }
+        let trades_before = self.trade_log.len();
+        // 没有接检测线程时不生成事件
+        let order_events = self.surveillance_tap.as_ref().map(|_| MarketEvent::from_command(&command));
+        match command {
+            EngineCommand::NewOrder(request) => self.process_new_order(request),
+            EngineCommand::CancelOrder(request) => self.process_cancel_order(request),
+            EngineCommand::AmendOrder(request) => self.process_amend_order(request),
+            EngineCommand::BlockTrade(request) => self.process_block_trade(request),
+            EngineCommand::QueryPosition(query) => self.process_position_query(query),
+            EngineCommand::EstimateFill(request) => self.process_fill_estimate(request),
+            EngineCommand::QueryMarketData(query) => self.process_market_data_query(query),
+            EngineCommand::SpreadOrder(request) => self.process_spread_order(request),
+            EngineCommand::BasketOrder(request) => self.process_basket_order(request),
+            EngineCommand::OcoOrder(request) => self.process_oco_order(request),
+            EngineCommand::TimedOrder(request) => self.process_timed_order(request),
+            EngineCommand::ReduceOnlyOrder(request) => self.process_reduce_only_order(request),
+            EngineCommand::CancelUserOrders(user_id) => self.cancel_user_orders(user_id),
+            EngineCommand::SnapshotDepth { depth, largest_orders, reply } => {
+                self.snapshot_depth(depth, largest_orders, reply)
+            }
+            EngineCommand::Control(control) => self.process_control(control),
+            EngineCommand::Request { .. }
+            | EngineCommand::Direct { .. }
+            | EngineCommand::Received { .. }
+            | EngineCommand::Traced { .. } => {
+                unreachable!("已在上面拆开")
+            }
+        }
+        if !self.reduce_only_dirty.is_empty() {
+            self.trim_reduce_only();
+        }
+        if !self.spreads.is_empty() {
+            self.publish_implied_quotes();
+        }
+        if let (Some(tap), Some(order_events)) = (self.surveillance_tap.as_ref(), order_events) {
+            // 取走成交记录的管理命令之后 trade_log 可能比处理前短
+            let trades = self.trade_log.get(trades_before..).unwrap_or_default().iter().cloned().map(MarketEvent::Trade);
+            // 检测线程退出后不再发送
+            if order_events.into_iter().chain(trades).any(|event| tap.send(event).is_err()) {
+                self.surveillance_tap = None;
+            }
+        }
+        if let (Some(seq), Some(log)) = (logged_seq, self.output_log.as_mut()) {
+            let record = OutputRecord { seq, trades: self.trade_log.get(trades_before..).unwrap_or_default().to_vec() };
+            if let Err(e) = log.append(&record) {
+                panic!("写入输出日志失败: {}", e);
+            }
+        }
+        self.commands_handled += 1;
+        if let Some((_, checksum_every)) = self.l3_feed {
+            self.flush_book_events();
+            if self.commands_handled.is_multiple_of(checksum_every) {
+                self.send_l3_checksums();
+            }
+        }
+        if let Some((config, _)) = &self.depth_view {
+            if self.commands_handled - self.depth_view_published_at >= config.every_commands {
+                self.publish_depth_view();
+            }
+        }
+    }
+
+    fn log_command(&mut self, logged: LoggedCommand) -> Option<u64> {
+        let log = self.command_log.as_mut()?;
+        match log.append(logged) {
+            Ok(seq) => Some(seq),
+            Err(e) => panic!("写入命令日志失败: {}", e),
+        }
+    }
+
+    // 把引擎时钟写入命令日志，重放到这里时时钟与记录时一致
+    fn log_clock(&mut self) {
+        self.logged_clock_ms = self.good_till_date.now();
+        self.log_command(LoggedCommand::AdvanceTime(self.logged_clock_ms));
+    }
+
+    fn process_new_order(&mut self, request: NewOrderRequest) {
+        let started = Instant::now();
+        self.set_taker(request.order_type);
+        self.place_order(request, started, None);
+    }
+
+    // 指定有效期的订单：GTC 与普通新订单相同；GTD 订单挂单后登记到期定时器
+    fn process_timed_order(&mut self, request: TimedOrderRequest) {
+        let TimedOrderRequest { order, time_in_force } = request;
+        let expire_at_ms = match time_in_force {
+            TimeInForce::GoodTillCancel => return self.process_new_order(order),
+            TimeInForce::GoodTillDate { expire_at_ms } => expire_at_ms,
+        };
+        let started = Instant::now();
+        self.set_taker(order.order_type);
+        if expire_at_ms <= self.good_till_date.now() {
+            self.send_reject(order.user_id, order.symbol, RejectReason::InvalidExpiry);
+            return;
+        }
+        let symbol = order.symbol.clone();
+        if let Some(order_id) = self.place_order(order, started, None).resting {
+            self.good_till_date.insert(expire_at_ms, (symbol, order_id));
+            self.good_till.insert(order_id, expire_at_ms);
+        }
+    }
+
+    // 只减仓订单：数量截断为反向持仓中尚未被同方向只减仓挂单占用的部分，没有剩余时拒绝
+    fn process_reduce_only_order(&mut self, mut request: NewOrderRequest) {
+        let started = Instant::now();
+        self.set_taker(request.order_type);
+        let key = (request.user_id, request.symbol.clone());
+        let room = self.reduce_only_room(&key, request.order_type);
+        if room == 0 {
+            self.send_reject(request.user_id, request.symbol, RejectReason::ReduceOnlyExceedsPosition);
+            return;
+        }
+        request.quantity = request.quantity.min(room);
+        if let Some(order_id) = self.place_order(request, started, None).resting {
+            self.reduce_only.entry(key).or_default().push(order_id);
+        }
+    }
+
+    // 反向持仓减去同方向只减仓挂单的剩余数量，即新的只减仓订单最多可以成交的数量
+    fn reduce_only_room(&self, key: &(u64, String), side: OrderType) -> u64 {
+        let position = self.positions.position(key.0, &key.1);
+        let opposite = match side {
+            OrderType::Buy => position.min(0).unsigned_abs(),
+            OrderType::Sell => position.max(0) as u64,
+        };
+        let (Some(order_ids), Some(market)) = (self.reduce_only.get(key), self.markets.get(&key.1)) else {
+            return opposite;
+        };
+        let resting: u64 = order_ids
+            .iter()
+            .filter_map(|&order_id| market.book.order(order_id))
+            .filter(|node| node.order_type == side)
+            .map(|node| node.quantity)
+            .sum();
+        opposite.saturating_sub(resting)
+    }
+
+    // 持仓变化后按新的反向持仓缩减只减仓挂单：先下的订单保留数量，超出的部分从最后下的订单开始截去，
+    // 截到 0 的订单撤销
+    fn trim_reduce_only(&mut self) {
+        for key in std::mem::take(&mut self.reduce_only_dirty) {
+            let Some(order_ids) = self.reduce_only.remove(&key) else {
+                continue;
+            };
+            let position = self.positions.position(key.0, &key.1);
+            let (mut buy_room, mut sell_room) = (position.min(0).unsigned_abs(), position.max(0) as u64);
+            let mut kept = Vec::with_capacity(order_ids.len());
+            let mut restated = Vec::new();
+            let Some(market) = self.markets.get_mut(&key.1) else {
+                continue;
+            };
+            for order_id in order_ids {
+                let Some(node) = market.book.order(order_id) else {
+                    continue;
+                };
+                let room = match node.order_type {
+                    OrderType::Buy => &mut buy_room,
+                    OrderType::Sell => &mut sell_room,
+                };
+                let leaves = node.quantity.min(*room);
+                *room -= leaves;
+                if leaves == node.quantity {
+                    kept.push(order_id);
+                    continue;
+                }
+                let node = if leaves == 0 {
+                    market.metrics.cancels.fetch_add(1, Ordering::Relaxed);
+                    let Ok(node) = market.book.cancel_order(order_id) else {
+                        continue;
+                    };
+                    self.recent_cancels.insert(key.1.clone(), order_id, node.user_id);
+                    node
+                } else {
+                    let node = node.clone();
+                    if market.book.reduce_order(order_id, leaves).is_err() {
+                        continue;
+                    }
+                    kept.push(order_id);
+                    node
+                };
+                restated.push(ExecutionReport {
+                    user_id: node.user_id,
+                    symbol: key.1.clone(),
+                    order_id,
+                    order_type: node.order_type,
+                    status: OrderStatus::Restated,
+                    trade_id: 0,
+                    last_price: 0,
+                    last_quantity: 0,
+                    cumulative_quantity: node.filled_quantity,
+                    leaves_quantity: leaves,
+                    link_id: None,
+                    received_at_ns: None,
+                });
+            }
+            for report in restated {
+                if self.output_sender.send(EngineOutput::ExecutionReport(report)).is_err() {
+                    eprintln!("输出通道已关闭，无法发送执行回报");
+                }
+            }
+            if !kept.is_empty() {
+                self.reduce_only.insert(key.clone(), kept);
+            }
+            self.reclaim_memory(&key.1);
+        }
+    }
+
+    // 推进引擎时钟，撤销到期的 GTD 订单并发送状态为 Expired 的执行回报。
+    // 定时器不随成交或撤单删除，触发时订单可能已经不在订单簿中
+    fn advance_time(&mut self, now_ms: u64) {
+        let mut expired = self.good_till_date.advance(now_ms);
+        if expired.is_empty() {
+            return;
+        }
+        // 跳过的节拍没有写日志，重放时时间轮一次推进到这里，同一批中的先后按到期时间和订单号确定
+        expired.sort_unstable_by_key(|(_, order_id)| (self.good_till.get(order_id).copied(), *order_id));
+        self.log_clock();
+        for (symbol, order_id) in expired {
+            self.good_till.remove(&order_id);
+            let Some(market) = self.markets.get_mut(&symbol) else {
+                continue;
+            };
+            let Ok(node) = market.book.cancel_order(order_id) else {
+                continue;
+            };
+            market.metrics.cancels.fetch_add(1, Ordering::Relaxed);
+            self.recent_cancels.insert(symbol.clone(), order_id, node.user_id);
+            let report = ExecutionReport {
+                user_id: node.user_id,
+                symbol: symbol.clone(),
+                order_id,
+                order_type: node.order_type,
+                status: OrderStatus::Expired,
+                trade_id: 0,
+                last_price: 0,
+                last_quantity: 0,
+                cumulative_quantity: node.filled_quantity,
+                leaves_quantity: 0,
+                link_id: None,
+                received_at_ns: None,
+            };
+            if self.output_sender.send(EngineOutput::ExecutionReport(report)).is_err() {
+                eprintln!("输出通道已关闭，无法发送执行回报");
+            }
+            self.reclaim_memory(&symbol);
+        }
+    }
+
+    // 下单类命令在写入日志之前按用户限流，被限流的命令不写日志也不处理，重放时不必重现限流的结果。
+    // 每条命令占用一个令牌；原价减量的改单在订单簿中原地完成，不算新订单
+    pub(crate) fn admit(&mut self, command: &EngineCommand) -> bool {
+        let (user_id, symbol) = match command {
+            EngineCommand::NewOrder(request) | EngineCommand::ReduceOnlyOrder(request) => {
+                (request.user_id, request.symbol.as_str())
+            }
+            EngineCommand::TimedOrder(request) => (request.order.user_id, request.order.symbol.as_str()),
+            // 两条腿各占一个令牌，否则 OCO 可以让挂单数翻倍地绕过限流
+            EngineCommand::OcoOrder(request) => {
+                return self.admit_order(request.first.user_id, &request.first.symbol, 2);
+            }
+            EngineCommand::SpreadOrder(request) => (request.user_id, request.spread.as_str()),
+            EngineCommand::BasketOrder(request) => {
+                (request.user_id, request.legs.first().map_or("", |leg| leg.symbol.as_str()))
+            }
+            EngineCommand::AmendOrder(request) => {
+                let in_place = self
+                    .markets
+                    .get(&request.symbol)
+                    .and_then(|market| market.book.order(request.order_id))
+                    .is_some_and(|order| order.price == request.new_price && request.new_quantity <= order.quantity);
+                if in_place {
+                    return true;
+                }
+                (request.user_id, request.symbol.as_str())
+            }
+            _ => return true,
+        };
+        self.admit_order(user_id, symbol, 1)
+    }
 
-                    if let Some(confirmation) = confirmation_opt {
-                        // 如果订单未完全成交，会有一个新挂单
-                        // 发送这个新挂单的确认信息
-                        if self.output_sender.send(EngineOutput::Confirmation(confirmation)).is_err() {
-                            eprintln!("输出通道已关闭，无法发送订单确认");
-                        }
+    // 按用户限流并计数，消耗 tokens 个令牌；被限流时发送拒绝回报并返回 false
+    fn admit_order(&mut self, user_id: u64, symbol: &str, tokens: u32) -> bool {
+        if let Some(limiter) = self.rate_limiter.as_mut() {
+            if !limiter.try_acquire_many(user_id, tokens) {
+                self.metrics.orders_throttled.fetch_add(1, Ordering::Relaxed);
+                self.send_reject(user_id, symbol.to_string(), RejectReason::Throttled);
+                return false;
+            }
+        }
+        self.metrics.orders_accepted.fetch_add(1, Ordering::Relaxed);
+        true
+    }
+
+    // 检查新订单并送入订单簿。link 是 OCO 订单的关联，在发布执行回报之前按订单号登记，
+    // 提交时即主动成交的回报也能带上关联 ID
+    fn place_order(&mut self, request: NewOrderRequest, started: Instant, link: Option<OcoLink>) -> Placement {
+        if self.expired.contains(&request.symbol) {
+            self.send_reject(request.user_id, request.symbol, RejectReason::ContractExpired);
+            return Placement::default();
+        }
+
+        if !self.within_position_limit(request.user_id, &request.symbol, request.order_type, request.quantity, 0) {
+            self.send_reject(request.user_id, request.symbol, RejectReason::PositionLimitExceeded);
+            return Placement::default();
+        }
+
+        let symbol = request.symbol.clone();
+        let band = self.price_bands.get(&symbol).copied();
+        let collar = self.price_collar.zip(self.mark_prices.get(&symbol));
+        let collar = collar.map(|(collar, mark)| collar.bounds(mark.mark_price));
+        let Some(market) = self.market_entry(&symbol) else {
+            self.send_reject(request.user_id, symbol, RejectReason::SymbolLimitReached);
+            return Placement::default();
+        };
+        market.metrics.orders.fetch_add(1, Ordering::Relaxed);
+        let symbol_metrics = market.metrics.clone();
+
+        if let Err(error) = market.book.validate(&request) {
+            self.send_reject(request.user_id, symbol, error.into());
+            return Placement::default();
+        }
+
+        if let Some((lower, upper)) = collar {
+            if !(lower..=upper).contains(&request.price) {
+                self.send_reject(request.user_id, symbol, RejectReason::PriceCollarBreach);
+                return Placement::default();
+            }
+        }
+
+        // 暂停和集合竞价期间只接受挂单，不撮合
+        if market.phase != TradingPhase::Continuous {
+            let confirmation = market.book.insert_order(request);
+            market.record_occupancy();
+            return self.rest_order(confirmation, link);
+        }
+
+        if let Some(band) = band {
+            let (lower, upper) = band.bounds();
+            if market.book.would_trade_outside(&request, lower, upper) {
+                match band.policy {
+                    BreachPolicy::Reject => {
+                        self.send_reject(request.user_id, symbol, RejectReason::PriceBandBreach);
+                        return Placement::default();
+                    }
+                    BreachPolicy::Halt => {
+                        market.phase = TradingPhase::Halted;
+                        let confirmation = market.book.insert_order(request);
+                        market.record_occupancy();
+                        self.send_trading_status(symbol, TradingPhase::Halted);
+                        return self.rest_order(confirmation, link);
                     }
                 }
-                EngineCommand::CancelOrder(request) => {
-                    // TODO: 实现取消订单逻辑
-                    // self.orderbook.remove_order(request.order_id);
-                    println!("收到取消订单请求: {:?}", request);
+            }
+        }
+
+        let (user_id, order_type) = (request.user_id, request.order_type);
+        let (trades, confirmation_opt) = match market.book.match_order(request) {
+            Ok(result) => result,
+            Err(error) => {
+                self.send_reject(user_id, symbol, error.into());
+                return Placement::default();
+            }
+        };
+
+        let taker_order_id = confirmation_opt.as_ref().map(|confirmation| confirmation.order_id).or_else(|| {
+            trades.first().map(|trade| match order_type {
+                OrderType::Buy => trade.buyer_order_id,
+                OrderType::Sell => trade.seller_order_id,
+            })
+        });
+        if let (Some(link), Some(order_id)) = (link, taker_order_id) {
+            self.oco_links.insert(order_id, link);
+        }
+        let placement = Placement {
+            resting: confirmation_opt.as_ref().map(|confirmation| confirmation.order_id),
+            traded: !trades.is_empty(),
+        };
+
+        for trade in trades {
+            self.publish_trade(trade);
+        }
+        self.publish_execution_reports(&symbol);
+
+        if let Some(confirmation) = confirmation_opt {
+            // 如果订单未完全成交，会有一个新挂单
+            // 发送这个新挂单的确认信息
+            self.send_confirmation(confirmation);
+        }
+        self.reclaim_memory(&symbol);
+        // 只统计连续竞价中完成撮合的订单：从进入引擎到回报全部发出
+        symbol_metrics.record_latency(started.elapsed());
+        placement
+    }
+
+    // 不经撮合直接挂单的订单
+    fn rest_order(&mut self, confirmation: OrderConfirmation, link: Option<OcoLink>) -> Placement {
+        if let Some(link) = link {
+            self.oco_links.insert(confirmation.order_id, link);
+        }
+        let placement = Placement { resting: Some(confirmation.order_id), traded: false };
+        self.send_confirmation(confirmation);
+        placement
+    }
+
+    // 下单前的检查，不改变任何状态。与 place_order 的检查一致，
+    // OCO 订单用它在提交任何一条之前确认两条都会被接受
+    fn check_order(&self, request: &NewOrderRequest) -> Result<(), RejectReason> {
+        self.check_replacement(request, 0)
+    }
+
+    // 改单的新订单：released 是将被撤销的原订单的剩余数量，不计入挂单敞口
+    fn check_replacement(&self, request: &NewOrderRequest, released: u64) -> Result<(), RejectReason> {
+        if self.expired.contains(&request.symbol) {
+            return Err(RejectReason::ContractExpired);
+        }
+        if !self.within_position_limit(request.user_id, &request.symbol, request.order_type, request.quantity, released) {
+            return Err(RejectReason::PositionLimitExceeded);
+        }
+        match self.markets.get(&request.symbol) {
+            Some(market) => {
+                market.book.validate(request)?;
+                let band = self.price_bands.get(&request.symbol).filter(|band| band.policy == BreachPolicy::Reject);
+                if let (Some(band), TradingPhase::Continuous) = (band, market.phase) {
+                    let (lower, upper) = band.bounds();
+                    if market.book.would_trade_outside(request, lower, upper) {
+                        return Err(RejectReason::PriceBandBreach);
+                    }
                 }
             }
+            // 尚未建立订单簿的合约使用默认的最小变动价位 1
+            None if request.quantity == 0 => return Err(RejectReason::InvalidQuantity),
+            None if request.price == 0 => return Err(RejectReason::InvalidPrice),
+            None => {}
+        }
+        let collar = self.price_collar.zip(self.mark_prices.get(&request.symbol));
+        if let Some((lower, upper)) = collar.map(|(collar, mark)| collar.bounds(mark.mark_price)) {
+            if !(lower..=upper).contains(&request.price) {
+                return Err(RejectReason::PriceCollarBreach);
+            }
+        }
+        Ok(())
+    }
+
+    // OCO 订单：先检查两条订单，任何一条不会被接受时整体拒绝，两条都不提交。
+    // 随后依次提交：第一条提交时即有成交则第二条不再提交；否则第二条带着指向第一条的关联提交，
+    // 它主动成交时第一条随即被撤销，两条都挂单时互相关联
+    fn process_oco_order(&mut self, request: OcoOrderRequest) {
+        let started = Instant::now();
+        let OcoOrderRequest { link_id, first, second } = request;
+        if first.user_id != second.user_id {
+            self.send_reject(second.user_id, second.symbol, RejectReason::UserMismatch);
+            return;
+        }
+        for order in [&first, &second] {
+            if let Err(reason) = self.check_order(order) {
+                self.send_reject(order.user_id, order.symbol.clone(), reason);
+                return;
+            }
+        }
+
+        let first_symbol = first.symbol.clone();
+        self.set_taker(first.order_type);
+        let placement = self.place_order(first, started, Some(OcoLink { link_id, sibling: None }));
+        let first_id = match placement {
+            Placement { traded: true, .. } => {
+                self.send_reject(second.user_id, second.symbol, RejectReason::LinkedOrderFilled);
+                return;
+            }
+            Placement { resting: Some(order_id), .. } => order_id,
+            // 检查之后不应被拒绝；拒绝回报已经发出，第二条不再提交
+            Placement { resting: None, .. } => return,
+        };
+
+        let second_symbol = second.symbol.clone();
+        self.set_taker(second.order_type);
+        let link = OcoLink { link_id, sibling: Some((first_symbol, first_id)) };
+        let placement = self.place_order(second, started, Some(link));
+        match placement {
+            // 第一条已在发布第二条的执行回报时被撤销
+            Placement { traded: true, .. } => {}
+            Placement { resting: Some(second_id), .. } => {
+                if let Some(link) = self.oco_links.get_mut(&first_id) {
+                    link.sibling = Some((second_symbol, second_id));
+                }
+            }
+            Placement { resting: None, .. } => self.unlink_order(first_id),
+        }
+    }
+
+    // 持仓限额检查，计入用户在该合约上同方向挂单的剩余数量；released 是其中即将撤销的部分
+    fn within_position_limit(&self, user_id: u64, symbol: &str, side: OrderType, quantity: u64, released: u64) -> bool {
+        let (mut buy, mut sell) = self.markets.get(symbol).map_or((0, 0), |market| market.book.open_quantity(user_id));
+        match side {
+            OrderType::Buy => buy = buy.saturating_sub(released),
+            OrderType::Sell => sell = sell.saturating_sub(released),
+        }
+        self.positions.check_order(user_id, symbol, side, quantity, (buy, sell))
+    }
+
+    fn set_taker(&mut self, order_type: OrderType) {
+        if let Some(active) = self.request.as_mut() {
+            active.taker = Some(order_type);
+        }
+    }
+
+    // 订单离开订单簿（撤单、到期）时解除 OCO 关联，另一条订单继续作为普通订单保留
+    fn unlink_order(&mut self, order_id: u64) {
+        let Some(link) = self.oco_links.remove(&order_id) else {
+            return;
+        };
+        if let Some((_, sibling_id)) = link.sibling {
+            if let Some(sibling) = self.oco_links.get_mut(&sibling_id) {
+                sibling.sibling = None;
+            }
+        }
+    }
+
+    // OCO 订单的一条成交后撤销另一条。它可能已经全部成交或被撤销，此时什么也不做
+    fn cancel_linked_order(&mut self, symbol: String, order_id: u64) {
+        self.oco_links.remove(&order_id);
+        let Some(market) = self.markets.get_mut(&symbol) else {
+            return;
+        };
+        let Ok(node) = market.book.cancel_order(order_id) else {
+            return;
+        };
+        market.metrics.cancels.fetch_add(1, Ordering::Relaxed);
+        self.recent_cancels.insert(symbol.clone(), order_id, node.user_id);
+        self.reclaim_memory(&symbol);
+        // 撤销由另一条订单的成交触发，可能发生在其他用户的请求中，不作为请求的应答
+        let ack = CancelAck {
+            user_id: node.user_id,
+            symbol,
+            order_id,
+            cancelled_quantity: node.quantity,
+            status: CancelStatus::LinkedOrderFilled,
+        };
+        if self.output_sender.send(EngineOutput::CancelAck(ack)).is_err() {
+            eprintln!("输出通道已关闭，无法发送撤单回报");
+        }
+    }
+
+    // 价差订单：按两腿的深度拆分为成对的腿订单立即成交，未成交的部分撤销。
+    // 所有检查都在成交之前完成，两腿总是成交相同的数量
+    fn process_spread_order(&mut self, request: SpreadOrderRequest) {
+        match self.plan_spread_order(&request) {
+            Ok((definition, fills)) => self.execute_spread_order(request.user_id, &definition, request.order_type, fills),
+            Err(reason) => self.send_reject(request.user_id, request.spread, reason),
+        }
+    }
+
+    fn plan_spread_order(&self, request: &SpreadOrderRequest) -> Result<(SpreadDefinition, Vec<LegFill>), RejectReason> {
+        if self.expired.contains(&request.spread) {
+            return Err(RejectReason::ContractExpired);
+        }
+        let definition = self.spreads.get(&request.spread).ok_or(RejectReason::UnknownSpread)?;
+        if request.quantity == 0 {
+            return Err(RejectReason::InvalidQuantity);
+        }
+        let (side1, side2) = SpreadDefinition::leg_sides(request.order_type);
+        let legs = [
+            self.leg_liquidity(request.user_id, &definition.leg1, side1, request.quantity)?,
+            self.leg_liquidity(request.user_id, &definition.leg2, side2, request.quantity)?,
+        ];
+        let fills = spread::plan_fills(request.order_type, request.price, request.quantity, &legs[0], &legs[1]);
+        if fills.is_empty() {
+            return Err(RejectReason::NoImpliedLiquidity);
+        }
+        Ok((definition.clone(), fills))
+    }
+
+    // 腿订单可以吃的对手盘档位，按由优到劣排列。先检查到期和持仓限额；
+    // 暂停或集合竞价中的合约不提供流动性，超出价格带或价格保护范围的档位不参与
+    fn leg_liquidity(
+        &self,
+        user_id: u64,
+        symbol: &str,
+        side: OrderType,
+        quantity: u64,
+    ) -> Result<Vec<DepthLevel>, RejectReason> {
+        if self.expired.contains(symbol) {
+            return Err(RejectReason::ContractExpired);
+        }
+        if !self.within_position_limit(user_id, symbol, side, quantity, 0) {
+            return Err(RejectReason::PositionLimitExceeded);
+        }
+        let market = self.markets.get(symbol).filter(|market| market.phase == TradingPhase::Continuous);
+        let Some(market) = market else {
+            return Ok(Vec::new());
+        };
+        let (bids, asks) = market.book.depth(usize::MAX);
+        let levels = match side {
+            OrderType::Buy => asks,
+            OrderType::Sell => bids,
+        };
+        let (lower, upper) = self.leg_bounds(symbol);
+        Ok(levels.into_iter().take_while(|level| (lower..=upper).contains(&level.price)).collect())
+    }
+
+    // 腿订单允许成交的价格区间：涨跌停价格带与标记价格保护范围的交集
+    fn leg_bounds(&self, symbol: &str) -> (u64, u64) {
+        let mut bounds = (0, u64::MAX);
+        let band = self.price_bands.get(symbol).map(PriceBand::bounds);
+        let collar = self.price_collar.zip(self.mark_prices.get(symbol));
+        let collar = collar.map(|(collar, mark)| collar.bounds(mark.mark_price));
+        for (lower, upper) in band.into_iter().chain(collar) {
+            bounds = (bounds.0.max(lower), bounds.1.min(upper));
+        }
+        bounds
+    }
+
+    // 按计划逐笔提交腿订单。每笔腿订单的价格和数量都取自订单簿现有的档位，必定全部成交
+    fn execute_spread_order(
+        &mut self,
+        user_id: u64,
+        definition: &SpreadDefinition,
+        order_type: OrderType,
+        fills: Vec<LegFill>,
+    ) {
+        let (side1, side2) = SpreadDefinition::leg_sides(order_type);
+        for fill in fills {
+            let legs = [(&definition.leg1, side1, fill.leg1_price), (&definition.leg2, side2, fill.leg2_price)];
+            for (symbol, order_type, price) in legs {
+                self.execute_leg(NewOrderRequest {
+                    user_id,
+                    symbol: symbol.clone(),
+                    order_type,
+                    price,
+                    quantity: fill.quantity,
+                });
+            }
+        }
+        for symbol in [&definition.leg1, &definition.leg2] {
+            self.publish_execution_reports(symbol);
+            self.reclaim_memory(symbol);
+        }
+    }
+
+    // 提交一笔腿订单并发布成交。价格和数量已在计划时按订单簿现有档位核对过，必定全部成交；
+    // 万一有剩余也直接撤销并回报 Cancelled，腿订单从不挂单。回报由调用方在全部腿订单提交后一起发布
+    fn execute_leg(&mut self, request: NewOrderRequest) {
+        let (user_id, symbol, quantity) = (request.user_id, request.symbol.clone(), request.quantity);
+        let market = self.markets.get_mut(&symbol).expect("腿订单的合约已在计划时检查");
+        market.metrics.orders.fetch_add(1, Ordering::Relaxed);
+        let trades = match market.book.match_order(request) {
+            Ok((trades, None)) => trades,
+            Ok((trades, Some(residual))) => {
+                let filled = trades.iter().map(|trade| trade.matched_quantity).sum();
+                tracing::warn!(
+                    order_id = residual.order_id,
+                    symbol = %symbol,
+                    filled,
+                    quantity,
+                    "腿订单未全部成交，撤销剩余部分"
+                );
+                if let Err(error) = market.book.cancel_with_report(residual.order_id, filled) {
+                    tracing::error!(order_id = residual.order_id, error = %error, "撤销腿订单剩余部分失败");
+                }
+                trades
+            }
+            Err(error) => {
+                tracing::error!(user_id, symbol = %symbol, error = %error, "腿订单撮合失败");
+                self.send_reject(user_id, symbol, error.into());
+                return;
+            }
+        };
+        for trade in trades {
+            self.publish_trade(trade);
+        }
+    }
+
+    // 篮子订单：所有腿都能在各自限价内全部成交时才逐条提交，否则整单拒绝，任何一条腿都不成交
+    fn process_basket_order(&mut self, request: BasketOrderRequest) {
+        // 预留：逐条核对，任何一条腿不满足即整单拒绝
+        if let Err(reason) = self.reserve_basket(&request) {
+            self.send_basket_reject(&request, reason);
+            return;
+        }
+        // 提交：核对和提交之间订单簿没有变化，每条腿都会全部成交
+        let user_id = request.user_id;
+        let symbols: Vec<String> = request.legs.iter().map(|leg| leg.symbol.clone()).collect();
+        for leg in request.legs {
+            self.execute_leg(NewOrderRequest {
+                user_id,
+                symbol: leg.symbol,
+                order_type: leg.order_type,
+                price: leg.price,
+                quantity: leg.quantity,
+            });
+        }
+        for symbol in &symbols {
+            self.publish_execution_reports(symbol);
+            self.reclaim_memory(symbol);
+        }
+    }
+
+    fn reserve_basket(&self, request: &BasketOrderRequest) -> Result<(), RejectReason> {
+        basket::check_legs(request)?;
+        for leg in &request.legs {
+            let order = NewOrderRequest {
+                user_id: request.user_id,
+                symbol: leg.symbol.clone(),
+                order_type: leg.order_type,
+                price: leg.price,
+                quantity: leg.quantity,
+            };
+            if let Some(market) = self.markets.get(&leg.symbol) {
+                market.book.validate(&order).map_err(RejectReason::from)?;
+            }
+            let levels = self.leg_liquidity(request.user_id, &leg.symbol, leg.order_type, leg.quantity)?;
+            if basket::fillable_within(leg.order_type, leg.price, &levels) < leg.quantity {
+                return Err(RejectReason::BasketNotFillable);
+            }
+        }
+        Ok(())
+    }
+
+    // 篮子订单的拒绝回报以第一条腿的合约发送
+    fn send_basket_reject(&self, request: &BasketOrderRequest, reason: RejectReason) {
+        let symbol = request.legs.first().map(|leg| leg.symbol.clone()).unwrap_or_default();
+        self.send_reject(request.user_id, symbol, reason);
+    }
+
+    // 重新计算各价差合约的隐含报价，只广播发生变化的
+    fn publish_implied_quotes(&mut self) {
+        let empty = (Vec::new(), Vec::new());
+        for (symbol, definition) in &self.spreads {
+            let top = |leg: &str| self.markets.get(leg).map_or_else(|| empty.clone(), |market| market.book.depth(1));
+            let (leg1, leg2) = (top(&definition.leg1), top(&definition.leg2));
+            let quote = spread::implied_quote(symbol, (&leg1.0, &leg1.1), (&leg2.0, &leg2.1));
+            if self.implied_quotes.get(symbol) == Some(&quote) {
+                continue;
+            }
+            self.implied_quotes.insert(symbol.clone(), quote.clone());
+            if self.output_sender.send(EngineOutput::ImpliedQuote(quote)).is_err() {
+                eprintln!("输出通道已关闭，无法发送隐含报价");
+            }
         }
-        println!("撮合引擎关闭。");
     }
-}
\ No newline at end of file
+
+    fn process_cancel_order(&mut self, request: CancelOrderRequest) {
+        // 只能撤销自己的订单。先于重复撤单检查，其他用户撤销已撤订单与撤销挂单得到相同的回报
+        let recent_owner = self.recent_cancels.owner(&request.symbol, request.order_id);
+        let owner = recent_owner.or_else(|| {
+            self.markets.get(&request.symbol).and_then(|market| market.book.order(request.order_id)).map(|node| node.user_id)
+        });
+        if owner.is_some_and(|owner| owner != request.user_id) {
+            self.send_cancel_ack(request, 0, CancelStatus::NotOwner);
+            return;
+        }
+        // 重复撤单直接返回幂等回报，不再访问订单簿
+        if recent_owner.is_some() {
+            self.send_cancel_ack(request, 0, CancelStatus::AlreadyCancelled);
+            return;
+        }
+
+        let cancelled = self
+            .market_mut(&request.symbol)
+            .and_then(|market| market.book.cancel_order(request.order_id));
+        match cancelled {
+            Ok(node) => {
+                self.unlink_order(request.order_id);
+                self.recent_cancels.insert(request.symbol.clone(), request.order_id, node.user_id);
+                self.reclaim_memory(&request.symbol);
+                self.send_cancel_ack(request, node.quantity, CancelStatus::Cancelled);
+            }
+            Err(_) => self.send_cancel_ack(request, 0, CancelStatus::UnknownOrder),
+        }
+    }
+
+    fn cancel_user_orders(&mut self, user_id: u64) {
+        let mut symbols: Vec<String> = self.markets.keys().cloned().collect();
+        symbols.sort();
+        for symbol in symbols {
+            for order_id in self.markets[&symbol].book.user_orders(user_id) {
+                self.process_cancel_order(CancelOrderRequest { user_id, symbol: symbol.clone(), order_id });
+            }
+        }
+    }
+
+    // 改单：只减少数量且价格不变时原地修改并保留时间优先级，
+    // 否则视为撤单后重新下单，失去原有的时间优先级
+    fn process_amend_order(&mut self, request: AmendOrderRequest) {
+        // 数量改为 0 等同于撤单
+        if request.new_quantity == 0 {
+            self.process_cancel_order(CancelOrderRequest {
+                user_id: request.user_id,
+                symbol: request.symbol,
+                order_id: request.order_id,
+            });
+            return;
+        }
+        let (user_id, symbol) = (request.user_id, request.symbol.clone());
+        if let Err(reason) = self.amend_order(request) {
+            self.send_reject(user_id, symbol, reason);
+        }
+    }
+
+    // 撤单重新下单前先做新订单的全部检查，任何一项不通过时拒绝改单，原订单保持不变。
+    // 新订单继承原订单的 GTD 到期时间、只减仓属性和 OCO 关联
+    fn amend_order(&mut self, request: AmendOrderRequest) -> Result<(), RejectReason> {
+        let market = self.market_mut(&request.symbol)?;
+        let order = market.book.order(request.order_id).ok_or(EngineError::OrderNotFound(request.order_id))?;
+        let (price, quantity, user_id, order_type) = (order.price, order.quantity, order.user_id, order.order_type);
+        if user_id != request.user_id {
+            return Err(EngineError::NotOrderOwner(request.order_id).into());
+        }
+
+        if request.new_price == price && request.new_quantity <= quantity {
+            market.book.reduce_order(request.order_id, request.new_quantity)?;
+            self.send_confirmation(OrderConfirmation { order_id: request.order_id, user_id });
+            return Ok(());
+        }
+
+        let started = Instant::now();
+        self.set_taker(order_type);
+        let mut replacement = NewOrderRequest {
+            user_id,
+            symbol: request.symbol,
+            order_type,
+            price: request.new_price,
+            quantity: request.new_quantity,
+        };
+        let key = (user_id, replacement.symbol.clone());
+        let reduce_only = self.reduce_only.get(&key).is_some_and(|order_ids| order_ids.contains(&request.order_id));
+        if reduce_only {
+            // 原订单撤销后腾出它占用的数量
+            let room = self.reduce_only_room(&key, order_type) + quantity;
+            replacement.quantity = replacement.quantity.min(room);
+        }
+        self.check_replacement(&replacement, quantity)?;
+
+        let node = self.market_mut(&replacement.symbol)?.book.cancel_order(request.order_id)?;
+        let link = self.oco_links.remove(&request.order_id);
+        let expire_at_ms = self.good_till.remove(&request.order_id);
+        if let Some(order_ids) = self.reduce_only.get_mut(&key) {
+            order_ids.retain(|&order_id| order_id != request.order_id);
+        }
+        let sibling = link.as_ref().and_then(|link| link.sibling.clone());
+        let placement = self.place_order(replacement, started, link);
+        let new_id = placement.resting;
+        // 新订单仍在订单簿中时沿用原订单的属性；另一条 OCO 订单改为指向新订单，新订单已成交或被拒绝时解除
+        if let Some((_, sibling_id)) = sibling {
+            if let Some(sibling) = self.oco_links.get_mut(&sibling_id) {
+                if sibling.sibling.is_some() {
+                    sibling.sibling = new_id.map(|new_id| (key.1.clone(), new_id));
+                }
+            }
+        }
+        if let Some(new_id) = new_id {
+            if let Some(expire_at_ms) = expire_at_ms {
+                self.good_till_date.insert(expire_at_ms, (key.1.clone(), new_id));
+                self.good_till.insert(new_id, expire_at_ms);
+            }
+            if reduce_only {
+                self.reduce_only.entry(key.clone()).or_default().push(new_id);
+            }
+        }
+        // 检查之后不应被拒绝；万一被拒绝，原订单已经撤销，明确告知
+        if !placement.traded && new_id.is_none() {
+            let request = CancelOrderRequest { user_id, symbol: key.1, order_id: request.order_id };
+            self.send_cancel_ack(request, node.quantity, CancelStatus::Cancelled);
+        }
+        Ok(())
+    }
+
+    // 按回收策略检查并压缩合约订单簿的节点池，并更新节点池占用的指标
+    fn reclaim_memory(&mut self, symbol: &str) {
+        let Some(market) = self.markets.get_mut(symbol) else {
+            return;
+        };
+        if self.reclaim_policy.should_compact(&market.book) {
+            let reclaimed = market.book.compact();
+            self.metrics.pool_compactions.fetch_add(1, Ordering::Relaxed);
+            self.metrics.pool_slots_reclaimed.fetch_add(reclaimed as u64, Ordering::Relaxed);
+        }
+        market.record_occupancy();
+    }
+
+    // 取得合约的市场状态，不存在时以共享的订单号生成器和定序组件创建；合约名驻留池已满时返回 None
+    fn market_entry(&mut self, symbol: &str) -> Option<&mut Market> {
+        if !self.markets.contains_key(symbol) {
+            let symbol_id = self.symbols.intern(symbol);
+            self.record_symbol_pool();
+            let market = self.new_market(symbol, symbol_id?);
+            self.markets.insert(symbol.to_string(), market);
+        }
+        self.markets.get_mut(symbol)
+    }
+
+    // 启动配置中的合约必须能够创建
+    fn configured_market(&mut self, symbol: &str) -> &mut Market {
+        self.market_entry(symbol).expect("配置的合约数超过合约名驻留池的上限")
+    }
+
+    fn new_market(&self, symbol: &str, symbol_id: SymbolId) -> Market {
+        let mut market = Market {
+            meter: self.surveillance.meter(symbol),
+            metrics: self.metrics.symbol(symbol),
+            symbol_id,
+            ..Market::default()
+        };
+        market.book.set_symbol(self.symbols.name(symbol_id).expect("刚驻留的合约").clone());
+        market.book.set_id_generator(self.order_ids.clone());
+        market.book.set_sequencer(self.sequencer.clone());
+        market.book.enable_execution_reports();
+        if self.l3_feed.is_some() {
+            market.book.enable_book_events();
+        }
+        market
+    }
+
+    // 依次发出各合约订单簿在本条命令中的逐笔变化
+    fn flush_book_events(&mut self) {
+        let mut events = Vec::new();
+        for (symbol, market) in self.markets.iter_mut() {
+            events.extend(market.book.take_book_events().into_iter().map(|event| (symbol.clone(), event)));
+        }
+        for (symbol, event) in events {
+            self.send_l3(L3Message::Event { seq: 0, symbol, event });
+        }
+    }
+
+    fn send_l3_checksums(&mut self) {
+        let mut checksums: Vec<(String, u64)> =
+            self.markets.iter().map(|(symbol, market)| (symbol.clone(), market.book.checksum())).collect();
+        checksums.sort();
+        for (symbol, checksum) in checksums {
+            self.send_l3(L3Message::Checksum { seq: 0, symbol, checksum });
+        }
+    }
+
+    // 按发送顺序分配序号；副本线程退出后不再发送
+    fn send_l3(&mut self, mut message: L3Message) {
+        let Some((feed, _)) = &self.l3_feed else { return };
+        self.l3_seq += 1;
+        match &mut message {
+            L3Message::Event { seq, .. } | L3Message::Checksum { seq, .. } => *seq = self.l3_seq,
+        }
+        if feed.send(message).is_err() {
+            self.l3_feed = None;
+        }
+    }
+
+    // 将合约名驻留池的统计同步到指标
+    fn record_symbol_pool(&self) {
+        let stats = self.symbols.stats();
+        self.metrics.symbol_pool_size.store(stats.size as u64, Ordering::Relaxed);
+        self.metrics.symbol_pool_hits.store(stats.hits, Ordering::Relaxed);
+        self.metrics.symbol_pool_misses.store(stats.misses, Ordering::Relaxed);
+        self.metrics.symbol_pool_evictions.store(stats.evictions, Ordering::Relaxed);
+    }
+
+    fn market_mut(&mut self, symbol: &str) -> Result<&mut Market, EngineError> {
+        self.markets.get_mut(symbol).ok_or_else(|| EngineError::SymbolUnknown(symbol.to_string()))
+    }
+
+    fn process_control(&mut self, control: ControlCommand) {
+        match control {
+            ControlCommand::SetPriceBand(symbol, band) => {
+                self.price_bands.insert(symbol, band);
+            }
+            ControlCommand::Halt(symbol) => self.halt(symbol),
+            ControlCommand::Resume(symbol) => self.resume(symbol),
+            ControlCommand::StartAuction(symbol) => self.start_auction(symbol),
+            ControlCommand::EndAuction(symbol) => self.end_auction(symbol),
+            ControlCommand::UpdateMarkPrice { symbol, mark_price, index_price } => {
+                self.update_mark_price(symbol, mark_price, index_price)
+            }
+            ControlCommand::Expire(symbol) => self.expire(symbol),
+            ControlCommand::AdvanceTime(now_ms) => self.advance_time(now_ms),
+            // 关闭接收端后，发送方无法再提交命令，主循环在取完剩余命令后结束
+            ControlCommand::Drain => self.command_receiver.close(),
+            ControlCommand::StatsRequest(reply) => {
+                let _ = reply.send(self.stats());
+            }
+            ControlCommand::EndOfDay(reply) => {
+                let _ = reply.send(std::mem::take(&mut self.trade_log));
+                self.market_data.reset_daily();
+            }
+            ControlCommand::SetFeature { feature, symbol, enabled } => {
+                self.feature_flags.set(feature, symbol.as_deref(), enabled);
+                self.apply_feature_flags();
+            }
+            ControlCommand::AddSurveillanceRule(rule) => self.surveillance.add_rule(rule),
+            ControlCommand::RemoveSurveillanceRule(name) => self.surveillance.remove_rule(&name),
+            ControlCommand::ShrinkSymbolPool(reply) => {
+                let evicted = self.symbols.shrink();
+                self.record_symbol_pool();
+                let _ = reply.send(evicted);
+            }
+            ControlCommand::Checkpoint(reply) => {
+                let _ = reply.send(self.checkpoint());
+            }
+        }
+    }
+
+    // 当前的恢复快照，序号为最后一条已写入日志的命令
+    pub fn checkpoint(&self) -> Snapshot {
+        let mut symbols: Vec<&String> = self.markets.keys().collect();
+        symbols.sort_unstable();
+        let mut orders = Vec::new();
+        let mut markets = Vec::new();
+        for symbol in symbols {
+            let market = &self.markets[symbol];
+            markets.push(SnapshotMarket {
+                symbol: symbol.clone(),
+                phase: market.phase,
+                last_trade_price: market.last_trade_price,
+            });
+            let book = &market.book;
+            for order_id in book.order_ids() {
+                let node = book.order(order_id).expect("order_ids 中的订单");
+                orders.push(SnapshotOrder {
+                    symbol: symbol.clone(),
+                    order_id,
+                    user_id: node.user_id,
+                    order_type: node.order_type,
+                    price: node.price,
+                    quantity: node.quantity,
+                    filled_quantity: node.filled_quantity,
+                });
+            }
+        }
+        let positions = self
+            .positions
+            .positions()
+            .into_iter()
+            .map(|(user_id, symbol, position)| SnapshotPosition { user_id, symbol, position })
+            .collect();
+        let mut price_bands: Vec<_> = self.price_bands.iter().map(|(symbol, band)| (symbol.clone(), *band)).collect();
+        price_bands.sort_unstable_by(|a, b| a.0.cmp(&b.0));
+        let mut mark_prices: Vec<_> = self.mark_prices.values().cloned().collect();
+        mark_prices.sort_unstable_by(|a, b| a.symbol.cmp(&b.symbol));
+        let mut expired: Vec<_> = self.expired.iter().cloned().collect();
+        expired.sort_unstable();
+        // 定时器惰性删除，只保存对应订单仍在订单簿中的
+        let mut good_till_date: Vec<_> = self
+            .good_till_date
+            .pending()
+            .filter(|(_, (symbol, order_id))| {
+                self.markets.get(symbol).is_some_and(|market| market.book.order(*order_id).is_some())
+            })
+            .map(|(expire_at_ms, (symbol, order_id))| SnapshotTimer {
+                expire_at_ms,
+                symbol: symbol.clone(),
+                order_id: *order_id,
+            })
+            .collect();
+        good_till_date.sort_unstable_by_key(|timer| (timer.expire_at_ms, timer.order_id));
+        let mut oco_links: Vec<_> = self
+            .oco_links
+            .iter()
+            .map(|(&order_id, link)| SnapshotOcoLink { order_id, link_id: link.link_id, sibling: link.sibling.clone() })
+            .collect();
+        oco_links.sort_unstable_by_key(|link| link.order_id);
+        let mut reduce_only: Vec<_> = self
+            .reduce_only
+            .iter()
+            .map(|((user_id, symbol), order_ids)| SnapshotReduceOnly {
+                user_id: *user_id,
+                symbol: symbol.clone(),
+                order_ids: order_ids.clone(),
+            })
+            .collect();
+        reduce_only.sort_unstable_by(|a, b| (a.user_id, &a.symbol).cmp(&(b.user_id, &b.symbol)));
+        Snapshot {
+            seq: self.command_log.as_ref().map_or(0, CommandLog::last_seq),
+            id_state: self.order_ids.checkpoint(),
+            trade_id_state: self.sequencer.checkpoint(),
+            orders,
+            positions,
+            markets,
+            price_bands,
+            mark_prices,
+            expired,
+            clock_ms: self.good_till_date.now(),
+            good_till_date,
+            oco_links,
+            reduce_only,
+            feature_flags: self.feature_flags.overrides(),
+            trade_log: self.trade_log.clone(),
+        }
+    }
+
+    // 用快照替换引擎中的挂单和持仓，恢复交易阶段、价格带、OCO、GTD、只减仓等订单簿之外的状态
+    // 和 ID 生成器状态。行情汇总和当日成交不在快照中，从快照之后重新累积
+    pub fn restore(&mut self, snapshot: &Snapshot) {
+        for market in self.markets.values_mut() {
+            for order_id in market.book.order_ids() {
+                let _ = market.book.cancel_order(order_id);
+            }
+        }
+        for (user_id, symbol, _) in self.positions.positions() {
+            self.positions.set_position(user_id, &symbol, 0);
+        }
+        for order in &snapshot.orders {
+            let market = self.configured_market(&order.symbol);
+            let request = NewOrderRequest {
+                user_id: order.user_id,
+                symbol: order.symbol.clone(),
+                order_type: order.order_type,
+                price: order.price,
+                quantity: order.quantity,
+            };
+            market.book.restore_order(request, order.order_id, order.filled_quantity);
+            market.record_occupancy();
+        }
+        for position in &snapshot.positions {
+            self.positions.set_position(position.user_id, &position.symbol, position.position);
+        }
+        for saved in &snapshot.markets {
+            let market = self.configured_market(&saved.symbol);
+            market.phase = saved.phase;
+            market.last_trade_price = saved.last_trade_price;
+        }
+        for (symbol, band) in &snapshot.price_bands {
+            self.price_bands.insert(symbol.clone(), *band);
+        }
+        for mark_price in &snapshot.mark_prices {
+            self.mark_prices.insert(mark_price.symbol.clone(), mark_price.clone());
+        }
+        for symbol in &snapshot.expired {
+            self.markets.remove(symbol);
+            self.expired.insert(symbol.clone());
+        }
+        self.good_till_date = TimerWheel::new(snapshot.clock_ms);
+        self.good_till.clear();
+        for timer in &snapshot.good_till_date {
+            self.good_till_date.insert(timer.expire_at_ms, (timer.symbol.clone(), timer.order_id));
+            self.good_till.insert(timer.order_id, timer.expire_at_ms);
+        }
+        self.oco_links = snapshot
+            .oco_links
+            .iter()
+            .map(|link| (link.order_id, OcoLink { link_id: link.link_id, sibling: link.sibling.clone() }))
+            .collect();
+        self.reduce_only = snapshot
+            .reduce_only
+            .iter()
+            .map(|entry| ((entry.user_id, entry.symbol.clone()), entry.order_ids.clone()))
+            .collect();
+        for (symbol, feature, enabled) in &snapshot.feature_flags {
+            self.feature_flags.set(*feature, symbol.as_deref(), *enabled);
+        }
+        self.apply_feature_flags();
+        self.order_ids.restore(snapshot.id_state);
+        self.sequencer.restore(snapshot.trade_id_state);
+        self.trade_log = snapshot.trade_log.clone();
+    }
+
+    // 按功能开关的当前状态调整各合约实际使用的撮合行为
+    fn apply_feature_flags(&mut self) {
+        for (symbol, market) in &mut self.markets {
+            let policy = if self.feature_flags.is_enabled(Feature::ProRataAllocation, symbol) {
+                market.allocation_policy
+            } else {
+                AllocationPolicy::Fifo
+            };
+            market.book.set_allocation_policy(policy);
+        }
+    }
+
+    pub fn stats(&self) -> EngineStats {
+        let snapshot = self.metrics.snapshot();
+        let markets = self
+            .markets
+            .iter()
+            .map(|(symbol, market)| MarketStats {
+                symbol: symbol.clone(),
+                symbol_id: market.symbol_id,
+                phase: market.phase,
+                resting_orders: market.book.order_count(),
+                best_bid: market.book.best_bid(),
+                best_ask: market.book.best_ask(),
+            })
+            .collect();
+        EngineStats {
+            orders_accepted: snapshot.orders_accepted,
+            orders_throttled: snapshot.orders_throttled,
+            trades_executed: snapshot.trades_executed,
+            markets,
+            symbol_pool: self.symbols.stats(),
+        }
+    }
+
+    fn halt(&mut self, symbol: String) {
+        if self.expired.contains(&symbol) {
+            return;
+        }
+        let Some(market) = self.market_entry(&symbol) else {
+            return;
+        };
+        if market.phase == TradingPhase::Halted {
+            return;
+        }
+        market.phase = TradingPhase::Halted;
+        self.send_trading_status(symbol, TradingPhase::Halted);
+    }
+
+    // 恢复合约撮合：先消除暂停期间形成的交叉，再恢复连续交易。配置了价格带时成交价限制在价格带之内，
+    // 只能在价格带之外成交的挂单被撤销
+    fn resume(&mut self, symbol: String) {
+        let bounds = self.price_bands.get(&symbol).map_or((0, u64::MAX), PriceBand::bounds);
+        let Some(market) = self.markets.get_mut(&symbol) else {
+            return;
+        };
+        if market.phase != TradingPhase::Halted {
+            return;
+        }
+        market.phase = TradingPhase::Continuous;
+        let (trades, cancelled) = market.book.uncross(&symbol, bounds);
+        self.send_trading_status(symbol.clone(), TradingPhase::Continuous);
+        for node in cancelled {
+            self.unlink_order(node.order_id);
+            self.recent_cancels.insert(symbol.clone(), node.order_id, node.user_id);
+            let request = CancelOrderRequest { user_id: node.user_id, symbol: symbol.clone(), order_id: node.order_id };
+            self.send_cancel_ack(request, node.quantity, CancelStatus::PriceBandBreach);
+        }
+        for trade in trades {
+            self.publish_trade(trade);
+        }
+        self.publish_execution_reports(&symbol);
+        self.reclaim_memory(&symbol);
+    }
+
+    // 只有连续竞价中的合约可以进入集合竞价；暂停的合约必须先恢复，已在竞价中的不重复通知
+    fn start_auction(&mut self, symbol: String) {
+        if self.expired.contains(&symbol) {
+            return;
+        }
+        let Some(market) = self.market_entry(&symbol) else {
+            return;
+        };
+        if market.phase != TradingPhase::Continuous {
+            return;
+        }
+        market.phase = TradingPhase::Auction;
+        self.send_trading_status(symbol, TradingPhase::Auction);
+    }
+
+    // 集合竞价结束：计算使成交量最大的均衡价格，所有可成交订单以该价格一次性撮合
+    fn end_auction(&mut self, symbol: String) {
+        let Some(market) = self.markets.get_mut(&symbol) else {
+            return;
+        };
+        if market.phase != TradingPhase::Auction {
+            return;
+        }
+        let (bids, asks) = market.book.depth(usize::MAX);
+        let trades = match auction::equilibrium_price(&bids, &asks, market.last_trade_price) {
+            Some(result) => market.book.auction_cross(&symbol, result.price),
+            None => Vec::new(),
+        };
+        market.phase = TradingPhase::Continuous;
+        self.send_trading_status(symbol.clone(), TradingPhase::Continuous);
+        for trade in trades {
+            self.publish_trade(trade);
+        }
+        self.publish_execution_reports(&symbol);
+        self.reclaim_memory(&symbol);
+    }
+
+    fn update_mark_price(&mut self, symbol: String, mark_price: u64, index_price: u64) {
+        if self.expired.contains(&symbol) {
+            return;
+        }
+        let price = MarkPrice { symbol, mark_price, index_price, timestamp: self.sequencer.next_timestamp() };
+        self.mark_prices.insert(price.symbol.clone(), price.clone());
+        if self.output_sender.send(EngineOutput::MarkPrice(price)).is_err() {
+            eprintln!("输出通道已关闭，无法发送标记价格");
+        }
+    }
+
+    // 合约到期：先暂停撮合，再逐笔撤销挂单并通知挂单用户，最后移除订单簿和价格带
+    fn expire(&mut self, symbol: String) {
+        if self.expired.contains(&symbol) {
+            return;
+        }
+        self.halt(symbol.clone());
+        let order_ids = self.markets.get(&symbol).map(|market| market.book.order_ids()).unwrap_or_default();
+        for order_id in order_ids {
+            let Ok(node) = self.markets.get_mut(&symbol).unwrap().book.cancel_order(order_id) else {
+                continue;
+            };
+            self.unlink_order(order_id);
+            let request = CancelOrderRequest { user_id: node.user_id, symbol: symbol.clone(), order_id };
+            self.send_cancel_ack(request, node.quantity, CancelStatus::Expired);
+        }
+        // 合约移除前发出撤单的逐笔变化
+        if self.l3_feed.is_some() {
+            self.flush_book_events();
+        }
+        if let Some(market) = self.markets.remove(&symbol) {
+            market.metrics.pool_slots.store(0, Ordering::Relaxed);
+            market.metrics.resting_orders.store(0, Ordering::Relaxed);
+            market.metrics.memory_bytes.store(0, Ordering::Relaxed);
+        }
+        self.price_bands.remove(&symbol);
+        self.mark_prices.remove(&symbol);
+        self.spreads.remove(&symbol);
+        self.implied_quotes.remove(&symbol);
+        self.expired.insert(symbol.clone());
+        self.send_trading_status(symbol, TradingPhase::Expired);
+    }
+
+    fn snapshot_depth(&mut self, depth: usize, largest_orders: usize, reply: std_mpsc::Sender<DepthSnapshot>) {
+        let timestamp = self.sequencer.next_timestamp();
+        for (symbol, market) in self.markets.iter_mut() {
+            if reply.send(Self::depth_snapshot(symbol, market, depth, largest_orders, timestamp)).is_err() {
+                // 请求方已经不再等待
+                return;
+            }
+        }
+    }
+
+    fn depth_snapshot(symbol: &str, market: &mut Market, depth: usize, largest_orders: usize, timestamp: u64) -> DepthSnapshot {
+        let (bids, asks, checksum) = market.book.depth_with_checksum(depth);
+        let (bid_levels, ask_levels) = market.book.level_counts();
+        DepthSnapshot {
+            symbol: symbol.to_string(),
+            timestamp,
+            best_bid: market.book.best_bid(),
+            best_ask: market.book.best_ask(),
+            bids,
+            asks,
+            resting_orders: market.book.order_count() as u64,
+            bid_levels: bid_levels as u64,
+            ask_levels: ask_levels as u64,
+            largest_orders: market.book.largest_orders(largest_orders),
+            checksum,
+        }
+    }
+
+    // 在两条命令之间为所有合约生成深度并整体发布
+    fn publish_depth_view(&mut self) {
+        let Some((config, view)) = &self.depth_view else { return };
+        // 不推进时钟，开启视图不改变逻辑时钟下的成交时间戳
+        let timestamp = self.sequencer.last_timestamp();
+        let books = self
+            .markets
+            .iter_mut()
+            .map(|(symbol, market)| (symbol.clone(), Self::depth_snapshot(symbol, market, config.depth, 0, timestamp)))
+            .collect();
+        let version = view.load().version + 1;
+        view.publish(BookView { version, commands: self.commands_handled, books });
+        self.depth_view_published_at = self.commands_handled;
+    }
+
+    // 登记一笔大宗交易：不与订单簿交互，校验通过后直接作为成交发布
+    fn process_block_trade(&mut self, request: BlockTradeRequest) {
+        if let Err(reason) = self.validate_block_trade(&request) {
+            self.send_reject(request.buyer_user_id, request.symbol, reason);
+            return;
+        }
+
+        let symbol = self.symbols.intern_name(&request.symbol);
+        self.record_symbol_pool();
+        let Some(symbol) = symbol else {
+            self.send_reject(request.buyer_user_id, request.symbol, RejectReason::SymbolLimitReached);
+            return;
+        };
+        let (trade_id, timestamp) = self.sequencer.next_trade();
+        self.publish_trade(TradeNotification {
+            trade_id,
+            symbol,
+            matched_price: request.price,
+            matched_quantity: request.quantity,
+            buyer_user_id: request.buyer_user_id,
+            buyer_order_id: 0, // 大宗交易不对应订单簿中的订单
+            seller_user_id: request.seller_user_id,
+            seller_order_id: 0,
+            timestamp,
+            is_block_trade: true,
+        });
+    }
+
+    fn validate_block_trade(&self, request: &BlockTradeRequest) -> Result<(), RejectReason> {
+        if self.expired.contains(&request.symbol) {
+            return Err(RejectReason::ContractExpired);
+        }
+        if request.quantity < self.block_trade_rules.min_quantity {
+            return Err(RejectReason::BlockTradeTooSmall);
+        }
+        if request.price == 0 {
+            return Err(RejectReason::BlockTradePriceOutOfRange);
+        }
+        let buyer_ok = self.within_position_limit(request.buyer_user_id, &request.symbol, OrderType::Buy, request.quantity, 0);
+        let seller_ok =
+            self.within_position_limit(request.seller_user_id, &request.symbol, OrderType::Sell, request.quantity, 0);
+        if !(buyer_ok && seller_ok) {
+            return Err(RejectReason::PositionLimitExceeded);
+        }
+        // 没有参考价时（尚无成交）只做基本校验
+        let last_trade_price = self.markets.get(&request.symbol).and_then(|market| market.last_trade_price);
+        if let Some(reference) = last_trade_price {
+            let max_deviation = reference as u128 * self.block_trade_rules.max_price_deviation_bps as u128 / 10_000;
+            if request.price.abs_diff(reference) as u128 > max_deviation {
+                return Err(RejectReason::BlockTradePriceOutOfRange);
+            }
+        }
+        Ok(())
+    }
+
+    fn process_position_query(&self, query: PositionQuery) {
+        let report = PositionReport {
+            net_position: self.positions.position(query.user_id, &query.symbol),
+            user_id: query.user_id,
+            symbol: query.symbol,
+        };
+        if self.output_sender.send(self.respond(EngineOutput::Position(report))).is_err() {
+            eprintln!("输出通道已关闭，无法发送持仓查询结果");
+        }
+    }
+
+    // 按当前订单簿估算成交均价和最差价格，供前端下单界面展示
+    fn process_fill_estimate(&self, request: FillEstimateRequest) {
+        let (fillable_quantity, notional, worst_price) = match self.markets.get(&request.symbol) {
+            Some(market) => market.book.estimate_fill(request.order_type, request.quantity),
+            None => (0, 0, None),
+        };
+        let estimate = FillEstimate {
+            user_id: request.user_id,
+            symbol: request.symbol,
+            order_type: request.order_type,
+            requested_quantity: request.quantity,
+            fillable_quantity,
+            average_price: (fillable_quantity > 0).then(|| notional as f64 / fillable_quantity as f64),
+            worst_price,
+        };
+        if self.output_sender.send(self.respond(EngineOutput::FillEstimate(estimate))).is_err() {
+            eprintln!("输出通道已关闭，无法发送预估成交结果");
+        }
+    }
+
+    fn process_market_data_query(&self, query: MarketDataQuery) {
+        let candles = self.market_data.recent_candles(
+            &query.symbol,
+            Duration::from_millis(query.candle_interval_ms),
+            query.candle_limit as usize,
+        );
+        let data = self.market_data.symbol(&query.symbol);
+        let snapshot = MarketDataSnapshot {
+            user_id: query.user_id,
+            last_price: data.and_then(|data| data.last_price),
+            high: data.and_then(|data| data.high),
+            low: data.and_then(|data| data.low),
+            volume: data.map_or(0, |data| data.volume),
+            trade_count: data.map_or(0, |data| data.trade_count),
+            turnover: data.map_or(0, |data| data.turnover),
+            vwap: data.and_then(|data| data.vwap()),
+            symbol: query.symbol,
+            candles,
+        };
+        if self.output_sender.send(self.respond(EngineOutput::MarketData(snapshot))).is_err() {
+            eprintln!("输出通道已关闭，无法发送行情查询结果");
+        }
+    }
+
+    fn send_confirmation(&self, confirmation: OrderConfirmation) {
+        if self.output_sender.send(self.respond(EngineOutput::Confirmation(confirmation))).is_err() {
+            eprintln!("输出通道已关闭，无法发送订单确认");
+        }
+    }
+
+    fn send_cancel_ack(&self, request: CancelOrderRequest, cancelled_quantity: u64, status: CancelStatus) {
+        if let (CancelStatus::Cancelled, Some(market)) = (status, self.markets.get(&request.symbol)) {
+            market.metrics.cancels.fetch_add(1, Ordering::Relaxed);
+        }
+        let ack = CancelAck {
+            user_id: request.user_id,
+            symbol: request.symbol,
+            order_id: request.order_id,
+            cancelled_quantity,
+            status,
+        };
+        if self.output_sender.send(self.respond(EngineOutput::CancelAck(ack))).is_err() {
+            eprintln!("输出通道已关闭，无法发送撤单回报");
+        }
+    }
+
+    fn send_trading_status(&self, symbol: String, phase: TradingPhase) {
+        let status = TradingStatus { symbol, phase };
+        if self.output_sender.send(EngineOutput::TradingStatus(status)).is_err() {
+            eprintln!("输出通道已关闭，无法发送交易状态");
+        }
+    }
+
+    fn send_reject(&self, user_id: u64, symbol: String, reason: RejectReason) {
+        // 不为未知合约登记指标，避免客户端随意填写的合约名让指标无限增长
+        if let Some(market) = self.markets.get(&symbol) {
+            market.metrics.rejects.fetch_add(1, Ordering::Relaxed);
+        }
+        let reject = OrderReject { user_id, symbol, reason };
+        if self.output_sender.send(self.respond(EngineOutput::Reject(reject))).is_err() {
+            eprintln!("输出通道已关闭，无法发送拒绝回报");
+        }
+    }
+
+    // 发布订单簿为最近一次撮合生成的执行回报
+    fn publish_execution_reports(&mut self, symbol: &str) {
+        let Some(market) = self.markets.get_mut(symbol) else {
+            return;
+        };
+        let taker = self.request.and_then(|active| active.taker);
+        let received_at_ns = self.request.and_then(|active| active.received_at_ns);
+        let mut linked_orders = Vec::new();
+        for mut report in market.book.take_execution_reports() {
+            if let Some(link) = self.oco_links.get_mut(&report.order_id) {
+                report.link_id = Some(link.link_id);
+                // OCO 订单的任何一笔成交都撤销另一条订单
+                linked_orders.extend(link.sibling.take());
+                if report.leaves_quantity == 0 {
+                    self.oco_links.remove(&report.order_id);
+                }
+            }
+            let output = if taker == Some(report.order_type) {
+                report.received_at_ns = received_at_ns;
+                self.respond(EngineOutput::ExecutionReport(report))
+            } else {
+                EngineOutput::ExecutionReport(report)
+            };
+            if self.output_sender.send(output).is_err() {
+                eprintln!("输出通道已关闭，无法发送执行回报");
+            }
+        }
+        for (symbol, order_id) in linked_orders {
+            self.cancel_linked_order(symbol, order_id);
+        }
+    }
+
+    // 正在处理带请求 ID 的命令时，把发给请求方的应答包装起来回显该 ID；
+    // 命令来自未登录的连接时包装为只发给该连接的应答
+    fn respond(&self, output: EngineOutput) -> EngineOutput {
+        let Some(active) = self.request else {
+            return output;
+        };
+        match (active.request_id, active.connection_id) {
+            (Some((request_id, connection_id)), _) => {
+                EngineOutput::Response { request_id, connection_id, output: Box::new(output) }
+            }
+            (None, Some(connection_id)) => EngineOutput::Direct { connection_id, output: Box::new(output) },
+            (None, None) => output,
+        }
+    }
+
+    // 发布一笔已由定序组件分配了成交号和时间戳的成交
+    fn publish_trade(&mut self, trade: TradeNotification) {
+        self.metrics.trades_executed.fetch_add(1, Ordering::Relaxed);
+        match self.markets.get_mut(&*trade.symbol) {
+            Some(market) => {
+                market.meter.record(trade.matched_quantity);
+                market.metrics.trades.fetch_add(1, Ordering::Relaxed);
+                // 大宗交易价格不参与形成参考价
+                if !trade.is_block_trade {
+                    market.last_trade_price = Some(trade.matched_price);
+                }
+            }
+            None => self.surveillance.meter(&trade.symbol).record(trade.matched_quantity),
+        }
+        self.positions.apply_trade(&trade);
+        if !self.reduce_only.is_empty() {
+            for user_id in [trade.buyer_user_id, trade.seller_user_id] {
+                let key = (user_id, trade.symbol.to_string());
+                if self.reduce_only.contains_key(&key) && !self.reduce_only_dirty.contains(&key) {
+                    self.reduce_only_dirty.push(key);
+                }
+            }
+        }
+        self.market_data.on_trade(&trade);
+        self.trade_log.push(trade.clone());
+        // 将成交结果发送出去
+        if self.output_sender.send(EngineOutput::Trade(trade)).is_err() {
+            eprintln!("输出通道已关闭，无法发送成交回报");
+        }
+    }
+}
@@ -0,0 +1,45 @@
+use crate::protocol::RejectReason;
+use std::fmt;
+
+// 引擎各层共用的错误类型，调用方可以按错误种类分别处理
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum EngineError {
+    // 价格为 0 或不是最小变动价位的整数倍
+    InvalidPrice { price: u64, tick_size: u64 },
+    // 数量为 0，或改单后的数量不合法
+    InvalidQuantity,
+    // 订单不存在（从未存在、已成交或已撤销）
+    OrderNotFound(u64),
+    // 引擎中没有该合约
+    SymbolUnknown(String),
+    // 订单属于其他用户
+    NotOrderOwner(u64),
+}
+
+impl fmt::Display for EngineError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            EngineError::InvalidPrice { price, tick_size } => {
+                write!(f, "无效的价格 {}（最小变动价位 {}）", price, tick_size)
+            }
+            EngineError::InvalidQuantity => write!(f, "无效的数量"),
+            EngineError::OrderNotFound(order_id) => write!(f, "订单 {} 不存在", order_id),
+            EngineError::SymbolUnknown(symbol) => write!(f, "未知的合约 {}", symbol),
+            EngineError::NotOrderOwner(order_id) => write!(f, "订单 {} 属于其他用户", order_id),
+        }
+    }
+}
+
+impl std::error::Error for EngineError {}
+
+// 错误回报给客户端时使用的拒绝原因
+impl From<EngineError> for RejectReason {
+    fn from(error: EngineError) -> Self {
+        match error {
+            EngineError::InvalidPrice { .. } => RejectReason::InvalidPrice,
+            EngineError::InvalidQuantity => RejectReason::InvalidQuantity,
+            EngineError::OrderNotFound(_) | EngineError::SymbolUnknown(_) => RejectReason::UnknownOrder,
+            EngineError::NotOrderOwner(_) => RejectReason::NotOrderOwner,
+        }
+    }
+}
@@ -0,0 +1,56 @@
+// 到期定时器：按合约参考数据中的到期时间，在到期时向引擎发送 ControlCommand::Expire；
+// 以及引擎时钟，定期发送 ControlCommand::AdvanceTime 撤销到期的 GTD 订单
+use crate::engine::{ControlCommand, EngineCommand};
+use crate::instruments::InstrumentMaster;
+use crate::sequencer;
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
+use tokio::sync::mpsc::UnboundedSender;
+
+// 等待期间检查引擎是否已关闭的最长间隔
+const POLL_INTERVAL: Duration = Duration::from_millis(500);
+// 引擎时钟的推进间隔，即 GTD 订单到期撤销的精度
+pub const CLOCK_INTERVAL: Duration = Duration::from_millis(10);
+
+// 有到期时间的合约，按到期时间（UNIX 毫秒）排列，同时到期的按合约名排列
+pub fn expiry_schedule(master: &InstrumentMaster) -> Vec<(u64, String)> {
+    let mut schedule: Vec<(u64, String)> =
+        master.iter().filter_map(|spec| Some((spec.expiry_ms?, spec.symbol.clone()))).collect();
+    schedule.sort();
+    schedule
+}
+
+// 启动后台线程，依次等到每个合约的到期时间后通知引擎；启动时已经过期的合约立即处理。
+// 全部合约处理完或引擎关闭后线程退出
+pub fn spawn_expiry_timer(master: &InstrumentMaster, command_sender: UnboundedSender<EngineCommand>) -> JoinHandle<()> {
+    let schedule = expiry_schedule(master);
+    thread::spawn(move || {
+        for (expiry_ms, symbol) in schedule {
+            loop {
+                if command_sender.is_closed() {
+                    return;
+                }
+                let now_ms = sequencer::now_nanos() / 1_000_000;
+                if now_ms >= expiry_ms {
+                    break;
+                }
+                thread::sleep(Duration::from_millis(expiry_ms - now_ms).min(POLL_INTERVAL));
+            }
+            tracing::info!(symbol = %symbol, "合约到期");
+            if command_sender.send(EngineCommand::Control(ControlCommand::Expire(symbol))).is_err() {
+                return;
+            }
+        }
+    })
+}
+
+// 启动后台线程，按固定间隔把当前时间发给引擎，引擎关闭后退出
+pub fn spawn_clock(command_sender: UnboundedSender<EngineCommand>, interval: Duration) -> JoinHandle<()> {
+    thread::spawn(move || loop {
+        let now_ms = sequencer::now_nanos() / 1_000_000;
+        if command_sender.send(EngineCommand::Control(ControlCommand::AdvanceTime(now_ms))).is_err() {
+            return;
+        }
+        thread::sleep(interval);
+    })
+}
@@ -0,0 +1,143 @@
+// 故障注入：在网络收发、引擎线程和网关日志上预留钩子，测试可以按规则注入延迟、丢帧、引擎停顿和日志写入失败，
+// 确定性地检验恢复和背压逻辑。只在开启 fault-injection feature 时生效，否则钩子恒为 None，编译后没有开销
+use std::time::Duration;
+
+// 注入点
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum FaultPoint {
+    // 网络层收到一帧客户端消息，尚未解码
+    InboundFrame,
+    // 网络层从出站队列取出一帧，尚未写入 socket
+    OutboundFrame,
+    // 引擎线程取出一条命令，尚未处理
+    EngineCommand,
+    // 网关写入一条日志记录
+    JournalWrite,
+}
+
+// 注入的故障；注入点不支持的动作被忽略
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FaultAction {
+    // 网络层异步等待，引擎线程阻塞（模拟停顿）
+    Delay(Duration),
+    // 丢弃这一帧，仅用于 InboundFrame 和 OutboundFrame
+    Drop,
+    // 写入失败，仅用于 JournalWrite
+    Error,
+}
+
+// 一条注入规则：跳过前 after 次经过，之后每次经过按 probability 触发，最多触发 limit 次
+#[derive(Debug, Clone)]
+pub struct FaultRule {
+    pub point: FaultPoint,
+    pub action: FaultAction,
+    pub after: u64,
+    pub probability: f64,
+    pub limit: Option<u64>,
+}
+
+impl FaultRule {
+    pub fn new(point: FaultPoint, action: FaultAction) -> Self {
+        FaultRule { point, action, after: 0, probability: 1.0, limit: None }
+    }
+
+    pub fn after(self, after: u64) -> Self {
+        FaultRule { after, ..self }
+    }
+
+    pub fn probability(self, probability: f64) -> Self {
+        FaultRule { probability, ..self }
+    }
+
+    pub fn limit(self, limit: u64) -> Self {
+        FaultRule { limit: Some(limit), ..self }
+    }
+}
+
+// 经过注入点时调用，返回需要模拟的故障
+#[cfg(not(feature = "fault-injection"))]
+#[inline(always)]
+pub fn inject(_point: FaultPoint) -> Option<FaultAction> {
+    None
+}
+
+#[cfg(feature = "fault-injection")]
+pub use enabled::{inject, install, triggered, FaultGuard};
+
+#[cfg(feature = "fault-injection")]
+mod enabled {
+    use super::{FaultAction, FaultPoint, FaultRule};
+    use parking_lot::{const_mutex, Mutex, MutexGuard};
+    use rand::rngs::StdRng;
+    use rand::{Rng, SeedableRng};
+    use std::collections::HashMap;
+    use std::sync::atomic::{AtomicBool, Ordering};
+
+    struct RuleState {
+        rule: FaultRule,
+        seen: u64,
+        fired: u64,
+    }
+
+    struct Plan {
+        rng: StdRng,
+        rules: Vec<RuleState>,
+        triggered: HashMap<FaultPoint, u64>,
+    }
+
+    // 没有安装规则时钩子只做一次原子读
+    static ACTIVE: AtomicBool = AtomicBool::new(false);
+    static PLAN: Mutex<Option<Plan>> = const_mutex(None);
+    // 规则是进程级的，同时只允许一组测试安装
+    static EXCLUSIVE: Mutex<()> = const_mutex(());
+
+    // 安装期间持有；释放时清除规则，下一组测试才能安装
+    pub struct FaultGuard {
+        _exclusive: MutexGuard<'static, ()>,
+    }
+
+    impl Drop for FaultGuard {
+        fn drop(&mut self) {
+            ACTIVE.store(false, Ordering::Release);
+            *PLAN.lock() = None;
+        }
+    }
+
+    // 安装一组规则；相同的种子和经过顺序总是得到相同的注入结果
+    pub fn install(seed: u64, rules: Vec<FaultRule>) -> FaultGuard {
+        let exclusive = EXCLUSIVE.lock();
+        let rules = rules.into_iter().map(|rule| RuleState { rule, seen: 0, fired: 0 }).collect();
+        *PLAN.lock() = Some(Plan { rng: StdRng::seed_from_u64(seed), rules, triggered: HashMap::new() });
+        ACTIVE.store(true, Ordering::Release);
+        FaultGuard { _exclusive: exclusive }
+    }
+
+    // 注入点累计触发的故障数
+    pub fn triggered(point: FaultPoint) -> u64 {
+        PLAN.lock().as_ref().and_then(|plan| plan.triggered.get(&point).copied()).unwrap_or(0)
+    }
+
+    pub fn inject(point: FaultPoint) -> Option<FaultAction> {
+        if !ACTIVE.load(Ordering::Acquire) {
+            return None;
+        }
+        let mut guard = PLAN.lock();
+        let plan = guard.as_mut()?;
+        let mut action = None;
+        for state in plan.rules.iter_mut().filter(|state| state.rule.point == point) {
+            state.seen += 1;
+            if action.is_some() || state.seen <= state.rule.after || state.rule.limit.is_some_and(|limit| state.fired >= limit) {
+                continue;
+            }
+            if state.rule.probability < 1.0 && !plan.rng.gen_bool(state.rule.probability.max(0.0)) {
+                continue;
+            }
+            state.fired += 1;
+            action = Some(state.rule.action);
+        }
+        if action.is_some() {
+            *plan.triggered.entry(point).or_default() += 1;
+        }
+        action
+    }
+}
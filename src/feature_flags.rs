@@ -0,0 +1,106 @@
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fmt;
+use std::str::FromStr;
+
+// 受开关控制的实验性撮合行为
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+pub enum Feature {
+    // 使用合约配置的按比例分配算法；关闭时回退到时间优先
+    ProRataAllocation,
+}
+
+impl Feature {
+    // 未显式配置时的默认状态
+    fn default_enabled(self) -> bool {
+        match self {
+            // 分配算法本身需要按合约显式配置，开关只用于紧急回退
+            Feature::ProRataAllocation => true,
+        }
+    }
+
+    fn name(self) -> &'static str {
+        match self {
+            Feature::ProRataAllocation => "pro_rata_allocation",
+        }
+    }
+}
+
+impl fmt::Display for Feature {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+impl FromStr for Feature {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "pro_rata_allocation" => Ok(Feature::ProRataAllocation),
+            _ => Err(format!("未知的功能开关: {}", s)),
+        }
+    }
+}
+
+// 功能开关：先查合约级别的设置，再查全局设置，最后使用默认值
+#[derive(Debug, Clone, Default)]
+pub struct FeatureFlags {
+    global: HashMap<Feature, bool>,
+    per_symbol: HashMap<(String, Feature), bool>,
+}
+
+impl FeatureFlags {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    // 解析形如 "pro_rata_allocation=off,ES:pro_rata_allocation=on" 的配置，
+    // 带 "合约:" 前缀的项只对该合约生效
+    pub fn parse(config: &str) -> Result<Self, String> {
+        let mut flags = FeatureFlags::new();
+        for item in config.split(',').map(str::trim).filter(|item| !item.is_empty()) {
+            let (key, value) = item.split_once('=').ok_or_else(|| format!("缺少 '=': {}", item))?;
+            let enabled = match value.trim() {
+                "on" | "true" | "1" => true,
+                "off" | "false" | "0" => false,
+                other => return Err(format!("无效的开关值: {}", other)),
+            };
+            match key.trim().split_once(':') {
+                Some((symbol, feature)) => flags.set(feature.parse()?, Some(symbol), enabled),
+                None => flags.set(key.trim().parse()?, None, enabled),
+            }
+        }
+        Ok(flags)
+    }
+
+    // 设置开关；symbol 为 None 时修改全局设置
+    pub fn set(&mut self, feature: Feature, symbol: Option<&str>, enabled: bool) {
+        match symbol {
+            Some(symbol) => {
+                self.per_symbol.insert((symbol.to_string(), feature), enabled);
+            }
+            None => {
+                self.global.insert(feature, enabled);
+            }
+        }
+    }
+
+    // 显式配置过的开关 (合约, 开关, 状态)，全局设置的合约为 None，按合约和开关排序
+    pub fn overrides(&self) -> Vec<(Option<String>, Feature, bool)> {
+        let global = self.global.iter().map(|(&feature, &enabled)| (None, feature, enabled));
+        let per_symbol =
+            self.per_symbol.iter().map(|((symbol, feature), &enabled)| (Some(symbol.clone()), *feature, enabled));
+        let mut overrides: Vec<_> = global.chain(per_symbol).collect();
+        overrides.sort();
+        overrides
+    }
+
+    pub fn is_enabled(&self, feature: Feature, symbol: &str) -> bool {
+        self.per_symbol
+            .get(&(symbol.to_string(), feature))
+            .or_else(|| self.global.get(&feature))
+            .copied()
+            .unwrap_or_else(|| feature.default_enabled())
+    }
+}
@@ -0,0 +1,267 @@
+// 订单接入网关的会话恢复：按用户为入站消息和执行回报分配序号，执行回报写入日志，
+// 客户端断线重连并登录后发送最后收到的序号，网关从日志中补发缺失的回报
+use crate::fault::{self, FaultAction, FaultPoint};
+use crate::protocol::{ExecutionReport, ServerMessage};
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, VecDeque};
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, BufRead, BufReader, BufWriter, Write};
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex;
+
+#[derive(Debug, Clone)]
+pub struct GatewayConfig {
+    // 追加写入的 ndjson 日志，每行一条回报或确认记录；为 None 时只保存在内存中，重启后丢失
+    pub journal_path: Option<PathBuf>,
+    // 每个用户保留的未确认回报数，超过时丢弃最早的，补发时客户端会看到序号缺口
+    pub max_retained_reports: usize,
+    // 日志记录数超过该值（且至少是上次压缩后记录数的两倍）时重写日志，只保留各用户的序号和未确认的回报；0 表示不压缩
+    pub compact_after_records: usize,
+}
+
+impl Default for GatewayConfig {
+    fn default() -> Self {
+        GatewayConfig { journal_path: None, max_retained_reports: 10_000, compact_after_records: 100_000 }
+    }
+}
+
+// 日志中的一条记录
+#[derive(Debug, Serialize, Deserialize)]
+enum JournalRecord {
+    Report { seq: u64, report: ExecutionReport },
+    // 客户端确认已收到 seq 及之前的所有回报
+    Ack { user_id: u64, seq: u64 },
+    // 用户的一条入站业务消息
+    Inbound { user_id: u64, seq: u64 },
+    // 压缩时写入的用户序号状态，之后跟随该用户未确认的回报
+    Session { user_id: u64, inbound_seq: u64, outbound_seq: u64, last_acked: u64 },
+}
+
+// 单个用户的序号状态，跨连接保持
+#[derive(Debug, Default)]
+struct UserLog {
+    // 已收到的入站业务消息数
+    inbound_seq: u64,
+    // 最近一条执行回报的序号
+    outbound_seq: u64,
+    last_acked: u64,
+    // 序号大于 last_acked 的回报
+    retained: VecDeque<(u64, ExecutionReport)>,
+}
+
+impl UserLog {
+    fn retain(&mut self, seq: u64, report: ExecutionReport, max_retained: usize) {
+        self.retained.push_back((seq, report));
+        while self.retained.len() > max_retained {
+            self.retained.pop_front();
+        }
+    }
+
+    fn ack(&mut self, seq: u64) {
+        self.last_acked = self.last_acked.max(seq);
+        while self.retained.front().is_some_and(|(retained, _)| *retained <= self.last_acked) {
+            self.retained.pop_front();
+        }
+    }
+}
+
+// 会话恢复的结果
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Resumed {
+    pub last_inbound_seq: u64,
+    pub last_outbound_seq: u64,
+    // 序号大于客户端最后收到序号的回报，按序号排列
+    pub missed: Vec<(u64, ExecutionReport)>,
+}
+
+#[derive(Debug)]
+struct Journal {
+    writer: BufWriter<File>,
+    // 文件中的记录数
+    records: usize,
+    // 记录数达到该值时压缩
+    compact_at: usize,
+}
+
+#[derive(Debug)]
+pub struct Gateway {
+    config: GatewayConfig,
+    users: Mutex<HashMap<u64, UserLog>>,
+    journal: Option<Mutex<Journal>>,
+    // 写入失败的日志记录数，这些回报在进程重启后无法补发
+    journal_errors: AtomicU64,
+}
+
+impl Gateway {
+    // 打开网关；配置了日志文件时先读取已有记录恢复各用户的序号和未确认的回报
+    pub fn open(config: GatewayConfig) -> io::Result<Self> {
+        let mut users: HashMap<u64, UserLog> = HashMap::new();
+        let journal = match &config.journal_path {
+            Some(path) => {
+                let mut records = 0;
+                if path.exists() {
+                    for line in BufReader::new(File::open(path)?).lines() {
+                        let line = line?;
+                        if line.trim().is_empty() {
+                            continue;
+                        }
+                        records += 1;
+                        let record: JournalRecord = serde_json::from_str(&line)
+                            .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+                        match record {
+                            JournalRecord::Report { seq, report } => {
+                                let log = users.entry(report.user_id).or_default();
+                                log.outbound_seq = log.outbound_seq.max(seq);
+                                if seq > log.last_acked {
+                                    log.retain(seq, report, config.max_retained_reports);
+                                }
+                            }
+                            JournalRecord::Ack { user_id, seq } => users.entry(user_id).or_default().ack(seq),
+                            JournalRecord::Inbound { user_id, seq } => {
+                                let log = users.entry(user_id).or_default();
+                                log.inbound_seq = log.inbound_seq.max(seq);
+                            }
+                            JournalRecord::Session { user_id, inbound_seq, outbound_seq, last_acked } => {
+                                let log = users.entry(user_id).or_default();
+                                log.inbound_seq = log.inbound_seq.max(inbound_seq);
+                                log.outbound_seq = log.outbound_seq.max(outbound_seq);
+                                log.ack(last_acked);
+                            }
+                        }
+                    }
+                }
+                let file = OpenOptions::new().create(true).append(true).open(path)?;
+                // 已有日志超过阈值时在下一次追加时压缩
+                let compact_at = config.compact_after_records;
+                Some(Mutex::new(Journal { writer: BufWriter::new(file), records, compact_at }))
+            }
+            None => None,
+        };
+        Ok(Gateway { config, users: Mutex::new(users), journal, journal_errors: AtomicU64::new(0) })
+    }
+
+    // 为用户的一条入站业务消息分配序号并写入日志
+    pub fn record_inbound(&self, user_id: u64) -> u64 {
+        let mut users = self.users.lock().unwrap();
+        let log = users.entry(user_id).or_default();
+        log.inbound_seq += 1;
+        let seq = log.inbound_seq;
+        self.append(&users, &JournalRecord::Inbound { user_id, seq });
+        seq
+    }
+
+    // 为执行回报分配所属用户的下一个序号并写入日志
+    pub fn record_report(&self, report: &ExecutionReport) -> u64 {
+        let mut users = self.users.lock().unwrap();
+        let log = users.entry(report.user_id).or_default();
+        log.outbound_seq += 1;
+        let seq = log.outbound_seq;
+        log.retain(seq, report.clone(), self.config.max_retained_reports);
+        self.append(&users, &JournalRecord::Report { seq, report: report.clone() });
+        seq
+    }
+
+    // 把消息中的执行回报（包括请求应答中的）包装为带序号的消息，其他消息原样返回
+    pub fn sequence(&self, message: ServerMessage) -> ServerMessage {
+        match message {
+            ServerMessage::ExecutionReport(report) => {
+                let seq = self.record_report(&report);
+                ServerMessage::Sequenced { seq, message: Box::new(ServerMessage::ExecutionReport(report)) }
+            }
+            ServerMessage::Response { request_id, message } => {
+                ServerMessage::Response { request_id, message: Box::new(self.sequence(*message)) }
+            }
+            message => message,
+        }
+    }
+
+    // 客户端重连后报告最后收到的回报序号：记录确认并返回之后缺失的回报
+    pub fn resume(&self, user_id: u64, last_received_seq: u64) -> Resumed {
+        let mut users = self.users.lock().unwrap();
+        let log = users.entry(user_id).or_default();
+        // 客户端不可能收到过尚未分配的序号
+        let acked = last_received_seq.min(log.outbound_seq);
+        if acked > log.last_acked {
+            log.ack(acked);
+            self.append(&users, &JournalRecord::Ack { user_id, seq: acked });
+        }
+        let log = &users[&user_id];
+        Resumed {
+            last_inbound_seq: log.inbound_seq,
+            last_outbound_seq: log.outbound_seq,
+            missed: log.retained.iter().filter(|(seq, _)| *seq > last_received_seq).cloned().collect(),
+        }
+    }
+
+    pub fn journal_errors(&self) -> u64 {
+        self.journal_errors.load(Ordering::Relaxed)
+    }
+
+    // 写入并立即刷新，保证已发出的回报在进程崩溃后仍能补发；调用方持有 users 锁，记录数达到阈值时据此压缩日志
+    fn append(&self, users: &HashMap<u64, UserLog>, record: &JournalRecord) {
+        let Some(journal) = &self.journal else {
+            return;
+        };
+        if let Some(FaultAction::Error) = fault::inject(FaultPoint::JournalWrite) {
+            self.journal_errors.fetch_add(1, Ordering::Relaxed);
+            eprintln!("写入网关日志失败: 注入的故障");
+            return;
+        }
+        let mut journal = journal.lock().unwrap();
+        let result = write_record(&mut journal.writer, record).and_then(|_| journal.writer.flush());
+        if let Err(e) = result {
+            self.journal_errors.fetch_add(1, Ordering::Relaxed);
+            eprintln!("写入网关日志失败: {}", e);
+            return;
+        }
+        journal.records += 1;
+        if self.config.compact_after_records > 0 && journal.records >= journal.compact_at {
+            if let Err(e) = self.compact(users, &mut journal) {
+                // 压缩失败不影响原日志，继续追加，达到下一个阈值时重试
+                journal.compact_at = journal.records.saturating_mul(2);
+                eprintln!("压缩网关日志失败: {}", e);
+            }
+        }
+    }
+
+    // 把各用户的序号和未确认的回报写入临时文件后替换日志，已确认的回报和历史确认记录随之丢弃
+    fn compact(&self, users: &HashMap<u64, UserLog>, journal: &mut Journal) -> io::Result<()> {
+        let Some(path) = &self.config.journal_path else {
+            return Ok(());
+        };
+        let temporary = path.with_extension("ndjson.tmp");
+        let mut writer = BufWriter::new(File::create(&temporary)?);
+        let mut records = 0;
+        let mut user_ids: Vec<u64> = users.keys().copied().collect();
+        user_ids.sort_unstable();
+        for user_id in user_ids {
+            let log = &users[&user_id];
+            let session = JournalRecord::Session {
+                user_id,
+                inbound_seq: log.inbound_seq,
+                outbound_seq: log.outbound_seq,
+                last_acked: log.last_acked,
+            };
+            write_record(&mut writer, &session)?;
+            records += 1;
+            for (seq, report) in &log.retained {
+                write_record(&mut writer, &JournalRecord::Report { seq: *seq, report: report.clone() })?;
+                records += 1;
+            }
+        }
+        writer.flush()?;
+        writer.get_ref().sync_all()?;
+        fs::rename(&temporary, path)?;
+        journal.writer = BufWriter::new(OpenOptions::new().append(true).open(path)?);
+        journal.records = records;
+        // 未确认的回报多时避免每次追加都重写
+        journal.compact_at = self.config.compact_after_records.max(records.saturating_mul(2));
+        Ok(())
+    }
+}
+
+fn write_record(writer: &mut BufWriter<File>, record: &JournalRecord) -> io::Result<()> {
+    serde_json::to_writer(&mut *writer, record).map_err(io::Error::from)?;
+    writer.write_all(b"\n")
+}
@@ -0,0 +1,176 @@
+// 进程内端到端测试工具：在临时端口上启动完整的服务（引擎线程 + 网络层），
+// 测试通过真实的 TCP 连接按脚本收发消息，覆盖编解码、会话、出站队列和关闭流程
+use crate::engine::{ControlCommand, EngineCommand, MatchingEngine};
+use crate::metrics::{EngineMetrics, OutboundMetrics};
+use crate::network::{self, OutboundConfig};
+use crate::protocol::{ClientMessage, LogonRequest, LogonResponse, ServerMessage};
+use crate::session::{sign_logon, SessionConfig};
+use bincode::config;
+use futures::{SinkExt, StreamExt};
+use std::net::SocketAddr;
+use std::sync::Arc;
+use std::thread;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use tokio::net::{TcpListener, TcpStream};
+use tokio::sync::{mpsc, oneshot};
+use tokio::task::JoinHandle;
+use tokio_util::codec::{Framed, LengthDelimitedCodec};
+
+// 等待单条消息的最长时间，超时视为测试失败
+pub const RECV_TIMEOUT: Duration = Duration::from_secs(5);
+
+pub struct TestServer {
+    addr: SocketAddr,
+    command_sender: Option<mpsc::UnboundedSender<EngineCommand>>,
+    engine_metrics: Arc<EngineMetrics>,
+    outbound_metrics: Arc<OutboundMetrics>,
+    stop_accepting: Option<oneshot::Sender<()>>,
+    server: Option<JoinHandle<()>>,
+    engine_thread: Option<thread::JoinHandle<()>>,
+}
+
+impl TestServer {
+    // 使用默认引擎和会话配置（不要求登录）启动
+    pub async fn start() -> Self {
+        Self::start_with(SessionConfig::default(), |engine| engine).await
+    }
+
+    // configure 可以像 main.rs 一样配置引擎
+    pub async fn start_with(sessions: SessionConfig, configure: impl FnOnce(MatchingEngine) -> MatchingEngine) -> Self {
+        let (command_sender, command_receiver) = mpsc::unbounded_channel();
+        let (output_sender, output_receiver) = mpsc::unbounded_channel();
+        let mut engine = configure(MatchingEngine::new(command_receiver, output_sender));
+        let engine_metrics = engine.metrics();
+        let engine_thread = thread::spawn(move || engine.run());
+
+        let listener = TcpListener::bind("127.0.0.1:0").await.expect("无法绑定临时端口");
+        let addr = listener.local_addr().expect("监听地址");
+        let outbound_metrics = Arc::new(OutboundMetrics::new());
+        let (stop_accepting, stop_accepting_rx) = oneshot::channel::<()>();
+        let server = tokio::spawn(network::serve_until(
+            listener,
+            command_sender.clone(),
+            output_receiver,
+            sessions,
+            OutboundConfig::default(),
+            outbound_metrics.clone(),
+            async {
+                let _ = stop_accepting_rx.await;
+            },
+        ));
+        TestServer {
+            addr,
+            command_sender: Some(command_sender),
+            engine_metrics,
+            outbound_metrics,
+            stop_accepting: Some(stop_accepting),
+            server: Some(server),
+            engine_thread: Some(engine_thread),
+        }
+    }
+
+    pub fn addr(&self) -> SocketAddr {
+        self.addr
+    }
+
+    pub fn engine_metrics(&self) -> &Arc<EngineMetrics> {
+        &self.engine_metrics
+    }
+
+    pub fn outbound_metrics(&self) -> &Arc<OutboundMetrics> {
+        &self.outbound_metrics
+    }
+
+    // 绕过网络层直接向引擎发送命令，例如管理命令或深度快照
+    pub fn send_command(&self, command: EngineCommand) {
+        let sender = self.command_sender.as_ref().expect("服务器已关闭");
+        sender.send(command).expect("引擎已退出");
+    }
+
+    pub async fn connect(&self) -> TestClient {
+        let stream = TcpStream::connect(self.addr).await.expect("无法连接测试服务器");
+        TestClient { framed: Framed::new(stream, LengthDelimitedCodec::new()) }
+    }
+
+    // 按 main.rs 的顺序有序关闭：停止接受新连接，引擎处理完已排队的命令后退出，
+    // 连接发完剩余回报和 Shutdown 通知后断开。客户端需要读完剩余消息或断开，否则会超时
+    pub async fn shutdown(mut self) {
+        if let Some(stop_accepting) = self.stop_accepting.take() {
+            let _ = stop_accepting.send(());
+        }
+        if let Some(sender) = self.command_sender.take() {
+            let _ = sender.send(EngineCommand::Control(ControlCommand::Drain));
+        }
+        if let Some(server) = self.server.take() {
+            tokio::time::timeout(RECV_TIMEOUT, server).await.expect("服务器没有在连接断开后返回").expect("服务器任务崩溃");
+        }
+        if let Some(engine_thread) = self.engine_thread.take() {
+            tokio::task::spawn_blocking(move || engine_thread.join()).await.unwrap().expect("撮合引擎线程崩溃");
+        }
+    }
+}
+
+impl Drop for TestServer {
+    // 测试没有调用 shutdown 时让引擎线程退出；网络任务随测试的运行时结束
+    fn drop(&mut self) {
+        if let Some(sender) = self.command_sender.take() {
+            let _ = sender.send(EngineCommand::Control(ControlCommand::Drain));
+        }
+    }
+}
+
+// 测试客户端，使用与服务器相同的长度前缀帧和 bincode 编码
+pub struct TestClient {
+    framed: Framed<TcpStream, LengthDelimitedCodec>,
+}
+
+impl TestClient {
+    pub async fn send(&mut self, message: ClientMessage) {
+        let payload = bincode::encode_to_vec(message, config::standard()).expect("客户端消息编码失败");
+        self.framed.send(payload.into()).await.expect("发送失败");
+    }
+
+    // 下一条消息；连接被服务器关闭时返回 None
+    pub async fn recv(&mut self) -> Option<ServerMessage> {
+        let frame = tokio::time::timeout(RECV_TIMEOUT, self.framed.next()).await.expect("等待消息超时")?;
+        let frame = frame.ok()?;
+        let (message, _) = bincode::decode_from_slice(&frame, config::standard()).expect("服务器消息解码失败");
+        Some(message)
+    }
+
+    // 跳过不关心的消息，返回第一条被 matcher 接受的消息
+    pub async fn expect<T>(&mut self, mut matcher: impl FnMut(ServerMessage) -> Option<T>) -> T {
+        loop {
+            let message = self.recv().await.expect("连接在收到期望的消息前关闭");
+            if let Some(matched) = matcher(message) {
+                return matched;
+            }
+        }
+    }
+
+    // 用当前时间签名登录，返回登录回报
+    pub async fn logon(&mut self, api_key: &str, secret: &[u8]) -> LogonResponse {
+        let timestamp_ms = SystemTime::now().duration_since(UNIX_EPOCH).expect("系统时间早于 UNIX 纪元").as_millis() as u64;
+        let signature = sign_logon(secret, api_key, timestamp_ms);
+        self.send(ClientMessage::Logon(LogonRequest { api_key: api_key.to_string(), timestamp_ms, signature })).await;
+        self.expect(|message| match message {
+            ServerMessage::Logon(response) => Some(response),
+            _ => None,
+        })
+        .await
+    }
+
+    // 读完剩余消息直到服务器关闭连接
+    pub async fn drain(&mut self) -> Vec<ServerMessage> {
+        let mut messages = Vec::new();
+        while let Some(message) = self.recv().await {
+            messages.push(message);
+        }
+        messages
+    }
+
+    // 客户端主动断开
+    pub async fn disconnect(mut self) {
+        let _ = self.framed.close().await;
+    }
+}
@@ -0,0 +1,150 @@
+use crate::metrics::{EngineMetrics, OutboundMetrics};
+use std::fmt::Write as _;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+
+// 服务的健康状态，按严重程度排序
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum HealthStatus {
+    Healthy,
+    // 仍在工作但处理不过来，例如命令或推送积压超过水位
+    Degraded,
+    // 撮合引擎线程已退出或监听端口已关闭，需要重启
+    Unhealthy,
+}
+
+impl HealthStatus {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            HealthStatus::Healthy => "healthy",
+            HealthStatus::Degraded => "degraded",
+            HealthStatus::Unhealthy => "unhealthy",
+        }
+    }
+}
+
+// 单项检查的结果
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HealthCheck {
+    pub name: &'static str,
+    pub status: HealthStatus,
+    pub detail: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HealthReport {
+    // 各项检查中最严重的状态
+    pub status: HealthStatus,
+    pub checks: Vec<HealthCheck>,
+}
+
+impl HealthReport {
+    // 存活探针：只有需要重启时才失败，积压不应导致 Kubernetes 杀掉进程
+    pub fn is_live(&self) -> bool {
+        self.status != HealthStatus::Unhealthy
+    }
+
+    // 就绪探针：积压时也返回未就绪，让负载均衡暂时不再分配新连接
+    pub fn is_ready(&self) -> bool {
+        self.status == HealthStatus::Healthy
+    }
+
+    // 每项检查一行的纯文本，作为探针响应的内容
+    pub fn render(&self) -> String {
+        let mut out = String::new();
+        let _ = writeln!(out, "status: {}", self.status.as_str());
+        for check in &self.checks {
+            let _ = writeln!(out, "{}: {} ({})", check.name, check.status.as_str(), check.detail);
+        }
+        out
+    }
+}
+
+// 积压水位，超过时状态降为 Degraded
+#[derive(Debug, Clone, Copy)]
+pub struct HealthConfig {
+    // 引擎命令通道中排队的命令数
+    pub command_queue_watermark: u64,
+    // 所有连接出站队列中等待发送的消息总数
+    pub outbound_queue_watermark: u64,
+}
+
+impl Default for HealthConfig {
+    fn default() -> Self {
+        HealthConfig { command_queue_watermark: 10_000, outbound_queue_watermark: 100_000 }
+    }
+}
+
+// 根据引擎线程、监听端口和队列积压的实际情况给出健康状态
+pub struct HealthChecker {
+    engine_running: AtomicBool,
+    listener_bound: AtomicBool,
+    metrics: Arc<EngineMetrics>,
+    outbound: Arc<OutboundMetrics>,
+    config: HealthConfig,
+}
+
+// 撮合引擎线程持有的标记，线程正常退出或 panic 时在 drop 中清除运行状态
+pub struct EngineRunning {
+    checker: Arc<HealthChecker>,
+}
+
+impl Drop for EngineRunning {
+    fn drop(&mut self) {
+        self.checker.engine_running.store(false, Ordering::Release);
+    }
+}
+
+impl HealthChecker {
+    pub fn new(metrics: Arc<EngineMetrics>, outbound: Arc<OutboundMetrics>, config: HealthConfig) -> Self {
+        HealthChecker {
+            engine_running: AtomicBool::new(false),
+            listener_bound: AtomicBool::new(false),
+            metrics,
+            outbound,
+            config,
+        }
+    }
+
+    // 在撮合引擎线程中调用，返回的标记应在 run() 期间一直持有
+    pub fn engine_running(self: &Arc<Self>) -> EngineRunning {
+        self.engine_running.store(true, Ordering::Release);
+        EngineRunning { checker: self.clone() }
+    }
+
+    // 网络服务器绑定端口后设为 true，接受连接的循环结束后设为 false
+    pub fn set_listener_bound(&self, bound: bool) {
+        self.listener_bound.store(bound, Ordering::Release);
+    }
+
+    pub fn check(&self) -> HealthReport {
+        let mut checks = Vec::new();
+        let running = self.engine_running.load(Ordering::Acquire);
+        checks.push(HealthCheck {
+            name: "engine",
+            status: if running { HealthStatus::Healthy } else { HealthStatus::Unhealthy },
+            detail: if running { "running" } else { "stopped" }.to_string(),
+        });
+        let bound = self.listener_bound.load(Ordering::Acquire);
+        checks.push(HealthCheck {
+            name: "listener",
+            status: if bound { HealthStatus::Healthy } else { HealthStatus::Unhealthy },
+            detail: if bound { "bound" } else { "not bound" }.to_string(),
+        });
+        let command_depth = self.metrics.snapshot().command_queue_depth;
+        checks.push(watermark_check("command_queue", command_depth, self.config.command_queue_watermark));
+        let outbound_depth = self.outbound.snapshot().queue_depth;
+        checks.push(watermark_check("outbound_queue", outbound_depth, self.config.outbound_queue_watermark));
+
+        let status = checks.iter().map(|check| check.status).max().unwrap_or(HealthStatus::Healthy);
+        HealthReport { status, checks }
+    }
+}
+
+fn watermark_check(name: &'static str, depth: u64, watermark: u64) -> HealthCheck {
+    HealthCheck {
+        name,
+        status: if depth > watermark { HealthStatus::Degraded } else { HealthStatus::Healthy },
+        detail: format!("depth {} / watermark {}", depth, watermark),
+    }
+}
@@ -0,0 +1,97 @@
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+// 雪花 ID 的纪元：2024-01-01T00:00:00Z，单位毫秒
+const SNOWFLAKE_EPOCH_MS: u64 = 1_704_067_200_000;
+const SEQUENCE_BITS: u32 = 12;
+const SHARD_BITS: u32 = 10;
+const MAX_SEQUENCE: u64 = (1 << SEQUENCE_BITS) - 1;
+pub const MAX_SHARD: u16 = (1 << SHARD_BITS) - 1;
+
+#[derive(Debug)]
+enum Scheme {
+    // 从 1 开始递增，适用于单进程
+    Sequential,
+    // 41 位毫秒时间戳 | 10 位分片号 | 12 位毫秒内序号，多个进程或分区之间也不会重复
+    Snowflake { shard: u64 },
+}
+
+// 无锁的全局 ID 生成器，由引擎内所有订单簿共享，保证订单号和成交号在各合约之间唯一
+#[derive(Debug)]
+pub struct IdGenerator {
+    scheme: Scheme,
+    // Sequential: 上一个已分配的 ID；Snowflake: 上一个 (毫秒 << 12 | 序号)
+    state: AtomicU64,
+}
+
+impl IdGenerator {
+    pub fn sequential() -> Self {
+        IdGenerator { scheme: Scheme::Sequential, state: AtomicU64::new(0) }
+    }
+
+    // 分片号用于区分同时运行的多个引擎实例或分区，取值范围 0..=MAX_SHARD
+    pub fn snowflake(shard: u16) -> Self {
+        assert!(shard <= MAX_SHARD, "分片号不能超过 {}", MAX_SHARD);
+        IdGenerator {
+            scheme: Scheme::Snowflake { shard: shard as u64 },
+            state: AtomicU64::new(0),
+        }
+    }
+
+    pub fn next_id(&self) -> u64 {
+        match self.scheme {
+            Scheme::Sequential => self.state.fetch_add(1, Ordering::Relaxed) + 1,
+            Scheme::Snowflake { shard } => {
+                let (millis, sequence) = self.next_snowflake_slot();
+                (millis << (SHARD_BITS + SEQUENCE_BITS)) | (shard << SEQUENCE_BITS) | sequence
+            }
+        }
+    }
+
+    // 生成器的内部状态，写入恢复快照。两种方案的状态都单调递增
+    pub fn checkpoint(&self) -> u64 {
+        self.state.load(Ordering::Relaxed)
+    }
+
+    // 从快照恢复：之后分配的 ID 不会与快照之前分配过的重复
+    pub fn restore(&self, state: u64) {
+        self.state.fetch_max(state, Ordering::Relaxed);
+    }
+
+    // 分配下一个 (毫秒, 序号)。同一毫秒内序号用尽时借用下一毫秒，而不是自旋等待，
+    // 因此 ID 始终严格递增，并大致按时间排序
+    fn next_snowflake_slot(&self) -> (u64, u64) {
+        let mut last = self.state.load(Ordering::Relaxed);
+        loop {
+            let now = now_millis().saturating_sub(SNOWFLAKE_EPOCH_MS);
+            let (last_millis, last_sequence) = (last >> SEQUENCE_BITS, last & MAX_SEQUENCE);
+            let (millis, sequence) = if now > last_millis {
+                (now, 0)
+            } else if last_sequence < MAX_SEQUENCE {
+                (last_millis, last_sequence + 1)
+            } else {
+                (last_millis + 1, 0)
+            };
+            let next = (millis << SEQUENCE_BITS) | sequence;
+            match self.state.compare_exchange_weak(last, next, Ordering::Relaxed, Ordering::Relaxed) {
+                Ok(_) => return (millis, sequence),
+                Err(current) => last = current,
+            }
+        }
+    }
+}
+
+impl Default for IdGenerator {
+    fn default() -> Self {
+        Self::sequential()
+    }
+}
+
+// 从雪花 ID 中取出分片号
+pub fn snowflake_shard(id: u64) -> u16 {
+    ((id >> SEQUENCE_BITS) & MAX_SHARD as u64) as u16
+}
+
+fn now_millis() -> u64 {
+    SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_millis() as u64).unwrap_or_default()
+}
@@ -0,0 +1,220 @@
+// 合约参考数据：每个合约的最小变动价位、价格精度、合约乘数、到期时间、交易时段和涨跌停幅度。
+// 启动时从 JSON 文件或 HTTP 地址加载，用于为各合约配置订单簿和校验规则
+use crate::circuit_breaker::{BreachPolicy, PriceBand};
+use crate::engine::MatchingEngine;
+use crate::price::{PriceScales, MAX_EXPONENT};
+use crate::spread::SpreadDefinition;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::io::{Read, Write};
+use std::net::TcpStream;
+use std::time::Duration;
+
+// HTTP 加载的连接和读写超时
+const HTTP_TIMEOUT: Duration = Duration::from_secs(5);
+
+// 每日交易时段，按 UTC 当日的分钟数表示，close 不包含在内；close 小于 open 时跨越午夜
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TradingHours {
+    pub open_minute: u32,
+    pub close_minute: u32,
+}
+
+impl TradingHours {
+    pub fn contains(&self, minute_of_day: u32) -> bool {
+        if self.open_minute <= self.close_minute {
+            (self.open_minute..self.close_minute).contains(&minute_of_day)
+        } else {
+            minute_of_day >= self.open_minute || minute_of_day < self.close_minute
+        }
+    }
+}
+
+// 以参考价为中心的涨跌停幅度（百分比）
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct PriceBandSpec {
+    pub reference_price: u64,
+    pub limit_up_percent: f64,
+    pub limit_down_percent: f64,
+    // 为 true 时触及价格带暂停撮合，否则拒绝订单
+    #[serde(default)]
+    pub halt_on_breach: bool,
+}
+
+impl PriceBandSpec {
+    pub fn to_price_band(&self) -> PriceBand {
+        PriceBand {
+            reference_price: self.reference_price,
+            limit_up_bps: (self.limit_up_percent * 100.0).round() as u64,
+            limit_down_bps: (self.limit_down_percent * 100.0).round() as u64,
+            policy: if self.halt_on_breach { BreachPolicy::Halt } else { BreachPolicy::Reject },
+        }
+    }
+}
+
+// 单个合约的规格
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ContractSpec {
+    pub symbol: String,
+    #[serde(default = "default_one")]
+    pub tick_size: u64,
+    // 价格的小数位数：整数价格 p 表示 p × 10^-price_exponent，tick_size 和涨跌停参考价同样按整数价格给出
+    #[serde(default)]
+    pub price_exponent: u8,
+    // 每手对应的标的数量，名义价值 = 价格 × 数量 × 乘数
+    #[serde(default = "default_one")]
+    pub contract_multiplier: u64,
+    // 到期时间（UNIX 毫秒），永续合约为 None
+    #[serde(default)]
+    pub expiry_ms: Option<u64>,
+    #[serde(default)]
+    pub trading_hours: Option<TradingHours>,
+    #[serde(default)]
+    pub price_band: Option<PriceBandSpec>,
+    // 价差合约的两腿 [leg1, leg2]，价差 = leg1 − leg2；价差合约本身没有订单簿
+    #[serde(default)]
+    pub spread_legs: Option<(String, String)>,
+}
+
+fn default_one() -> u64 {
+    1
+}
+
+impl ContractSpec {
+    pub fn notional(&self, price: u64, quantity: u64) -> u128 {
+        price as u128 * quantity as u128 * self.contract_multiplier as u128
+    }
+
+    fn validate(&self) -> Result<(), String> {
+        if self.symbol.is_empty() {
+            return Err("合约名不能为空".to_string());
+        }
+        if self.tick_size == 0 || self.contract_multiplier == 0 {
+            return Err(format!("{}: 最小变动价位和合约乘数必须大于 0", self.symbol));
+        }
+        if self.price_exponent > MAX_EXPONENT {
+            return Err(format!("{}: 价格精度不能超过 {} 位小数", self.symbol, MAX_EXPONENT));
+        }
+        if let Some(hours) = self.trading_hours {
+            if hours.open_minute >= 1440 || hours.close_minute > 1440 || hours.open_minute == hours.close_minute {
+                return Err(format!("{}: 无效的交易时段", self.symbol));
+            }
+        }
+        if let Some((leg1, leg2)) = &self.spread_legs {
+            if leg1 == leg2 || leg1 == &self.symbol || leg2 == &self.symbol {
+                return Err(format!("{}: 无效的价差合约腿", self.symbol));
+            }
+        }
+        if let Some(band) = self.price_band {
+            let valid = |percent: f64| percent.is_finite() && (0.0..=100.0).contains(&percent);
+            if band.reference_price == 0 || !valid(band.limit_up_percent) || !valid(band.limit_down_percent) {
+                return Err(format!("{}: 无效的涨跌停幅度", self.symbol));
+            }
+        }
+        Ok(())
+    }
+}
+
+// 配置文件的格式：{"instruments": [ContractSpec, ...]}
+#[derive(Debug, Serialize, Deserialize)]
+struct InstrumentFile {
+    instruments: Vec<ContractSpec>,
+}
+
+// 按合约名索引的全部合约规格
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct InstrumentMaster {
+    specs: BTreeMap<String, ContractSpec>,
+}
+
+impl InstrumentMaster {
+    pub fn from_specs(specs: impl IntoIterator<Item = ContractSpec>) -> Result<Self, String> {
+        let mut master = InstrumentMaster::default();
+        for spec in specs {
+            spec.validate()?;
+            if master.specs.contains_key(&spec.symbol) {
+                return Err(format!("重复的合约: {}", spec.symbol));
+            }
+            master.specs.insert(spec.symbol.clone(), spec);
+        }
+        Ok(master)
+    }
+
+    pub fn parse_json(json: &str) -> Result<Self, String> {
+        let file: InstrumentFile = serde_json::from_str(json).map_err(|e| format!("无效的合约参考数据: {}", e))?;
+        Self::from_specs(file.instruments)
+    }
+
+    // source 以 http:// 开头时通过 HTTP GET 获取，否则作为文件路径读取
+    pub fn load(source: &str) -> Result<Self, String> {
+        let json = match source.strip_prefix("http://") {
+            Some(location) => http_get(location)?,
+            None => std::fs::read_to_string(source).map_err(|e| format!("无法读取 {}: {}", source, e))?,
+        };
+        Self::parse_json(&json)
+    }
+
+    pub fn get(&self, symbol: &str) -> Option<&ContractSpec> {
+        self.specs.get(symbol)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &ContractSpec> {
+        self.specs.values()
+    }
+
+    pub fn len(&self) -> usize {
+        self.specs.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.specs.is_empty()
+    }
+
+    // 各合约的价格精度，用于在对外接口上换算十进制价格
+    pub fn price_scales(&self) -> PriceScales {
+        PriceScales::new(self.specs.values().map(|spec| (spec.symbol.clone(), spec.price_exponent)))
+    }
+
+    // 为每个合约创建订单簿，设置最小变动价位和涨跌停价格带，并登记价差合约。未列出的合约仍按默认设置交易
+    pub fn configure(&self, mut engine: MatchingEngine) -> MatchingEngine {
+        for spec in self.specs.values() {
+            if let Some((leg1, leg2)) = &spec.spread_legs {
+                let definition = SpreadDefinition { symbol: spec.symbol.clone(), leg1: leg1.clone(), leg2: leg2.clone() };
+                engine = engine.with_spread(definition);
+                continue;
+            }
+            engine = engine.with_tick_size(&spec.symbol, spec.tick_size);
+            if let Some(band) = spec.price_band {
+                engine = engine.with_price_band(&spec.symbol, band.to_price_band());
+            }
+        }
+        engine
+    }
+}
+
+// 最简单的 HTTP/1.0 GET，只支持明文 HTTP 和非分块的响应体；location 为 "host:port/path"。
+// 标记价格采集也使用它轮询外部行情源
+pub fn http_get(location: &str) -> Result<String, String> {
+    let (host, path) = match location.find('/') {
+        Some(index) => (&location[..index], &location[index..]),
+        None => (location, "/"),
+    };
+    let address = if host.contains(':') { host.to_string() } else { format!("{}:80", host) };
+    let error = |e: std::io::Error| format!("请求 http://{} 失败: {}", location, e);
+
+    let mut stream = TcpStream::connect(&address).map_err(error)?;
+    stream.set_read_timeout(Some(HTTP_TIMEOUT)).map_err(error)?;
+    stream.set_write_timeout(Some(HTTP_TIMEOUT)).map_err(error)?;
+    // 请求一次写出，避免 write! 按格式化片段多次写入时对端只读到半个请求
+    let request = format!("GET {} HTTP/1.0\r\nHost: {}\r\nConnection: close\r\n\r\n", path, host);
+    stream.write_all(request.as_bytes()).map_err(error)?;
+    let mut response = String::new();
+    stream.read_to_string(&mut response).map_err(error)?;
+
+    let (head, body) = response.split_once("\r\n\r\n").ok_or_else(|| format!("http://{} 返回了无效的响应", location))?;
+    let status = head.lines().next().unwrap_or_default();
+    if status.split_whitespace().nth(1) != Some("200") {
+        return Err(format!("http://{} 返回 {}", location, status));
+    }
+    Ok(body.to_string())
+}
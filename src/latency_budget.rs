@@ -0,0 +1,150 @@
+// 延迟预算：为单条消息在流水线各阶段留下时间戳（面包屑），按阶段汇总到 HDR 直方图，
+// 尾延迟变差时可以定位到具体阶段。可以在运行时开关，关闭时网络层不创建面包屑，开销只是一次原子读
+use hdrhistogram::Histogram;
+use parking_lot::Mutex;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::Instant;
+
+const MAX_LATENCY_NANOS: u64 = 10_000_000_000;
+const LATENCY_SIGFIG: u8 = 3;
+
+// 相邻两个面包屑之间的阶段
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Stage {
+    // 收到完整的帧到解码完成
+    Decode,
+    // 解码完成到放入引擎命令通道，包括会话层的校验
+    Enqueue,
+    // 放入命令通道到撮合完成，包括在通道中排队的时间
+    Match,
+    // 撮合完成到本条命令的全部输出编码完成
+    Encode,
+    // 编码完成到全部输出写入来源连接的 socket，包括在出站队列中排队的时间
+    Send,
+    // 收到帧到全部输出写入 socket
+    Total,
+}
+
+impl Stage {
+    pub const ALL: [Stage; 6] = [Stage::Decode, Stage::Enqueue, Stage::Match, Stage::Encode, Stage::Send, Stage::Total];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Stage::Decode => "decode",
+            Stage::Enqueue => "enqueue",
+            Stage::Match => "match",
+            Stage::Encode => "encode",
+            Stage::Send => "send",
+            Stage::Total => "total",
+        }
+    }
+}
+
+// 一条客户端消息经过各阶段的时刻。网络层填写前三个，随命令进入引擎，
+// 引擎和广播任务依次补上 matched 和 encoded，来源连接发送完毕后按阶段记录
+#[derive(Debug, Clone)]
+pub struct Breadcrumb {
+    // 来源连接，只有它的发送时刻计入 Send 阶段
+    pub connection_id: u64,
+    pub received: Instant,
+    pub decoded: Instant,
+    pub enqueued: Instant,
+    pub matched: Option<Instant>,
+    pub encoded: Option<Instant>,
+}
+
+impl Breadcrumb {
+    pub fn new(connection_id: u64, received: Instant, decoded: Instant) -> Self {
+        Breadcrumb { connection_id, received, decoded, enqueued: decoded, matched: None, encoded: None }
+    }
+}
+
+// 某个阶段的耗时分布（纳秒）
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct StageSnapshot {
+    pub stage: Stage,
+    pub count: u64,
+    pub p50_ns: u64,
+    pub p99_ns: u64,
+    pub p999_ns: u64,
+    pub max_ns: u64,
+}
+
+#[derive(Debug)]
+pub struct LatencyBudget {
+    enabled: AtomicBool,
+    // 与 Stage::ALL 一一对应
+    stages: Vec<Mutex<Histogram<u64>>>,
+}
+
+impl Default for LatencyBudget {
+    fn default() -> Self {
+        let histogram = || Histogram::new_with_bounds(1, MAX_LATENCY_NANOS, LATENCY_SIGFIG).expect("直方图参数有效");
+        LatencyBudget {
+            enabled: AtomicBool::new(false),
+            stages: Stage::ALL.iter().map(|_| Mutex::new(histogram())).collect(),
+        }
+    }
+}
+
+impl LatencyBudget {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.enabled.load(Ordering::Relaxed)
+    }
+
+    // 关闭后已在途的面包屑仍会记录，新消息不再创建面包屑
+    pub fn set_enabled(&self, enabled: bool) {
+        self.enabled.store(enabled, Ordering::Relaxed);
+    }
+
+    // 来源连接发送完本条消息的全部输出后记录各阶段耗时；缺少的面包屑所在阶段不记录
+    pub fn record(&self, breadcrumb: &Breadcrumb, sent: Instant) {
+        let Breadcrumb { received, decoded, enqueued, matched, encoded, .. } = *breadcrumb;
+        self.record_stage(Stage::Decode, received, Some(decoded));
+        self.record_stage(Stage::Enqueue, decoded, Some(enqueued));
+        if let Some(matched) = matched {
+            self.record_stage(Stage::Match, enqueued, Some(matched));
+            self.record_stage(Stage::Encode, matched, encoded);
+        }
+        if let Some(encoded) = encoded {
+            self.record_stage(Stage::Send, encoded, Some(sent));
+        }
+        self.record_stage(Stage::Total, received, Some(sent));
+    }
+
+    fn record_stage(&self, stage: Stage, from: Instant, to: Option<Instant>) {
+        let Some(to) = to else { return };
+        let nanos = to.saturating_duration_since(from).as_nanos() as u64;
+        let index = Stage::ALL.iter().position(|&s| s == stage).expect("阶段在 Stage::ALL 中");
+        self.stages[index].lock().saturating_record(nanos.max(1));
+    }
+
+    pub fn snapshot(&self) -> Vec<StageSnapshot> {
+        Stage::ALL
+            .iter()
+            .zip(&self.stages)
+            .map(|(&stage, histogram)| {
+                let histogram = histogram.lock();
+                StageSnapshot {
+                    stage,
+                    count: histogram.len(),
+                    p50_ns: histogram.value_at_quantile(0.5),
+                    p99_ns: histogram.value_at_quantile(0.99),
+                    p999_ns: histogram.value_at_quantile(0.999),
+                    max_ns: histogram.max(),
+                }
+            })
+            .collect()
+    }
+
+    // 清空已记录的分布，例如在调整配置后重新开始观察
+    pub fn reset(&self) {
+        for histogram in &self.stages {
+            histogram.lock().reset();
+        }
+    }
+}
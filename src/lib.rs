@@ -1,5 +1,54 @@
 // 将所有模块声明为公共的，这样二进制文件、测试和基准测试都能访问它们
 pub mod protocol;
+pub mod error;
+pub mod id;
+pub mod sequencer;
+pub mod symbols;
 pub mod orderbook;
 pub mod engine;
 pub mod network;
+pub mod rx_timestamp;
+pub mod rate_limiter;
+pub mod priority_lanes;
+pub mod metrics;
+pub mod latency_budget;
+pub mod health;
+pub mod position;
+pub mod circuit_breaker;
+pub mod price;
+pub mod instruments;
+pub mod expiry;
+pub mod timer_wheel;
+pub mod mark_price;
+pub mod book_export;
+pub mod depth_view;
+pub mod replica;
+pub mod recent_cancels;
+pub mod auction;
+pub mod basket;
+pub mod spread;
+pub mod allocation;
+pub mod feature_flags;
+pub mod settlement;
+#[cfg(feature = "loadgen")]
+pub mod load_script;
+pub mod surveillance;
+pub mod market_data;
+pub mod session;
+pub mod gateway;
+pub mod sbe;
+pub mod codec;
+pub mod protobuf;
+pub mod workload;
+pub mod bench;
+pub mod replay;
+pub mod recovery;
+pub mod audit;
+pub mod replication;
+#[cfg(feature = "cluster")]
+pub mod cluster;
+pub mod verify;
+pub mod book_analysis;
+pub mod testing;
+pub mod harness;
+pub mod fault;
@@ -0,0 +1,96 @@
+use crate::protocol::OrderType;
+use rand::Rng;
+use rhai::{Array, Dynamic, Engine, Map, AST};
+use std::time::Duration;
+
+// 脚本看到的行情和客户端状态
+#[derive(Debug, Clone, Default)]
+pub struct MarketView {
+    // 第几次调用脚本，从 0 开始，可用于控制节奏
+    pub tick: u64,
+    // 最新成交价
+    pub last_price: Option<u64>,
+    // 本客户端已确认、尚未撤销的挂单
+    pub open_orders: Vec<u64>,
+}
+
+// 脚本请求执行的动作
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ScriptAction {
+    Order { order_type: OrderType, price: u64, quantity: u64 },
+    Cancel { order_id: u64 },
+    Sleep(Duration),
+}
+
+// 用 rhai 描述的压测场景。
+// 脚本需要定义 `fn next(ctx)`，ctx 包含 client、tick、last_price（无成交时为 ()）和 open_orders，
+// 返回由动作组成的数组，每个动作是一个对象：
+//   #{ type: "buy" | "sell", price: 50000, quantity: 1 }
+//   #{ type: "cancel", order_id: 42 }
+//   #{ type: "sleep", micros: 100 }
+// 脚本中可以使用 rand(lo, hi) 生成 [lo, hi] 范围内的随机整数
+pub struct LoadScript {
+    engine: Engine,
+    ast: AST,
+}
+
+impl LoadScript {
+    pub fn compile(source: &str) -> Result<Self, String> {
+        let mut engine = Engine::new();
+        // 默认的表达式嵌套深度在 debug 构建下过小，放宽到足以容纳常见的动作对象
+        engine.set_max_expr_depths(64, 64);
+        engine.register_fn("rand", |lo: i64, hi: i64| -> i64 {
+            if lo >= hi {
+                lo
+            } else {
+                rand::thread_rng().gen_range(lo..=hi)
+            }
+        });
+        let ast = engine.compile(source).map_err(|e| format!("脚本编译失败: {}", e))?;
+        Ok(LoadScript { engine, ast })
+    }
+
+    // 调用脚本的 next 函数，得到本轮要执行的动作
+    pub fn next_actions(&self, client_id: u32, view: &MarketView) -> Result<Vec<ScriptAction>, String> {
+        let mut ctx = Map::new();
+        ctx.insert("client".into(), Dynamic::from(client_id as i64));
+        ctx.insert("tick".into(), Dynamic::from(view.tick as i64));
+        ctx.insert(
+            "last_price".into(),
+            view.last_price.map_or(Dynamic::UNIT, |price| Dynamic::from(price as i64)),
+        );
+        let open_orders: Array = view.open_orders.iter().map(|&id| Dynamic::from(id as i64)).collect();
+        ctx.insert("open_orders".into(), Dynamic::from(open_orders));
+
+        let result: Array = self
+            .engine
+            .call_fn(&mut rhai::Scope::new(), &self.ast, "next", (ctx,))
+            .map_err(|e| format!("脚本执行失败: {}", e))?;
+        result.into_iter().map(parse_action).collect()
+    }
+}
+
+fn parse_action(value: Dynamic) -> Result<ScriptAction, String> {
+    let map = value.try_cast::<Map>().ok_or("动作必须是对象")?;
+    let number = |key: &str| -> Result<u64, String> {
+        map.get(key)
+            .and_then(|value| value.as_int().ok())
+            .filter(|value| *value >= 0)
+            .map(|value| value as u64)
+            .ok_or_else(|| format!("动作缺少非负整数字段 {}", key))
+    };
+    let kind = map
+        .get("type")
+        .and_then(|value| value.clone().into_string().ok())
+        .ok_or("动作缺少 type 字段")?;
+    match kind.as_str() {
+        "buy" | "sell" => Ok(ScriptAction::Order {
+            order_type: if kind == "buy" { OrderType::Buy } else { OrderType::Sell },
+            price: number("price")?,
+            quantity: number("quantity")?,
+        }),
+        "cancel" => Ok(ScriptAction::Cancel { order_id: number("order_id")? }),
+        "sleep" => Ok(ScriptAction::Sleep(Duration::from_micros(number("micros")?))),
+        other => Err(format!("未知的动作类型: {}", other)),
+    }
+}
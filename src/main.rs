@@ -1,51 +1,408 @@
 use std::net::SocketAddr;
+use std::sync::Arc;
 use std::thread;
-use std::time::Duration;
+use tokio::signal::unix::{signal, SignalKind};
 use tokio::sync::mpsc;
-use matching_engine::{engine, network};
+use matching_engine::{
+    audit, bench, book_export, circuit_breaker, depth_view, engine, expiry, feature_flags, gateway, health, instruments, mark_price, metrics,
+    network, price, priority_lanes, recovery, replica, replication, session, settlement, surveillance,
+};
+use std::time::Duration;
+use tracing_subscriber::fmt::format::FmtSpan;
+use tracing_subscriber::EnvFilter;
 
 #[tokio::main]
 async fn main() {
-    println!("程序启动 - main() 函数入口");
-    // 强制刷新，确保即使立即崩溃也能看到输出
-    use std::io::{self, Write};
-    io::stdout().flush().unwrap();
-
-    // 初始化日志
-    tracing_subscriber::fmt::init();
+    // 初始化日志。设置 MATCHING_ENGINE_TRACE_SPANS 时在每个 span 结束时输出耗时，
+    // 配合 RUST_LOG=matching_engine=debug 可以看到订单路径上解码、撮合、编码、发送各阶段的延迟
+    let span_events = match std::env::var_os("MATCHING_ENGINE_TRACE_SPANS") {
+        Some(_) => FmtSpan::CLOSE,
+        None => FmtSpan::NONE,
+    };
+    tracing_subscriber::fmt()
+        .with_env_filter(EnvFilter::from_default_env())
+        .with_span_events(span_events)
+        .init();
 
-    println!("日志系统已初始化");
+    // matching-engine bench [key=value ...]：运行内置基准测试后退出，不启动服务器
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    if args.first().map(String::as_str) == Some("bench") {
+        run_bench(&args[1..]);
+        return;
+    }
 
     // 创建用于网络层和引擎层通信的通道
     let (command_sender, command_receiver) = mpsc::unbounded_channel::<engine::EngineCommand>();
-    let (output_sender, output_receiver) = mpsc::unbounded_channel::<engine::EngineOutput>();
+    let (output_sender, mut output_receiver) = mpsc::unbounded_channel::<engine::EngineOutput>();
+
+    let mut engine = engine::MatchingEngine::new(command_receiver, output_sender);
+
+    // 合约参考数据：为列出的合约设置最小变动价位和涨跌停价格带，并在到期时间撤销挂单、停止交易
+    let mut price_scales = price::PriceScales::default();
+    if let Ok(source) = std::env::var("MATCHING_ENGINE_INSTRUMENTS") {
+        let master = instruments::InstrumentMaster::load(&source).unwrap_or_else(|e| panic!("{}", e));
+        println!("已加载 {} 个合约的参考数据", master.len());
+        engine = master.configure(engine);
+        expiry::spawn_expiry_timer(&master, command_sender.clone());
+        price_scales = master.price_scales();
+    }
+    // 引擎时钟，驱动 GTD 订单到期撤销
+    expiry::spawn_clock(command_sender.clone(), expiry::CLOCK_INTERVAL);
+
+    // 功能开关的初始配置，运行期间可以通过控制命令调整
+    if let Ok(features) = std::env::var("MATCHING_ENGINE_FEATURES") {
+        let flags = feature_flags::FeatureFlags::parse(&features).expect("无效的功能开关配置");
+        engine = engine.with_feature_flags(flags);
+    }
+
+    // 标记价格：按合约轮询 HTTP 行情源，或监听 UDP 推送；配置了价格保护时拒绝限价偏离标记价格过大的订单
+    if let Ok(spec) = std::env::var("MATCHING_ENGINE_MARK_PRICE_SOURCES") {
+        let config = mark_price::MarkPriceFeedConfig::parse_sources(&spec, Duration::from_secs(1))
+            .unwrap_or_else(|e| panic!("{}", e))
+            .with_price_scales(price_scales.clone());
+        mark_price::spawn_http_poller(config, command_sender.clone());
+    }
+    if let Ok(addr) = std::env::var("MATCHING_ENGINE_MARK_PRICE_UDP") {
+        let socket = std::net::UdpSocket::bind(&addr).expect("无法监听标记价格 UDP 地址");
+        mark_price::spawn_udp_listener(socket, price_scales.clone(), command_sender.clone())
+            .expect("无法启动标记价格接收");
+    }
+    if let Ok(bps) = std::env::var("MATCHING_ENGINE_PRICE_COLLAR_BPS") {
+        let collar_bps = bps.parse().expect("无效的价格保护幅度");
+        engine = engine.with_price_collar(circuit_breaker::PriceCollar { collar_bps });
+    }
+    // 撤单、改单的优先车道，格式为 优先权重:普通权重，例如 4:1
+    if let Ok(spec) = std::env::var("MATCHING_ENGINE_PRIORITY_LANES") {
+        let weights = priority_lanes::LaneWeights::parse(&spec).unwrap_or_else(|e| panic!("{}", e));
+        engine = engine.with_priority_lanes(weights);
+    }
+    if let Ok(limit) = std::env::var("MATCHING_ENGINE_SYMBOL_LIMIT") {
+        engine = engine.with_symbol_limit(limit.parse().expect("无效的合约数上限"));
+    }
+    // 查询线程读取引擎定期发布的一致深度视图，不经过命令队列；MATCHING_ENGINE_DEPTH_VIEW_EVERY 为发布间隔的命令数
+    if let Ok(addr) = std::env::var("MATCHING_ENGINE_DEPTH_VIEW_ADDR") {
+        let mut config = depth_view::DepthViewConfig::default();
+        if let Ok(every) = std::env::var("MATCHING_ENGINE_DEPTH_VIEW_EVERY") {
+            config.every_commands = every.parse().expect("无效的深度视图发布间隔");
+        }
+        engine = engine.with_depth_view(config);
+        let listener = std::net::TcpListener::bind(&addr).expect("无法监听深度查询地址");
+        depth_view::spawn_query_server(listener, engine.depth_view().expect("已开启深度视图"));
+    }
+    // 副本订单簿：消费逐笔行情流在撮合线程之外维护订单簿副本，按间隔（命令数）核对校验和，不一致时写入日志
+    if let Ok(every) = std::env::var("MATCHING_ENGINE_L3_REPLICA") {
+        let (feed, receiver) = std::sync::mpsc::channel();
+        replica::spawn_replica(receiver);
+        engine = engine.with_l3_feed(feed, every.parse().expect("无效的校验和间隔"));
+    }
+    // 批量发送引擎输出，值为每批的输出数上限；设置 MATCHING_ENGINE_OUTPUT_BATCH_UNTIL_IDLE 时积压期间跨命令合批
+    if let Ok(max_outputs) = std::env::var("MATCHING_ENGINE_OUTPUT_BATCH") {
+        engine = engine.with_output_batching(engine::OutputBatching {
+            max_outputs: max_outputs.parse().expect("无效的批量输出数"),
+            until_idle: std::env::var_os("MATCHING_ENGINE_OUTPUT_BATCH_UNTIL_IDLE").is_some(),
+        });
+    }
+    // 市场行为检测：对敲（按实益拥有人，格式为 用户:拥有人,...）、自成交，以及可选的报单轰炸（每秒消息数上限）
+    let owners = std::env::var("MATCHING_ENGINE_BENEFICIAL_OWNERS").ok();
+    let stuffing_limit = std::env::var("MATCHING_ENGINE_QUOTE_STUFFING").ok();
+    if std::env::var_os("MATCHING_ENGINE_MARKET_SURVEILLANCE").is_some() || owners.is_some() || stuffing_limit.is_some() {
+        let owners = owners.map(|spec| surveillance::WashTradeDetector::parse_owners(&spec).unwrap_or_else(|e| panic!("{}", e)));
+        let mut detectors: Vec<Box<dyn surveillance::Detector>> = vec![
+            Box::new(surveillance::WashTradeDetector::new(owners.unwrap_or_default())),
+            Box::new(surveillance::SelfCrossDetector),
+        ];
+        if let Some(limit) = stuffing_limit {
+            let limit = limit.parse().expect("无效的报单轰炸阈值");
+            detectors.push(Box::new(surveillance::QuoteStuffingDetector::new(limit, Duration::from_secs(1))));
+        }
+        let (alert_sender, alert_receiver) = std::sync::mpsc::channel();
+        let (tap, _) = surveillance::spawn_market_detectors(detectors, engine.metrics(), alert_sender);
+        engine = engine.with_surveillance_tap(tap);
+        thread::spawn(move || {
+            for alert in alert_receiver {
+                tracing::warn!(
+                    detector = alert.detector,
+                    symbol = %alert.symbol,
+                    users = ?alert.user_ids,
+                    detail = %alert.detail,
+                    "市场行为检测告警"
+                );
+            }
+        });
+    }
+
+    // 网络层的指标；延迟预算也可以在运行时通过指标端口开关
+    let outbound_metrics = Arc::new(metrics::OutboundMetrics::new());
+    if std::env::var_os("MATCHING_ENGINE_LATENCY_BUDGET").is_some() {
+        outbound_metrics.latency_budget.set_enabled(true);
+    }
+
+    // 配置了 statsd 地址时，主动推送指标
+    if let Ok(statsd_addr) = std::env::var("MATCHING_ENGINE_STATSD_ADDR") {
+        let statsd_addr: SocketAddr = statsd_addr.parse().expect("无效的 statsd 地址");
+        let config = metrics::StatsdConfig::new(statsd_addr);
+        metrics::spawn_statsd_exporter(engine.metrics(), outbound_metrics.clone(), config)
+            .expect("无法启动 statsd 推送");
+    }
+
+    // 健康状态由引擎线程和网络服务器在启动、退出时更新
+    let health = Arc::new(health::HealthChecker::new(
+        engine.metrics(),
+        outbound_metrics.clone(),
+        health::HealthConfig::default(),
+    ));
 
-    println!("通道已创建");
+    // 配置了监听地址时，在 /metrics 上提供 Prometheus 抓取，在 /healthz、/readyz 上提供探针
+    if let Ok(metrics_addr) = std::env::var("MATCHING_ENGINE_METRICS_ADDR") {
+        let listener = std::net::TcpListener::bind(&metrics_addr).expect("无法监听指标地址");
+        metrics::spawn_prometheus_exporter(listener, engine.metrics(), outbound_metrics.clone(), Some(health.clone()));
+    }
+
+    // 成交监控：规则通过 ControlCommand::AddSurveillanceRule 注册，在独立线程中评估，告警写入日志
+    let (alert_sender, alert_receiver) = std::sync::mpsc::channel();
+    surveillance::spawn_surveillance_monitor(engine.surveillance(), Duration::from_millis(100), alert_sender);
+    thread::spawn(move || {
+        for alert in alert_receiver {
+            tracing::warn!(
+                rule = %alert.rule,
+                symbol = %alert.symbol,
+                observed = alert.observed,
+                threshold = alert.threshold,
+                "成交监控规则触发"
+            );
+        }
+    });
+
+    // 配置了导出路径时，定期将各合约的深度快照写入 ndjson 文件，关闭前再导出一次最终状态
+    let book_export = std::env::var("MATCHING_ENGINE_BOOK_EXPORT").ok().map(|path| book_export::BookExportConfig {
+        path: path.into(),
+        interval: Duration::from_secs(1),
+        depth: 10,
+        largest_orders: 5,
+    });
+    if let Some(config) = &book_export {
+        book_export::spawn_book_exporter(command_sender.clone(), config.clone()).expect("无法启动深度快照导出");
+    }
+
+    // 配置了恢复目录时，启动前加载最新快照并重放命令日志，之后的命令先写日志再处理，并定期写检查点。
+    // 日志序号有缺口时拒绝启动，确认可以接受丢失的命令后设置 MATCHING_ENGINE_RECOVERY_ALLOW_GAPS 放行
+    let recovery_dir = std::env::var("MATCHING_ENGINE_RECOVERY_DIR").ok().map(std::path::PathBuf::from);
+    if let Some(dir) = &recovery_dir {
+        let config = recovery::RecoveryConfig {
+            allow_gaps: std::env::var_os("MATCHING_ENGINE_RECOVERY_ALLOW_GAPS").is_some(),
+            sync: std::env::var_os("MATCHING_ENGINE_RECOVERY_FSYNC").is_some(),
+            // 记录每条命令产生的成交，之后可以用 verify 工具校验撮合的确定性
+            record_outputs: std::env::var_os("MATCHING_ENGINE_OUTPUT_JOURNAL").is_some(),
+            ..recovery::RecoveryConfig::new(dir)
+        };
+        if let Ok(primary) = std::env::var("MATCHING_ENGINE_STANDBY_OF") {
+            // 热备机：跟随主机的命令日志，主机连续错过心跳后提升为主机，继续下面的启动流程
+            let standby = replication::StandbyConfig {
+                recovery: config,
+                ..replication::StandbyConfig::new(primary.parse().expect("无效的主机复制地址"), dir)
+            };
+            println!("以备机身份运行，跟随主机 {}", standby.primary);
+            let (promoted, report) =
+                tokio::task::block_in_place(|| replication::run_standby(&standby, engine, &mut output_receiver))
+                    .unwrap_or_else(|e| panic!("备机恢复失败: {}", e));
+            engine = promoted;
+            println!(
+                "备机提升为主机: 应用 {} 条复制记录，日志序号 {}（主机最后报告 {}）",
+                report.applied, report.last_seq, report.primary_last_seq
+            );
+        } else {
+            let (recovered, report) =
+                recovery::recover(&config, engine).unwrap_or_else(|e| panic!("恢复失败，拒绝启动: {}", e));
+            engine = recovered;
+            // 重放产生的回报在崩溃前已经发出过
+            while output_receiver.try_recv().is_ok() {}
+            println!(
+                "已恢复: 快照序号 {:?}，重放 {} 条命令，日志序号 {}，缺口 {:?}",
+                report.snapshot_seq, report.replayed, report.last_seq, report.gaps
+            );
+        }
+        let interval = match std::env::var("MATCHING_ENGINE_CHECKPOINT_INTERVAL") {
+            Ok(secs) => Duration::from_secs(secs.parse().expect("无效的检查点间隔秒数")),
+            Err(_) => Duration::from_secs(60),
+        };
+        recovery::spawn_checkpointer(command_sender.clone(), dir.clone(), interval);
+        // 向热备机发送命令日志
+        if let Ok(addr) = std::env::var("MATCHING_ENGINE_REPLICATION_ADDR") {
+            let listener = std::net::TcpListener::bind(&addr).expect("无法监听复制地址");
+            let config = replication::ReplicationConfig { dir: dir.clone(), heartbeat_interval: Duration::from_millis(100) };
+            replication::spawn_replication_server(listener, config).expect("无法启动复制服务");
+        }
+    }
+
+    // 每天在 MATCHING_ENGINE_END_OF_DAY（UTC 的 HH:MM，默认 00:00）执行日终，取走当日成交。
+    // 配置 MATCHING_ENGINE_SETTLEMENT_DIR 时写出结算文件，否则丢弃，引擎中的成交记录不会无限增长
+    let cutoff = match std::env::var("MATCHING_ENGINE_END_OF_DAY") {
+        Ok(time) => parse_time_of_day(&time).expect("无效的日终时刻，格式为 HH:MM"),
+        Err(_) => Duration::ZERO,
+    };
+    let settlement = std::env::var("MATCHING_ENGINE_SETTLEMENT_DIR").ok().map(|dir| settlement::SettlementConfig {
+        dir: dir.into(),
+        fee_bps: std::env::var("MATCHING_ENGINE_SETTLEMENT_FEE_BPS").map_or(0, |bps| bps.parse().expect("无效的手续费基点")),
+    });
+    settlement::spawn_end_of_day_scheduler(command_sender.clone(), settlement, cutoff);
+
+    // 收到关闭信号后等待引擎排空、回报发完的最长时间
+    let shutdown_timeout = match std::env::var("MATCHING_ENGINE_SHUTDOWN_TIMEOUT") {
+        Ok(secs) => Duration::from_secs(secs.parse().expect("无效的关闭超时秒数")),
+        Err(_) => Duration::from_secs(10),
+    };
 
     // 在一个独立的系统线程中运行撮合引擎
-    // let engine_thread = thread::spawn(move || {
-    //     let mut engine = engine::MatchingEngine::new(command_receiver, output_sender);
-    //     engine.run();
-    // });
+    let engine_health = health.clone();
+    let engine_thread = thread::spawn(move || {
+        let _running = engine_health.engine_running();
+        engine.run();
+    });
 
-    println!("撮合引擎线程已启动");
+    // 配置了 API 凭证时要求客户端先登录，否则不做身份校验
+    let sessions = match std::env::var("MATCHING_ENGINE_API_KEYS") {
+        Ok(spec) => {
+            let credentials = session::SessionConfig::parse_credentials(&spec).expect("无效的 API 凭证配置");
+            session::SessionConfig::with_credentials(credentials)
+        }
+        Err(_) => {
+            eprintln!("未配置 MATCHING_ENGINE_API_KEYS，客户端无需登录");
+            session::SessionConfig::default()
+        }
+    };
+    // 配置了网关日志时为执行回报分配序号，客户端重连登录后可以补发错过的回报
+    let sessions = match std::env::var("MATCHING_ENGINE_GATEWAY_JOURNAL") {
+        Ok(path) => {
+            let config = gateway::GatewayConfig { journal_path: Some(path.into()), ..Default::default() };
+            let gateway = gateway::Gateway::open(config).expect("无法打开网关日志");
+            session::SessionConfig { gateway: Some(Arc::new(gateway)), ..sessions }
+        }
+        Err(_) => sessions,
+    };
+    // 配置了审计目录时把每个订单事件写入每日 CSV 文件，可选地提供按订单号查询的 HTTP 接口
+    let sessions = match std::env::var("MATCHING_ENGINE_AUDIT_DIR") {
+        Ok(dir) => {
+            let audit = audit::AuditTrail::spawn(audit::AuditConfig { dir: dir.clone().into() }).expect("无法创建审计目录");
+            if let Ok(query_addr) = std::env::var("MATCHING_ENGINE_AUDIT_QUERY_ADDR") {
+                let listener = std::net::TcpListener::bind(&query_addr).expect("无法监听审计查询地址");
+                audit::spawn_query_server(listener, dir.into());
+            }
+            session::SessionConfig { audit: Some(Arc::new(audit)), ..sessions }
+        }
+        Err(_) => sessions,
+    };
+    // 开启后订单携带内核或网卡的接收时间戳，用于测量线上到撮合完成的耗时
+    let sessions = session::SessionConfig {
+        rx_timestamps: std::env::var_os("MATCHING_ENGINE_RX_TIMESTAMPS").is_some(),
+        ..sessions
+    };
 
     // 在 Tokio 运行时中启动网络服务器
-    // let addr: SocketAddr = "127.0.0.1:8080".parse().unwrap();
-    // let server_handle = tokio::spawn(network::run_server(addr, command_sender, output_receiver));
+    let addr: SocketAddr = "127.0.0.1:8080".parse().unwrap();
+    let listener = tokio::net::TcpListener::bind(&addr).await.expect("无法绑定地址");
+    println!("服务器正在监听: {}", addr);
+    health.set_listener_bound(true);
+    let (stop_accepting, stop_accepting_rx) = tokio::sync::oneshot::channel::<()>();
+    let server_command_sender = command_sender.clone();
+    let mut server_handle = tokio::spawn(async move {
+        let shutdown = async {
+            let _ = stop_accepting_rx.await;
+        };
+        network::serve_until(
+            listener,
+            server_command_sender,
+            output_receiver,
+            sessions,
+            network::OutboundConfig::default(),
+            outbound_metrics,
+            shutdown,
+        )
+        .await;
+    });
 
-    println!("网络服务器任务已启动");
+    tokio::select! {
+        result = &mut server_handle => {
+            health.set_listener_bound(false);
+            if let Err(e) = result {
+                eprintln!("网络服务器任务出现严重错误: {:?}", e);
+            }
+            engine_thread.join().expect("撮合引擎线程崩溃");
+            return;
+        }
+        _ = shutdown_signal() => {}
+    }
 
-    // 引入延迟，以便观察进程状态和日志文件
-    println!("进入2秒休眠...");
-    thread::sleep(Duration::from_secs(2));
-    println!("休眠结束");
+    // 有序关闭：停止接受新连接，保存最终的订单簿快照，让引擎处理完已排队的命令后退出；
+    // 引擎退出后每个连接收到 Shutdown 通知，发完剩余回报后断开
+    println!("收到关闭信号，开始关闭（最长等待 {:?}）", shutdown_timeout);
+    let _ = stop_accepting.send(());
+    health.set_listener_bound(false);
+    if let Some(config) = book_export {
+        let sender = command_sender.clone();
+        match tokio::task::spawn_blocking(move || book_export::export_once(&sender, &config)).await {
+            Ok(Ok(count)) => println!("已导出 {} 个合约的最终深度快照", count),
+            Ok(Err(e)) => eprintln!("导出最终深度快照失败: {}", e),
+            Err(e) => eprintln!("导出最终深度快照失败: {:?}", e),
+        }
+    }
+    if let Some(dir) = recovery_dir {
+        let sender = command_sender.clone();
+        match tokio::task::spawn_blocking(move || recovery::checkpoint_once(&sender, &dir)).await {
+            Ok(Ok(seq)) => println!("已写入最终恢复快照，日志序号 {:?}", seq),
+            Ok(Err(e)) => eprintln!("写入最终恢复快照失败: {}", e),
+            Err(e) => eprintln!("写入最终恢复快照失败: {:?}", e),
+        }
+    }
+    let _ = command_sender.send(engine::EngineCommand::Control(engine::ControlCommand::Drain));
+    drop(command_sender);
 
-    // 等待服务器任务结束
-    // if let Err(e) = server_handle.await {
-    //     eprintln!("网络服务器任务出现严重错误: {:?}", e);
-    // }
+    match tokio::time::timeout(shutdown_timeout, server_handle).await {
+        Ok(_) => {
+            engine_thread.join().expect("撮合引擎线程崩溃");
+            println!("服务器已关闭");
+        }
+        Err(_) => {
+            eprintln!("关闭超时，仍有连接未断开，强制退出");
+            std::process::exit(1);
+        }
+    }
+}
+
+// 等待 SIGTERM（Kubernetes 停止 Pod 时发送）或 Ctrl-C
+async fn shutdown_signal() {
+    let mut terminate = signal(SignalKind::terminate()).expect("无法注册 SIGTERM 处理");
+    tokio::select! {
+        _ = tokio::signal::ctrl_c() => {}
+        _ = terminate.recv() => {}
+    }
+}
+
+// HH:MM 形式的时刻，返回距零点的时长
+fn parse_time_of_day(time: &str) -> Option<Duration> {
+    let (hours, minutes) = time.split_once(':')?;
+    let (hours, minutes): (u64, u64) = (hours.parse().ok()?, minutes.parse().ok()?);
+    (hours < 24 && minutes < 60).then(|| Duration::from_secs(hours * 3_600 + minutes * 60))
+}
 
-    // 等待引擎线程结束（虽然在当前设计中它是一个无限循环）
-    // engine_thread.join().expect("撮合引擎线程崩溃");
+// 使用与服务器相同的功能开关和合约参考数据运行基准测试
+fn run_bench(args: &[String]) {
+    let config = bench::BenchConfig::parse(args.iter().map(String::as_str)).unwrap_or_else(|e| panic!("{}", e));
+    let profile = &config.profile;
+    println!("基准测试: {} 个用户, 合约 {}, 持续 {:?}", profile.connections, profile.symbols.join(", "), profile.duration);
+    let flags = std::env::var("MATCHING_ENGINE_FEATURES")
+        .ok()
+        .map(|features| feature_flags::FeatureFlags::parse(&features).expect("无效的功能开关配置"));
+    let master = std::env::var("MATCHING_ENGINE_INSTRUMENTS")
+        .ok()
+        .map(|source| instruments::InstrumentMaster::load(&source).unwrap_or_else(|e| panic!("{}", e)));
+    let report = bench::run(&config, |mut engine| {
+        if let Some(flags) = flags {
+            engine = engine.with_feature_flags(flags);
+        }
+        match master {
+            Some(master) => master.configure(engine),
+            None => engine,
+        }
+    });
+    report.print();
 }
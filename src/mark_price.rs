@@ -0,0 +1,153 @@
+// 标记价格和指数价格采集：按合约轮询 HTTP 接口，或监听 UDP 推送，
+// 将采集到的价格通过 ControlCommand::UpdateMarkPrice 交给引擎，用于价格保护并广播给客户端
+use crate::engine::{ControlCommand, EngineCommand};
+use crate::instruments::http_get;
+use crate::price::{Price, PriceScales};
+use serde::Deserialize;
+use std::net::UdpSocket;
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
+use tokio::sync::mpsc::UnboundedSender;
+
+// UDP 监听等待数据时检查引擎是否已关闭的间隔
+const UDP_POLL_INTERVAL: Duration = Duration::from_millis(500);
+
+// 行情源返回的一次报价。HTTP 响应体为 {"mark_price": ..., "index_price": ...}；
+// UDP 数据报另外带上合约名 {"symbol": ..., "mark_price": ..., "index_price": ...}。
+// 价格是十进制字符串（如 "50123.45"）或整数，按合约的价格精度换算为整数价格
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MarkPriceQuote {
+    pub symbol: Option<String>,
+    pub mark_price: u64,
+    pub index_price: u64,
+}
+
+#[derive(Deserialize)]
+struct RawQuote {
+    #[serde(default)]
+    symbol: Option<String>,
+    mark_price: Price,
+    index_price: Price,
+}
+
+impl MarkPriceQuote {
+    // 按价格精度 0 解析，即价格就是整数价格
+    pub fn parse_json(json: &str) -> Result<Self, String> {
+        Self::parse_scaled(json, &PriceScales::default(), None)
+    }
+
+    // symbol 为 HTTP 来源对应的合约，为 None 时按报价自带的合约名确定价格精度
+    pub fn parse_scaled(json: &str, scales: &PriceScales, symbol: Option<&str>) -> Result<Self, String> {
+        let raw: RawQuote = serde_json::from_str(json).map_err(|e| format!("无效的标记价格: {}", e))?;
+        let exponent = scales.exponent(symbol.or(raw.symbol.as_deref()).unwrap_or_default());
+        let ticks = |price: Price| price.to_ticks(exponent).map_err(|e| format!("无效的标记价格: {}", e));
+        let quote = MarkPriceQuote {
+            mark_price: ticks(raw.mark_price)?,
+            index_price: ticks(raw.index_price)?,
+            symbol: raw.symbol,
+        };
+        if quote.mark_price == 0 || quote.index_price == 0 {
+            return Err("标记价格和指数价格必须大于 0".to_string());
+        }
+        Ok(quote)
+    }
+}
+
+// 按合约轮询的 HTTP 行情源
+#[derive(Debug, Clone)]
+pub struct MarkPriceFeedConfig {
+    // (合约, "host:port/path")
+    pub sources: Vec<(String, String)>,
+    pub poll_interval: Duration,
+    // 各合约的价格精度，未配置时报价按整数价格处理
+    pub price_scales: PriceScales,
+}
+
+impl MarkPriceFeedConfig {
+    // 解析 "合约=http://host:port/path"，多个合约以逗号分隔
+    pub fn parse_sources(spec: &str, poll_interval: Duration) -> Result<Self, String> {
+        let mut sources = Vec::new();
+        for entry in spec.split(',').map(str::trim).filter(|entry| !entry.is_empty()) {
+            let (symbol, url) = entry.split_once('=').ok_or_else(|| format!("无效的标记价格来源: {}", entry))?;
+            let location = url.strip_prefix("http://").ok_or_else(|| format!("只支持 http:// 地址: {}", entry))?;
+            sources.push((symbol.to_string(), location.to_string()));
+        }
+        if sources.is_empty() {
+            return Err("未配置标记价格来源".to_string());
+        }
+        Ok(MarkPriceFeedConfig { sources, poll_interval, price_scales: PriceScales::default() })
+    }
+
+    pub fn with_price_scales(mut self, price_scales: PriceScales) -> Self {
+        self.price_scales = price_scales;
+        self
+    }
+}
+
+// 启动后台线程，按固定间隔依次轮询每个合约的行情源；单个来源失败时记录日志并继续，引擎关闭后退出
+pub fn spawn_http_poller(config: MarkPriceFeedConfig, command_sender: UnboundedSender<EngineCommand>) -> JoinHandle<()> {
+    thread::spawn(move || loop {
+        for (symbol, location) in &config.sources {
+            let quote = http_get(location)
+                .and_then(|body| MarkPriceQuote::parse_scaled(&body, &config.price_scales, Some(symbol)));
+            match quote {
+                Ok(quote) => {
+                    if !send_update(&command_sender, symbol.clone(), &quote) {
+                        return;
+                    }
+                }
+                Err(e) => tracing::warn!(symbol = %symbol, error = %e, "采集标记价格失败"),
+            }
+        }
+        if command_sender.is_closed() {
+            return;
+        }
+        thread::sleep(config.poll_interval);
+    })
+}
+
+// 启动后台线程，接收 UDP 推送的报价；格式错误或缺少合约名的数据报被丢弃，引擎关闭后退出
+pub fn spawn_udp_listener(
+    socket: UdpSocket,
+    price_scales: PriceScales,
+    command_sender: UnboundedSender<EngineCommand>,
+) -> std::io::Result<JoinHandle<()>> {
+    socket.set_read_timeout(Some(UDP_POLL_INTERVAL))?;
+    Ok(thread::spawn(move || {
+        let mut buffer = [0u8; 1024];
+        while !command_sender.is_closed() {
+            let len = match socket.recv(&mut buffer) {
+                Ok(len) => len,
+                Err(e) if matches!(e.kind(), std::io::ErrorKind::WouldBlock | std::io::ErrorKind::TimedOut) => continue,
+                Err(e) => {
+                    tracing::warn!(error = %e, "接收标记价格失败");
+                    continue;
+                }
+            };
+            let quote = std::str::from_utf8(&buffer[..len])
+                .map_err(|e| e.to_string())
+                .and_then(|json| MarkPriceQuote::parse_scaled(json, &price_scales, None));
+            match quote {
+                Ok(quote) => match quote.symbol.clone().filter(|symbol| !symbol.is_empty()) {
+                    Some(symbol) => {
+                        if !send_update(&command_sender, symbol, &quote) {
+                            return;
+                        }
+                    }
+                    None => tracing::warn!("标记价格数据报缺少合约名"),
+                },
+                Err(e) => tracing::warn!(error = %e, "丢弃无效的标记价格数据报"),
+            }
+        }
+    }))
+}
+
+// 引擎已关闭时返回 false
+fn send_update(command_sender: &UnboundedSender<EngineCommand>, symbol: String, quote: &MarkPriceQuote) -> bool {
+    let command = ControlCommand::UpdateMarkPrice {
+        symbol,
+        mark_price: quote.mark_price,
+        index_price: quote.index_price,
+    };
+    command_sender.send(EngineCommand::Control(command)).is_ok()
+}
@@ -0,0 +1,133 @@
+use crate::protocol::{Candle, TradeNotification};
+use std::collections::{HashMap, VecDeque};
+use std::time::Duration;
+
+// 每个周期默认保留的 K 线根数
+pub const DEFAULT_CANDLE_HISTORY: usize = 1_440;
+
+// 单个合约的成交行情
+#[derive(Debug, Clone, Default)]
+pub struct SymbolMarketData {
+    pub last_price: Option<u64>,
+    // 当日最高价、最低价和成交量，日终时清零
+    pub high: Option<u64>,
+    pub low: Option<u64>,
+    pub volume: u64,
+    // 当日成交笔数和成交金额（价格 × 数量），含大宗交易
+    pub trade_count: u64,
+    pub turnover: u128,
+    // 计算 VWAP 用的当日撮合成交金额和数量，不含大宗交易
+    matched_notional: u128,
+    matched_volume: u64,
+    // 与 MarketData::intervals 一一对应的 K 线序列，最新的在末尾
+    candles: Vec<VecDeque<Candle>>,
+}
+
+impl SymbolMarketData {
+    // 当日撮合成交的成交量加权均价，尚无撮合成交时为 None
+    pub fn vwap(&self) -> Option<f64> {
+        (self.matched_volume > 0).then(|| self.matched_notional as f64 / self.matched_volume as f64)
+    }
+
+    pub fn candles(&self, index: usize) -> impl Iterator<Item = &Candle> {
+        self.candles.get(index).into_iter().flatten()
+    }
+}
+
+// 逐笔成交行情汇总：最新价、当日高低价、成交量、笔数、成交金额和 VWAP，以及按配置周期滚动的 K 线。
+// 大宗交易计入当日成交量、笔数和成交金额，不影响价格、VWAP 和 K 线
+#[derive(Debug, Clone)]
+pub struct MarketData {
+    // K 线周期（纳秒）
+    intervals: Vec<u64>,
+    history: usize,
+    symbols: HashMap<String, SymbolMarketData>,
+}
+
+impl Default for MarketData {
+    fn default() -> Self {
+        Self::new(&[Duration::from_secs(60)], DEFAULT_CANDLE_HISTORY)
+    }
+}
+
+impl MarketData {
+    pub fn new(intervals: &[Duration], history: usize) -> Self {
+        assert!(intervals.iter().all(|interval| !interval.is_zero()), "K 线周期必须大于 0");
+        MarketData {
+            intervals: intervals.iter().map(|interval| interval.as_nanos() as u64).collect(),
+            history,
+            symbols: HashMap::new(),
+        }
+    }
+
+    pub fn on_trade(&mut self, trade: &TradeNotification) {
+        let data = self.symbols.entry(trade.symbol.to_string()).or_default();
+        let notional = trade.matched_price as u128 * trade.matched_quantity as u128;
+        data.volume += trade.matched_quantity;
+        data.trade_count += 1;
+        data.turnover += notional;
+        if trade.is_block_trade {
+            return;
+        }
+
+        let price = trade.matched_price;
+        data.matched_notional += notional;
+        data.matched_volume += trade.matched_quantity;
+        data.last_price = Some(price);
+        data.high = Some(data.high.map_or(price, |high| high.max(price)));
+        data.low = Some(data.low.map_or(price, |low| low.min(price)));
+
+        data.candles.resize_with(self.intervals.len(), VecDeque::new);
+        for (series, &interval) in data.candles.iter_mut().zip(&self.intervals) {
+            let start = trade.timestamp - trade.timestamp % interval;
+            match series.back_mut() {
+                Some(candle) if candle.start == start => {
+                    candle.high = candle.high.max(price);
+                    candle.low = candle.low.min(price);
+                    candle.close = price;
+                    candle.volume += trade.matched_quantity;
+                }
+                // 定序组件保证时间戳严格递增，不会出现早于当前 K 线的成交
+                _ => {
+                    if series.len() == self.history {
+                        series.pop_front();
+                    }
+                    series.push_back(Candle {
+                        start,
+                        open: price,
+                        high: price,
+                        low: price,
+                        close: price,
+                        volume: trade.matched_quantity,
+                    });
+                }
+            }
+        }
+    }
+
+    pub fn symbol(&self, symbol: &str) -> Option<&SymbolMarketData> {
+        self.symbols.get(symbol)
+    }
+
+    // 指定周期的最近 limit 根 K 线，从旧到新；周期未配置时返回空
+    pub fn recent_candles(&self, symbol: &str, interval: Duration, limit: usize) -> Vec<Candle> {
+        let interval = interval.as_nanos() as u64;
+        let (Some(index), Some(data)) = (self.intervals.iter().position(|&i| i == interval), self.symbols.get(symbol))
+        else {
+            return Vec::new();
+        };
+        let candles: Vec<Candle> = data.candles(index).copied().collect();
+        candles[candles.len().saturating_sub(limit)..].to_vec()
+    }
+
+    // 日终清零当日统计，最新价和 K 线保留
+    pub fn reset_daily(&mut self) {
+        for data in self.symbols.values_mut() {
+            *data = SymbolMarketData {
+                last_price: data.last_price,
+                candles: std::mem::take(&mut data.candles),
+                ..SymbolMarketData::default()
+            };
+        }
+    }
+}
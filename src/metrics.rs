@@ -0,0 +1,531 @@
+use crate::health::HealthChecker;
+use crate::latency_budget::LatencyBudget;
+use parking_lot::RwLock;
+use std::collections::{BTreeMap, HashMap, VecDeque};
+use std::fmt::Write as _;
+use std::io::{Read, Write};
+use std::net::{SocketAddr, TcpListener, TcpStream, UdpSocket};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
+
+// 引擎运行时指标，使用原子计数器以便在引擎线程之外读取
+#[derive(Debug, Default)]
+pub struct EngineMetrics {
+    // 被限流器放行的订单数
+    pub orders_accepted: AtomicU64,
+    // 被限流器拒绝的订单数
+    pub orders_throttled: AtomicU64,
+    // 已发布的成交笔数（含大宗交易）
+    pub trades_executed: AtomicU64,
+    // 订单簿节点池的压缩次数
+    pub pool_compactions: AtomicU64,
+    // 压缩累计回收的节点槽位数
+    pub pool_slots_reclaimed: AtomicU64,
+    // 引擎取出上一条命令时命令通道中仍在排队的命令数
+    pub command_queue_depth: AtomicU64,
+    // 合约名驻留池的大小和累计统计，引擎每次驻留或回收后更新，见 SymbolPool::stats
+    pub symbol_pool_size: AtomicU64,
+    pub symbol_pool_hits: AtomicU64,
+    pub symbol_pool_misses: AtomicU64,
+    pub symbol_pool_evictions: AtomicU64,
+    // 带接收时间戳的命令从到达网卡或内核到撮合完成的耗时
+    pub wire_latency: LatencyHistogram,
+    // 市场行为检测器产生的告警数，见 surveillance::spawn_market_detectors
+    pub surveillance_alerts: AtomicU64,
+    // 各合约的指标，合约在引擎中首次出现时登记
+    symbols: RwLock<HashMap<String, Arc<SymbolMetrics>>>,
+}
+
+// 某一时刻的指标快照
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MetricsSnapshot {
+    pub orders_accepted: u64,
+    pub orders_throttled: u64,
+    pub trades_executed: u64,
+    pub pool_compactions: u64,
+    pub pool_slots_reclaimed: u64,
+    pub command_queue_depth: u64,
+    pub symbol_pool_size: u64,
+    pub symbol_pool_hits: u64,
+    pub symbol_pool_misses: u64,
+    pub symbol_pool_evictions: u64,
+    pub surveillance_alerts: u64,
+}
+
+impl EngineMetrics {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn snapshot(&self) -> MetricsSnapshot {
+        MetricsSnapshot {
+            orders_accepted: self.orders_accepted.load(Ordering::Relaxed),
+            orders_throttled: self.orders_throttled.load(Ordering::Relaxed),
+            trades_executed: self.trades_executed.load(Ordering::Relaxed),
+            pool_compactions: self.pool_compactions.load(Ordering::Relaxed),
+            pool_slots_reclaimed: self.pool_slots_reclaimed.load(Ordering::Relaxed),
+            command_queue_depth: self.command_queue_depth.load(Ordering::Relaxed),
+            symbol_pool_size: self.symbol_pool_size.load(Ordering::Relaxed),
+            symbol_pool_hits: self.symbol_pool_hits.load(Ordering::Relaxed),
+            symbol_pool_misses: self.symbol_pool_misses.load(Ordering::Relaxed),
+            symbol_pool_evictions: self.symbol_pool_evictions.load(Ordering::Relaxed),
+            surveillance_alerts: self.surveillance_alerts.load(Ordering::Relaxed),
+        }
+    }
+
+    // 返回合约的指标，不存在时创建
+    pub fn symbol(&self, symbol: &str) -> Arc<SymbolMetrics> {
+        if let Some(metrics) = self.symbols.read().get(symbol) {
+            return metrics.clone();
+        }
+        self.symbols.write().entry(symbol.to_string()).or_default().clone()
+    }
+
+    pub fn record_wire_latency(&self, latency: Duration) {
+        self.wire_latency.record(latency);
+    }
+
+    // 按合约名排序的各合约指标快照
+    pub fn symbol_snapshots(&self) -> BTreeMap<String, SymbolSnapshot> {
+        self.symbols.read().iter().map(|(symbol, metrics)| (symbol.clone(), metrics.snapshot())).collect()
+    }
+}
+
+impl MetricsSnapshot {
+    // 按 statsd 协议格式化为若干行 gauge
+    pub fn to_statsd_lines(&self, prefix: &str) -> Vec<String> {
+        vec![
+            format!("{}.orders_accepted:{}|g", prefix, self.orders_accepted),
+            format!("{}.orders_throttled:{}|g", prefix, self.orders_throttled),
+            format!("{}.trades_executed:{}|g", prefix, self.trades_executed),
+            format!("{}.pool_compactions:{}|g", prefix, self.pool_compactions),
+            format!("{}.pool_slots_reclaimed:{}|g", prefix, self.pool_slots_reclaimed),
+            format!("{}.command_queue_depth:{}|g", prefix, self.command_queue_depth),
+            format!("{}.symbol_pool_size:{}|g", prefix, self.symbol_pool_size),
+            format!("{}.symbol_pool_hits:{}|g", prefix, self.symbol_pool_hits),
+            format!("{}.symbol_pool_misses:{}|g", prefix, self.symbol_pool_misses),
+            format!("{}.symbol_pool_evictions:{}|g", prefix, self.symbol_pool_evictions),
+            format!("{}.surveillance_alerts:{}|g", prefix, self.surveillance_alerts),
+        ]
+    }
+}
+
+// 撮合耗时直方图各桶的上界（纳秒），超过最后一档的计入 +Inf 桶
+pub const LATENCY_BUCKETS_NS: [u64; 12] = [
+    1_000, 2_500, 5_000, 10_000, 25_000, 50_000, 100_000, 250_000, 500_000, 1_000_000, 2_500_000, 10_000_000,
+];
+
+// 单个合约的指标。引擎在创建合约时取得实例并缓存，撮合路径上只做原子加法
+#[derive(Debug, Default)]
+pub struct SymbolMetrics {
+    // 进入订单簿的新订单数（通过限流和持仓检查之后）
+    pub orders: AtomicU64,
+    pub trades: AtomicU64,
+    // 成功撤销的订单数，不含重复撤单和未知订单
+    pub cancels: AtomicU64,
+    pub rejects: AtomicU64,
+    // 节点池的槽位数（含空闲槽位）和其中存活的挂单数，引擎每次操作订单簿后更新
+    pub pool_slots: AtomicU64,
+    pub resting_orders: AtomicU64,
+    // 订单簿的估算内存占用（字节），见 OrderBook::memory_usage
+    pub memory_bytes: AtomicU64,
+    // 新订单撮合耗时的分布
+    latency: LatencyHistogram,
+}
+
+// 按 LATENCY_BUCKETS_NS 分桶的耗时分布，最后一个桶是 +Inf 桶，各桶不累积
+#[derive(Debug, Default)]
+pub struct LatencyHistogram {
+    buckets: [AtomicU64; LATENCY_BUCKETS_NS.len() + 1],
+    sum_ns: AtomicU64,
+}
+
+impl LatencyHistogram {
+    pub fn record(&self, latency: Duration) {
+        let nanos = latency.as_nanos().min(u64::MAX as u128) as u64;
+        let bucket = LATENCY_BUCKETS_NS.iter().position(|&bound| nanos <= bound).unwrap_or(LATENCY_BUCKETS_NS.len());
+        self.buckets[bucket].fetch_add(1, Ordering::Relaxed);
+        self.sum_ns.fetch_add(nanos, Ordering::Relaxed);
+    }
+
+    pub fn buckets(&self) -> Vec<u64> {
+        self.buckets.iter().map(|bucket| bucket.load(Ordering::Relaxed)).collect()
+    }
+
+    pub fn sum_ns(&self) -> u64 {
+        self.sum_ns.load(Ordering::Relaxed)
+    }
+
+    pub fn count(&self) -> u64 {
+        self.buckets.iter().map(|bucket| bucket.load(Ordering::Relaxed)).sum()
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SymbolSnapshot {
+    pub orders: u64,
+    pub trades: u64,
+    pub cancels: u64,
+    pub rejects: u64,
+    pub pool_slots: u64,
+    pub resting_orders: u64,
+    pub memory_bytes: u64,
+    // 与 LATENCY_BUCKETS_NS 对应的各桶计数，最后一个元素是 +Inf 桶
+    pub latency_buckets: Vec<u64>,
+    pub latency_sum_ns: u64,
+}
+
+impl SymbolMetrics {
+    pub fn record_latency(&self, latency: Duration) {
+        self.latency.record(latency);
+    }
+
+    pub fn snapshot(&self) -> SymbolSnapshot {
+        SymbolSnapshot {
+            orders: self.orders.load(Ordering::Relaxed),
+            trades: self.trades.load(Ordering::Relaxed),
+            cancels: self.cancels.load(Ordering::Relaxed),
+            rejects: self.rejects.load(Ordering::Relaxed),
+            pool_slots: self.pool_slots.load(Ordering::Relaxed),
+            resting_orders: self.resting_orders.load(Ordering::Relaxed),
+            memory_bytes: self.memory_bytes.load(Ordering::Relaxed),
+            latency_buckets: self.latency.buckets(),
+            latency_sum_ns: self.latency.sum_ns(),
+        }
+    }
+}
+
+impl SymbolSnapshot {
+    // 记录了耗时的订单数
+    pub fn latency_count(&self) -> u64 {
+        self.latency_buckets.iter().sum()
+    }
+}
+
+// 网络层的指标，所有连接共享
+#[derive(Debug, Default)]
+pub struct OutboundMetrics {
+    // 所有连接出站队列中等待发送的消息总数
+    pub queue_depth: AtomicU64,
+    // 因队列已满而丢弃的行情消息数
+    pub market_data_dropped: AtomicU64,
+    // 因跟不上推送速度而被断开的连接数
+    pub slow_consumer_disconnects: AtomicU64,
+    // 消息经过网络层和引擎各阶段的耗时，默认关闭
+    pub latency_budget: LatencyBudget,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct OutboundSnapshot {
+    pub queue_depth: u64,
+    pub market_data_dropped: u64,
+    pub slow_consumer_disconnects: u64,
+}
+
+impl OutboundMetrics {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn snapshot(&self) -> OutboundSnapshot {
+        OutboundSnapshot {
+            queue_depth: self.queue_depth.load(Ordering::Relaxed),
+            market_data_dropped: self.market_data_dropped.load(Ordering::Relaxed),
+            slow_consumer_disconnects: self.slow_consumer_disconnects.load(Ordering::Relaxed),
+        }
+    }
+}
+
+impl OutboundSnapshot {
+    pub fn to_statsd_lines(&self, prefix: &str) -> Vec<String> {
+        vec![
+            format!("{}.outbound_queue_depth:{}|g", prefix, self.queue_depth),
+            format!("{}.outbound_market_data_dropped:{}|g", prefix, self.market_data_dropped),
+            format!("{}.outbound_slow_consumer_disconnects:{}|g", prefix, self.slow_consumer_disconnects),
+        ]
+    }
+}
+
+// 缓冲区满时的丢弃策略
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DropPolicy {
+    // 丢弃最旧的数据，保留最新的指标
+    DropOldest,
+    // 丢弃新产生的数据
+    DropNewest,
+}
+
+// statsd 推送配置，用于不允许从外部抓取交易主机的部署环境
+#[derive(Debug, Clone)]
+pub struct StatsdConfig {
+    pub addr: SocketAddr,
+    pub prefix: String,
+    pub interval: Duration,
+    // 发送失败时最多缓冲的行数
+    pub buffer_capacity: usize,
+    pub drop_policy: DropPolicy,
+}
+
+impl StatsdConfig {
+    pub fn new(addr: SocketAddr) -> Self {
+        StatsdConfig {
+            addr,
+            prefix: "matching_engine".to_string(),
+            interval: Duration::from_secs(1),
+            buffer_capacity: 1024,
+            drop_policy: DropPolicy::DropOldest,
+        }
+    }
+}
+
+// 待发送指标的有界缓冲区
+pub struct PushBuffer {
+    lines: VecDeque<String>,
+    capacity: usize,
+    drop_policy: DropPolicy,
+    dropped: u64,
+}
+
+impl PushBuffer {
+    pub fn new(capacity: usize, drop_policy: DropPolicy) -> Self {
+        PushBuffer {
+            lines: VecDeque::with_capacity(capacity),
+            capacity,
+            drop_policy,
+            dropped: 0,
+        }
+    }
+
+    pub fn push(&mut self, line: String) {
+        if self.lines.len() >= self.capacity {
+            self.dropped += 1;
+            match self.drop_policy {
+                DropPolicy::DropOldest => {
+                    self.lines.pop_front();
+                }
+                DropPolicy::DropNewest => return,
+            }
+        }
+        self.lines.push_back(line);
+    }
+
+    pub fn len(&self) -> usize {
+        self.lines.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.lines.is_empty()
+    }
+
+    // 因缓冲区已满而被丢弃的行数
+    pub fn dropped(&self) -> u64 {
+        self.dropped
+    }
+
+    // 依次发送缓冲的数据，遇到发送失败时保留剩余数据等待下次重试
+    pub fn flush<F>(&mut self, mut send: F)
+    where
+        F: FnMut(&str) -> std::io::Result<()>,
+    {
+        while let Some(line) = self.lines.front() {
+            if send(line).is_err() {
+                break;
+            }
+            self.lines.pop_front();
+        }
+    }
+}
+
+// 启动后台线程，按固定间隔通过 UDP 向 statsd 推送指标
+pub fn spawn_statsd_exporter(
+    metrics: Arc<EngineMetrics>,
+    outbound: Arc<OutboundMetrics>,
+    config: StatsdConfig,
+) -> std::io::Result<JoinHandle<()>> {
+    let socket = UdpSocket::bind("0.0.0.0:0")?;
+    let handle = thread::spawn(move || {
+        let mut buffer = PushBuffer::new(config.buffer_capacity, config.drop_policy);
+        loop {
+            thread::sleep(config.interval);
+            let lines = metrics.snapshot().to_statsd_lines(&config.prefix);
+            for line in lines.into_iter().chain(outbound.snapshot().to_statsd_lines(&config.prefix)) {
+                buffer.push(line);
+            }
+            buffer.flush(|line| socket.send_to(line.as_bytes(), config.addr).map(|_| ()));
+        }
+    });
+    Ok(handle)
+}
+
+// 按 Prometheus 文本格式（0.0.4）输出全部指标
+pub fn render_prometheus(metrics: &EngineMetrics, outbound: &OutboundMetrics) -> String {
+    let engine = metrics.snapshot();
+    let outbound_stages = outbound.latency_budget.snapshot();
+    let outbound = outbound.snapshot();
+    let mut out = String::new();
+    let counters = [
+        ("orders_accepted_total", "被限流器放行的订单数", engine.orders_accepted),
+        ("orders_throttled_total", "被限流器拒绝的订单数", engine.orders_throttled),
+        ("trades_executed_total", "已发布的成交笔数", engine.trades_executed),
+        ("pool_compactions_total", "订单簿节点池的压缩次数", engine.pool_compactions),
+        ("pool_slots_reclaimed_total", "压缩累计回收的节点槽位数", engine.pool_slots_reclaimed),
+        ("symbol_pool_hits_total", "驻留时合约名已在池中的次数", engine.symbol_pool_hits),
+        ("symbol_pool_misses_total", "驻留时需要新增合约名的次数", engine.symbol_pool_misses),
+        ("symbol_pool_evictions_total", "从驻留池回收的合约名数", engine.symbol_pool_evictions),
+        ("surveillance_alerts_total", "市场行为检测器产生的告警数", engine.surveillance_alerts),
+        ("outbound_market_data_dropped_total", "因出站队列已满而丢弃的行情消息数", outbound.market_data_dropped),
+        ("outbound_slow_consumer_disconnects_total", "因跟不上推送速度而被断开的连接数", outbound.slow_consumer_disconnects),
+    ];
+    for (name, help, value) in counters {
+        write_metric_header(&mut out, name, help, "counter");
+        let _ = writeln!(out, "matching_engine_{} {}", name, value);
+    }
+    let symbols = metrics.symbol_snapshots();
+    let gauges = [
+        ("command_queue_depth", "引擎命令通道中排队的命令数", engine.command_queue_depth),
+        ("outbound_queue_depth", "所有连接出站队列中等待发送的消息数", outbound.queue_depth),
+        ("symbol_pool_size", "驻留池中的合约名数", engine.symbol_pool_size),
+        ("book_memory_bytes", "所有合约订单簿的估算内存占用", symbols.values().map(|s| s.memory_bytes).sum()),
+    ];
+    for (name, help, value) in gauges {
+        write_metric_header(&mut out, name, help, "gauge");
+        let _ = writeln!(out, "matching_engine_{} {}", name, value);
+    }
+
+    write_symbol_metric(&mut out, "symbol_orders_total", "各合约进入订单簿的新订单数", "counter", &symbols, |s| s.orders);
+    write_symbol_metric(&mut out, "symbol_trades_total", "各合约的成交笔数", "counter", &symbols, |s| s.trades);
+    write_symbol_metric(&mut out, "symbol_cancels_total", "各合约成功撤销的订单数", "counter", &symbols, |s| s.cancels);
+    write_symbol_metric(&mut out, "symbol_rejects_total", "各合约被拒绝的订单数", "counter", &symbols, |s| s.rejects);
+    write_symbol_metric(&mut out, "symbol_pool_slots", "各合约订单簿节点池的槽位数", "gauge", &symbols, |s| s.pool_slots);
+    write_symbol_metric(&mut out, "symbol_resting_orders", "各合约订单簿中的挂单数", "gauge", &symbols, |s| s.resting_orders);
+    write_symbol_metric(&mut out, "symbol_memory_bytes", "各合约订单簿的估算内存占用", "gauge", &symbols, |s| s.memory_bytes);
+
+    write_metric_header(&mut out, "match_latency_seconds", "新订单的撮合耗时", "histogram");
+    for (symbol, snapshot) in &symbols {
+        let symbol = escape_label(symbol);
+        let mut cumulative = 0;
+        for (bound, count) in LATENCY_BUCKETS_NS.iter().zip(&snapshot.latency_buckets) {
+            cumulative += count;
+            let le = *bound as f64 / 1e9;
+            let _ = writeln!(out, "matching_engine_match_latency_seconds_bucket{{symbol=\"{}\",le=\"{}\"}} {}", symbol, le, cumulative);
+        }
+        let count = snapshot.latency_count();
+        let _ = writeln!(out, "matching_engine_match_latency_seconds_bucket{{symbol=\"{}\",le=\"+Inf\"}} {}", symbol, count);
+        let sum = snapshot.latency_sum_ns as f64 / 1e9;
+        let _ = writeln!(out, "matching_engine_match_latency_seconds_sum{{symbol=\"{}\"}} {}", symbol, sum);
+        let _ = writeln!(out, "matching_engine_match_latency_seconds_count{{symbol=\"{}\"}} {}", symbol, count);
+    }
+
+    write_metric_header(&mut out, "wire_to_match_seconds", "订单从到达网卡或内核到撮合完成的耗时", "histogram");
+    let mut cumulative = 0;
+    for (bound, count) in LATENCY_BUCKETS_NS.iter().zip(metrics.wire_latency.buckets()) {
+        cumulative += count;
+        let le = *bound as f64 / 1e9;
+        let _ = writeln!(out, "matching_engine_wire_to_match_seconds_bucket{{le=\"{}\"}} {}", le, cumulative);
+    }
+    let count = metrics.wire_latency.count();
+    let _ = writeln!(out, "matching_engine_wire_to_match_seconds_bucket{{le=\"+Inf\"}} {}", count);
+    let _ = writeln!(out, "matching_engine_wire_to_match_seconds_sum {}", metrics.wire_latency.sum_ns() as f64 / 1e9);
+    let _ = writeln!(out, "matching_engine_wire_to_match_seconds_count {}", count);
+
+    write_metric_header(&mut out, "stage_latency_seconds", "消息在流水线各阶段的耗时，开启延迟预算时记录", "summary");
+    for stage in outbound_stages {
+        let name = stage.stage.name();
+        for (quantile, nanos) in [("0.5", stage.p50_ns), ("0.99", stage.p99_ns), ("0.999", stage.p999_ns), ("1", stage.max_ns)] {
+            let seconds = nanos as f64 / 1e9;
+            let _ = writeln!(out, "matching_engine_stage_latency_seconds{{stage=\"{}\",quantile=\"{}\"}} {}", name, quantile, seconds);
+        }
+        let _ = writeln!(out, "matching_engine_stage_latency_seconds_count{{stage=\"{}\"}} {}", name, stage.count);
+    }
+    out
+}
+
+fn write_symbol_metric(
+    out: &mut String,
+    name: &str,
+    help: &str,
+    kind: &str,
+    symbols: &BTreeMap<String, SymbolSnapshot>,
+    value: impl Fn(&SymbolSnapshot) -> u64,
+) {
+    write_metric_header(out, name, help, kind);
+    for (symbol, snapshot) in symbols {
+        let _ = writeln!(out, "matching_engine_{}{{symbol=\"{}\"}} {}", name, escape_label(symbol), value(snapshot));
+    }
+}
+
+fn write_metric_header(out: &mut String, name: &str, help: &str, kind: &str) {
+    let _ = writeln!(out, "# HELP matching_engine_{} {}", name, help);
+    let _ = writeln!(out, "# TYPE matching_engine_{} {}", name, kind);
+}
+
+// 标签值中的反斜杠、双引号和换行需要转义
+fn escape_label(value: &str) -> String {
+    value.replace('\\', "\\\\").replace('"', "\\\"").replace('\n', "\\n")
+}
+
+// 启动后台线程，在 listener 上提供 GET /metrics，供 Prometheus 抓取；
+// 传入 health 时同时提供 /healthz（存活）和 /readyz（就绪），失败时返回 503。
+// POST /latency_budget/enable 和 /latency_budget/disable 在运行时开关延迟预算。
+// 请求频率很低，逐个连接同步处理即可
+pub fn spawn_prometheus_exporter(
+    listener: TcpListener,
+    metrics: Arc<EngineMetrics>,
+    outbound: Arc<OutboundMetrics>,
+    health: Option<Arc<HealthChecker>>,
+) -> JoinHandle<()> {
+    thread::spawn(move || {
+        for stream in listener.incoming() {
+            let Ok(stream) = stream else { continue };
+            if let Err(e) = serve_scrape(stream, &metrics, &outbound, health.as_deref()) {
+                eprintln!("处理指标抓取请求失败: {}", e);
+            }
+        }
+    })
+}
+
+fn serve_scrape(
+    mut stream: TcpStream,
+    metrics: &EngineMetrics,
+    outbound: &OutboundMetrics,
+    health: Option<&HealthChecker>,
+) -> std::io::Result<()> {
+    stream.set_read_timeout(Some(Duration::from_secs(5)))?;
+    // 只需要请求行，读到请求头结束或缓冲区满为止
+    let mut request = [0u8; 4096];
+    let mut len = 0;
+    while len < request.len() && !request[..len].windows(4).any(|w| w == b"\r\n\r\n") {
+        let n = stream.read(&mut request[len..])?;
+        if n == 0 {
+            break;
+        }
+        len += n;
+    }
+    let request = String::from_utf8_lossy(&request[..len]);
+    let mut parts = request.split_whitespace();
+    let (status, body) = match (parts.next(), parts.next(), health) {
+        (Some("GET"), Some("/metrics"), _) => ("200 OK", render_prometheus(metrics, outbound)),
+        // 运行时开关延迟预算
+        (Some("POST"), Some(path @ ("/latency_budget/enable" | "/latency_budget/disable")), _) => {
+            let enabled = path == "/latency_budget/enable";
+            outbound.latency_budget.set_enabled(enabled);
+            ("200 OK", format!("latency budget {}\n", if enabled { "enabled" } else { "disabled" }))
+        }
+        (Some("GET"), Some(path @ ("/healthz" | "/readyz")), Some(health)) => {
+            let report = health.check();
+            let passed = if path == "/healthz" { report.is_live() } else { report.is_ready() };
+            (if passed { "200 OK" } else { "503 Service Unavailable" }, report.render())
+        }
+        _ => ("404 Not Found", "not found\n".to_string()),
+    };
+    write!(
+        stream,
+        "HTTP/1.1 {}\r\nContent-Type: text/plain; version=0.0.4\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+        status,
+        body.len(),
+        body
+    )?;
+    stream.flush()
+}
@@ -1,70 +1,556 @@
+use crate::codec;
 use crate::engine::{EngineCommand, EngineOutput};
-use crate::protocol::{ClientMessage, ServerMessage};
+use crate::fault::{self, FaultAction, FaultPoint};
+use crate::latency_budget::Breadcrumb;
+use crate::metrics::OutboundMetrics;
+use crate::rx_timestamp::{self, TimestampedStream};
+use crate::protocol::{
+    ClientMessage, LogonResponse, LogonStatus, MarketDataMode, OrderReject, RejectReason, ResumeResponse, ServerMessage,
+};
+use crate::session::{Session, SessionConfig};
 use bytes::Bytes;
 use futures::stream::StreamExt;
 use futures::SinkExt;
+use parking_lot::Mutex;
+use std::collections::{HashSet, VecDeque};
+use std::future::Future;
 use std::net::SocketAddr;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::Instant;
 use tokio::net::{TcpListener, TcpStream};
-use tokio::sync::{broadcast, mpsc};
+use tokio::sync::{mpsc, Notify};
 use tokio_util::codec::{Framed, LengthDelimitedCodec};
+use tracing::Instrument;
 use bincode::config;
 
+// 每个连接出站队列中行情类消息的默认容量
+const OUTBOUND_CAPACITY: usize = 1024;
+// 每个连接允许积压的成交、回报、应答等关键消息数量，超过后断开连接
+const MAX_ESSENTIAL_BACKLOG: usize = 64 * 1024;
+
+// 推送给连接的一条已编码消息
+#[derive(Debug, Clone)]
+pub struct OutboundMessage {
+    pub payload: Bytes,
+    // 成交、交易状态以及发给用户本人的回报和应答在合并模式下仍然推送，队列满时也不会被丢弃；
+    // 标记价格、隐含报价等公共行情只在完整模式下推送
+    pub essential: bool,
+    // 延迟预算的标记：前面的消息发完后记录面包屑，本身不发送，payload 为空
+    pub trace: Option<Box<Breadcrumb>>,
+}
+
+// 行情类消息在出站队列已满时的处理方式
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OverflowPolicy {
+    // 丢弃队列中最旧的行情消息
+    DropOldest,
+    // 丢弃新到的行情消息
+    DropNewest,
+    // 断开跟不上的连接
+    Disconnect,
+}
+
+// 出站队列配置
+#[derive(Debug, Clone, Copy)]
+pub struct OutboundConfig {
+    // 行情类消息的队列容量
+    pub capacity: usize,
+    pub overflow: OverflowPolicy,
+    // 关键消息的积压上限，超过后无法保证送达，只能断开连接
+    pub max_essential_backlog: usize,
+}
+
+impl Default for OutboundConfig {
+    fn default() -> Self {
+        OutboundConfig {
+            capacity: OUTBOUND_CAPACITY,
+            overflow: OverflowPolicy::DropOldest,
+            max_essential_backlog: MAX_ESSENTIAL_BACKLOG,
+        }
+    }
+}
+
+// 向出站队列追加消息的结果
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PushOutcome {
+    Queued,
+    // 队列已满，按策略丢弃了一条行情消息
+    Dropped,
+    // 连接跟不上推送速度，需要断开
+    Disconnect,
+}
+
+// 单个连接的有界出站队列。广播任务只向队列追加消息，从不等待慢连接，
+// 连接任务再按自己的速度把队列中的消息写入 socket
+#[derive(Debug)]
+pub struct OutboundQueue {
+    config: OutboundConfig,
+    messages: VecDeque<OutboundMessage>,
+    // 队列中行情类消息的数量
+    market_data_len: usize,
+    dropped: u64,
+}
+
+impl OutboundQueue {
+    pub fn new(config: OutboundConfig) -> Self {
+        OutboundQueue {
+            config,
+            messages: VecDeque::new(),
+            market_data_len: 0,
+            dropped: 0,
+        }
+    }
+
+    pub fn push(&mut self, message: OutboundMessage) -> PushOutcome {
+        if message.essential {
+            if self.messages.len() - self.market_data_len >= self.config.max_essential_backlog {
+                return PushOutcome::Disconnect;
+            }
+            self.messages.push_back(message);
+            return PushOutcome::Queued;
+        }
+        if self.market_data_len < self.config.capacity {
+            self.market_data_len += 1;
+            self.messages.push_back(message);
+            return PushOutcome::Queued;
+        }
+        match self.config.overflow {
+            OverflowPolicy::DropOldest => {
+                // 只丢弃行情消息，关键消息保持原有顺序
+                if let Some(index) = self.messages.iter().position(|queued| !queued.essential) {
+                    self.messages.remove(index);
+                    self.messages.push_back(message);
+                }
+            }
+            OverflowPolicy::DropNewest => {}
+            OverflowPolicy::Disconnect => return PushOutcome::Disconnect,
+        }
+        self.dropped += 1;
+        PushOutcome::Dropped
+    }
+
+    pub fn pop(&mut self) -> Option<OutboundMessage> {
+        let message = self.messages.pop_front()?;
+        if !message.essential {
+            self.market_data_len -= 1;
+        }
+        Some(message)
+    }
+
+    pub fn len(&self) -> usize {
+        self.messages.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.messages.is_empty()
+    }
+
+    // 因队列已满而被丢弃的行情消息数
+    pub fn dropped(&self) -> u64 {
+        self.dropped
+    }
+}
+
+// 连接编号，面包屑据此找到来源连接
+static NEXT_CONNECTION_ID: AtomicU64 = AtomicU64::new(1);
+
+// 广播任务与连接任务共享的出站队列
+struct Outbound {
+    id: u64,
+    queue: Mutex<OutboundQueue>,
+    notify: Notify,
+    // 连接已关闭，或者因跟不上推送被广播任务断开；只在持有 queue 锁时修改
+    closed: AtomicBool,
+    // 引擎输出已经结束（服务器正在关闭）：发完队列中剩余的消息后断开连接
+    finished: AtomicBool,
+    // 登录的用户，连接接收该用户的私有回报。未登录连接的用户 ID 由客户端自报，不据此订阅，
+    // 只接收自己命令的应答（EngineCommand::Direct）
+    users: Mutex<HashSet<u64>>,
+    metrics: Arc<OutboundMetrics>,
+}
+
+impl Outbound {
+    fn new(config: OutboundConfig, metrics: Arc<OutboundMetrics>) -> Self {
+        Outbound {
+            id: NEXT_CONNECTION_ID.fetch_add(1, Ordering::Relaxed),
+            queue: Mutex::new(OutboundQueue::new(config)),
+            notify: Notify::new(),
+            closed: AtomicBool::new(false),
+            finished: AtomicBool::new(false),
+            users: Mutex::new(HashSet::new()),
+            metrics,
+        }
+    }
+
+    fn serve_user(&self, user_id: u64) {
+        self.users.lock().insert(user_id);
+    }
+
+    // 一条输出是否发给该连接
+    fn receives(&self, route: Route) -> bool {
+        match route {
+            Route::All => true,
+            Route::Connection(id) => id == self.id,
+            Route::User(user_id) => self.users.lock().contains(&user_id),
+        }
+    }
+
+    // 追加一条消息，连接需要断开时返回 false
+    fn push(&self, message: OutboundMessage) -> bool {
+        let mut queue = self.queue.lock();
+        if self.closed.load(Ordering::Acquire) {
+            return false;
+        }
+        match queue.push(message) {
+            PushOutcome::Queued => {
+                self.metrics.queue_depth.fetch_add(1, Ordering::Relaxed);
+            }
+            PushOutcome::Dropped => {
+                self.metrics.market_data_dropped.fetch_add(1, Ordering::Relaxed);
+            }
+            PushOutcome::Disconnect => {
+                self.metrics.slow_consumer_disconnects.fetch_add(1, Ordering::Relaxed);
+                self.closed.store(true, Ordering::Release);
+            }
+        }
+        drop(queue);
+        self.notify.notify_one();
+        !self.closed.load(Ordering::Acquire)
+    }
+
+    // 取出下一条消息和取出后的积压数量
+    fn pop(&self) -> Option<(OutboundMessage, usize)> {
+        let mut queue = self.queue.lock();
+        let message = queue.pop()?;
+        self.metrics.queue_depth.fetch_sub(1, Ordering::Relaxed);
+        Some((message, queue.len()))
+    }
+
+    // 不会再有新消息，通知连接任务发完剩余消息后退出
+    fn finish(&self) {
+        self.finished.store(true, Ordering::Release);
+        self.notify.notify_one();
+    }
+
+    // 连接关闭后丢弃队列中剩余的消息
+    fn close(&self) {
+        let mut queue = self.queue.lock();
+        self.closed.store(true, Ordering::Release);
+        while queue.pop().is_some() {
+            self.metrics.queue_depth.fetch_sub(1, Ordering::Relaxed);
+        }
+    }
+}
+
+// 订阅者积压阈值：积压达到 degrade_at 时降级为合并推送，回落到 recover_at 以下时恢复完整推送
+#[derive(Debug, Clone, Copy)]
+pub struct ConflationConfig {
+    pub degrade_at: usize,
+    pub recover_at: usize,
+}
+
+impl Default for ConflationConfig {
+    fn default() -> Self {
+        ConflationConfig {
+            degrade_at: OUTBOUND_CAPACITY / 2,
+            recover_at: OUTBOUND_CAPACITY / 16,
+        }
+    }
+}
+
+// 单个订阅者的推送模式状态机
+#[derive(Debug)]
+pub struct Conflation {
+    config: ConflationConfig,
+    mode: MarketDataMode,
+}
+
+impl Conflation {
+    pub fn new(config: ConflationConfig) -> Self {
+        Conflation { config, mode: MarketDataMode::Full }
+    }
+
+    pub fn mode(&self) -> MarketDataMode {
+        self.mode
+    }
+
+    // 根据订阅者当前积压的消息数量更新推送模式，模式发生变化时返回新模式
+    pub fn update(&mut self, backlog: usize) -> Option<MarketDataMode> {
+        let next = match self.mode {
+            MarketDataMode::Full if backlog >= self.config.degrade_at => MarketDataMode::Conflated,
+            MarketDataMode::Conflated if backlog <= self.config.recover_at => MarketDataMode::Full,
+            _ => return None,
+        };
+        self.mode = next;
+        Some(next)
+    }
+
+    // 当前模式下是否推送该消息
+    pub fn should_forward(&self, essential: bool) -> bool {
+        essential || self.mode == MarketDataMode::Full
+    }
+}
+
 // 启动网络服务器
 pub async fn run_server(
     addr: SocketAddr,
     command_sender: mpsc::UnboundedSender<EngineCommand>,
-    mut output_receiver: mpsc::UnboundedReceiver<EngineOutput>,
+    output_receiver: mpsc::UnboundedReceiver<EngineOutput>,
+    sessions: SessionConfig,
+    outbound: OutboundConfig,
+    metrics: Arc<OutboundMetrics>,
 ) {
     let listener = TcpListener::bind(&addr).await.expect("无法绑定地址");
     println!("服务器正在监听: {}", addr);
+    serve_with_outbound(listener, command_sender, output_receiver, sessions, outbound, metrics).await;
+}
 
-    // 创建一个广播通道用于分发引擎的输出，现在使用 Bytes
-    let (broadcast_tx, _) = broadcast::channel::<Bytes>(1024);
+// 在已绑定的监听器上提供服务，测试可以借此绑定临时端口；不要求登录
+pub fn serve(
+    listener: TcpListener,
+    command_sender: mpsc::UnboundedSender<EngineCommand>,
+    output_receiver: mpsc::UnboundedReceiver<EngineOutput>,
+) -> impl Future<Output = ()> {
+    serve_with_sessions(listener, command_sender, output_receiver, SessionConfig::default())
+}
 
+// 按给定的会话配置提供服务
+pub fn serve_with_sessions(
+    listener: TcpListener,
+    command_sender: mpsc::UnboundedSender<EngineCommand>,
+    output_receiver: mpsc::UnboundedReceiver<EngineOutput>,
+    sessions: SessionConfig,
+) -> impl Future<Output = ()> {
+    let metrics = Arc::new(OutboundMetrics::new());
+    serve_with_outbound(listener, command_sender, output_receiver, sessions, OutboundConfig::default(), metrics)
+}
 
+// 按给定的会话配置和出站队列配置提供服务
+pub fn serve_with_outbound(
+    listener: TcpListener,
+    command_sender: mpsc::UnboundedSender<EngineCommand>,
+    output_receiver: mpsc::UnboundedReceiver<EngineOutput>,
+    sessions: SessionConfig,
+    outbound: OutboundConfig,
+    metrics: Arc<OutboundMetrics>,
+) -> impl Future<Output = ()> {
+    let shutdown = std::future::pending();
+    serve_until(listener, command_sender, output_receiver, sessions, outbound, metrics, shutdown)
+}
 
-    // 这个任务负责将引擎的输出广播给所有连接的客户端
-    let broadcaster_tx_clone = broadcast_tx.clone();
+// 提供服务直到 shutdown 完成：此后不再接受新连接，已有连接继续收发，
+// 直到引擎排空命令并退出（输出通道关闭），各连接收到 Shutdown 通知并发完剩余回报后断开。
+// 全部连接断开后返回。
+// 接收时间戳在调用时（返回的 future 被 spawn 之前）就在监听套接字上开启：内核在握手完成时
+// 从监听套接字复制出连接，任务开始运行前就已连上的客户端同样继承该设置，首批订单也带有时间戳
+pub fn serve_until(
+    listener: TcpListener,
+    command_sender: mpsc::UnboundedSender<EngineCommand>,
+    output_receiver: mpsc::UnboundedReceiver<EngineOutput>,
+    sessions: SessionConfig,
+    outbound: OutboundConfig,
+    metrics: Arc<OutboundMetrics>,
+    shutdown: impl Future<Output = ()>,
+) -> impl Future<Output = ()> {
+    if sessions.rx_timestamps {
+        if let Err(e) = rx_timestamp::enable(&listener) {
+            eprintln!("无法在监听套接字上开启接收时间戳: {}", e);
+        }
+    }
+    accept_loop(listener, command_sender, output_receiver, sessions, outbound, metrics, shutdown)
+}
+
+async fn accept_loop(
+    listener: TcpListener,
+    command_sender: mpsc::UnboundedSender<EngineCommand>,
+    mut output_receiver: mpsc::UnboundedReceiver<EngineOutput>,
+    sessions: SessionConfig,
+    outbound: OutboundConfig,
+    metrics: Arc<OutboundMetrics>,
+    shutdown: impl Future<Output = ()>,
+) {
+    let sessions = Arc::new(sessions);
+    // 所有连接的出站队列
+    let connections: Arc<Mutex<Vec<Arc<Outbound>>>> = Arc::new(Mutex::new(Vec::new()));
+
+    // 这个任务负责将引擎的输出分发到每个连接的出站队列，追加消息不会等待慢连接
+    let broadcast_connections = connections.clone();
+    let broadcast_sessions = sessions.clone();
     tokio::spawn(async move {
         let config = config::standard();
         while let Some(output) = output_receiver.recv().await {
-            let server_msg = match output {
-                EngineOutput::Trade(trade) => ServerMessage::Trade(trade),
-                EngineOutput::Confirmation(conf) => ServerMessage::Confirmation(conf),
-            };
-            let msg_bytes_res = bincode::encode_to_vec(server_msg, config);
-            match msg_bytes_res {
-                Ok(msg_bytes) => {
-                    if broadcaster_tx_clone.send(Bytes::from(msg_bytes)).is_err() {
-                        // 当没有客户端连接时，发送会失败，这是正常现象
+            match output {
+                // 批量输出按原有顺序逐条分发
+                EngineOutput::Batch(outputs) => {
+                    for output in outputs {
+                        broadcast(output, &broadcast_connections, &broadcast_sessions);
                     }
                 }
-                Err(e) => {
-                    eprintln!("Bincode encoding error in broadcaster: {:?}", e);
-                }
+                output => broadcast(output, &broadcast_connections, &broadcast_sessions),
             }
         }
+        // 引擎已退出：通知每个连接服务器正在关闭，连接在发完剩余回报后断开
+        let payload = Bytes::from(bincode::encode_to_vec(ServerMessage::Shutdown, config).expect("服务器消息编码失败"));
+        for connection in broadcast_connections.lock().drain(..) {
+            connection.push(OutboundMessage { payload: payload.clone(), essential: true, trace: None });
+            connection.finish();
+        }
     });
 
-    while let Ok((stream, _)) = listener.accept().await {
+    // 每个连接任务持有一个发送端，全部连接结束后 recv 返回 None
+    let (connection_guard, mut connections_done) = mpsc::channel::<()>(1);
+    tokio::pin!(shutdown);
+    loop {
+        let (stream, _) = tokio::select! {
+            accepted = listener.accept() => match accepted {
+                Ok(accepted) => accepted,
+                Err(_) => break,
+            },
+            _ = &mut shutdown => break,
+        };
         println!("接受新连接: {}", stream.peer_addr().unwrap());
         let command_sender_clone = command_sender.clone();
-        let broadcast_rx = broadcast_tx.subscribe();
+        let outbound = Arc::new(Outbound::new(outbound, metrics.clone()));
+        connections.lock().push(outbound.clone());
+        let sessions = sessions.clone();
+        let guard = connection_guard.clone();
 
         tokio::spawn(async move {
-            handle_connection(stream, command_sender_clone, broadcast_rx).await;
+            handle_connection(stream, command_sender_clone, outbound.clone(), sessions).await;
+            outbound.close();
+            drop(guard);
         });
     }
+
+    // 停止接受新连接；已有连接在引擎退出、剩余回报发完后断开
+    drop(listener);
+    drop(command_sender);
+    drop(connection_guard);
+    let _ = connections_done.recv().await;
+}
+
+// 把一条引擎输出编码后放入所有连接的出站队列
+fn broadcast(output: EngineOutput, connections: &Mutex<Vec<Arc<Outbound>>>, sessions: &SessionConfig) {
+    if let EngineOutput::Traced(mut breadcrumb) = output {
+        // 本条命令的输出都已编码并放入出站队列，标记排在来源连接的这些输出之后
+        breadcrumb.encoded = Some(Instant::now());
+        let connections = connections.lock();
+        if let Some(connection) = connections.iter().find(|connection| connection.id == breadcrumb.connection_id) {
+            connection.push(OutboundMessage { payload: Bytes::new(), essential: true, trace: Some(breadcrumb) });
+        }
+        return;
+    }
+    if let Some(audit) = &sessions.audit {
+        audit.record_output(&output);
+    }
+    let essential = is_essential(&output);
+    let route = route(&output);
+    let server_msg = server_message(output);
+    // 启用网关时执行回报在编码前分配序号并写入日志，所有连接收到同样的序号
+    let server_msg = match &sessions.gateway {
+        Some(gateway) => gateway.sequence(server_msg),
+        None => server_msg,
+    };
+    let _span = tracing::debug_span!("encode", essential).entered();
+    let msg_bytes_res = bincode::encode_to_vec(server_msg, config::standard());
+    match msg_bytes_res {
+        Ok(msg_bytes) => {
+            let message = OutboundMessage { payload: Bytes::from(msg_bytes), essential, trace: None };
+            // 当没有客户端连接时消息直接丢弃，这是正常现象
+            connections
+                .lock()
+                .retain(|connection| !connection.receives(route) || connection.push(message.clone()));
+        }
+        Err(e) => {
+            eprintln!("Bincode encoding error in broadcaster: {:?}", e);
+        }
+    }
+}
+
+// 只有公共行情可以被合并或丢弃；成交、交易状态变化，以及确认、拒绝、撤单回报、查询结果等
+// 发给用户本人的回报和应答必须送达
+pub fn is_essential(output: &EngineOutput) -> bool {
+    match output {
+        EngineOutput::MarkPrice(_) | EngineOutput::ImpliedQuote(_) => false,
+        EngineOutput::Batch(outputs) => outputs.iter().any(is_essential),
+        _ => true,
+    }
+}
+
+// 引擎输出的接收方
+#[derive(Debug, Clone, Copy)]
+enum Route {
+    // 成交、交易状态等公共消息发给所有连接
+    All,
+    // 对带请求 ID 命令和未登录连接命令的应答只发给发出命令的连接
+    Connection(u64),
+    // 确认、拒绝、回报和查询结果只发给该用户的连接
+    User(u64),
+}
+
+fn route(output: &EngineOutput) -> Route {
+    match output {
+        EngineOutput::Response { connection_id, .. } | EngineOutput::Direct { connection_id, .. } => {
+            Route::Connection(*connection_id)
+        }
+        EngineOutput::Confirmation(confirmation) => Route::User(confirmation.user_id),
+        EngineOutput::Reject(reject) => Route::User(reject.user_id),
+        EngineOutput::Position(report) => Route::User(report.user_id),
+        EngineOutput::CancelAck(ack) => Route::User(ack.user_id),
+        EngineOutput::FillEstimate(estimate) => Route::User(estimate.user_id),
+        EngineOutput::ExecutionReport(report) => Route::User(report.user_id),
+        EngineOutput::MarketData(snapshot) => Route::User(snapshot.user_id),
+        EngineOutput::Trade(_)
+        | EngineOutput::TradingStatus(_)
+        | EngineOutput::MarkPrice(_)
+        | EngineOutput::ImpliedQuote(_)
+        | EngineOutput::Traced(_)
+        | EngineOutput::Batch(_) => Route::All,
+    }
+}
+
+// 引擎输出对应的客户端消息
+pub fn server_message(output: EngineOutput) -> ServerMessage {
+    match output {
+        EngineOutput::Trade(trade) => ServerMessage::Trade(trade),
+        EngineOutput::Confirmation(conf) => ServerMessage::Confirmation(conf),
+        EngineOutput::Reject(reject) => ServerMessage::Reject(reject),
+        EngineOutput::Position(report) => ServerMessage::Position(report),
+        EngineOutput::TradingStatus(status) => ServerMessage::TradingStatus(status),
+        EngineOutput::CancelAck(ack) => ServerMessage::CancelAck(ack),
+        EngineOutput::FillEstimate(estimate) => ServerMessage::FillEstimate(estimate),
+        EngineOutput::ExecutionReport(report) => ServerMessage::ExecutionReport(report),
+        EngineOutput::MarketData(snapshot) => ServerMessage::MarketData(snapshot),
+        EngineOutput::MarkPrice(price) => ServerMessage::MarkPrice(price),
+        EngineOutput::ImpliedQuote(quote) => ServerMessage::ImpliedQuote(quote),
+        EngineOutput::Response { request_id, output, .. } => {
+            ServerMessage::Response { request_id, message: Box::new(server_message(*output)) }
+        }
+        EngineOutput::Direct { output, .. } => server_message(*output),
+        EngineOutput::Traced(_) => unreachable!("面包屑由广播任务处理，不发送给客户端"),
+        EngineOutput::Batch(_) => unreachable!("批量输出由广播任务拆开后逐条转换"),
+    }
 }
 
 // 处理单个客户端连接
 async fn handle_connection(
     stream: TcpStream,
     command_sender: mpsc::UnboundedSender<EngineCommand>,
-    mut broadcast_rx: broadcast::Receiver<Bytes>,
+    outbound: Arc<Outbound>,
+    sessions: Arc<SessionConfig>,
 ) {
-    let mut framed = Framed::new(stream, LengthDelimitedCodec::new());
-    let config = config::standard();
+    // 审计轨迹记录客户端 IP
+    let peer_ip = stream.peer_addr().ok().map(|addr| addr.ip());
+    let mut framed = Framed::new(TimestampedStream::new(stream, sessions.rx_timestamps), LengthDelimitedCodec::new());
+    let config = codec::decode_config();
+    let mut conflation = Conflation::new(ConflationConfig::default());
+    let mut session = Session::new();
+    let mut heartbeat = sessions.heartbeat_interval.map(|interval| {
+        let mut timer = tokio::time::interval_at(tokio::time::Instant::now() + interval, interval);
+        timer.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+        timer
+    });
 
     loop {
         tokio::select! {
@@ -72,11 +558,158 @@ async fn handle_connection(
             result = framed.next() => {
                 match result {
                     Some(Ok(data)) => {
-                        match bincode::decode_from_slice(&data, config) {
+                        match fault::inject(FaultPoint::InboundFrame) {
+                            Some(FaultAction::Drop) => continue,
+                            Some(FaultAction::Delay(delay)) => tokio::time::sleep(delay).await,
+                            _ => {}
+                        }
+                        // 延迟预算关闭时不取时间
+                        let received = outbound.metrics.latency_budget.is_enabled().then(Instant::now);
+                        let decoded = tracing::debug_span!("decode", bytes = data.len())
+                            .in_scope(|| bincode::decode_from_slice(&data, config));
+                        match decoded {
                             Ok((decoded, _len)) => {
+                                session.touch();
+                                let breadcrumb = received.map(|received| Breadcrumb::new(outbound.id, received, Instant::now()));
+                                let decoded: ClientMessage = decoded;
+                                // 带请求 ID 的消息按内层消息处理，直接回复和引擎的应答都回显该 ID
+                                let (request_id, decoded) = match decoded {
+                                    ClientMessage::Request { request_id, message } => (Some(request_id), *message),
+                                    message => (None, message),
+                                };
+                                // 登录、会话恢复和会话层的拒绝直接回复本连接，不经过引擎
+                                let mut replay = Vec::new();
+                                let reply = match &decoded {
+                                    // 心跳只用于刷新活跃时间
+                                    ClientMessage::Heartbeat => continue,
+                                    ClientMessage::Request { .. } => {
+                                        eprintln!("不支持嵌套的请求 ID，忽略该消息");
+                                        continue;
+                                    }
+                                    ClientMessage::Logon(request) => {
+                                        let status = session.logon(&sessions, request);
+                                        let user_id = session.user_id().filter(|_| status == LogonStatus::Accepted);
+                                        if let Some(user_id) = user_id {
+                                            outbound.serve_user(user_id);
+                                        }
+                                        Some(ServerMessage::Logon(LogonResponse { user_id: user_id.unwrap_or(0), status }))
+                                    }
+                                    ClientMessage::Resume(request) => Some(match session.user_id() {
+                                        Some(user_id) => {
+                                            // 未启用网关时没有可补发的回报
+                                            let resumed = sessions
+                                                .gateway
+                                                .as_ref()
+                                                .map(|gateway| gateway.resume(user_id, request.last_received_seq))
+                                                .unwrap_or_default();
+                                            let response = ResumeResponse {
+                                                last_inbound_seq: resumed.last_inbound_seq,
+                                                last_outbound_seq: resumed.last_outbound_seq,
+                                                replayed: resumed.missed.len() as u64,
+                                            };
+                                            replay = resumed.missed;
+                                            ServerMessage::Resume(response)
+                                        }
+                                        // 回报按用户编号，恢复前必须先登录
+                                        None => ServerMessage::Reject(session_reject(&decoded, RejectReason::Unauthenticated)),
+                                    }),
+                                    message => session
+                                        .authorize(&sessions, message)
+                                        .err()
+                                        .map(|reason| ServerMessage::Reject(session_reject(message, reason))),
+                                };
+                                if let Some(reply) = reply {
+                                    let reply = match request_id {
+                                        Some(request_id) => ServerMessage::Response { request_id, message: Box::new(reply) },
+                                        None => reply,
+                                    };
+                                    if !send_direct(&mut framed, &mut session, reply).await {
+                                        break;
+                                    }
+                                    let mut sent = true;
+                                    for (seq, report) in replay {
+                                        let message = ServerMessage::Sequenced {
+                                            seq,
+                                            message: Box::new(ServerMessage::ExecutionReport(report)),
+                                        };
+                                        sent = send_direct(&mut framed, &mut session, message).await;
+                                        if !sent {
+                                            break;
+                                        }
+                                    }
+                                    if !sent {
+                                        break;
+                                    }
+                                    continue;
+                                }
+
                                 let engine_command = match decoded {
-                                    ClientMessage::NewOrder(req) => EngineCommand::NewOrder(req),
+                                    ClientMessage::NewOrder(req) => {
+                                        session.record_order_user(req.user_id);
+                                        EngineCommand::NewOrder(req)
+                                    }
                                     ClientMessage::CancelOrder(req) => EngineCommand::CancelOrder(req),
+                                    ClientMessage::AmendOrder(req) => EngineCommand::AmendOrder(req),
+                                    ClientMessage::BlockTrade(req) => EngineCommand::BlockTrade(req),
+                                    ClientMessage::QueryPosition(query) => EngineCommand::QueryPosition(query),
+                                    ClientMessage::EstimateFill(request) => EngineCommand::EstimateFill(request),
+                                    ClientMessage::QueryMarketData(query) => EngineCommand::QueryMarketData(query),
+                                    ClientMessage::SpreadOrder(request) => EngineCommand::SpreadOrder(request),
+                                    ClientMessage::BasketOrder(request) => EngineCommand::BasketOrder(request),
+                                    // 会话层已确认两条腿都属于会话用户，登记第一条腿的用户即可覆盖两条
+                                    ClientMessage::OcoOrder(request) => {
+                                        session.record_order_user(request.first.user_id);
+                                        EngineCommand::OcoOrder(request)
+                                    }
+                                    ClientMessage::TimedOrder(request) => {
+                                        session.record_order_user(request.order.user_id);
+                                        EngineCommand::TimedOrder(request)
+                                    }
+                                    ClientMessage::ReduceOnlyOrder(req) => {
+                                        session.record_order_user(req.user_id);
+                                        EngineCommand::ReduceOnlyOrder(req)
+                                    }
+                                    ClientMessage::Logon(_)
+                                    | ClientMessage::Heartbeat
+                                    | ClientMessage::Resume(_)
+                                    | ClientMessage::Request { .. } => {
+                                        unreachable!("登录、心跳、会话恢复和嵌套请求已在会话层处理")
+                                    }
+                                };
+                                if let (Some(gateway), Some(user_id)) = (&sessions.gateway, session.user_id()) {
+                                    let seq = gateway.record_inbound(user_id);
+                                    tracing::trace!(user_id, seq, "入站消息");
+                                }
+                                if let Some(audit) = &sessions.audit {
+                                    audit.record_request(outbound.id, peer_ip, &engine_command);
+                                }
+                                // 应答默认按用户分发；未登录连接和代其他用户提交的命令（如卖方申报的大宗交易）
+                                // 的应答只发给本连接
+                                let direct = session.user_id().is_none_or(|user_id| engine_command.user_id() != Some(user_id));
+                                let engine_command = match request_id {
+                                    Some(request_id) => EngineCommand::Request {
+                                        request_id,
+                                        connection_id: outbound.id,
+                                        command: Box::new(engine_command),
+                                    },
+                                    None if direct => {
+                                        EngineCommand::Direct { connection_id: outbound.id, command: Box::new(engine_command) }
+                                    }
+                                    None => engine_command,
+                                };
+                                // 接收时间戳包在最外层，引擎据此测量线上到撮合完成的耗时
+                                let engine_command = match framed.get_ref().last_timestamp() {
+                                    Some(received_at_ns) => {
+                                        EngineCommand::Received { received_at_ns, command: Box::new(engine_command) }
+                                    }
+                                    None => engine_command,
+                                };
+                                let engine_command = match breadcrumb {
+                                    Some(mut breadcrumb) => {
+                                        breadcrumb.enqueued = Instant::now();
+                                        EngineCommand::Traced { breadcrumb: Box::new(breadcrumb), command: Box::new(engine_command) }
+                                    }
+                                    None => engine_command,
                                 };
 
                                 if command_sender.send(engine_command).is_err() {
@@ -96,14 +729,128 @@ async fn handle_connection(
                     None => break, // 连接已关闭
                 }
             }
-            // 从广播通道接收数据并发送给客户端
-            Ok(msg) = broadcast_rx.recv() => {
-                if framed.send(msg).await.is_err() {
-                    println!("发送数据到客户端失败");
+            // 出站队列中有新消息，按顺序发送给客户端
+            _ = outbound.notify.notified() => {
+                if !drain_outbound(&mut framed, &mut session, &outbound, &mut conflation).await {
+                    break;
+                }
+            }
+            // 按配置的间隔向客户端发送心跳
+            _ = next_heartbeat(&mut heartbeat) => {
+                if !send_direct(&mut framed, &mut session, ServerMessage::Heartbeat).await {
                     break;
                 }
             }
+            // 客户端连续错过多个心跳间隔没有发送任何消息
+            _ = idle_timeout(session.idle_deadline(&sessions)) => {
+                println!("连接错过 {} 次心跳，断开连接", sessions.max_missed_heartbeats);
+                break;
+            }
+        }
+    }
+
+    // 断线撤单：撤销该连接上用户的所有挂单，撤单回报照常推送；服务器关闭时引擎已退出，无需撤单
+    if sessions.cancel_on_disconnect && !outbound.finished.load(Ordering::Acquire) {
+        for user_id in session.disconnect_users() {
+            if command_sender.send(EngineCommand::CancelUserOrders(user_id)).is_err() {
+                eprintln!("命令通道已关闭，无法执行断线撤单");
+                break;
+            }
+        }
+    }
+    println!("连接 {} 已关闭", framed.get_ref().get_ref().peer_addr().unwrap());
+}
+
+// 发送出站队列中的所有消息；发送失败或连接被判定为慢消费者时返回 false
+async fn drain_outbound(
+    framed: &mut Framed<TimestampedStream, LengthDelimitedCodec>,
+    session: &mut Session,
+    outbound: &Outbound,
+    conflation: &mut Conflation,
+) -> bool {
+    loop {
+        if outbound.closed.load(Ordering::Acquire) {
+            println!("连接跟不上推送速度，断开连接");
+            return false;
+        }
+        let Some((message, backlog)) = outbound.pop() else {
+            return !outbound.finished.load(Ordering::Acquire);
+        };
+        if let Some(breadcrumb) = &message.trace {
+            outbound.metrics.latency_budget.record(breadcrumb, Instant::now());
+            continue;
+        }
+        // 推送模式变化时先通知客户端
+        if let Some(mode) = conflation.update(backlog) {
+            if !send_direct(framed, session, ServerMessage::MarketDataMode(mode)).await {
+                return false;
+            }
+        }
+        if conflation.should_forward(message.essential) {
+            match fault::inject(FaultPoint::OutboundFrame) {
+                Some(FaultAction::Drop) => continue,
+                Some(FaultAction::Delay(delay)) => tokio::time::sleep(delay).await,
+                _ => {}
+            }
+            let span = tracing::debug_span!("send", bytes = message.payload.len(), backlog);
+            if framed.send(message.payload).instrument(span).await.is_err() {
+                println!("发送数据到客户端失败");
+                return false;
+            }
+            session.next_outbound_seq();
+        }
+    }
+}
+
+// 直接发送给本连接的消息，不经过出站队列；发送失败时返回 false
+async fn send_direct(framed: &mut Framed<TimestampedStream, LengthDelimitedCodec>, session: &mut Session, message: ServerMessage) -> bool {
+    let payload = bincode::encode_to_vec(message, config::standard()).expect("服务器消息编码失败");
+    if framed.send(Bytes::from(payload)).await.is_err() {
+        println!("发送数据到客户端失败");
+        return false;
+    }
+    session.next_outbound_seq();
+    true
+}
+
+// 会话层拒绝一条业务消息时的回报
+fn session_reject(message: &ClientMessage, reason: RejectReason) -> OrderReject {
+    let no_symbol = String::new();
+    let (user_id, symbol) = match message {
+        ClientMessage::NewOrder(request) => (request.user_id, &request.symbol),
+        ClientMessage::CancelOrder(request) => (request.user_id, &request.symbol),
+        ClientMessage::AmendOrder(request) => (request.user_id, &request.symbol),
+        ClientMessage::BlockTrade(request) => (request.buyer_user_id, &request.symbol),
+        ClientMessage::QueryPosition(query) => (query.user_id, &query.symbol),
+        ClientMessage::EstimateFill(request) => (request.user_id, &request.symbol),
+        ClientMessage::QueryMarketData(query) => (query.user_id, &query.symbol),
+        ClientMessage::SpreadOrder(request) => (request.user_id, &request.spread),
+        // 篮子订单以第一条腿的合约回报
+        ClientMessage::BasketOrder(request) => (request.user_id, request.legs.first().map_or(&no_symbol, |leg| &leg.symbol)),
+        ClientMessage::OcoOrder(request) => (request.first.user_id, &request.first.symbol),
+        ClientMessage::TimedOrder(request) => (request.order.user_id, &request.order.symbol),
+        ClientMessage::ReduceOnlyOrder(request) => (request.user_id, &request.symbol),
+        ClientMessage::Logon(request) => (0, &request.api_key),
+        ClientMessage::Heartbeat | ClientMessage::Resume(_) => (0, &no_symbol),
+        ClientMessage::Request { message, .. } => return session_reject(message, reason),
+    };
+    OrderReject { user_id, symbol: symbol.clone(), reason }
+}
+
+// 心跳定时器的下一次触发，未配置心跳时永不完成
+async fn next_heartbeat(timer: &mut Option<tokio::time::Interval>) {
+    match timer {
+        Some(timer) => {
+            timer.tick().await;
         }
+        None => std::future::pending().await,
+    }
+}
+
+// 空闲超时的截止时间到达时完成，未配置超时时永不完成
+async fn idle_timeout(deadline: Option<Instant>) {
+    match deadline {
+        Some(deadline) => tokio::time::sleep_until(deadline.into()).await,
+        None => std::future::pending().await,
     }
-    println!("连接 {} 已关闭", framed.get_ref().peer_addr().unwrap());
 }
\ No newline at end of file
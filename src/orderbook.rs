@@ -1,5 +1,34 @@
-use crate::protocol::{NewOrderRequest, OrderConfirmation, OrderType, TradeNotification};
-use std::collections::BTreeMap;
+use crate::allocation::{self, AllocationPolicy};
+use crate::error::EngineError;
+use crate::id::IdGenerator;
+use crate::sequencer::Sequencer;
+use crate::protocol::{
+    DepthLevel, ExecutionReport, NewOrderRequest, OrderConfirmation, OrderStatus, OrderType, RestingOrder,
+    TradeNotification,
+};
+use rustc_hash::FxHashMap;
+use std::cmp::Reverse;
+use std::collections::{BTreeMap, BinaryHeap};
+use std::mem::size_of;
+use std::sync::Arc;
+
+// 撮合一个订单的结果：(成交列表, 新挂单的确认信息)
+pub type MatchResult = (Vec<TradeNotification>, Option<OrderConfirmation>);
+
+// 订单簿的内存占用，用于容量规划。bytes 按各结构的元素大小计算，不含 BTreeMap 节点和分配器的额外开销
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct MemoryUsage {
+    pub bid_levels: usize,
+    pub ask_levels: usize,
+    pub resting_orders: usize,
+    // 节点池已使用的槽位数（含空闲槽位）和已分配的容量
+    pub pool_slots: usize,
+    pub pool_capacity: usize,
+    pub bytes: usize,
+}
+
+// 节点池中订单节点的下标。用 u32 而不是 usize，链表指针各省一半空间，单个订单簿最多约 42 亿个节点
+pub type NodeHandle = u32;
 
 // 订单簿中的一个节点，代表一个具体的订单
 #[derive(Clone)]
@@ -8,20 +37,69 @@ pub struct OrderNode {
     pub order_id: u64,
     pub price: u64,
     pub quantity: u64,
+    // 累计已成交数量，quantity 为剩余数量
+    pub filled_quantity: u64,
     pub order_type: OrderType,
     // 指向同一个价格队列中的下一个订单
-    pub next: Option<usize>,
+    pub next: Option<NodeHandle>,
     // 指向同一个价格队列中的上一个订单
-    pub prev: Option<usize>,
+    pub prev: Option<NodeHandle>,
 }
 
 // 代表一个价格层级的所有订单，以双向链表形式存在
 #[derive(Clone)]
 struct PriceLevel {
     // 链表头
-    head: Option<usize>,
+    head: Option<NodeHandle>,
     // 链表尾
-    tail: Option<usize>,
+    tail: Option<NodeHandle>,
+}
+
+// 撮合一个价格层级时复用的缓冲区：层级上的挂单、各挂单的剩余数量和分配结果。
+// 容量随最长的价格层级增长后保留，撮合路径上不再为每个层级分配内存
+#[derive(Clone, Default)]
+struct MatchScratch {
+    resting: Vec<NodeHandle>,
+    quantities: Vec<u64>,
+    fills: Vec<u64>,
+}
+
+// 订单簿的逐笔（L3）变化，按发生顺序记录，供副本订单簿重建同样的挂单队列
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum BookEvent {
+    // 挂单进入所在价格队列的队尾
+    Added { order_id: u64, user_id: u64, order_type: OrderType, price: u64, quantity: u64 },
+    // 挂单成交 quantity，剩余为 0 时移出订单簿
+    Executed { order_id: u64, quantity: u64 },
+    // 挂单的剩余数量原地减少为 quantity
+    Reduced { order_id: u64, quantity: u64 },
+    // 挂单被撤销
+    Removed { order_id: u64 },
+}
+
+// 前 depth 档深度校验和的缓存。bid_floor、ask_ceiling 是缓存覆盖的最低买价和最高卖价，
+// 该侧不足 depth 档时为 None，此时该侧任何变化都会改变前 depth 档
+#[derive(Debug, Clone, Copy)]
+struct CachedChecksum {
+    depth: usize,
+    bid_floor: Option<u64>,
+    ask_ceiling: Option<u64>,
+    checksum: u32,
+}
+
+// 深度消息的校验和（CRC-32/IEEE）：先卖盘从低到高、再买盘从高到低，依次取每档的价格和数量（各 8 字节小端）。
+// 订阅方按收到的档位计算同样的值，不一致说明本地订单簿已经失步
+pub fn depth_checksum(bids: &[DepthLevel], asks: &[DepthLevel]) -> u32 {
+    let mut crc = !0u32;
+    for level in asks.iter().chain(bids) {
+        for byte in level.price.to_le_bytes().into_iter().chain(level.quantity.to_le_bytes()) {
+            crc ^= u32::from(byte);
+            for _ in 0..8 {
+                crc = if crc & 1 == 1 { (crc >> 1) ^ 0xEDB8_8320 } else { crc >> 1 };
+            }
+        }
+    }
+    !crc
 }
 
 // 订单簿核心结构
@@ -33,12 +111,39 @@ pub struct OrderBook {
     asks: BTreeMap<u64, PriceLevel>,
     // 订单节点池，所有订单实体都存放在这里
     orders: Vec<OrderNode>,
-    // 从 order_id 到 Vec 索引的映射，用于快速查找
-    order_id_to_index: BTreeMap<u64, usize>,
+    // 从 order_id 到 Vec 索引的映射，用于快速查找。订单号是整数，用 FxHash 代替默认的 SipHash，
+    // 撤单和成交路径上每次查找只需一次乘法；遍历顺序不确定，需要有序时由调用方排序
+    order_id_to_index: FxHashMap<u64, NodeHandle>,
+    // 每个用户挂单的剩余数量 [买, 卖]，即持仓限额检查中的挂单敞口
+    open_quantity: FxHashMap<u64, [u64; 2]>,
     // 空闲节点链表的头指针，用于复用已删除的订单节点空间
-    free_list_head: Option<usize>,
-    // 用于生成唯一订单 ID
-    next_order_id: u64,
+    free_list_head: Option<NodeHandle>,
+    // 空闲链表中的节点数
+    free_slots: usize,
+    // 用于生成唯一订单 ID，可以在多个订单簿之间共享
+    ids: Arc<IdGenerator>,
+    // 为成交分配成交号和时间戳
+    sequencer: Arc<Sequencer>,
+    // 同一价格层级上的成交分配算法
+    allocation_policy: AllocationPolicy,
+    // 最小变动价位
+    tick_size: u64,
+    // 尚未取走的执行回报，为 None 时不生成执行回报
+    execution_reports: Option<Vec<ExecutionReport>>,
+    // 尚未取走的逐笔变化，为 None 时不记录
+    book_events: Option<Vec<BookEvent>>,
+    // 最近一次计算的深度校验和，只有落在其覆盖档位内的变化才使其失效
+    cached_checksum: Option<CachedChecksum>,
+    scratch: MatchScratch,
+    // 订单簿的合约名，成交回报共享这一份。引擎创建订单簿时从 SymbolPool 设置；
+    // 单独使用的订单簿在第一次成交时按订单的合约名驻留
+    symbol: Arc<str>,
+}
+
+impl Default for OrderBook {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl OrderBook {
@@ -47,136 +152,473 @@ impl OrderBook {
             bids: BTreeMap::new(),
             asks: BTreeMap::new(),
             orders: Vec::with_capacity(1_000_000), // 预分配一百万个订单的空间
-            order_id_to_index: BTreeMap::new(),
+            order_id_to_index: FxHashMap::default(),
+            open_quantity: FxHashMap::default(),
             free_list_head: None,
-            next_order_id: 1,
+            free_slots: 0,
+            ids: Arc::new(IdGenerator::sequential()),
+            sequencer: Arc::new(Sequencer::default()),
+            allocation_policy: AllocationPolicy::Fifo,
+            tick_size: 1,
+            execution_reports: None,
+            book_events: None,
+            cached_checksum: None,
+            scratch: MatchScratch::default(),
+            symbol: Arc::from(""),
+        }
+    }
+
+    // 校验订单的价格和数量
+    pub fn validate(&self, request: &NewOrderRequest) -> Result<(), EngineError> {
+        if request.quantity == 0 {
+            return Err(EngineError::InvalidQuantity);
+        }
+        if request.price == 0 || !request.price.is_multiple_of(self.tick_size) {
+            return Err(EngineError::InvalidPrice { price: request.price, tick_size: self.tick_size });
         }
+        Ok(())
     }
 
     // 撮合一个新订单
-    // 返回值是一个元组，包含 (成交列表, 新挂单的确认信息)
-    pub fn match_order(&mut self, mut request: NewOrderRequest) -> (Vec<TradeNotification>, Option<OrderConfirmation>) {
+    // 订单无效时返回错误且订单簿保持不变，否则返回 (成交列表, 新挂单的确认信息)
+    pub fn match_order(&mut self, mut request: NewOrderRequest) -> Result<MatchResult, EngineError> {
+        self.validate(&request)?;
+        // 新订单的订单号在撮合前分配，成交回报和挂单确认使用同一个订单号
+        let order_id = self.ids.next_id();
         let mut trades = Vec::new();
         let mut remaining_quantity = request.quantity;
-        let symbol = request.symbol.clone(); // Clone once here
-
-        // 移除已完全成交的对手订单ID列表
-        let mut orders_to_remove = Vec::new();
-        // 需要从价格map中移除的key列表
-        let mut prices_to_remove = Vec::new();
-
-        match request.order_type {
-            OrderType::Buy => {
-                // 对手盘是卖单(asks)，从价格最低的开始匹配
-                for (&price, level) in self.asks.iter() {
-                    if remaining_quantity == 0 || request.price < price {
-                        break; // 新订单已完全成交，或对手价格已高于买价
-                    }
 
-                    let mut current_node_idx = level.head;
-                    while let Some(node_idx) = current_node_idx {
-                        let counter_order = &mut self.orders[node_idx];
-                        let trade_quantity = std::cmp::min(remaining_quantity, counter_order.quantity);
-
-                        trades.push(TradeNotification {
-                            trade_id: 0, 
-                            symbol: symbol.clone(),
-                            matched_price: counter_order.price,
-                            matched_quantity: trade_quantity,
-                            buyer_user_id: request.user_id,
-                            buyer_order_id: self.next_order_id, // 假设新订单ID
-                            seller_user_id: counter_order.user_id,
-                            seller_order_id: counter_order.order_id,
-                            timestamp: 0,
-                        });
-
-                        remaining_quantity -= trade_quantity;
-                        counter_order.quantity -= trade_quantity;
-
-                        if counter_order.quantity == 0 {
-                            orders_to_remove.push(counter_order.order_id);
-                        }
-                        current_node_idx = counter_order.next;
+        // 从对手盘的最优价格开始逐层撮合：买单对卖一价，卖单对买一价
+        while remaining_quantity > 0 {
+            let best_price = match request.order_type {
+                OrderType::Buy => self.best_ask().filter(|&price| price <= request.price),
+                OrderType::Sell => self.best_bid().filter(|&price| price >= request.price),
+            };
+            let Some(price) = best_price else {
+                break; // 对手价格已不可成交
+            };
+            remaining_quantity -= self.match_level(&request, order_id, price, remaining_quantity, &mut trades);
+        }
 
-                        if remaining_quantity == 0 {
-                            break;
-                        }
-                    }
-                    if level.head.is_some() && self.orders[level.head.unwrap()].quantity == 0 {
-                        prices_to_remove.push(price);
-                    }
-                }
+        // 如果新订单还有剩余数量，则将其添加到订单簿中
+        if remaining_quantity > 0 {
+            request.quantity = remaining_quantity;
+            let user_id = self.add_order(request, order_id);
+            let confirmation = OrderConfirmation { order_id, user_id };
+            Ok((trades, Some(confirmation)))
+        } else {
+            Ok((trades, None)) // 完全成交，没有新挂单
+        }
+    }
+
+    // 在对手盘的一个价格层级上按分配算法撮合，返回本层的成交总量
+    fn match_level(
+        &mut self,
+        request: &NewOrderRequest,
+        order_id: u64,
+        price: u64,
+        quantity: u64,
+        trades: &mut Vec<TradeNotification>,
+    ) -> u64 {
+        let level = match request.order_type {
+            OrderType::Buy => &self.asks[&price],
+            OrderType::Sell => &self.bids[&price],
+        };
+        let mut scratch = std::mem::take(&mut self.scratch);
+        scratch.resting.clear();
+        scratch.quantities.clear();
+        let mut current = level.head;
+        while let Some(idx) = current {
+            scratch.resting.push(idx);
+            scratch.quantities.push(self.orders[idx as usize].quantity);
+            current = self.orders[idx as usize].next;
+        }
+        allocation::allocate_into(self.allocation_policy, &scratch.quantities, quantity, &mut scratch.fills);
+
+        let symbol = self.interned_symbol(&request.symbol);
+        let mut matched = 0;
+        for (&idx, &fill) in scratch.resting.iter().zip(&scratch.fills) {
+            if fill == 0 {
+                continue;
+            }
+            let counter_order = &mut self.orders[idx as usize];
+            counter_order.quantity -= fill;
+            counter_order.filled_quantity += fill;
+            release_open(&mut self.open_quantity, counter_order.user_id, counter_order.order_type, fill);
+            matched += fill;
+
+            let (buyer_user_id, buyer_order_id, seller_user_id, seller_order_id) = match request.order_type {
+                OrderType::Buy => (request.user_id, order_id, counter_order.user_id, counter_order.order_id),
+                OrderType::Sell => (counter_order.user_id, counter_order.order_id, request.user_id, order_id),
+            };
+            let (trade_id, timestamp) = self.sequencer.next_trade();
+            trades.push(TradeNotification {
+                trade_id,
+                symbol: symbol.clone(),
+                matched_price: price,
+                matched_quantity: fill,
+                buyer_user_id,
+                buyer_order_id,
+                seller_user_id,
+                seller_order_id,
+                timestamp,
+                is_block_trade: false,
+            });
+
+            let counter = (counter_order.user_id, counter_order.order_id, counter_order.order_type);
+            let (counter_filled, counter_leaves) = (counter_order.filled_quantity, counter_order.quantity);
+            self.record_book_event(BookEvent::Executed { order_id: counter.1, quantity: fill });
+            self.touch_level(counter.2, price);
+            if self.execution_reports.is_some() {
+                let trade = trades.last().expect("刚写入的成交");
+                let filled = request.quantity - quantity + matched;
+                let aggressor = (request.user_id, order_id, request.order_type);
+                self.record_execution(trade, aggressor, filled, request.quantity - filled);
+                self.record_execution(trade, counter, counter_filled, counter_leaves);
             }
-            OrderType::Sell => {
-                // 对手盘是买单(bids)，从价格最高的开始匹配
-                for (&price, level) in self.bids.iter().rev() { // 使用 .rev() 来反向遍历
-                    if remaining_quantity == 0 || request.price > price {
-                        break; // 新订单已完全成交，或对手价格已低于卖价
-                    }
 
-                    let mut current_node_idx = level.head;
-                    while let Some(node_idx) = current_node_idx {
-                        let counter_order = &mut self.orders[node_idx];
-                        let trade_quantity = std::cmp::min(remaining_quantity, counter_order.quantity);
-
-                        trades.push(TradeNotification {
-                            trade_id: 0,
-                            symbol: symbol.clone(),
-                            matched_price: counter_order.price,
-                            matched_quantity: trade_quantity,
-                            buyer_user_id: counter_order.user_id,
-                            buyer_order_id: counter_order.order_id,
-                            seller_user_id: request.user_id,
-                            seller_order_id: self.next_order_id, // 假设新订单ID
-                            timestamp: 0,
-                        });
-
-                        remaining_quantity -= trade_quantity;
-                        counter_order.quantity -= trade_quantity;
-
-                        if counter_order.quantity == 0 {
-                            orders_to_remove.push(counter_order.order_id);
-                        }
-                        current_node_idx = counter_order.next;
+            // 已完全成交的订单移出订单簿，价格层级在清空时由 remove_order 一并移除
+            if counter_leaves == 0 {
+                self.remove_order(counter.1);
+            }
+        }
+        self.scratch = scratch;
+        matched
+    }
 
-                        if remaining_quantity == 0 {
-                            break;
-                        }
-                    }
-                    if level.head.is_some() && self.orders[level.head.unwrap()].quantity == 0 {
-                        prices_to_remove.push(price);
-                    }
-                }
+    // 由订单簿生成执行回报，引擎每次操作订单簿后通过 take_execution_reports 取走
+    pub fn enable_execution_reports(&mut self) {
+        self.execution_reports.get_or_insert_with(Vec::new);
+    }
+
+    // 取走上次调用以来生成的执行回报，顺序与成交顺序一致，每笔成交先主动方（竞价撮合时为买方）后被动方
+    pub fn take_execution_reports(&mut self) -> Vec<ExecutionReport> {
+        self.execution_reports.as_mut().map(std::mem::take).unwrap_or_default()
+    }
+
+    // 记录挂单的逐笔变化，引擎每次处理完命令后通过 take_book_events 取走
+    pub fn enable_book_events(&mut self) {
+        self.book_events.get_or_insert_with(Vec::new);
+    }
+
+    pub fn take_book_events(&mut self) -> Vec<BookEvent> {
+        self.book_events.as_mut().map(std::mem::take).unwrap_or_default()
+    }
+
+    fn record_book_event(&mut self, event: BookEvent) {
+        if let Some(events) = self.book_events.as_mut() {
+            events.push(event);
+        }
+    }
+
+    // 某一侧某个价格档位的数量发生了变化
+    fn touch_level(&mut self, order_type: OrderType, price: u64) {
+        let Some(cached) = self.cached_checksum else { return };
+        let affected = match order_type {
+            OrderType::Buy => cached.bid_floor.is_none_or(|floor| price >= floor),
+            OrderType::Sell => cached.ask_ceiling.is_none_or(|ceiling| price <= ceiling),
+        };
+        if affected {
+            self.cached_checksum = None;
+        }
+    }
+
+    // 前 n 档深度及其校验和。前 n 档没有变化时直接使用缓存的校验和
+    pub fn depth_with_checksum(&mut self, n: usize) -> (Vec<DepthLevel>, Vec<DepthLevel>, u32) {
+        let (bids, asks) = self.depth(n);
+        let checksum = match self.cached_checksum {
+            Some(cached) if cached.depth == n => cached.checksum,
+            _ => {
+                let checksum = depth_checksum(&bids, &asks);
+                self.cached_checksum = Some(CachedChecksum {
+                    depth: n,
+                    bid_floor: (bids.len() == n).then(|| bids.last().map(|level| level.price)).flatten(),
+                    ask_ceiling: (asks.len() == n).then(|| asks.last().map(|level| level.price)).flatten(),
+                    checksum,
+                });
+                checksum
             }
+        };
+        (bids, asks, checksum)
+    }
+
+    // 前 n 档深度的校验和，见 depth_checksum
+    pub fn checksum_of_depth(&mut self, n: usize) -> u32 {
+        if let Some(cached) = self.cached_checksum.filter(|cached| cached.depth == n) {
+            return cached.checksum;
+        }
+        self.depth_with_checksum(n).2
+    }
+
+    fn record_execution(
+        &mut self,
+        trade: &TradeNotification,
+        (user_id, order_id, order_type): (u64, u64, OrderType),
+        cumulative_quantity: u64,
+        leaves_quantity: u64,
+    ) {
+        let Some(reports) = self.execution_reports.as_mut() else {
+            return;
+        };
+        let status = if leaves_quantity == 0 { OrderStatus::Filled } else { OrderStatus::PartiallyFilled };
+        reports.push(ExecutionReport {
+            user_id,
+            symbol: trade.symbol.to_string(),
+            order_id,
+            order_type,
+            status,
+            trade_id: trade.trade_id,
+            last_price: trade.matched_price,
+            last_quantity: trade.matched_quantity,
+            cumulative_quantity,
+            leaves_quantity,
+            link_id: None,
+            received_at_ns: None,
+        });
+    }
+
+    // 使用共享的 ID 生成器分配订单号，应在订单簿接收订单之前设置
+    pub fn set_id_generator(&mut self, ids: Arc<IdGenerator>) {
+        self.ids = ids;
+    }
+
+    // 使用共享的成交定序组件，应在订单簿产生成交之前设置
+    pub fn set_sequencer(&mut self, sequencer: Arc<Sequencer>) {
+        self.sequencer = sequencer;
+    }
+
+    pub fn set_symbol(&mut self, symbol: Arc<str>) {
+        self.symbol = symbol;
+    }
+
+    // 驻留的合约名；与 symbol 不同时（单独使用的订单簿）改为驻留 symbol
+    fn interned_symbol(&mut self, symbol: &str) -> Arc<str> {
+        if *self.symbol != *symbol {
+            self.symbol = Arc::from(symbol);
+        }
+        self.symbol.clone()
+    }
+
+    // 设置最小变动价位，之后价格不是其整数倍的订单会被拒绝
+    pub fn set_tick_size(&mut self, tick_size: u64) {
+        assert!(tick_size > 0, "最小变动价位必须大于 0");
+        self.tick_size = tick_size;
+    }
+
+    // 设置同一价格层级上多个挂单之间的成交分配算法
+    pub fn set_allocation_policy(&mut self, policy: AllocationPolicy) {
+        self.allocation_policy = policy;
+    }
+
+    // 买一价
+    pub fn best_bid(&self) -> Option<u64> {
+        self.bids.keys().next_back().copied()
+    }
+
+    // 卖一价
+    pub fn best_ask(&self) -> Option<u64> {
+        self.asks.keys().next().copied()
+    }
+
+    // 某个价格层级上所有订单的剩余数量之和
+    fn level_quantity(&self, level: &PriceLevel) -> u64 {
+        self.level_summary(0, level).quantity
+    }
+
+    fn level_summary(&self, price: u64, level: &PriceLevel) -> DepthLevel {
+        let mut summary = DepthLevel { price, quantity: 0, order_count: 0 };
+        let mut current = level.head;
+        while let Some(idx) = current {
+            summary.quantity += self.orders[idx as usize].quantity;
+            summary.order_count += 1;
+            current = self.orders[idx as usize].next;
         }
+        summary
+    }
+
+    // 返回买卖双方各自的前 n 档聚合深度
+    pub fn depth(&self, n: usize) -> (Vec<DepthLevel>, Vec<DepthLevel>) {
+        let bids = self
+            .bids
+            .iter()
+            .rev()
+            .take(n)
+            .map(|(&price, level)| self.level_summary(price, level))
+            .collect();
+        let asks = self
+            .asks
+            .iter()
+            .take(n)
+            .map(|(&price, level)| self.level_summary(price, level))
+            .collect();
+        (bids, asks)
+    }
 
-        // 移除已成交的订单和价格层级
-        for order_id in orders_to_remove {
-            self.remove_order(order_id);
+    // 估算以市价立即买入/卖出 quantity 的结果，不修改订单簿。
+    // 返回 (可成交数量, 成交金额, 最差价格)
+    pub fn estimate_fill(&self, order_type: OrderType, quantity: u64) -> (u64, u128, Option<u64>) {
+        let levels: Box<dyn Iterator<Item = (&u64, &PriceLevel)>> = match order_type {
+            OrderType::Buy => Box::new(self.asks.iter()),
+            OrderType::Sell => Box::new(self.bids.iter().rev()),
+        };
+        let (mut filled, mut notional, mut worst_price) = (0, 0, None);
+        for (&price, level) in levels {
+            if filled == quantity {
+                break;
+            }
+            let take = self.level_quantity(level).min(quantity - filled);
+            filled += take;
+            notional += price as u128 * take as u128;
+            worst_price = Some(price);
         }
-        for price in prices_to_remove {
-            match request.order_type {
-                OrderType::Buy => self.asks.remove(&price),
-                OrderType::Sell => self.bids.remove(&price),
+        (filled, notional, worst_price)
+    }
+
+    // 判断订单如果立即撮合，是否会在 [lower, upper] 区间之外成交
+    pub fn would_trade_outside(&self, request: &NewOrderRequest, lower: u64, upper: u64) -> bool {
+        let mut remaining = request.quantity;
+        let levels: Box<dyn Iterator<Item = (&u64, &PriceLevel)>> = match request.order_type {
+            OrderType::Buy => Box::new(self.asks.iter()),
+            OrderType::Sell => Box::new(self.bids.iter().rev()),
+        };
+        for (&price, level) in levels {
+            let crosses = match request.order_type {
+                OrderType::Buy => price <= request.price,
+                OrderType::Sell => price >= request.price,
             };
+            if remaining == 0 || !crosses {
+                break;
+            }
+            if price < lower || price > upper {
+                return true;
+            }
+            remaining = remaining.saturating_sub(self.level_quantity(level));
         }
+        false
+    }
 
-        // 如果新订单还有剩余数量，则将其添加到订单簿中
-        if remaining_quantity > 0 {
-            request.quantity = remaining_quantity;
-            let (new_order_id, user_id) = self.add_order(request);
-            let confirmation = OrderConfirmation { order_id: new_order_id, user_id };
-            (trades, Some(confirmation))
-        } else {
-            (trades, None) // 完全成交，没有新挂单
+    // 直接挂单而不进行撮合（例如交易暂停期间），订单簿可能因此出现交叉。
+    // 调用方需要先通过 validate 校验订单
+    pub fn insert_order(&mut self, request: NewOrderRequest) -> OrderConfirmation {
+        let order_id = self.ids.next_id();
+        let user_id = self.add_order(request, order_id);
+        OrderConfirmation { order_id, user_id }
+    }
+
+    // 从恢复快照中按原订单号放回一笔挂单，不撮合。按订单号升序放回才能保持原有的时间优先
+    pub fn restore_order(&mut self, request: NewOrderRequest, order_id: u64, filled_quantity: u64) {
+        self.add_order(request, order_id);
+        let index = self.order_id_to_index[&order_id];
+        self.orders[index as usize].filled_quantity = filled_quantity;
+    }
+
+    // 消除交叉的订单簿：只要买一价不低于卖一价就按时间优先撮合，成交价取两者中先到订单的价格，
+    // 并限制在允许成交的价格区间 bounds (下限, 上限) 之内。只能在区间之外成交的交叉订单
+    // （卖价高于上限或买价低于下限）被撤销，一并返回，撮合结束后订单簿不再交叉
+    pub fn uncross(&mut self, symbol: &str, bounds: (u64, u64)) -> (Vec<TradeNotification>, Vec<OrderNode>) {
+        self.cross(symbol, None, bounds)
+    }
+
+    // 集合竞价撮合：所有交叉的订单都以统一的竞价价格成交
+    pub fn auction_cross(&mut self, symbol: &str, price: u64) -> Vec<TradeNotification> {
+        self.cross(symbol, Some(price), (0, u64::MAX)).0
+    }
+
+    fn cross(
+        &mut self,
+        symbol: &str,
+        fixed_price: Option<u64>,
+        (lower, upper): (u64, u64),
+    ) -> (Vec<TradeNotification>, Vec<OrderNode>) {
+        let mut trades = Vec::new();
+        let mut cancelled = Vec::new();
+        let symbol = self.interned_symbol(symbol);
+        while let (Some(bid_price), Some(ask_price)) = (self.best_bid(), self.best_ask()) {
+            if bid_price < ask_price {
+                break;
+            }
+            let bid_idx = self.bids[&bid_price].head.expect("价格层级不应为空");
+            let ask_idx = self.asks[&ask_price].head.expect("价格层级不应为空");
+            let (bid, ask) = (&self.orders[bid_idx as usize], &self.orders[ask_idx as usize]);
+            let out_of_bounds = if ask_price > upper {
+                Some(ask.order_id)
+            } else if bid_price < lower {
+                Some(bid.order_id)
+            } else {
+                None
+            };
+            if let Some(order_id) = out_of_bounds {
+                cancelled.extend(self.cancel_order(order_id));
+                continue;
+            }
+            let quantity = std::cmp::min(bid.quantity, ask.quantity);
+            // 两者都不在区间之外时 [max(卖价, 下限), min(买价, 上限)] 非空
+            let price = match fixed_price {
+                Some(price) => price,
+                None if bid.order_id < ask.order_id => bid.price.clamp(ask_price.max(lower), bid_price.min(upper)),
+                None => ask.price.clamp(ask_price.max(lower), bid_price.min(upper)),
+            };
+
+            let (trade_id, timestamp) = self.sequencer.next_trade();
+            trades.push(TradeNotification {
+                trade_id,
+                symbol: symbol.clone(),
+                matched_price: price,
+                matched_quantity: quantity,
+                buyer_user_id: bid.user_id,
+                buyer_order_id: bid.order_id,
+                seller_user_id: ask.user_id,
+                seller_order_id: ask.order_id,
+                timestamp,
+                is_block_trade: false,
+            });
+
+            for idx in [bid_idx, ask_idx] {
+                let node = &mut self.orders[idx as usize];
+                node.quantity -= quantity;
+                node.filled_quantity += quantity;
+                release_open(&mut self.open_quantity, node.user_id, node.order_type, quantity);
+                let order = (node.user_id, node.order_id, node.order_type);
+                let (filled, leaves) = (node.filled_quantity, node.quantity);
+                self.record_book_event(BookEvent::Executed { order_id: order.1, quantity });
+                self.touch_level(order.2, if order.2 == OrderType::Buy { bid_price } else { ask_price });
+                let trade = trades.last().expect("刚写入的成交");
+                if self.execution_reports.is_some() {
+                    self.record_execution(trade, order, filled, leaves);
+                }
+                if leaves == 0 {
+                    self.remove_order(order.1);
+                }
+            }
         }
+        (trades, cancelled)
     }
 
-    // 添加一个新订单到订单簿，返回 (order_id, user_id)
-    fn add_order(&mut self, request: NewOrderRequest) -> (u64, u64) {
-        let order_id = self.next_order_id;
-        self.next_order_id += 1;
+    // 交易所撤销订单的剩余部分，开启执行回报时生成一条 Cancelled 回报，与成交回报一起由 take_execution_reports 取走。
+    // cumulative_quantity 为订单此前累计成交的数量
+    pub fn cancel_with_report(&mut self, order_id: u64, cumulative_quantity: u64) -> Result<OrderNode, EngineError> {
+        let node = self.cancel_order(order_id)?;
+        if let Some(reports) = self.execution_reports.as_mut() {
+            reports.push(ExecutionReport {
+                user_id: node.user_id,
+                symbol: self.symbol.to_string(),
+                order_id,
+                order_type: node.order_type,
+                status: OrderStatus::Cancelled,
+                trade_id: 0,
+                last_price: 0,
+                last_quantity: 0,
+                cumulative_quantity,
+                leaves_quantity: 0,
+                link_id: None,
+                received_at_ns: None,
+            });
+        }
+        Ok(node)
+    }
 
+    // 以给定的订单号添加一个新订单到订单簿，返回 user_id
+    fn add_order(&mut self, request: NewOrderRequest, order_id: u64) -> u64 {
         let user_id = request.user_id;
 
         let node = OrderNode {
@@ -184,6 +626,7 @@ impl OrderBook {
             order_id,
             price: request.price,
             quantity: request.quantity,
+            filled_quantity: 0,
             order_type: request.order_type,
             next: None,
             prev: None,
@@ -192,16 +635,19 @@ impl OrderBook {
         // 分配节点索引，优先从 free list 中获取
         let node_index = if let Some(free_index) = self.free_list_head {
             // 更新 free list 头指针
-            self.free_list_head = self.orders[free_index].next;
-            self.orders[free_index] = node;
+            self.free_list_head = self.orders[free_index as usize].next;
+            self.free_slots -= 1;
+            self.orders[free_index as usize] = node;
             free_index
         } else {
+            let handle = NodeHandle::try_from(self.orders.len()).expect("节点池超过 u32 下标的上限");
             self.orders.push(node);
-            self.orders.len() - 1
+            handle
         };
 
         // 存储 order_id 到索引的映射
         self.order_id_to_index.insert(order_id, node_index);
+        self.open_quantity.entry(user_id).or_default()[side_index(request.order_type)] += request.quantity;
 
         let price_map = match request.order_type {
             OrderType::Buy => &mut self.bids,
@@ -212,8 +658,8 @@ impl OrderBook {
 
         // 将新节点添加到价格队列的尾部
         if let Some(tail_index) = level.tail {
-            self.orders[tail_index].next = Some(node_index);
-            self.orders[node_index].prev = Some(tail_index);
+            self.orders[tail_index as usize].next = Some(node_index);
+            self.orders[node_index as usize].prev = Some(tail_index);
             level.tail = Some(node_index);
         } else {
             // 队列为空
@@ -221,7 +667,201 @@ impl OrderBook {
             level.tail = Some(node_index);
         }
 
-        (order_id, user_id)
+        self.touch_level(request.order_type, request.price);
+        self.record_book_event(BookEvent::Added {
+            order_id,
+            user_id,
+            order_type: request.order_type,
+            price: request.price,
+            quantity: request.quantity,
+        });
+        user_id
+    }
+
+    // 查询一个挂单的当前状态
+    pub fn order(&self, order_id: u64) -> Option<&OrderNode> {
+        let index = *self.order_id_to_index.get(&order_id)?;
+        Some(&self.orders[index as usize])
+    }
+
+    // 订单簿中挂单的数量
+    pub fn order_count(&self) -> usize {
+        self.order_id_to_index.len()
+    }
+
+    // 买卖两侧的价格档位数
+    pub fn level_counts(&self) -> (usize, usize) {
+        (self.bids.len(), self.asks.len())
+    }
+
+    // 剩余数量最大的 n 笔挂单，从大到小；数量相同时订单号小的在前
+    pub fn largest_orders(&self, n: usize) -> Vec<RestingOrder> {
+        if n == 0 {
+            return Vec::new();
+        }
+        // 保留 n 个最大元素的小顶堆
+        let mut heap = BinaryHeap::with_capacity(n + 1);
+        for (&order_id, &index) in &self.order_id_to_index {
+            heap.push(Reverse((self.orders[index as usize].quantity, Reverse(order_id), index)));
+            if heap.len() > n {
+                heap.pop();
+            }
+        }
+        heap.into_sorted_vec()
+            .into_iter()
+            .map(|Reverse((_, _, index))| {
+                let node = &self.orders[index as usize];
+                RestingOrder {
+                    order_id: node.order_id,
+                    user_id: node.user_id,
+                    order_type: node.order_type,
+                    price: node.price,
+                    quantity: node.quantity,
+                }
+            })
+            .collect()
+    }
+
+    // 原地减少挂单的剩余数量，不改变其在价格队列中的位置
+    pub fn reduce_order(&mut self, order_id: u64, new_quantity: u64) -> Result<(), EngineError> {
+        let index = *self.order_id_to_index.get(&order_id).ok_or(EngineError::OrderNotFound(order_id))?;
+        if new_quantity == 0 || new_quantity > self.orders[index as usize].quantity {
+            return Err(EngineError::InvalidQuantity);
+        }
+        let node = &mut self.orders[index as usize];
+        release_open(&mut self.open_quantity, node.user_id, node.order_type, node.quantity - new_quantity);
+        node.quantity = new_quantity;
+        let node = &self.orders[index as usize];
+        self.touch_level(node.order_type, node.price);
+        self.record_book_event(BookEvent::Reduced { order_id, quantity: new_quantity });
+        Ok(())
+    }
+
+    // 撤销一个挂单，返回被撤销订单的信息；订单不存在（已成交或已撤销）时返回 OrderNotFound
+    pub fn cancel_order(&mut self, order_id: u64) -> Result<OrderNode, EngineError> {
+        let node_index = *self.order_id_to_index.get(&order_id).ok_or(EngineError::OrderNotFound(order_id))?;
+        let node = self.orders[node_index as usize].clone();
+        self.remove_order(order_id);
+        self.record_book_event(BookEvent::Removed { order_id });
+        Ok(node)
+    }
+
+    // 全部挂单，同一价格队列内按时间优先的顺序
+    pub fn queued_orders(&self) -> Vec<OrderNode> {
+        let mut orders = Vec::with_capacity(self.order_id_to_index.len());
+        for level in self.bids.values().chain(self.asks.values()) {
+            let mut current = level.head;
+            while let Some(index) = current {
+                let node = &self.orders[index as usize];
+                orders.push(node.clone());
+                current = node.next;
+            }
+        }
+        orders
+    }
+
+    // 挂单内容的校验和（FNV-1a）：按价格层级和队列顺序覆盖每笔挂单的方向、价格、订单号和剩余数量，
+    // 不含累计成交数量，用于核对副本订单簿
+    pub fn checksum(&self) -> u64 {
+        let mut hash: u64 = 0xcbf2_9ce4_8422_2325;
+        for (side, levels) in [(0u64, &self.bids), (1, &self.asks)] {
+            for (&price, level) in levels {
+                let mut current = level.head;
+                while let Some(index) = current {
+                    let node = &self.orders[index as usize];
+                    for value in [side, price, node.order_id, node.quantity] {
+                        for byte in value.to_le_bytes() {
+                            hash ^= u64::from(byte);
+                            hash = hash.wrapping_mul(0x0000_0100_0000_01b3);
+                        }
+                    }
+                    current = node.next;
+                }
+            }
+        }
+        hash
+    }
+
+    // 某个用户全部挂单的剩余数量 (买, 卖)
+    pub fn open_quantity(&self, user_id: u64) -> (u64, u64) {
+        let [buy, sell] = self.open_quantity.get(&user_id).copied().unwrap_or_default();
+        (buy, sell)
+    }
+
+    // 某个用户的全部挂单的订单号，按订单号升序
+    pub fn user_orders(&self, user_id: u64) -> Vec<u64> {
+        let mut order_ids: Vec<u64> = self
+            .order_id_to_index
+            .iter()
+            .filter(|&(_, &index)| self.orders[index as usize].user_id == user_id)
+            .map(|(&order_id, _)| order_id)
+            .collect();
+        order_ids.sort_unstable();
+        order_ids
+    }
+
+    // 全部挂单的订单号，按订单号升序
+    pub fn order_ids(&self) -> Vec<u64> {
+        let mut order_ids: Vec<u64> = self.order_id_to_index.keys().copied().collect();
+        order_ids.sort_unstable();
+        order_ids
+    }
+
+    // 节点池的总槽位数（含空闲槽位）
+    pub fn pool_slots(&self) -> usize {
+        self.orders.len()
+    }
+
+    // 节点池中等待复用的空闲槽位数
+    pub fn free_slots(&self) -> usize {
+        self.free_slots
+    }
+
+    // 节点池按容量计算，预分配但尚未使用的槽位同样占用内存
+    pub fn memory_usage(&self) -> MemoryUsage {
+        let (bid_levels, ask_levels) = self.level_counts();
+        let resting_orders = self.order_id_to_index.len();
+        let pool_capacity = self.orders.capacity();
+        let level_bytes = size_of::<u64>() + size_of::<PriceLevel>();
+        let bytes = pool_capacity * size_of::<OrderNode>()
+            + resting_orders * size_of::<(u64, NodeHandle)>()
+            + (bid_levels + ask_levels) * level_bytes;
+        MemoryUsage { bid_levels, ask_levels, resting_orders, pool_slots: self.orders.len(), pool_capacity, bytes }
+    }
+
+    // 压缩节点池：按价格层级顺序重新排列存活的订单并释放空闲槽位和多余容量，
+    // 返回回收的槽位数。订单的优先级和订单号保持不变
+    pub fn compact(&mut self) -> usize {
+        let reclaimed = self.free_slots;
+        let mut orders = Vec::with_capacity(self.order_id_to_index.len());
+        for level in self.bids.values_mut().chain(self.asks.values_mut()) {
+            let mut current = level.head;
+            let mut prev = None;
+            level.head = None;
+            while let Some(old_index) = current {
+                let mut node = self.orders[old_index as usize].clone();
+                current = node.next;
+
+                let new_index = orders.len() as NodeHandle;
+                node.prev = prev;
+                node.next = None;
+                match prev {
+                    Some(prev_index) => {
+                        let prev_node: &mut OrderNode = &mut orders[prev_index as usize];
+                        prev_node.next = Some(new_index);
+                    }
+                    None => level.head = Some(new_index),
+                }
+                self.order_id_to_index.insert(node.order_id, new_index);
+                orders.push(node);
+                prev = Some(new_index);
+            }
+            level.tail = prev;
+        }
+        self.orders = orders;
+        self.free_list_head = None;
+        self.free_slots = 0;
+        reclaimed
     }
 
     // 从订单簿中移除一个订单
@@ -235,13 +875,15 @@ impl OrderBook {
         };
 
         let (prev, next, price, order_type) = {
-            let node = &self.orders[node_index];
+            let node = &self.orders[node_index as usize];
+            release_open(&mut self.open_quantity, node.user_id, node.order_type, node.quantity);
             (node.prev, node.next, node.price, node.order_type)
         };
+        self.touch_level(order_type, price);
 
         // 2. 从价格队列的双向链表中移除节点
         if let Some(prev_index) = prev {
-            self.orders[prev_index].next = next;
+            self.orders[prev_index as usize].next = next;
         } else {
             // 节点是头节点
             let price_map = match order_type {
@@ -254,7 +896,7 @@ impl OrderBook {
         }
 
         if let Some(next_index) = next {
-            self.orders[next_index].prev = prev;
+            self.orders[next_index as usize].prev = prev;
         } else {
             // 节点是尾节点
             let price_map = match order_type {
@@ -278,7 +920,28 @@ impl OrderBook {
         }
 
         // 4. 将移除的节点索引添加到 free list 头部
-        self.orders[node_index].next = self.free_list_head;
+        self.orders[node_index as usize].next = self.free_list_head;
         self.free_list_head = Some(node_index);
+        self.free_slots += 1;
+    }
+}
+
+fn side_index(order_type: OrderType) -> usize {
+    match order_type {
+        OrderType::Buy => 0,
+        OrderType::Sell => 1,
     }
-}
\ No newline at end of file
+}
+
+// 挂单成交、减量或离开订单簿时减少用户的挂单敞口，两侧都为 0 时移除
+fn release_open(open_quantity: &mut FxHashMap<u64, [u64; 2]>, user_id: u64, order_type: OrderType, quantity: u64) {
+    if quantity == 0 {
+        return;
+    }
+    if let Some(open) = open_quantity.get_mut(&user_id) {
+        open[side_index(order_type)] -= quantity;
+        if *open == [0, 0] {
+            open_quantity.remove(&user_id);
+        }
+    }
+}
@@ -0,0 +1,92 @@
+use crate::protocol::{OrderType, TradeNotification};
+use std::collections::HashMap;
+
+// 持仓限额配置：按合约设置，未单独配置的合约使用默认限额
+#[derive(Debug, Clone, Default)]
+pub struct PositionLimits {
+    pub default_limit: Option<u64>,
+    pub per_symbol: HashMap<String, u64>,
+}
+
+impl PositionLimits {
+    fn limit_for(&self, symbol: &str) -> Option<u64> {
+        self.per_symbol.get(symbol).copied().or(self.default_limit)
+    }
+}
+
+// 按 (用户, 合约) 维护由成交累积的净持仓，多头为正，空头为负。
+// 按用户再按合约两层索引，查询时直接用 &str 查找，不分配合约名
+#[derive(Debug, Default)]
+pub struct PositionTracker {
+    positions: HashMap<u64, HashMap<String, i64>>,
+    limits: PositionLimits,
+}
+
+impl PositionTracker {
+    pub fn new(limits: PositionLimits) -> Self {
+        PositionTracker {
+            positions: HashMap::new(),
+            limits,
+        }
+    }
+
+    // 查询用户在某个合约上的净持仓
+    pub fn position(&self, user_id: u64, symbol: &str) -> i64 {
+        self.positions.get(&user_id).and_then(|symbols| symbols.get(symbol)).copied().unwrap_or(0)
+    }
+
+    // 全部非零持仓 (用户, 合约, 净持仓)，按用户和合约排序，用于恢复快照
+    pub fn positions(&self) -> Vec<(u64, String, i64)> {
+        let mut positions: Vec<(u64, String, i64)> = self
+            .positions
+            .iter()
+            .flat_map(|(&user_id, symbols)| symbols.iter().map(move |(symbol, &position)| (user_id, symbol, position)))
+            .filter(|&(_, _, position)| position != 0)
+            .map(|(user_id, symbol, position)| (user_id, symbol.clone(), position))
+            .collect();
+        positions.sort_unstable();
+        positions
+    }
+
+    // 从快照恢复持仓
+    pub fn set_position(&mut self, user_id: u64, symbol: &str, position: i64) {
+        *self.entry(user_id, symbol) = position;
+    }
+
+    // 根据一笔成交更新买卖双方的持仓
+    pub fn apply_trade(&mut self, trade: &TradeNotification) {
+        let quantity = trade.matched_quantity as i64;
+        *self.entry(trade.buyer_user_id, &trade.symbol) += quantity;
+        *self.entry(trade.seller_user_id, &trade.symbol) -= quantity;
+    }
+
+    // 合约名只在用户第一次持有该合约时分配
+    fn entry(&mut self, user_id: u64, symbol: &str) -> &mut i64 {
+        let symbols = self.positions.entry(user_id).or_default();
+        if !symbols.contains_key(symbol) {
+            symbols.insert(symbol.to_string(), 0);
+        }
+        symbols.get_mut(symbol).expect("刚插入的合约")
+    }
+
+    // 事前风控：假设用户在该合约上同方向的挂单 open_quantity (买, 卖) 和这笔订单全部成交，
+    // 持仓不能超过限额。只检查订单所在的方向，减少持仓的订单不会被拒绝
+    pub fn check_order(
+        &self,
+        user_id: u64,
+        symbol: &str,
+        order_type: OrderType,
+        quantity: u64,
+        open_quantity: (u64, u64),
+    ) -> bool {
+        let Some(limit) = self.limits.limit_for(symbol) else {
+            return true;
+        };
+        let current = self.position(user_id, symbol) as i128;
+        let (open_buy, open_sell) = open_quantity;
+        match order_type {
+            OrderType::Buy => current + open_buy as i128 + quantity as i128 <= limit as i128,
+            OrderType::Sell => current - open_sell as i128 - quantity as i128 >= -(limit as i128),
+        }
+    }
+}
@@ -0,0 +1,167 @@
+// 定点小数价格。引擎内部的价格是整数，表示 10^-exponent 的倍数，精度（exponent）由合约参考数据给出；
+// 对外接收或展示十进制价格时在这里换算，换算过程只做整数运算，丢失精度或溢出时报错而不是舍入
+use serde::de::{self, Visitor};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use std::collections::HashMap;
+use std::fmt;
+use std::str::FromStr;
+
+// 支持的最大小数位数，10^19 已超出 u64
+pub const MAX_EXPONENT: u8 = 18;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PriceError {
+    // 不是非负的十进制数
+    Malformed(String),
+    // 小数位数超过目标精度，换算会丢失精度
+    TooPrecise { price: String, exponent: u8 },
+    // 换算后超出 u64
+    Overflow(String),
+}
+
+impl fmt::Display for PriceError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PriceError::Malformed(text) => write!(f, "无效的价格 {:?}", text),
+            PriceError::TooPrecise { price, exponent } => write!(f, "价格 {} 超过 {} 位小数的精度", price, exponent),
+            PriceError::Overflow(price) => write!(f, "价格 {} 超出范围", price),
+        }
+    }
+}
+
+impl std::error::Error for PriceError {}
+
+// 价格 = value × 10^-exponent。exponent 不同的价格即使数值相等也不相等，比较前先换算到同一精度
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Price {
+    pub value: u64,
+    pub exponent: u8,
+}
+
+impl Price {
+    pub fn new(value: u64, exponent: u8) -> Self {
+        Price { value, exponent }
+    }
+
+    // 解析十进制字符串，例如 "50123.45" 得到 value 5012345、exponent 2
+    pub fn parse(text: &str) -> Result<Self, PriceError> {
+        let malformed = || PriceError::Malformed(text.to_string());
+        let (integer, fraction) = text.split_once('.').unwrap_or((text, ""));
+        let digits = |part: &str| part.bytes().all(|byte| byte.is_ascii_digit());
+        if integer.is_empty() || !digits(integer) || !digits(fraction) || (text.contains('.') && fraction.is_empty()) {
+            return Err(malformed());
+        }
+        if fraction.len() > MAX_EXPONENT as usize {
+            return Err(PriceError::TooPrecise { price: text.to_string(), exponent: MAX_EXPONENT });
+        }
+        let mut value: u64 = 0;
+        for byte in integer.bytes().chain(fraction.bytes()) {
+            value = value
+                .checked_mul(10)
+                .and_then(|value| value.checked_add((byte - b'0') as u64))
+                .ok_or_else(|| PriceError::Overflow(text.to_string()))?;
+        }
+        Ok(Price { value, exponent: fraction.len() as u8 })
+    }
+
+    // 换算到另一精度；小数位数减少时要求被截去的部分全为 0
+    pub fn rescale(self, exponent: u8) -> Result<Self, PriceError> {
+        if exponent > MAX_EXPONENT {
+            return Err(PriceError::TooPrecise { price: self.to_string(), exponent });
+        }
+        let value = if exponent >= self.exponent {
+            10u64
+                .checked_pow((exponent - self.exponent) as u32)
+                .and_then(|factor| self.value.checked_mul(factor))
+                .ok_or_else(|| PriceError::Overflow(self.to_string()))?
+        } else {
+            let factor = 10u64.pow((self.exponent - exponent) as u32);
+            if !self.value.is_multiple_of(factor) {
+                return Err(PriceError::TooPrecise { price: self.to_string(), exponent });
+            }
+            self.value / factor
+        };
+        Ok(Price { value, exponent })
+    }
+
+    // 换算为 exponent 精度下的整数价格，即引擎和线上协议使用的价格
+    pub fn to_ticks(self, exponent: u8) -> Result<u64, PriceError> {
+        Ok(self.rescale(exponent)?.value)
+    }
+}
+
+impl fmt::Display for Price {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.exponent == 0 {
+            return write!(f, "{}", self.value);
+        }
+        let factor = 10u64.pow(self.exponent as u32);
+        write!(f, "{}.{:0width$}", self.value / factor, self.value % factor, width = self.exponent as usize)
+    }
+}
+
+impl FromStr for Price {
+    type Err = PriceError;
+
+    fn from_str(text: &str) -> Result<Self, Self::Err> {
+        Price::parse(text)
+    }
+}
+
+// JSON 中以十进制字符串表示，保留原有的小数位数
+impl Serialize for Price {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+// 接受十进制字符串或整数。带小数点的 JSON 数字会先被解析为 f64 而失去精度，因此拒绝
+impl<'de> Deserialize<'de> for Price {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        struct PriceVisitor;
+
+        impl Visitor<'_> for PriceVisitor {
+            type Value = Price;
+
+            fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                write!(f, "十进制字符串或非负整数表示的价格")
+            }
+
+            fn visit_u64<E: de::Error>(self, value: u64) -> Result<Price, E> {
+                Ok(Price::new(value, 0))
+            }
+
+            fn visit_str<E: de::Error>(self, text: &str) -> Result<Price, E> {
+                Price::parse(text).map_err(E::custom)
+            }
+        }
+
+        deserializer.deserialize_any(PriceVisitor)
+    }
+}
+
+// 各合约的价格精度，由合约参考数据生成；未列出的合约精度为 0，即价格就是整数价格
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct PriceScales {
+    exponents: HashMap<String, u8>,
+}
+
+impl PriceScales {
+    pub fn new(exponents: impl IntoIterator<Item = (String, u8)>) -> Self {
+        PriceScales { exponents: exponents.into_iter().collect() }
+    }
+
+    pub fn exponent(&self, symbol: &str) -> u8 {
+        self.exponents.get(symbol).copied().unwrap_or(0)
+    }
+
+    // 十进制价格换算为合约的整数价格
+    pub fn to_ticks(&self, symbol: &str, price: Price) -> Result<u64, PriceError> {
+        price.to_ticks(self.exponent(symbol))
+    }
+
+    // 合约的整数价格换算为十进制价格
+    pub fn to_price(&self, symbol: &str, ticks: u64) -> Price {
+        Price::new(ticks, self.exponent(symbol))
+    }
+}
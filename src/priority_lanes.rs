@@ -0,0 +1,174 @@
+// 引擎入口的优先车道：撤单、改单和按用户撤单走优先车道，其余命令走普通车道，
+// 新订单洪峰时降低风险的操作不会排在大量新订单之后。
+// 两条车道都有命令时按权重轮流服务：每轮先处理至多 priority 条优先命令，再处理至多 normal 条普通命令；
+// 只有一条车道有命令时直接处理。普通车道的权重至少为 1，撤单再多也不会饿死新订单。
+// 同一会话（按用户区分）的命令保持提交顺序：每条命令按到达顺序编号，优先命令只能越过其他会话的普通命令，
+// 同一会话更早提交的普通命令还在排队时，它的撤单、改单留在优先车道中等待，
+// 例如先下单再按用户撤单，撤单一定在下单之后处理
+use crate::engine::EngineCommand;
+use std::collections::{BTreeSet, HashMap, VecDeque};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LaneWeights {
+    pub priority: u32,
+    pub normal: u32,
+}
+
+impl Default for LaneWeights {
+    fn default() -> Self {
+        LaneWeights { priority: 4, normal: 1 }
+    }
+}
+
+impl LaneWeights {
+    // 解析 "优先权重:普通权重"，例如 "4:1"
+    pub fn parse(spec: &str) -> Result<Self, String> {
+        let (priority, normal) = spec.split_once(':').ok_or_else(|| format!("应为 优先权重:普通权重: {}", spec))?;
+        let weight = |value: &str| -> Result<u32, String> {
+            match value.trim().parse() {
+                Ok(weight) if weight > 0 => Ok(weight),
+                _ => Err(format!("无效的车道权重: {}", value)),
+            }
+        };
+        Ok(LaneWeights { priority: weight(priority)?, normal: weight(normal)? })
+    }
+}
+
+// 排队中的命令及其到达序号
+struct Queued {
+    arrival: u64,
+    session: Option<u64>,
+    command: EngineCommand,
+}
+
+pub struct PriorityLanes {
+    weights: LaneWeights,
+    // 优先车道按会话分队，每个会话的队列按到达顺序排列；没有用户的命令归入 None
+    priority: HashMap<Option<u64>, VecDeque<Queued>>,
+    priority_len: usize,
+    // 队首可以处理的会话，按队首的到达序号排序，最小的即为优先车道中最早到达的可处理命令
+    ready: BTreeSet<(u64, Option<u64>)>,
+    normal: VecDeque<Queued>,
+    // 每个会话在普通车道中排队的命令的到达序号，按到达顺序排列
+    pending_normal: HashMap<u64, VecDeque<u64>>,
+    next_arrival: u64,
+    // 本轮已服务的命令数
+    served_priority: u32,
+    served_normal: u32,
+}
+
+impl PriorityLanes {
+    pub fn new(weights: LaneWeights) -> Self {
+        assert!(weights.priority > 0 && weights.normal > 0, "车道权重必须大于 0");
+        PriorityLanes {
+            weights,
+            priority: HashMap::new(),
+            priority_len: 0,
+            ready: BTreeSet::new(),
+            normal: VecDeque::new(),
+            pending_normal: HashMap::new(),
+            next_arrival: 0,
+            served_priority: 0,
+            served_normal: 0,
+        }
+    }
+
+    // 降低风险的命令走优先车道
+    pub fn is_priority(command: &EngineCommand) -> bool {
+        matches!(command.kind(), "cancel_order" | "amend_order" | "cancel_user_orders")
+    }
+
+    pub fn push(&mut self, command: EngineCommand) {
+        let queued = Queued { arrival: self.next_arrival, session: command.user_id(), command };
+        self.next_arrival += 1;
+        if Self::is_priority(&queued.command) {
+            let (arrival, session) = (queued.arrival, queued.session);
+            let lane = self.priority.entry(session).or_default();
+            lane.push_back(queued);
+            self.priority_len += 1;
+            // 新到的命令成为队首时，只要同会话没有更早的普通命令就可以处理
+            if lane.len() == 1 && self.is_ready(session, arrival) {
+                self.ready.insert((arrival, session));
+            }
+        } else {
+            if let Some(session) = queued.session {
+                self.pending_normal.entry(session).or_default().push_back(queued.arrival);
+            }
+            self.normal.push_back(queued);
+        }
+    }
+
+    // 同一会话没有更早到达、还在普通车道中排队的命令
+    fn is_ready(&self, session: Option<u64>, arrival: u64) -> bool {
+        let earliest_normal = session.and_then(|session| self.pending_normal.get(&session)?.front().copied());
+        earliest_normal.is_none_or(|earliest| earliest > arrival)
+    }
+
+    // 会话的优先队首若已可以处理，登记到 ready 中
+    fn refresh(&mut self, session: Option<u64>) {
+        let head = self.priority.get(&session).and_then(|lane| lane.front()).map(|queued| queued.arrival);
+        if let Some(arrival) = head {
+            if self.is_ready(session, arrival) {
+                self.ready.insert((arrival, session));
+            }
+        }
+    }
+
+    pub fn pop(&mut self) -> Option<EngineCommand> {
+        // 被阻塞的优先命令一定有同会话的普通命令在排队，所以普通车道为空时优先命令全部可以处理
+        let ready = self.ready.first().copied();
+        let from_priority = match (ready.is_some(), self.normal.is_empty()) {
+            (false, true) => return None,
+            (true, true) => true,
+            (false, false) => false,
+            (true, false) => {
+                if self.served_priority >= self.weights.priority && self.served_normal >= self.weights.normal {
+                    self.served_priority = 0;
+                    self.served_normal = 0;
+                }
+                self.served_priority < self.weights.priority
+            }
+        };
+        let queued = if from_priority {
+            self.served_priority = self.served_priority.saturating_add(1);
+            let (arrival, session) = ready?;
+            self.ready.remove(&(arrival, session));
+            let lane = self.priority.get_mut(&session)?;
+            let queued = lane.pop_front()?;
+            if lane.is_empty() {
+                self.priority.remove(&session);
+            }
+            self.priority_len -= 1;
+            self.refresh(session);
+            queued
+        } else {
+            self.served_normal = self.served_normal.saturating_add(1);
+            let queued = self.normal.pop_front()?;
+            // 普通车道队首是其会话中最早到达的普通命令
+            if let Some(session) = queued.session {
+                if let Some(pending) = self.pending_normal.get_mut(&session) {
+                    pending.pop_front();
+                    if pending.is_empty() {
+                        self.pending_normal.remove(&session);
+                    }
+                }
+                // 该会话更早的普通命令出队后，它在优先车道中等待的命令可能已可以处理
+                self.refresh(Some(session));
+            }
+            queued
+        };
+        Some(queued.command)
+    }
+
+    pub fn len(&self) -> usize {
+        self.priority_len + self.normal.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub fn priority_len(&self) -> usize {
+        self.priority_len
+    }
+}
@@ -0,0 +1,1178 @@
+// Protobuf 编码，消息定义见 proto/matching_engine.proto。
+// pb 模块中的类型与 .proto 文件逐字段对应（字段号、类型保持一致），修改协议时两边需要同步
+use crate::codec::{Codec, CodecError};
+use crate::protocol::{
+    AmendOrderRequest, BasketLeg, BasketOrderRequest, BlockTradeRequest, CancelAck, CancelOrderRequest, CancelStatus,
+    Candle, ClientMessage, ExecutionReport, FillEstimate, FillEstimateRequest, ImpliedQuote, LogonRequest,
+    LogonResponse, LogonStatus, MarkPrice, MarketDataMode, MarketDataQuery, MarketDataSnapshot, NewOrderRequest,
+    OcoOrderRequest, OrderConfirmation, OrderReject, OrderStatus, OrderType, PositionQuery, PositionReport,
+    RejectReason, ResumeRequest, ResumeResponse, ServerMessage, SpreadOrderRequest, TimeInForce, TimedOrderRequest,
+    TradeNotification, TradingPhase, TradingStatus,
+};
+use prost::Message;
+
+pub mod pb {
+    #[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord, prost::Enumeration)]
+    #[repr(i32)]
+    pub enum OrderSide {
+        Unspecified = 0,
+        Buy = 1,
+        Sell = 2,
+    }
+
+    #[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord, prost::Enumeration)]
+    #[repr(i32)]
+    pub enum CancelStatus {
+        Unspecified = 0,
+        Cancelled = 1,
+        AlreadyCancelled = 2,
+        UnknownOrder = 3,
+        Expired = 4,
+        LinkedOrderFilled = 5,
+        NotOwner = 6,
+        PriceBandBreach = 7,
+    }
+
+    #[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord, prost::Enumeration)]
+    #[repr(i32)]
+    pub enum OrderStatus {
+        Unspecified = 0,
+        PartiallyFilled = 2,
+        Filled = 3,
+        Cancelled = 4,
+        Expired = 6,
+        Restated = 7,
+    }
+
+    #[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord, prost::Enumeration)]
+    #[repr(i32)]
+    pub enum RejectReason {
+        Unspecified = 0,
+        BlockTradeTooSmall = 1,
+        BlockTradePriceOutOfRange = 2,
+        Throttled = 3,
+        PositionLimitExceeded = 4,
+        PriceBandBreach = 5,
+        UnknownOrder = 6,
+        InvalidPrice = 7,
+        InvalidQuantity = 8,
+        Unauthenticated = 9,
+        UserMismatch = 10,
+        ContractExpired = 11,
+        PriceCollarBreach = 12,
+        UnknownSpread = 13,
+        NoImpliedLiquidity = 14,
+        InvalidBasket = 15,
+        BasketNotFillable = 16,
+        LinkedOrderFilled = 17,
+        InvalidExpiry = 18,
+        ReduceOnlyExceedsPosition = 19,
+        SymbolLimitReached = 20,
+        NotOrderOwner = 21,
+    }
+
+    #[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord, prost::Enumeration)]
+    #[repr(i32)]
+    pub enum TradingPhase {
+        Unspecified = 0,
+        Continuous = 1,
+        Auction = 2,
+        Halted = 3,
+        Expired = 4,
+    }
+
+    #[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord, prost::Enumeration)]
+    #[repr(i32)]
+    pub enum MarketDataMode {
+        Unspecified = 0,
+        Full = 1,
+        Conflated = 2,
+    }
+
+    #[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord, prost::Enumeration)]
+    #[repr(i32)]
+    pub enum LogonStatus {
+        Unspecified = 0,
+        Accepted = 1,
+        UnknownApiKey = 2,
+        InvalidSignature = 3,
+        StaleTimestamp = 4,
+        AlreadyLoggedOn = 5,
+    }
+
+    #[derive(Clone, PartialEq, prost::Message)]
+    pub struct NewOrderRequest {
+        #[prost(uint64, tag = "1")]
+        pub user_id: u64,
+        #[prost(string, tag = "2")]
+        pub symbol: String,
+        #[prost(enumeration = "OrderSide", tag = "3")]
+        pub side: i32,
+        #[prost(uint64, tag = "4")]
+        pub price: u64,
+        #[prost(uint64, tag = "5")]
+        pub quantity: u64,
+    }
+
+    #[derive(Clone, PartialEq, prost::Message)]
+    pub struct SpreadOrderRequest {
+        #[prost(uint64, tag = "1")]
+        pub user_id: u64,
+        #[prost(string, tag = "2")]
+        pub spread: String,
+        #[prost(enumeration = "OrderSide", tag = "3")]
+        pub side: i32,
+        #[prost(sint64, tag = "4")]
+        pub price: i64,
+        #[prost(uint64, tag = "5")]
+        pub quantity: u64,
+    }
+
+    #[derive(Clone, PartialEq, prost::Message)]
+    pub struct BasketLeg {
+        #[prost(string, tag = "1")]
+        pub symbol: String,
+        #[prost(enumeration = "OrderSide", tag = "2")]
+        pub side: i32,
+        #[prost(uint64, tag = "3")]
+        pub price: u64,
+        #[prost(uint64, tag = "4")]
+        pub quantity: u64,
+    }
+
+    #[derive(Clone, PartialEq, prost::Message)]
+    pub struct BasketOrderRequest {
+        #[prost(uint64, tag = "1")]
+        pub user_id: u64,
+        #[prost(message, repeated, tag = "2")]
+        pub legs: Vec<BasketLeg>,
+    }
+
+    #[derive(Clone, PartialEq, prost::Message)]
+    pub struct TimedOrderRequest {
+        #[prost(message, optional, tag = "1")]
+        pub order: Option<NewOrderRequest>,
+        #[prost(uint64, optional, tag = "2")]
+        pub expire_at_ms: Option<u64>,
+    }
+
+    #[derive(Clone, PartialEq, prost::Message)]
+    pub struct OcoOrderRequest {
+        #[prost(uint64, tag = "1")]
+        pub link_id: u64,
+        #[prost(message, optional, tag = "2")]
+        pub first: Option<NewOrderRequest>,
+        #[prost(message, optional, tag = "3")]
+        pub second: Option<NewOrderRequest>,
+    }
+
+    #[derive(Clone, PartialEq, prost::Message)]
+    pub struct CancelOrderRequest {
+        #[prost(uint64, tag = "1")]
+        pub user_id: u64,
+        #[prost(string, tag = "2")]
+        pub symbol: String,
+        #[prost(uint64, tag = "3")]
+        pub order_id: u64,
+    }
+
+    #[derive(Clone, PartialEq, prost::Message)]
+    pub struct AmendOrderRequest {
+        #[prost(uint64, tag = "1")]
+        pub user_id: u64,
+        #[prost(string, tag = "2")]
+        pub symbol: String,
+        #[prost(uint64, tag = "3")]
+        pub order_id: u64,
+        #[prost(uint64, tag = "4")]
+        pub new_price: u64,
+        #[prost(uint64, tag = "5")]
+        pub new_quantity: u64,
+    }
+
+    #[derive(Clone, PartialEq, prost::Message)]
+    pub struct BlockTradeRequest {
+        #[prost(string, tag = "1")]
+        pub symbol: String,
+        #[prost(uint64, tag = "2")]
+        pub price: u64,
+        #[prost(uint64, tag = "3")]
+        pub quantity: u64,
+        #[prost(uint64, tag = "4")]
+        pub buyer_user_id: u64,
+        #[prost(uint64, tag = "5")]
+        pub seller_user_id: u64,
+    }
+
+    #[derive(Clone, PartialEq, prost::Message)]
+    pub struct PositionQuery {
+        #[prost(uint64, tag = "1")]
+        pub user_id: u64,
+        #[prost(string, tag = "2")]
+        pub symbol: String,
+    }
+
+    #[derive(Clone, PartialEq, prost::Message)]
+    pub struct FillEstimateRequest {
+        #[prost(uint64, tag = "1")]
+        pub user_id: u64,
+        #[prost(string, tag = "2")]
+        pub symbol: String,
+        #[prost(enumeration = "OrderSide", tag = "3")]
+        pub side: i32,
+        #[prost(uint64, tag = "4")]
+        pub quantity: u64,
+    }
+
+    #[derive(Clone, PartialEq, prost::Message)]
+    pub struct MarketDataQuery {
+        #[prost(uint64, tag = "1")]
+        pub user_id: u64,
+        #[prost(string, tag = "2")]
+        pub symbol: String,
+        #[prost(uint64, tag = "3")]
+        pub candle_interval_ms: u64,
+        #[prost(uint32, tag = "4")]
+        pub candle_limit: u32,
+    }
+
+    #[derive(Clone, PartialEq, prost::Message)]
+    pub struct LogonRequest {
+        #[prost(string, tag = "1")]
+        pub api_key: String,
+        #[prost(uint64, tag = "2")]
+        pub timestamp_ms: u64,
+        #[prost(bytes = "vec", tag = "3")]
+        pub signature: Vec<u8>,
+    }
+
+    #[derive(Clone, PartialEq, prost::Message)]
+    pub struct ResumeRequest {
+        #[prost(uint64, tag = "1")]
+        pub last_received_seq: u64,
+    }
+
+    #[derive(Clone, PartialEq, prost::Message)]
+    pub struct Heartbeat {}
+
+    #[derive(Clone, PartialEq, prost::Message)]
+    pub struct Shutdown {}
+
+    #[derive(Clone, PartialEq, prost::Message)]
+    pub struct ClientEnvelope {
+        #[prost(oneof = "client_envelope::Message", tags = "1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 16")]
+        pub message: Option<client_envelope::Message>,
+        #[prost(uint64, tag = "15")]
+        pub request_id: u64,
+    }
+
+    pub mod client_envelope {
+        #[derive(Clone, PartialEq, prost::Oneof)]
+        pub enum Message {
+            #[prost(message, tag = "1")]
+            NewOrder(super::NewOrderRequest),
+            #[prost(message, tag = "2")]
+            CancelOrder(super::CancelOrderRequest),
+            #[prost(message, tag = "3")]
+            AmendOrder(super::AmendOrderRequest),
+            #[prost(message, tag = "4")]
+            BlockTrade(super::BlockTradeRequest),
+            #[prost(message, tag = "5")]
+            QueryPosition(super::PositionQuery),
+            #[prost(message, tag = "6")]
+            EstimateFill(super::FillEstimateRequest),
+            #[prost(message, tag = "7")]
+            QueryMarketData(super::MarketDataQuery),
+            #[prost(message, tag = "8")]
+            Logon(super::LogonRequest),
+            #[prost(message, tag = "9")]
+            Heartbeat(super::Heartbeat),
+            #[prost(message, tag = "10")]
+            Resume(super::ResumeRequest),
+            #[prost(message, tag = "11")]
+            SpreadOrder(super::SpreadOrderRequest),
+            #[prost(message, tag = "12")]
+            BasketOrder(super::BasketOrderRequest),
+            #[prost(message, tag = "13")]
+            OcoOrder(super::OcoOrderRequest),
+            #[prost(message, tag = "14")]
+            TimedOrder(super::TimedOrderRequest),
+            #[prost(message, tag = "16")]
+            ReduceOnlyOrder(super::NewOrderRequest),
+        }
+    }
+
+    #[derive(Clone, PartialEq, prost::Message)]
+    pub struct TradeNotification {
+        #[prost(uint64, tag = "1")]
+        pub trade_id: u64,
+        #[prost(string, tag = "2")]
+        pub symbol: String,
+        #[prost(uint64, tag = "3")]
+        pub matched_price: u64,
+        #[prost(uint64, tag = "4")]
+        pub matched_quantity: u64,
+        #[prost(uint64, tag = "5")]
+        pub buyer_user_id: u64,
+        #[prost(uint64, tag = "6")]
+        pub buyer_order_id: u64,
+        #[prost(uint64, tag = "7")]
+        pub seller_user_id: u64,
+        #[prost(uint64, tag = "8")]
+        pub seller_order_id: u64,
+        #[prost(uint64, tag = "9")]
+        pub timestamp: u64,
+        #[prost(bool, tag = "10")]
+        pub is_block_trade: bool,
+    }
+
+    #[derive(Clone, PartialEq, prost::Message)]
+    pub struct OrderConfirmation {
+        #[prost(uint64, tag = "1")]
+        pub order_id: u64,
+        #[prost(uint64, tag = "2")]
+        pub user_id: u64,
+    }
+
+    #[derive(Clone, PartialEq, prost::Message)]
+    pub struct OrderReject {
+        #[prost(uint64, tag = "1")]
+        pub user_id: u64,
+        #[prost(string, tag = "2")]
+        pub symbol: String,
+        #[prost(enumeration = "RejectReason", tag = "3")]
+        pub reason: i32,
+    }
+
+    #[derive(Clone, PartialEq, prost::Message)]
+    pub struct PositionReport {
+        #[prost(uint64, tag = "1")]
+        pub user_id: u64,
+        #[prost(string, tag = "2")]
+        pub symbol: String,
+        #[prost(sint64, tag = "3")]
+        pub net_position: i64,
+    }
+
+    #[derive(Clone, PartialEq, prost::Message)]
+    pub struct TradingStatus {
+        #[prost(string, tag = "1")]
+        pub symbol: String,
+        #[prost(enumeration = "TradingPhase", tag = "2")]
+        pub phase: i32,
+    }
+
+    #[derive(Clone, PartialEq, prost::Message)]
+    pub struct CancelAck {
+        #[prost(uint64, tag = "1")]
+        pub user_id: u64,
+        #[prost(string, tag = "2")]
+        pub symbol: String,
+        #[prost(uint64, tag = "3")]
+        pub order_id: u64,
+        #[prost(uint64, tag = "4")]
+        pub cancelled_quantity: u64,
+        #[prost(enumeration = "CancelStatus", tag = "5")]
+        pub status: i32,
+    }
+
+    #[derive(Clone, PartialEq, prost::Message)]
+    pub struct FillEstimate {
+        #[prost(uint64, tag = "1")]
+        pub user_id: u64,
+        #[prost(string, tag = "2")]
+        pub symbol: String,
+        #[prost(enumeration = "OrderSide", tag = "3")]
+        pub side: i32,
+        #[prost(uint64, tag = "4")]
+        pub requested_quantity: u64,
+        #[prost(uint64, tag = "5")]
+        pub fillable_quantity: u64,
+        #[prost(double, optional, tag = "6")]
+        pub average_price: Option<f64>,
+        #[prost(uint64, optional, tag = "7")]
+        pub worst_price: Option<u64>,
+    }
+
+    #[derive(Clone, PartialEq, prost::Message)]
+    pub struct ExecutionReport {
+        #[prost(uint64, tag = "1")]
+        pub user_id: u64,
+        #[prost(string, tag = "2")]
+        pub symbol: String,
+        #[prost(uint64, tag = "3")]
+        pub order_id: u64,
+        #[prost(enumeration = "OrderSide", tag = "4")]
+        pub side: i32,
+        #[prost(enumeration = "OrderStatus", tag = "5")]
+        pub status: i32,
+        #[prost(uint64, tag = "6")]
+        pub trade_id: u64,
+        #[prost(uint64, tag = "7")]
+        pub last_price: u64,
+        #[prost(uint64, tag = "8")]
+        pub last_quantity: u64,
+        #[prost(uint64, tag = "9")]
+        pub cumulative_quantity: u64,
+        #[prost(uint64, tag = "10")]
+        pub leaves_quantity: u64,
+        #[prost(uint64, optional, tag = "11")]
+        pub link_id: Option<u64>,
+        #[prost(uint64, optional, tag = "12")]
+        pub received_at_ns: Option<u64>,
+    }
+
+    #[derive(Clone, PartialEq, prost::Message)]
+    pub struct Candle {
+        #[prost(uint64, tag = "1")]
+        pub start: u64,
+        #[prost(uint64, tag = "2")]
+        pub open: u64,
+        #[prost(uint64, tag = "3")]
+        pub high: u64,
+        #[prost(uint64, tag = "4")]
+        pub low: u64,
+        #[prost(uint64, tag = "5")]
+        pub close: u64,
+        #[prost(uint64, tag = "6")]
+        pub volume: u64,
+    }
+
+    #[derive(Clone, PartialEq, prost::Message)]
+    pub struct MarketDataSnapshot {
+        #[prost(uint64, tag = "1")]
+        pub user_id: u64,
+        #[prost(string, tag = "2")]
+        pub symbol: String,
+        #[prost(uint64, optional, tag = "3")]
+        pub last_price: Option<u64>,
+        #[prost(uint64, optional, tag = "4")]
+        pub high: Option<u64>,
+        #[prost(uint64, optional, tag = "5")]
+        pub low: Option<u64>,
+        #[prost(uint64, tag = "6")]
+        pub volume: u64,
+        #[prost(uint64, tag = "7")]
+        pub trade_count: u64,
+        #[prost(string, tag = "8")]
+        pub turnover: String,
+        #[prost(double, optional, tag = "9")]
+        pub vwap: Option<f64>,
+        #[prost(message, repeated, tag = "10")]
+        pub candles: Vec<Candle>,
+    }
+
+    #[derive(Clone, PartialEq, prost::Message)]
+    pub struct MarketDataModeNotice {
+        #[prost(enumeration = "MarketDataMode", tag = "1")]
+        pub mode: i32,
+    }
+
+    #[derive(Clone, PartialEq, prost::Message)]
+    pub struct LogonResponse {
+        #[prost(uint64, tag = "1")]
+        pub user_id: u64,
+        #[prost(enumeration = "LogonStatus", tag = "2")]
+        pub status: i32,
+    }
+
+    #[derive(Clone, PartialEq, prost::Message)]
+    pub struct ResumeResponse {
+        #[prost(uint64, tag = "1")]
+        pub last_inbound_seq: u64,
+        #[prost(uint64, tag = "2")]
+        pub last_outbound_seq: u64,
+        #[prost(uint64, tag = "3")]
+        pub replayed: u64,
+    }
+
+    #[derive(Clone, PartialEq, prost::Message)]
+    pub struct MarkPrice {
+        #[prost(string, tag = "1")]
+        pub symbol: String,
+        #[prost(uint64, tag = "2")]
+        pub mark_price: u64,
+        #[prost(uint64, tag = "3")]
+        pub index_price: u64,
+        #[prost(uint64, tag = "4")]
+        pub timestamp: u64,
+    }
+
+    #[derive(Clone, PartialEq, prost::Message)]
+    pub struct ImpliedQuote {
+        #[prost(string, tag = "1")]
+        pub spread: String,
+        #[prost(sint64, optional, tag = "2")]
+        pub bid_price: Option<i64>,
+        #[prost(uint64, tag = "3")]
+        pub bid_quantity: u64,
+        #[prost(sint64, optional, tag = "4")]
+        pub ask_price: Option<i64>,
+        #[prost(uint64, tag = "5")]
+        pub ask_quantity: u64,
+    }
+
+    #[derive(Clone, PartialEq, prost::Message)]
+    pub struct ServerEnvelope {
+        #[prost(oneof = "server_envelope::Message", tags = "1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 17, 18")]
+        pub message: Option<server_envelope::Message>,
+        #[prost(uint64, tag = "15")]
+        pub request_id: u64,
+        #[prost(uint64, tag = "16")]
+        pub seq: u64,
+    }
+
+    pub mod server_envelope {
+        #[derive(Clone, PartialEq, prost::Oneof)]
+        pub enum Message {
+            #[prost(message, tag = "1")]
+            Trade(super::TradeNotification),
+            #[prost(message, tag = "2")]
+            Confirmation(super::OrderConfirmation),
+            #[prost(message, tag = "3")]
+            Reject(super::OrderReject),
+            #[prost(message, tag = "4")]
+            Position(super::PositionReport),
+            #[prost(message, tag = "5")]
+            TradingStatus(super::TradingStatus),
+            #[prost(message, tag = "6")]
+            CancelAck(super::CancelAck),
+            #[prost(message, tag = "7")]
+            MarketDataMode(super::MarketDataModeNotice),
+            #[prost(message, tag = "8")]
+            FillEstimate(super::FillEstimate),
+            #[prost(message, tag = "9")]
+            ExecutionReport(super::ExecutionReport),
+            #[prost(message, tag = "10")]
+            MarketData(super::MarketDataSnapshot),
+            #[prost(message, tag = "11")]
+            Logon(super::LogonResponse),
+            #[prost(message, tag = "12")]
+            Heartbeat(super::Heartbeat),
+            #[prost(message, tag = "13")]
+            Shutdown(super::Shutdown),
+            #[prost(message, tag = "14")]
+            Resume(super::ResumeResponse),
+            #[prost(message, tag = "17")]
+            MarkPrice(super::MarkPrice),
+            #[prost(message, tag = "18")]
+            ImpliedQuote(super::ImpliedQuote),
+        }
+    }
+}
+
+// Protobuf 编码，供非 Rust 客户端互通
+#[derive(Debug, Clone, Copy, Default)]
+pub struct ProtobufCodec;
+
+impl Codec for ProtobufCodec {
+    fn encode_client(&self, message: &ClientMessage) -> Result<Vec<u8>, CodecError> {
+        Ok(client_to_pb(message.clone()).encode_to_vec())
+    }
+
+    fn decode_client(&self, bytes: &[u8]) -> Result<ClientMessage, CodecError> {
+        let envelope = pb::ClientEnvelope::decode(bytes).map_err(|e| CodecError::Decode(e.to_string()))?;
+        client_from_pb(envelope)
+    }
+
+    fn encode_server(&self, message: &ServerMessage) -> Result<Vec<u8>, CodecError> {
+        Ok(server_to_pb(message.clone()).encode_to_vec())
+    }
+
+    fn decode_server(&self, bytes: &[u8]) -> Result<ServerMessage, CodecError> {
+        let envelope = pb::ServerEnvelope::decode(bytes).map_err(|e| CodecError::Decode(e.to_string()))?;
+        server_from_pb(envelope)
+    }
+}
+
+fn client_to_pb(message: ClientMessage) -> pb::ClientEnvelope {
+    use pb::client_envelope::Message;
+    let message = match message {
+        ClientMessage::NewOrder(request) => Message::NewOrder(new_order_to_pb(request)),
+        ClientMessage::CancelOrder(request) => Message::CancelOrder(pb::CancelOrderRequest {
+            user_id: request.user_id,
+            symbol: request.symbol,
+            order_id: request.order_id,
+        }),
+        ClientMessage::AmendOrder(request) => Message::AmendOrder(pb::AmendOrderRequest {
+            user_id: request.user_id,
+            symbol: request.symbol,
+            order_id: request.order_id,
+            new_price: request.new_price,
+            new_quantity: request.new_quantity,
+        }),
+        ClientMessage::BlockTrade(request) => Message::BlockTrade(pb::BlockTradeRequest {
+            symbol: request.symbol,
+            price: request.price,
+            quantity: request.quantity,
+            buyer_user_id: request.buyer_user_id,
+            seller_user_id: request.seller_user_id,
+        }),
+        ClientMessage::QueryPosition(query) => Message::QueryPosition(pb::PositionQuery {
+            user_id: query.user_id,
+            symbol: query.symbol,
+        }),
+        ClientMessage::EstimateFill(request) => Message::EstimateFill(pb::FillEstimateRequest {
+            user_id: request.user_id,
+            symbol: request.symbol,
+            side: side_to_pb(request.order_type),
+            quantity: request.quantity,
+        }),
+        ClientMessage::QueryMarketData(query) => Message::QueryMarketData(pb::MarketDataQuery {
+            user_id: query.user_id,
+            symbol: query.symbol,
+            candle_interval_ms: query.candle_interval_ms,
+            candle_limit: query.candle_limit,
+        }),
+        ClientMessage::Logon(request) => Message::Logon(pb::LogonRequest {
+            api_key: request.api_key,
+            timestamp_ms: request.timestamp_ms,
+            signature: request.signature,
+        }),
+        ClientMessage::Heartbeat => Message::Heartbeat(pb::Heartbeat {}),
+        ClientMessage::Resume(request) => Message::Resume(pb::ResumeRequest {
+            last_received_seq: request.last_received_seq,
+        }),
+        ClientMessage::SpreadOrder(request) => Message::SpreadOrder(pb::SpreadOrderRequest {
+            user_id: request.user_id,
+            spread: request.spread,
+            side: side_to_pb(request.order_type),
+            price: request.price,
+            quantity: request.quantity,
+        }),
+        ClientMessage::BasketOrder(request) => Message::BasketOrder(pb::BasketOrderRequest {
+            user_id: request.user_id,
+            legs: request
+                .legs
+                .into_iter()
+                .map(|leg| pb::BasketLeg {
+                    symbol: leg.symbol,
+                    side: side_to_pb(leg.order_type),
+                    price: leg.price,
+                    quantity: leg.quantity,
+                })
+                .collect(),
+        }),
+        ClientMessage::OcoOrder(request) => Message::OcoOrder(pb::OcoOrderRequest {
+            link_id: request.link_id,
+            first: Some(new_order_to_pb(request.first)),
+            second: Some(new_order_to_pb(request.second)),
+        }),
+        ClientMessage::TimedOrder(request) => Message::TimedOrder(pb::TimedOrderRequest {
+            order: Some(new_order_to_pb(request.order)),
+            expire_at_ms: match request.time_in_force {
+                TimeInForce::GoodTillCancel => None,
+                TimeInForce::GoodTillDate { expire_at_ms } => Some(expire_at_ms),
+            },
+        }),
+        ClientMessage::ReduceOnlyOrder(request) => Message::ReduceOnlyOrder(new_order_to_pb(request)),
+        // 请求 ID 是信封上的字段；嵌套时以最外层的 ID 为准
+        ClientMessage::Request { request_id, message } => {
+            return pb::ClientEnvelope { request_id, ..client_to_pb(*message) };
+        }
+    };
+    pb::ClientEnvelope { message: Some(message), request_id: 0 }
+}
+
+fn client_from_pb(envelope: pb::ClientEnvelope) -> Result<ClientMessage, CodecError> {
+    use pb::client_envelope::Message;
+    let message = match envelope.message.ok_or_else(|| missing("ClientEnvelope.message"))? {
+        Message::NewOrder(request) => ClientMessage::NewOrder(new_order_from_pb(request)?),
+        Message::CancelOrder(request) => ClientMessage::CancelOrder(CancelOrderRequest {
+            user_id: request.user_id,
+            symbol: request.symbol,
+            order_id: request.order_id,
+        }),
+        Message::AmendOrder(request) => ClientMessage::AmendOrder(AmendOrderRequest {
+            user_id: request.user_id,
+            symbol: request.symbol,
+            order_id: request.order_id,
+            new_price: request.new_price,
+            new_quantity: request.new_quantity,
+        }),
+        Message::BlockTrade(request) => ClientMessage::BlockTrade(BlockTradeRequest {
+            symbol: request.symbol,
+            price: request.price,
+            quantity: request.quantity,
+            buyer_user_id: request.buyer_user_id,
+            seller_user_id: request.seller_user_id,
+        }),
+        Message::QueryPosition(query) => ClientMessage::QueryPosition(PositionQuery {
+            user_id: query.user_id,
+            symbol: query.symbol,
+        }),
+        Message::EstimateFill(request) => ClientMessage::EstimateFill(FillEstimateRequest {
+            user_id: request.user_id,
+            order_type: side_from_pb(request.side)?,
+            symbol: request.symbol,
+            quantity: request.quantity,
+        }),
+        Message::QueryMarketData(query) => ClientMessage::QueryMarketData(MarketDataQuery {
+            user_id: query.user_id,
+            symbol: query.symbol,
+            candle_interval_ms: query.candle_interval_ms,
+            candle_limit: query.candle_limit,
+        }),
+        Message::Logon(request) => ClientMessage::Logon(LogonRequest {
+            api_key: request.api_key,
+            timestamp_ms: request.timestamp_ms,
+            signature: request.signature,
+        }),
+        Message::Heartbeat(_) => ClientMessage::Heartbeat,
+        Message::Resume(request) => ClientMessage::Resume(ResumeRequest {
+            last_received_seq: request.last_received_seq,
+        }),
+        Message::SpreadOrder(request) => ClientMessage::SpreadOrder(SpreadOrderRequest {
+            user_id: request.user_id,
+            order_type: side_from_pb(request.side)?,
+            spread: request.spread,
+            price: request.price,
+            quantity: request.quantity,
+        }),
+        Message::BasketOrder(request) => ClientMessage::BasketOrder(BasketOrderRequest {
+            user_id: request.user_id,
+            legs: request
+                .legs
+                .into_iter()
+                .map(|leg| {
+                    Ok(BasketLeg {
+                        symbol: leg.symbol,
+                        order_type: side_from_pb(leg.side)?,
+                        price: leg.price,
+                        quantity: leg.quantity,
+                    })
+                })
+                .collect::<Result<_, CodecError>>()?,
+        }),
+        Message::OcoOrder(request) => ClientMessage::OcoOrder(OcoOrderRequest {
+            link_id: request.link_id,
+            first: new_order_from_pb(request.first.ok_or_else(|| missing("OcoOrderRequest.first"))?)?,
+            second: new_order_from_pb(request.second.ok_or_else(|| missing("OcoOrderRequest.second"))?)?,
+        }),
+        Message::TimedOrder(request) => ClientMessage::TimedOrder(TimedOrderRequest {
+            order: new_order_from_pb(request.order.ok_or_else(|| missing("TimedOrderRequest.order"))?)?,
+            time_in_force: match request.expire_at_ms {
+                None => TimeInForce::GoodTillCancel,
+                Some(expire_at_ms) => TimeInForce::GoodTillDate { expire_at_ms },
+            },
+        }),
+        Message::ReduceOnlyOrder(request) => ClientMessage::ReduceOnlyOrder(new_order_from_pb(request)?),
+    };
+    Ok(match envelope.request_id {
+        0 => message,
+        request_id => ClientMessage::Request { request_id, message: Box::new(message) },
+    })
+}
+
+fn server_to_pb(message: ServerMessage) -> pb::ServerEnvelope {
+    use pb::server_envelope::Message;
+    let message = match message {
+        ServerMessage::Trade(trade) => Message::Trade(pb::TradeNotification {
+            trade_id: trade.trade_id,
+            symbol: trade.symbol.to_string(),
+            matched_price: trade.matched_price,
+            matched_quantity: trade.matched_quantity,
+            buyer_user_id: trade.buyer_user_id,
+            buyer_order_id: trade.buyer_order_id,
+            seller_user_id: trade.seller_user_id,
+            seller_order_id: trade.seller_order_id,
+            timestamp: trade.timestamp,
+            is_block_trade: trade.is_block_trade,
+        }),
+        ServerMessage::Confirmation(confirmation) => Message::Confirmation(pb::OrderConfirmation {
+            order_id: confirmation.order_id,
+            user_id: confirmation.user_id,
+        }),
+        ServerMessage::Reject(reject) => Message::Reject(pb::OrderReject {
+            user_id: reject.user_id,
+            symbol: reject.symbol,
+            reason: reject_reason_to_pb(reject.reason) as i32,
+        }),
+        ServerMessage::Position(report) => Message::Position(pb::PositionReport {
+            user_id: report.user_id,
+            symbol: report.symbol,
+            net_position: report.net_position,
+        }),
+        ServerMessage::TradingStatus(status) => Message::TradingStatus(pb::TradingStatus {
+            symbol: status.symbol,
+            phase: match status.phase {
+                TradingPhase::Continuous => pb::TradingPhase::Continuous,
+                TradingPhase::Auction => pb::TradingPhase::Auction,
+                TradingPhase::Halted => pb::TradingPhase::Halted,
+                TradingPhase::Expired => pb::TradingPhase::Expired,
+            } as i32,
+        }),
+        ServerMessage::CancelAck(ack) => Message::CancelAck(pb::CancelAck {
+            user_id: ack.user_id,
+            symbol: ack.symbol,
+            order_id: ack.order_id,
+            cancelled_quantity: ack.cancelled_quantity,
+            status: match ack.status {
+                CancelStatus::Cancelled => pb::CancelStatus::Cancelled,
+                CancelStatus::AlreadyCancelled => pb::CancelStatus::AlreadyCancelled,
+                CancelStatus::UnknownOrder => pb::CancelStatus::UnknownOrder,
+                CancelStatus::Expired => pb::CancelStatus::Expired,
+                CancelStatus::LinkedOrderFilled => pb::CancelStatus::LinkedOrderFilled,
+                CancelStatus::NotOwner => pb::CancelStatus::NotOwner,
+                CancelStatus::PriceBandBreach => pb::CancelStatus::PriceBandBreach,
+            } as i32,
+        }),
+        ServerMessage::MarketDataMode(mode) => Message::MarketDataMode(pb::MarketDataModeNotice {
+            mode: match mode {
+                MarketDataMode::Full => pb::MarketDataMode::Full,
+                MarketDataMode::Conflated => pb::MarketDataMode::Conflated,
+            } as i32,
+        }),
+        ServerMessage::FillEstimate(estimate) => Message::FillEstimate(pb::FillEstimate {
+            user_id: estimate.user_id,
+            symbol: estimate.symbol,
+            side: side_to_pb(estimate.order_type),
+            requested_quantity: estimate.requested_quantity,
+            fillable_quantity: estimate.fillable_quantity,
+            average_price: estimate.average_price,
+            worst_price: estimate.worst_price,
+        }),
+        ServerMessage::ExecutionReport(report) => Message::ExecutionReport(pb::ExecutionReport {
+            user_id: report.user_id,
+            symbol: report.symbol,
+            order_id: report.order_id,
+            side: side_to_pb(report.order_type),
+            status: match report.status {
+                OrderStatus::PartiallyFilled => pb::OrderStatus::PartiallyFilled,
+                OrderStatus::Filled => pb::OrderStatus::Filled,
+                OrderStatus::Expired => pb::OrderStatus::Expired,
+                OrderStatus::Restated => pb::OrderStatus::Restated,
+                OrderStatus::Cancelled => pb::OrderStatus::Cancelled,
+            } as i32,
+            trade_id: report.trade_id,
+            last_price: report.last_price,
+            last_quantity: report.last_quantity,
+            cumulative_quantity: report.cumulative_quantity,
+            leaves_quantity: report.leaves_quantity,
+            link_id: report.link_id,
+            received_at_ns: report.received_at_ns,
+        }),
+        ServerMessage::MarketData(snapshot) => Message::MarketData(pb::MarketDataSnapshot {
+            user_id: snapshot.user_id,
+            symbol: snapshot.symbol,
+            last_price: snapshot.last_price,
+            high: snapshot.high,
+            low: snapshot.low,
+            volume: snapshot.volume,
+            trade_count: snapshot.trade_count,
+            turnover: snapshot.turnover.to_string(),
+            vwap: snapshot.vwap,
+            candles: snapshot
+                .candles
+                .into_iter()
+                .map(|candle| pb::Candle {
+                    start: candle.start,
+                    open: candle.open,
+                    high: candle.high,
+                    low: candle.low,
+                    close: candle.close,
+                    volume: candle.volume,
+                })
+                .collect(),
+        }),
+        ServerMessage::Logon(response) => Message::Logon(pb::LogonResponse {
+            user_id: response.user_id,
+            status: match response.status {
+                LogonStatus::Accepted => pb::LogonStatus::Accepted,
+                LogonStatus::UnknownApiKey => pb::LogonStatus::UnknownApiKey,
+                LogonStatus::InvalidSignature => pb::LogonStatus::InvalidSignature,
+                LogonStatus::StaleTimestamp => pb::LogonStatus::StaleTimestamp,
+                LogonStatus::AlreadyLoggedOn => pb::LogonStatus::AlreadyLoggedOn,
+            } as i32,
+        }),
+        ServerMessage::Heartbeat => Message::Heartbeat(pb::Heartbeat {}),
+        ServerMessage::Shutdown => Message::Shutdown(pb::Shutdown {}),
+        ServerMessage::Resume(response) => Message::Resume(pb::ResumeResponse {
+            last_inbound_seq: response.last_inbound_seq,
+            last_outbound_seq: response.last_outbound_seq,
+            replayed: response.replayed,
+        }),
+        ServerMessage::MarkPrice(price) => Message::MarkPrice(pb::MarkPrice {
+            symbol: price.symbol,
+            mark_price: price.mark_price,
+            index_price: price.index_price,
+            timestamp: price.timestamp,
+        }),
+        ServerMessage::ImpliedQuote(quote) => Message::ImpliedQuote(pb::ImpliedQuote {
+            spread: quote.spread,
+            bid_price: quote.bid_price,
+            bid_quantity: quote.bid_quantity,
+            ask_price: quote.ask_price,
+            ask_quantity: quote.ask_quantity,
+        }),
+        // 请求 ID 和回报序号是信封上的字段
+        ServerMessage::Response { request_id, message } => {
+            return pb::ServerEnvelope { request_id, ..server_to_pb(*message) };
+        }
+        ServerMessage::Sequenced { seq, message } => {
+            return pb::ServerEnvelope { seq, ..server_to_pb(*message) };
+        }
+    };
+    pb::ServerEnvelope { message: Some(message), request_id: 0, seq: 0 }
+}
+
+fn server_from_pb(envelope: pb::ServerEnvelope) -> Result<ServerMessage, CodecError> {
+    use pb::server_envelope::Message;
+    let message = match envelope.message.ok_or_else(|| missing("ServerEnvelope.message"))? {
+        Message::Trade(trade) => ServerMessage::Trade(TradeNotification {
+            trade_id: trade.trade_id,
+            symbol: trade.symbol.into(),
+            matched_price: trade.matched_price,
+            matched_quantity: trade.matched_quantity,
+            buyer_user_id: trade.buyer_user_id,
+            buyer_order_id: trade.buyer_order_id,
+            seller_user_id: trade.seller_user_id,
+            seller_order_id: trade.seller_order_id,
+            timestamp: trade.timestamp,
+            is_block_trade: trade.is_block_trade,
+        }),
+        Message::Confirmation(confirmation) => ServerMessage::Confirmation(OrderConfirmation {
+            order_id: confirmation.order_id,
+            user_id: confirmation.user_id,
+        }),
+        Message::Reject(reject) => ServerMessage::Reject(OrderReject {
+            user_id: reject.user_id,
+            reason: reject_reason_from_pb(reject.reason)?,
+            symbol: reject.symbol,
+        }),
+        Message::Position(report) => ServerMessage::Position(PositionReport {
+            user_id: report.user_id,
+            symbol: report.symbol,
+            net_position: report.net_position,
+        }),
+        Message::TradingStatus(status) => ServerMessage::TradingStatus(TradingStatus {
+            phase: match enum_from_pb::<pb::TradingPhase>(status.phase, "TradingPhase")? {
+                pb::TradingPhase::Continuous => TradingPhase::Continuous,
+                pb::TradingPhase::Auction => TradingPhase::Auction,
+                pb::TradingPhase::Halted => TradingPhase::Halted,
+                pb::TradingPhase::Expired => TradingPhase::Expired,
+                pb::TradingPhase::Unspecified => return Err(unspecified("TradingPhase")),
+            },
+            symbol: status.symbol,
+        }),
+        Message::CancelAck(ack) => ServerMessage::CancelAck(CancelAck {
+            user_id: ack.user_id,
+            order_id: ack.order_id,
+            cancelled_quantity: ack.cancelled_quantity,
+            status: match enum_from_pb::<pb::CancelStatus>(ack.status, "CancelStatus")? {
+                pb::CancelStatus::Cancelled => CancelStatus::Cancelled,
+                pb::CancelStatus::AlreadyCancelled => CancelStatus::AlreadyCancelled,
+                pb::CancelStatus::UnknownOrder => CancelStatus::UnknownOrder,
+                pb::CancelStatus::Expired => CancelStatus::Expired,
+                pb::CancelStatus::LinkedOrderFilled => CancelStatus::LinkedOrderFilled,
+                pb::CancelStatus::NotOwner => CancelStatus::NotOwner,
+                pb::CancelStatus::PriceBandBreach => CancelStatus::PriceBandBreach,
+                pb::CancelStatus::Unspecified => return Err(unspecified("CancelStatus")),
+            },
+            symbol: ack.symbol,
+        }),
+        Message::MarketDataMode(notice) => ServerMessage::MarketDataMode(
+            match enum_from_pb::<pb::MarketDataMode>(notice.mode, "MarketDataMode")? {
+                pb::MarketDataMode::Full => MarketDataMode::Full,
+                pb::MarketDataMode::Conflated => MarketDataMode::Conflated,
+                pb::MarketDataMode::Unspecified => return Err(unspecified("MarketDataMode")),
+            },
+        ),
+        Message::FillEstimate(estimate) => ServerMessage::FillEstimate(FillEstimate {
+            user_id: estimate.user_id,
+            order_type: side_from_pb(estimate.side)?,
+            symbol: estimate.symbol,
+            requested_quantity: estimate.requested_quantity,
+            fillable_quantity: estimate.fillable_quantity,
+            average_price: estimate.average_price,
+            worst_price: estimate.worst_price,
+        }),
+        Message::ExecutionReport(report) => ServerMessage::ExecutionReport(ExecutionReport {
+            user_id: report.user_id,
+            order_id: report.order_id,
+            order_type: side_from_pb(report.side)?,
+            status: match enum_from_pb::<pb::OrderStatus>(report.status, "OrderStatus")? {
+                pb::OrderStatus::PartiallyFilled => OrderStatus::PartiallyFilled,
+                pb::OrderStatus::Filled => OrderStatus::Filled,
+                pb::OrderStatus::Expired => OrderStatus::Expired,
+                pb::OrderStatus::Restated => OrderStatus::Restated,
+                pb::OrderStatus::Cancelled => OrderStatus::Cancelled,
+                pb::OrderStatus::Unspecified => return Err(unspecified("OrderStatus")),
+            },
+            symbol: report.symbol,
+            trade_id: report.trade_id,
+            last_price: report.last_price,
+            last_quantity: report.last_quantity,
+            cumulative_quantity: report.cumulative_quantity,
+            leaves_quantity: report.leaves_quantity,
+            link_id: report.link_id,
+            received_at_ns: report.received_at_ns,
+        }),
+        Message::MarketData(snapshot) => ServerMessage::MarketData(MarketDataSnapshot {
+            user_id: snapshot.user_id,
+            symbol: snapshot.symbol,
+            last_price: snapshot.last_price,
+            high: snapshot.high,
+            low: snapshot.low,
+            volume: snapshot.volume,
+            trade_count: snapshot.trade_count,
+            turnover: snapshot
+                .turnover
+                .parse()
+                .map_err(|_| CodecError::Decode(format!("无效的成交金额: {}", snapshot.turnover)))?,
+            vwap: snapshot.vwap,
+            candles: snapshot
+                .candles
+                .into_iter()
+                .map(|candle| Candle {
+                    start: candle.start,
+                    open: candle.open,
+                    high: candle.high,
+                    low: candle.low,
+                    close: candle.close,
+                    volume: candle.volume,
+                })
+                .collect(),
+        }),
+        Message::Logon(response) => ServerMessage::Logon(LogonResponse {
+            user_id: response.user_id,
+            status: match enum_from_pb::<pb::LogonStatus>(response.status, "LogonStatus")? {
+                pb::LogonStatus::Accepted => LogonStatus::Accepted,
+                pb::LogonStatus::UnknownApiKey => LogonStatus::UnknownApiKey,
+                pb::LogonStatus::InvalidSignature => LogonStatus::InvalidSignature,
+                pb::LogonStatus::StaleTimestamp => LogonStatus::StaleTimestamp,
+                pb::LogonStatus::AlreadyLoggedOn => LogonStatus::AlreadyLoggedOn,
+                pb::LogonStatus::Unspecified => return Err(unspecified("LogonStatus")),
+            },
+        }),
+        Message::Heartbeat(_) => ServerMessage::Heartbeat,
+        Message::Shutdown(_) => ServerMessage::Shutdown,
+        Message::Resume(response) => ServerMessage::Resume(ResumeResponse {
+            last_inbound_seq: response.last_inbound_seq,
+            last_outbound_seq: response.last_outbound_seq,
+            replayed: response.replayed,
+        }),
+        Message::MarkPrice(price) => ServerMessage::MarkPrice(MarkPrice {
+            symbol: price.symbol,
+            mark_price: price.mark_price,
+            index_price: price.index_price,
+            timestamp: price.timestamp,
+        }),
+        Message::ImpliedQuote(quote) => ServerMessage::ImpliedQuote(ImpliedQuote {
+            spread: quote.spread,
+            bid_price: quote.bid_price,
+            bid_quantity: quote.bid_quantity,
+            ask_price: quote.ask_price,
+            ask_quantity: quote.ask_quantity,
+        }),
+    };
+    // 与网关相同的嵌套顺序：请求应答在外，带序号的回报在内
+    let message = match envelope.seq {
+        0 => message,
+        seq => ServerMessage::Sequenced { seq, message: Box::new(message) },
+    };
+    Ok(match envelope.request_id {
+        0 => message,
+        request_id => ServerMessage::Response { request_id, message: Box::new(message) },
+    })
+}
+
+fn new_order_to_pb(request: NewOrderRequest) -> pb::NewOrderRequest {
+    pb::NewOrderRequest {
+        user_id: request.user_id,
+        symbol: request.symbol,
+        side: side_to_pb(request.order_type),
+        price: request.price,
+        quantity: request.quantity,
+    }
+}
+
+fn new_order_from_pb(request: pb::NewOrderRequest) -> Result<NewOrderRequest, CodecError> {
+    Ok(NewOrderRequest {
+        user_id: request.user_id,
+        order_type: side_from_pb(request.side)?,
+        symbol: request.symbol,
+        price: request.price,
+        quantity: request.quantity,
+    })
+}
+
+fn side_to_pb(order_type: OrderType) -> i32 {
+    match order_type {
+        OrderType::Buy => pb::OrderSide::Buy as i32,
+        OrderType::Sell => pb::OrderSide::Sell as i32,
+    }
+}
+
+fn side_from_pb(side: i32) -> Result<OrderType, CodecError> {
+    match enum_from_pb::<pb::OrderSide>(side, "OrderSide")? {
+        pb::OrderSide::Buy => Ok(OrderType::Buy),
+        pb::OrderSide::Sell => Ok(OrderType::Sell),
+        pb::OrderSide::Unspecified => Err(unspecified("OrderSide")),
+    }
+}
+
+fn reject_reason_to_pb(reason: RejectReason) -> pb::RejectReason {
+    match reason {
+        RejectReason::BlockTradeTooSmall => pb::RejectReason::BlockTradeTooSmall,
+        RejectReason::BlockTradePriceOutOfRange => pb::RejectReason::BlockTradePriceOutOfRange,
+        RejectReason::Throttled => pb::RejectReason::Throttled,
+        RejectReason::PositionLimitExceeded => pb::RejectReason::PositionLimitExceeded,
+        RejectReason::PriceBandBreach => pb::RejectReason::PriceBandBreach,
+        RejectReason::UnknownOrder => pb::RejectReason::UnknownOrder,
+        RejectReason::InvalidPrice => pb::RejectReason::InvalidPrice,
+        RejectReason::InvalidQuantity => pb::RejectReason::InvalidQuantity,
+        RejectReason::Unauthenticated => pb::RejectReason::Unauthenticated,
+        RejectReason::UserMismatch => pb::RejectReason::UserMismatch,
+        RejectReason::ContractExpired => pb::RejectReason::ContractExpired,
+        RejectReason::PriceCollarBreach => pb::RejectReason::PriceCollarBreach,
+        RejectReason::UnknownSpread => pb::RejectReason::UnknownSpread,
+        RejectReason::NoImpliedLiquidity => pb::RejectReason::NoImpliedLiquidity,
+        RejectReason::InvalidBasket => pb::RejectReason::InvalidBasket,
+        RejectReason::BasketNotFillable => pb::RejectReason::BasketNotFillable,
+        RejectReason::LinkedOrderFilled => pb::RejectReason::LinkedOrderFilled,
+        RejectReason::InvalidExpiry => pb::RejectReason::InvalidExpiry,
+        RejectReason::ReduceOnlyExceedsPosition => pb::RejectReason::ReduceOnlyExceedsPosition,
+        RejectReason::SymbolLimitReached => pb::RejectReason::SymbolLimitReached,
+        RejectReason::NotOrderOwner => pb::RejectReason::NotOrderOwner,
+    }
+}
+
+fn reject_reason_from_pb(reason: i32) -> Result<RejectReason, CodecError> {
+    Ok(match enum_from_pb::<pb::RejectReason>(reason, "RejectReason")? {
+        pb::RejectReason::BlockTradeTooSmall => RejectReason::BlockTradeTooSmall,
+        pb::RejectReason::BlockTradePriceOutOfRange => RejectReason::BlockTradePriceOutOfRange,
+        pb::RejectReason::Throttled => RejectReason::Throttled,
+        pb::RejectReason::PositionLimitExceeded => RejectReason::PositionLimitExceeded,
+        pb::RejectReason::PriceBandBreach => RejectReason::PriceBandBreach,
+        pb::RejectReason::UnknownOrder => RejectReason::UnknownOrder,
+        pb::RejectReason::InvalidPrice => RejectReason::InvalidPrice,
+        pb::RejectReason::InvalidQuantity => RejectReason::InvalidQuantity,
+        pb::RejectReason::Unauthenticated => RejectReason::Unauthenticated,
+        pb::RejectReason::UserMismatch => RejectReason::UserMismatch,
+        pb::RejectReason::ContractExpired => RejectReason::ContractExpired,
+        pb::RejectReason::PriceCollarBreach => RejectReason::PriceCollarBreach,
+        pb::RejectReason::UnknownSpread => RejectReason::UnknownSpread,
+        pb::RejectReason::NoImpliedLiquidity => RejectReason::NoImpliedLiquidity,
+        pb::RejectReason::InvalidBasket => RejectReason::InvalidBasket,
+        pb::RejectReason::BasketNotFillable => RejectReason::BasketNotFillable,
+        pb::RejectReason::LinkedOrderFilled => RejectReason::LinkedOrderFilled,
+        pb::RejectReason::InvalidExpiry => RejectReason::InvalidExpiry,
+        pb::RejectReason::ReduceOnlyExceedsPosition => RejectReason::ReduceOnlyExceedsPosition,
+        pb::RejectReason::SymbolLimitReached => RejectReason::SymbolLimitReached,
+        pb::RejectReason::NotOrderOwner => RejectReason::NotOrderOwner,
+        pb::RejectReason::Unspecified => return Err(unspecified("RejectReason")),
+    })
+}
+
+fn enum_from_pb<E: TryFrom<i32>>(value: i32, name: &str) -> Result<E, CodecError> {
+    E::try_from(value).map_err(|_| CodecError::Decode(format!("未知的 {} 取值: {}", name, value)))
+}
+
+fn unspecified(name: &str) -> CodecError {
+    CodecError::Decode(format!("{} 未设置", name))
+}
+
+fn missing(field: &str) -> CodecError {
+    CodecError::Decode(format!("缺少字段 {}", field))
+}
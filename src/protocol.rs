@@ -1,5 +1,6 @@
 use serde::{Deserialize, Serialize};
 use bincode::{Encode, Decode};
+use std::sync::Arc;
 
 /// 订单类型，区分买单和卖单
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Encode, Decode)]
@@ -22,7 +23,48 @@ pub struct NewOrderRequest {
 #[derive(Debug, Clone, Serialize, Deserialize, Encode, Decode)]
 pub struct CancelOrderRequest {
     pub user_id: u64,
+    pub symbol: String,
+    pub order_id: u64,
+}
+
+/// 改单请求：修改挂单的价格和/或数量
+#[derive(Debug, Clone, Serialize, Deserialize, Encode, Decode)]
+pub struct AmendOrderRequest {
+    pub user_id: u64,
+    pub symbol: String,
     pub order_id: u64,
+    pub new_price: u64,
+    pub new_quantity: u64,
+}
+
+/// 撤单处理结果
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Encode, Decode)]
+pub enum CancelStatus {
+    // 订单已从订单簿中撤销
+    Cancelled,
+    // 重复的撤单请求，订单此前已被撤销
+    AlreadyCancelled,
+    // 订单不存在或已全部成交
+    UnknownOrder,
+    // 合约到期，剩余挂单被交易所撤销
+    Expired,
+    // OCO 订单的另一条订单发生成交，本订单被自动撤销
+    LinkedOrderFilled,
+    // 订单属于其他用户，撤单被拒绝，订单保持不变
+    NotOwner,
+    // 恢复撮合时订单只能在涨跌停价格带之外成交，被交易所撤销
+    PriceBandBreach,
+}
+
+/// 撤单回报，对同一订单的重复撤单会得到相同的幂等回报
+#[derive(Debug, Clone, Serialize, Deserialize, Encode, Decode)]
+pub struct CancelAck {
+    pub user_id: u64,
+    pub symbol: String,
+    pub order_id: u64,
+    // 被撤销的剩余数量，重复撤单时为 0
+    pub cancelled_quantity: u64,
+    pub status: CancelStatus,
 }
 
 /// 订单确认回报，发送给下单用户
@@ -33,10 +75,11 @@ pub struct OrderConfirmation {
 }
 
 /// 成交回报，发送给交易双方
-#[derive(Debug, Clone, Serialize, Deserialize, Encode, Decode)]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, Encode, Decode)]
 pub struct TradeNotification {
     pub trade_id: u64,
-    pub symbol: String,
+    // 同一订单簿产生的成交共享订单簿驻留的合约名，生成成交时不复制字符串
+    pub symbol: Arc<str>,
     // 撮合价格
     pub matched_price: u64,
     // 撮合数量
@@ -49,6 +92,379 @@ pub struct TradeNotification {
     pub seller_order_id: u64,
     // 时间戳
     pub timestamp: u64,
+    // 是否为场外协商的大宗交易（不经过订单簿撮合）
+    pub is_block_trade: bool,
+}
+
+/// 执行回报中的订单状态。挂单确认、撤单和拒绝分别由 OrderConfirmation、CancelAck 和 OrderReject 回报
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Encode, Decode)]
+pub enum OrderStatus {
+    PartiallyFilled,
+    Filled,
+    // GTD 订单到期，剩余部分被交易所撤销
+    Expired,
+    // 只减仓订单因持仓减少被交易所缩减，leaves_quantity 为缩减后的剩余数量，为 0 时订单已撤销
+    Restated,
+    // 交易所撤销了订单的剩余部分，例如价差或篮子订单的腿订单没有全部成交；客户撤单仍由 CancelAck 回报
+    Cancelled,
+}
+
+/// 执行回报：每笔成交为买卖双方各生成一条，携带订单成交后的状态
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, Encode, Decode)]
+pub struct ExecutionReport {
+    pub user_id: u64,
+    pub symbol: String,
+    pub order_id: u64,
+    pub order_type: OrderType,
+    pub status: OrderStatus,
+    // 触发本回报的成交
+    pub trade_id: u64,
+    pub last_price: u64,
+    pub last_quantity: u64,
+    // 累计成交数量
+    pub cumulative_quantity: u64,
+    // 剩余未成交数量
+    pub leaves_quantity: u64,
+    // OCO 订单的关联 ID，普通订单为 None
+    pub link_id: Option<u64>,
+    // 主动方订单到达网卡或内核的时间（Unix 纳秒），仅在开启接收时间戳时填写，被动方回报为 None
+    pub received_at_ns: Option<u64>,
+}
+
+/// 跨期价差订单：买入一手价差即买入 leg1、卖出 leg2 各一手，卖出则相反。
+/// 价格为 leg1 与 leg2 成交价之差，可以为负。订单立即与两腿订单簿的隐含流动性撮合，
+/// 两腿总是同时成交相同数量，未成交的部分撤销，不在价差订单簿中挂单
+#[derive(Debug, Clone, Serialize, Deserialize, Encode, Decode)]
+pub struct SpreadOrderRequest {
+    pub user_id: u64,
+    pub spread: String,
+    pub order_type: OrderType,
+    pub price: i64,
+    pub quantity: u64,
+}
+
+/// 篮子订单中的一条腿：在 symbol 上以不差于 price 的价格买入或卖出 quantity
+#[derive(Debug, Clone, Serialize, Deserialize, Encode, Decode)]
+pub struct BasketLeg {
+    pub symbol: String,
+    pub order_type: OrderType,
+    pub price: u64,
+    pub quantity: u64,
+}
+
+/// 篮子订单：跨合约的多条腿要么全部在各自限价内立即成交，要么整单拒绝，任何一条腿都不成交。
+/// 同一合约不能出现在多条腿中，未成交的部分不挂单
+#[derive(Debug, Clone, Serialize, Deserialize, Encode, Decode)]
+pub struct BasketOrderRequest {
+    pub user_id: u64,
+    pub legs: Vec<BasketLeg>,
+}
+
+/// 订单有效期
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Encode, Decode)]
+pub enum TimeInForce {
+    // 一直有效，直到全部成交或撤单
+    GoodTillCancel,
+    // 在 expire_at_ms（Unix 毫秒时间戳）到期时由交易所撤销剩余部分，并发送状态为 Expired 的执行回报
+    GoodTillDate { expire_at_ms: u64 },
+}
+
+/// 指定有效期的新订单
+#[derive(Debug, Clone, Serialize, Deserialize, Encode, Decode)]
+pub struct TimedOrderRequest {
+    pub order: NewOrderRequest,
+    pub time_in_force: TimeInForce,
+}
+
+/// 二选一（OCO）订单：两条关联的订单一起提交，其中一条发生成交后另一条自动撤销。
+/// 两条订单必须属于同一用户，可以是不同合约。link_id 由客户端分配，在两条订单的执行回报中回显
+#[derive(Debug, Clone, Serialize, Deserialize, Encode, Decode)]
+pub struct OcoOrderRequest {
+    pub link_id: u64,
+    pub first: NewOrderRequest,
+    pub second: NewOrderRequest,
+}
+
+/// 大宗交易申报，买卖双方在场外协商好价格和数量后直接登记成交
+#[derive(Debug, Clone, Serialize, Deserialize, Encode, Decode)]
+pub struct BlockTradeRequest {
+    pub symbol: String,
+    pub price: u64,
+    pub quantity: u64,
+    pub buyer_user_id: u64,
+    pub seller_user_id: u64,
+}
+
+/// 订单被拒绝的原因
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Encode, Decode)]
+pub enum RejectReason {
+    // 大宗交易数量低于最小申报数量
+    BlockTradeTooSmall,
+    // 大宗交易价格偏离最新成交价过多
+    BlockTradePriceOutOfRange,
+    // 用户下单速率超过限制
+    Throttled,
+    // 订单全部成交后持仓将超过限额
+    PositionLimitExceeded,
+    // 订单会在涨跌停价格带之外成交
+    PriceBandBreach,
+    // 要修改的订单不存在或已全部成交
+    UnknownOrder,
+    // 价格为 0 或不是最小变动价位的整数倍
+    InvalidPrice,
+    // 数量为 0
+    InvalidQuantity,
+    // 连接尚未登录
+    Unauthenticated,
+    // 消息中的用户与连接登录的用户不一致
+    UserMismatch,
+    // 合约已到期
+    ContractExpired,
+    // 限价偏离标记价格超过价格保护范围
+    PriceCollarBreach,
+    // 未定义的价差合约
+    UnknownSpread,
+    // 价差订单在限价内没有可成交的隐含流动性
+    NoImpliedLiquidity,
+    // 篮子订单没有腿，或同一合约出现在多条腿中
+    InvalidBasket,
+    // 篮子订单至少有一条腿在限价内无法全部成交
+    BasketNotFillable,
+    // OCO 订单的第一条订单提交时即已成交，第二条不再提交
+    LinkedOrderFilled,
+    // GTD 订单的到期时间已过
+    InvalidExpiry,
+    // 只减仓订单没有可以减少的反向持仓（已被同方向的只减仓挂单占满）
+    ReduceOnlyExceedsPosition,
+    // 新合约无法加入已满的合约名驻留池
+    SymbolLimitReached,
+    // 要修改的订单属于其他用户
+    NotOrderOwner,
+}
+
+/// 订单拒绝回报
+#[derive(Debug, Clone, Serialize, Deserialize, Encode, Decode)]
+pub struct OrderReject {
+    pub user_id: u64,
+    pub symbol: String,
+    pub reason: RejectReason,
+}
+
+/// 持仓查询请求
+#[derive(Debug, Clone, Serialize, Deserialize, Encode, Decode)]
+pub struct PositionQuery {
+    pub user_id: u64,
+    pub symbol: String,
+}
+
+/// 持仓查询结果，净持仓为正表示多头，为负表示空头
+#[derive(Debug, Clone, Serialize, Deserialize, Encode, Decode)]
+pub struct PositionReport {
+    pub user_id: u64,
+    pub symbol: String,
+    pub net_position: i64,
+}
+
+/// 预估成交价查询：不下单，只按当前可见的订单簿估算立即成交的结果
+#[derive(Debug, Clone, Serialize, Deserialize, Encode, Decode)]
+pub struct FillEstimateRequest {
+    pub user_id: u64,
+    pub symbol: String,
+    pub order_type: OrderType,
+    pub quantity: u64,
+}
+
+/// 预估成交结果；对手盘深度不足时 fillable_quantity 小于请求数量
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, Encode, Decode)]
+pub struct FillEstimate {
+    pub user_id: u64,
+    pub symbol: String,
+    pub order_type: OrderType,
+    pub requested_quantity: u64,
+    pub fillable_quantity: u64,
+    // 成交均价，没有可成交数量时为 None
+    pub average_price: Option<f64>,
+    // 需要吃到的最差价格
+    pub worst_price: Option<u64>,
+}
+
+/// 行情查询：当日统计及指定周期的最近若干根 K 线
+#[derive(Debug, Clone, Serialize, Deserialize, Encode, Decode)]
+pub struct MarketDataQuery {
+    pub user_id: u64,
+    pub symbol: String,
+    // K 线周期（毫秒），必须是引擎配置的周期之一，否则不返回 K 线
+    pub candle_interval_ms: u64,
+    // 最多返回的 K 线根数，从旧到新排列
+    pub candle_limit: u32,
+}
+
+/// 一根 K 线（OHLCV）
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Encode, Decode)]
+pub struct Candle {
+    // 周期起始时间，UNIX 纳秒
+    pub start: u64,
+    pub open: u64,
+    pub high: u64,
+    pub low: u64,
+    pub close: u64,
+    pub volume: u64,
+}
+
+/// 行情查询结果；当日尚无成交时价格字段为 None
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, Encode, Decode)]
+pub struct MarketDataSnapshot {
+    pub user_id: u64,
+    pub symbol: String,
+    pub last_price: Option<u64>,
+    pub high: Option<u64>,
+    pub low: Option<u64>,
+    // 当日成交量、成交笔数和成交金额，含大宗交易
+    pub volume: u64,
+    pub trade_count: u64,
+    pub turnover: u128,
+    // 当日撮合成交的成交量加权均价，不含大宗交易
+    pub vwap: Option<f64>,
+    pub candles: Vec<Candle>,
+}
+
+/// 合约所处的交易阶段
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, Encode, Decode)]
+pub enum TradingPhase {
+    // 连续竞价，订单到达即撮合
+    #[default]
+    Continuous,
+    // 集合竞价，订单只累积不撮合，竞价结束时以统一价格成交
+    Auction,
+    // 暂停撮合，订单仍可挂单和撤单
+    Halted,
+    // 合约已到期，挂单全部撤销，不再接受新订单
+    Expired,
+}
+
+/// 合约交易阶段变更通知
+#[derive(Debug, Clone, Serialize, Deserialize, Encode, Decode)]
+pub struct TradingStatus {
+    pub symbol: String,
+    pub phase: TradingPhase,
+}
+
+/// 某个价格档位的聚合深度
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, Encode, Decode)]
+pub struct DepthLevel {
+    pub price: u64,
+    pub quantity: u64,
+    pub order_count: u32,
+}
+
+/// 订单簿中的一笔挂单
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, Encode, Decode)]
+pub struct RestingOrder {
+    pub order_id: u64,
+    pub user_id: u64,
+    pub order_type: OrderType,
+    pub price: u64,
+    pub quantity: u64,
+}
+
+/// 合约的前 N 档深度及最优买卖价
+#[derive(Debug, Clone, Serialize, Deserialize, Encode, Decode)]
+pub struct DepthSnapshot {
+    pub symbol: String,
+    pub timestamp: u64,
+    pub best_bid: Option<u64>,
+    pub best_ask: Option<u64>,
+    // 买盘按价格从高到低
+    pub bids: Vec<DepthLevel>,
+    // 卖盘按价格从低到高
+    pub asks: Vec<DepthLevel>,
+    // 整个订单簿的挂单数和两侧价格档位数；旧版本导出的文件中没有这些字段
+    #[serde(default)]
+    pub resting_orders: u64,
+    #[serde(default)]
+    pub bid_levels: u64,
+    #[serde(default)]
+    pub ask_levels: u64,
+    // 剩余数量最大的若干笔挂单，从大到小，只在请求时填充
+    #[serde(default)]
+    pub largest_orders: Vec<RestingOrder>,
+    // bids、asks 中各档的校验和（CRC-32），订阅方用 orderbook::depth_checksum 核对本地订单簿
+    #[serde(default)]
+    pub checksum: u32,
+}
+
+/// 行情推送模式：订阅者处理不过来时由完整推送降级为只推送成交、交易状态和用户本人的回报
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Encode, Decode)]
+pub enum MarketDataMode {
+    Full,
+    Conflated,
+}
+
+/// 登录请求：signature 为 API 密钥对应的 secret 对 "api_key:timestamp_ms" 计算的 HMAC-SHA256
+#[derive(Debug, Clone, Serialize, Deserialize, Encode, Decode)]
+pub struct LogonRequest {
+    pub api_key: String,
+    // 客户端签名时的 UNIX 毫秒时间戳，与服务器时间相差过大的请求会被拒绝
+    pub timestamp_ms: u64,
+    pub signature: Vec<u8>,
+}
+
+/// 登录结果
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Encode, Decode)]
+pub enum LogonStatus {
+    Accepted,
+    UnknownApiKey,
+    InvalidSignature,
+    StaleTimestamp,
+    AlreadyLoggedOn,
+}
+
+/// 登录回报，只发送给发起登录的连接
+#[derive(Debug, Clone, Serialize, Deserialize, Encode, Decode)]
+pub struct LogonResponse {
+    // 登录成功时为会话绑定的用户，否则为 0
+    pub user_id: u64,
+    pub status: LogonStatus,
+}
+
+/// 会话恢复请求：登录后发送，报告最后收到的执行回报序号（见 `ServerMessage::Sequenced`），
+/// 服务器补发之后的回报。从未收到过回报时为 0
+#[derive(Debug, Clone, Serialize, Deserialize, Encode, Decode)]
+pub struct ResumeRequest {
+    pub last_received_seq: u64,
+}
+
+/// 会话恢复回报，随后是补发的带序号执行回报
+#[derive(Debug, Clone, Serialize, Deserialize, Encode, Decode)]
+pub struct ResumeResponse {
+    // 服务器已收到该用户的业务消息数（本次进程启动以来）
+    pub last_inbound_seq: u64,
+    // 服务器为该用户分配的最新回报序号
+    pub last_outbound_seq: u64,
+    // 随后补发的回报数；早于保留范围的回报无法补发，客户端可从序号缺口发现
+    pub replayed: u64,
+}
+
+/// 外部行情源提供的标记价格和指数价格，每次采集到新价格时广播
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, Encode, Decode)]
+pub struct MarkPrice {
+    pub symbol: String,
+    pub mark_price: u64,
+    pub index_price: u64,
+    // 引擎收到价格的时间（纳秒）
+    pub timestamp: u64,
+}
+
+/// 价差合约的隐含报价，由两腿订单簿的最优报价推导：
+/// 买价 = leg1 买一 − leg2 卖一，卖价 = leg1 卖一 − leg2 买一，数量取两腿最优档位中较小的一个。
+/// 任一腿变化导致报价改变时广播
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, Encode, Decode)]
+pub struct ImpliedQuote {
+    pub spread: String,
+    pub bid_price: Option<i64>,
+    pub bid_quantity: u64,
+    pub ask_price: Option<i64>,
+    pub ask_quantity: u64,
 }
 
 /// 客户端发送给服务器的所有消息的顶层枚举
@@ -56,6 +472,26 @@ pub struct TradeNotification {
 pub enum ClientMessage {
     NewOrder(NewOrderRequest),
     CancelOrder(CancelOrderRequest),
+    AmendOrder(AmendOrderRequest),
+    BlockTrade(BlockTradeRequest),
+    QueryPosition(PositionQuery),
+    EstimateFill(FillEstimateRequest),
+    QueryMarketData(MarketDataQuery),
+    Logon(LogonRequest),
+    // 心跳，表示客户端仍然在线
+    Heartbeat,
+    /// 带请求 ID 的消息，服务器对它的直接应答以 `ServerMessage::Response` 回显同一 ID，
+    /// 客户端可以在一个连接上流水线发送多个请求，不必依赖应答的先后顺序。
+    /// ID 由客户端分配，0 保留表示未设置，不支持嵌套
+    Request { request_id: u64, message: Box<ClientMessage> },
+    Resume(ResumeRequest),
+    SpreadOrder(SpreadOrderRequest),
+    BasketOrder(BasketOrderRequest),
+    OcoOrder(OcoOrderRequest),
+    TimedOrder(TimedOrderRequest),
+    /// 只减仓订单：可成交数量不超过用户在该合约上的反向持仓，下单时超出的部分被截去；
+    /// 挂单期间持仓减少时，交易所随之缩减剩余数量并发送状态为 Restated 的执行回报
+    ReduceOnlyOrder(NewOrderRequest),
 }
 
 /// 服务器发送给客户端的所有消息的顶层枚举
@@ -63,4 +499,26 @@ pub enum ClientMessage {
 pub enum ServerMessage {
     Trade(TradeNotification),
     Confirmation(OrderConfirmation),
-}
\ No newline at end of file
+    Reject(OrderReject),
+    Position(PositionReport),
+    TradingStatus(TradingStatus),
+    CancelAck(CancelAck),
+    MarketDataMode(MarketDataMode),
+    FillEstimate(FillEstimate),
+    ExecutionReport(ExecutionReport),
+    MarketData(MarketDataSnapshot),
+    Logon(LogonResponse),
+    // 服务器按会话配置的间隔发送的心跳
+    Heartbeat,
+    // 服务器正在关闭：此前的回报已全部发出，之后连接会被断开
+    Shutdown,
+    /// 对 `ClientMessage::Request` 的应答：确认、拒绝、撤单回报、查询结果，
+    /// 以及新订单或改单作为主动方产生的执行回报。成交通知等公共消息不带请求 ID
+    Response { request_id: u64, message: Box<ServerMessage> },
+    Resume(ResumeResponse),
+    /// 启用网关时发给用户的执行回报，seq 按用户从 1 开始连续分配，跨连接保持。
+    /// 重连补发期间实时回报可能重复到达，客户端忽略不大于已处理序号的回报
+    Sequenced { seq: u64, message: Box<ServerMessage> },
+    MarkPrice(MarkPrice),
+    ImpliedQuote(ImpliedQuote),
+}
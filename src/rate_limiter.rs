@@ -0,0 +1,67 @@
+use std::collections::HashMap;
+use std::time::Instant;
+
+// 限流配置：每秒补充的令牌数和桶容量（允许的突发量）
+#[derive(Debug, Clone, Copy)]
+pub struct RateLimitConfig {
+    pub orders_per_second: u32,
+    pub burst: u32,
+}
+
+// 单个用户的令牌桶
+struct TokenBucket {
+    tokens: f64,
+    last_refill: Instant,
+}
+
+// 按 user_id 划分的令牌桶限流器
+pub struct RateLimiter {
+    config: RateLimitConfig,
+    buckets: HashMap<u64, TokenBucket>,
+}
+
+impl RateLimiter {
+    pub fn new(config: RateLimitConfig) -> Self {
+        RateLimiter {
+            config,
+            buckets: HashMap::new(),
+        }
+    }
+
+    // 尝试为用户消耗一个令牌，返回 false 表示该请求应被限流
+    pub fn try_acquire(&mut self, user_id: u64) -> bool {
+        self.try_acquire_at(user_id, Instant::now())
+    }
+
+    // 与 try_acquire 相同，但由调用方提供当前时间，便于测试
+    pub fn try_acquire_at(&mut self, user_id: u64, now: Instant) -> bool {
+        self.try_acquire_many_at(user_id, 1, now)
+    }
+
+    // 一次消耗多个令牌（如 OCO 的两条腿），令牌不足时一个也不消耗
+    pub fn try_acquire_many(&mut self, user_id: u64, tokens: u32) -> bool {
+        self.try_acquire_many_at(user_id, tokens, Instant::now())
+    }
+
+    pub fn try_acquire_many_at(&mut self, user_id: u64, tokens: u32, now: Instant) -> bool {
+        let burst = self.config.burst as f64;
+        let rate = self.config.orders_per_second as f64;
+        let bucket = self.buckets.entry(user_id).or_insert(TokenBucket {
+            tokens: burst,
+            last_refill: now,
+        });
+
+        // 按流逝的时间补充令牌，但不超过桶容量
+        let elapsed = now.saturating_duration_since(bucket.last_refill).as_secs_f64();
+        bucket.tokens = (bucket.tokens + elapsed * rate).min(burst);
+        bucket.last_refill = now;
+
+        let tokens = tokens as f64;
+        if bucket.tokens >= tokens {
+            bucket.tokens -= tokens;
+            true
+        } else {
+            false
+        }
+    }
+}
@@ -0,0 +1,44 @@
+use std::collections::{HashMap, VecDeque};
+
+// 最近处理过的撤单集合，容量固定，超出时淘汰最早的记录。
+// 压力场景下客户端常常重复发送同一个撤单，命中该集合的撤单无需再访问订单簿。
+// 同时记录订单的所属用户，重复撤单也要先校验归属，不向其他用户透露订单已被撤销。
+// 订单号在所有合约间唯一，按订单号索引，查找时不必为合约名分配内存；合约名随记录保存，不一致时视为未命中
+pub struct RecentCancels {
+    capacity: usize,
+    order: VecDeque<u64>,
+    // 订单号 -> (合约, 所属用户)
+    owners: HashMap<u64, (String, u64)>,
+}
+
+impl RecentCancels {
+    pub fn new(capacity: usize) -> Self {
+        RecentCancels {
+            capacity,
+            order: VecDeque::with_capacity(capacity),
+            owners: HashMap::with_capacity(capacity),
+        }
+    }
+
+    pub fn contains(&self, symbol: &str, order_id: u64) -> bool {
+        self.owner(symbol, order_id).is_some()
+    }
+
+    // 最近撤销的订单的所属用户
+    pub fn owner(&self, symbol: &str, order_id: u64) -> Option<u64> {
+        self.owners.get(&order_id).filter(|(cancelled, _)| cancelled == symbol).map(|&(_, user_id)| user_id)
+    }
+
+    pub fn insert(&mut self, symbol: String, order_id: u64, user_id: u64) {
+        if self.capacity == 0 || self.owners.contains_key(&order_id) {
+            return;
+        }
+        if self.order.len() == self.capacity {
+            if let Some(oldest) = self.order.pop_front() {
+                self.owners.remove(&oldest);
+            }
+        }
+        self.owners.insert(order_id, (symbol, user_id));
+        self.order.push_back(order_id);
+    }
+}
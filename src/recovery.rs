@@ -0,0 +1,523 @@
+// 崩溃恢复：引擎在处理会改变订单簿的命令之前，先按连续的序号把命令追加到命令日志（WAL），
+// 检查点定期把各订单簿的挂单、持仓、交易阶段等状态和 ID 生成器状态写成快照。启动时加载最新的快照，
+// 重放快照之后的日志，并校验日志序号连续；发现缺口时拒绝启动，除非显式允许。
+// 订单号和成交号由引擎顺序分配，重放得到与崩溃前相同的订单号和成交号，已确认的订单不会丢失，
+// 崩溃前已发出的成交也不会以新的成交号重复出现
+use crate::circuit_breaker::PriceBand;
+use crate::engine::{ControlCommand, EngineCommand, MatchingEngine};
+use crate::feature_flags::Feature;
+use crate::protocol::{
+    AmendOrderRequest, BasketOrderRequest, BlockTradeRequest, CancelOrderRequest, MarkPrice, NewOrderRequest,
+    OcoOrderRequest, OrderType, SpreadOrderRequest, TimedOrderRequest, TradeNotification, TradingPhase,
+};
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, Read, Write};
+use std::path::{Path, PathBuf};
+use std::sync::mpsc as std_mpsc;
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
+use tokio::sync::mpsc::UnboundedSender;
+
+// 恢复目录中的命令日志文件名，快照文件名为 snapshot-<序号>.json
+pub const COMMAND_LOG_FILE: &str = "commands.ndjson";
+// 输出日志文件名，每条命令日志记录对应一行，记录该命令产生的成交
+pub const OUTPUT_LOG_FILE: &str = "outputs.ndjson";
+
+#[derive(Debug, Clone)]
+pub struct RecoveryConfig {
+    // 命令日志和快照所在的目录，不存在时创建
+    pub dir: PathBuf,
+    // 日志序号出现缺口时仍然启动，缺口记录在恢复报告中
+    pub allow_gaps: bool,
+    // 每条命令写入后调用 fsync。关闭时只写入内核缓冲区，能承受进程崩溃，但不能承受断电
+    pub sync: bool,
+    // 同时记录输出日志，供 verify 校验撮合的确定性
+    pub record_outputs: bool,
+}
+
+impl RecoveryConfig {
+    pub fn new(dir: impl Into<PathBuf>) -> Self {
+        RecoveryConfig { dir: dir.into(), allow_gaps: false, sync: false, record_outputs: false }
+    }
+}
+
+// 写入命令日志的命令：所有改变引擎状态的交易命令和管理命令。查询、深度快照、统计、检查点、
+// Drain 和监控规则不改变撮合状态，不记录。日终和回收合约名的回复通道不写入日志，重放时回复被丢弃
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum LoggedCommand {
+    NewOrder(NewOrderRequest),
+    CancelOrder(CancelOrderRequest),
+    AmendOrder(AmendOrderRequest),
+    CancelUserOrders(u64),
+    BlockTrade(BlockTradeRequest),
+    SpreadOrder(SpreadOrderRequest),
+    BasketOrder(BasketOrderRequest),
+    OcoOrder(OcoOrderRequest),
+    TimedOrder(TimedOrderRequest),
+    ReduceOnlyOrder(NewOrderRequest),
+    SetPriceBand(String, PriceBand),
+    Halt(String),
+    Resume(String),
+    StartAuction(String),
+    EndAuction(String),
+    UpdateMarkPrice { symbol: String, mark_price: u64, index_price: u64 },
+    Expire(String),
+    AdvanceTime(u64),
+    SetFeature { feature: Feature, symbol: Option<String>, enabled: bool },
+    EndOfDay,
+    ShrinkSymbolPool,
+}
+
+impl LoggedCommand {
+    // 需要写入日志的命令，其他命令返回 None
+    pub fn from_command(command: &EngineCommand) -> Option<Self> {
+        let logged = match command {
+            EngineCommand::NewOrder(request) => LoggedCommand::NewOrder(request.clone()),
+            EngineCommand::CancelOrder(request) => LoggedCommand::CancelOrder(request.clone()),
+            EngineCommand::AmendOrder(request) => LoggedCommand::AmendOrder(request.clone()),
+            EngineCommand::CancelUserOrders(user_id) => LoggedCommand::CancelUserOrders(*user_id),
+            EngineCommand::BlockTrade(request) => LoggedCommand::BlockTrade(request.clone()),
+            EngineCommand::SpreadOrder(request) => LoggedCommand::SpreadOrder(request.clone()),
+            EngineCommand::BasketOrder(request) => LoggedCommand::BasketOrder(request.clone()),
+            EngineCommand::OcoOrder(request) => LoggedCommand::OcoOrder(request.clone()),
+            EngineCommand::TimedOrder(request) => LoggedCommand::TimedOrder(request.clone()),
+            EngineCommand::ReduceOnlyOrder(request) => LoggedCommand::ReduceOnlyOrder(request.clone()),
+            EngineCommand::Control(control) => match control {
+                ControlCommand::SetPriceBand(symbol, band) => LoggedCommand::SetPriceBand(symbol.clone(), *band),
+                ControlCommand::Halt(symbol) => LoggedCommand::Halt(symbol.clone()),
+                ControlCommand::Resume(symbol) => LoggedCommand::Resume(symbol.clone()),
+                ControlCommand::StartAuction(symbol) => LoggedCommand::StartAuction(symbol.clone()),
+                ControlCommand::EndAuction(symbol) => LoggedCommand::EndAuction(symbol.clone()),
+                ControlCommand::UpdateMarkPrice { symbol, mark_price, index_price } => {
+                    LoggedCommand::UpdateMarkPrice { symbol: symbol.clone(), mark_price: *mark_price, index_price: *index_price }
+                }
+                ControlCommand::Expire(symbol) => LoggedCommand::Expire(symbol.clone()),
+                ControlCommand::AdvanceTime(now_ms) => LoggedCommand::AdvanceTime(*now_ms),
+                ControlCommand::SetFeature { feature, symbol, enabled } => {
+                    LoggedCommand::SetFeature { feature: *feature, symbol: symbol.clone(), enabled: *enabled }
+                }
+                ControlCommand::EndOfDay(_) => LoggedCommand::EndOfDay,
+                ControlCommand::ShrinkSymbolPool(_) => LoggedCommand::ShrinkSymbolPool,
+                ControlCommand::Drain
+                | ControlCommand::StatsRequest(_)
+                | ControlCommand::AddSurveillanceRule(_)
+                | ControlCommand::RemoveSurveillanceRule(_)
+                | ControlCommand::Checkpoint(_) => return None,
+            },
+            EngineCommand::QueryPosition(_)
+            | EngineCommand::EstimateFill(_)
+            | EngineCommand::QueryMarketData(_)
+            | EngineCommand::SnapshotDepth { .. }
+            | EngineCommand::Request { .. }
+            | EngineCommand::Direct { .. }
+            | EngineCommand::Received { .. }
+            | EngineCommand::Traced { .. } => return None,
+        };
+        Some(logged)
+    }
+
+    pub fn into_command(self) -> EngineCommand {
+        let control = match self {
+            LoggedCommand::NewOrder(request) => return EngineCommand::NewOrder(request),
+            LoggedCommand::CancelOrder(request) => return EngineCommand::CancelOrder(request),
+            LoggedCommand::AmendOrder(request) => return EngineCommand::AmendOrder(request),
+            LoggedCommand::CancelUserOrders(user_id) => return EngineCommand::CancelUserOrders(user_id),
+            LoggedCommand::BlockTrade(request) => return EngineCommand::BlockTrade(request),
+            LoggedCommand::SpreadOrder(request) => return EngineCommand::SpreadOrder(request),
+            LoggedCommand::BasketOrder(request) => return EngineCommand::BasketOrder(request),
+            LoggedCommand::OcoOrder(request) => return EngineCommand::OcoOrder(request),
+            LoggedCommand::TimedOrder(request) => return EngineCommand::TimedOrder(request),
+            LoggedCommand::ReduceOnlyOrder(request) => return EngineCommand::ReduceOnlyOrder(request),
+            LoggedCommand::SetPriceBand(symbol, band) => ControlCommand::SetPriceBand(symbol, band),
+            LoggedCommand::Halt(symbol) => ControlCommand::Halt(symbol),
+            LoggedCommand::Resume(symbol) => ControlCommand::Resume(symbol),
+            LoggedCommand::StartAuction(symbol) => ControlCommand::StartAuction(symbol),
+            LoggedCommand::EndAuction(symbol) => ControlCommand::EndAuction(symbol),
+            LoggedCommand::UpdateMarkPrice { symbol, mark_price, index_price } => {
+                ControlCommand::UpdateMarkPrice { symbol, mark_price, index_price }
+            }
+            LoggedCommand::Expire(symbol) => ControlCommand::Expire(symbol),
+            LoggedCommand::AdvanceTime(now_ms) => ControlCommand::AdvanceTime(now_ms),
+            LoggedCommand::SetFeature { feature, symbol, enabled } => ControlCommand::SetFeature { feature, symbol, enabled },
+            LoggedCommand::EndOfDay => ControlCommand::EndOfDay(std_mpsc::channel().0),
+            LoggedCommand::ShrinkSymbolPool => ControlCommand::ShrinkSymbolPool(std_mpsc::channel().0),
+        };
+        EngineCommand::Control(control)
+    }
+}
+
+// 日志中的一行，也是主备复制中传输的记录
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LogRecord {
+    pub seq: u64,
+    pub command: LoggedCommand,
+}
+
+// 追加写入的命令日志，只由引擎线程写入。每条记录在引擎处理命令、发出任何回报之前写入内核，
+// 因此客户端收到确认的命令一定在日志中
+#[derive(Debug)]
+pub struct CommandLog {
+    file: File,
+    last_seq: u64,
+    sync: bool,
+}
+
+impl CommandLog {
+    // 最后一条已写入记录的序号
+    pub fn last_seq(&self) -> u64 {
+        self.last_seq
+    }
+
+    // 追加一条命令，返回分配的序号。整行一次写入，崩溃时最多留下一行不完整的记录，恢复时被截去
+    pub fn append(&mut self, command: LoggedCommand) -> io::Result<u64> {
+        let seq = self.last_seq + 1;
+        append_line(&mut self.file, &LogRecord { seq, command }, self.sync)?;
+        self.last_seq = seq;
+        Ok(seq)
+    }
+}
+
+// 把一条记录作为一行 JSON 追加到日志文件，sync 时 fsync 后才返回。命令日志、输出日志和集群的 Raft 日志共用
+pub(crate) fn append_line<T: Serialize>(file: &mut File, record: &T, sync: bool) -> io::Result<()> {
+    let mut line = serde_json::to_vec(record).map_err(io::Error::from)?;
+    line.push(b'\n');
+    file.write_all(&line)?;
+    if sync {
+        file.sync_data()?;
+    }
+    Ok(())
+}
+
+// 输出日志中的一行：命令日志中序号为 seq 的命令处理完后产生的全部成交，没有成交时为空
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OutputRecord {
+    pub seq: u64,
+    pub trades: Vec<TradeNotification>,
+}
+
+// 追加写入的输出日志，只由引擎线程在命令处理完后写入。
+// 命令已写入命令日志但输出还没写入时崩溃，恢复后该命令没有输出记录
+#[derive(Debug)]
+pub struct OutputLog {
+    file: File,
+    sync: bool,
+}
+
+impl OutputLog {
+    pub fn append(&mut self, record: &OutputRecord) -> io::Result<()> {
+        append_line(&mut self.file, record, self.sync)
+    }
+}
+
+// 恢复快照：某个日志序号时刻的挂单、持仓、订单簿之外的撮合状态和 ID 生成器状态
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Snapshot {
+    // 快照已包含的最后一条日志记录的序号
+    pub seq: u64,
+    // 订单号和成交号生成器的状态，恢复后新分配的 ID 不会与快照之前的重复
+    pub id_state: u64,
+    pub trade_id_state: u64,
+    // 全部挂单，按合约和订单号排列
+    pub orders: Vec<SnapshotOrder>,
+    pub positions: Vec<SnapshotPosition>,
+    // 以下是订单簿之外、日志中的命令会改变的状态；旧版本的快照中没有这些字段
+    #[serde(default)]
+    pub markets: Vec<SnapshotMarket>,
+    #[serde(default)]
+    pub price_bands: Vec<(String, PriceBand)>,
+    #[serde(default)]
+    pub mark_prices: Vec<MarkPrice>,
+    #[serde(default)]
+    pub expired: Vec<String>,
+    // 引擎时钟（Unix 毫秒）和仍在订单簿中的 GTD 订单
+    #[serde(default)]
+    pub clock_ms: u64,
+    #[serde(default)]
+    pub good_till_date: Vec<SnapshotTimer>,
+    #[serde(default)]
+    pub oco_links: Vec<SnapshotOcoLink>,
+    #[serde(default)]
+    pub reduce_only: Vec<SnapshotReduceOnly>,
+    // 显式设置过的功能开关 (合约, 开关, 状态)，全局设置的合约为 None
+    #[serde(default)]
+    pub feature_flags: Vec<(Option<String>, Feature, bool)>,
+    // 上次日终之后的成交，日终时取走结算
+    #[serde(default)]
+    pub trade_log: Vec<TradeNotification>,
+}
+
+// 合约的交易阶段和最新成交价（大宗交易校验和集合竞价的参考价）
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SnapshotMarket {
+    pub symbol: String,
+    pub phase: TradingPhase,
+    pub last_trade_price: Option<u64>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SnapshotTimer {
+    pub expire_at_ms: u64,
+    pub symbol: String,
+    pub order_id: u64,
+}
+
+// OCO 关联，sibling 是另一条仍在订单簿中的订单 (合约, 订单号)
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SnapshotOcoLink {
+    pub order_id: u64,
+    pub link_id: u64,
+    pub sibling: Option<(String, u64)>,
+}
+
+// 某个用户在某个合约上的只减仓挂单，按下单先后排列
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SnapshotReduceOnly {
+    pub user_id: u64,
+    pub symbol: String,
+    pub order_ids: Vec<u64>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SnapshotOrder {
+    pub symbol: String,
+    pub order_id: u64,
+    pub user_id: u64,
+    pub order_type: OrderType,
+    pub price: u64,
+    // 剩余数量和累计已成交数量
+    pub quantity: u64,
+    pub filled_quantity: u64,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SnapshotPosition {
+    pub user_id: u64,
+    pub symbol: String,
+    pub position: i64,
+}
+
+// 恢复结果
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct RecoveryReport {
+    // 加载的快照的序号，没有快照时从空引擎开始重放
+    pub snapshot_seq: Option<u64>,
+    // 重放的日志记录数
+    pub replayed: usize,
+    // 恢复后最后一条日志记录的序号，新命令从下一个序号开始
+    pub last_seq: u64,
+    // 允许缺口时跳过的序号区间 (期望的序号, 实际读到的序号)
+    pub gaps: Vec<(u64, u64)>,
+    // 崩溃时写了一半、被截去的日志尾部字节数
+    pub truncated_bytes: u64,
+}
+
+#[derive(Debug)]
+pub enum RecoveryError {
+    Io(io::Error),
+    // 日志或快照中无法解析的记录，line 从 1 开始，快照文件为 0
+    Corrupt { path: PathBuf, line: usize, error: String },
+    // 日志序号不连续：期望 expected，实际读到 found
+    Gap { expected: u64, found: u64 },
+}
+
+impl fmt::Display for RecoveryError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RecoveryError::Io(e) => write!(f, "读取恢复数据失败: {}", e),
+            RecoveryError::Corrupt { path, line, error } => {
+                write!(f, "{} 第 {} 行无法解析: {}", path.display(), line, error)
+            }
+            RecoveryError::Gap { expected, found } => {
+                write!(f, "命令日志序号不连续：期望 {}，读到 {}", expected, found)
+            }
+        }
+    }
+}
+
+impl std::error::Error for RecoveryError {}
+
+impl From<io::Error> for RecoveryError {
+    fn from(error: io::Error) -> Self {
+        RecoveryError::Io(error)
+    }
+}
+
+// 启动时恢复引擎：加载最新的快照，重放之后的日志记录，再为引擎接上命令日志，之后的命令从下一个序号续写。
+// 重放产生的回报照常写入引擎的输出通道，调用方在启动网络层之前丢弃，不会重复发给客户端
+pub fn recover(
+    config: &RecoveryConfig,
+    mut engine: MatchingEngine,
+) -> Result<(MatchingEngine, RecoveryReport), RecoveryError> {
+    fs::create_dir_all(&config.dir)?;
+    let mut report = RecoveryReport::default();
+    if let Some(snapshot) = latest_snapshot(&config.dir)? {
+        engine.restore(&snapshot);
+        report.snapshot_seq = Some(snapshot.seq);
+        report.last_seq = snapshot.seq;
+    }
+
+    let path = config.dir.join(COMMAND_LOG_FILE);
+    let (records, complete_len, file_len) = read_log::<LogRecord>(&path)?;
+    let mut previous = None;
+    for record in records {
+        // 日志本身的序号必须严格递增，即使记录已包含在快照中
+        if let Some(previous) = previous {
+            if record.seq <= previous {
+                return Err(RecoveryError::Gap { expected: previous + 1, found: record.seq });
+            }
+        }
+        previous = Some(record.seq);
+        if record.seq <= report.last_seq {
+            continue;
+        }
+        let expected = report.last_seq + 1;
+        if record.seq != expected {
+            if !config.allow_gaps {
+                return Err(RecoveryError::Gap { expected, found: record.seq });
+            }
+            report.gaps.push((expected, record.seq));
+        }
+        engine.apply_logged(record.command);
+        report.replayed += 1;
+        report.last_seq = record.seq;
+    }
+
+    // 截去不完整的最后一行，续写的记录才不会接在半行之后
+    if complete_len < file_len {
+        OpenOptions::new().write(true).open(&path)?.set_len(complete_len)?;
+        report.truncated_bytes = file_len - complete_len;
+    }
+    let file = OpenOptions::new().create(true).append(true).open(&path)?;
+    let log = CommandLog { file, last_seq: report.last_seq, sync: config.sync };
+    let mut engine = engine.with_command_log(log);
+    // 重放完成后才接上输出日志，重放的命令不会重复记录
+    if config.record_outputs {
+        let path = config.dir.join(OUTPUT_LOG_FILE);
+        let (_, complete_len, file_len) = read_log::<OutputRecord>(&path)?;
+        if complete_len < file_len {
+            OpenOptions::new().write(true).open(&path)?.set_len(complete_len)?;
+        }
+        let file = OpenOptions::new().create(true).append(true).open(&path)?;
+        engine = engine.with_output_log(OutputLog { file, sync: config.sync });
+    }
+    Ok((engine, report))
+}
+
+// 读取恢复目录中全部完整的命令日志记录
+pub fn read_command_log(dir: &Path) -> Result<Vec<LogRecord>, RecoveryError> {
+    read_log(&dir.join(COMMAND_LOG_FILE)).map(|(records, _, _)| records)
+}
+
+// 读取恢复目录中全部完整的输出日志记录
+pub fn read_output_log(dir: &Path) -> Result<Vec<OutputRecord>, RecoveryError> {
+    read_log(&dir.join(OUTPUT_LOG_FILE)).map(|(records, _, _)| records)
+}
+
+// 读取全部完整的日志记录，返回 (记录, 完整记录的总字节数, 文件字节数)。
+// 没有换行结尾的最后一行是崩溃时写了一半的记录，对应的命令还没有被处理，直接忽略
+pub(crate) fn read_log<T: DeserializeOwned>(path: &Path) -> Result<(Vec<T>, u64, u64), RecoveryError> {
+    let mut content = Vec::new();
+    match File::open(path) {
+        Ok(mut file) => {
+            file.read_to_end(&mut content)?;
+        }
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok((Vec::new(), 0, 0)),
+        Err(e) => return Err(e.into()),
+    }
+    let complete_len = content.iter().rposition(|&byte| byte == b'\n').map_or(0, |index| index + 1);
+    let mut records = Vec::new();
+    for (index, line) in content[..complete_len].split(|&byte| byte == b'\n').enumerate() {
+        if line.iter().all(u8::is_ascii_whitespace) {
+            continue;
+        }
+        let record = serde_json::from_slice(line).map_err(|e| RecoveryError::Corrupt {
+            path: path.to_path_buf(),
+            line: index + 1,
+            error: e.to_string(),
+        })?;
+        records.push(record);
+    }
+    Ok((records, complete_len as u64, content.len() as u64))
+}
+
+// 写入快照：先写临时文件并 fsync，再原子地改名，崩溃时不会留下不完整的快照
+pub fn write_snapshot(dir: &Path, snapshot: &Snapshot) -> io::Result<PathBuf> {
+    let path = dir.join(format!("snapshot-{:020}.json", snapshot.seq));
+    write_json_atomic(&path, snapshot)?;
+    Ok(path)
+}
+
+// 先写临时文件并 fsync，再改名为 path
+pub(crate) fn write_json_atomic<T: Serialize>(path: &Path, value: &T) -> io::Result<()> {
+    let temporary = path.with_extension("json.tmp");
+    let mut file = File::create(&temporary)?;
+    serde_json::to_writer(&mut file, value).map_err(io::Error::from)?;
+    file.sync_all()?;
+    fs::rename(&temporary, path)
+}
+
+// 目录中序号最大的快照
+pub fn latest_snapshot(dir: &Path) -> Result<Option<Snapshot>, RecoveryError> {
+    let mut latest: Option<(u64, PathBuf)> = None;
+    for entry in fs::read_dir(dir)? {
+        let path = entry?.path();
+        let seq = path
+            .file_name()
+            .and_then(|name| name.to_str())
+            .and_then(|name| name.strip_prefix("snapshot-")?.strip_suffix(".json")?.parse::<u64>().ok());
+        if let Some(seq) = seq {
+            if latest.as_ref().is_none_or(|(latest, _)| seq > *latest) {
+                latest = Some((seq, path));
+            }
+        }
+    }
+    let Some((_, path)) = latest else {
+        return Ok(None);
+    };
+    read_json(&path)
+}
+
+// 读取一个 JSON 文件，文件不存在时返回 None
+pub(crate) fn read_json<T: DeserializeOwned>(path: &Path) -> Result<Option<T>, RecoveryError> {
+    let content = match fs::read(path) {
+        Ok(content) => content,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(None),
+        Err(e) => return Err(e.into()),
+    };
+    let value = serde_json::from_slice(&content)
+        .map_err(|e| RecoveryError::Corrupt { path: path.to_path_buf(), line: 0, error: e.to_string() })?;
+    Ok(Some(value))
+}
+
+// 请求引擎生成一份快照并写入目录，返回快照的序号；引擎已关闭时返回 None
+pub fn checkpoint_once(command_sender: &UnboundedSender<EngineCommand>, dir: &Path) -> io::Result<Option<u64>> {
+    let (reply, snapshot) = std_mpsc::channel();
+    if command_sender.send(EngineCommand::Control(ControlCommand::Checkpoint(reply))).is_err() {
+        return Ok(None);
+    }
+    let Ok(snapshot) = snapshot.recv() else {
+        return Ok(None);
+    };
+    write_snapshot(dir, &snapshot)?;
+    Ok(Some(snapshot.seq))
+}
+
+// 启动后台线程按固定间隔写检查点，快照在引擎线程上生成、在后台线程上写入磁盘
+pub fn spawn_checkpointer(
+    command_sender: UnboundedSender<EngineCommand>,
+    dir: PathBuf,
+    interval: Duration,
+) -> JoinHandle<()> {
+    thread::spawn(move || loop {
+        thread::sleep(interval);
+        match checkpoint_once(&command_sender, &dir) {
+            Ok(Some(_)) => {}
+            // 引擎已关闭
+            Ok(None) => break,
+            Err(e) => eprintln!("写入恢复快照失败: {}", e),
+        }
+    })
+}
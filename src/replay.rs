@@ -0,0 +1,266 @@
+use crate::engine::{ControlCommand, EngineCommand, EngineOutput, MatchingEngine};
+use crate::protocol::{AmendOrderRequest, CancelOrderRequest, NewOrderRequest, OrderType};
+use std::fmt::Write as _;
+use std::time::{Duration, Instant};
+use tokio::sync::mpsc;
+
+// 记录文件中的一笔成交
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RecordedTrade {
+    pub symbol: String,
+    pub buyer_order_id: u64,
+    pub seller_order_id: u64,
+    pub price: u64,
+    pub quantity: u64,
+}
+
+// 记录文件中的一条事件
+#[derive(Debug, Clone)]
+pub enum JournalEvent {
+    Order(NewOrderRequest),
+    Cancel(CancelOrderRequest),
+    Amend(AmendOrderRequest),
+    // 当时引擎输出的成交，回放时用于校验
+    Trade(RecordedTrade),
+}
+
+#[derive(Debug, Clone)]
+pub struct JournalEntry {
+    // 事件发生的时间（纳秒），只用于按原始节奏回放，起点任意
+    pub timestamp: u64,
+    pub event: JournalEvent,
+}
+
+// 行情记录文件，类似 ITCH 每种消息有自己的字段，逗号分隔、每行一条、`#` 开头为注释：
+//   <ts>,order,<symbol>,<user>,<buy|sell>,<price>,<quantity>
+//   <ts>,cancel,<symbol>,<user>,<order_id>
+//   <ts>,amend,<symbol>,<user>,<order_id>,<new_price>,<new_quantity>
+//   <ts>,trade,<symbol>,<buyer_order_id>,<seller_order_id>,<price>,<quantity>
+// 订单号由引擎顺序分配，记录必须从空引擎开始，回放时才会得到相同的订单号
+#[derive(Debug, Clone, Default)]
+pub struct Journal {
+    pub entries: Vec<JournalEntry>,
+}
+
+impl Journal {
+    pub fn parse(content: &str) -> Result<Self, String> {
+        let mut entries = Vec::new();
+        for (index, raw) in content.lines().enumerate() {
+            let line = raw.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let entry = parse_entry(line).map_err(|e| format!("第 {} 行: {}", index + 1, e))?;
+            entries.push(entry);
+        }
+        Ok(Journal { entries })
+    }
+
+    pub fn to_csv(&self) -> String {
+        let mut output = String::new();
+        for entry in &self.entries {
+            let ts = entry.timestamp;
+            let _ = match &entry.event {
+                JournalEvent::Order(request) => writeln!(
+                    output,
+                    "{},order,{},{},{},{},{}",
+                    ts,
+                    request.symbol,
+                    request.user_id,
+                    match request.order_type {
+                        OrderType::Buy => "buy",
+                        OrderType::Sell => "sell",
+                    },
+                    request.price,
+                    request.quantity
+                ),
+                JournalEvent::Cancel(request) => {
+                    writeln!(output, "{},cancel,{},{},{}", ts, request.symbol, request.user_id, request.order_id)
+                }
+                JournalEvent::Amend(request) => writeln!(
+                    output,
+                    "{},amend,{},{},{},{},{}",
+                    ts, request.symbol, request.user_id, request.order_id, request.new_price, request.new_quantity
+                ),
+                JournalEvent::Trade(trade) => writeln!(
+                    output,
+                    "{},trade,{},{},{},{},{}",
+                    ts, trade.symbol, trade.buyer_order_id, trade.seller_order_id, trade.price, trade.quantity
+                ),
+            };
+        }
+        output
+    }
+
+    // 记录中的成交
+    pub fn trades(&self) -> Vec<RecordedTrade> {
+        self.entries
+            .iter()
+            .filter_map(|entry| match &entry.event {
+                JournalEvent::Trade(trade) => Some(trade.clone()),
+                _ => None,
+            })
+            .collect()
+    }
+}
+
+fn parse_entry(line: &str) -> Result<JournalEntry, String> {
+    let fields: Vec<&str> = line.split(',').map(str::trim).collect();
+    let number = |index: usize| -> Result<u64, String> {
+        let field = fields.get(index).ok_or_else(|| format!("缺少第 {} 列", index + 1))?;
+        field.parse().map_err(|_| format!("无效的数字: {}", field))
+    };
+    let expect_columns = |count: usize| -> Result<(), String> {
+        if fields.len() == count {
+            Ok(())
+        } else {
+            Err(format!("{} 应有 {} 列", fields[1], count))
+        }
+    };
+    if fields.len() < 3 {
+        return Err("列数不足".to_string());
+    }
+    let timestamp = number(0)?;
+    let symbol = fields[2].to_string();
+    let event = match fields[1] {
+        "order" => {
+            expect_columns(7)?;
+            let order_type = match fields[4] {
+                "buy" => OrderType::Buy,
+                "sell" => OrderType::Sell,
+                other => return Err(format!("无效的方向: {}", other)),
+            };
+            JournalEvent::Order(NewOrderRequest {
+                user_id: number(3)?,
+                symbol,
+                order_type,
+                price: number(5)?,
+                quantity: number(6)?,
+            })
+        }
+        "cancel" => {
+            expect_columns(5)?;
+            JournalEvent::Cancel(CancelOrderRequest { user_id: number(3)?, symbol, order_id: number(4)? })
+        }
+        "amend" => {
+            expect_columns(7)?;
+            JournalEvent::Amend(AmendOrderRequest {
+                user_id: number(3)?,
+                symbol,
+                order_id: number(4)?,
+                new_price: number(5)?,
+                new_quantity: number(6)?,
+            })
+        }
+        "trade" => {
+            expect_columns(7)?;
+            JournalEvent::Trade(RecordedTrade {
+                symbol,
+                buyer_order_id: number(3)?,
+                seller_order_id: number(4)?,
+                price: number(5)?,
+                quantity: number(6)?,
+            })
+        }
+        other => return Err(format!("未知的事件类型: {}", other)),
+    };
+    Ok(JournalEntry { timestamp, event })
+}
+
+// 回放速度：1 为原始节奏，10 为十倍速，0 为不等待、尽快回放
+#[derive(Debug, Clone, Copy)]
+pub struct ReplayConfig {
+    pub speed: f64,
+}
+
+impl Default for ReplayConfig {
+    fn default() -> Self {
+        ReplayConfig { speed: 0.0 }
+    }
+}
+
+// 回放结果
+#[derive(Debug, Clone)]
+pub struct ReplayReport {
+    // 送入引擎的命令数
+    pub commands: usize,
+    // 回放中引擎产生的成交
+    pub trades: Vec<RecordedTrade>,
+    // 与记录不一致的成交说明，为空表示完全一致
+    pub mismatches: Vec<String>,
+    // 从第一条命令到引擎处理完全部命令的耗时
+    pub elapsed: Duration,
+}
+
+impl ReplayReport {
+    pub fn is_consistent(&self) -> bool {
+        self.mismatches.is_empty()
+    }
+
+    pub fn commands_per_sec(&self) -> f64 {
+        self.commands as f64 / self.elapsed.as_secs_f64().max(f64::EPSILON)
+    }
+}
+
+// 把记录送入一个新的撮合引擎，并将引擎产生的成交与记录逐笔比对
+pub fn replay(journal: &Journal, config: ReplayConfig) -> ReplayReport {
+    let (command_sender, command_receiver) = mpsc::unbounded_channel();
+    let (output_sender, mut output_receiver) = mpsc::unbounded_channel();
+    let engine_thread = std::thread::spawn(move || {
+        MatchingEngine::new(command_receiver, output_sender).run();
+    });
+
+    let origin = journal.entries.first().map_or(0, |entry| entry.timestamp);
+    let start = Instant::now();
+    let mut commands = 0;
+    for entry in &journal.entries {
+        let command = match &entry.event {
+            JournalEvent::Order(request) => EngineCommand::NewOrder(request.clone()),
+            JournalEvent::Cancel(request) => EngineCommand::CancelOrder(request.clone()),
+            JournalEvent::Amend(request) => EngineCommand::AmendOrder(request.clone()),
+            JournalEvent::Trade(_) => continue,
+        };
+        if config.speed > 0.0 {
+            let offset = entry.timestamp.saturating_sub(origin) as f64 / config.speed;
+            let due = start + Duration::from_nanos(offset as u64);
+            let now = Instant::now();
+            if due > now {
+                std::thread::sleep(due - now);
+            }
+        }
+        command_sender.send(command).expect("撮合引擎已退出");
+        commands += 1;
+    }
+    command_sender.send(EngineCommand::Control(ControlCommand::Drain)).expect("撮合引擎已退出");
+    engine_thread.join().expect("撮合引擎线程崩溃");
+    let elapsed = start.elapsed();
+
+    let mut trades = Vec::new();
+    while let Ok(output) = output_receiver.try_recv() {
+        if let EngineOutput::Trade(trade) = output {
+            trades.push(RecordedTrade {
+                symbol: trade.symbol.to_string(),
+                buyer_order_id: trade.buyer_order_id,
+                seller_order_id: trade.seller_order_id,
+                price: trade.matched_price,
+                quantity: trade.matched_quantity,
+            });
+        }
+    }
+    let mismatches = compare_trades(&journal.trades(), &trades);
+    ReplayReport { commands, trades, mismatches, elapsed }
+}
+
+// 按顺序逐笔比对成交
+fn compare_trades(recorded: &[RecordedTrade], replayed: &[RecordedTrade]) -> Vec<String> {
+    let mut mismatches = Vec::new();
+    for (index, (expected, actual)) in recorded.iter().zip(replayed).enumerate() {
+        if expected != actual {
+            mismatches.push(format!("第 {} 笔成交不一致: 记录 {:?}，回放 {:?}", index + 1, expected, actual));
+        }
+    }
+    if recorded.len() != replayed.len() {
+        mismatches.push(format!("成交笔数不一致: 记录 {}，回放 {}", recorded.len(), replayed.len()));
+    }
+    mismatches
+}
@@ -0,0 +1,150 @@
+// 只读副本订单簿：消费引擎通过 with_l3_feed 发出的逐笔（L3）事件，在撮合线程之外为每个合约维护一份 OrderBook 副本，
+// 行情服务、分析和查询接口读取副本而不接触撮合中的订单簿。
+// 引擎每隔若干条命令发出各合约订单簿的校验和，副本按同样的算法核对；消息序号连续，缺失的消息同样报告为不一致
+use crate::orderbook::{self, BookEvent, OrderBook};
+use crate::protocol::{DepthLevel, DepthSnapshot, NewOrderRequest};
+use parking_lot::RwLock;
+use std::collections::HashMap;
+use std::sync::mpsc::Receiver;
+use std::sync::Arc;
+use std::thread::{self, JoinHandle};
+
+const BATCH: usize = 1_024;
+
+// L3 行情流中的一条消息，seq 从 1 开始连续编号
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum L3Message {
+    Event { seq: u64, symbol: String, event: BookEvent },
+    Checksum { seq: u64, symbol: String, checksum: u64 },
+}
+
+impl L3Message {
+    pub fn seq(&self) -> u64 {
+        match self {
+            L3Message::Event { seq, .. } | L3Message::Checksum { seq, .. } => *seq,
+        }
+    }
+}
+
+#[derive(Default)]
+pub struct ReplicaBooks {
+    books: HashMap<String, OrderBook>,
+    last_seq: u64,
+    checksums_verified: u64,
+    mismatches: Vec<String>,
+}
+
+impl ReplicaBooks {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn apply(&mut self, message: L3Message) {
+        let seq = message.seq();
+        if seq != self.last_seq + 1 {
+            self.mismatches.push(format!("消息序号不连续: 期望 {}，收到 {}", self.last_seq + 1, seq));
+        }
+        self.last_seq = seq;
+        match message {
+            L3Message::Event { symbol, event, .. } => {
+                if let Err(e) = self.apply_event(&symbol, event) {
+                    self.mismatches.push(format!("#{} {}: {}", seq, symbol, e));
+                }
+            }
+            L3Message::Checksum { symbol, checksum, .. } => {
+                // 还没有挂单事件的合约按空订单簿核对
+                let replica = self.books.entry(symbol.clone()).or_default().checksum();
+                if replica == checksum {
+                    self.checksums_verified += 1;
+                } else {
+                    self.mismatches.push(format!("#{} {}: 校验和不一致，引擎 {:016x}，副本 {:016x}", seq, symbol, checksum, replica));
+                }
+            }
+        }
+    }
+
+    fn apply_event(&mut self, symbol: &str, event: BookEvent) -> Result<(), String> {
+        let book = self.books.entry(symbol.to_string()).or_default();
+        match event {
+            BookEvent::Added { order_id, user_id, order_type, price, quantity } => {
+                if book.order(order_id).is_some() {
+                    return Err(format!("订单 {} 已在副本中", order_id));
+                }
+                let request = NewOrderRequest { user_id, symbol: symbol.to_string(), order_type, price, quantity };
+                book.restore_order(request, order_id, 0);
+            }
+            BookEvent::Executed { order_id, quantity } => {
+                let leaves = book.order(order_id).map(|node| node.quantity).ok_or_else(|| format!("成交的订单 {} 不在副本中", order_id))?;
+                if quantity >= leaves {
+                    if quantity > leaves {
+                        return Err(format!("订单 {} 成交 {} 超过剩余数量 {}", order_id, quantity, leaves));
+                    }
+                    book.cancel_order(order_id).map_err(|e| e.to_string())?;
+                } else {
+                    book.reduce_order(order_id, leaves - quantity).map_err(|e| e.to_string())?;
+                }
+            }
+            BookEvent::Reduced { order_id, quantity } => book.reduce_order(order_id, quantity).map_err(|e| e.to_string())?,
+            BookEvent::Removed { order_id } => {
+                book.cancel_order(order_id).map_err(|e| e.to_string())?;
+            }
+        }
+        Ok(())
+    }
+
+    pub fn book(&self, symbol: &str) -> Option<&OrderBook> {
+        self.books.get(symbol)
+    }
+
+    pub fn depth(&self, symbol: &str, n: usize) -> Option<(Vec<DepthLevel>, Vec<DepthLevel>)> {
+        self.books.get(symbol).map(|book| book.depth(n))
+    }
+
+    // 用深度消息的校验和核对副本：副本前同样档数的深度算出的校验和与消息一致。
+    // 消息和副本需处于同一状态，例如引擎在副本追上之后发出的深度
+    pub fn verify_depth(&self, snapshot: &DepthSnapshot) -> bool {
+        let depth = snapshot.bids.len().max(snapshot.asks.len());
+        let (bids, asks) = self.depth(&snapshot.symbol, depth).unwrap_or_default();
+        orderbook::depth_checksum(&bids, &asks) == snapshot.checksum
+    }
+
+    pub fn symbols(&self) -> Vec<String> {
+        let mut symbols: Vec<String> = self.books.keys().cloned().collect();
+        symbols.sort();
+        symbols
+    }
+
+    pub fn last_seq(&self) -> u64 {
+        self.last_seq
+    }
+
+    pub fn checksums_verified(&self) -> u64 {
+        self.checksums_verified
+    }
+
+    // 发现的全部不一致，按发现顺序
+    pub fn mismatches(&self) -> &[String] {
+        &self.mismatches
+    }
+}
+
+// 启动副本线程，返回供读取方共享的副本；引擎退出（发送端关闭）后线程退出。
+// 读取方持有读锁期间副本暂停应用事件，事件在通道中排队，不影响撮合
+pub fn spawn_replica(receiver: Receiver<L3Message>) -> (Arc<RwLock<ReplicaBooks>>, JoinHandle<()>) {
+    let replica = Arc::new(RwLock::new(ReplicaBooks::new()));
+    let books = replica.clone();
+    let handle = thread::spawn(move || {
+        while let Ok(message) = receiver.recv() {
+            let mut books = books.write();
+            let reported = books.mismatches.len();
+            // 一次写锁内最多应用 BATCH 条已到达的消息，读取方不会被持续的事件流挡住
+            for message in std::iter::once(message).chain(receiver.try_iter().take(BATCH - 1)) {
+                books.apply(message);
+            }
+            for mismatch in &books.mismatches[reported..] {
+                tracing::warn!(mismatch = %mismatch, "副本订单簿与引擎不一致");
+            }
+        }
+    });
+    (replica, handle)
+}
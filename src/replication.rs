@@ -0,0 +1,227 @@
+// 主备复制：主机把命令日志（见 recovery）按序号实时发送给热备机，备机用同样确定性的撮合逻辑重放，
+// 订单簿、订单号和成交号与主机保持一致，并写入自己的命令日志。主机空闲时定期发送心跳，
+// 备机连续错过若干个心跳（主机崩溃或网络断开）后提升为主机，从已应用的最后一条记录继续服务。
+// 复制是异步的：主机处理完、尚未发出的记录在切换时会丢失；也没有隔离旧主机的机制，需要由部署保证
+use crate::engine::{EngineOutput, MatchingEngine};
+use crate::recovery::{self, LogRecord, RecoveryConfig, RecoveryError, RecoveryReport, COMMAND_LOG_FILE};
+use serde::{Deserialize, Serialize};
+use std::fs::File;
+use std::io::{self, BufRead, BufReader, BufWriter, Write};
+use std::net::{SocketAddr, TcpListener, TcpStream};
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc as std_mpsc;
+use std::sync::Arc;
+use std::thread;
+use std::time::{Duration, Instant};
+use tokio::sync::mpsc::UnboundedReceiver;
+
+// 复制连接上的消息，每行一个 JSON
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum ReplicationMessage {
+    // 备机连接后发送：请求序号大于 after 的记录
+    Subscribe { after: u64 },
+    Record(LogRecord),
+    // 主机空闲时发送，last_seq 为主机日志中最后一条记录的序号
+    Heartbeat { last_seq: u64 },
+}
+
+// 主机的复制服务配置
+#[derive(Debug, Clone)]
+pub struct ReplicationConfig {
+    // 主机的恢复目录，复制服务跟随其中的命令日志
+    pub dir: PathBuf,
+    pub heartbeat_interval: Duration,
+}
+
+// 主机的复制服务句柄
+pub struct ReplicationServer {
+    addr: SocketAddr,
+    stop: Arc<AtomicBool>,
+}
+
+impl ReplicationServer {
+    pub fn addr(&self) -> SocketAddr {
+        self.addr
+    }
+
+    // 停止发送记录和心跳并断开备机，备机随后提升为主机；用于计划内切换和测试
+    pub fn stop(&self) {
+        self.stop.store(true, Ordering::Relaxed);
+    }
+}
+
+// 启动主机的复制服务：每个备机连接一个线程，从备机请求的序号开始跟随命令日志发送记录
+pub fn spawn_replication_server(listener: TcpListener, config: ReplicationConfig) -> io::Result<ReplicationServer> {
+    let addr = listener.local_addr()?;
+    let stop = Arc::new(AtomicBool::new(false));
+    let server_stop = stop.clone();
+    thread::spawn(move || {
+        for stream in listener.incoming() {
+            if server_stop.load(Ordering::Relaxed) {
+                break;
+            }
+            let Ok(stream) = stream else {
+                continue;
+            };
+            let (config, stop) = (config.clone(), server_stop.clone());
+            thread::spawn(move || {
+                if let Err(e) = serve_standby(stream, &config, &stop) {
+                    eprintln!("复制连接断开: {}", e);
+                }
+            });
+        }
+    });
+    Ok(ReplicationServer { addr, stop })
+}
+
+fn serve_standby(stream: TcpStream, config: &ReplicationConfig, stop: &AtomicBool) -> io::Result<()> {
+    stream.set_nodelay(true)?;
+    let mut line = String::new();
+    BufReader::new(stream.try_clone()?).read_line(&mut line)?;
+    let after = match serde_json::from_str(&line) {
+        Ok(ReplicationMessage::Subscribe { after }) => after,
+        _ => return Err(io::Error::new(io::ErrorKind::InvalidData, "备机应先发送 Subscribe")),
+    };
+    let mut writer = BufWriter::new(stream);
+    let mut log = BufReader::new(File::open(config.dir.join(COMMAND_LOG_FILE))?);
+    let mut pending = String::new();
+    let mut last_seq = 0;
+    let mut last_sent = Instant::now();
+    while !stop.load(Ordering::Relaxed) {
+        // 日志只追加，读到不完整的一行时保留已读部分，等引擎写完
+        if log.read_line(&mut pending)? > 0 && pending.ends_with('\n') {
+            let record: LogRecord =
+                serde_json::from_str(&pending).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+            pending.clear();
+            last_seq = record.seq;
+            if record.seq > after {
+                send(&mut writer, &ReplicationMessage::Record(record))?;
+                last_sent = Instant::now();
+            }
+            continue;
+        }
+        if last_sent.elapsed() >= config.heartbeat_interval {
+            send(&mut writer, &ReplicationMessage::Heartbeat { last_seq })?;
+            last_sent = Instant::now();
+        }
+        writer.flush()?;
+        thread::sleep(Duration::from_millis(1));
+    }
+    Ok(())
+}
+
+fn send(writer: &mut impl Write, message: &ReplicationMessage) -> io::Result<()> {
+    serde_json::to_writer(&mut *writer, message).map_err(io::Error::from)?;
+    writer.write_all(b"\n")
+}
+
+// 备机配置
+#[derive(Debug, Clone)]
+pub struct StandbyConfig {
+    pub primary: SocketAddr,
+    // 备机自己的恢复目录，提升后继续在其中写日志
+    pub recovery: RecoveryConfig,
+    pub heartbeat_interval: Duration,
+    // 连续这么多个心跳间隔收不到主机的任何消息时提升为主机
+    pub missed_heartbeats: u32,
+}
+
+impl StandbyConfig {
+    pub fn new(primary: SocketAddr, dir: impl Into<PathBuf>) -> Self {
+        StandbyConfig {
+            primary,
+            recovery: RecoveryConfig::new(dir),
+            heartbeat_interval: Duration::from_millis(100),
+            missed_heartbeats: 5,
+        }
+    }
+
+    fn failover_timeout(&self) -> Duration {
+        self.heartbeat_interval * self.missed_heartbeats
+    }
+}
+
+// 备机提升时的状态
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct StandbyReport {
+    // 启动时从备机自己的目录恢复的结果
+    pub recovered: RecoveryReport,
+    // 从主机收到并应用的记录数
+    pub applied: u64,
+    // 提升时已应用的最后一条记录的序号
+    pub last_seq: u64,
+    // 最后一次心跳中主机报告的序号；大于 last_seq 说明切换时有记录没有复制过来
+    pub primary_last_seq: u64,
+}
+
+// 以备机身份运行，阻塞到提升为主机：先从自己的目录恢复，再连接主机补齐并跟随之后的记录。
+// 重放产生的回报从 outputs 中丢弃；返回的引擎已接上备机的命令日志，可以直接对外服务
+pub fn run_standby(
+    config: &StandbyConfig,
+    engine: MatchingEngine,
+    outputs: &mut UnboundedReceiver<EngineOutput>,
+) -> Result<(MatchingEngine, StandbyReport), RecoveryError> {
+    let (mut engine, recovered) = recovery::recover(&config.recovery, engine)?;
+    while outputs.try_recv().is_ok() {}
+    let mut report = StandbyReport { last_seq: recovered.last_seq, recovered, ..StandbyReport::default() };
+    let mut connection: Option<std_mpsc::Receiver<ReplicationMessage>> = None;
+    let mut last_contact = Instant::now();
+    while last_contact.elapsed() < config.failover_timeout() {
+        let Some(messages) = &connection else {
+            connection = subscribe(config, report.last_seq).ok();
+            if connection.is_none() {
+                thread::sleep(config.heartbeat_interval);
+            }
+            continue;
+        };
+        match messages.recv_timeout(config.heartbeat_interval) {
+            Ok(ReplicationMessage::Record(record)) => {
+                last_contact = Instant::now();
+                if record.seq <= report.last_seq {
+                    continue;
+                }
+                if record.seq != report.last_seq + 1 {
+                    // 备机不能跳过记录，断开后从已应用的序号重新订阅
+                    eprintln!("复制记录不连续：期望 {}，收到 {}", report.last_seq + 1, record.seq);
+                    connection = None;
+                    continue;
+                }
+                // 主机已按限流放行，备机原样应用
+                engine.apply_logged(record.command);
+                while outputs.try_recv().is_ok() {}
+                report.applied += 1;
+                report.last_seq = record.seq;
+            }
+            Ok(ReplicationMessage::Heartbeat { last_seq }) => {
+                last_contact = Instant::now();
+                report.primary_last_seq = last_seq;
+            }
+            Ok(ReplicationMessage::Subscribe { .. }) | Err(std_mpsc::RecvTimeoutError::Timeout) => {}
+            Err(std_mpsc::RecvTimeoutError::Disconnected) => connection = None,
+        }
+    }
+    Ok((engine, report))
+}
+
+// 连接主机并订阅 after 之后的记录，由后台线程逐行读取
+fn subscribe(config: &StandbyConfig, after: u64) -> io::Result<std_mpsc::Receiver<ReplicationMessage>> {
+    let mut stream = TcpStream::connect_timeout(&config.primary, config.heartbeat_interval)?;
+    stream.set_nodelay(true)?;
+    send(&mut stream, &ReplicationMessage::Subscribe { after })?;
+    let (sender, receiver) = std_mpsc::channel();
+    thread::spawn(move || {
+        for line in BufReader::new(stream).lines() {
+            let Ok(line) = line else {
+                break;
+            };
+            let Ok(message) = serde_json::from_str(&line) else {
+                break;
+            };
+            if sender.send(message).is_err() {
+                break;
+            }
+        }
+    });
+    Ok(receiver)
+}
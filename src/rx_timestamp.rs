@@ -0,0 +1,167 @@
+// 接收时间戳：在 Linux 上通过 SO_TIMESTAMPING 让内核为收到的数据打上时间戳，用 recvmsg 读取数据时
+// 一并取出，得到订单到达网卡（硬件时间戳）或进入内核协议栈（软件时间戳）的时刻，用于测量从线上到撮合完成的耗时。
+// 硬件时间戳还需要在网卡上开启（SIOCSHWTSTAMP，例如 hwstamp_ctl），并用 phc2sys 把网卡时钟同步到系统时钟，
+// 否则只有软件时间戳。TCP 的一次读取可能包含多帧，同一次读取得到的帧共享该次读取的时间戳
+use std::io;
+use std::pin::Pin;
+use std::task::{ready, Context, Poll};
+use std::time::{SystemTime, UNIX_EPOCH};
+use tokio::io::{AsyncRead, AsyncWrite, Interest, ReadBuf};
+use tokio::net::TcpStream;
+
+// 当前的 Unix 时间（纳秒），与软件接收时间戳使用同一个时钟
+pub fn unix_nanos() -> u64 {
+    SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_nanos() as u64).unwrap_or_default()
+}
+
+// 在套接字上开启接收时间戳，同时请求硬件和软件时间戳，内核给出哪个就用哪个。
+// 在监听套接字上开启时，接受的连接继承该设置，连接建立后立即到达的数据也带有时间戳
+#[cfg(target_os = "linux")]
+pub fn enable(socket: &impl std::os::fd::AsRawFd) -> io::Result<()> {
+    sys::enable(socket.as_raw_fd())
+}
+
+#[cfg(not(target_os = "linux"))]
+pub fn enable<S>(_socket: &S) -> io::Result<()> {
+    Err(io::Error::new(io::ErrorKind::Unsupported, "接收时间戳仅支持 Linux"))
+}
+
+// 读取时记录接收时间戳的 TCP 连接；未开启时与直接读写 TcpStream 相同
+#[derive(Debug)]
+pub struct TimestampedStream {
+    stream: TcpStream,
+    capture: bool,
+    last_timestamp: Option<u64>,
+}
+
+impl TimestampedStream {
+    // capture 为 true 时尝试开启接收时间戳，平台或内核不支持时退回普通读取
+    pub fn new(stream: TcpStream, capture: bool) -> Self {
+        let capture = capture
+            && match enable(&stream) {
+                Ok(()) => true,
+                Err(e) => {
+                    eprintln!("无法开启接收时间戳: {}", e);
+                    false
+                }
+            };
+        TimestampedStream { stream, capture, last_timestamp: None }
+    }
+
+    pub fn capturing(&self) -> bool {
+        self.capture
+    }
+
+    // 最近一次读取的接收时间戳（Unix 纳秒）
+    pub fn last_timestamp(&self) -> Option<u64> {
+        self.last_timestamp
+    }
+
+    pub fn get_ref(&self) -> &TcpStream {
+        &self.stream
+    }
+}
+
+impl AsyncRead for TimestampedStream {
+    fn poll_read(mut self: Pin<&mut Self>, cx: &mut Context<'_>, buf: &mut ReadBuf<'_>) -> Poll<io::Result<()>> {
+        if !self.capture {
+            return Pin::new(&mut self.stream).poll_read(cx, buf);
+        }
+        #[cfg(target_os = "linux")]
+        loop {
+            ready!(self.stream.poll_read_ready(cx))?;
+            let this = &mut *self;
+            let unfilled = buf.initialize_unfilled();
+            let result = this.stream.try_io(Interest::READABLE, || {
+                use std::os::fd::AsRawFd;
+                sys::recv(this.stream.as_raw_fd(), unfilled)
+            });
+            match result {
+                Ok((read, timestamp)) => {
+                    buf.advance(read);
+                    if timestamp.is_some() {
+                        this.last_timestamp = timestamp;
+                    }
+                    return Poll::Ready(Ok(()));
+                }
+                // try_io 已清除就绪状态，重新等待
+                Err(e) if e.kind() == io::ErrorKind::WouldBlock => continue,
+                Err(e) => return Poll::Ready(Err(e)),
+            }
+        }
+        #[cfg(not(target_os = "linux"))]
+        unreachable!("其他平台不会开启接收时间戳")
+    }
+}
+
+impl AsyncWrite for TimestampedStream {
+    fn poll_write(mut self: Pin<&mut Self>, cx: &mut Context<'_>, buf: &[u8]) -> Poll<io::Result<usize>> {
+        Pin::new(&mut self.stream).poll_write(cx, buf)
+    }
+
+    fn poll_flush(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.stream).poll_flush(cx)
+    }
+
+    fn poll_shutdown(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.stream).poll_shutdown(cx)
+    }
+}
+
+#[cfg(target_os = "linux")]
+mod sys {
+    use std::io;
+    use std::mem;
+    use std::os::fd::RawFd;
+
+    pub fn enable(fd: RawFd) -> io::Result<()> {
+        let flags: libc::c_uint = libc::SOF_TIMESTAMPING_RX_HARDWARE
+            | libc::SOF_TIMESTAMPING_RAW_HARDWARE
+            | libc::SOF_TIMESTAMPING_RX_SOFTWARE
+            | libc::SOF_TIMESTAMPING_SOFTWARE;
+        let result = unsafe {
+            libc::setsockopt(
+                fd,
+                libc::SOL_SOCKET,
+                libc::SO_TIMESTAMPING,
+                (&flags as *const libc::c_uint).cast(),
+                mem::size_of_val(&flags) as libc::socklen_t,
+            )
+        };
+        if result < 0 {
+            return Err(io::Error::last_os_error());
+        }
+        Ok(())
+    }
+
+    // 读取数据并取出 SCM_TIMESTAMPING 控制消息中的时间戳：ts[0] 为软件时间戳，ts[2] 为网卡原始硬件时间戳，
+    // 两者都有时优先使用硬件时间戳
+    pub fn recv(fd: RawFd, buf: &mut [u8]) -> io::Result<(usize, Option<u64>)> {
+        let mut iov = libc::iovec { iov_base: buf.as_mut_ptr().cast(), iov_len: buf.len() };
+        // 按 u64 对齐，足以容纳一条带三个 timespec 的控制消息
+        let mut control = [0u64; 16];
+        let mut msg: libc::msghdr = unsafe { mem::zeroed() };
+        msg.msg_iov = &mut iov;
+        msg.msg_iovlen = 1;
+        msg.msg_control = control.as_mut_ptr().cast();
+        msg.msg_controllen = mem::size_of_val(&control) as _;
+        let read = unsafe { libc::recvmsg(fd, &mut msg, 0) };
+        if read < 0 {
+            return Err(io::Error::last_os_error());
+        }
+        let mut timestamp = None;
+        let mut cmsg = unsafe { libc::CMSG_FIRSTHDR(&msg) };
+        while !cmsg.is_null() {
+            let header = unsafe { &*cmsg };
+            if header.cmsg_level == libc::SOL_SOCKET && header.cmsg_type == libc::SCM_TIMESTAMPING {
+                let ts = unsafe { (libc::CMSG_DATA(cmsg) as *const [libc::timespec; 3]).read_unaligned() };
+                timestamp = [ts[2], ts[0]]
+                    .into_iter()
+                    .find(|t| t.tv_sec != 0 || t.tv_nsec != 0)
+                    .map(|t| t.tv_sec as u64 * 1_000_000_000 + t.tv_nsec as u64);
+            }
+            cmsg = unsafe { libc::CMSG_NXTHDR(&msg, cmsg) };
+        }
+        Ok((read as usize, timestamp))
+    }
+}
@@ -0,0 +1,401 @@
+// SBE（Simple Binary Encoding）风格的定长编码：所有字段按固定偏移以小端序排列，
+// 合约代码使用定长字节数组，解码不做任何堆分配。
+// 目前只覆盖热路径上的新订单和成交回报两种消息
+use crate::protocol::{NewOrderRequest, OrderType, TradeNotification};
+use std::fmt;
+
+// 消息头长度：block_length、template_id、schema_id、version 各 2 字节
+pub const HEADER_LEN: usize = 8;
+pub const SCHEMA_ID: u16 = 1;
+pub const SCHEMA_VERSION: u16 = 1;
+pub const NEW_ORDER_TEMPLATE_ID: u16 = 1;
+pub const TRADE_TEMPLATE_ID: u16 = 2;
+// 合约代码的最大字节数，不足部分以 0 填充
+pub const SYMBOL_LEN: usize = 16;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SbeError {
+    // 缓冲区长度不足
+    BufferTooShort { needed: usize, available: usize },
+    // 消息头中的 schema 或版本与本实现不一致
+    SchemaMismatch { schema_id: u16, version: u16 },
+    // 消息头中的模板号不是期望的消息类型
+    UnexpectedTemplate(u16),
+    // 合约代码超过 SYMBOL_LEN 字节或不是合法的 UTF-8
+    InvalidSymbol,
+    InvalidSide(u8),
+}
+
+impl fmt::Display for SbeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SbeError::BufferTooShort { needed, available } => {
+                write!(f, "缓冲区长度不足: 需要 {} 字节，实际 {} 字节", needed, available)
+            }
+            SbeError::SchemaMismatch { schema_id, version } => {
+                write!(f, "不支持的 schema: id {} 版本 {}", schema_id, version)
+            }
+            SbeError::UnexpectedTemplate(template_id) => write!(f, "意外的消息模板: {}", template_id),
+            SbeError::InvalidSymbol => write!(f, "无效的合约代码"),
+            SbeError::InvalidSide(side) => write!(f, "无效的买卖方向: {}", side),
+        }
+    }
+}
+
+impl std::error::Error for SbeError {}
+
+// 定长合约代码
+#[derive(Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Symbol([u8; SYMBOL_LEN]);
+
+impl Symbol {
+    pub fn new(symbol: &str) -> Result<Self, SbeError> {
+        let bytes = symbol.as_bytes();
+        if bytes.len() > SYMBOL_LEN || bytes.contains(&0) {
+            return Err(SbeError::InvalidSymbol);
+        }
+        let mut fixed = [0; SYMBOL_LEN];
+        fixed[..bytes.len()].copy_from_slice(bytes);
+        Ok(Symbol(fixed))
+    }
+
+    // 校验定长字节数组：0 之后只能是填充，有效部分必须是 UTF-8
+    pub fn from_bytes(bytes: [u8; SYMBOL_LEN]) -> Result<Self, SbeError> {
+        let len = bytes.iter().position(|&b| b == 0).unwrap_or(SYMBOL_LEN);
+        if bytes[len..].iter().any(|&b| b != 0) || std::str::from_utf8(&bytes[..len]).is_err() {
+            return Err(SbeError::InvalidSymbol);
+        }
+        Ok(Symbol(bytes))
+    }
+
+    pub fn as_str(&self) -> &str {
+        let len = self.0.iter().position(|&b| b == 0).unwrap_or(SYMBOL_LEN);
+        std::str::from_utf8(&self.0[..len]).expect("构造时已校验 UTF-8")
+    }
+
+    pub fn as_bytes(&self) -> &[u8; SYMBOL_LEN] {
+        &self.0
+    }
+}
+
+impl fmt::Debug for Symbol {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:?}", self.as_str())
+    }
+}
+
+// 新订单，消息体布局：user_id | symbol | side | price | quantity
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SbeNewOrder {
+    pub user_id: u64,
+    pub symbol: Symbol,
+    pub order_type: OrderType,
+    pub price: u64,
+    pub quantity: u64,
+}
+
+// 成交回报，消息体布局：trade_id | symbol | price | quantity | buyer_user_id | buyer_order_id |
+// seller_user_id | seller_order_id | timestamp | is_block_trade
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SbeTrade {
+    pub trade_id: u64,
+    pub symbol: Symbol,
+    pub matched_price: u64,
+    pub matched_quantity: u64,
+    pub buyer_user_id: u64,
+    pub buyer_order_id: u64,
+    pub seller_user_id: u64,
+    pub seller_order_id: u64,
+    pub timestamp: u64,
+    pub is_block_trade: bool,
+}
+
+impl SbeNewOrder {
+    pub const BLOCK_LENGTH: usize = 8 + SYMBOL_LEN + 1 + 8 + 8;
+    pub const ENCODED_LEN: usize = HEADER_LEN + Self::BLOCK_LENGTH;
+
+    pub fn from_request(request: &NewOrderRequest) -> Result<Self, SbeError> {
+        Ok(SbeNewOrder {
+            user_id: request.user_id,
+            symbol: Symbol::new(&request.symbol)?,
+            order_type: request.order_type,
+            price: request.price,
+            quantity: request.quantity,
+        })
+    }
+
+    // 转换为引擎使用的请求，合约代码在这里才分配 String
+    pub fn to_request(&self) -> NewOrderRequest {
+        NewOrderRequest {
+            user_id: self.user_id,
+            symbol: self.symbol.as_str().to_string(),
+            order_type: self.order_type,
+            price: self.price,
+            quantity: self.quantity,
+        }
+    }
+
+    // 编码到 buf 的开头，返回写入的字节数
+    pub fn encode(&self, buf: &mut [u8]) -> Result<usize, SbeError> {
+        let mut writer = Writer::new(buf, Self::ENCODED_LEN)?;
+        writer.header(Self::BLOCK_LENGTH, NEW_ORDER_TEMPLATE_ID);
+        writer.u64(self.user_id);
+        writer.bytes(self.symbol.as_bytes());
+        writer.u8(encode_side(self.order_type));
+        writer.u64(self.price);
+        writer.u64(self.quantity);
+        Ok(Self::ENCODED_LEN)
+    }
+
+    pub fn decode(buf: &[u8]) -> Result<Self, SbeError> {
+        let mut reader = Reader::new(buf, NEW_ORDER_TEMPLATE_ID, Self::BLOCK_LENGTH)?;
+        Ok(SbeNewOrder {
+            user_id: reader.u64(),
+            symbol: reader.symbol()?,
+            order_type: decode_side(reader.u8())?,
+            price: reader.u64(),
+            quantity: reader.u64(),
+        })
+    }
+}
+
+impl SbeTrade {
+    pub const BLOCK_LENGTH: usize = 8 + SYMBOL_LEN + 8 * 7 + 1;
+    pub const ENCODED_LEN: usize = HEADER_LEN + Self::BLOCK_LENGTH;
+
+    pub fn from_notification(trade: &TradeNotification) -> Result<Self, SbeError> {
+        Ok(SbeTrade {
+            trade_id: trade.trade_id,
+            symbol: Symbol::new(&trade.symbol)?,
+            matched_price: trade.matched_price,
+            matched_quantity: trade.matched_quantity,
+            buyer_user_id: trade.buyer_user_id,
+            buyer_order_id: trade.buyer_order_id,
+            seller_user_id: trade.seller_user_id,
+            seller_order_id: trade.seller_order_id,
+            timestamp: trade.timestamp,
+            is_block_trade: trade.is_block_trade,
+        })
+    }
+
+    pub fn to_notification(&self) -> TradeNotification {
+        TradeNotification {
+            trade_id: self.trade_id,
+            symbol: self.symbol.as_str().into(),
+            matched_price: self.matched_price,
+            matched_quantity: self.matched_quantity,
+            buyer_user_id: self.buyer_user_id,
+            buyer_order_id: self.buyer_order_id,
+            seller_user_id: self.seller_user_id,
+            seller_order_id: self.seller_order_id,
+            timestamp: self.timestamp,
+            is_block_trade: self.is_block_trade,
+        }
+    }
+
+    pub fn encode(&self, buf: &mut [u8]) -> Result<usize, SbeError> {
+        let mut writer = Writer::new(buf, Self::ENCODED_LEN)?;
+        writer.header(Self::BLOCK_LENGTH, TRADE_TEMPLATE_ID);
+        writer.u64(self.trade_id);
+        writer.bytes(self.symbol.as_bytes());
+        for value in [
+            self.matched_price,
+            self.matched_quantity,
+            self.buyer_user_id,
+            self.buyer_order_id,
+            self.seller_user_id,
+            self.seller_order_id,
+            self.timestamp,
+        ] {
+            writer.u64(value);
+        }
+        writer.u8(self.is_block_trade as u8);
+        Ok(Self::ENCODED_LEN)
+    }
+
+    pub fn decode(buf: &[u8]) -> Result<Self, SbeError> {
+        let mut reader = Reader::new(buf, TRADE_TEMPLATE_ID, Self::BLOCK_LENGTH)?;
+        Ok(SbeTrade {
+            trade_id: reader.u64(),
+            symbol: reader.symbol()?,
+            matched_price: reader.u64(),
+            matched_quantity: reader.u64(),
+            buyer_user_id: reader.u64(),
+            buyer_order_id: reader.u64(),
+            seller_user_id: reader.u64(),
+            seller_order_id: reader.u64(),
+            timestamp: reader.u64(),
+            is_block_trade: reader.u8() != 0,
+        })
+    }
+}
+
+// 新订单的零拷贝视图：构造时一次性校验消息头、长度、买卖方向和合约代码，
+// 之后的访问器直接从接收缓冲区按固定偏移读取字段
+#[derive(Debug, Clone, Copy)]
+pub struct NewOrderView<'a> {
+    body: &'a [u8],
+}
+
+impl<'a> NewOrderView<'a> {
+    const USER_ID: usize = 0;
+    const SYMBOL: usize = 8;
+    const SIDE: usize = Self::SYMBOL + SYMBOL_LEN;
+    const PRICE: usize = Self::SIDE + 1;
+    const QUANTITY: usize = Self::PRICE + 8;
+
+    pub fn new(buf: &'a [u8]) -> Result<Self, SbeError> {
+        let offset = check_header(buf, NEW_ORDER_TEMPLATE_ID, SbeNewOrder::BLOCK_LENGTH)?;
+        let view = NewOrderView { body: &buf[offset..offset + SbeNewOrder::BLOCK_LENGTH] };
+        decode_side(view.body[Self::SIDE])?;
+        Symbol::from_bytes(view.symbol_bytes())?;
+        Ok(view)
+    }
+
+    pub fn user_id(&self) -> u64 {
+        self.u64_at(Self::USER_ID)
+    }
+
+    pub fn symbol(&self) -> &'a str {
+        let bytes = &self.body[Self::SYMBOL..Self::SIDE];
+        let len = bytes.iter().position(|&b| b == 0).unwrap_or(SYMBOL_LEN);
+        std::str::from_utf8(&bytes[..len]).expect("构造时已校验 UTF-8")
+    }
+
+    pub fn order_type(&self) -> OrderType {
+        decode_side(self.body[Self::SIDE]).expect("构造时已校验买卖方向")
+    }
+
+    pub fn price(&self) -> u64 {
+        self.u64_at(Self::PRICE)
+    }
+
+    pub fn quantity(&self) -> u64 {
+        self.u64_at(Self::QUANTITY)
+    }
+
+    // 转换为引擎使用的请求，合约代码在这里才分配 String
+    pub fn to_request(&self) -> NewOrderRequest {
+        NewOrderRequest {
+            user_id: self.user_id(),
+            symbol: self.symbol().to_string(),
+            order_type: self.order_type(),
+            price: self.price(),
+            quantity: self.quantity(),
+        }
+    }
+
+    fn symbol_bytes(&self) -> [u8; SYMBOL_LEN] {
+        self.body[Self::SYMBOL..Self::SIDE].try_into().expect("长度已校验")
+    }
+
+    fn u64_at(&self, offset: usize) -> u64 {
+        u64::from_le_bytes(self.body[offset..offset + 8].try_into().expect("长度已校验"))
+    }
+}
+
+// 读取消息头中的模板号，用于在解码前分派消息类型
+pub fn peek_template_id(buf: &[u8]) -> Result<u16, SbeError> {
+    check_len(buf.len(), HEADER_LEN)?;
+    Ok(u16::from_le_bytes([buf[2], buf[3]]))
+}
+
+fn encode_side(order_type: OrderType) -> u8 {
+    match order_type {
+        OrderType::Buy => 0,
+        OrderType::Sell => 1,
+    }
+}
+
+fn decode_side(side: u8) -> Result<OrderType, SbeError> {
+    match side {
+        0 => Ok(OrderType::Buy),
+        1 => Ok(OrderType::Sell),
+        other => Err(SbeError::InvalidSide(other)),
+    }
+}
+
+fn check_len(available: usize, needed: usize) -> Result<(), SbeError> {
+    if available < needed {
+        return Err(SbeError::BufferTooShort { needed, available });
+    }
+    Ok(())
+}
+
+// 校验消息头，返回消息体的起始偏移
+fn check_header(buf: &[u8], template_id: u16, block_length: usize) -> Result<usize, SbeError> {
+    check_len(buf.len(), HEADER_LEN)?;
+    let field = |offset: usize| u16::from_le_bytes([buf[offset], buf[offset + 1]]);
+    let (schema_id, version) = (field(4), field(6));
+    if schema_id != SCHEMA_ID || version != SCHEMA_VERSION {
+        return Err(SbeError::SchemaMismatch { schema_id, version });
+    }
+    if field(2) != template_id {
+        return Err(SbeError::UnexpectedTemplate(field(2)));
+    }
+    // 新版本可以在消息体末尾追加字段，按消息头中的 block_length 校验长度
+    let encoded_block = field(0) as usize;
+    check_len(encoded_block, block_length)?;
+    check_len(buf.len(), HEADER_LEN + encoded_block)?;
+    Ok(HEADER_LEN)
+}
+
+struct Writer<'a> {
+    buf: &'a mut [u8],
+    offset: usize,
+}
+
+impl<'a> Writer<'a> {
+    fn new(buf: &'a mut [u8], len: usize) -> Result<Self, SbeError> {
+        check_len(buf.len(), len)?;
+        Ok(Writer { buf, offset: 0 })
+    }
+
+    fn header(&mut self, block_length: usize, template_id: u16) {
+        for value in [block_length as u16, template_id, SCHEMA_ID, SCHEMA_VERSION] {
+            self.bytes(&value.to_le_bytes());
+        }
+    }
+
+    fn u8(&mut self, value: u8) {
+        self.bytes(&[value]);
+    }
+
+    fn u64(&mut self, value: u64) {
+        self.bytes(&value.to_le_bytes());
+    }
+
+    fn bytes(&mut self, bytes: &[u8]) {
+        self.buf[self.offset..self.offset + bytes.len()].copy_from_slice(bytes);
+        self.offset += bytes.len();
+    }
+}
+
+struct Reader<'a> {
+    buf: &'a [u8],
+    offset: usize,
+}
+
+impl<'a> Reader<'a> {
+    fn new(buf: &'a [u8], template_id: u16, block_length: usize) -> Result<Self, SbeError> {
+        let offset = check_header(buf, template_id, block_length)?;
+        Ok(Reader { buf, offset })
+    }
+
+    fn u8(&mut self) -> u8 {
+        self.offset += 1;
+        self.buf[self.offset - 1]
+    }
+
+    fn u64(&mut self) -> u64 {
+        let bytes = self.buf[self.offset..self.offset + 8].try_into().expect("长度已校验");
+        self.offset += 8;
+        u64::from_le_bytes(bytes)
+    }
+
+    fn symbol(&mut self) -> Result<Symbol, SbeError> {
+        let bytes = self.buf[self.offset..self.offset + SYMBOL_LEN].try_into().expect("长度已校验");
+        self.offset += SYMBOL_LEN;
+        Symbol::from_bytes(bytes)
+    }
+}
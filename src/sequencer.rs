@@ -0,0 +1,91 @@
+use crate::id::IdGenerator;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+
+// 成交定序组件：为每笔成交分配成交号和时间戳。
+// 引擎和所有订单簿共享同一个实例，无论成交来自连续撮合、集合竞价还是大宗交易，
+// 都在产生时就带有有效且唯一的成交号。
+//
+// 排序约定：时间戳严格递增（系统时钟回拨或同一纳秒内的多个事件在上一个时间戳上加 1 纳秒），
+// 成交号越大时间戳越大，下游按时间戳或成交号排序都能还原引擎内的事件顺序。
+// 深度快照的时间戳来自同一个时钟，大于之前任何成交的时间戳。
+//
+// 时间戳存放在原子变量中，每笔成交用一次 CAS 推进而不加锁。成交号在读取旧时间戳之后、CAS 之前分配：
+// 时间戳每次都严格变大，CAS 成功说明两者之间没有别的事件推进时钟，成交号与时间戳的先后顺序因此一致；
+// CAS 失败时重新取号，放弃的成交号只在多线程争用时出现，留下空号
+#[derive(Debug, Default)]
+pub struct Sequencer {
+    trade_ids: Arc<IdGenerator>,
+    clock: Clock,
+    // 最近一次分配的时间戳
+    last_timestamp: AtomicU64,
+}
+
+#[derive(Debug, Default, Clone, Copy)]
+enum Clock {
+    // 系统时钟
+    #[default]
+    System,
+    // 逻辑时钟：每次取时间前进 1 纳秒，与真实时间无关
+    Logical,
+}
+
+impl Sequencer {
+    pub fn new(trade_ids: Arc<IdGenerator>) -> Self {
+        Sequencer { trade_ids, clock: Clock::System, last_timestamp: AtomicU64::new(0) }
+    }
+
+    // 使用逻辑时钟的定序组件，时间戳从 1 开始逐次加 1，用于确定性仿真
+    pub fn logical(trade_ids: Arc<IdGenerator>) -> Self {
+        Sequencer { trade_ids, clock: Clock::Logical, last_timestamp: AtomicU64::new(0) }
+    }
+
+    // 返回下一笔成交的 (成交号, 时间戳)
+    pub fn next_trade(&self) -> (u64, u64) {
+        self.advance(|| self.trade_ids.next_id())
+    }
+
+    // 成交号生成器的状态，写入恢复快照
+    pub fn checkpoint(&self) -> u64 {
+        self.trade_ids.checkpoint()
+    }
+
+    // 从快照恢复，之后的成交号不会与快照之前的重复
+    pub fn restore(&self, state: u64) {
+        self.trade_ids.restore(state);
+    }
+
+    // 返回不早于之前所有事件的时间戳，用于不占用成交号的事件
+    pub fn next_timestamp(&self) -> u64 {
+        self.advance(|| ()).1
+    }
+
+    // 最近一个事件的时间戳，不推进时钟
+    pub fn last_timestamp(&self) -> u64 {
+        self.last_timestamp.load(Ordering::Acquire)
+    }
+
+    // 推进时钟，并在同一次推进内调用 assign 分配序号，返回 (序号, 时间戳)
+    fn advance<T>(&self, mut assign: impl FnMut() -> T) -> (T, u64) {
+        let mut last = self.last_timestamp.load(Ordering::Acquire);
+        loop {
+            let value = assign();
+            let next = match self.clock {
+                Clock::System => now_nanos().max(last + 1),
+                Clock::Logical => last + 1,
+            };
+            match self.last_timestamp.compare_exchange_weak(last, next, Ordering::AcqRel, Ordering::Acquire) {
+                Ok(_) => return (value, next),
+                Err(current) => last = current,
+            }
+        }
+    }
+}
+
+// 当前 UNIX 时间，单位纳秒
+pub fn now_nanos() -> u64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_nanos() as u64
+}
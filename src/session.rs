@@ -0,0 +1,231 @@
+use crate::audit::AuditTrail;
+use crate::gateway::Gateway;
+use crate::protocol::{ClientMessage, LogonRequest, LogonStatus, RejectReason};
+use crate::sequencer::now_nanos;
+use hmac::{Hmac, Mac};
+use sha2::Sha256;
+use std::collections::{BTreeSet, HashMap};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+type HmacSha256 = Hmac<Sha256>;
+
+// 启用登录校验时默认的心跳间隔
+const DEFAULT_HEARTBEAT_INTERVAL: Duration = Duration::from_secs(10);
+
+// 一个 API 密钥对应的用户和签名 secret
+#[derive(Debug, Clone)]
+pub struct ApiCredential {
+    pub user_id: u64,
+    pub secret: Vec<u8>,
+}
+
+// 会话配置。默认不要求登录、不发心跳也不断开空闲连接，以兼容没有登录流程的内部工具
+#[derive(Debug, Clone)]
+pub struct SessionConfig {
+    // 按 API 密钥索引的凭证
+    pub credentials: HashMap<String, ApiCredential>,
+    // 为 true 时未登录连接的所有业务消息都会被拒绝
+    pub require_logon: bool,
+    // 服务器按该间隔发送心跳；客户端在 max_missed_heartbeats 个间隔内没有发送任何消息
+    //（业务消息或心跳）时断开连接。为 None 时不发心跳也不断开
+    pub heartbeat_interval: Option<Duration>,
+    pub max_missed_heartbeats: u32,
+    // 连接断开时撤销该连接上用户的所有挂单
+    pub cancel_on_disconnect: bool,
+    // 为执行回报分配序号并支持重连补发，为 None 时不启用
+    pub gateway: Option<Arc<Gateway>>,
+    // 记录订单事件的监管审计轨迹，为 None 时不记录
+    pub audit: Option<Arc<AuditTrail>>,
+    // 登录时间戳与服务器时间允许的最大偏差，用于拒绝重放的登录请求
+    pub max_clock_skew: Duration,
+    // 在连接上开启 SO_TIMESTAMPING，订单携带内核或网卡的接收时间进入引擎，仅支持 Linux
+    pub rx_timestamps: bool,
+}
+
+impl Default for SessionConfig {
+    fn default() -> Self {
+        SessionConfig {
+            credentials: HashMap::new(),
+            require_logon: false,
+            heartbeat_interval: None,
+            max_missed_heartbeats: 3,
+            cancel_on_disconnect: false,
+            gateway: None,
+            audit: None,
+            max_clock_skew: Duration::from_secs(30),
+            rx_timestamps: false,
+        }
+    }
+}
+
+impl SessionConfig {
+    // 使用给定凭证启用登录校验、心跳和断线撤单
+    pub fn with_credentials(credentials: HashMap<String, ApiCredential>) -> Self {
+        SessionConfig {
+            credentials,
+            require_logon: true,
+            heartbeat_interval: Some(DEFAULT_HEARTBEAT_INTERVAL),
+            cancel_on_disconnect: true,
+            ..SessionConfig::default()
+        }
+    }
+
+    // 客户端允许的最长静默时间
+    pub fn idle_timeout(&self) -> Option<Duration> {
+        self.heartbeat_interval.map(|interval| interval * self.max_missed_heartbeats)
+    }
+
+    // 解析 "api_key:user_id:secret,..." 格式的凭证列表
+    pub fn parse_credentials(spec: &str) -> Result<HashMap<String, ApiCredential>, String> {
+        let mut credentials = HashMap::new();
+        for entry in spec.split(',').map(str::trim).filter(|entry| !entry.is_empty()) {
+            let mut parts = entry.splitn(3, ':');
+            let (Some(api_key), Some(user_id), Some(secret)) = (parts.next(), parts.next(), parts.next()) else {
+                return Err(format!("无效的凭证配置: {}", entry));
+            };
+            let user_id = user_id.parse().map_err(|_| format!("无效的用户 ID: {}", user_id))?;
+            credentials.insert(api_key.to_string(), ApiCredential { user_id, secret: secret.as_bytes().to_vec() });
+        }
+        Ok(credentials)
+    }
+}
+
+// 计算登录请求的签名，供客户端使用
+pub fn sign_logon(secret: &[u8], api_key: &str, timestamp_ms: u64) -> Vec<u8> {
+    let mut mac = HmacSha256::new_from_slice(secret).expect("HMAC 接受任意长度的密钥");
+    mac.update(format!("{}:{}", api_key, timestamp_ms).as_bytes());
+    mac.finalize().into_bytes().to_vec()
+}
+
+// 单个连接的会话状态
+#[derive(Debug)]
+pub struct Session {
+    // 登录成功后绑定的用户
+    user_id: Option<u64>,
+    // 最近一次收到客户端消息的时间
+    last_activity: Instant,
+    // 已发送给该连接的消息数
+    outbound_seq: u64,
+    // 未登录时在该连接上下过单的用户，用于断线撤单
+    order_users: BTreeSet<u64>,
+}
+
+impl Default for Session {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Session {
+    pub fn new() -> Self {
+        Session {
+            user_id: None,
+            last_activity: Instant::now(),
+            outbound_seq: 0,
+            order_users: BTreeSet::new(),
+        }
+    }
+
+    pub fn user_id(&self) -> Option<u64> {
+        self.user_id
+    }
+
+    pub fn outbound_seq(&self) -> u64 {
+        self.outbound_seq
+    }
+
+    // 记录一条发往客户端的消息，返回它的出站序号（从 1 开始）
+    pub fn next_outbound_seq(&mut self) -> u64 {
+        self.outbound_seq += 1;
+        self.outbound_seq
+    }
+
+    // 记录收到客户端消息
+    pub fn touch(&mut self) {
+        self.last_activity = Instant::now();
+    }
+
+    // 空闲超时的截止时间，未配置心跳时为 None
+    pub fn idle_deadline(&self, config: &SessionConfig) -> Option<Instant> {
+        config.idle_timeout().map(|timeout| self.last_activity + timeout)
+    }
+
+    // 记录在该连接上下单的用户
+    pub fn record_order_user(&mut self, user_id: u64) {
+        self.order_users.insert(user_id);
+    }
+
+    // 断线时需要撤单的用户：已登录时为绑定的用户，否则为在该连接上下过单的用户
+    pub fn disconnect_users(&self) -> Vec<u64> {
+        match self.user_id {
+            Some(user_id) => vec![user_id],
+            None => self.order_users.iter().copied().collect(),
+        }
+    }
+
+    pub fn logon(&mut self, config: &SessionConfig, request: &LogonRequest) -> LogonStatus {
+        self.logon_at(config, request, now_nanos() / 1_000_000)
+    }
+
+    // 按给定的服务器时间（UNIX 毫秒）校验登录请求，成功时绑定用户
+    pub fn logon_at(&mut self, config: &SessionConfig, request: &LogonRequest, now_ms: u64) -> LogonStatus {
+        if self.user_id.is_some() {
+            return LogonStatus::AlreadyLoggedOn;
+        }
+        let Some(credential) = config.credentials.get(&request.api_key) else {
+            return LogonStatus::UnknownApiKey;
+        };
+        if request.timestamp_ms.abs_diff(now_ms) > config.max_clock_skew.as_millis() as u64 {
+            return LogonStatus::StaleTimestamp;
+        }
+        let mut mac = HmacSha256::new_from_slice(&credential.secret).expect("HMAC 接受任意长度的密钥");
+        mac.update(format!("{}:{}", request.api_key, request.timestamp_ms).as_bytes());
+        // verify_slice 以常数时间比较签名
+        if mac.verify_slice(&request.signature).is_err() {
+            return LogonStatus::InvalidSignature;
+        }
+        self.user_id = Some(credential.user_id);
+        LogonStatus::Accepted
+    }
+
+    // 检查业务消息是否允许提交给引擎；消息中的用户必须与会话绑定的用户一致，
+    // 大宗交易要求会话用户是买方或卖方之一。
+    // OCO 订单无论是否要求登录都检查两条腿：连接只按一个用户登记断线撤单，两条腿必须属于会话用户，
+    // 未登录时以第一条腿的用户为会话用户
+    pub fn authorize(&self, config: &SessionConfig, message: &ClientMessage) -> Result<(), RejectReason> {
+        if let ClientMessage::OcoOrder(request) = message {
+            let user_id = self.user_id.unwrap_or(request.first.user_id);
+            if request.first.user_id != user_id || request.second.user_id != user_id {
+                return Err(RejectReason::UserMismatch);
+            }
+        }
+        if !config.require_logon {
+            return Ok(());
+        }
+        let Some(user_id) = self.user_id else {
+            return Err(RejectReason::Unauthenticated);
+        };
+        let permitted = match message {
+            ClientMessage::NewOrder(request) => request.user_id == user_id,
+            ClientMessage::CancelOrder(request) => request.user_id == user_id,
+            ClientMessage::AmendOrder(request) => request.user_id == user_id,
+            ClientMessage::BlockTrade(request) => request.buyer_user_id == user_id || request.seller_user_id == user_id,
+            ClientMessage::QueryPosition(query) => query.user_id == user_id,
+            ClientMessage::EstimateFill(request) => request.user_id == user_id,
+            ClientMessage::QueryMarketData(query) => query.user_id == user_id,
+            ClientMessage::SpreadOrder(request) => request.user_id == user_id,
+            ClientMessage::BasketOrder(request) => request.user_id == user_id,
+            ClientMessage::TimedOrder(request) => request.order.user_id == user_id,
+            ClientMessage::ReduceOnlyOrder(request) => request.user_id == user_id,
+            ClientMessage::OcoOrder(request) => request.first.user_id == user_id && request.second.user_id == user_id,
+            ClientMessage::Logon(_) | ClientMessage::Heartbeat | ClientMessage::Resume(_) => true,
+            ClientMessage::Request { message, .. } => return self.authorize(config, message),
+        };
+        if permitted {
+            Ok(())
+        } else {
+            Err(RejectReason::UserMismatch)
+        }
+    }
+}
@@ -0,0 +1,166 @@
+use crate::audit;
+use crate::engine::{ControlCommand, EngineCommand};
+use crate::protocol::TradeNotification;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::fmt::Write as _;
+use std::io;
+use std::path::PathBuf;
+use std::sync::mpsc as std_mpsc;
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use tokio::sync::mpsc::UnboundedSender;
+
+const DAY: Duration = Duration::from_secs(86_400);
+
+const CSV_HEADER: &str =
+    "business_date,trade_id,symbol,side,account,order_id,counterparty,price,quantity,notional,fee,block_trade,timestamp";
+
+// 日终结算导出配置
+#[derive(Debug, Clone)]
+pub struct SettlementConfig {
+    // 结算文件和清单的输出目录
+    pub dir: PathBuf,
+    // 每条成交腿按成交金额收取的手续费，单位为基点
+    pub fee_bps: u64,
+}
+
+// 结算清单，与结算文件一起交给清算所，用于核对文件是否完整
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SettlementManifest {
+    pub business_date: String,
+    pub file: String,
+    pub trades: usize,
+    // 每笔成交拆分为买卖两条腿
+    pub legs: usize,
+    pub total_quantity: u64,
+    pub total_fees: u128,
+    // 结算文件内容的 SHA-256，十六进制
+    pub sha256: String,
+}
+
+// 日终流程：从引擎取走当日成交并写出结算文件和清单
+pub fn run_end_of_day(
+    command_sender: &UnboundedSender<EngineCommand>,
+    business_date: &str,
+    config: &SettlementConfig,
+) -> io::Result<SettlementManifest> {
+    let trades = take_trades(command_sender)?;
+    write_settlement(&trades, business_date, config)
+}
+
+// 取走引擎中的当日成交，引擎已关闭时返回 BrokenPipe
+fn take_trades(command_sender: &UnboundedSender<EngineCommand>) -> io::Result<Vec<TradeNotification>> {
+    let (reply_tx, reply_rx) = std_mpsc::channel();
+    let engine_closed = || io::Error::new(io::ErrorKind::BrokenPipe, "撮合引擎已关闭");
+    command_sender
+        .send(EngineCommand::Control(ControlCommand::EndOfDay(reply_tx)))
+        .map_err(|_| engine_closed())?;
+    reply_rx.recv().map_err(|_| engine_closed())
+}
+
+// 每天在 UTC 零点之后 cutoff 的时刻执行日终，营业日为日终时刻前一秒所在的日期。
+// 配置了结算导出时写出结算文件；否则取走后丢弃当日成交，引擎中的成交记录最多保存一天
+pub fn spawn_end_of_day_scheduler(
+    command_sender: UnboundedSender<EngineCommand>,
+    config: Option<SettlementConfig>,
+    cutoff: Duration,
+) -> JoinHandle<()> {
+    thread::spawn(move || loop {
+        let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default();
+        thread::sleep(until_cutoff(now, cutoff));
+        let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default();
+        let business_date = audit::utc_date(now.as_secs().saturating_sub(1));
+        let result = match &config {
+            Some(config) => run_end_of_day(&command_sender, &business_date, config).map(|manifest| {
+                println!("已写出 {} 的结算文件 {}: {} 笔成交", business_date, manifest.file, manifest.trades);
+            }),
+            None => take_trades(&command_sender).map(|trades| {
+                println!("{} 日终: 未配置结算导出，丢弃 {} 笔成交记录", business_date, trades.len());
+            }),
+        };
+        match result {
+            Ok(()) => {}
+            Err(e) if e.kind() == io::ErrorKind::BrokenPipe => break,
+            Err(e) => eprintln!("{} 日终结算失败: {}", business_date, e),
+        }
+    })
+}
+
+// 从 Unix 时间 now 到下一个日终时刻（UTC 零点之后 cutoff）的时长；恰好在日终时刻时等到第二天
+pub fn until_cutoff(now: Duration, cutoff: Duration) -> Duration {
+    let cutoff = Duration::from_nanos((cutoff.as_nanos() % DAY.as_nanos()) as u64);
+    let since_midnight = Duration::from_nanos((now.as_nanos() % DAY.as_nanos()) as u64);
+    if cutoff > since_midnight {
+        cutoff - since_midnight
+    } else {
+        DAY - since_midnight + cutoff
+    }
+}
+
+// 写出 settlement_<日期>.csv 及对应的 settlement_<日期>.manifest.json
+pub fn write_settlement(
+    trades: &[TradeNotification],
+    business_date: &str,
+    config: &SettlementConfig,
+) -> io::Result<SettlementManifest> {
+    let mut csv = String::new();
+    csv.push_str(CSV_HEADER);
+    csv.push('\n');
+
+    let mut total_quantity = 0;
+    let mut total_fees = 0;
+    for trade in trades {
+        let notional = trade.matched_price as u128 * trade.matched_quantity as u128;
+        let fee = notional * config.fee_bps as u128 / 10_000;
+        let legs = [
+            ("BUY", trade.buyer_user_id, trade.buyer_order_id, trade.seller_user_id),
+            ("SELL", trade.seller_user_id, trade.seller_order_id, trade.buyer_user_id),
+        ];
+        for (side, account, order_id, counterparty) in legs {
+            let _ = writeln!(
+                csv,
+                "{},{},{},{},{},{},{},{},{},{},{},{},{}",
+                business_date,
+                trade.trade_id,
+                trade.symbol,
+                side,
+                account,
+                order_id,
+                counterparty,
+                trade.matched_price,
+                trade.matched_quantity,
+                notional,
+                fee,
+                trade.is_block_trade,
+                trade.timestamp,
+            );
+            total_fees += fee;
+        }
+        total_quantity += trade.matched_quantity;
+    }
+
+    let file = format!("settlement_{}.csv", business_date);
+    std::fs::create_dir_all(&config.dir)?;
+    std::fs::write(config.dir.join(&file), &csv)?;
+
+    let manifest = SettlementManifest {
+        business_date: business_date.to_string(),
+        file,
+        trades: trades.len(),
+        legs: trades.len() * 2,
+        total_quantity,
+        total_fees,
+        sha256: sha256_hex(csv.as_bytes()),
+    };
+    let manifest_path = config.dir.join(format!("settlement_{}.manifest.json", business_date));
+    std::fs::write(manifest_path, serde_json::to_vec_pretty(&manifest)?)?;
+    Ok(manifest)
+}
+
+pub fn sha256_hex(data: &[u8]) -> String {
+    Sha256::digest(data).iter().fold(String::new(), |mut hex, byte| {
+        let _ = write!(hex, "{:02x}", byte);
+        hex
+    })
+}
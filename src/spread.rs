@@ -0,0 +1,97 @@
+// 跨期价差合约：价差 = leg1 − leg2。两腿订单簿的流动性隐含出价差报价（implied-in），
+// 价差订单按两腿的深度拆分为成对的腿订单，在同一次命令处理中原子地成交
+use crate::protocol::{DepthLevel, ImpliedQuote, OrderType};
+
+// 价差合约定义：买入一手价差即买入 leg1、卖出 leg2 各一手
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SpreadDefinition {
+    pub symbol: String,
+    pub leg1: String,
+    pub leg2: String,
+}
+
+impl SpreadDefinition {
+    // 两腿各自的买卖方向
+    pub fn leg_sides(order_type: OrderType) -> (OrderType, OrderType) {
+        match order_type {
+            OrderType::Buy => (OrderType::Buy, OrderType::Sell),
+            OrderType::Sell => (OrderType::Sell, OrderType::Buy),
+        }
+    }
+}
+
+// 价差订单的一次成交分解到两腿的价格，两腿数量相同
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LegFill {
+    pub leg1_price: u64,
+    pub leg2_price: u64,
+    pub quantity: u64,
+}
+
+impl LegFill {
+    pub fn spread_price(&self) -> i64 {
+        self.leg1_price as i64 - self.leg2_price as i64
+    }
+}
+
+// 由两腿的 (买盘, 卖盘) 最优档位计算隐含报价，档位按由优到劣排列
+pub fn implied_quote(
+    spread: &str,
+    leg1: (&[DepthLevel], &[DepthLevel]),
+    leg2: (&[DepthLevel], &[DepthLevel]),
+) -> ImpliedQuote {
+    let side = |near: Option<&DepthLevel>, far: Option<&DepthLevel>| match (near, far) {
+        (Some(near), Some(far)) => (Some(near.price as i64 - far.price as i64), near.quantity.min(far.quantity)),
+        _ => (None, 0),
+    };
+    let (bid_price, bid_quantity) = side(leg1.0.first(), leg2.1.first());
+    let (ask_price, ask_quantity) = side(leg1.1.first(), leg2.0.first());
+    ImpliedQuote { spread: spread.to_string(), bid_price, bid_quantity, ask_price, ask_quantity }
+}
+
+// 计算价差订单在限价内可以成交的部分。leg1_levels 和 leg2_levels 是两腿各自要吃的对手盘，
+// 按由优到劣排列：买入价差时为 leg1 卖盘和 leg2 买盘，卖出时为 leg1 买盘和 leg2 卖盘。
+// 越往后价差越差，遇到第一个超出限价的组合即停止
+pub fn plan_fills(
+    order_type: OrderType,
+    limit_price: i64,
+    quantity: u64,
+    leg1_levels: &[DepthLevel],
+    leg2_levels: &[DepthLevel],
+) -> Vec<LegFill> {
+    let mut fills = Vec::new();
+    let (mut i, mut j) = (0, 0);
+    let (mut leg1_left, mut leg2_left) = (0, 0);
+    let mut remaining = quantity;
+    while remaining > 0 && i < leg1_levels.len() && j < leg2_levels.len() {
+        if leg1_left == 0 {
+            leg1_left = leg1_levels[i].quantity;
+        }
+        if leg2_left == 0 {
+            leg2_left = leg2_levels[j].quantity;
+        }
+        let fill = LegFill {
+            leg1_price: leg1_levels[i].price,
+            leg2_price: leg2_levels[j].price,
+            quantity: remaining.min(leg1_left).min(leg2_left),
+        };
+        let acceptable = match order_type {
+            OrderType::Buy => fill.spread_price() <= limit_price,
+            OrderType::Sell => fill.spread_price() >= limit_price,
+        };
+        if !acceptable {
+            break;
+        }
+        fills.push(fill);
+        remaining -= fill.quantity;
+        leg1_left -= fill.quantity;
+        leg2_left -= fill.quantity;
+        if leg1_left == 0 {
+            i += 1;
+        }
+        if leg2_left == 0 {
+            j += 1;
+        }
+    }
+    fills
+}
@@ -0,0 +1,402 @@
+use crate::engine::EngineCommand;
+use crate::metrics::EngineMetrics;
+use crate::protocol::{OrderType, TradeNotification};
+use crate::sequencer::now_nanos;
+use parking_lot::{Mutex, RwLock};
+use std::collections::{HashMap, VecDeque};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::mpsc::{self as std_mpsc, Receiver, Sender};
+use std::sync::Arc;
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
+
+// 计量桶的宽度（毫秒）和数量，最长可观察 1 秒的窗口
+const BUCKET_MS: u64 = 100;
+const BUCKETS: usize = 10;
+
+// 单个计量桶，tick 为该桶对应的时间片编号（毫秒时间戳 / BUCKET_MS）
+#[derive(Debug, Default)]
+struct Bucket {
+    tick: AtomicU64,
+    trades: AtomicU64,
+    volume: AtomicU64,
+}
+
+// 单个合约的成交计量器，由引擎线程写入，监控线程读取
+#[derive(Debug, Default)]
+pub struct TradeMeter {
+    buckets: [Bucket; BUCKETS],
+}
+
+// 一个窗口内的成交速率
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct MeterReading {
+    pub trades_per_sec: f64,
+    pub volume_per_sec: f64,
+}
+
+impl TradeMeter {
+    pub fn record(&self, quantity: u64) {
+        self.record_at(now_millis(), quantity);
+    }
+
+    // 在给定时刻（毫秒）记录一笔成交；只允许一个写入方
+    pub fn record_at(&self, now_ms: u64, quantity: u64) {
+        let tick = now_ms / BUCKET_MS;
+        let bucket = &self.buckets[tick as usize % BUCKETS];
+        if bucket.tick.load(Ordering::Acquire) != tick {
+            // 桶中是上一轮的数据，先清零再复用
+            bucket.trades.store(0, Ordering::Relaxed);
+            bucket.volume.store(0, Ordering::Relaxed);
+            bucket.tick.store(tick, Ordering::Release);
+        }
+        bucket.trades.fetch_add(1, Ordering::Relaxed);
+        bucket.volume.fetch_add(quantity, Ordering::Relaxed);
+    }
+
+    pub fn reading(&self, window: Duration) -> MeterReading {
+        self.reading_at(now_millis(), window)
+    }
+
+    // 截至给定时刻的最近 window 内的成交速率，窗口按桶宽取整，最长 1 秒
+    pub fn reading_at(&self, now_ms: u64, window: Duration) -> MeterReading {
+        let window_buckets = (window.as_millis() as u64).div_ceil(BUCKET_MS).clamp(1, BUCKETS as u64);
+        let now_tick = now_ms / BUCKET_MS;
+        let (mut trades, mut volume) = (0, 0);
+        for bucket in &self.buckets {
+            let tick = bucket.tick.load(Ordering::Acquire);
+            if tick <= now_tick && now_tick - tick < window_buckets {
+                trades += bucket.trades.load(Ordering::Relaxed);
+                volume += bucket.volume.load(Ordering::Relaxed);
+            }
+        }
+        let seconds = (window_buckets * BUCKET_MS) as f64 / 1000.0;
+        MeterReading {
+            trades_per_sec: trades as f64 / seconds,
+            volume_per_sec: volume as f64 / seconds,
+        }
+    }
+}
+
+// 监控规则所观察的指标
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MeterKind {
+    TradesPerSec,
+    VolumePerSec,
+}
+
+// 监控规则，例如“1 秒内成交量超过 X 时告警”
+#[derive(Debug, Clone, PartialEq)]
+pub struct SurveillanceRule {
+    pub name: String,
+    pub symbol: String,
+    pub kind: MeterKind,
+    pub window: Duration,
+    pub threshold: f64,
+}
+
+// 规则从正常转为触发时产生的告警
+#[derive(Debug, Clone, PartialEq)]
+pub struct SurveillanceAlert {
+    pub rule: String,
+    pub symbol: String,
+    pub observed: f64,
+    pub threshold: f64,
+}
+
+// 各合约的成交计量器和已注册的监控规则。
+// 引擎在成交时只更新计量器，规则在监控线程中评估，不占用撮合路径
+#[derive(Debug, Default)]
+pub struct Surveillance {
+    meters: RwLock<HashMap<String, Arc<TradeMeter>>>,
+    // 规则及其上次评估时是否处于触发状态
+    rules: Mutex<Vec<(SurveillanceRule, bool)>>,
+}
+
+impl Surveillance {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    // 返回合约的计量器，不存在时创建
+    pub fn meter(&self, symbol: &str) -> Arc<TradeMeter> {
+        if let Some(meter) = self.meters.read().get(symbol) {
+            return meter.clone();
+        }
+        self.meters.write().entry(symbol.to_string()).or_default().clone()
+    }
+
+    pub fn reading(&self, symbol: &str, window: Duration) -> Option<MeterReading> {
+        self.meters.read().get(symbol).map(|meter| meter.reading(window))
+    }
+
+    // 注册规则，同名规则会被替换
+    pub fn add_rule(&self, rule: SurveillanceRule) {
+        let mut rules = self.rules.lock();
+        rules.retain(|(existing, _)| existing.name != rule.name);
+        rules.push((rule, false));
+    }
+
+    pub fn remove_rule(&self, name: &str) {
+        self.rules.lock().retain(|(rule, _)| rule.name != name);
+    }
+
+    pub fn rules(&self) -> Vec<SurveillanceRule> {
+        self.rules.lock().iter().map(|(rule, _)| rule.clone()).collect()
+    }
+
+    pub fn evaluate(&self) -> Vec<SurveillanceAlert> {
+        self.evaluate_at(now_millis())
+    }
+
+    // 评估所有规则，只对由正常转为触发的规则告警，持续超限期间不重复告警
+    pub fn evaluate_at(&self, now_ms: u64) -> Vec<SurveillanceAlert> {
+        let meters = self.meters.read();
+        let mut alerts = Vec::new();
+        for (rule, breached) in self.rules.lock().iter_mut() {
+            let Some(meter) = meters.get(&rule.symbol) else {
+                *breached = false;
+                continue;
+            };
+            let reading = meter.reading_at(now_ms, rule.window);
+            let observed = match rule.kind {
+                MeterKind::TradesPerSec => reading.trades_per_sec,
+                MeterKind::VolumePerSec => reading.volume_per_sec,
+            };
+            let breaching = observed > rule.threshold;
+            if breaching && !*breached {
+                alerts.push(SurveillanceAlert {
+                    rule: rule.name.clone(),
+                    symbol: rule.symbol.clone(),
+                    observed,
+                    threshold: rule.threshold,
+                });
+            }
+            *breached = breaching;
+        }
+        alerts
+    }
+}
+
+// 启动监控线程，按固定间隔评估规则并把告警发给 alerts；接收方关闭后线程退出
+pub fn spawn_surveillance_monitor(
+    surveillance: Arc<Surveillance>,
+    interval: Duration,
+    alerts: Sender<SurveillanceAlert>,
+) -> JoinHandle<()> {
+    thread::spawn(move || loop {
+        thread::sleep(interval);
+        for alert in surveillance.evaluate() {
+            if alerts.send(alert).is_err() {
+                return;
+            }
+        }
+    })
+}
+
+// 市场行为检测：引擎通过 with_surveillance_tap 把处理过的每个订单事件和成交发给检测线程，
+// 各检测器在引擎线程之外逐条观察事件，发现可疑行为时产生告警
+#[derive(Debug, Clone)]
+pub enum MarketEvent {
+    Order { user_id: u64, symbol: String, side: OrderType, price: u64, quantity: u64 },
+    Cancel { user_id: u64, symbol: String, order_id: u64 },
+    Amend { user_id: u64, symbol: String, order_id: u64, price: u64, quantity: u64 },
+    Trade(TradeNotification),
+}
+
+impl MarketEvent {
+    // 命令中的订单事件；OCO 订单是两条订单，篮子订单每条腿是一条订单，查询和管理命令没有订单事件
+    pub fn from_command(command: &EngineCommand) -> Vec<MarketEvent> {
+        let order = |request: &crate::protocol::NewOrderRequest| MarketEvent::Order {
+            user_id: request.user_id,
+            symbol: request.symbol.clone(),
+            side: request.order_type,
+            price: request.price,
+            quantity: request.quantity,
+        };
+        match command {
+            EngineCommand::NewOrder(request) | EngineCommand::ReduceOnlyOrder(request) => vec![order(request)],
+            EngineCommand::TimedOrder(request) => vec![order(&request.order)],
+            EngineCommand::OcoOrder(request) => vec![order(&request.first), order(&request.second)],
+            EngineCommand::BasketOrder(request) => request
+                .legs
+                .iter()
+                .map(|leg| MarketEvent::Order {
+                    user_id: request.user_id,
+                    symbol: leg.symbol.clone(),
+                    side: leg.order_type,
+                    price: leg.price,
+                    quantity: leg.quantity,
+                })
+                .collect(),
+            EngineCommand::CancelOrder(request) => vec![MarketEvent::Cancel {
+                user_id: request.user_id,
+                symbol: request.symbol.clone(),
+                order_id: request.order_id,
+            }],
+            EngineCommand::AmendOrder(request) => vec![MarketEvent::Amend {
+                user_id: request.user_id,
+                symbol: request.symbol.clone(),
+                order_id: request.order_id,
+                price: request.new_price,
+                quantity: request.new_quantity,
+            }],
+            EngineCommand::Request { command, .. }
+            | EngineCommand::Direct { command, .. }
+            | EngineCommand::Received { command, .. }
+            | EngineCommand::Traced { command, .. } => MarketEvent::from_command(command),
+            _ => Vec::new(),
+        }
+    }
+}
+
+// 检测器产生的告警
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MarketAlert {
+    pub detector: &'static str,
+    pub symbol: String,
+    // 涉及的用户
+    pub user_ids: Vec<u64>,
+    pub detail: String,
+}
+
+// 可插拔的检测器，在检测线程中按引擎处理的顺序观察每个事件，now_ms 为事件到达检测线程的时刻
+pub trait Detector: Send {
+    fn observe(&mut self, event: &MarketEvent, now_ms: u64) -> Option<MarketAlert>;
+}
+
+// 对敲：买卖双方是不同用户但属于同一实益拥有人。未登记的用户自成一个拥有人
+#[derive(Debug, Clone, Default)]
+pub struct WashTradeDetector {
+    owners: HashMap<u64, u64>,
+}
+
+impl WashTradeDetector {
+    pub fn new(owners: HashMap<u64, u64>) -> Self {
+        WashTradeDetector { owners }
+    }
+
+    fn owner(&self, user_id: u64) -> u64 {
+        self.owners.get(&user_id).copied().unwrap_or(user_id)
+    }
+
+    // 解析 "用户:实益拥有人,..."，例如 "1:100,2:100"
+    pub fn parse_owners(spec: &str) -> Result<HashMap<u64, u64>, String> {
+        spec.split(',')
+            .filter(|entry| !entry.trim().is_empty())
+            .map(|entry| {
+                let (user_id, owner) = entry.split_once(':').ok_or_else(|| format!("应为 用户:实益拥有人: {}", entry))?;
+                let parse = |value: &str| value.trim().parse::<u64>().map_err(|_| format!("无效的用户编号: {}", value));
+                Ok((parse(user_id)?, parse(owner)?))
+            })
+            .collect()
+    }
+}
+
+impl Detector for WashTradeDetector {
+    fn observe(&mut self, event: &MarketEvent, _now_ms: u64) -> Option<MarketAlert> {
+        let MarketEvent::Trade(trade) = event else { return None };
+        if trade.buyer_user_id == trade.seller_user_id || self.owner(trade.buyer_user_id) != self.owner(trade.seller_user_id) {
+            return None;
+        }
+        Some(MarketAlert {
+            detector: "wash_trade",
+            symbol: trade.symbol.to_string(),
+            user_ids: vec![trade.buyer_user_id, trade.seller_user_id],
+            detail: format!(
+                "trade_id={} owner={} price={} quantity={}",
+                trade.trade_id,
+                self.owner(trade.buyer_user_id),
+                trade.matched_price,
+                trade.matched_quantity
+            ),
+        })
+    }
+}
+
+// 自成交：引擎没有自成交保护，同一用户的新订单越过自己的对手方挂单时直接成交，表现为买卖双方是同一用户的成交
+#[derive(Debug, Clone, Default)]
+pub struct SelfCrossDetector;
+
+impl Detector for SelfCrossDetector {
+    fn observe(&mut self, event: &MarketEvent, _now_ms: u64) -> Option<MarketAlert> {
+        let MarketEvent::Trade(trade) = event else { return None };
+        if trade.buyer_user_id != trade.seller_user_id {
+            return None;
+        }
+        Some(MarketAlert {
+            detector: "self_cross",
+            symbol: trade.symbol.to_string(),
+            user_ids: vec![trade.buyer_user_id],
+            detail: format!("trade_id={} price={} quantity={}", trade.trade_id, trade.matched_price, trade.matched_quantity),
+        })
+    }
+}
+
+// 报单轰炸：同一用户在 window 内的下单、撤单和改单消息超过 max_messages 时告警，
+// 持续超限期间不重复告警，回落到上限以内后重新计算
+#[derive(Debug, Clone)]
+pub struct QuoteStuffingDetector {
+    max_messages: usize,
+    window_ms: u64,
+    // 每个用户窗口内消息的到达时刻，以及是否处于超限状态
+    users: HashMap<u64, (VecDeque<u64>, bool)>,
+}
+
+impl QuoteStuffingDetector {
+    pub fn new(max_messages: usize, window: Duration) -> Self {
+        QuoteStuffingDetector { max_messages, window_ms: window.as_millis() as u64, users: HashMap::new() }
+    }
+}
+
+impl Detector for QuoteStuffingDetector {
+    fn observe(&mut self, event: &MarketEvent, now_ms: u64) -> Option<MarketAlert> {
+        let (user_id, symbol) = match event {
+            MarketEvent::Order { user_id, symbol, .. }
+            | MarketEvent::Cancel { user_id, symbol, .. }
+            | MarketEvent::Amend { user_id, symbol, .. } => (*user_id, symbol),
+            MarketEvent::Trade(_) => return None,
+        };
+        let (arrivals, breached) = self.users.entry(user_id).or_default();
+        arrivals.push_back(now_ms);
+        while arrivals.front().is_some_and(|&arrival| now_ms.saturating_sub(arrival) >= self.window_ms) {
+            arrivals.pop_front();
+        }
+        let breaching = arrivals.len() > self.max_messages;
+        let alert = (breaching && !*breached).then(|| MarketAlert {
+            detector: "quote_stuffing",
+            symbol: symbol.clone(),
+            user_ids: vec![user_id],
+            detail: format!("messages={} window_ms={} max={}", arrivals.len(), self.window_ms, self.max_messages),
+        });
+        *breached = breaching;
+        alert
+    }
+}
+
+// 启动检测线程，返回交给引擎 with_surveillance_tap 的发送端。
+// 每条告警计入 surveillance_alerts 指标并发给 alerts；引擎退出（发送端全部关闭）后线程退出
+pub fn spawn_market_detectors(
+    mut detectors: Vec<Box<dyn Detector>>,
+    metrics: Arc<EngineMetrics>,
+    alerts: Sender<MarketAlert>,
+) -> (Sender<MarketEvent>, JoinHandle<()>) {
+    let (sender, receiver): (Sender<MarketEvent>, Receiver<MarketEvent>) = std_mpsc::channel();
+    let handle = thread::spawn(move || {
+        for event in receiver {
+            let now_ms = now_millis();
+            for detector in detectors.iter_mut() {
+                if let Some(alert) = detector.observe(&event, now_ms) {
+                    metrics.surveillance_alerts.fetch_add(1, Ordering::Relaxed);
+                    // 没有人接收告警时只计入指标
+                    let _ = alerts.send(alert);
+                }
+            }
+        }
+    });
+    (sender, handle)
+}
+
+fn now_millis() -> u64 {
+    now_nanos() / 1_000_000
+}
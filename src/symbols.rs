@@ -0,0 +1,158 @@
+// 合约名驻留：每个合约名只保存一份 Arc<str>，并分配一个数字 ID。
+// 订单簿和成交回报共享驻留的合约名，生成成交时只增加引用计数而不复制字符串。
+// 池中之外已没有引用（没有订单簿、也没有尚未发出的成交）的合约可以被回收，回收的 ID 会被复用
+use std::collections::HashMap;
+use std::sync::Arc;
+
+pub type SymbolId = u32;
+
+// 驻留统计：hits/misses 为驻留时合约已在池中/需要新增的次数，evictions 为回收的合约数
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct SymbolPoolStats {
+    pub size: usize,
+    pub capacity_limit: Option<usize>,
+    pub hits: u64,
+    pub misses: u64,
+    pub evictions: u64,
+}
+
+#[derive(Debug)]
+struct Entry {
+    name: Arc<str>,
+    // 最近一次驻留的逻辑时刻，池满时回收其中最久未使用的合约
+    last_used: u64,
+}
+
+#[derive(Debug, Default)]
+pub struct SymbolPool {
+    ids: HashMap<Arc<str>, SymbolId>,
+    // 按 ID 排列，已回收的 ID 为 None
+    entries: Vec<Option<Entry>>,
+    free_ids: Vec<SymbolId>,
+    clock: u64,
+    // 池中最多保存的合约数，None 为不限
+    capacity_limit: Option<usize>,
+    stats: SymbolPoolStats,
+}
+
+impl SymbolPool {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn set_capacity_limit(&mut self, limit: Option<usize>) {
+        self.capacity_limit = limit;
+    }
+
+    // 返回合约的 ID，第一次出现的合约分配 ID。池已满时先回收最久未使用的空闲合约，
+    // 仍没有空位则返回 None
+    pub fn intern(&mut self, symbol: &str) -> Option<SymbolId> {
+        self.clock += 1;
+        if let Some(&id) = self.ids.get(symbol) {
+            self.stats.hits += 1;
+            self.entries[id as usize].as_mut().expect("已驻留的合约").last_used = self.clock;
+            return Some(id);
+        }
+        self.stats.misses += 1;
+        if self.capacity_limit.is_some_and(|limit| self.ids.len() >= limit) && !self.evict_least_recently_used() {
+            return None;
+        }
+        let name: Arc<str> = Arc::from(symbol);
+        let entry = Entry { name: name.clone(), last_used: self.clock };
+        let id = match self.free_ids.pop() {
+            Some(id) => {
+                self.entries[id as usize] = Some(entry);
+                id
+            }
+            None => {
+                let id = SymbolId::try_from(self.entries.len()).expect("合约数超过 u32 的上限");
+                self.entries.push(Some(entry));
+                id
+            }
+        };
+        self.ids.insert(name, id);
+        Some(id)
+    }
+
+    pub fn id(&self, symbol: &str) -> Option<SymbolId> {
+        self.ids.get(symbol).copied()
+    }
+
+    pub fn name(&self, id: SymbolId) -> Option<&Arc<str>> {
+        self.entries.get(id as usize)?.as_ref().map(|entry| &entry.name)
+    }
+
+    // 驻留合约名并返回共享的那一份
+    pub fn intern_name(&mut self, symbol: &str) -> Option<Arc<str>> {
+        let id = self.intern(symbol)?;
+        self.name(id).cloned()
+    }
+
+    pub fn len(&self) -> usize {
+        self.ids.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.ids.is_empty()
+    }
+
+    // 全部 (ID, 合约名)，按 ID 升序
+    pub fn iter(&self) -> impl Iterator<Item = (SymbolId, &str)> {
+        self.entries
+            .iter()
+            .enumerate()
+            .filter_map(|(id, entry)| entry.as_ref().map(|entry| (id as SymbolId, &*entry.name)))
+    }
+
+    pub fn stats(&self) -> SymbolPoolStats {
+        SymbolPoolStats { size: self.ids.len(), capacity_limit: self.capacity_limit, ..self.stats }
+    }
+
+    // 回收全部空闲的合约并释放多余容量，返回回收的合约数
+    pub fn shrink(&mut self) -> usize {
+        let idle: Vec<SymbolId> = self.iter().map(|(id, _)| id).filter(|&id| self.is_idle(id)).collect();
+        for &id in &idle {
+            self.evict(id);
+        }
+        // 末尾已回收的 ID 直接截掉，不再留在空闲列表中
+        while matches!(self.entries.last(), Some(None)) {
+            self.entries.pop();
+        }
+        let len = self.entries.len();
+        self.free_ids.retain(|&id| (id as usize) < len);
+        self.entries.shrink_to_fit();
+        self.free_ids.shrink_to_fit();
+        self.ids.shrink_to_fit();
+        idle.len()
+    }
+
+    // 池之外已没有引用：只剩 entries 和 ids 中的两份
+    fn is_idle(&self, id: SymbolId) -> bool {
+        self.entries[id as usize].as_ref().is_some_and(|entry| Arc::strong_count(&entry.name) == 2)
+    }
+
+    fn evict_least_recently_used(&mut self) -> bool {
+        let candidate = self
+            .entries
+            .iter()
+            .enumerate()
+            .filter(|&(id, _)| self.is_idle(id as SymbolId))
+            .filter_map(|(id, entry)| entry.as_ref().map(|entry| (entry.last_used, id as SymbolId)))
+            .min();
+        match candidate {
+            Some((_, id)) => {
+                self.evict(id);
+                true
+            }
+            None => false,
+        }
+    }
+
+    fn evict(&mut self, id: SymbolId) {
+        if let Some(entry) = self.entries[id as usize].take() {
+            self.ids.remove(&entry.name);
+            self.free_ids.push(id);
+            self.stats.evictions += 1;
+        }
+    }
+}
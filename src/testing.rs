@@ -0,0 +1,309 @@
+// 确定性仿真测试工具：在当前线程逐条驱动撮合引擎，使用顺序订单号和逻辑时钟，
+// 相同的命令序列（或相同种子生成的随机序列）总是得到逐字节相同的输出记录，
+// 可以与保存的 golden 文件比对，让复杂的撮合场景可以稳定复现
+use crate::engine::{EngineCommand, EngineOutput, MatchingEngine};
+use crate::protocol::{
+    AmendOrderRequest, CancelOrderRequest, CancelStatus, DepthSnapshot, NewOrderRequest, OrderStatus, OrderType,
+};
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use std::collections::BTreeMap;
+use std::fmt::Write as _;
+use std::path::Path;
+use std::sync::mpsc as std_mpsc;
+use tokio::sync::mpsc;
+
+// 设置该环境变量时 assert_golden 会用实际输出覆盖 golden 文件
+pub const UPDATE_GOLDEN_ENV: &str = "UPDATE_GOLDEN";
+
+// 仿真中的一笔挂单
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SimOrder {
+    pub order_id: u64,
+    pub user_id: u64,
+    pub symbol: String,
+}
+
+pub struct Simulation {
+    engine: MatchingEngine,
+    outputs: mpsc::UnboundedReceiver<EngineOutput>,
+    // 保持命令通道打开；仿真直接调用 handle_command，不经过该通道
+    _commands: mpsc::UnboundedSender<EngineCommand>,
+    // 按订单号排序的挂单，随机序列从中选择撤单和改单的目标
+    resting: BTreeMap<u64, SimOrder>,
+    transcript: String,
+    steps: usize,
+}
+
+impl Default for Simulation {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Simulation {
+    pub fn new() -> Self {
+        Self::with_engine(|engine| engine)
+    }
+
+    // configure 可以为引擎设置最小变动价位、分配算法等；逻辑时钟在其后启用
+    pub fn with_engine(configure: impl FnOnce(MatchingEngine) -> MatchingEngine) -> Self {
+        let (commands, command_receiver) = mpsc::unbounded_channel();
+        let (output_sender, outputs) = mpsc::unbounded_channel();
+        let engine = configure(MatchingEngine::new(command_receiver, output_sender)).with_logical_clock();
+        Simulation {
+            engine,
+            outputs,
+            _commands: commands,
+            resting: BTreeMap::new(),
+            transcript: String::new(),
+            steps: 0,
+        }
+    }
+
+    // 执行一条命令，返回它产生的全部输出，并追加到输出记录中
+    pub fn execute(&mut self, command: EngineCommand) -> Vec<EngineOutput> {
+        self.steps += 1;
+        let _ = writeln!(self.transcript, "> #{} {}", self.steps, describe(&command));
+        let context = match &command {
+            EngineCommand::NewOrder(request) => Some((request.user_id, request.symbol.clone(), None)),
+            EngineCommand::TimedOrder(request) => Some((request.order.user_id, request.order.symbol.clone(), None)),
+            EngineCommand::ReduceOnlyOrder(request) => Some((request.user_id, request.symbol.clone(), None)),
+            EngineCommand::AmendOrder(request) => {
+                Some((request.user_id, request.symbol.clone(), Some(request.order_id)))
+            }
+            _ => None,
+        };
+
+        self.engine.handle_command(command);
+        let mut outputs = Vec::new();
+        while let Ok(output) = self.outputs.try_recv() {
+            let _ = writeln!(self.transcript, "  {:?}", output);
+            outputs.push(output);
+        }
+        self.track_resting(context, &outputs);
+        outputs
+    }
+
+    fn track_resting(&mut self, context: Option<(u64, String, Option<u64>)>, outputs: &[EngineOutput]) {
+        if let Some((user_id, symbol, amended)) = context {
+            let rejected = outputs
+                .iter()
+                .any(|output| matches!(output, EngineOutput::Reject(reject) if reject.user_id == user_id));
+            // 改单成功时原订单要么被原地修改（随后以同一订单号确认），要么被替换
+            if let (Some(order_id), false) = (amended, rejected) {
+                self.resting.remove(&order_id);
+            }
+            for output in outputs {
+                if let EngineOutput::Confirmation(confirmation) = output {
+                    if confirmation.user_id == user_id {
+                        let order = SimOrder { order_id: confirmation.order_id, user_id, symbol: symbol.clone() };
+                        self.resting.insert(confirmation.order_id, order);
+                    }
+                }
+            }
+        }
+        for output in outputs {
+            match output {
+                EngineOutput::ExecutionReport(report)
+                    if matches!(report.status, OrderStatus::Filled | OrderStatus::Expired | OrderStatus::Restated | OrderStatus::Cancelled)
+                        && report.leaves_quantity == 0 =>
+                {
+                    self.resting.remove(&report.order_id);
+                }
+                EngineOutput::CancelAck(ack)
+                    if matches!(
+                        ack.status,
+                        CancelStatus::Cancelled
+                            | CancelStatus::Expired
+                            | CancelStatus::LinkedOrderFilled
+                            | CancelStatus::PriceBandBreach
+                    ) =>
+                {
+                    self.resting.remove(&ack.order_id);
+                }
+                _ => {}
+            }
+        }
+    }
+
+    pub fn resting_orders(&self) -> impl Iterator<Item = &SimOrder> {
+        self.resting.values()
+    }
+
+    // 某个合约的完整深度，不写入输出记录
+    pub fn depth(&mut self, symbol: &str) -> Option<DepthSnapshot> {
+        let (reply_tx, reply_rx) = std_mpsc::channel();
+        self.engine.handle_command(EngineCommand::SnapshotDepth { depth: usize::MAX, largest_orders: 0, reply: reply_tx });
+        reply_rx.try_iter().find(|snapshot| snapshot.symbol == symbol)
+    }
+
+    // 在输出记录末尾按合约名顺序追加各合约的最终深度，返回完整记录
+    pub fn finish(&mut self) -> &str {
+        let (reply_tx, reply_rx) = std_mpsc::channel();
+        self.engine.handle_command(EngineCommand::SnapshotDepth { depth: usize::MAX, largest_orders: 0, reply: reply_tx });
+        // 引擎内合约按哈希表顺序遍历，排序后才是确定的
+        let mut snapshots: Vec<DepthSnapshot> = reply_rx.try_iter().collect();
+        snapshots.sort_by(|a, b| a.symbol.cmp(&b.symbol));
+        for snapshot in snapshots {
+            let _ = writeln!(self.transcript, "= {}", snapshot.symbol);
+            for level in &snapshot.asks {
+                let _ = writeln!(self.transcript, "  ask {} x {} ({})", level.price, level.quantity, level.order_count);
+            }
+            for level in &snapshot.bids {
+                let _ = writeln!(self.transcript, "  bid {} x {} ({})", level.price, level.quantity, level.order_count);
+            }
+        }
+        &self.transcript
+    }
+
+    pub fn transcript(&self) -> &str {
+        &self.transcript
+    }
+}
+
+fn describe(command: &EngineCommand) -> String {
+    match command {
+        EngineCommand::NewOrder(request) => format!("{:?}", request),
+        EngineCommand::CancelOrder(request) => format!("{:?}", request),
+        EngineCommand::AmendOrder(request) => format!("{:?}", request),
+        EngineCommand::BlockTrade(request) => format!("{:?}", request),
+        EngineCommand::QueryPosition(query) => format!("{:?}", query),
+        EngineCommand::EstimateFill(request) => format!("{:?}", request),
+        EngineCommand::QueryMarketData(query) => format!("{:?}", query),
+        EngineCommand::SpreadOrder(request) => format!("{:?}", request),
+        EngineCommand::BasketOrder(request) => format!("{:?}", request),
+        EngineCommand::OcoOrder(request) => format!("{:?}", request),
+        EngineCommand::TimedOrder(request) => format!("{:?}", request),
+        EngineCommand::ReduceOnlyOrder(request) => format!("ReduceOnly {:?}", request),
+        EngineCommand::CancelUserOrders(user_id) => format!("CancelUserOrders({})", user_id),
+        EngineCommand::SnapshotDepth { depth, .. } => format!("SnapshotDepth({})", depth),
+        EngineCommand::Control(_) => "Control".to_string(),
+        EngineCommand::Request { request_id, command, .. } => format!("Request({}) {}", request_id, describe(command)),
+        EngineCommand::Direct { command, .. } => describe(command),
+        EngineCommand::Received { received_at_ns, command } => format!("Received({}) {}", received_at_ns, describe(command)),
+        EngineCommand::Traced { command, .. } => format!("Traced {}", describe(command)),
+    }
+}
+
+// 随机订单流的参数
+#[derive(Debug, Clone)]
+pub struct RandomFlowConfig {
+    pub symbols: Vec<String>,
+    // 用户 ID 取 1..=users
+    pub users: u64,
+    pub mid_price: u64,
+    // 报价距中间价的最大偏移，买卖报价区间重叠时会产生成交
+    pub spread: u64,
+    pub max_quantity: u64,
+    // 有挂单时发送撤单、改单的概率
+    pub cancel_ratio: f64,
+    pub amend_ratio: f64,
+}
+
+impl Default for RandomFlowConfig {
+    fn default() -> Self {
+        RandomFlowConfig {
+            symbols: vec!["SIM".to_string()],
+            users: 4,
+            mid_price: 1_000,
+            spread: 5,
+            max_quantity: 10,
+            cancel_ratio: 0.15,
+            amend_ratio: 0.1,
+        }
+    }
+}
+
+// 由种子确定的随机订单流
+pub struct RandomFlow {
+    config: RandomFlowConfig,
+    rng: StdRng,
+}
+
+impl RandomFlow {
+    pub fn new(seed: u64, config: RandomFlowConfig) -> Self {
+        RandomFlow { config, rng: StdRng::seed_from_u64(seed) }
+    }
+
+    // 根据仿真当前的挂单生成下一条命令
+    pub fn next_command(&mut self, simulation: &Simulation) -> EngineCommand {
+        let resting: Vec<&SimOrder> = simulation.resting_orders().collect();
+        let roll: f64 = self.rng.gen();
+        if !resting.is_empty() && roll < self.config.cancel_ratio + self.config.amend_ratio {
+            let order = resting[self.rng.gen_range(0..resting.len())];
+            if roll < self.config.cancel_ratio {
+                return EngineCommand::CancelOrder(CancelOrderRequest {
+                    user_id: order.user_id,
+                    symbol: order.symbol.clone(),
+                    order_id: order.order_id,
+                });
+            }
+            let (_, price) = self.quote();
+            return EngineCommand::AmendOrder(AmendOrderRequest {
+                user_id: order.user_id,
+                symbol: order.symbol.clone(),
+                order_id: order.order_id,
+                new_price: price,
+                new_quantity: self.rng.gen_range(1..=self.config.max_quantity),
+            });
+        }
+
+        let (order_type, price) = self.quote();
+        EngineCommand::NewOrder(NewOrderRequest {
+            user_id: self.rng.gen_range(1..=self.config.users),
+            symbol: self.config.symbols[self.rng.gen_range(0..self.config.symbols.len())].clone(),
+            order_type,
+            price,
+            quantity: self.rng.gen_range(1..=self.config.max_quantity),
+        })
+    }
+
+    fn quote(&mut self) -> (OrderType, u64) {
+        let offset = self.rng.gen_range(0..=self.config.spread);
+        // 报价偏向对手方一侧，使买卖区间重叠
+        if self.rng.gen::<bool>() {
+            (OrderType::Buy, self.config.mid_price + self.config.spread / 2 - offset)
+        } else {
+            (OrderType::Sell, self.config.mid_price - self.config.spread / 2 + offset)
+        }
+    }
+}
+
+// 用种子生成 steps 条随机命令并依次执行
+pub fn run_random(seed: u64, steps: usize, config: RandomFlowConfig) -> Simulation {
+    let mut simulation = Simulation::new();
+    let mut flow = RandomFlow::new(seed, config);
+    for _ in 0..steps {
+        let command = flow.next_command(&simulation);
+        simulation.execute(command);
+    }
+    simulation
+}
+
+// 与 golden 文件比对；设置 UPDATE_GOLDEN 环境变量时改为写入实际输出
+pub fn assert_golden(path: impl AsRef<Path>, actual: &str) {
+    let path = path.as_ref();
+    if std::env::var_os(UPDATE_GOLDEN_ENV).is_some() {
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent).expect("无法创建 golden 目录");
+        }
+        std::fs::write(path, actual).expect("无法写入 golden 文件");
+        return;
+    }
+    let expected = std::fs::read_to_string(path).unwrap_or_else(|e| {
+        panic!("无法读取 golden 文件 {}: {}（设置 {}=1 生成）", path.display(), e, UPDATE_GOLDEN_ENV)
+    });
+    if expected == actual {
+        return;
+    }
+    let mismatch = expected.lines().zip(actual.lines()).position(|(e, a)| e != a);
+    let line = mismatch.unwrap_or_else(|| expected.lines().count().min(actual.lines().count()));
+    panic!(
+        "输出与 golden 文件 {} 不一致，第 {} 行:\n  期望: {}\n  实际: {}",
+        path.display(),
+        line + 1,
+        expected.lines().nth(line).unwrap_or("<文件结束>"),
+        actual.lines().nth(line).unwrap_or("<输出结束>"),
+    );
+}
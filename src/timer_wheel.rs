@@ -0,0 +1,121 @@
+// 分层时间轮：定时器按到期时间（毫秒）挂在不同精度的槽中，插入为 O(1)；
+// 推进时钟时只处理到达边界的槽，远期定时器随时间逐层下放到更精细的层。
+// 每层 64 个槽，第 n 层每槽跨度 64^n 毫秒，六层覆盖约两年。
+// 定时器不支持删除，由调用方在触发时确认对应的对象是否仍然有效（惰性删除）
+const LEVELS: usize = 6;
+const SLOT_BITS: u32 = 6;
+const SLOTS: usize = 1 << SLOT_BITS;
+
+pub struct TimerWheel<T> {
+    // levels[层][槽] 中保存 (到期时间, 定时器)
+    levels: Vec<Vec<Vec<(u64, T)>>>,
+    // 各层非空槽的位图，推进时据此整段跳过没有定时器的时间
+    occupied: [u64; LEVELS],
+    // 已经推进到的时刻
+    now: u64,
+    // 插入时已经到期的定时器，在下一次推进时触发
+    due: Vec<T>,
+    len: usize,
+}
+
+impl<T> TimerWheel<T> {
+    pub fn new(now: u64) -> Self {
+        TimerWheel {
+            levels: (0..LEVELS).map(|_| (0..SLOTS).map(|_| Vec::new()).collect()).collect(),
+            occupied: [0; LEVELS],
+            now,
+            due: Vec::new(),
+            len: 0,
+        }
+    }
+
+    pub fn now(&self) -> u64 {
+        self.now
+    }
+
+    // 尚未触发的定时器数量
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    // 尚未触发的定时器 (到期时间, 定时器)；插入时已经到期的以当前时刻作为到期时间
+    pub fn pending(&self) -> impl Iterator<Item = (u64, &T)> {
+        let due = self.due.iter().map(|item| (self.now, item));
+        let placed = self.levels.iter().flatten().flatten().map(|(deadline, item)| (*deadline, item));
+        due.chain(placed)
+    }
+
+    pub fn insert(&mut self, deadline: u64, item: T) {
+        self.len += 1;
+        if deadline <= self.now {
+            self.due.push(item);
+        } else {
+            self.place(deadline, item);
+        }
+    }
+
+    // 推进时钟到 now，按到期时间先后返回期间到期的定时器；now 早于当前时刻时只返回已到期的
+    pub fn advance(&mut self, now: u64) -> Vec<T> {
+        let mut expired = std::mem::take(&mut self.due);
+        while self.now < now {
+            // 最低的若干层都为空时，下一个可能有定时器到期的时刻是更高一层的下一个槽边界
+            let empty_levels = self.occupied.iter().take_while(|&&bits| bits == 0).count();
+            if empty_levels == LEVELS {
+                self.now = now;
+                break;
+            }
+            let shift = SLOT_BITS * empty_levels as u32;
+            let next = ((self.now >> shift) + 1) << shift;
+            if next > now {
+                self.now = now;
+                break;
+            }
+            self.now = next;
+            // 先由高到低下放到达边界的槽，再触发第 0 层的当前槽
+            for level in (1..LEVELS).rev() {
+                let shift = SLOT_BITS * level as u32;
+                if self.now & ((1 << shift) - 1) == 0 {
+                    let slot = (self.now >> shift) as usize & (SLOTS - 1);
+                    for (deadline, item) in self.take_slot(level, slot) {
+                        if deadline <= self.now {
+                            expired.push(item);
+                        } else {
+                            self.place(deadline, item);
+                        }
+                    }
+                }
+            }
+            let slot = self.now as usize & (SLOTS - 1);
+            expired.extend(self.take_slot(0, slot).into_iter().map(|(_, item)| item));
+        }
+        self.len -= expired.len();
+        expired
+    }
+
+    // 放入槽距离不超过一圈的最低层；超出最高层范围的定时器放在最高层，下放时重新计算
+    fn place(&mut self, deadline: u64, item: T) {
+        let mut level = 0;
+        while level + 1 < LEVELS {
+            let shift = SLOT_BITS * level as u32;
+            if (deadline >> shift) - (self.now >> shift) < SLOTS as u64 {
+                break;
+            }
+            level += 1;
+        }
+        let slot = (deadline >> (SLOT_BITS * level as u32)) as usize & (SLOTS - 1);
+        self.levels[level][slot].push((deadline, item));
+        self.occupied[level] |= 1 << slot;
+    }
+
+    fn take_slot(&mut self, level: usize, slot: usize) -> Vec<(u64, T)> {
+        if self.occupied[level] & (1 << slot) == 0 {
+            return Vec::new();
+        }
+        self.occupied[level] &= !(1 << slot);
+        std::mem::take(&mut self.levels[level][slot])
+    }
+}
@@ -0,0 +1,102 @@
+// 确定性校验：把恢复目录中的命令日志从头送入一个新引擎重新撮合，逐条命令比对产生的成交
+// 与输出日志（见 recovery::OutputRecord）中记录的成交。成交号、价格、数量、双方和先后顺序都必须一致，
+// 任何差异都说明撮合依赖了命令之外的输入，例如系统时间或哈希表的遍历顺序。
+// 系统时钟的成交时间戳每次运行都不同，默认不比较；记录时使用逻辑时钟的引擎可以要求时间戳也一致。
+// 校验用默认配置的引擎，记录时的引擎不能有影响撮合的额外配置（合约参考数据、熔断等）
+use crate::engine::{EngineOutput, MatchingEngine};
+use crate::protocol::TradeNotification;
+use crate::recovery::{self, RecoveryError};
+use std::collections::BTreeMap;
+use std::path::Path;
+use tokio::sync::mpsc;
+
+#[derive(Debug, Clone, Copy, Default)]
+pub struct VerifyConfig {
+    // 记录时使用了逻辑时钟（MatchingEngine::with_logical_clock），时间戳也要一致
+    pub logical_clock: bool,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct VerifyReport {
+    // 重新撮合的命令数
+    pub commands: usize,
+    // 有输出记录、参与比对的命令数
+    pub verified: usize,
+    // 没有输出记录的命令数：开启输出日志之前的命令，或崩溃时还没写出输出的命令
+    pub unrecorded: usize,
+    // 重新撮合产生的成交数
+    pub trades: usize,
+    // 与记录不一致的说明，为空表示完全一致
+    pub mismatches: Vec<String>,
+}
+
+impl VerifyReport {
+    pub fn is_consistent(&self) -> bool {
+        self.mismatches.is_empty()
+    }
+}
+
+pub fn verify(dir: &Path, config: VerifyConfig) -> Result<VerifyReport, RecoveryError> {
+    let commands = recovery::read_command_log(dir)?;
+    let mut recorded: BTreeMap<u64, Vec<TradeNotification>> =
+        recovery::read_output_log(dir)?.into_iter().map(|record| (record.seq, record.trades)).collect();
+
+    let (_command_sender, command_receiver) = mpsc::unbounded_channel();
+    let (output_sender, mut output_receiver) = mpsc::unbounded_channel();
+    let mut engine = MatchingEngine::new(command_receiver, output_sender);
+    if config.logical_clock {
+        engine = engine.with_logical_clock();
+    }
+
+    let mut report = VerifyReport::default();
+    for record in commands {
+        engine.apply_logged(record.command);
+        report.commands += 1;
+        let mut produced = Vec::new();
+        while let Ok(output) = output_receiver.try_recv() {
+            if let EngineOutput::Trade(trade) = output {
+                produced.push(trade);
+            }
+        }
+        report.trades += produced.len();
+        let Some(expected) = recorded.remove(&record.seq) else {
+            report.unrecorded += 1;
+            continue;
+        };
+        report.verified += 1;
+        let describe = |trades: &[TradeNotification]| -> Vec<String> {
+            trades.iter().map(|trade| describe_trade(trade, config.logical_clock)).collect()
+        };
+        let (expected, produced) = (describe(&expected), describe(&produced));
+        if expected != produced {
+            report.mismatches.push(format!("命令 #{}: 记录的成交 {:?}，重新撮合得到 {:?}", record.seq, expected, produced));
+        }
+    }
+    // 输出日志中有、命令日志中没有的序号
+    for seq in recorded.keys() {
+        report.mismatches.push(format!("命令 #{}: 有输出记录但命令日志中没有该命令", seq));
+    }
+    Ok(report)
+}
+
+// 成交中应当确定的全部字段
+fn describe_trade(trade: &TradeNotification, with_timestamp: bool) -> String {
+    let mut description = format!(
+        "#{} {} {}@{} 买方 {}/{} 卖方 {}/{}",
+        trade.trade_id,
+        trade.symbol,
+        trade.matched_quantity,
+        trade.matched_price,
+        trade.buyer_user_id,
+        trade.buyer_order_id,
+        trade.seller_user_id,
+        trade.seller_order_id
+    );
+    if trade.is_block_trade {
+        description.push_str(" 大宗");
+    }
+    if with_timestamp {
+        description.push_str(&format!(" t={}", trade.timestamp));
+    }
+    description
+}
@@ -0,0 +1,210 @@
+use crate::protocol::{
+    AmendOrderRequest, CancelOrderRequest, ClientMessage, NewOrderRequest, OrderStatus, OrderType, ServerMessage,
+};
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use std::collections::{HashMap, VecDeque};
+use std::time::{Duration, Instant};
+
+// 压测负载模型。通过 "key=value" 形式的参数配置，例如
+//   connections=16 rate=2000 symbols=BTC/USD,ETH/USD cancel=0.2 replace=0.1
+#[derive(Debug, Clone)]
+pub struct WorkloadProfile {
+    // 并发连接数，每个连接使用自己的用户 ID
+    pub connections: u32,
+    pub duration: Duration,
+    pub symbols: Vec<String>,
+    // 每个连接的平均下单速率（笔/秒），请求间隔服从指数分布（泊松到达）；为 0 时不限速
+    pub rate: f64,
+    // 各合约的初始中间价
+    pub mid_price: u64,
+    // 每笔请求前中间价随机游走的最大步长
+    pub volatility: u64,
+    // 报价距中间价的最大偏移
+    pub spread: u64,
+    pub max_quantity: u64,
+    // 有挂单时发送撤单、改单请求的概率
+    pub cancel_ratio: f64,
+    pub replace_ratio: f64,
+    // 随机数种子，便于复现同一组请求
+    pub seed: u64,
+}
+
+impl Default for WorkloadProfile {
+    fn default() -> Self {
+        WorkloadProfile {
+            connections: 8,
+            duration: Duration::from_secs(10),
+            symbols: vec!["BTC/USD".to_string()],
+            rate: 0.0,
+            mid_price: 50_000,
+            volatility: 1,
+            spread: 10,
+            max_quantity: 5,
+            cancel_ratio: 0.0,
+            replace_ratio: 0.0,
+            seed: 0,
+        }
+    }
+}
+
+impl WorkloadProfile {
+    // 在默认配置上应用 "key=value" 参数
+    pub fn parse<'a>(args: impl IntoIterator<Item = &'a str>) -> Result<Self, String> {
+        let mut profile = WorkloadProfile::default();
+        for arg in args {
+            let (key, value) = arg.split_once('=').ok_or_else(|| format!("缺少 '=': {}", arg))?;
+            let invalid = || format!("无效的参数值: {}", arg);
+            match key {
+                "connections" => profile.connections = value.parse().map_err(|_| invalid())?,
+                "duration" => profile.duration = Duration::from_secs_f64(value.parse().map_err(|_| invalid())?),
+                "symbols" => profile.symbols = value.split(',').map(str::to_string).collect(),
+                "rate" => profile.rate = value.parse().map_err(|_| invalid())?,
+                "mid" => profile.mid_price = value.parse().map_err(|_| invalid())?,
+                "volatility" => profile.volatility = value.parse().map_err(|_| invalid())?,
+                "spread" => profile.spread = value.parse().map_err(|_| invalid())?,
+                "max_quantity" => profile.max_quantity = value.parse().map_err(|_| invalid())?,
+                "cancel" => profile.cancel_ratio = value.parse().map_err(|_| invalid())?,
+                "replace" => profile.replace_ratio = value.parse().map_err(|_| invalid())?,
+                "seed" => profile.seed = value.parse().map_err(|_| invalid())?,
+                _ => return Err(format!("未知参数: {}", key)),
+            }
+        }
+        if profile.symbols.is_empty() || profile.symbols.iter().any(String::is_empty) {
+            return Err("至少需要一个合约".to_string());
+        }
+        if profile.max_quantity == 0 || profile.mid_price <= profile.spread {
+            return Err("数量上限必须大于 0，中间价必须大于报价偏移".to_string());
+        }
+        if profile.cancel_ratio < 0.0 || profile.replace_ratio < 0.0 || profile.cancel_ratio + profile.replace_ratio > 1.0 {
+            return Err("撤单和改单比例之和必须在 [0, 1] 之间".to_string());
+        }
+        Ok(profile)
+    }
+}
+
+// 单个连接的请求生成器，同时根据服务器回报跟踪挂单并计算应答延迟。
+// 引擎按连接上的请求顺序处理，每笔下单和改单都会得到一条确认或拒绝，
+// 因此用先进先出队列把确认对应回请求
+pub struct WorkloadGenerator {
+    profile: WorkloadProfile,
+    user_id: u64,
+    rng: StdRng,
+    // 与 profile.symbols 一一对应的当前中间价
+    mids: Vec<u64>,
+    // 已发送、尚未确认的下单和改单：(合约, 发送时间)
+    pending: VecDeque<(usize, Instant)>,
+    // 已发送、尚未回报的撤单
+    pending_cancels: HashMap<u64, Instant>,
+    // 已确认的挂单：(订单号, 合约)
+    open_orders: Vec<(u64, usize)>,
+}
+
+impl WorkloadGenerator {
+    pub fn new(profile: WorkloadProfile, user_id: u64) -> Self {
+        let rng = StdRng::seed_from_u64(profile.seed ^ user_id);
+        let mids = vec![profile.mid_price; profile.symbols.len()];
+        WorkloadGenerator {
+            profile,
+            user_id,
+            rng,
+            mids,
+            pending: VecDeque::new(),
+            pending_cancels: HashMap::new(),
+            open_orders: Vec::new(),
+        }
+    }
+
+    pub fn open_orders(&self) -> usize {
+        self.open_orders.len()
+    }
+
+    // 到下一笔请求的等待时间
+    pub fn next_delay(&mut self) -> Duration {
+        if self.profile.rate <= 0.0 {
+            return Duration::ZERO;
+        }
+        // 指数分布的逆变换采样；1 - u 落在 (0, 1]，避免 ln(0)
+        let u: f64 = self.rng.gen();
+        Duration::from_secs_f64(-(1.0 - u).ln() / self.profile.rate)
+    }
+
+    pub fn next_message(&mut self, now: Instant) -> ClientMessage {
+        let roll: f64 = self.rng.gen();
+        if !self.open_orders.is_empty() && roll < self.profile.cancel_ratio + self.profile.replace_ratio {
+            let index = self.rng.gen_range(0..self.open_orders.len());
+            // 先从本地挂单中移除，避免在回报到达前重复撤单或改单
+            let (order_id, symbol) = self.open_orders.swap_remove(index);
+            if roll < self.profile.cancel_ratio {
+                self.pending_cancels.insert(order_id, now);
+                return ClientMessage::CancelOrder(CancelOrderRequest {
+                    user_id: self.user_id,
+                    symbol: self.profile.symbols[symbol].clone(),
+                    order_id,
+                });
+            }
+            let (_, price, quantity) = self.quote(symbol);
+            self.pending.push_back((symbol, now));
+            return ClientMessage::AmendOrder(AmendOrderRequest {
+                user_id: self.user_id,
+                symbol: self.profile.symbols[symbol].clone(),
+                order_id,
+                new_price: price,
+                new_quantity: quantity,
+            });
+        }
+
+        let symbol = self.rng.gen_range(0..self.profile.symbols.len());
+        let (order_type, price, quantity) = self.quote(symbol);
+        self.pending.push_back((symbol, now));
+        ClientMessage::NewOrder(NewOrderRequest {
+            user_id: self.user_id,
+            symbol: self.profile.symbols[symbol].clone(),
+            order_type,
+            price,
+            quantity,
+        })
+    }
+
+    // 中间价随机游走一步，然后在中间价两侧生成报价：买单不高于中间价，卖单不低于中间价
+    fn quote(&mut self, symbol: usize) -> (OrderType, u64, u64) {
+        let volatility = self.profile.volatility as i64;
+        let step = self.rng.gen_range(-volatility..=volatility);
+        let floor = self.profile.spread + 1;
+        self.mids[symbol] = self.mids[symbol].saturating_add_signed(step).max(floor);
+
+        let mid = self.mids[symbol];
+        let offset = self.rng.gen_range(0..=self.profile.spread);
+        let quantity = self.rng.gen_range(1..=self.profile.max_quantity);
+        if self.rng.gen::<bool>() {
+            (OrderType::Buy, mid - offset, quantity)
+        } else {
+            (OrderType::Sell, mid + offset, quantity)
+        }
+    }
+
+    // 处理一条服务器回报，返回本连接某个请求从发送到得到应答的延迟
+    pub fn on_server_message(&mut self, message: &ServerMessage, now: Instant) -> Option<Duration> {
+        match message {
+            ServerMessage::Confirmation(confirmation) if confirmation.user_id == self.user_id => {
+                let (symbol, sent) = self.pending.pop_front()?;
+                self.open_orders.push((confirmation.order_id, symbol));
+                Some(now - sent)
+            }
+            ServerMessage::Reject(reject) if reject.user_id == self.user_id => {
+                let (_, sent) = self.pending.pop_front()?;
+                Some(now - sent)
+            }
+            ServerMessage::CancelAck(ack) if ack.user_id == self.user_id => {
+                self.pending_cancels.remove(&ack.order_id).map(|sent| now - sent)
+            }
+            ServerMessage::ExecutionReport(report)
+                if report.user_id == self.user_id && report.status == OrderStatus::Filled =>
+            {
+                self.open_orders.retain(|&(order_id, _)| order_id != report.order_id);
+                None
+            }
+            _ => None,
+        }
+    }
+}
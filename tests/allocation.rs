@@ -0,0 +1,74 @@
+use matching_engine::allocation::{allocate, AllocationPolicy};
+use matching_engine::engine::{ControlCommand, EngineCommand, EngineOutput, MatchingEngine};
+use matching_engine::orderbook::OrderBook;
+use matching_engine::protocol::{NewOrderRequest, OrderType};
+use tokio::sync::mpsc;
+
+fn order(user_id: u64, order_type: OrderType, price: u64, quantity: u64) -> NewOrderRequest {
+    NewOrderRequest {
+        user_id,
+        symbol: "ES".to_string(),
+        order_type,
+        price,
+        quantity,
+    }
+}
+
+#[test]
+fn test_allocate() {
+    let resting = [10, 30, 60];
+
+    assert_eq!(allocate(AllocationPolicy::Fifo, &resting, 25), vec![10, 15, 0]);
+    // 按 10%/30%/60% 分配 50 手：5/15/30
+    assert_eq!(allocate(AllocationPolicy::ProRata, &resting, 50), vec![5, 15, 30]);
+    // 7 手按比例取整为 0/2/4，余下 1 手按时间顺序分给第一个挂单
+    assert_eq!(allocate(AllocationPolicy::ProRata, &resting, 7), vec![1, 2, 4]);
+    // 最早的挂单先成交 10 手，剩余 18 手在 30/60 之间按比例分配为 6/12
+    assert_eq!(allocate(AllocationPolicy::TopOrderProRata, &resting, 28), vec![10, 6, 12]);
+    // 主动数量超过层级总量时全部成交
+    for policy in [AllocationPolicy::Fifo, AllocationPolicy::ProRata, AllocationPolicy::TopOrderProRata] {
+        assert_eq!(allocate(policy, &resting, 500), vec![10, 30, 60]);
+    }
+}
+
+#[test]
+fn test_pro_rata_orderbook() {
+    let mut book = OrderBook::new();
+    book.set_allocation_policy(AllocationPolicy::ProRata);
+    book.match_order(order(1, OrderType::Sell, 100, 25)).unwrap();
+    book.match_order(order(2, OrderType::Sell, 100, 75)).unwrap();
+    book.match_order(order(3, OrderType::Sell, 101, 10)).unwrap();
+
+    let (trades, confirmation) = book.match_order(order(4, OrderType::Buy, 100, 40)).unwrap();
+    assert!(confirmation.is_none());
+    let fills: Vec<(u64, u64)> = trades.iter().map(|t| (t.seller_user_id, t.matched_quantity)).collect();
+    assert_eq!(fills, vec![(1, 10), (2, 30)]);
+
+    let (_, asks) = book.depth(10);
+    assert_eq!((asks[0].price, asks[0].quantity, asks[0].order_count), (100, 60, 2));
+}
+
+#[test]
+fn test_engine_allocation_policy_per_symbol() {
+    let (commands, command_receiver) = mpsc::unbounded_channel();
+    let (output_sender, mut outputs) = mpsc::unbounded_channel();
+    let engine_thread = std::thread::spawn(move || {
+        MatchingEngine::new(command_receiver, output_sender)
+            .with_allocation_policy("ES", AllocationPolicy::ProRata)
+            .run();
+    });
+
+    commands.send(EngineCommand::NewOrder(order(1, OrderType::Sell, 100, 10))).unwrap();
+    commands.send(EngineCommand::NewOrder(order(2, OrderType::Sell, 100, 10))).unwrap();
+    commands.send(EngineCommand::NewOrder(order(3, OrderType::Buy, 100, 10))).unwrap();
+    commands.send(EngineCommand::Control(ControlCommand::Drain)).unwrap();
+    engine_thread.join().unwrap();
+
+    let mut fills = Vec::new();
+    while let Ok(output) = outputs.try_recv() {
+        if let EngineOutput::Trade(trade) = output {
+            fills.push((trade.seller_user_id, trade.matched_quantity));
+        }
+    }
+    assert_eq!(fills, vec![(1, 5), (2, 5)]);
+}
@@ -0,0 +1,89 @@
+use matching_engine::auction::{equilibrium_price, AuctionResult};
+use matching_engine::engine::{ControlCommand, EngineCommand, EngineOutput, MatchingEngine};
+use matching_engine::protocol::{DepthLevel, NewOrderRequest, OrderType, TradingPhase};
+use tokio::sync::mpsc;
+
+fn level(price: u64, quantity: u64) -> DepthLevel {
+    DepthLevel { price, quantity, order_count: 1 }
+}
+
+#[test]
+fn test_equilibrium_maximizes_volume() {
+    // 买: 102x10, 101x20, 100x30；卖: 99x15, 100x15, 101x20
+    let bids = [level(102, 10), level(101, 20), level(100, 30)];
+    let asks = [level(99, 15), level(100, 15), level(101, 20)];
+    // 价格 100: 需求 60, 供给 30 → 30；价格 101: 需求 30, 供给 50 → 30
+    // 成交量相同，101 的不平衡 (20) 小于 100 的不平衡 (30)
+    assert_eq!(equilibrium_price(&bids, &asks, None), Some(AuctionResult { price: 101, volume: 30 }));
+
+    // 没有交叉时不产生成交
+    assert_eq!(equilibrium_price(&[level(90, 10)], &[level(100, 10)], None), None);
+}
+
+#[tokio::test]
+async fn test_auction_crosses_at_single_price() {
+    let (commands, command_receiver) = mpsc::unbounded_channel();
+    let (output_sender, mut outputs) = mpsc::unbounded_channel();
+    std::thread::spawn(move || {
+        MatchingEngine::new(command_receiver, output_sender).run();
+    });
+
+    commands.send(EngineCommand::Control(ControlCommand::StartAuction("BTC/USD".to_string()))).unwrap();
+    assert!(matches!(outputs.recv().await, Some(EngineOutput::TradingStatus(_))));
+
+    let orders = [(1, OrderType::Buy, 105, 10), (2, OrderType::Sell, 95, 4), (3, OrderType::Sell, 100, 4)];
+    for (user_id, order_type, price, quantity) in orders {
+        commands.send(EngineCommand::NewOrder(NewOrderRequest {
+            user_id,
+            symbol: "BTC/USD".to_string(),
+            order_type,
+            price,
+            quantity,
+        })).unwrap();
+        // 竞价期间只挂单
+        assert!(matches!(outputs.recv().await, Some(EngineOutput::Confirmation(_))));
+    }
+
+    commands.send(EngineCommand::Control(ControlCommand::EndAuction("BTC/USD".to_string()))).unwrap();
+    let Some(EngineOutput::TradingStatus(status)) = outputs.recv().await else {
+        panic!("期望收到交易阶段变更通知");
+    };
+    assert_eq!(status.phase, TradingPhase::Continuous);
+    for _ in 0..2 {
+        let Some(EngineOutput::Trade(trade)) = outputs.recv().await else {
+            panic!("期望收到成交回报");
+        };
+        assert_eq!(trade.matched_price, 100);
+        assert_eq!(trade.matched_quantity, 4);
+    }
+}
+
+// 暂停中的合约不能直接进入集合竞价，随后的结束竞价也不会撮合或恢复交易
+#[test]
+fn test_start_auction_does_not_override_halt() {
+    let (_commands, command_receiver) = mpsc::unbounded_channel();
+    let (output_sender, mut outputs) = mpsc::unbounded_channel();
+    let mut engine = MatchingEngine::new(command_receiver, output_sender);
+    let control = |control| EngineCommand::Control(control);
+    let symbol = || "BTC/USD".to_string();
+
+    engine.handle_command(control(ControlCommand::Halt(symbol())));
+    for (user_id, order_type) in [(1, OrderType::Buy), (2, OrderType::Sell)] {
+        let request = NewOrderRequest { user_id, symbol: symbol(), order_type, price: 100, quantity: 1 };
+        engine.handle_command(EngineCommand::NewOrder(request));
+    }
+    engine.handle_command(control(ControlCommand::StartAuction(symbol())));
+    engine.handle_command(control(ControlCommand::EndAuction(symbol())));
+
+    let mut phases = Vec::new();
+    while let Ok(output) = outputs.try_recv() {
+        match output {
+            EngineOutput::TradingStatus(status) => phases.push(status.phase),
+            EngineOutput::Trade(_) => panic!("暂停期间不应撮合"),
+            _ => {}
+        }
+    }
+    assert_eq!(phases, vec![TradingPhase::Halted]);
+    assert_eq!(engine.checkpoint().markets[0].phase, TradingPhase::Halted);
+    assert_eq!(engine.checkpoint().orders.len(), 2);
+}
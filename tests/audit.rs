@@ -0,0 +1,109 @@
+use matching_engine::audit::{self, AuditConfig, AuditEvent, AuditTrail};
+use matching_engine::engine::{EngineCommand, MatchingEngine};
+use matching_engine::protocol::{CancelOrderRequest, NewOrderRequest, OrderType};
+use std::io::{Read, Write};
+use std::net::{IpAddr, TcpListener, TcpStream};
+use std::path::PathBuf;
+use tokio::sync::mpsc;
+
+fn temp_dir(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("audit-{}-{}", name, std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+    dir
+}
+
+fn new_order(user_id: u64, order_type: OrderType, price: u64, quantity: u64) -> EngineCommand {
+    EngineCommand::NewOrder(NewOrderRequest { user_id, symbol: "BTC/USD".to_string(), order_type, price, quantity })
+}
+
+// 像网络层一样记录请求和引擎输出，返回审计目录
+fn record_session(name: &str) -> PathBuf {
+    let dir = temp_dir(name);
+    let trail = AuditTrail::spawn(AuditConfig { dir: dir.clone() }).unwrap();
+    let (_command_sender, command_receiver) = mpsc::unbounded_channel();
+    let (output_sender, mut output_receiver) = mpsc::unbounded_channel();
+    let mut engine = MatchingEngine::new(command_receiver, output_sender);
+    let ip: IpAddr = "10.0.0.7".parse().unwrap();
+    let commands = [
+        (1, new_order(1, OrderType::Sell, 100, 10)),
+        (2, new_order(2, OrderType::Buy, 100, 4)),
+        (1, EngineCommand::CancelOrder(CancelOrderRequest { user_id: 1, symbol: "BTC/USD".to_string(), order_id: 1 })),
+    ];
+    for (session_id, command) in commands {
+        trail.record_request(session_id, Some(ip), &command);
+        engine.handle_command(command);
+        while let Ok(output) = output_receiver.try_recv() {
+            trail.record_output(&output);
+        }
+    }
+    trail.flush();
+    dir
+}
+
+#[test]
+fn test_order_lifecycle_is_queryable() {
+    let dir = record_session("lifecycle");
+    let events = audit::query_order(&dir, 1).unwrap();
+    let kinds: Vec<&str> = events.iter().map(|event| event.event.as_str()).collect();
+    assert_eq!(kinds, ["accepted", "execution", "cancel_request", "cancel_ack"]);
+    // 引擎产生的事件按用户最近提交请求的连接填写会话和 IP
+    assert!(events.iter().all(|event| event.user_id == 1 && event.session_id == Some(1)));
+    assert!(events.iter().all(|event| event.ip == Some("10.0.0.7".parse().unwrap())));
+    assert!(events.windows(2).all(|pair| pair[0].timestamp_us <= pair[1].timestamp_us));
+    assert_eq!((events[1].price, events[1].quantity), (Some(100), Some(4)));
+    assert_eq!(events[3].quantity, Some(6));
+
+    // 新订单请求在分配订单号之前没有订单号
+    let files: Vec<_> = std::fs::read_dir(&dir).unwrap().map(|entry| entry.unwrap().file_name()).collect();
+    assert_eq!(files.len(), 1);
+    let content = std::fs::read_to_string(dir.join(&files[0])).unwrap();
+    assert!(content.starts_with("timestamp_us,event,order_id,"));
+    assert_eq!(content.lines().filter(|line| line.split(',').nth(1) == Some("new_order")).count(), 2);
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn test_query_server_returns_csv() {
+    let dir = record_session("query");
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap();
+    audit::spawn_query_server(listener, dir.clone());
+
+    let get = |path: &str| {
+        let mut stream = TcpStream::connect(addr).unwrap();
+        write!(stream, "GET {} HTTP/1.1\r\nHost: localhost\r\n\r\n", path).unwrap();
+        let mut response = String::new();
+        stream.read_to_string(&mut response).unwrap();
+        response
+    };
+    let response = get("/orders/2");
+    assert!(response.starts_with("HTTP/1.1 200 OK"), "{}", response);
+    let body = response.split("\r\n\r\n").nth(1).unwrap();
+    let events: Vec<AuditEvent> = body.lines().skip(1).map(|line| AuditEvent::parse_csv(line).unwrap()).collect();
+    assert_eq!(events.len(), 1);
+    assert_eq!((events[0].event.as_str(), events[0].session_id), ("execution", Some(2)));
+    assert!(get("/orders/abc").starts_with("HTTP/1.1 404"));
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn test_csv_roundtrip_and_utc_date() {
+    let event = AuditEvent {
+        timestamp_us: 1_700_000_000_123_456,
+        event: "reject".to_string(),
+        order_id: None,
+        user_id: 9,
+        session_id: Some(3),
+        ip: Some("::1".parse().unwrap()),
+        symbol: "ETH/USD".to_string(),
+        side: Some(OrderType::Sell),
+        price: Some(5),
+        quantity: None,
+        detail: "reason=a,b".to_string(),
+    };
+    let parsed = AuditEvent::parse_csv(&event.to_csv()).unwrap();
+    assert_eq!(parsed, AuditEvent { detail: "reason=a;b".to_string(), ..event });
+    assert_eq!(audit::utc_date(0), "1970-01-01");
+    assert_eq!(audit::utc_date(1_700_000_000), "2023-11-14");
+    assert_eq!(audit::utc_date(951_782_400), "2000-02-29");
+}
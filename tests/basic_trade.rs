@@ -1,14 +1,34 @@
-use tokio::net::TcpStream;
+use tokio::net::{TcpListener, TcpStream};
+use tokio::sync::mpsc;
 use tokio_util::codec::{Framed, LengthDelimitedCodec};
 use futures::{SinkExt, StreamExt};
-use matching_engine::protocol::{NewOrderRequest, OrderType, TradeNotification, OrderConfirmation};
-use serde_json;
+use matching_engine::engine::{EngineCommand, EngineOutput, MatchingEngine};
+use matching_engine::network;
+use matching_engine::protocol::{ClientMessage, NewOrderRequest, OrderType, ServerMessage};
+use bincode::config;
+
+// 在进程内启动撮合引擎和网络服务器，返回监听地址
+async fn start_server() -> std::net::SocketAddr {
+    let (command_sender, command_receiver) = mpsc::unbounded_channel::<EngineCommand>();
+    let (output_sender, output_receiver) = mpsc::unbounded_channel::<EngineOutput>();
+
+    std::thread::spawn(move || {
+        let mut engine = MatchingEngine::new(command_receiver, output_sender);
+        engine.run();
+    });
+
+    let listener = TcpListener::bind("127.0.0.1:0").await.expect("无法绑定临时端口");
+    let addr = listener.local_addr().unwrap();
+    tokio::spawn(network::serve(listener, command_sender, output_receiver));
+    addr
+}
 
 #[tokio::test]
 async fn test_basic_match() {
-    // 确保服务器正在运行中
-    let stream = TcpStream::connect("127.0.0.1:8080").await.expect("无法连接到服务器");
+    let addr = start_server().await;
+    let stream = TcpStream::connect(addr).await.expect("无法连接到服务器");
     let mut framed = Framed::new(stream, LengthDelimitedCodec::new());
+    let config = config::standard();
 
     // 1. 发送一个买单 (限价单)
     let buy_order = NewOrderRequest {
@@ -18,12 +38,15 @@ async fn test_basic_match() {
         price: 50000,
         quantity: 10,
     };
-    let buy_order_json = serde_json::to_string(&buy_order).unwrap();
-    framed.send(buy_order_json.into()).await.unwrap();
+    let buy_order_bytes = bincode::encode_to_vec(ClientMessage::NewOrder(buy_order), config).unwrap();
+    framed.send(buy_order_bytes.into()).await.unwrap();
 
     // 2. 应该收到一个挂单确认
     let confirmation_msg = framed.next().await.unwrap().unwrap();
-    let confirmation: OrderConfirmation = serde_json::from_slice(&confirmation_msg).unwrap();
+    let (confirmation, _): (ServerMessage, usize) = bincode::decode_from_slice(&confirmation_msg, config).unwrap();
+    let ServerMessage::Confirmation(confirmation) = confirmation else {
+        panic!("期望收到挂单确认, 实际收到: {:?}", confirmation);
+    };
     assert_eq!(confirmation.user_id, 101);
     println!("收到买单确认: {:?}", confirmation);
 
@@ -35,14 +58,16 @@ async fn test_basic_match() {
         price: 50000, // 价格匹配
         quantity: 7,      // 数量小于买单
     };
-    let sell_order_json = serde_json::to_string(&sell_order).unwrap();
-    framed.send(sell_order_json.into()).await.unwrap();
+    let sell_order_bytes = bincode::encode_to_vec(ClientMessage::NewOrder(sell_order), config).unwrap();
+    framed.send(sell_order_bytes.into()).await.unwrap();
 
     // 4. 应该收到一个成交回报
-    // 注意：由于服务器是广播，我们可能会收到自己的挂单确认，需要处理
-    // 在这个简化的测试中，我们假设成交回报会先到
+    // 卖单被完全成交，不会产生新的挂单确认
     let trade_msg = framed.next().await.unwrap().unwrap();
-    let trade: TradeNotification = serde_json::from_slice(&trade_msg).unwrap();
+    let (trade, _): (ServerMessage, usize) = bincode::decode_from_slice(&trade_msg, config).unwrap();
+    let ServerMessage::Trade(trade) = trade else {
+        panic!("期望收到成交回报, 实际收到: {:?}", trade);
+    };
 
     assert_eq!(trade.matched_price, 50000);
     assert_eq!(trade.matched_quantity, 7);
@@ -0,0 +1,109 @@
+use matching_engine::basket::{check_legs, fillable_within};
+use matching_engine::engine::{EngineCommand, EngineOutput};
+use matching_engine::position::PositionLimits;
+use matching_engine::protocol::{
+    BasketLeg, BasketOrderRequest, DepthLevel, NewOrderRequest, OrderType, PositionQuery, RejectReason,
+};
+use matching_engine::testing::Simulation;
+
+fn order(user_id: u64, symbol: &str, order_type: OrderType, price: u64, quantity: u64) -> EngineCommand {
+    EngineCommand::NewOrder(NewOrderRequest { user_id, symbol: symbol.to_string(), order_type, price, quantity })
+}
+
+fn leg(symbol: &str, order_type: OrderType, price: u64, quantity: u64) -> BasketLeg {
+    BasketLeg { symbol: symbol.to_string(), order_type, price, quantity }
+}
+
+fn basket(legs: Vec<BasketLeg>) -> EngineCommand {
+    EngineCommand::BasketOrder(BasketOrderRequest { user_id: 9, legs })
+}
+
+fn position(sim: &mut Simulation, symbol: &str) -> i64 {
+    let query = PositionQuery { user_id: 9, symbol: symbol.to_string() };
+    match sim.execute(EngineCommand::QueryPosition(query)).pop() {
+        Some(EngineOutput::Position(report)) => report.net_position,
+        other => panic!("期望收到持仓回报: {:?}", other),
+    }
+}
+
+fn reject(outputs: Vec<EngineOutput>) -> RejectReason {
+    match outputs.as_slice() {
+        [EngineOutput::Reject(reject)] => reject.reason,
+        other => panic!("期望收到拒绝: {:?}", other),
+    }
+}
+
+// BTC 卖盘 100×2、101×3，ETH 买盘 30×5
+fn seeded() -> Simulation {
+    let mut sim = Simulation::new();
+    sim.execute(order(1, "BTC", OrderType::Sell, 100, 2));
+    sim.execute(order(1, "BTC", OrderType::Sell, 101, 3));
+    sim.execute(order(2, "ETH", OrderType::Buy, 30, 5));
+    sim
+}
+
+#[test]
+fn test_fillable_within_limit() {
+    let asks = [DepthLevel { price: 100, quantity: 2, order_count: 1 }, DepthLevel { price: 101, quantity: 3, order_count: 2 }];
+    assert_eq!(fillable_within(OrderType::Buy, 100, &asks), 2);
+    assert_eq!(fillable_within(OrderType::Buy, 105, &asks), 5);
+    assert_eq!(fillable_within(OrderType::Buy, 99, &asks), 0);
+    let bids = [DepthLevel { price: 30, quantity: 5, order_count: 1 }];
+    assert_eq!(fillable_within(OrderType::Sell, 31, &bids), 0);
+
+    let duplicate = BasketOrderRequest { user_id: 1, legs: vec![leg("A", OrderType::Buy, 1, 1), leg("A", OrderType::Sell, 1, 1)] };
+    assert_eq!(check_legs(&duplicate), Err(RejectReason::InvalidBasket));
+    assert_eq!(check_legs(&BasketOrderRequest { user_id: 1, legs: Vec::new() }), Err(RejectReason::InvalidBasket));
+}
+
+#[test]
+fn test_basket_fills_every_leg() {
+    let mut sim = seeded();
+    let outputs = sim.execute(basket(vec![leg("BTC", OrderType::Buy, 101, 4), leg("ETH", OrderType::Sell, 30, 5)]));
+    let trades: Vec<(String, u64, u64)> = outputs
+        .iter()
+        .filter_map(|output| match output {
+            EngineOutput::Trade(trade) => Some((trade.symbol.to_string(), trade.matched_price, trade.matched_quantity)),
+            _ => None,
+        })
+        .collect();
+    assert_eq!(
+        trades,
+        vec![("BTC".to_string(), 100, 2), ("BTC".to_string(), 101, 2), ("ETH".to_string(), 30, 5)]
+    );
+    // 腿订单全部成交，不产生挂单
+    assert!(!outputs.iter().any(|output| matches!(output, EngineOutput::Confirmation(_))));
+    assert_eq!((position(&mut sim, "BTC"), position(&mut sim, "ETH")), (4, -5));
+}
+
+#[test]
+fn test_basket_is_all_or_nothing() {
+    let mut sim = seeded();
+    // BTC 腿可以成交，但 ETH 腿在限价内只有 5 手，整单拒绝
+    let outputs = sim.execute(basket(vec![leg("BTC", OrderType::Buy, 101, 1), leg("ETH", OrderType::Sell, 30, 6)]));
+    assert_eq!(reject(outputs), RejectReason::BasketNotFillable);
+    // 没有订单簿的合约没有流动性
+    let outputs = sim.execute(basket(vec![leg("BTC", OrderType::Buy, 101, 1), leg("SOL", OrderType::Buy, 10, 1)]));
+    assert_eq!(reject(outputs), RejectReason::BasketNotFillable);
+    let outputs = sim.execute(basket(vec![leg("BTC", OrderType::Buy, 101, 1), leg("ETH", OrderType::Sell, 30, 0)]));
+    assert_eq!(reject(outputs), RejectReason::InvalidQuantity);
+    let outputs = sim.execute(basket(vec![leg("BTC", OrderType::Buy, 1, 1), leg("BTC", OrderType::Buy, 101, 1)]));
+    assert_eq!(reject(outputs), RejectReason::InvalidBasket);
+    assert_eq!((position(&mut sim, "BTC"), position(&mut sim, "ETH")), (0, 0));
+
+    // 订单簿保持原样，之后的篮子仍然可以全部成交
+    let outputs = sim.execute(basket(vec![leg("BTC", OrderType::Buy, 101, 5), leg("ETH", OrderType::Sell, 30, 5)]));
+    assert_eq!(outputs.iter().filter(|output| matches!(output, EngineOutput::Trade(_))).count(), 3);
+}
+
+#[test]
+fn test_basket_checks_position_limits_per_leg() {
+    let mut sim = Simulation::with_engine(|engine| {
+        engine.with_position_limits(PositionLimits { default_limit: Some(3), ..Default::default() })
+    });
+    sim.execute(order(1, "BTC", OrderType::Sell, 100, 3));
+    sim.execute(order(2, "ETH", OrderType::Buy, 30, 3));
+    let outputs = sim.execute(basket(vec![leg("ETH", OrderType::Sell, 30, 2), leg("BTC", OrderType::Buy, 100, 4)]));
+    assert_eq!(reject(outputs), RejectReason::PositionLimitExceeded);
+    assert_eq!(position(&mut sim, "ETH"), 0);
+}
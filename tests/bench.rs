@@ -0,0 +1,21 @@
+use matching_engine::bench::{self, BenchConfig};
+
+#[test]
+fn test_bench_reports_throughput_and_percentiles() {
+    let config = BenchConfig::parse(["duration=0.2", "connections=4", "cancel=0.2", "replace=0.1", "tick=5"]).unwrap();
+    assert_eq!(config.tick_size, Some(5));
+    let report = bench::run(&config, |engine| engine);
+
+    assert!(report.commands > 0);
+    assert_eq!(report.latencies.len(), report.commands);
+    assert!(report.trades > 0);
+    assert!(report.ops_per_sec() > 0.0);
+    assert!(report.latencies.value_at_quantile(0.5) <= report.latencies.value_at_quantile(0.999));
+}
+
+#[test]
+fn test_bench_rejects_invalid_arguments() {
+    assert!(BenchConfig::parse(["tick=0"]).is_err());
+    assert!(BenchConfig::parse(["connections=0"]).is_err());
+    assert!(BenchConfig::parse(["partitions=4"]).is_err());
+}
@@ -0,0 +1,115 @@
+use matching_engine::engine::{BlockTradeRules, EngineCommand, EngineOutput, MatchingEngine};
+use matching_engine::protocol::{BlockTradeRequest, NewOrderRequest, OrderType, RejectReason};
+use tokio::sync::mpsc;
+
+fn start_engine() -> (mpsc::UnboundedSender<EngineCommand>, mpsc::UnboundedReceiver<EngineOutput>) {
+    let (command_sender, command_receiver) = mpsc::unbounded_channel();
+    let (output_sender, output_receiver) = mpsc::unbounded_channel();
+    std::thread::spawn(move || {
+        let rules = BlockTradeRules { min_quantity: 100, max_price_deviation_bps: 500 };
+        let mut engine = MatchingEngine::new(command_receiver, output_sender).with_block_trade_rules(rules);
+        engine.run();
+    });
+    (command_sender, output_receiver)
+}
+
+fn block_trade(price: u64, quantity: u64) -> EngineCommand {
+    EngineCommand::BlockTrade(BlockTradeRequest {
+        symbol: "BTC/USD".to_string(),
+        price,
+        quantity,
+        buyer_user_id: 1,
+        seller_user_id: 2,
+    })
+}
+
+#[tokio::test]
+async fn test_block_trade_bypasses_book() {
+    let (commands, mut outputs) = start_engine();
+
+    // 先挂一个卖单，大宗交易不应与其撮合
+    commands.send(EngineCommand::NewOrder(NewOrderRequest {
+        user_id: 3,
+        symbol: "BTC/USD".to_string(),
+        order_type: OrderType::Sell,
+        price: 50000,
+        quantity: 10,
+    })).unwrap();
+    assert!(matches!(outputs.recv().await, Some(EngineOutput::Confirmation(_))));
+
+    commands.send(block_trade(50000, 500)).unwrap();
+    let Some(EngineOutput::Trade(trade)) = outputs.recv().await else {
+        panic!("期望收到大宗交易成交回报");
+    };
+    assert!(trade.is_block_trade);
+    assert_eq!(trade.matched_quantity, 500);
+    assert_eq!(trade.buyer_user_id, 1);
+    assert_eq!(trade.seller_user_id, 2);
+    assert_eq!(trade.buyer_order_id, 0);
+}
+
+#[tokio::test]
+async fn test_block_trade_validation() {
+    let (commands, mut outputs) = start_engine();
+
+    // 数量不足
+    commands.send(block_trade(50000, 10)).unwrap();
+    let Some(EngineOutput::Reject(reject)) = outputs.recv().await else {
+        panic!("期望收到拒绝回报");
+    };
+    assert_eq!(reject.reason, RejectReason::BlockTradeTooSmall);
+
+    // 形成参考价 50000
+    for (user_id, order_type) in [(3, OrderType::Sell), (4, OrderType::Buy)] {
+        commands.send(EngineCommand::NewOrder(NewOrderRequest {
+            user_id,
+            symbol: "BTC/USD".to_string(),
+            order_type,
+            price: 50000,
+            quantity: 1,
+        })).unwrap();
+    }
+    assert!(matches!(outputs.recv().await, Some(EngineOutput::Confirmation(_))));
+    assert!(matches!(outputs.recv().await, Some(EngineOutput::Trade(_))));
+    // 买卖双方各一条执行回报
+    for _ in 0..2 {
+        assert!(matches!(outputs.recv().await, Some(EngineOutput::ExecutionReport(_))));
+    }
+
+    // 偏离参考价超过 5%
+    commands.send(block_trade(60000, 500)).unwrap();
+    let Some(EngineOutput::Reject(reject)) = outputs.recv().await else {
+        panic!("期望收到拒绝回报");
+    };
+    assert_eq!(reject.reason, RejectReason::BlockTradePriceOutOfRange);
+}
+
+// 参考价乘以偏离基点超出 u64 时不溢出
+#[test]
+fn test_block_trade_deviation_with_large_reference_price() {
+    let (_commands, command_receiver) = mpsc::unbounded_channel();
+    let (output_sender, mut outputs) = mpsc::unbounded_channel();
+    let rules = BlockTradeRules { min_quantity: 100, max_price_deviation_bps: 500 };
+    let mut engine = MatchingEngine::new(command_receiver, output_sender).with_block_trade_rules(rules);
+    let reference = 100_000_000_000_000_000;
+    for (user_id, order_type) in [(3, OrderType::Sell), (4, OrderType::Buy)] {
+        engine.handle_command(EngineCommand::NewOrder(NewOrderRequest {
+            user_id,
+            symbol: "BTC/USD".to_string(),
+            order_type,
+            price: reference,
+            quantity: 1,
+        }));
+    }
+    engine.handle_command(block_trade(reference + reference / 100, 500));
+    engine.handle_command(block_trade(reference * 2, 500));
+    let mut results = Vec::new();
+    while let Ok(output) = outputs.try_recv() {
+        match output {
+            EngineOutput::Trade(trade) if trade.is_block_trade => results.push(Ok(trade.matched_price)),
+            EngineOutput::Reject(reject) => results.push(Err(reject.reason)),
+            _ => {}
+        }
+    }
+    assert_eq!(results, vec![Ok(reference + reference / 100), Err(RejectReason::BlockTradePriceOutOfRange)]);
+}
@@ -0,0 +1,81 @@
+use matching_engine::book_analysis::{self, LEVEL_BYTES, ORDER_BYTES};
+use matching_engine::engine::{ControlCommand, EngineCommand, MatchingEngine};
+use matching_engine::protocol::{DepthSnapshot, NewOrderRequest, OrderType};
+use std::io::Cursor;
+use std::sync::mpsc as std_mpsc;
+use tokio::sync::mpsc;
+
+fn order(user_id: u64, order_type: OrderType, price: u64, quantity: u64) -> EngineCommand {
+    EngineCommand::NewOrder(NewOrderRequest { user_id, symbol: "BTC/USD".to_string(), order_type, price, quantity })
+}
+
+fn snapshot(commands: Vec<EngineCommand>, largest_orders: usize) -> DepthSnapshot {
+    let (command_tx, command_rx) = mpsc::unbounded_channel();
+    let (output_tx, _output_rx) = mpsc::unbounded_channel();
+    let engine = std::thread::spawn(move || MatchingEngine::new(command_rx, output_tx).run());
+    for command in commands {
+        command_tx.send(command).unwrap();
+    }
+    let (reply_tx, reply_rx) = std_mpsc::channel();
+    command_tx.send(EngineCommand::SnapshotDepth { depth: 1, largest_orders, reply: reply_tx }).unwrap();
+    command_tx.send(EngineCommand::Control(ControlCommand::Drain)).unwrap();
+    engine.join().unwrap();
+    reply_rx.recv().unwrap()
+}
+
+#[test]
+fn test_snapshot_includes_book_totals_and_largest_orders() {
+    let snapshot = snapshot(
+        vec![
+            order(1, OrderType::Buy, 99, 5),
+            order(2, OrderType::Buy, 98, 20),
+            order(3, OrderType::Buy, 98, 7),
+            order(4, OrderType::Sell, 101, 20),
+            order(5, OrderType::Sell, 102, 1),
+        ],
+        3,
+    );
+    // 深度只取 1 档，但总数覆盖整个订单簿
+    assert_eq!((snapshot.bids.len(), snapshot.asks.len()), (1, 1));
+    assert_eq!((snapshot.resting_orders, snapshot.bid_levels, snapshot.ask_levels), (5, 2, 2));
+
+    let largest: Vec<(u64, u64)> = snapshot.largest_orders.iter().map(|o| (o.user_id, o.quantity)).collect();
+    // 数量相同时先挂的订单在前
+    assert_eq!(largest, vec![(2, 20), (4, 20), (3, 7)]);
+
+    assert!(!book_analysis::is_crossed(&snapshot));
+    assert_eq!(book_analysis::estimate_memory(&snapshot), (5 * ORDER_BYTES + 4 * LEVEL_BYTES) as u64);
+
+    let report = book_analysis::render_report(&snapshot, 5);
+    assert!(report.contains("价差 2"));
+    assert!(report.contains("挂单 5 笔"));
+    assert!(report.contains("最大挂单"));
+    assert!(!report.contains("警告"));
+}
+
+#[test]
+fn test_largest_orders_only_when_requested() {
+    let snapshot = snapshot(vec![order(1, OrderType::Buy, 99, 5)], 0);
+    assert!(snapshot.largest_orders.is_empty());
+    assert_eq!(snapshot.resting_orders, 1);
+}
+
+#[test]
+fn test_latest_snapshots_and_crossed_book() {
+    // 旧版本导出的快照没有总数和最大挂单字段
+    let file = [
+        r#"{"symbol":"BTC/USD","timestamp":1,"best_bid":99,"best_ask":101,"bids":[],"asks":[]}"#,
+        r#"{"symbol":"ETH/USD","timestamp":2,"best_bid":10,"best_ask":10,"bids":[],"asks":[]}"#,
+        r#"{"symbol":"BTC/USD","timestamp":3,"best_bid":100,"best_ask":101,"bids":[],"asks":[]}"#,
+    ]
+    .join("\n");
+    let latest = book_analysis::latest_snapshots(Cursor::new(file)).unwrap();
+    assert_eq!(latest.len(), 2);
+    assert_eq!(latest["BTC/USD"].best_bid, Some(100));
+    assert_eq!(latest["BTC/USD"].resting_orders, 0);
+    assert!(!book_analysis::is_crossed(&latest["BTC/USD"]));
+    assert!(book_analysis::is_crossed(&latest["ETH/USD"]));
+    assert!(book_analysis::render_report(&latest["ETH/USD"], 5).contains("警告: 订单簿交叉"));
+
+    assert!(book_analysis::latest_snapshots(Cursor::new("not json")).is_err());
+}
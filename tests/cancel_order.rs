@@ -0,0 +1,99 @@
+use matching_engine::engine::{EngineCommand, EngineOutput, MatchingEngine};
+use matching_engine::protocol::{CancelOrderRequest, CancelStatus, NewOrderRequest, OrderType};
+use tokio::sync::mpsc;
+
+fn start_engine() -> (mpsc::UnboundedSender<EngineCommand>, mpsc::UnboundedReceiver<EngineOutput>) {
+    let (command_sender, command_receiver) = mpsc::unbounded_channel();
+    let (output_sender, output_receiver) = mpsc::unbounded_channel();
+    std::thread::spawn(move || {
+        let mut engine = MatchingEngine::new(command_receiver, output_sender);
+        engine.run();
+    });
+    (command_sender, output_receiver)
+}
+
+fn cancel(order_id: u64) -> EngineCommand {
+    cancel_as(1, order_id)
+}
+
+fn cancel_as(user_id: u64, order_id: u64) -> EngineCommand {
+    EngineCommand::CancelOrder(CancelOrderRequest {
+        user_id,
+        symbol: "BTC/USD".to_string(),
+        order_id,
+    })
+}
+
+#[tokio::test]
+async fn test_duplicate_cancel_is_idempotent() {
+    let (commands, mut outputs) = start_engine();
+
+    commands.send(EngineCommand::NewOrder(NewOrderRequest {
+        user_id: 1,
+        symbol: "BTC/USD".to_string(),
+        order_type: OrderType::Buy,
+        price: 50000,
+        quantity: 10,
+    })).unwrap();
+    let Some(EngineOutput::Confirmation(confirmation)) = outputs.recv().await else {
+        panic!("期望收到挂单确认");
+    };
+
+    commands.send(cancel(confirmation.order_id)).unwrap();
+    let Some(EngineOutput::CancelAck(ack)) = outputs.recv().await else {
+        panic!("期望收到撤单回报");
+    };
+    assert_eq!(ack.status, CancelStatus::Cancelled);
+    assert_eq!(ack.cancelled_quantity, 10);
+
+    // 重复撤单
+    commands.send(cancel(confirmation.order_id)).unwrap();
+    let Some(EngineOutput::CancelAck(ack)) = outputs.recv().await else {
+        panic!("期望收到撤单回报");
+    };
+    assert_eq!(ack.status, CancelStatus::AlreadyCancelled);
+    assert_eq!(ack.cancelled_quantity, 0);
+
+    // 从未存在过的订单
+    commands.send(cancel(999)).unwrap();
+    let Some(EngineOutput::CancelAck(ack)) = outputs.recv().await else {
+        panic!("期望收到撤单回报");
+    };
+    assert_eq!(ack.status, CancelStatus::UnknownOrder);
+}
+
+#[tokio::test]
+async fn test_cancel_by_other_user_is_rejected() {
+    let (commands, mut outputs) = start_engine();
+
+    commands.send(EngineCommand::NewOrder(NewOrderRequest {
+        user_id: 1,
+        symbol: "BTC/USD".to_string(),
+        order_type: OrderType::Sell,
+        price: 50000,
+        quantity: 10,
+    })).unwrap();
+    let Some(EngineOutput::Confirmation(confirmation)) = outputs.recv().await else {
+        panic!("期望收到挂单确认");
+    };
+
+    commands.send(cancel_as(2, confirmation.order_id)).unwrap();
+    let Some(EngineOutput::CancelAck(ack)) = outputs.recv().await else {
+        panic!("期望收到撤单回报");
+    };
+    assert_eq!((ack.user_id, ack.status, ack.cancelled_quantity), (2, CancelStatus::NotOwner, 0));
+
+    // 订单仍在订单簿中，所有者可以正常撤单
+    commands.send(cancel(confirmation.order_id)).unwrap();
+    let Some(EngineOutput::CancelAck(ack)) = outputs.recv().await else {
+        panic!("期望收到撤单回报");
+    };
+    assert_eq!((ack.user_id, ack.status, ack.cancelled_quantity), (1, CancelStatus::Cancelled, 10));
+
+    // 订单撤销后，其他用户得到的仍是 NotOwner，不会得知订单已被撤销
+    commands.send(cancel_as(2, confirmation.order_id)).unwrap();
+    let Some(EngineOutput::CancelAck(ack)) = outputs.recv().await else {
+        panic!("期望收到撤单回报");
+    };
+    assert_eq!((ack.user_id, ack.status, ack.cancelled_quantity), (2, CancelStatus::NotOwner, 0));
+}
@@ -0,0 +1,92 @@
+use matching_engine::circuit_breaker::{BreachPolicy, PriceBand};
+use matching_engine::engine::{ControlCommand, EngineCommand, EngineOutput, MatchingEngine};
+use matching_engine::protocol::{CancelStatus, NewOrderRequest, OrderType, RejectReason, TradingPhase};
+use tokio::sync::mpsc;
+
+fn start_engine(policy: BreachPolicy) -> (mpsc::UnboundedSender<EngineCommand>, mpsc::UnboundedReceiver<EngineOutput>) {
+    let (command_sender, command_receiver) = mpsc::unbounded_channel();
+    let (output_sender, output_receiver) = mpsc::unbounded_channel();
+    std::thread::spawn(move || {
+        let mut engine = MatchingEngine::new(command_receiver, output_sender);
+        engine.run();
+    });
+    // 参考价 1000，上下 10%
+    let band = PriceBand { reference_price: 1000, limit_up_bps: 1000, limit_down_bps: 1000, policy };
+    command_sender.send(EngineCommand::Control(ControlCommand::SetPriceBand("BTC/USD".to_string(), band))).unwrap();
+    (command_sender, output_receiver)
+}
+
+fn order(user_id: u64, order_type: OrderType, price: u64, quantity: u64) -> EngineCommand {
+    EngineCommand::NewOrder(NewOrderRequest {
+        user_id,
+        symbol: "BTC/USD".to_string(),
+        order_type,
+        price,
+        quantity,
+    })
+}
+
+#[tokio::test]
+async fn test_band_breach_rejected() {
+    let (commands, mut outputs) = start_engine(BreachPolicy::Reject);
+
+    // 挂在价格带之外的卖单本身不会成交，可以挂单
+    commands.send(order(1, OrderType::Sell, 1200, 5)).unwrap();
+    assert!(matches!(outputs.recv().await, Some(EngineOutput::Confirmation(_))));
+
+    // 与之成交会突破涨停价，应被拒绝
+    commands.send(order(2, OrderType::Buy, 1200, 5)).unwrap();
+    let Some(EngineOutput::Reject(reject)) = outputs.recv().await else {
+        panic!("期望收到拒绝回报");
+    };
+    assert_eq!(reject.reason, RejectReason::PriceBandBreach);
+}
+
+#[tokio::test]
+async fn test_band_breach_halts_until_resume() {
+    let (commands, mut outputs) = start_engine(BreachPolicy::Halt);
+
+    commands.send(order(1, OrderType::Sell, 1200, 5)).unwrap();
+    assert!(matches!(outputs.recv().await, Some(EngineOutput::Confirmation(_))));
+
+    // 触发暂停，订单仍被接受挂单
+    commands.send(order(2, OrderType::Buy, 1200, 5)).unwrap();
+    let Some(EngineOutput::TradingStatus(status)) = outputs.recv().await else {
+        panic!("期望收到暂停通知");
+    };
+    assert_eq!(status.phase, TradingPhase::Halted);
+    assert!(matches!(outputs.recv().await, Some(EngineOutput::Confirmation(_))));
+
+    // 恢复时卖单只能在涨停价之上成交，被撤销，不产生突破价格带的成交
+    commands.send(EngineCommand::Control(ControlCommand::Resume("BTC/USD".to_string()))).unwrap();
+    let Some(EngineOutput::TradingStatus(status)) = outputs.recv().await else {
+        panic!("期望收到恢复通知");
+    };
+    assert_eq!(status.phase, TradingPhase::Continuous);
+    let Some(EngineOutput::CancelAck(ack)) = outputs.recv().await else {
+        panic!("期望收到撤单回报");
+    };
+    assert_eq!((ack.user_id, ack.cancelled_quantity, ack.status), (1, 5, CancelStatus::PriceBandBreach));
+
+    // 价格带之内的卖单与挂着的买单在涨停价成交
+    commands.send(order(3, OrderType::Sell, 1050, 5)).unwrap();
+    let Some(EngineOutput::TradingStatus(status)) = outputs.recv().await else {
+        panic!("期望收到暂停通知");
+    };
+    assert_eq!(status.phase, TradingPhase::Halted);
+    assert!(matches!(outputs.recv().await, Some(EngineOutput::Confirmation(_))));
+    commands.send(EngineCommand::Control(ControlCommand::Resume("BTC/USD".to_string()))).unwrap();
+    assert!(matches!(outputs.recv().await, Some(EngineOutput::TradingStatus(_))));
+    let Some(EngineOutput::Trade(trade)) = outputs.recv().await else {
+        panic!("期望收到成交回报");
+    };
+    assert_eq!(trade.matched_price, 1100);
+    assert_eq!(trade.matched_quantity, 5);
+    assert_eq!((trade.buyer_user_id, trade.seller_user_id), (2, 3));
+}
+
+#[test]
+fn test_band_bounds_saturate() {
+    let band = PriceBand { reference_price: u64::MAX - 10, limit_up_bps: 1_000, limit_down_bps: 20_000, policy: BreachPolicy::Reject };
+    assert_eq!(band.bounds(), (0, u64::MAX));
+}
@@ -0,0 +1,291 @@
+#![cfg(feature = "cluster")]
+
+use matching_engine::cluster::{ClusterConfig, ClusterError, ClusterNode, Message, NodeId, RaftNode};
+use matching_engine::engine::{ControlCommand, EngineCommand, EngineOutput, MatchingEngine};
+use matching_engine::protocol::{CancelOrderRequest, NewOrderRequest, OrderType};
+use matching_engine::rate_limiter::RateLimitConfig;
+use matching_engine::recovery::Snapshot;
+use std::collections::{BTreeMap, HashSet};
+use std::path::{Path, PathBuf};
+use tokio::sync::mpsc::{self, UnboundedReceiver};
+
+// 进程内模拟的网络：消息按发送顺序投递，被隔离节点收发的消息直接丢弃
+struct Network {
+    nodes: BTreeMap<NodeId, ClusterNode>,
+    _outputs: Vec<UnboundedReceiver<EngineOutput>>,
+    isolated: HashSet<NodeId>,
+    snapshots_sent: usize,
+}
+
+fn temp_dir(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("cluster-{}-{}", name, std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+    dir
+}
+
+impl Network {
+    fn new(size: u64, configure: impl Fn(ClusterConfig) -> ClusterConfig) -> Self {
+        Network::build(size, configure, |engine| engine, None)
+    }
+
+    // 每个节点持久化到 dir 下以节点号命名的子目录，目录中已有的状态会被加载
+    fn open(size: u64, configure: impl Fn(ClusterConfig) -> ClusterConfig, dir: &Path) -> Self {
+        Network::build(size, configure, |engine| engine, Some(dir))
+    }
+
+    fn build(
+        size: u64,
+        configure: impl Fn(ClusterConfig) -> ClusterConfig,
+        configure_engine: impl Fn(MatchingEngine) -> MatchingEngine,
+        dir: Option<&Path>,
+    ) -> Self {
+        let ids: Vec<NodeId> = (1..=size).collect();
+        let mut outputs = Vec::new();
+        let nodes = ids
+            .iter()
+            .map(|&id| {
+                let (_command_sender, command_receiver) = mpsc::unbounded_channel();
+                let (output_sender, output_receiver) = mpsc::unbounded_channel();
+                outputs.push(output_receiver);
+                let peers = ids.iter().copied().filter(|&peer| peer != id).collect();
+                let config = configure(ClusterConfig::new(id, peers));
+                let engine = configure_engine(MatchingEngine::new(command_receiver, output_sender));
+                let node = match dir {
+                    Some(dir) => ClusterNode::open(config, &dir.join(id.to_string()), engine).unwrap(),
+                    None => ClusterNode::new(config, engine),
+                };
+                (id, node)
+            })
+            .collect();
+        Network { nodes, _outputs: outputs, isolated: HashSet::new(), snapshots_sent: 0 }
+    }
+
+    fn deliver(&mut self) {
+        loop {
+            let mut pending = Vec::new();
+            for (&from, node) in self.nodes.iter_mut() {
+                pending.extend(node.take_messages().into_iter().map(|(to, message)| (from, to, message)));
+            }
+            if pending.is_empty() {
+                return;
+            }
+            for (from, to, message) in pending {
+                if self.isolated.contains(&from) || self.isolated.contains(&to) {
+                    continue;
+                }
+                if matches!(message, Message::InstallSnapshot { .. }) {
+                    self.snapshots_sent += 1;
+                }
+                self.nodes.get_mut(&to).unwrap().step(from, message);
+            }
+        }
+    }
+
+    fn tick(&mut self, ticks: usize) {
+        for _ in 0..ticks {
+            for node in self.nodes.values_mut() {
+                node.tick();
+            }
+            self.deliver();
+        }
+    }
+
+    // 不属于被隔离节点的领导者
+    fn leader(&self) -> Option<NodeId> {
+        self.nodes.iter().find(|(id, node)| node.is_leader() && !self.isolated.contains(id)).map(|(&id, _)| id)
+    }
+
+    fn elect(&mut self) -> NodeId {
+        for _ in 0..200 {
+            if let Some(leader) = self.leader() {
+                return leader;
+            }
+            self.tick(1);
+        }
+        panic!("没有选出领导者");
+    }
+
+    fn propose(&mut self, leader: NodeId, command: &EngineCommand) {
+        self.nodes.get_mut(&leader).unwrap().propose(command).unwrap();
+        self.deliver();
+    }
+
+    // 成交时间戳取自各节点自己的系统时钟，比较状态时忽略
+    fn checkpoint(&self, id: NodeId) -> Snapshot {
+        let mut snapshot = self.nodes[&id].engine().checkpoint();
+        for trade in &mut snapshot.trade_log {
+            trade.timestamp = 0;
+        }
+        snapshot
+    }
+}
+
+fn command(index: u64) -> EngineCommand {
+    if index % 7 == 6 {
+        return EngineCommand::CancelOrder(CancelOrderRequest {
+            user_id: index % 5 + 1,
+            symbol: "BTC/USD".to_string(),
+            order_id: index / 2 + 1,
+        });
+    }
+    EngineCommand::NewOrder(NewOrderRequest {
+        user_id: index % 5 + 1,
+        symbol: "BTC/USD".to_string(),
+        order_type: if index.is_multiple_of(2) { OrderType::Buy } else { OrderType::Sell },
+        price: 100 + index % 3,
+        quantity: index % 4 + 1,
+    })
+}
+
+#[test]
+fn test_commands_commit_only_with_quorum() {
+    let mut network = Network::new(3, |config| config);
+    let leader = network.elect();
+    let follower = network.nodes.keys().copied().find(|&id| id != leader).unwrap();
+    assert_eq!(
+        network.nodes.get_mut(&follower).unwrap().propose(&command(0)),
+        Err(ClusterError::NotLeader { leader: Some(leader) })
+    );
+    let (reply, _) = std::sync::mpsc::channel();
+    assert!(matches!(
+        network.nodes.get_mut(&leader).unwrap().propose(&EngineCommand::Control(ControlCommand::Checkpoint(reply))),
+        Err(ClusterError::Unsupported(_))
+    ));
+
+    for index in 0..100 {
+        network.propose(leader, &command(index));
+    }
+    // 跟随者在下一次心跳中得知最后一条命令已提交
+    network.tick(2);
+    let expected = network.checkpoint(leader);
+    assert!(!expected.orders.is_empty());
+    for &id in network.nodes.keys() {
+        assert_eq!(network.checkpoint(id), expected);
+    }
+
+    // 被隔离的领导者得不到多数派确认，命令不会送入撮合
+    network.isolated.insert(leader);
+    let commit_index = network.nodes[&leader].raft().commit_index();
+    network.propose(leader, &command(100));
+    assert_eq!(network.nodes[&leader].raft().commit_index(), commit_index);
+    assert_eq!(network.checkpoint(leader), expected);
+
+    // 剩余的多数派选出新领导者继续服务
+    let new_leader = network.elect();
+    assert_ne!(new_leader, leader);
+    assert!(network.nodes[&new_leader].raft().term() > network.nodes[&leader].raft().term());
+    for index in 200..300 {
+        network.propose(new_leader, &command(index));
+    }
+
+    // 旧领导者恢复连接后退为跟随者，丢弃未提交的命令并追上新领导者
+    network.isolated.clear();
+    network.tick(10);
+    assert!(!network.nodes[&leader].is_leader());
+    let expected = network.checkpoint(new_leader);
+    assert_ne!(expected.id_state, 0);
+    for &id in network.nodes.keys() {
+        assert_eq!(network.checkpoint(id), expected);
+    }
+}
+
+#[test]
+fn test_lagging_follower_catches_up_from_snapshot() {
+    let mut network = Network::new(3, |config| ClusterConfig { snapshot_threshold: 50, ..config });
+    let leader = network.elect();
+    let lagging = network.nodes.keys().copied().find(|&id| id != leader).unwrap();
+    network.isolated.insert(lagging);
+    for index in 0..500 {
+        network.propose(leader, &command(index));
+    }
+    // 领导者已截去日志，跟不上的节点只能安装快照
+    assert!(network.nodes[&leader].raft().log_len() <= 50);
+    assert_eq!(network.snapshots_sent, 0);
+
+    network.isolated.clear();
+    network.tick(5);
+    assert!(network.snapshots_sent > 0);
+    let expected = network.checkpoint(leader);
+    assert!(!expected.orders.is_empty());
+    assert_eq!(network.checkpoint(lagging), expected);
+
+    // 安装快照后继续按日志复制
+    for index in 500..520 {
+        network.propose(leader, &command(index));
+    }
+    network.tick(2);
+    assert_eq!(network.checkpoint(lagging), network.checkpoint(leader));
+}
+
+#[test]
+fn test_vote_survives_restart() {
+    let dir = temp_dir("vote");
+    let mut node = RaftNode::open(ClusterConfig::new(1, vec![2, 3]), &dir).unwrap();
+    node.step(2, Message::RequestVote { term: 5, last_log_index: 0, last_log_term: 0 });
+    assert!(matches!(node.take_messages()[..], [(2, Message::Vote { term: 5, granted: true })]));
+
+    // 重启后同一任期不会再投给其他候选者
+    drop(node);
+    let mut node = RaftNode::open(ClusterConfig::new(1, vec![2, 3]), &dir).unwrap();
+    assert_eq!(node.term(), 5);
+    node.step(3, Message::RequestVote { term: 5, last_log_index: 0, last_log_term: 0 });
+    assert!(matches!(node.take_messages()[..], [(3, Message::Vote { term: 5, granted: false })]));
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn test_cluster_restarts_from_disk() {
+    let dir = temp_dir("restart");
+    let configure = |config| ClusterConfig { snapshot_threshold: 50, ..config };
+    let mut network = Network::open(3, configure, &dir);
+    let leader = network.elect();
+    for index in 0..120 {
+        network.propose(leader, &command(index));
+    }
+    network.tick(2);
+    let expected = network.checkpoint(leader);
+    assert!(!expected.orders.is_empty());
+    let term = network.nodes[&leader].raft().term();
+
+    // 全部节点同时重启：快照装入引擎，快照之后的条目在新领导者提交后重新应用
+    drop(network);
+    let mut network = Network::open(3, configure, &dir);
+    for node in network.nodes.values() {
+        assert!(node.raft().term() >= term);
+        assert!(node.raft().last_index() >= 120);
+    }
+    let leader = network.elect();
+    assert!(network.nodes[&leader].raft().term() > term);
+    network.tick(2);
+    for &id in network.nodes.keys() {
+        assert_eq!(network.checkpoint(id), expected);
+    }
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn test_rate_limit_checked_only_by_leader() {
+    let limit = RateLimitConfig { orders_per_second: 1, burst: 3 };
+    let mut network = Network::build(3, |config| config, |engine| engine.with_rate_limit(limit), None);
+    let leader = network.elect();
+    let order = |price| {
+        EngineCommand::NewOrder(NewOrderRequest {
+            user_id: 1,
+            symbol: "BTC/USD".to_string(),
+            order_type: OrderType::Buy,
+            price,
+            quantity: 1,
+        })
+    };
+    for price in 100..103 {
+        network.propose(leader, &order(price));
+    }
+    assert_eq!(network.nodes.get_mut(&leader).unwrap().propose(&order(103)), Err(ClusterError::Throttled));
+    // 各节点（包括领导者自己）应用已提交的命令时不再占用限流额度，也不会再按限流拒绝
+    network.tick(2);
+    let expected = network.checkpoint(leader);
+    assert_eq!(expected.orders.len(), 3);
+    for &id in network.nodes.keys() {
+        assert_eq!(network.checkpoint(id), expected);
+    }
+}
@@ -0,0 +1,94 @@
+// fuzz/ 目录下模糊测试的确定性版本：用固定种子的随机字节和变异后的合法消息检查解码器，
+// 不需要 cargo-fuzz 也能在普通的 cargo test 中运行
+use matching_engine::codec::{BincodeCodec, Codec};
+use matching_engine::protobuf::ProtobufCodec;
+use matching_engine::protocol::{
+    Candle, ClientMessage, MarketDataSnapshot, NewOrderRequest, OrderType, ServerMessage,
+};
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+
+// 解码成功的消息重新编码后必须能解码回相同的内容；协议类型没有实现 PartialEq，用 Debug 输出比较
+fn check_round_trip(codec: &impl Codec, data: &[u8]) {
+    if let Ok(message) = codec.decode_client(data) {
+        let decoded = codec.decode_client(&codec.encode_client(&message).unwrap()).unwrap();
+        assert_eq!(format!("{:?}", message), format!("{:?}", decoded), "输入 {:?}", data);
+    }
+    if let Ok(message) = codec.decode_server(data) {
+        let decoded = codec.decode_server(&codec.encode_server(&message).unwrap()).unwrap();
+        assert_eq!(format!("{:?}", message), format!("{:?}", decoded), "输入 {:?}", data);
+    }
+}
+
+fn market_data() -> ServerMessage {
+    let candle = Candle { start: 1, open: 100, high: 105, low: 99, close: 101, volume: 20 };
+    ServerMessage::MarketData(MarketDataSnapshot {
+        user_id: 7,
+        symbol: "BTC/USD".to_string(),
+        last_price: Some(101),
+        high: Some(105),
+        low: Some(99),
+        volume: 20,
+        trade_count: 3,
+        turnover: 2_020,
+        vwap: Some(101.0),
+        candles: vec![candle, candle],
+    })
+}
+
+fn new_order() -> ClientMessage {
+    ClientMessage::NewOrder(NewOrderRequest {
+        user_id: 1,
+        symbol: "BTC/USD".to_string(),
+        order_type: OrderType::Buy,
+        price: 50_000,
+        quantity: 3,
+    })
+}
+
+#[test]
+fn test_oversized_container_length_is_rejected() {
+    // MarketData 消息，各字段取零值，最后的 candles 声明 2^50 个元素。
+    // 没有分配上限时 bincode 会先按声明的长度分配内存，进程因分配失败中止
+    let mut data = vec![9, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0xFD];
+    data.extend_from_slice(&(1u64 << 50).to_le_bytes());
+    assert!(BincodeCodec.decode_server(&data).is_err());
+}
+
+#[test]
+fn test_random_bytes_do_not_panic() {
+    let mut rng = StdRng::seed_from_u64(3061);
+    for _ in 0..5_000 {
+        let len = rng.gen_range(0..64);
+        let data: Vec<u8> = (0..len).map(|_| rng.gen()).collect();
+        check_round_trip(&BincodeCodec, &data);
+        check_round_trip(&ProtobufCodec, &data);
+    }
+}
+
+#[test]
+fn test_mutated_messages_do_not_panic() {
+    let mut rng = StdRng::seed_from_u64(3062);
+    // 两种编码的合法消息，作为变异的起点
+    let samples = [
+        BincodeCodec.encode_client(&new_order()).unwrap(),
+        BincodeCodec.encode_server(&market_data()).unwrap(),
+        ProtobufCodec.encode_client(&new_order()).unwrap(),
+        ProtobufCodec.encode_server(&market_data()).unwrap(),
+    ];
+    for sample in &samples {
+        for _ in 0..500 {
+            let mut data = sample.clone();
+            for _ in 0..rng.gen_range(1..4) {
+                let index = rng.gen_range(0..data.len());
+                data[index] = rng.gen();
+            }
+            if rng.gen_bool(0.2) {
+                data.truncate(rng.gen_range(0..data.len()));
+            }
+            check_round_trip(&BincodeCodec, &data);
+            check_round_trip(&ProtobufCodec, &data);
+        }
+    }
+}
+
@@ -0,0 +1,21 @@
+use matching_engine::network::{Conflation, ConflationConfig};
+use matching_engine::protocol::MarketDataMode;
+
+#[test]
+fn test_degrade_and_recover() {
+    let mut conflation = Conflation::new(ConflationConfig { degrade_at: 100, recover_at: 10 });
+    assert_eq!(conflation.mode(), MarketDataMode::Full);
+    assert!(conflation.should_forward(false));
+
+    assert_eq!(conflation.update(99), None);
+    assert_eq!(conflation.update(100), Some(MarketDataMode::Conflated));
+    // 合并模式下只推送成交、交易状态和用户本人的回报
+    assert!(conflation.should_forward(true));
+    assert!(!conflation.should_forward(false));
+
+    // 积压在两个阈值之间时保持当前模式，避免来回切换
+    assert_eq!(conflation.update(50), None);
+    assert_eq!(conflation.update(10), Some(MarketDataMode::Full));
+    assert_eq!(conflation.update(50), None);
+    assert!(conflation.should_forward(false));
+}
@@ -0,0 +1,106 @@
+use matching_engine::depth_view::DepthViewConfig;
+use matching_engine::engine::{EngineCommand, MatchingEngine};
+use matching_engine::orderbook::{self, OrderBook};
+use matching_engine::protocol::{DepthLevel, NewOrderRequest, OrderType};
+use matching_engine::replica;
+use std::sync::mpsc as std_mpsc;
+use tokio::sync::mpsc;
+
+fn request(user_id: u64, order_type: OrderType, price: u64, quantity: u64) -> NewOrderRequest {
+    NewOrderRequest { user_id, symbol: "BTC/USD".to_string(), order_type, price, quantity }
+}
+
+// 不经过缓存重新计算的校验和
+fn fresh_checksum(book: &OrderBook, n: usize) -> u32 {
+    let (bids, asks) = book.depth(n);
+    orderbook::depth_checksum(&bids, &asks)
+}
+
+#[test]
+fn test_checksum_covers_levels_and_sides() {
+    assert_eq!(orderbook::depth_checksum(&[], &[]), 0);
+    let level = |price, quantity| DepthLevel { price, quantity, order_count: 1 };
+    let bids = [level(99, 5)];
+    let asks = [level(101, 7)];
+    let checksum = orderbook::depth_checksum(&bids, &asks);
+    assert_ne!(checksum, 0);
+    // 档位顺序和买卖方向都参与计算
+    assert_ne!(checksum, orderbook::depth_checksum(&asks, &bids));
+    assert_ne!(checksum, orderbook::depth_checksum(&bids, &[level(101, 6)]));
+}
+
+// 每次变化之后，缓存的校验和与重新计算的一致，无论变化是否落在前 n 档内
+#[test]
+fn test_cached_checksum_follows_every_update() {
+    const DEPTH: usize = 3;
+    let mut book = OrderBook::new();
+    let mut resting = Vec::new();
+    for index in 0..2_000u64 {
+        match index % 5 {
+            // 撤掉较早的挂单，可能在前 n 档内也可能在外
+            3 if !resting.is_empty() => {
+                let id = resting.swap_remove((index as usize * 7) % resting.len());
+                let _ = book.cancel_order(id);
+            }
+            4 if !resting.is_empty() => {
+                let id = resting[(index as usize * 3) % resting.len()];
+                if let Some(quantity) = book.order(id).map(|node| node.quantity) {
+                    if quantity > 1 {
+                        book.reduce_order(id, quantity - 1).unwrap();
+                    }
+                }
+            }
+            _ => {
+                let order_type = if index.is_multiple_of(2) { OrderType::Buy } else { OrderType::Sell };
+                // 买价 90..=100、卖价 98..=108，部分订单穿过对手价成交
+                let price = if order_type == OrderType::Buy { 90 + index % 11 } else { 98 + index % 11 };
+                let (_, confirmation) = book.match_order(request(index % 4 + 1, order_type, price, index % 6 + 1)).unwrap();
+                resting.extend(confirmation.map(|confirmation| confirmation.order_id));
+            }
+        }
+        assert_eq!(book.checksum_of_depth(DEPTH), fresh_checksum(&book, DEPTH), "第 {} 次更新后", index);
+    }
+}
+
+#[test]
+fn test_change_outside_top_levels_keeps_checksum() {
+    let mut book = OrderBook::new();
+    for price in [100, 99, 98, 97] {
+        book.match_order(request(1, OrderType::Buy, price, 5)).unwrap();
+    }
+    let before = book.checksum_of_depth(2);
+    // 前 2 档之外的变化不影响缓存的校验和
+    book.match_order(request(2, OrderType::Buy, 96, 5)).unwrap();
+    book.cancel_order(4).unwrap();
+    assert_eq!(book.checksum_of_depth(2), before);
+    book.reduce_order(2, 3).unwrap();
+    assert_ne!(book.checksum_of_depth(2), before);
+    assert_eq!(book.checksum_of_depth(2), fresh_checksum(&book, 2));
+}
+
+#[test]
+fn test_depth_messages_carry_checksum_replica_can_verify() {
+    let (_commands, command_receiver) = mpsc::unbounded_channel();
+    let (output_sender, _outputs) = mpsc::unbounded_channel();
+    let (feed, receiver) = std_mpsc::channel();
+    let mut engine = MatchingEngine::new(command_receiver, output_sender)
+        .with_depth_view(DepthViewConfig { depth: 5, every_commands: 1 })
+        .with_l3_feed(feed, 1_000);
+    let view = engine.depth_view().unwrap();
+    let mut books = replica::ReplicaBooks::new();
+    for index in 0..300u64 {
+        let order_type = if index.is_multiple_of(2) { OrderType::Buy } else { OrderType::Sell };
+        engine.handle_command(EngineCommand::NewOrder(request(index % 3 + 1, order_type, 95 + index % 11, index % 4 + 1)));
+        for message in receiver.try_iter() {
+            books.apply(message);
+        }
+        let snapshot = view.load().get("BTC/USD").cloned().unwrap();
+        assert_eq!(snapshot.checksum, orderbook::depth_checksum(&snapshot.bids, &snapshot.asks));
+        assert!(books.verify_depth(&snapshot), "第 {} 条命令后", index);
+    }
+
+    // 副本漏掉一条消息后，深度消息的校验和暴露出失步
+    engine.handle_command(EngineCommand::NewOrder(request(1, OrderType::Buy, 200, 9)));
+    let _lost = receiver.try_iter().count();
+    assert!(!books.verify_depth(view.load().get("BTC/USD").unwrap()));
+}
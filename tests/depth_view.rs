@@ -0,0 +1,100 @@
+use matching_engine::depth_view::{self, DepthViewConfig};
+use matching_engine::engine::{ControlCommand, EngineCommand, MatchingEngine};
+use matching_engine::protocol::{DepthSnapshot, NewOrderRequest, OrderType};
+use std::io::{Read, Write};
+use std::net::{TcpListener, TcpStream};
+use tokio::sync::mpsc;
+
+fn order(user_id: u64, order_type: OrderType, price: u64, quantity: u64) -> EngineCommand {
+    EngineCommand::NewOrder(NewOrderRequest { user_id, symbol: "BTC/USD".to_string(), order_type, price, quantity })
+}
+
+#[test]
+fn test_view_published_every_n_commands() {
+    let (_commands, command_receiver) = mpsc::unbounded_channel();
+    let (output_sender, _outputs) = mpsc::unbounded_channel();
+    let mut engine =
+        MatchingEngine::new(command_receiver, output_sender).with_depth_view(DepthViewConfig { depth: 2, every_commands: 3 });
+    let view = engine.depth_view().unwrap();
+    assert_eq!((view.load().version, view.load().books.len()), (1, 0));
+
+    engine.handle_command(order(1, OrderType::Buy, 99, 5));
+    engine.handle_command(order(1, OrderType::Buy, 98, 5));
+    // 还没到发布间隔，视图停留在上次发布时的状态
+    assert_eq!(view.load().version, 1);
+    let held = view.load();
+    engine.handle_command(order(2, OrderType::Sell, 101, 7));
+    let latest = view.load();
+    assert_eq!((latest.version, latest.commands), (2, 3));
+    let book = latest.get("BTC/USD").unwrap();
+    assert_eq!((book.best_bid, book.best_ask, book.resting_orders), (Some(99), Some(101), 3));
+    // 持有的旧视图不受新的发布影响
+    assert!(held.books.is_empty());
+}
+
+// 查询线程在撮合进行中反复读取视图：每个视图都是某条命令之后的完整状态
+#[test]
+fn test_concurrent_reads_see_consistent_books() {
+    const ORDERS: u64 = 20_000;
+    let (commands, command_receiver) = mpsc::unbounded_channel();
+    let (output_sender, _outputs) = mpsc::unbounded_channel();
+    let mut engine =
+        MatchingEngine::new(command_receiver, output_sender).with_depth_view(DepthViewConfig { depth: 5, every_commands: 50 });
+    let view = engine.depth_view().unwrap();
+    let engine_thread = std::thread::spawn(move || engine.run());
+
+    let reader = std::thread::spawn(move || {
+        let mut last = (0, 0);
+        let mut reads = 0;
+        loop {
+            let current = view.load();
+            assert!(current.version >= last.0 && current.commands >= last.1);
+            last = (current.version, current.commands);
+            if let Some(book) = current.get("BTC/USD") {
+                // 买卖两侧来自同一时刻，不会交叉
+                if let (Some(bid), Some(ask)) = (book.best_bid, book.best_ask) {
+                    assert!(bid < ask, "{:?}", book);
+                }
+                assert_eq!(book.bids.first().map(|level| level.price), book.best_bid);
+            }
+            reads += 1;
+            if current.commands == ORDERS {
+                return reads;
+            }
+        }
+    });
+    for index in 0..ORDERS {
+        let order_type = if index.is_multiple_of(2) { OrderType::Buy } else { OrderType::Sell };
+        commands.send(order(index % 7 + 1, order_type, 95 + index % 11, index % 3 + 1)).unwrap();
+    }
+    // 队列排空后发布的视图包含最后一条命令
+    assert!(reader.join().unwrap() > 0);
+    commands.send(EngineCommand::Control(ControlCommand::Drain)).unwrap();
+    engine_thread.join().unwrap();
+}
+
+#[test]
+fn test_query_server_serves_latest_view() {
+    let (_commands, command_receiver) = mpsc::unbounded_channel();
+    let (output_sender, _outputs) = mpsc::unbounded_channel();
+    let mut engine =
+        MatchingEngine::new(command_receiver, output_sender).with_depth_view(DepthViewConfig { depth: 10, every_commands: 1 });
+    engine.handle_command(order(1, OrderType::Buy, 99, 5));
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap();
+    depth_view::spawn_query_server(listener, engine.depth_view().unwrap());
+
+    let get = |path: &str| {
+        let mut stream = TcpStream::connect(addr).unwrap();
+        write!(stream, "GET {} HTTP/1.1\r\nHost: localhost\r\n\r\n", path).unwrap();
+        let mut response = String::new();
+        stream.read_to_string(&mut response).unwrap();
+        response
+    };
+    let response = get("/depth/BTC%2FUSD");
+    assert!(response.starts_with("HTTP/1.1 200 OK"), "{}", response);
+    assert!(response.contains("X-View-Version: 2"));
+    let snapshot: DepthSnapshot = serde_json::from_str(response.split("\r\n\r\n").nth(1).unwrap()).unwrap();
+    assert_eq!((snapshot.best_bid, snapshot.bids.len()), (Some(99), 1));
+    assert!(get("/depth/ETH/USD").starts_with("HTTP/1.1 404"));
+}
@@ -0,0 +1,120 @@
+use matching_engine::harness::TestServer;
+use matching_engine::protocol::{
+    CancelOrderRequest, CancelStatus, ClientMessage, LogonStatus, NewOrderRequest, OrderStatus, OrderType, ServerMessage,
+};
+use matching_engine::session::{ApiCredential, SessionConfig};
+use std::collections::HashMap;
+use std::sync::atomic::Ordering;
+
+// 不要求登录，但用户 1 可以登录以接收自己的私有回报
+fn optional_logon() -> SessionConfig {
+    let mut credentials = HashMap::new();
+    credentials.insert("key-1".to_string(), ApiCredential { user_id: 1, secret: b"secret".to_vec() });
+    SessionConfig { require_logon: false, ..SessionConfig::with_credentials(credentials) }
+}
+
+fn new_order(user_id: u64, order_type: OrderType, price: u64, quantity: u64) -> ClientMessage {
+    ClientMessage::NewOrder(NewOrderRequest { user_id, symbol: "BTC/USD".to_string(), order_type, price, quantity })
+}
+
+fn cancel(user_id: u64, order_id: u64) -> ClientMessage {
+    ClientMessage::CancelOrder(CancelOrderRequest { user_id, symbol: "BTC/USD".to_string(), order_id })
+}
+
+fn confirmation(message: ServerMessage) -> Option<u64> {
+    match message {
+        ServerMessage::Confirmation(confirmation) => Some(confirmation.order_id),
+        _ => None,
+    }
+}
+
+#[tokio::test]
+async fn test_trade_between_sessions() {
+    let server = TestServer::start_with(optional_logon(), |engine| engine).await;
+    let mut maker = server.connect().await;
+    let mut taker = server.connect().await;
+    // 挂单方的成交回报按用户分发，只有登录的连接能收到
+    assert_eq!(maker.logon("key-1", b"secret").await.status, LogonStatus::Accepted);
+
+    maker.send(new_order(1, OrderType::Sell, 100, 5)).await;
+    let resting = maker.expect(confirmation).await;
+
+    taker.send(new_order(2, OrderType::Buy, 101, 3)).await;
+    // 成交广播给所有连接，按挂单价格成交
+    for client in [&mut maker, &mut taker] {
+        let trade = client
+            .expect(|message| match message {
+                ServerMessage::Trade(trade) => Some(trade),
+                _ => None,
+            })
+            .await;
+        assert_eq!((trade.matched_price, trade.matched_quantity), (100, 3));
+        assert_eq!((trade.seller_order_id, trade.buyer_user_id), (resting, 2));
+    }
+    let report = maker
+        .expect(|message| match message {
+            ServerMessage::ExecutionReport(report) if report.user_id == 1 => Some(report),
+            _ => None,
+        })
+        .await;
+    assert_eq!((report.status, report.leaves_quantity), (OrderStatus::PartiallyFilled, 2));
+    assert_eq!(server.engine_metrics().trades_executed.load(Ordering::Relaxed), 1);
+
+    server.shutdown().await;
+}
+
+#[tokio::test]
+async fn test_cancels_and_cancel_on_disconnect() {
+    let sessions = SessionConfig { cancel_on_disconnect: true, ..optional_logon() };
+    let server = TestServer::start_with(sessions, |engine| engine).await;
+    let mut trader = server.connect().await;
+    let mut observer = server.connect().await;
+    assert_eq!(observer.logon("key-1", b"secret").await.status, LogonStatus::Accepted);
+
+    trader.send(new_order(1, OrderType::Buy, 99, 1)).await;
+    let first = trader.expect(confirmation).await;
+    trader.send(new_order(1, OrderType::Buy, 98, 2)).await;
+    let second = trader.expect(confirmation).await;
+
+    // 撤单和重复撤单
+    for expected in [CancelStatus::Cancelled, CancelStatus::AlreadyCancelled] {
+        trader.send(cancel(1, first)).await;
+        let ack = trader
+            .expect(|message| match message {
+                ServerMessage::CancelAck(ack) => Some(ack),
+                _ => None,
+            })
+            .await;
+        assert_eq!((ack.order_id, ack.status), (first, expected));
+    }
+
+    // 断线后剩余的挂单被撤销，撤单回报推送给该用户登录的连接
+    trader.disconnect().await;
+    let ack = observer
+        .expect(|message| match message {
+            ServerMessage::CancelAck(ack) if ack.order_id == second => Some(ack),
+            _ => None,
+        })
+        .await;
+    assert_eq!((ack.user_id, ack.cancelled_quantity, ack.status), (1, 2, CancelStatus::Cancelled));
+
+    // 订单簿已空：新的卖单只挂单不成交
+    observer.send(new_order(2, OrderType::Sell, 90, 1)).await;
+    observer.expect(confirmation).await;
+    assert_eq!(server.engine_metrics().trades_executed.load(Ordering::Relaxed), 0);
+
+    server.shutdown().await;
+}
+
+#[tokio::test]
+async fn test_shutdown_notifies_connected_clients() {
+    let server = TestServer::start().await;
+    let mut client = server.connect().await;
+    client.send(new_order(1, OrderType::Buy, 100, 1)).await;
+    client.expect(confirmation).await;
+
+    let shutdown = tokio::spawn(server.shutdown());
+    let remaining = client.drain().await;
+    assert!(matches!(remaining.last(), Some(ServerMessage::Shutdown)), "{:?}", remaining);
+    shutdown.await.unwrap();
+}
@@ -0,0 +1,246 @@
+use matching_engine::circuit_breaker::{BreachPolicy, PriceBand};
+use matching_engine::engine::{ControlCommand, EngineCommand, EngineOutput, MatchingEngine};
+use matching_engine::protocol::{
+    AmendOrderRequest, NewOrderRequest, OcoOrderRequest, OrderStatus, OrderType, RejectReason, TimeInForce,
+    TimedOrderRequest, TradingPhase,
+};
+use matching_engine::rate_limiter::RateLimitConfig;
+use std::sync::mpsc as std_mpsc;
+use tokio::sync::mpsc;
+
+fn new_order(user_id: u64, order_type: OrderType, price: u64, quantity: u64) -> EngineCommand {
+    EngineCommand::NewOrder(NewOrderRequest {
+        user_id,
+        symbol: "BTC/USD".to_string(),
+        order_type,
+        price,
+        quantity,
+    })
+}
+
+fn amend(order_id: u64, new_price: u64, new_quantity: u64) -> EngineCommand {
+    EngineCommand::AmendOrder(AmendOrderRequest {
+        user_id: 1,
+        symbol: "BTC/USD".to_string(),
+        order_id,
+        new_price,
+        new_quantity,
+    })
+}
+
+#[tokio::test]
+async fn test_amend_stats_and_drain() {
+    let (commands, command_receiver) = mpsc::unbounded_channel();
+    let (output_sender, mut outputs) = mpsc::unbounded_channel();
+    let engine_thread = std::thread::spawn(move || {
+        MatchingEngine::new(command_receiver, output_sender).run();
+    });
+
+    commands.send(new_order(1, OrderType::Buy, 100, 10)).unwrap();
+    let Some(EngineOutput::Confirmation(first)) = outputs.recv().await else {
+        panic!("期望收到挂单确认");
+    };
+
+    // 只减少数量：保留原订单号
+    commands.send(amend(first.order_id, 100, 4)).unwrap();
+    let Some(EngineOutput::Confirmation(reduced)) = outputs.recv().await else {
+        panic!("期望收到改单确认");
+    };
+    assert_eq!(reduced.order_id, first.order_id);
+
+    // 改价：撤单后作为新订单重新进入订单簿
+    commands.send(amend(first.order_id, 101, 4)).unwrap();
+    let Some(EngineOutput::Confirmation(repriced)) = outputs.recv().await else {
+        panic!("期望收到改单确认");
+    };
+    assert_ne!(repriced.order_id, first.order_id);
+
+    commands.send(EngineCommand::Control(ControlCommand::Halt("BTC/USD".to_string()))).unwrap();
+    assert!(matches!(outputs.recv().await, Some(EngineOutput::TradingStatus(_))));
+
+    let (reply_tx, reply_rx) = std_mpsc::channel();
+    commands.send(EngineCommand::Control(ControlCommand::StatsRequest(reply_tx))).unwrap();
+    let stats = reply_rx.recv().unwrap();
+    assert_eq!(stats.markets.len(), 1);
+    assert_eq!(stats.markets[0].resting_orders, 1);
+    assert_eq!(stats.markets[0].best_bid, Some(101));
+    assert_eq!(stats.markets[0].phase, TradingPhase::Halted);
+
+    // Drain 之后的命令被拒收，引擎线程退出
+    commands.send(EngineCommand::Control(ControlCommand::Drain)).unwrap();
+    engine_thread.join().unwrap();
+    assert!(commands.send(new_order(2, OrderType::Sell, 100, 1)).is_err());
+}
+
+// 异步主循环与其他任务共用一个线程：处理积压命令期间其他任务仍被调度，Drain 之后循环退出
+#[tokio::test]
+async fn test_run_async_shares_the_runtime_thread() {
+    const ORDERS: u64 = 20_000;
+    let (commands, command_receiver) = mpsc::unbounded_channel();
+    let (output_sender, mut outputs) = mpsc::unbounded_channel();
+    let mut engine = MatchingEngine::new(command_receiver, output_sender);
+    let metrics = engine.metrics();
+    for index in 0..ORDERS {
+        let order_type = if index.is_multiple_of(2) { OrderType::Buy } else { OrderType::Sell };
+        commands.send(new_order(index % 5 + 1, order_type, 100, 1)).unwrap();
+    }
+    commands.send(EngineCommand::Control(ControlCommand::Drain)).unwrap();
+    let engine_task = tokio::spawn(async move { engine.run_async().await });
+
+    let mut observed = Vec::new();
+    while !engine_task.is_finished() {
+        observed.push(metrics.trades_executed.load(std::sync::atomic::Ordering::Relaxed));
+        tokio::task::yield_now().await;
+    }
+    engine_task.await.unwrap();
+    assert!(observed.iter().any(|&trades| trades > 0 && trades < ORDERS / 2), "{:?}", &observed[..observed.len().min(10)]);
+    assert_eq!(metrics.trades_executed.load(std::sync::atomic::Ordering::Relaxed), ORDERS / 2);
+    // 发送端仍然存在，Drain 关闭通道后循环退出
+    assert!(commands.send(new_order(1, OrderType::Buy, 100, 1)).is_err());
+    let mut trades = 0;
+    while let Ok(output) = outputs.try_recv() {
+        trades += u64::from(matches!(output, EngineOutput::Trade(_)));
+    }
+    assert_eq!(trades, ORDERS / 2);
+}
+
+fn amend_as(user_id: u64, order_id: u64, new_price: u64, new_quantity: u64) -> EngineCommand {
+    EngineCommand::AmendOrder(AmendOrderRequest {
+        user_id,
+        symbol: "BTC/USD".to_string(),
+        order_id,
+        new_price,
+        new_quantity,
+    })
+}
+
+fn new_request(user_id: u64, order_type: OrderType, price: u64, quantity: u64) -> NewOrderRequest {
+    NewOrderRequest { user_id, symbol: "BTC/USD".to_string(), order_type, price, quantity }
+}
+
+// 撤单重下的改单沿用原订单的 GTD 到期时间、OCO 关联和只减仓属性
+#[test]
+fn test_amend_keeps_order_attributes() {
+    let (_commands, command_receiver) = mpsc::unbounded_channel();
+    let (output_sender, mut outputs) = mpsc::unbounded_channel();
+    let mut engine = MatchingEngine::new(command_receiver, output_sender);
+
+    engine.handle_command(EngineCommand::Control(ControlCommand::AdvanceTime(1_000)));
+    engine.handle_command(EngineCommand::TimedOrder(TimedOrderRequest {
+        order: new_request(1, OrderType::Buy, 50, 1),
+        time_in_force: TimeInForce::GoodTillDate { expire_at_ms: 5_000 },
+    }));
+    engine.handle_command(amend_as(1, 1, 51, 1));
+    engine.handle_command(EngineCommand::OcoOrder(OcoOrderRequest {
+        link_id: 9,
+        first: new_request(3, OrderType::Buy, 80, 1),
+        second: new_request(3, OrderType::Sell, 200, 1),
+    }));
+    engine.handle_command(amend_as(3, 3, 81, 1));
+    engine.handle_command(new_order(5, OrderType::Buy, 150, 2));
+    engine.handle_command(new_order(6, OrderType::Sell, 150, 2));
+    engine.handle_command(EngineCommand::ReduceOnlyOrder(new_request(5, OrderType::Sell, 300, 3)));
+    let snapshot = engine.checkpoint();
+    let reduce_only_id = snapshot.reduce_only[0].order_ids[0];
+    // 加量也不能超过反向持仓
+    engine.handle_command(amend_as(5, reduce_only_id, 299, 5));
+
+    let snapshot = engine.checkpoint();
+    assert_eq!(snapshot.good_till_date.len(), 1);
+    assert_eq!(snapshot.good_till_date[0].expire_at_ms, 5_000);
+    let amended_gtd = snapshot.good_till_date[0].order_id;
+    assert_eq!(snapshot.oco_links.len(), 2);
+    let amended_leg = snapshot.oco_links.iter().find(|link| link.sibling == Some(("BTC/USD".to_string(), 4))).unwrap();
+    let sibling = snapshot.oco_links.iter().find(|link| link.order_id == 4).unwrap();
+    assert_eq!(sibling.sibling, Some(("BTC/USD".to_string(), amended_leg.order_id)));
+    let amended_leg = amended_leg.order_id;
+    assert_eq!(snapshot.reduce_only.len(), 1);
+    let amended_reduce_only = snapshot.reduce_only[0].order_ids[0];
+    assert_ne!(amended_reduce_only, reduce_only_id);
+    let resting = snapshot.orders.iter().find(|order| order.order_id == amended_reduce_only).unwrap();
+    assert_eq!((resting.price, resting.quantity), (299, 2));
+    while outputs.try_recv().is_ok() {}
+
+    // 改单后的 OCO 订单成交时另一条被撤销
+    engine.handle_command(new_order(7, OrderType::Sell, 81, 1));
+    engine.handle_command(EngineCommand::Control(ControlCommand::AdvanceTime(6_000)));
+    let (mut linked_fill, mut sibling_cancelled, mut expired) = (false, false, false);
+    while let Ok(output) = outputs.try_recv() {
+        match output {
+            EngineOutput::ExecutionReport(report) if report.order_id == amended_leg => {
+                linked_fill |= report.link_id == Some(9);
+            }
+            EngineOutput::ExecutionReport(report) if report.order_id == amended_gtd => {
+                expired |= report.status == OrderStatus::Expired;
+            }
+            EngineOutput::CancelAck(ack) => sibling_cancelled |= ack.order_id == 4,
+            _ => {}
+        }
+    }
+    assert_eq!((linked_fill, sibling_cancelled, expired), (true, true, true));
+}
+
+// 改单在撤销原订单之前完成全部检查，不通过时原订单保持不变
+#[test]
+fn test_rejected_amend_keeps_original_order() {
+    let (_commands, command_receiver) = mpsc::unbounded_channel();
+    let (output_sender, mut outputs) = mpsc::unbounded_channel();
+    let band = PriceBand { reference_price: 100, limit_up_bps: 1_000, limit_down_bps: 1_000, policy: BreachPolicy::Reject };
+    let mut engine = MatchingEngine::new(command_receiver, output_sender)
+        .with_price_band("BTC/USD", band)
+        .with_rate_limit(RateLimitConfig { orders_per_second: 1, burst: 3 });
+
+    engine.handle_command(new_order(2, OrderType::Sell, 120, 5));
+    engine.handle_command(new_order(1, OrderType::Buy, 100, 5));
+    // 会在 120 成交，超出涨停价 110
+    engine.handle_command(amend_as(1, 2, 125, 5));
+    // 限流在检查之前，被拒绝的改单也占用额度；下单、两次改单用完额度，下一次改单被限流
+    engine.handle_command(amend_as(1, 2, 101, 5));
+    engine.handle_command(amend_as(1, 3, 102, 5));
+
+    let mut rejects = Vec::new();
+    while let Ok(output) = outputs.try_recv() {
+        match output {
+            EngineOutput::Reject(reject) => rejects.push(reject.reason),
+            EngineOutput::CancelAck(ack) => panic!("原订单不应被撤销: {:?}", ack),
+            _ => {}
+        }
+    }
+    assert_eq!(rejects, vec![RejectReason::PriceBandBreach, RejectReason::Throttled]);
+    let orders = engine.checkpoint().orders;
+    assert!(orders.iter().any(|order| order.user_id == 1 && order.price == 101 && order.quantity == 5));
+}
+
+// OCO 的两条腿各占一个令牌，额度不足两个时整组被限流，也不消耗剩余的令牌
+#[test]
+fn test_oco_charges_one_token_per_leg() {
+    let (_commands, command_receiver) = mpsc::unbounded_channel();
+    let (output_sender, mut outputs) = mpsc::unbounded_channel();
+    let mut engine = MatchingEngine::new(command_receiver, output_sender)
+        .with_rate_limit(RateLimitConfig { orders_per_second: 1, burst: 3 });
+    let oco = |link_id| {
+        EngineCommand::OcoOrder(OcoOrderRequest {
+            link_id,
+            first: new_request(1, OrderType::Buy, 80, 1),
+            second: new_request(1, OrderType::Sell, 200, 1),
+        })
+    };
+
+    engine.handle_command(oco(1));
+    engine.handle_command(oco(2));
+    engine.handle_command(new_order(1, OrderType::Buy, 90, 1));
+    engine.handle_command(new_order(1, OrderType::Buy, 91, 1));
+
+    let mut confirmed = 0;
+    let mut rejects = Vec::new();
+    while let Ok(output) = outputs.try_recv() {
+        match output {
+            EngineOutput::Confirmation(_) => confirmed += 1,
+            EngineOutput::Reject(reject) => rejects.push(reject.reason),
+            _ => {}
+        }
+    }
+    // 第一组 OCO 用掉两个令牌，第二组被限流，剩下的一个令牌给下一笔单腿订单
+    assert_eq!(confirmed, 3);
+    assert_eq!(rejects, vec![RejectReason::Throttled, RejectReason::Throttled]);
+}
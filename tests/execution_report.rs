@@ -0,0 +1,109 @@
+use matching_engine::engine::{ControlCommand, EngineCommand, EngineOutput, MatchingEngine};
+use matching_engine::orderbook::OrderBook;
+use matching_engine::protocol::{ExecutionReport, NewOrderRequest, OrderStatus, OrderType};
+use tokio::sync::mpsc;
+
+fn order(user_id: u64, order_type: OrderType, price: u64, quantity: u64) -> EngineCommand {
+    EngineCommand::NewOrder(NewOrderRequest {
+        user_id,
+        symbol: "BTC/USD".to_string(),
+        order_type,
+        price,
+        quantity,
+    })
+}
+
+fn run(commands: Vec<EngineCommand>) -> Vec<ExecutionReport> {
+    let (command_sender, command_receiver) = mpsc::unbounded_channel();
+    let (output_sender, mut outputs) = mpsc::unbounded_channel();
+    let engine_thread = std::thread::spawn(move || {
+        MatchingEngine::new(command_receiver, output_sender).run();
+    });
+    for command in commands {
+        command_sender.send(command).unwrap();
+    }
+    command_sender.send(EngineCommand::Control(ControlCommand::Drain)).unwrap();
+    engine_thread.join().unwrap();
+
+    let mut reports = Vec::new();
+    while let Ok(output) = outputs.try_recv() {
+        if let EngineOutput::ExecutionReport(report) = output {
+            reports.push(report);
+        }
+    }
+    reports
+}
+
+fn summary(reports: &[ExecutionReport]) -> Vec<(u64, OrderStatus, u64, u64, u64)> {
+    reports
+        .iter()
+        .map(|report| (report.user_id, report.status, report.last_quantity, report.cumulative_quantity, report.leaves_quantity))
+        .collect()
+}
+
+#[test]
+fn test_reports_for_both_sides_of_each_trade() {
+    let reports = run(vec![
+        order(1, OrderType::Sell, 100, 10),
+        order(2, OrderType::Sell, 101, 8),
+        // 吃掉 100 价位的 10 和 101 价位的 5
+        order(3, OrderType::Buy, 101, 15),
+        // 被动方的累计成交数量跨多次撮合累加
+        order(4, OrderType::Buy, 101, 2),
+    ]);
+
+    assert_eq!(
+        summary(&reports),
+        vec![
+            (3, OrderStatus::PartiallyFilled, 10, 10, 5),
+            (1, OrderStatus::Filled, 10, 10, 0),
+            (3, OrderStatus::Filled, 5, 15, 0),
+            (2, OrderStatus::PartiallyFilled, 5, 5, 3),
+            (4, OrderStatus::Filled, 2, 2, 0),
+            (2, OrderStatus::PartiallyFilled, 2, 7, 1),
+        ]
+    );
+    // 同一笔成交的两条回报带有相同的成交号
+    assert_eq!(reports[0].trade_id, reports[1].trade_id);
+    assert_ne!(reports[1].trade_id, reports[2].trade_id);
+}
+
+#[test]
+fn test_reports_for_auction_cross() {
+    let reports = run(vec![
+        EngineCommand::Control(ControlCommand::StartAuction("BTC/USD".to_string())),
+        order(1, OrderType::Buy, 102, 6),
+        order(2, OrderType::Sell, 100, 4),
+        EngineCommand::Control(ControlCommand::EndAuction("BTC/USD".to_string())),
+    ]);
+
+    assert_eq!(
+        summary(&reports),
+        vec![(1, OrderStatus::PartiallyFilled, 4, 4, 2), (2, OrderStatus::Filled, 4, 4, 0)]
+    );
+}
+
+#[test]
+fn test_exchange_cancel_reported_after_fills() {
+    let mut book = OrderBook::new();
+    book.enable_execution_reports();
+    let request = |user_id, order_type, quantity| NewOrderRequest {
+        user_id,
+        symbol: "BTC/USD".to_string(),
+        order_type,
+        price: 100,
+        quantity,
+    };
+    book.match_order(request(1, OrderType::Sell, 3)).unwrap();
+    let (trades, residual) = book.match_order(request(2, OrderType::Buy, 5)).unwrap();
+    let residual = residual.unwrap();
+    // 交易所撤销剩余的 2，回报排在成交回报之后
+    book.cancel_with_report(residual.order_id, trades[0].matched_quantity).unwrap();
+    let reports = book.take_execution_reports();
+    assert_eq!(
+        summary(&reports),
+        vec![(2, OrderStatus::PartiallyFilled, 3, 3, 2), (1, OrderStatus::Filled, 3, 3, 0), (2, OrderStatus::Cancelled, 0, 3, 0)]
+    );
+    assert_eq!(reports[2].order_id, residual.order_id);
+    assert!(book.order(residual.order_id).is_none());
+}
@@ -0,0 +1,129 @@
+use matching_engine::engine::{ControlCommand, EngineCommand, EngineOutput, MatchingEngine};
+use matching_engine::expiry::{expiry_schedule, spawn_expiry_timer};
+use matching_engine::instruments::{ContractSpec, InstrumentMaster};
+use matching_engine::protocol::{
+    BlockTradeRequest, CancelStatus, NewOrderRequest, OrderType, RejectReason, TradingPhase,
+};
+use matching_engine::sequencer;
+use std::sync::mpsc as std_mpsc;
+use tokio::sync::mpsc;
+
+fn order(user_id: u64, symbol: &str, order_type: OrderType, price: u64) -> EngineCommand {
+    EngineCommand::NewOrder(NewOrderRequest { user_id, symbol: symbol.to_string(), order_type, price, quantity: 2 })
+}
+
+fn spec(symbol: &str, expiry_ms: Option<u64>) -> ContractSpec {
+    ContractSpec {
+        symbol: symbol.to_string(),
+        tick_size: 1,
+        price_exponent: 0,
+        contract_multiplier: 1,
+        expiry_ms,
+        trading_hours: None,
+        price_band: None,
+        spread_legs: None,
+    }
+}
+
+fn run_engine(commands: Vec<EngineCommand>) -> Vec<EngineOutput> {
+    let (command_sender, command_receiver) = mpsc::unbounded_channel();
+    let (output_sender, mut output_receiver) = mpsc::unbounded_channel();
+    let handle = std::thread::spawn(move || MatchingEngine::new(command_receiver, output_sender).run());
+    for command in commands {
+        command_sender.send(command).unwrap();
+    }
+    command_sender.send(EngineCommand::Control(ControlCommand::Drain)).unwrap();
+    handle.join().unwrap();
+    let mut outputs = Vec::new();
+    while let Ok(output) = output_receiver.try_recv() {
+        outputs.push(output);
+    }
+    outputs
+}
+
+#[test]
+fn test_expire_cancels_resting_orders_and_removes_book() {
+    let (stats_tx, stats_rx) = std_mpsc::channel();
+    let outputs = run_engine(vec![
+        order(1, "BTC-DEC", OrderType::Buy, 100),
+        order(2, "BTC-DEC", OrderType::Sell, 110),
+        order(3, "ETH-PERP", OrderType::Buy, 50),
+        EngineCommand::Control(ControlCommand::Expire("BTC-DEC".to_string())),
+        // 重复的到期命令不产生任何输出
+        EngineCommand::Control(ControlCommand::Expire("BTC-DEC".to_string())),
+        order(1, "BTC-DEC", OrderType::Buy, 100),
+        EngineCommand::BlockTrade(BlockTradeRequest {
+            buyer_user_id: 1,
+            seller_user_id: 2,
+            symbol: "BTC-DEC".to_string(),
+            price: 100,
+            quantity: 1_000,
+        }),
+        // 到期后不能再暂停或进入竞价
+        EngineCommand::Control(ControlCommand::Halt("BTC-DEC".to_string())),
+        EngineCommand::Control(ControlCommand::StatsRequest(stats_tx)),
+    ]);
+
+    let statuses: Vec<TradingPhase> = outputs
+        .iter()
+        .filter_map(|output| match output {
+            EngineOutput::TradingStatus(status) if status.symbol == "BTC-DEC" => Some(status.phase),
+            _ => None,
+        })
+        .collect();
+    assert_eq!(statuses, vec![TradingPhase::Halted, TradingPhase::Expired]);
+
+    let acks: Vec<(u64, u64, CancelStatus)> = outputs
+        .iter()
+        .filter_map(|output| match output {
+            EngineOutput::CancelAck(ack) => Some((ack.user_id, ack.cancelled_quantity, ack.status)),
+            _ => None,
+        })
+        .collect();
+    assert_eq!(acks, vec![(1, 2, CancelStatus::Expired), (2, 2, CancelStatus::Expired)]);
+
+    let rejects: Vec<RejectReason> = outputs
+        .iter()
+        .filter_map(|output| match output {
+            EngineOutput::Reject(reject) => Some(reject.reason),
+            _ => None,
+        })
+        .collect();
+    assert_eq!(rejects, vec![RejectReason::ContractExpired, RejectReason::ContractExpired]);
+
+    // 只有未到期合约的订单簿保留下来
+    let stats = stats_rx.recv().unwrap();
+    let symbols: Vec<&str> = stats.markets.iter().map(|market| market.symbol.as_str()).collect();
+    assert_eq!(symbols, vec!["ETH-PERP"]);
+}
+
+#[test]
+fn test_timer_expires_contracts_from_reference_data() {
+    let now_ms = sequencer::now_nanos() / 1_000_000;
+    let master = InstrumentMaster::from_specs([
+        spec("LATER", Some(now_ms + 200)),
+        spec("PAST", Some(now_ms - 1_000)),
+        spec("PERP", None),
+        spec("FAR", Some(now_ms + 3_600_000)),
+    ])
+    .unwrap();
+    let schedule: Vec<String> = expiry_schedule(&master).into_iter().map(|(_, symbol)| symbol).collect();
+    assert_eq!(schedule, vec!["PAST", "LATER", "FAR"]);
+
+    let (command_sender, mut command_receiver) = mpsc::unbounded_channel();
+    let timer = spawn_expiry_timer(&master, command_sender);
+    let mut expired = Vec::new();
+    for _ in 0..2 {
+        let command = command_receiver.blocking_recv().unwrap();
+        let EngineCommand::Control(ControlCommand::Expire(symbol)) = command else {
+            panic!("期望收到到期命令");
+        };
+        expired.push(symbol);
+    }
+    assert_eq!(expired, vec!["PAST", "LATER"]);
+    assert!(sequencer::now_nanos() / 1_000_000 >= now_ms + 200);
+
+    // 引擎关闭后定时器不再等待远期合约
+    drop(command_receiver);
+    timer.join().unwrap();
+}
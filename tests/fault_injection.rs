@@ -0,0 +1,136 @@
+// 运行：cargo test --features fault-injection --test fault_injection
+#![cfg(feature = "fault-injection")]
+
+use matching_engine::fault::{self, FaultAction, FaultPoint, FaultRule};
+use matching_engine::gateway::{Gateway, GatewayConfig};
+use matching_engine::harness::TestServer;
+use matching_engine::protocol::{ClientMessage, ExecutionReport, NewOrderRequest, OrderStatus, OrderType, ServerMessage};
+use std::time::{Duration, Instant};
+
+fn new_order(user_id: u64, price: u64) -> ClientMessage {
+    ClientMessage::NewOrder(NewOrderRequest {
+        user_id,
+        symbol: "BTC/USD".to_string(),
+        order_type: OrderType::Buy,
+        price,
+        quantity: 1,
+    })
+}
+
+fn confirmed_user(message: ServerMessage) -> Option<u64> {
+    match message {
+        ServerMessage::Confirmation(confirmation) => Some(confirmation.user_id),
+        _ => None,
+    }
+}
+
+fn report(user_id: u64, trade_id: u64) -> ExecutionReport {
+    ExecutionReport {
+        user_id,
+        symbol: "BTC/USD".to_string(),
+        order_id: trade_id,
+        order_type: OrderType::Buy,
+        status: OrderStatus::Filled,
+        trade_id,
+        last_price: 100,
+        last_quantity: 1,
+        cumulative_quantity: 1,
+        leaves_quantity: 0,
+        link_id: None,
+        received_at_ns: None,
+    }
+}
+
+#[test]
+fn test_same_seed_same_faults() {
+    let pattern = |seed| {
+        let _guard = fault::install(seed, vec![FaultRule::new(FaultPoint::InboundFrame, FaultAction::Drop).probability(0.3)]);
+        (0..200).map(|_| fault::inject(FaultPoint::InboundFrame).is_some()).collect::<Vec<_>>()
+    };
+    let first = pattern(7);
+    assert_eq!(first, pattern(7));
+    assert_ne!(first, pattern(8));
+    let dropped = first.iter().filter(|&&dropped| dropped).count();
+    assert!((30..90).contains(&dropped), "{}", dropped);
+
+    // 规则释放后不再注入
+    assert_eq!(fault::inject(FaultPoint::InboundFrame), None);
+}
+
+#[test]
+fn test_after_and_limit() {
+    let _guard = fault::install(1, vec![FaultRule::new(FaultPoint::JournalWrite, FaultAction::Error).after(2).limit(2)]);
+    let fired: Vec<bool> = (0..6).map(|_| fault::inject(FaultPoint::JournalWrite).is_some()).collect();
+    assert_eq!(fired, vec![false, false, true, true, false, false]);
+    assert_eq!(fault::triggered(FaultPoint::JournalWrite), 2);
+    // 其他注入点不受影响
+    assert_eq!(fault::inject(FaultPoint::OutboundFrame), None);
+}
+
+#[tokio::test]
+async fn test_dropped_frames() {
+    let server = TestServer::start().await;
+    let mut client = server.connect().await;
+    let _guard = fault::install(
+        3,
+        vec![
+            FaultRule::new(FaultPoint::InboundFrame, FaultAction::Drop).limit(1),
+            // 第一条出站消息，即第二笔订单的确认，在发送前被丢弃
+            FaultRule::new(FaultPoint::OutboundFrame, FaultAction::Drop).limit(1),
+        ],
+    );
+
+    for user_id in 1..=3 {
+        client.send(new_order(user_id, 100 + user_id)).await;
+    }
+    // 第一帧没有进入引擎，第二笔的确认丢失，只收到第三笔的确认
+    assert_eq!(client.expect(confirmed_user).await, 3);
+    assert_eq!(server.engine_metrics().orders_accepted.load(std::sync::atomic::Ordering::Relaxed), 2);
+    assert_eq!((fault::triggered(FaultPoint::InboundFrame), fault::triggered(FaultPoint::OutboundFrame)), (1, 1));
+}
+
+#[tokio::test]
+async fn test_engine_stall_backs_up_commands() {
+    let server = TestServer::start().await;
+    let mut client = server.connect().await;
+    let stall = Duration::from_millis(100);
+    let _guard = fault::install(5, vec![FaultRule::new(FaultPoint::EngineCommand, FaultAction::Delay(stall)).limit(1)]);
+
+    let started = Instant::now();
+    for user_id in 1..=20 {
+        client.send(new_order(user_id, 100)).await;
+    }
+    for _ in 1..=20 {
+        client.expect(confirmed_user).await;
+    }
+    assert!(started.elapsed() >= stall);
+    // 停顿期间后续命令在通道中排队
+    let metrics = server.engine_metrics().snapshot();
+    assert_eq!(metrics.orders_accepted, 20);
+    assert_eq!(fault::triggered(FaultPoint::EngineCommand), 1);
+}
+
+#[test]
+fn test_journal_write_error_loses_report_after_restart() {
+    let path = std::env::temp_dir().join(format!("fault-journal-{}.ndjson", std::process::id()));
+    let _ = std::fs::remove_file(&path);
+    let config = GatewayConfig { journal_path: Some(path.clone()), ..GatewayConfig::default() };
+
+    let gateway = Gateway::open(config.clone()).unwrap();
+    {
+        let _guard = fault::install(9, vec![FaultRule::new(FaultPoint::JournalWrite, FaultAction::Error).after(1).limit(1)]);
+        for trade_id in 1..=3 {
+            gateway.record_report(&report(1, trade_id));
+        }
+    }
+    assert_eq!(gateway.journal_errors(), 1);
+    // 进程内仍保留全部回报
+    assert_eq!(gateway.resume(1, 0).missed.len(), 3);
+    drop(gateway);
+
+    // 重启后写入失败的第二条回报无法补发
+    let gateway = Gateway::open(config).unwrap();
+    let missed: Vec<u64> = gateway.resume(1, 0).missed.iter().map(|(_, report)| report.trade_id).collect();
+    assert_eq!(missed, vec![1, 3]);
+    std::fs::remove_file(&path).unwrap();
+}
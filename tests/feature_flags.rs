@@ -0,0 +1,70 @@
+use matching_engine::allocation::AllocationPolicy;
+use matching_engine::engine::{ControlCommand, EngineCommand, EngineOutput, MatchingEngine};
+use matching_engine::feature_flags::{Feature, FeatureFlags};
+use matching_engine::protocol::{NewOrderRequest, OrderType};
+use tokio::sync::mpsc;
+
+fn order(user_id: u64, order_type: OrderType, quantity: u64) -> EngineCommand {
+    EngineCommand::NewOrder(NewOrderRequest {
+        user_id,
+        symbol: "ES".to_string(),
+        order_type,
+        price: 100,
+        quantity,
+    })
+}
+
+#[test]
+fn test_parse_and_precedence() {
+    let flags = FeatureFlags::parse("pro_rata_allocation=off, ES:pro_rata_allocation=on").unwrap();
+    assert!(flags.is_enabled(Feature::ProRataAllocation, "ES"));
+    assert!(!flags.is_enabled(Feature::ProRataAllocation, "NQ"));
+    assert!(FeatureFlags::new().is_enabled(Feature::ProRataAllocation, "NQ"));
+
+    assert!(FeatureFlags::parse("unknown=on").is_err());
+    assert!(FeatureFlags::parse("pro_rata_allocation=maybe").is_err());
+}
+
+#[test]
+fn test_toggle_allocation_at_runtime() {
+    let (commands, command_receiver) = mpsc::unbounded_channel();
+    let (output_sender, mut outputs) = mpsc::unbounded_channel();
+    let engine_thread = std::thread::spawn(move || {
+        MatchingEngine::new(command_receiver, output_sender)
+            .with_allocation_policy("ES", AllocationPolicy::ProRata)
+            .run();
+    });
+
+    // 关闭开关后回退到时间优先
+    commands
+        .send(EngineCommand::Control(ControlCommand::SetFeature {
+            feature: Feature::ProRataAllocation,
+            symbol: Some("ES".to_string()),
+            enabled: false,
+        }))
+        .unwrap();
+    commands.send(order(1, OrderType::Sell, 10)).unwrap();
+    commands.send(order(2, OrderType::Sell, 10)).unwrap();
+    commands.send(order(3, OrderType::Buy, 10)).unwrap();
+
+    // 重新打开后恢复按比例分配
+    commands
+        .send(EngineCommand::Control(ControlCommand::SetFeature {
+            feature: Feature::ProRataAllocation,
+            symbol: Some("ES".to_string()),
+            enabled: true,
+        }))
+        .unwrap();
+    commands.send(order(4, OrderType::Sell, 10)).unwrap();
+    commands.send(order(5, OrderType::Buy, 10)).unwrap();
+    commands.send(EngineCommand::Control(ControlCommand::Drain)).unwrap();
+    engine_thread.join().unwrap();
+
+    let mut fills = Vec::new();
+    while let Ok(output) = outputs.try_recv() {
+        if let EngineOutput::Trade(trade) = output {
+            fills.push((trade.buyer_user_id, trade.seller_user_id, trade.matched_quantity));
+        }
+    }
+    assert_eq!(fills, vec![(3, 1, 10), (5, 2, 5), (5, 4, 5)]);
+}
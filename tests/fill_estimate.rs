@@ -0,0 +1,59 @@
+use matching_engine::engine::{ControlCommand, EngineCommand, EngineOutput, MatchingEngine};
+use matching_engine::protocol::{FillEstimateRequest, NewOrderRequest, OrderType};
+use tokio::sync::mpsc;
+
+fn estimate(order_type: OrderType, quantity: u64) -> EngineCommand {
+    EngineCommand::EstimateFill(FillEstimateRequest {
+        user_id: 9,
+        symbol: "BTC/USD".to_string(),
+        order_type,
+        quantity,
+    })
+}
+
+#[test]
+fn test_estimate_fill_does_not_trade() {
+    let (commands, command_receiver) = mpsc::unbounded_channel();
+    let (output_sender, mut outputs) = mpsc::unbounded_channel();
+    let engine_thread = std::thread::spawn(move || {
+        MatchingEngine::new(command_receiver, output_sender).run();
+    });
+
+    for (price, quantity) in [(100, 10), (101, 10), (103, 5)] {
+        let order = NewOrderRequest {
+            user_id: 1,
+            symbol: "BTC/USD".to_string(),
+            order_type: OrderType::Sell,
+            price,
+            quantity,
+        };
+        commands.send(EngineCommand::NewOrder(order)).unwrap();
+    }
+    commands.send(estimate(OrderType::Buy, 15)).unwrap();
+    // 估算不消耗深度：再次估算得到相同的结果；深度不足时只返回可成交部分
+    commands.send(estimate(OrderType::Buy, 40)).unwrap();
+    commands.send(estimate(OrderType::Sell, 1)).unwrap();
+    commands.send(EngineCommand::Control(ControlCommand::Drain)).unwrap();
+    engine_thread.join().unwrap();
+
+    let mut estimates = Vec::new();
+    while let Ok(output) = outputs.try_recv() {
+        match output {
+            EngineOutput::FillEstimate(estimate) => estimates.push(estimate),
+            EngineOutput::Trade(_) => panic!("估算不应产生成交"),
+            _ => {}
+        }
+    }
+    assert_eq!(estimates.len(), 3);
+
+    assert_eq!(estimates[0].fillable_quantity, 15);
+    assert_eq!(estimates[0].worst_price, Some(101));
+    // (100 * 10 + 101 * 5) / 15
+    assert!((estimates[0].average_price.unwrap() - 100.333).abs() < 0.001);
+
+    assert_eq!((estimates[1].requested_quantity, estimates[1].fillable_quantity), (40, 25));
+    assert_eq!(estimates[1].worst_price, Some(103));
+
+    assert_eq!(estimates[2].fillable_quantity, 0);
+    assert_eq!((estimates[2].average_price, estimates[2].worst_price), (None, None));
+}
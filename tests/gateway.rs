@@ -0,0 +1,191 @@
+use bincode::config;
+use futures::{SinkExt, StreamExt};
+use matching_engine::engine::MatchingEngine;
+use matching_engine::gateway::{Gateway, GatewayConfig};
+use matching_engine::network;
+use matching_engine::protocol::{
+    ClientMessage, ExecutionReport, LogonRequest, NewOrderRequest, OrderStatus, OrderType, RejectReason,
+    ResumeRequest, ServerMessage,
+};
+use matching_engine::session::{sign_logon, ApiCredential, SessionConfig};
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use tokio::net::{TcpListener, TcpStream};
+use tokio::sync::mpsc;
+use tokio_util::codec::{Framed, LengthDelimitedCodec};
+
+fn report(user_id: u64, trade_id: u64) -> ExecutionReport {
+    ExecutionReport {
+        user_id,
+        symbol: "BTC/USD".to_string(),
+        order_id: trade_id,
+        order_type: OrderType::Buy,
+        status: OrderStatus::Filled,
+        trade_id,
+        last_price: 100,
+        last_quantity: 1,
+        cumulative_quantity: 1,
+        leaves_quantity: 0,
+        link_id: None,
+        received_at_ns: None,
+    }
+}
+
+#[test]
+fn test_resume_replays_unacked_reports_after_restart() {
+    let path = std::env::temp_dir().join(format!("gateway-test-{}.ndjson", std::process::id()));
+    let _ = std::fs::remove_file(&path);
+    let config = GatewayConfig { journal_path: Some(path.clone()), ..GatewayConfig::default() };
+
+    let gateway = Gateway::open(config.clone()).unwrap();
+    // 序号按用户分别分配
+    assert_eq!(gateway.record_report(&report(1, 1)), 1);
+    assert_eq!(gateway.record_report(&report(2, 1)), 1);
+    assert_eq!(gateway.record_report(&report(1, 2)), 2);
+    assert_eq!(gateway.record_report(&report(1, 3)), 3);
+    let resumed = gateway.resume(1, 1);
+    assert_eq!(resumed.last_outbound_seq, 3);
+    assert_eq!(resumed.missed.iter().map(|(seq, _)| *seq).collect::<Vec<_>>(), vec![2, 3]);
+    drop(gateway);
+
+    // 重启后从日志恢复：序号继续递增，已确认的回报不再补发
+    let gateway = Gateway::open(config).unwrap();
+    assert_eq!(gateway.record_report(&report(1, 4)), 4);
+    let resumed = gateway.resume(1, 2);
+    assert_eq!(resumed.missed.iter().map(|(seq, r)| (*seq, r.trade_id)).collect::<Vec<_>>(), vec![(3, 3), (4, 4)]);
+    // 客户端报告的序号超前时只确认已分配的部分
+    let resumed = gateway.resume(2, 99);
+    assert_eq!((resumed.last_outbound_seq, resumed.missed.len()), (1, 0));
+    std::fs::remove_file(&path).unwrap();
+}
+
+#[test]
+fn test_compaction_keeps_sequences_and_unacked_reports() {
+    let path = std::env::temp_dir().join(format!("gateway-compact-test-{}.ndjson", std::process::id()));
+    let _ = std::fs::remove_file(&path);
+    let config = GatewayConfig { journal_path: Some(path.clone()), compact_after_records: 8, ..GatewayConfig::default() };
+
+    let gateway = Gateway::open(config.clone()).unwrap();
+    for trade_id in 1..=20 {
+        gateway.record_inbound(1);
+        gateway.record_report(&report(1, trade_id));
+        if trade_id % 5 == 0 {
+            gateway.resume(1, trade_id - 2);
+        }
+    }
+    gateway.record_report(&report(2, 1));
+    drop(gateway);
+    // 已确认的回报被丢弃，日志只剩序号状态和未确认的回报
+    let lines = std::fs::read_to_string(&path).unwrap().lines().count();
+    assert!(lines < 16, "日志没有压缩: {} 行", lines);
+
+    // 重启后入站序号和未确认的回报都能恢复
+    let gateway = Gateway::open(config).unwrap();
+    assert_eq!(gateway.record_inbound(1), 21);
+    let resumed = gateway.resume(1, 18);
+    assert_eq!((resumed.last_inbound_seq, resumed.last_outbound_seq), (21, 20));
+    assert_eq!(resumed.missed.iter().map(|(seq, r)| (*seq, r.trade_id)).collect::<Vec<_>>(), vec![(19, 19), (20, 20)]);
+    assert_eq!(gateway.resume(2, 0).missed.len(), 1);
+    std::fs::remove_file(&path).unwrap();
+}
+
+#[test]
+fn test_retention_limit_leaves_visible_gap() {
+    let gateway = Gateway::open(GatewayConfig { journal_path: None, max_retained_reports: 2, ..GatewayConfig::default() }).unwrap();
+    for trade_id in 1..=5 {
+        gateway.record_report(&report(1, trade_id));
+    }
+    let missed: Vec<u64> = gateway.resume(1, 0).missed.iter().map(|(seq, _)| *seq).collect();
+    assert_eq!(missed, vec![4, 5]);
+}
+
+async fn next_message(framed: &mut Framed<TcpStream, LengthDelimitedCodec>) -> ServerMessage {
+    let frame = tokio::time::timeout(Duration::from_secs(5), framed.next()).await.expect("等待消息超时");
+    bincode::decode_from_slice(&frame.unwrap().unwrap(), config::standard()).unwrap().0
+}
+
+async fn send(framed: &mut Framed<TcpStream, LengthDelimitedCodec>, message: ClientMessage) {
+    framed.send(bincode::encode_to_vec(&message, config::standard()).unwrap().into()).await.unwrap();
+}
+
+async fn logon(addr: std::net::SocketAddr, user_id: u64) -> Framed<TcpStream, LengthDelimitedCodec> {
+    let mut framed = Framed::new(TcpStream::connect(addr).await.unwrap(), LengthDelimitedCodec::new());
+    let api_key = format!("key-{}", user_id);
+    let timestamp_ms = SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_millis() as u64;
+    let signature = sign_logon(b"secret", &api_key, timestamp_ms);
+    send(&mut framed, ClientMessage::Logon(LogonRequest { api_key, timestamp_ms, signature })).await;
+    assert!(matches!(next_message(&mut framed).await, ServerMessage::Logon(_)));
+    framed
+}
+
+fn order(user_id: u64, order_type: OrderType) -> ClientMessage {
+    ClientMessage::NewOrder(NewOrderRequest {
+        user_id,
+        symbol: "BTC/USD".to_string(),
+        order_type,
+        price: 100,
+        quantity: 1,
+    })
+}
+
+#[tokio::test]
+async fn test_reconnect_replays_missed_execution_reports() {
+    let mut credentials = HashMap::new();
+    for user_id in [1, 2] {
+        credentials.insert(format!("key-{}", user_id), ApiCredential { user_id, secret: b"secret".to_vec() });
+    }
+    let sessions = SessionConfig {
+        cancel_on_disconnect: false,
+        gateway: Some(Arc::new(Gateway::open(GatewayConfig::default()).unwrap())),
+        ..SessionConfig::with_credentials(credentials)
+    };
+    let (command_sender, command_receiver) = mpsc::unbounded_channel();
+    let (output_sender, output_receiver) = mpsc::unbounded_channel();
+    std::thread::spawn(move || MatchingEngine::new(command_receiver, output_sender).run());
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    tokio::spawn(network::serve_with_sessions(listener, command_sender, output_receiver, sessions));
+
+    // 未登录时不能恢复会话
+    let mut anonymous = Framed::new(TcpStream::connect(addr).await.unwrap(), LengthDelimitedCodec::new());
+    send(&mut anonymous, ClientMessage::Resume(ResumeRequest { last_received_seq: 0 })).await;
+    assert!(matches!(
+        next_message(&mut anonymous).await,
+        ServerMessage::Reject(reject) if reject.reason == RejectReason::Unauthenticated
+    ));
+
+    // 用户 1 挂单后断开，期间用户 2 的订单与之成交
+    let mut first = logon(addr, 1).await;
+    send(&mut first, order(1, OrderType::Sell)).await;
+    assert!(matches!(next_message(&mut first).await, ServerMessage::Confirmation(_)));
+    drop(first);
+    let mut taker = logon(addr, 2).await;
+    send(&mut taker, order(2, OrderType::Buy)).await;
+    loop {
+        if let ServerMessage::Sequenced { message, .. } = next_message(&mut taker).await {
+            if matches!(*message, ServerMessage::ExecutionReport(ref report) if report.user_id == 2) {
+                break;
+            }
+        }
+    }
+
+    // 重连后从序号 0 恢复，收到错过的成交回报
+    let mut resumed = logon(addr, 1).await;
+    send(&mut resumed, ClientMessage::Resume(ResumeRequest { last_received_seq: 0 })).await;
+    let ServerMessage::Resume(response) = next_message(&mut resumed).await else {
+        panic!("期望收到会话恢复回报");
+    };
+    assert_eq!((response.last_inbound_seq, response.last_outbound_seq, response.replayed), (1, 1, 1));
+    let ServerMessage::Sequenced { seq: 1, message } = next_message(&mut resumed).await else {
+        panic!("期望收到补发的回报");
+    };
+    assert!(matches!(*message, ServerMessage::ExecutionReport(report) if report.user_id == 1 && report.status == OrderStatus::Filled));
+
+    // 确认之后再次恢复不会重复补发
+    send(&mut resumed, ClientMessage::Resume(ResumeRequest { last_received_seq: 1 })).await;
+    let ServerMessage::Resume(response) = next_message(&mut resumed).await else {
+        panic!("期望收到会话恢复回报");
+    };
+    assert_eq!(response.replayed, 0);
+}
@@ -0,0 +1,77 @@
+use bincode::config;
+use futures::{SinkExt, StreamExt};
+use matching_engine::engine::{ControlCommand, EngineCommand, MatchingEngine};
+use matching_engine::metrics::OutboundMetrics;
+use matching_engine::network::{self, OutboundConfig};
+use matching_engine::protocol::{ClientMessage, NewOrderRequest, OrderType, ServerMessage};
+use matching_engine::session::SessionConfig;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::net::{TcpListener, TcpStream};
+use tokio::sync::{mpsc, oneshot};
+use tokio_util::codec::{Framed, LengthDelimitedCodec};
+
+async fn next_message(framed: &mut Framed<TcpStream, LengthDelimitedCodec>) -> Option<ServerMessage> {
+    let frame = tokio::time::timeout(Duration::from_secs(5), framed.next()).await.expect("等待消息超时")?;
+    let (message, _) = bincode::decode_from_slice(&frame.unwrap(), config::standard()).unwrap();
+    Some(message)
+}
+
+#[tokio::test]
+async fn test_shutdown_flushes_reports_then_disconnects() {
+    let (command_sender, command_receiver) = mpsc::unbounded_channel();
+    let (output_sender, output_receiver) = mpsc::unbounded_channel();
+    let engine_thread = std::thread::spawn(move || MatchingEngine::new(command_receiver, output_sender).run());
+
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    let (stop_accepting, stop_accepting_rx) = oneshot::channel::<()>();
+    let server = tokio::spawn(network::serve_until(
+        listener,
+        command_sender.clone(),
+        output_receiver,
+        SessionConfig::default(),
+        OutboundConfig::default(),
+        Arc::new(OutboundMetrics::new()),
+        async {
+            let _ = stop_accepting_rx.await;
+        },
+    ));
+
+    let mut client = Framed::new(TcpStream::connect(addr).await.unwrap(), LengthDelimitedCodec::new());
+    let order = ClientMessage::NewOrder(NewOrderRequest {
+        user_id: 1,
+        symbol: "BTC/USD".to_string(),
+        order_type: OrderType::Buy,
+        price: 100,
+        quantity: 1,
+    });
+    client.send(bincode::encode_to_vec(&order, config::standard()).unwrap().into()).await.unwrap();
+    assert!(matches!(next_message(&mut client).await, Some(ServerMessage::Confirmation(_))));
+
+    // 停止接受新连接后，已有连接仍然保持
+    stop_accepting.send(()).unwrap();
+    tokio::time::sleep(Duration::from_millis(50)).await;
+    assert!(TcpStream::connect(addr).await.is_err());
+
+    // 引擎处理完已排队的命令后退出，连接先收到这些回报，再收到 Shutdown 通知，然后被断开
+    command_sender.send(EngineCommand::NewOrder(NewOrderRequest {
+        user_id: 2,
+        symbol: "BTC/USD".to_string(),
+        order_type: OrderType::Sell,
+        price: 100,
+        quantity: 1,
+    })).unwrap();
+    command_sender.send(EngineCommand::Control(ControlCommand::Drain)).unwrap();
+    drop(command_sender);
+    engine_thread.join().unwrap();
+
+    let mut received = Vec::new();
+    while let Some(message) = next_message(&mut client).await {
+        received.push(message);
+    }
+    assert!(received.iter().any(|message| matches!(message, ServerMessage::Trade(_))));
+    assert!(matches!(received.last(), Some(ServerMessage::Shutdown)));
+
+    tokio::time::timeout(Duration::from_secs(5), server).await.expect("服务器没有在连接断开后返回").unwrap();
+}
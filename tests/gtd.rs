@@ -0,0 +1,162 @@
+use matching_engine::engine::{ControlCommand, EngineCommand, EngineOutput};
+use matching_engine::protocol::{
+    CancelOrderRequest, CancelStatus, NewOrderRequest, OrderStatus, OrderType, RejectReason, TimeInForce,
+    TimedOrderRequest,
+};
+use matching_engine::testing::Simulation;
+use matching_engine::timer_wheel::TimerWheel;
+
+fn order(user_id: u64, order_type: OrderType, price: u64, quantity: u64) -> EngineCommand {
+    EngineCommand::NewOrder(NewOrderRequest { user_id, symbol: "BTC".to_string(), order_type, price, quantity })
+}
+
+fn timed(order_type: OrderType, price: u64, quantity: u64, time_in_force: TimeInForce) -> EngineCommand {
+    let order = NewOrderRequest { user_id: 9, symbol: "BTC".to_string(), order_type, price, quantity };
+    EngineCommand::TimedOrder(TimedOrderRequest { order, time_in_force })
+}
+
+fn gtd(order_type: OrderType, price: u64, quantity: u64, expire_at_ms: u64) -> EngineCommand {
+    timed(order_type, price, quantity, TimeInForce::GoodTillDate { expire_at_ms })
+}
+
+fn advance(now_ms: u64) -> EngineCommand {
+    EngineCommand::Control(ControlCommand::AdvanceTime(now_ms))
+}
+
+fn confirmation(outputs: &[EngineOutput]) -> u64 {
+    outputs
+        .iter()
+        .find_map(|output| match output {
+            EngineOutput::Confirmation(confirmation) => Some(confirmation.order_id),
+            _ => None,
+        })
+        .expect("期望收到订单确认")
+}
+
+// 到期回报：(订单号, 已成交数量)
+fn expired(outputs: &[EngineOutput]) -> Vec<(u64, u64)> {
+    outputs
+        .iter()
+        .filter_map(|output| match output {
+            EngineOutput::ExecutionReport(report) if report.status == OrderStatus::Expired => {
+                assert_eq!(report.leaves_quantity, 0);
+                Some((report.order_id, report.cumulative_quantity))
+            }
+            _ => None,
+        })
+        .collect()
+}
+
+#[test]
+fn test_wheel_fires_in_deadline_order() {
+    let mut wheel = TimerWheel::new(1_000);
+    for deadline in [1_100, 1_005, 70_000, 1_064, 5_000_000] {
+        wheel.insert(deadline, deadline);
+    }
+    assert_eq!(wheel.len(), 5);
+    assert_eq!(wheel.advance(1_004), Vec::<u64>::new());
+    assert_eq!(wheel.advance(1_064), vec![1_005, 1_064]);
+    assert_eq!(wheel.advance(1_099), Vec::<u64>::new());
+    assert_eq!(wheel.advance(100_000), vec![1_100, 70_000]);
+    // 时钟回退时不触发也不倒退
+    assert_eq!(wheel.advance(50), Vec::<u64>::new());
+    assert_eq!(wheel.now(), 100_000);
+    // 已经过期的定时器在下一次推进时触发
+    wheel.insert(10, 10);
+    assert_eq!(wheel.advance(100_000), vec![10]);
+    assert_eq!(wheel.advance(u64::MAX >> 8), vec![5_000_000]);
+    assert!(wheel.is_empty());
+}
+
+#[test]
+fn test_wheel_handles_wall_clock_deadlines() {
+    // 以 Unix 毫秒时间戳为起点，远超最高层范围的定时器也能按时触发
+    let start = 1_760_000_000_000;
+    let mut wheel = TimerWheel::new(0);
+    wheel.advance(start);
+    let far = start + (1 << 40);
+    wheel.insert(far, "far");
+    wheel.insert(start + 1, "near");
+    assert_eq!(wheel.advance(start + 1), vec!["near"]);
+    assert_eq!(wheel.advance(far - 1), Vec::<&str>::new());
+    assert_eq!(wheel.advance(far), vec!["far"]);
+}
+
+#[test]
+fn test_wheel_with_many_timers() {
+    let mut wheel = TimerWheel::new(0);
+    let count = 200_000u64;
+    // 到期时间打散在约 11 天内
+    for i in 0..count {
+        wheel.insert(1 + (i * 7_919_993) % 1_000_000_000, i);
+    }
+    let mut fired = 0;
+    let mut now = 0;
+    while now < 1_000_000_000 {
+        now += 3_600_000;
+        fired += wheel.advance(now).len() as u64;
+    }
+    assert_eq!(fired, count);
+    assert!(wheel.is_empty());
+}
+
+#[test]
+fn test_resting_order_expires() {
+    let mut sim = Simulation::new();
+    sim.execute(advance(1_000));
+    let order_id = confirmation(&sim.execute(gtd(OrderType::Buy, 95, 3, 2_000)));
+    // 到期前部分成交
+    sim.execute(order(1, OrderType::Sell, 95, 1));
+    assert!(expired(&sim.execute(advance(1_999))).is_empty());
+    assert_eq!(expired(&sim.execute(advance(2_000))), vec![(order_id, 1)]);
+    assert_eq!(sim.resting_orders().count(), 0);
+
+    // 到期后再撤单得到幂等回报
+    let cancel = CancelOrderRequest { user_id: 9, symbol: "BTC".to_string(), order_id };
+    let outputs = sim.execute(EngineCommand::CancelOrder(cancel));
+    assert!(matches!(
+        outputs.as_slice(),
+        [EngineOutput::CancelAck(ack)] if ack.status == CancelStatus::AlreadyCancelled
+    ));
+    // 到期后的卖单不会再与它成交
+    let outputs = sim.execute(order(1, OrderType::Sell, 95, 1));
+    assert!(!outputs.iter().any(|output| matches!(output, EngineOutput::Trade(_))));
+}
+
+#[test]
+fn test_timer_of_finished_order_is_ignored() {
+    let mut sim = Simulation::new();
+    let filled = confirmation(&sim.execute(gtd(OrderType::Buy, 95, 1, 500)));
+    let cancelled = confirmation(&sim.execute(gtd(OrderType::Buy, 90, 1, 500)));
+    sim.execute(order(1, OrderType::Sell, 95, 1));
+    let cancel = CancelOrderRequest { user_id: 9, symbol: "BTC".to_string(), order_id: cancelled };
+    sim.execute(EngineCommand::CancelOrder(cancel));
+    assert_ne!(filled, cancelled);
+    assert!(sim.execute(advance(500)).is_empty());
+}
+
+#[test]
+fn test_expiry_must_be_in_future() {
+    let mut sim = Simulation::new();
+    sim.execute(advance(1_000));
+    let outputs = sim.execute(gtd(OrderType::Buy, 95, 1, 1_000));
+    assert!(matches!(
+        outputs.as_slice(),
+        [EngineOutput::Reject(reject)] if reject.reason == RejectReason::InvalidExpiry
+    ));
+
+    // 立即全部成交的 GTD 订单不登记定时器
+    sim.execute(order(1, OrderType::Sell, 95, 1));
+    let outputs = sim.execute(gtd(OrderType::Buy, 95, 1, 1_500));
+    assert!(outputs.iter().any(|output| matches!(output, EngineOutput::Trade(_))));
+    assert!(sim.execute(advance(2_000)).is_empty());
+}
+
+#[test]
+fn test_good_till_cancel_never_expires() {
+    let mut sim = Simulation::new();
+    let order_id = confirmation(&sim.execute(timed(OrderType::Sell, 105, 2, TimeInForce::GoodTillCancel)));
+    assert!(sim.execute(advance(u64::MAX >> 8)).is_empty());
+    let resting: Vec<u64> = sim.resting_orders().map(|order| order.order_id).collect();
+    assert_eq!(resting, vec![order_id]);
+}
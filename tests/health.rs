@@ -0,0 +1,113 @@
+use matching_engine::engine::{ControlCommand, EngineCommand, MatchingEngine};
+use matching_engine::health::{HealthChecker, HealthConfig, HealthStatus};
+use matching_engine::metrics::{self, EngineMetrics, OutboundMetrics};
+use std::io::{Read, Write};
+use std::net::{TcpListener, TcpStream};
+use std::sync::atomic::Ordering;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::mpsc;
+
+fn checker(metrics: Arc<EngineMetrics>, outbound: Arc<OutboundMetrics>) -> Arc<HealthChecker> {
+    let config = HealthConfig { command_queue_watermark: 10, outbound_queue_watermark: 100 };
+    Arc::new(HealthChecker::new(metrics, outbound, config))
+}
+
+fn status_of(report: &matching_engine::health::HealthReport, name: &str) -> HealthStatus {
+    report.checks.iter().find(|check| check.name == name).unwrap().status
+}
+
+#[test]
+fn test_unhealthy_until_engine_and_listener_start() {
+    let health = checker(Arc::new(EngineMetrics::new()), Arc::new(OutboundMetrics::new()));
+    let report = health.check();
+    assert_eq!(report.status, HealthStatus::Unhealthy);
+    assert!(!report.is_live());
+
+    let running = health.engine_running();
+    health.set_listener_bound(true);
+    let report = health.check();
+    assert_eq!(report.status, HealthStatus::Healthy);
+    assert!(report.is_live() && report.is_ready());
+
+    drop(running);
+    assert_eq!(status_of(&health.check(), "engine"), HealthStatus::Unhealthy);
+}
+
+#[test]
+fn test_engine_exit_is_detected() {
+    let (command_sender, command_receiver) = mpsc::unbounded_channel();
+    let (output_sender, _output_receiver) = mpsc::unbounded_channel();
+    let mut engine = MatchingEngine::new(command_receiver, output_sender);
+    let health = checker(engine.metrics(), Arc::new(OutboundMetrics::new()));
+    health.set_listener_bound(true);
+
+    // 引擎线程内 panic 时标记同样被清除
+    let engine_health = health.clone();
+    let (started_sender, started_receiver) = std::sync::mpsc::channel();
+    let engine_thread = std::thread::spawn(move || {
+        let _running = engine_health.engine_running();
+        started_sender.send(()).unwrap();
+        engine.run();
+        panic!("引擎退出");
+    });
+    started_receiver.recv().unwrap();
+    assert_eq!(health.check().status, HealthStatus::Healthy);
+
+    command_sender.send(EngineCommand::Control(ControlCommand::Drain)).unwrap();
+    assert!(engine_thread.join().is_err());
+    assert_eq!(health.check().status, HealthStatus::Unhealthy);
+}
+
+#[test]
+fn test_backlog_degrades_but_stays_live() {
+    let metrics = Arc::new(EngineMetrics::new());
+    let outbound = Arc::new(OutboundMetrics::new());
+    let health = checker(metrics.clone(), outbound.clone());
+    let _running = health.engine_running();
+    health.set_listener_bound(true);
+
+    metrics.command_queue_depth.store(11, Ordering::Relaxed);
+    let report = health.check();
+    assert_eq!(report.status, HealthStatus::Degraded);
+    assert_eq!(status_of(&report, "command_queue"), HealthStatus::Degraded);
+    assert!(report.is_live());
+    assert!(!report.is_ready());
+
+    metrics.command_queue_depth.store(0, Ordering::Relaxed);
+    outbound.queue_depth.store(101, Ordering::Relaxed);
+    assert_eq!(status_of(&health.check(), "outbound_queue"), HealthStatus::Degraded);
+    outbound.queue_depth.store(100, Ordering::Relaxed);
+    assert_eq!(health.check().status, HealthStatus::Healthy);
+}
+
+fn http_get(addr: std::net::SocketAddr, path: &str) -> String {
+    let mut stream = TcpStream::connect(addr).unwrap();
+    stream.set_read_timeout(Some(Duration::from_secs(5))).unwrap();
+    write!(stream, "GET {} HTTP/1.1\r\nHost: localhost\r\n\r\n", path).unwrap();
+    let mut response = String::new();
+    stream.read_to_string(&mut response).unwrap();
+    response
+}
+
+#[test]
+fn test_probe_endpoints() {
+    let metrics = Arc::new(EngineMetrics::new());
+    let outbound = Arc::new(OutboundMetrics::new());
+    let health = checker(metrics.clone(), outbound.clone());
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap();
+    metrics::spawn_prometheus_exporter(listener, metrics.clone(), outbound, Some(health.clone()));
+
+    let response = http_get(addr, "/healthz");
+    assert!(response.starts_with("HTTP/1.1 503 Service Unavailable\r\n"));
+    assert!(response.contains("engine: unhealthy (stopped)"));
+
+    let _running = health.engine_running();
+    health.set_listener_bound(true);
+    assert!(http_get(addr, "/readyz").starts_with("HTTP/1.1 200 OK\r\n"));
+
+    metrics.command_queue_depth.store(11, Ordering::Relaxed);
+    assert!(http_get(addr, "/healthz").starts_with("HTTP/1.1 200 OK\r\n"));
+    assert!(http_get(addr, "/readyz").starts_with("HTTP/1.1 503 Service Unavailable\r\n"));
+}
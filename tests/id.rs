@@ -0,0 +1,64 @@
+use matching_engine::engine::{ControlCommand, EngineCommand, EngineOutput, MatchingEngine};
+use matching_engine::id::{snowflake_shard, IdGenerator};
+use matching_engine::protocol::{NewOrderRequest, OrderType};
+use std::collections::HashSet;
+use std::sync::Arc;
+use tokio::sync::mpsc;
+
+#[test]
+fn test_snowflake_ids_are_unique_and_increasing() {
+    let ids = Arc::new(IdGenerator::snowflake(7));
+    let handles: Vec<_> = (0..4)
+        .map(|_| {
+            let ids = ids.clone();
+            std::thread::spawn(move || {
+                let generated: Vec<u64> = (0..10_000).map(|_| ids.next_id()).collect();
+                // 单个线程观察到的 ID 严格递增
+                assert!(generated.windows(2).all(|pair| pair[0] < pair[1]));
+                generated
+            })
+        })
+        .collect();
+
+    let mut all = HashSet::new();
+    for handle in handles {
+        for id in handle.join().unwrap() {
+            assert_eq!(snowflake_shard(id), 7);
+            assert!(all.insert(id), "重复的 ID: {}", id);
+        }
+    }
+    assert_eq!(all.len(), 40_000);
+
+    let sequential = IdGenerator::sequential();
+    assert_eq!((sequential.next_id(), sequential.next_id()), (1, 2));
+}
+
+#[test]
+fn test_order_ids_unique_across_symbols() {
+    let (commands, command_receiver) = mpsc::unbounded_channel();
+    let (output_sender, mut outputs) = mpsc::unbounded_channel();
+    let engine_thread = std::thread::spawn(move || {
+        MatchingEngine::new(command_receiver, output_sender).run();
+    });
+
+    for symbol in ["BTC/USD", "ETH/USD", "BTC/USD", "ETH/USD"] {
+        let order = NewOrderRequest {
+            user_id: 1,
+            symbol: symbol.to_string(),
+            order_type: OrderType::Buy,
+            price: 100,
+            quantity: 1,
+        };
+        commands.send(EngineCommand::NewOrder(order)).unwrap();
+    }
+    commands.send(EngineCommand::Control(ControlCommand::Drain)).unwrap();
+    engine_thread.join().unwrap();
+
+    let mut order_ids = Vec::new();
+    while let Ok(output) = outputs.try_recv() {
+        if let EngineOutput::Confirmation(confirmation) = output {
+            order_ids.push(confirmation.order_id);
+        }
+    }
+    assert_eq!(order_ids, vec![1, 2, 3, 4]);
+}
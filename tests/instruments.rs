@@ -0,0 +1,97 @@
+use matching_engine::engine::{ControlCommand, EngineCommand, EngineOutput, MatchingEngine};
+use matching_engine::instruments::{InstrumentMaster, TradingHours};
+use matching_engine::protocol::{NewOrderRequest, OrderType, RejectReason};
+use std::io::{Read, Write};
+use std::net::TcpListener;
+use tokio::sync::mpsc;
+
+const INSTRUMENTS: &str = r#"{
+    "instruments": [
+        {
+            "symbol": "BTC-DEC",
+            "tick_size": 5,
+            "contract_multiplier": 10,
+            "expiry_ms": 1798761600000,
+            "trading_hours": {"open_minute": 1320, "close_minute": 1260},
+            "price_band": {"reference_price": 1000, "limit_up_percent": 10, "limit_down_percent": 10}
+        },
+        {"symbol": "ETH-PERP"}
+    ]
+}"#;
+
+fn order(symbol: &str, order_type: OrderType, price: u64) -> EngineCommand {
+    EngineCommand::NewOrder(NewOrderRequest { user_id: 1, symbol: symbol.to_string(), order_type, price, quantity: 1 })
+}
+
+#[test]
+fn test_parse_reference_data() {
+    let master = InstrumentMaster::parse_json(INSTRUMENTS).unwrap();
+    assert_eq!(master.len(), 2);
+    let btc = master.get("BTC-DEC").unwrap();
+    assert_eq!((btc.tick_size, btc.expiry_ms), (5, Some(1_798_761_600_000)));
+    assert_eq!(btc.notional(1_000, 3), 30_000);
+    assert_eq!(btc.price_band.unwrap().to_price_band().bounds(), (900, 1_100));
+    // 跨越午夜的交易时段
+    let hours = btc.trading_hours.unwrap();
+    assert!(hours.contains(1_400) && hours.contains(0) && !hours.contains(1_300));
+    assert!(TradingHours { open_minute: 540, close_minute: 900 }.contains(540));
+
+    // 未填写的字段使用默认值
+    let eth = master.get("ETH-PERP").unwrap();
+    assert_eq!((eth.tick_size, eth.contract_multiplier, eth.expiry_ms), (1, 1, None));
+}
+
+#[test]
+fn test_rejects_invalid_reference_data() {
+    let duplicate = r#"{"instruments": [{"symbol": "A"}, {"symbol": "A"}]}"#;
+    assert!(InstrumentMaster::parse_json(duplicate).unwrap_err().contains("重复"));
+    assert!(InstrumentMaster::parse_json(r#"{"instruments": [{"symbol": "A", "tick_size": 0}]}"#).is_err());
+    let band = r#"{"instruments": [{"symbol": "A", "price_band":
+        {"reference_price": 100, "limit_up_percent": 150, "limit_down_percent": 5}}]}"#;
+    assert!(InstrumentMaster::parse_json(band).is_err());
+    assert!(InstrumentMaster::parse_json("not json").is_err());
+}
+
+#[test]
+fn test_configure_engine_from_reference_data() {
+    let master = InstrumentMaster::parse_json(INSTRUMENTS).unwrap();
+    let (command_sender, command_receiver) = mpsc::unbounded_channel();
+    let (output_sender, mut output_receiver) = mpsc::unbounded_channel();
+    let mut engine = master.configure(MatchingEngine::new(command_receiver, output_sender));
+    let engine_thread = std::thread::spawn(move || engine.run());
+
+    command_sender.send(order("BTC-DEC", OrderType::Sell, 1_003)).unwrap();
+    command_sender.send(order("BTC-DEC", OrderType::Sell, 1_200)).unwrap();
+    // 会以 1200 成交，超出涨停价 1100
+    command_sender.send(order("BTC-DEC", OrderType::Buy, 1_200)).unwrap();
+    command_sender.send(order("ETH-PERP", OrderType::Sell, 1_003)).unwrap();
+    command_sender.send(EngineCommand::Control(ControlCommand::Drain)).unwrap();
+    engine_thread.join().unwrap();
+
+    let mut outputs = Vec::new();
+    while let Ok(output) = output_receiver.try_recv() {
+        outputs.push(output);
+    }
+    assert!(matches!(&outputs[0], EngineOutput::Reject(r) if r.reason == RejectReason::InvalidPrice));
+    assert!(matches!(&outputs[1], EngineOutput::Confirmation(_)));
+    assert!(matches!(&outputs[2], EngineOutput::Reject(r) if r.reason == RejectReason::PriceBandBreach));
+    assert!(matches!(&outputs[3], EngineOutput::Confirmation(_)));
+}
+
+#[test]
+fn test_load_over_http() {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap();
+    let server = std::thread::spawn(move || {
+        let (mut stream, _) = listener.accept().unwrap();
+        let mut request = [0u8; 1024];
+        let len = stream.read(&mut request).unwrap();
+        assert!(String::from_utf8_lossy(&request[..len]).starts_with("GET /instruments.json HTTP/1.0"));
+        write!(stream, "HTTP/1.0 200 OK\r\nContent-Type: application/json\r\n\r\n{}", INSTRUMENTS).unwrap();
+    });
+
+    let master = InstrumentMaster::load(&format!("http://{}/instruments.json", addr)).unwrap();
+    server.join().unwrap();
+    assert_eq!(master, InstrumentMaster::parse_json(INSTRUMENTS).unwrap());
+    assert!(InstrumentMaster::load("/nonexistent/instruments.json").is_err());
+}
@@ -0,0 +1,155 @@
+use bincode::config;
+use futures::{SinkExt, StreamExt};
+use matching_engine::engine::{EngineCommand, EngineOutput, MatchingEngine};
+use matching_engine::latency_budget::{Breadcrumb, LatencyBudget, Stage};
+use matching_engine::metrics::{self, EngineMetrics, OutboundMetrics};
+use matching_engine::network::{self, OutboundConfig};
+use matching_engine::protocol::{ClientMessage, NewOrderRequest, OrderType, ServerMessage};
+use matching_engine::session::SessionConfig;
+use matching_engine::testing::Simulation;
+use std::io::{Read, Write};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio::net::{TcpListener, TcpStream};
+use tokio::sync::mpsc;
+use tokio_util::codec::{Framed, LengthDelimitedCodec};
+
+fn order(user_id: u64, price: u64) -> NewOrderRequest {
+    NewOrderRequest { user_id, symbol: "BTC/USD".to_string(), order_type: OrderType::Buy, price, quantity: 1 }
+}
+
+fn count(budget: &LatencyBudget, stage: Stage) -> u64 {
+    budget.snapshot().iter().find(|snapshot| snapshot.stage == stage).unwrap().count
+}
+
+#[test]
+fn test_records_each_stage() {
+    let budget = LatencyBudget::new();
+    assert!(!budget.is_enabled());
+    let start = Instant::now();
+    let at = |micros| start + Duration::from_micros(micros);
+    let mut breadcrumb = Breadcrumb::new(1, at(0), at(10));
+    breadcrumb.enqueued = at(15);
+    breadcrumb.matched = Some(at(115));
+    breadcrumb.encoded = Some(at(120));
+    budget.record(&breadcrumb, at(1_120));
+
+    let snapshot = budget.snapshot();
+    let stage = |stage| *snapshot.iter().find(|snapshot| snapshot.stage == stage).unwrap();
+    for (name, micros) in [(Stage::Decode, 10), (Stage::Enqueue, 5), (Stage::Match, 100), (Stage::Encode, 5), (Stage::Send, 1_000), (Stage::Total, 1_120)] {
+        let recorded = stage(name);
+        assert_eq!(recorded.count, 1, "{}", name.name());
+        // 三位有效数字
+        let expected = micros * 1_000;
+        assert!(recorded.max_ns.abs_diff(expected) <= expected / 500, "{}: {}", name.name(), recorded.max_ns);
+    }
+
+    // 没有经过引擎的消息（例如被会话层拒绝）只记录已有的阶段
+    budget.record(&Breadcrumb::new(1, at(0), at(10)), at(20));
+    assert_eq!((count(&budget, Stage::Decode), count(&budget, Stage::Match), count(&budget, Stage::Total)), (2, 1, 2));
+
+    budget.reset();
+    assert!(budget.snapshot().iter().all(|snapshot| snapshot.count == 0));
+}
+
+#[test]
+fn test_engine_returns_breadcrumb_after_outputs() {
+    let mut simulation = Simulation::new();
+    let now = Instant::now();
+    let breadcrumb = Box::new(Breadcrumb::new(7, now, now));
+    let outputs = simulation.execute(EngineCommand::Traced { breadcrumb, command: Box::new(EngineCommand::NewOrder(order(1, 100))) });
+    assert!(matches!(outputs.first(), Some(EngineOutput::Confirmation(_))));
+    let Some(EngineOutput::Traced(breadcrumb)) = outputs.last() else {
+        panic!("面包屑应排在最后: {:?}", outputs);
+    };
+    assert_eq!(breadcrumb.connection_id, 7);
+    assert!(breadcrumb.matched.is_some_and(|matched| matched >= now));
+}
+
+async fn start_server(outbound: Arc<OutboundMetrics>) -> Framed<TcpStream, LengthDelimitedCodec> {
+    let (command_sender, command_receiver) = mpsc::unbounded_channel::<EngineCommand>();
+    let (output_sender, output_receiver) = mpsc::unbounded_channel::<EngineOutput>();
+    std::thread::spawn(move || MatchingEngine::new(command_receiver, output_sender).run());
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    tokio::spawn(network::serve_with_outbound(
+        listener,
+        command_sender,
+        output_receiver,
+        SessionConfig::default(),
+        OutboundConfig::default(),
+        outbound,
+    ));
+    Framed::new(TcpStream::connect(addr).await.unwrap(), LengthDelimitedCodec::new())
+}
+
+async fn confirm(framed: &mut Framed<TcpStream, LengthDelimitedCodec>, request: NewOrderRequest) {
+    let config = config::standard();
+    framed.send(bincode::encode_to_vec(ClientMessage::NewOrder(request), config).unwrap().into()).await.unwrap();
+    loop {
+        let frame = tokio::time::timeout(Duration::from_secs(5), framed.next()).await.unwrap().unwrap().unwrap();
+        if let (ServerMessage::Confirmation(_), _) = bincode::decode_from_slice(&frame, config).unwrap() {
+            return;
+        }
+    }
+}
+
+async fn wait_for_total(budget: &LatencyBudget, expected: u64) {
+    let deadline = Instant::now() + Duration::from_secs(5);
+    while count(budget, Stage::Total) < expected && Instant::now() < deadline {
+        tokio::time::sleep(Duration::from_millis(1)).await;
+    }
+}
+
+#[tokio::test]
+async fn test_toggle_at_runtime() {
+    let outbound = Arc::new(OutboundMetrics::new());
+    let mut client = start_server(outbound.clone()).await;
+    let budget = &outbound.latency_budget;
+
+    confirm(&mut client, order(1, 100)).await;
+    // 关闭时不记录
+    tokio::time::sleep(Duration::from_millis(20)).await;
+    assert_eq!(count(budget, Stage::Total), 0);
+
+    budget.set_enabled(true);
+    confirm(&mut client, order(1, 101)).await;
+    wait_for_total(budget, 1).await;
+    for stage in Stage::ALL {
+        assert_eq!(count(budget, stage), 1, "{}", stage.name());
+    }
+
+    budget.set_enabled(false);
+    confirm(&mut client, order(1, 102)).await;
+    tokio::time::sleep(Duration::from_millis(20)).await;
+    assert_eq!(count(budget, Stage::Total), 1);
+}
+
+#[test]
+fn test_scrape_endpoint_toggles_budget() {
+    let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap();
+    let outbound = Arc::new(OutboundMetrics::new());
+    metrics::spawn_prometheus_exporter(listener, Arc::new(EngineMetrics::new()), outbound.clone(), None);
+    let request = |method: &str, path: &str| {
+        let mut stream = std::net::TcpStream::connect(addr).unwrap();
+        write!(stream, "{} {} HTTP/1.1\r\nHost: localhost\r\n\r\n", method, path).unwrap();
+        let mut response = String::new();
+        stream.read_to_string(&mut response).unwrap();
+        response
+    };
+
+    assert!(request("POST", "/latency_budget/enable").starts_with("HTTP/1.1 200 OK\r\n"));
+    assert!(outbound.latency_budget.is_enabled());
+    let now = Instant::now();
+    outbound.latency_budget.record(&Breadcrumb::new(1, now, now + Duration::from_micros(3)), now + Duration::from_micros(8));
+    let body = request("GET", "/metrics");
+    assert!(body.contains("# TYPE matching_engine_stage_latency_seconds summary\n"));
+    assert!(body.contains("matching_engine_stage_latency_seconds_count{stage=\"decode\"} 1\n"));
+    assert!(body.contains("matching_engine_stage_latency_seconds_count{stage=\"match\"} 0\n"));
+    assert!(body.contains("matching_engine_stage_latency_seconds{stage=\"total\",quantile=\"0.99\"} 0.000008"));
+
+    assert!(request("POST", "/latency_budget/disable").starts_with("HTTP/1.1 200 OK\r\n"));
+    assert!(!outbound.latency_budget.is_enabled());
+    assert!(request("GET", "/latency_budget/enable").starts_with("HTTP/1.1 404 Not Found\r\n"));
+}
@@ -0,0 +1,56 @@
+#![cfg(feature = "loadgen")]
+
+use matching_engine::load_script::{LoadScript, MarketView, ScriptAction};
+use matching_engine::protocol::OrderType;
+use std::time::Duration;
+
+#[test]
+fn test_script_actions() {
+    let script = LoadScript::compile(
+        r#"
+        fn next(ctx) {
+            let mid = if ctx.last_price == () { 100 } else { ctx.last_price };
+            let actions = [#{ type: "buy", price: mid - 1, quantity: ctx.client + 1 }];
+            if ctx.open_orders.len() > 1 {
+                actions.push(#{ type: "cancel", order_id: ctx.open_orders[0] });
+            }
+            actions.push(#{ type: "sleep", micros: ctx.tick });
+            actions
+        }
+        "#,
+    )
+    .unwrap();
+
+    let actions = script.next_actions(2, &MarketView::default()).unwrap();
+    assert_eq!(
+        actions,
+        vec![
+            ScriptAction::Order { order_type: OrderType::Buy, price: 99, quantity: 3 },
+            ScriptAction::Sleep(Duration::ZERO),
+        ]
+    );
+
+    let view = MarketView { tick: 7, last_price: Some(200), open_orders: vec![11, 12] };
+    let actions = script.next_actions(0, &view).unwrap();
+    assert_eq!(
+        actions,
+        vec![
+            ScriptAction::Order { order_type: OrderType::Buy, price: 199, quantity: 1 },
+            ScriptAction::Cancel { order_id: 11 },
+            ScriptAction::Sleep(Duration::from_micros(7)),
+        ]
+    );
+}
+
+#[test]
+fn test_bundled_script_and_errors() {
+    let source = std::fs::read_to_string(concat!(env!("CARGO_MANIFEST_DIR"), "/scripts/cancel_storm.rhai")).unwrap();
+    let script = LoadScript::compile(&source).unwrap();
+    let view = MarketView { tick: 0, last_price: None, open_orders: (1..=21).collect() };
+    let actions = script.next_actions(0, &view).unwrap();
+    assert!(actions.contains(&ScriptAction::Cancel { order_id: 1 }));
+
+    assert!(LoadScript::compile("fn next(ctx) {").is_err());
+    let bad = LoadScript::compile(r#"fn next(ctx) { [#{ type: "teleport" }] }"#).unwrap();
+    assert!(bad.next_actions(0, &MarketView::default()).is_err());
+}
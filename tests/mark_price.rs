@@ -0,0 +1,148 @@
+use matching_engine::circuit_breaker::PriceCollar;
+use matching_engine::engine::{ControlCommand, EngineCommand, EngineOutput, MatchingEngine};
+use matching_engine::mark_price::{spawn_http_poller, spawn_udp_listener, MarkPriceFeedConfig, MarkPriceQuote};
+use matching_engine::price::PriceScales;
+use matching_engine::network;
+use matching_engine::protocol::{NewOrderRequest, OrderType, RejectReason, ServerMessage};
+use std::io::{Read, Write};
+use std::net::{TcpListener, UdpSocket};
+use std::time::Duration;
+use tokio::sync::mpsc;
+
+fn order(price: u64) -> EngineCommand {
+    EngineCommand::NewOrder(NewOrderRequest {
+        user_id: 1,
+        symbol: "BTC/USD".to_string(),
+        order_type: OrderType::Buy,
+        price,
+        quantity: 1,
+    })
+}
+
+fn update(mark_price: u64) -> EngineCommand {
+    EngineCommand::Control(ControlCommand::UpdateMarkPrice {
+        symbol: "BTC/USD".to_string(),
+        mark_price,
+        index_price: mark_price - 5,
+    })
+}
+
+#[test]
+fn test_price_collar_follows_mark_price() {
+    let (command_sender, command_receiver) = mpsc::unbounded_channel();
+    let (output_sender, mut output_receiver) = mpsc::unbounded_channel();
+    let mut engine =
+        MatchingEngine::new(command_receiver, output_sender).with_price_collar(PriceCollar { collar_bps: 500 });
+    let engine_thread = std::thread::spawn(move || engine.run());
+
+    // 尚未收到标记价格时不检查
+    command_sender.send(order(10)).unwrap();
+    command_sender.send(update(1_000)).unwrap();
+    command_sender.send(order(1_050)).unwrap();
+    command_sender.send(order(1_051)).unwrap();
+    command_sender.send(order(949)).unwrap();
+    // 标记价格更新后按新的价格计算范围
+    command_sender.send(update(2_000)).unwrap();
+    command_sender.send(order(1_050)).unwrap();
+    command_sender.send(EngineCommand::Control(ControlCommand::Drain)).unwrap();
+    engine_thread.join().unwrap();
+
+    let mut outputs = Vec::new();
+    while let Ok(output) = output_receiver.try_recv() {
+        outputs.push(output);
+    }
+    let results: Vec<Option<RejectReason>> = outputs
+        .iter()
+        .filter_map(|output| match output {
+            EngineOutput::Confirmation(_) => Some(None),
+            EngineOutput::Reject(reject) => Some(Some(reject.reason)),
+            _ => None,
+        })
+        .collect();
+    let breach = Some(RejectReason::PriceCollarBreach);
+    assert_eq!(results, vec![None, None, breach, breach, breach]);
+
+    let marks: Vec<(u64, u64)> = outputs
+        .into_iter()
+        .filter_map(|output| match network::server_message(output) {
+            ServerMessage::MarkPrice(price) => Some((price.mark_price, price.index_price)),
+            _ => None,
+        })
+        .collect();
+    assert_eq!(marks, vec![(1_000, 995), (2_000, 1_995)]);
+}
+
+#[test]
+fn test_parse_quotes_and_sources() {
+    let quote = MarkPriceQuote::parse_json(r#"{"mark_price": 100, "index_price": 99}"#).unwrap();
+    assert_eq!((quote.symbol, quote.mark_price, quote.index_price), (None, 100, 99));
+    assert!(MarkPriceQuote::parse_json(r#"{"mark_price": 0, "index_price": 99}"#).is_err());
+    assert!(MarkPriceQuote::parse_json("100").is_err());
+
+    // 十进制价格按合约的价格精度换算
+    let scales = PriceScales::new([("BTC/USD".to_string(), 2)]);
+    let quote = MarkPriceQuote::parse_scaled(r#"{"mark_price": "50123.45", "index_price": 50120}"#, &scales, Some("BTC/USD"));
+    assert_eq!(quote.map(|quote| (quote.mark_price, quote.index_price)), Ok((5_012_345, 5_012_000)));
+    let udp = r#"{"symbol": "BTC/USD", "mark_price": "1.5", "index_price": "1.49"}"#;
+    assert_eq!(MarkPriceQuote::parse_scaled(udp, &scales, None).map(|quote| quote.mark_price), Ok(150));
+    assert!(MarkPriceQuote::parse_scaled(r#"{"mark_price": "1.005", "index_price": 1}"#, &scales, Some("BTC/USD")).is_err());
+    assert!(MarkPriceQuote::parse_json(r#"{"mark_price": 100.5, "index_price": 99}"#).is_err());
+
+    let spec = "BTC/USD=http://a:80/btc, ETH/USD=http://b/eth";
+    let config = MarkPriceFeedConfig::parse_sources(spec, Duration::ZERO).unwrap();
+    assert_eq!(
+        config.sources,
+        vec![("BTC/USD".to_string(), "a:80/btc".to_string()), ("ETH/USD".to_string(), "b/eth".to_string())]
+    );
+    assert!(MarkPriceFeedConfig::parse_sources("BTC/USD=https://a/btc", Duration::ZERO).is_err());
+    assert!(MarkPriceFeedConfig::parse_sources("", Duration::ZERO).is_err());
+}
+
+fn expect_update(command_receiver: &mut mpsc::UnboundedReceiver<EngineCommand>) -> (String, u64, u64) {
+    match command_receiver.blocking_recv() {
+        Some(EngineCommand::Control(ControlCommand::UpdateMarkPrice { symbol, mark_price, index_price })) => {
+            (symbol, mark_price, index_price)
+        }
+        _ => panic!("期望收到标记价格更新"),
+    }
+}
+
+#[test]
+fn test_http_poller_forwards_quotes() {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap();
+    std::thread::spawn(move || {
+        for stream in listener.incoming() {
+            let mut stream = stream.unwrap();
+            let mut request = [0u8; 1024];
+            let _ = stream.read(&mut request).unwrap();
+            write!(stream, "HTTP/1.0 200 OK\r\n\r\n{{\"mark_price\": 1000, \"index_price\": 998}}").unwrap();
+        }
+    });
+
+    let config = MarkPriceFeedConfig::parse_sources(&format!("BTC/USD=http://{}/btc", addr), Duration::from_millis(10))
+        .unwrap();
+    let (command_sender, mut command_receiver) = mpsc::unbounded_channel();
+    let poller = spawn_http_poller(config, command_sender);
+    for _ in 0..2 {
+        assert_eq!(expect_update(&mut command_receiver), ("BTC/USD".to_string(), 1_000, 998));
+    }
+    // 引擎关闭后轮询线程退出
+    drop(command_receiver);
+    poller.join().unwrap();
+}
+
+#[test]
+fn test_udp_listener_forwards_valid_datagrams() {
+    let socket = UdpSocket::bind("127.0.0.1:0").unwrap();
+    let addr = socket.local_addr().unwrap();
+    let (command_sender, mut command_receiver) = mpsc::unbounded_channel();
+    spawn_udp_listener(socket, PriceScales::default(), command_sender).unwrap();
+
+    let sender = UdpSocket::bind("127.0.0.1:0").unwrap();
+    // 缺少合约名和格式错误的数据报被丢弃
+    sender.send_to(br#"{"mark_price": 10, "index_price": 10}"#, addr).unwrap();
+    sender.send_to(b"not json", addr).unwrap();
+    sender.send_to(br#"{"symbol": "ETH/USD", "mark_price": 300, "index_price": 299}"#, addr).unwrap();
+    assert_eq!(expect_update(&mut command_receiver), ("ETH/USD".to_string(), 300, 299));
+}
@@ -0,0 +1,120 @@
+use matching_engine::engine::{ControlCommand, EngineCommand, EngineOutput, MatchingEngine};
+use matching_engine::market_data::MarketData;
+use matching_engine::protocol::{Candle, MarketDataQuery, NewOrderRequest, OrderType, TradeNotification};
+use std::sync::mpsc as std_mpsc;
+use std::time::Duration;
+use tokio::sync::mpsc;
+
+const SECOND: u64 = 1_000_000_000;
+
+fn trade(timestamp: u64, price: u64, quantity: u64, is_block_trade: bool) -> TradeNotification {
+    TradeNotification {
+        trade_id: 0,
+        symbol: "BTC/USD".into(),
+        matched_price: price,
+        matched_quantity: quantity,
+        buyer_user_id: 1,
+        buyer_order_id: 1,
+        seller_user_id: 2,
+        seller_order_id: 2,
+        timestamp,
+        is_block_trade,
+    }
+}
+
+#[test]
+fn test_candles_roll_per_interval() {
+    let mut data = MarketData::new(&[Duration::from_secs(60), Duration::from_secs(300)], 2);
+    data.on_trade(&trade(60 * SECOND, 100, 1, false));
+    data.on_trade(&trade(70 * SECOND, 105, 2, false));
+    data.on_trade(&trade(80 * SECOND, 98, 3, false));
+    // 大宗交易只计入成交量
+    data.on_trade(&trade(90 * SECOND, 500, 100, true));
+    data.on_trade(&trade(130 * SECOND, 101, 4, false));
+    data.on_trade(&trade(190 * SECOND, 102, 5, false));
+
+    let summary = data.symbol("BTC/USD").unwrap();
+    assert_eq!(summary.last_price, Some(102));
+    assert_eq!((summary.high, summary.low), (Some(105), Some(98)));
+    assert_eq!(summary.volume, 115);
+    assert_eq!(summary.trade_count, 6);
+    assert_eq!(summary.turnover, 100 + 210 + 294 + 50_000 + 404 + 510);
+    // VWAP 不含大宗交易
+    assert_eq!(summary.vwap(), Some(1518.0 / 15.0));
+
+    // 1 分钟 K 线只保留最近 2 根
+    assert_eq!(
+        data.recent_candles("BTC/USD", Duration::from_secs(60), 10),
+        vec![
+            Candle { start: 120 * SECOND, open: 101, high: 101, low: 101, close: 101, volume: 4 },
+            Candle { start: 180 * SECOND, open: 102, high: 102, low: 102, close: 102, volume: 5 },
+        ]
+    );
+    assert_eq!(
+        data.recent_candles("BTC/USD", Duration::from_secs(300), 1),
+        vec![Candle { start: 0, open: 100, high: 105, low: 98, close: 102, volume: 15 }]
+    );
+    // 未配置的周期
+    assert!(data.recent_candles("BTC/USD", Duration::from_secs(30), 10).is_empty());
+
+    data.reset_daily();
+    let summary = data.symbol("BTC/USD").unwrap();
+    assert_eq!((summary.high, summary.low, summary.volume), (None, None, 0));
+    assert_eq!((summary.trade_count, summary.turnover, summary.vwap()), (0, 0, None));
+    assert_eq!(summary.last_price, Some(102));
+    assert_eq!(data.recent_candles("BTC/USD", Duration::from_secs(60), 10).len(), 2);
+}
+
+#[test]
+fn test_query_market_data() {
+    let (commands, command_receiver) = mpsc::unbounded_channel();
+    let (output_sender, mut outputs) = mpsc::unbounded_channel();
+    let engine_thread = std::thread::spawn(move || {
+        MatchingEngine::new(command_receiver, output_sender)
+            .with_candle_intervals(&[Duration::from_secs(1)])
+            .run();
+    });
+
+    for (order_type, price, quantity) in [(OrderType::Sell, 100, 5), (OrderType::Sell, 101, 5), (OrderType::Buy, 101, 8)] {
+        commands.send(EngineCommand::NewOrder(NewOrderRequest {
+            user_id: 1,
+            symbol: "BTC/USD".to_string(),
+            order_type,
+            price,
+            quantity,
+        })).unwrap();
+    }
+    let query = MarketDataQuery {
+        user_id: 7,
+        symbol: "BTC/USD".to_string(),
+        candle_interval_ms: 1_000,
+        candle_limit: 5,
+    };
+    commands.send(EngineCommand::QueryMarketData(query.clone())).unwrap();
+    let (eod_sender, eod_receiver) = std_mpsc::channel();
+    commands.send(EngineCommand::Control(ControlCommand::EndOfDay(eod_sender))).unwrap();
+    commands.send(EngineCommand::QueryMarketData(query)).unwrap();
+    commands.send(EngineCommand::Control(ControlCommand::Drain)).unwrap();
+    engine_thread.join().unwrap();
+    assert_eq!(eod_receiver.recv().unwrap().len(), 2);
+
+    let mut snapshots = Vec::new();
+    while let Ok(output) = outputs.try_recv() {
+        if let EngineOutput::MarketData(snapshot) = output {
+            snapshots.push(snapshot);
+        }
+    }
+    let [before, after] = &snapshots[..] else {
+        panic!("期望收到两次行情查询结果");
+    };
+    assert_eq!(before.user_id, 7);
+    assert_eq!(before.last_price, Some(101));
+    assert_eq!((before.high, before.low, before.volume), (Some(101), Some(100), 8));
+    assert_eq!((before.trade_count, before.turnover), (2, 500 + 303));
+    assert_eq!(before.vwap, Some(803.0 / 8.0));
+    let candle_volume: u64 = before.candles.iter().map(|candle| candle.volume).sum();
+    assert_eq!(candle_volume, 8);
+    // 日终后当日统计清零
+    assert_eq!((after.high, after.low, after.volume), (None, None, 0));
+    assert_eq!(after.last_price, Some(101));
+}
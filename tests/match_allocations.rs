@@ -0,0 +1,82 @@
+use matching_engine::allocation::AllocationPolicy;
+use matching_engine::orderbook::OrderBook;
+use matching_engine::protocol::{NewOrderRequest, OrderType};
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::cell::Cell;
+
+// 统计当前线程在开启计数期间的内存分配次数，其他测试线程的分配不计入
+struct CountingAllocator;
+
+thread_local! {
+    static COUNTING: Cell<bool> = const { Cell::new(false) };
+    static ALLOCATIONS: Cell<usize> = const { Cell::new(0) };
+}
+
+fn record_allocation() {
+    if COUNTING.with(Cell::get) {
+        ALLOCATIONS.with(|count| count.set(count.get() + 1));
+    }
+}
+
+unsafe impl GlobalAlloc for CountingAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        record_allocation();
+        System.alloc(layout)
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        record_allocation();
+        System.realloc(ptr, layout, new_size)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout)
+    }
+}
+
+#[global_allocator]
+static ALLOCATOR: CountingAllocator = CountingAllocator;
+
+fn count_allocations<T>(f: impl FnOnce() -> T) -> (T, usize) {
+    ALLOCATIONS.with(|count| count.set(0));
+    COUNTING.with(|counting| counting.set(true));
+    let result = f();
+    COUNTING.with(|counting| counting.set(false));
+    (result, ALLOCATIONS.with(Cell::get))
+}
+
+fn order(user_id: u64, order_type: OrderType, price: u64, quantity: u64) -> NewOrderRequest {
+    NewOrderRequest { user_id, symbol: "BTC/USD".to_string(), order_type, price, quantity }
+}
+
+// 在 levels 个价格层级上各挂 per_level 笔卖单，再用一笔买单全部吃掉，返回 (成交笔数, 撮合期间的分配次数)。
+// 第一轮让撮合缓冲区增长到最长价格层级的长度，统计的是第二轮
+fn sweep(policy: AllocationPolicy, levels: u64, per_level: u64) -> (usize, usize) {
+    let mut book = OrderBook::new();
+    book.set_allocation_policy(policy);
+    let mut result = (0, 0);
+    for _ in 0..2 {
+        for level in 0..levels {
+            for i in 0..per_level {
+                book.match_order(order(i + 1, OrderType::Sell, 100 + level, 2)).unwrap();
+            }
+        }
+        let sweep = order(99, OrderType::Buy, 100 + levels, 2 * levels * per_level);
+        let ((trades, confirmation), allocations) = count_allocations(|| book.match_order(sweep).unwrap());
+        assert!(confirmation.is_none());
+        result = (trades.len(), allocations);
+    }
+    result
+}
+
+#[test]
+fn test_sweep_allocates_only_trade_list() {
+    for policy in [AllocationPolicy::Fifo, AllocationPolicy::ProRata, AllocationPolicy::TopOrderProRata] {
+        for (levels, per_level) in [(500, 1), (20, 50)] {
+            let (trades, allocations) = sweep(policy, levels, per_level);
+            assert_eq!(trades as u64, levels * per_level);
+            // 成交共享订单簿驻留的合约名，只剩成交列表按倍数扩容
+            assert!(allocations <= 16, "{:?}: {} 笔成交分配了 {} 次", policy, trades, allocations);
+        }
+    }
+}
@@ -0,0 +1,245 @@
+//! 基于 CSV 场景文件的撮合规则测试。
+//!
+//! tests/scenarios 目录下的每个 .csv 文件描述一个场景，业务人员无需编写 Rust 即可补充用例。
+//! 以 `#` 开头的行为注释，首个非注释行为表头 `kind,ref,user,side,price,quantity`。
+//!
+//! 输入行（按顺序送入撮合引擎）：
+//! - `order,<ref>,<user>,<buy|sell>,<price>,<quantity>`：新订单，`ref` 为场景内的订单别名
+//! - `cancel,<ref>,<user>,,,`：撤单
+//! - `amend,<ref>,<user>,,<new_price>,<new_quantity>`：改单
+//! - `auction_start,,,,,` / `auction_end,,,,,`：开始 / 结束集合竞价
+//!
+//! 期望行（与所在位置无关，在全部输入处理完之后校验）：
+//! - `trade,<buy_ref>:<sell_ref>,,,<price>,<quantity>`：按发生顺序列出的全部成交
+//! - `book,,,<buy|sell>,<price>,<quantity>`：场景结束时订单簿的价格层级，买盘从高到低、卖盘从低到高
+
+use matching_engine::engine::{ControlCommand, EngineCommand, EngineOutput, MatchingEngine};
+use matching_engine::protocol::{AmendOrderRequest, CancelOrderRequest, DepthLevel, NewOrderRequest, OrderType};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::mpsc as std_mpsc;
+use tokio::sync::mpsc;
+
+const SYMBOL: &str = "SCENARIO";
+
+struct Row {
+    line: usize,
+    kind: String,
+    reference: String,
+    user: u64,
+    side: Option<OrderType>,
+    price: u64,
+    quantity: u64,
+}
+
+fn parse_scenario(path: &Path) -> Vec<Row> {
+    let content = std::fs::read_to_string(path).expect("无法读取场景文件");
+    let mut rows = Vec::new();
+    for (index, raw) in content.lines().enumerate() {
+        let line = raw.trim();
+        if line.is_empty() || line.starts_with('#') || line.starts_with("kind,") {
+            continue;
+        }
+        let fields: Vec<&str> = line.split(',').map(str::trim).collect();
+        assert_eq!(fields.len(), 6, "{}:{} 应有 6 列", path.display(), index + 1);
+        let number = |field: &str| -> u64 {
+            if field.is_empty() {
+                0
+            } else {
+                field.parse().unwrap_or_else(|_| panic!("{}:{} 无效的数字 {}", path.display(), index + 1, field))
+            }
+        };
+        let side = match fields[3] {
+            "buy" => Some(OrderType::Buy),
+            "sell" => Some(OrderType::Sell),
+            "" => None,
+            other => panic!("{}:{} 无效的方向 {}", path.display(), index + 1, other),
+        };
+        rows.push(Row {
+            line: index + 1,
+            kind: fields[0].to_string(),
+            reference: fields[1].to_string(),
+            user: number(fields[2]),
+            side,
+            price: number(fields[4]),
+            quantity: number(fields[5]),
+        });
+    }
+    rows
+}
+
+// 订单簿一侧的价格层级 (价格, 数量)
+type Levels = Vec<(u64, u64)>;
+
+// 场景执行结果：成交以 (买方别名, 卖方别名, 价格, 数量) 表示，订单簿以 (价格, 数量) 表示
+#[derive(Debug, Default, PartialEq)]
+struct Outcome {
+    trades: Vec<(String, String, u64, u64)>,
+    bids: Levels,
+    asks: Levels,
+}
+
+struct Harness {
+    commands: mpsc::UnboundedSender<EngineCommand>,
+    outputs: mpsc::UnboundedReceiver<EngineOutput>,
+    engine_thread: std::thread::JoinHandle<()>,
+}
+
+impl Harness {
+    fn start() -> Self {
+        let (commands, command_receiver) = mpsc::unbounded_channel();
+        let (output_sender, outputs) = mpsc::unbounded_channel();
+        let engine_thread = std::thread::spawn(move || {
+            MatchingEngine::new(command_receiver, output_sender).run();
+        });
+        Harness { commands, outputs, engine_thread }
+    }
+
+    // 发送一条命令并收集它产生的全部输出。
+    // 引擎按顺序处理命令，因此统计请求得到回复时，前一条命令的输出都已进入输出通道。
+    fn execute(&mut self, command: EngineCommand) -> Vec<EngineOutput> {
+        self.commands.send(command).unwrap();
+        let (reply_tx, reply_rx) = std_mpsc::channel();
+        self.commands.send(EngineCommand::Control(ControlCommand::StatsRequest(reply_tx))).unwrap();
+        reply_rx.recv().expect("撮合引擎已退出");
+        let mut outputs = Vec::new();
+        while let Ok(output) = self.outputs.try_recv() {
+            outputs.push(output);
+        }
+        outputs
+    }
+
+    fn finish(self) -> (Levels, Levels) {
+        let (reply_tx, reply_rx) = std_mpsc::channel();
+        self.commands.send(EngineCommand::SnapshotDepth { depth: usize::MAX, largest_orders: 0, reply: reply_tx }).unwrap();
+        self.commands.send(EngineCommand::Control(ControlCommand::Drain)).unwrap();
+        self.engine_thread.join().unwrap();
+
+        let levels = |levels: Vec<DepthLevel>| -> Levels {
+            levels.into_iter().map(|level| (level.price, level.quantity)).collect()
+        };
+        match reply_rx.iter().find(|snapshot| snapshot.symbol == SYMBOL) {
+            Some(snapshot) => (levels(snapshot.bids), levels(snapshot.asks)),
+            None => (Vec::new(), Vec::new()),
+        }
+    }
+}
+
+fn run_scenario(path: &Path) -> (Outcome, Outcome) {
+    let rows = parse_scenario(path);
+    let mut harness = Harness::start();
+    let mut order_ids: HashMap<String, u64> = HashMap::new();
+    let mut refs: HashMap<u64, String> = HashMap::new();
+    let mut trades = Vec::new();
+    let mut expected = Outcome::default();
+
+    for row in &rows {
+        let location = format!("{}:{}", path.display(), row.line);
+        let order_id = |order_ids: &HashMap<String, u64>| -> u64 {
+            *order_ids.get(&row.reference).unwrap_or_else(|| panic!("{} 未知的订单别名 {}", location, row.reference))
+        };
+        let command = match row.kind.as_str() {
+            "order" => EngineCommand::NewOrder(NewOrderRequest {
+                user_id: row.user,
+                symbol: SYMBOL.to_string(),
+                order_type: row.side.unwrap_or_else(|| panic!("{} 缺少买卖方向", location)),
+                price: row.price,
+                quantity: row.quantity,
+            }),
+            "cancel" => EngineCommand::CancelOrder(CancelOrderRequest {
+                user_id: row.user,
+                symbol: SYMBOL.to_string(),
+                order_id: order_id(&order_ids),
+            }),
+            "amend" => EngineCommand::AmendOrder(AmendOrderRequest {
+                user_id: row.user,
+                symbol: SYMBOL.to_string(),
+                order_id: order_id(&order_ids),
+                new_price: row.price,
+                new_quantity: row.quantity,
+            }),
+            "auction_start" => EngineCommand::Control(ControlCommand::StartAuction(SYMBOL.to_string())),
+            "auction_end" => EngineCommand::Control(ControlCommand::EndAuction(SYMBOL.to_string())),
+            "trade" => {
+                let (buy_ref, sell_ref) = row
+                    .reference
+                    .split_once(':')
+                    .unwrap_or_else(|| panic!("{} 成交别名应为 <buy_ref>:<sell_ref>", location));
+                expected.trades.push((buy_ref.to_string(), sell_ref.to_string(), row.price, row.quantity));
+                continue;
+            }
+            "book" => {
+                match row.side {
+                    Some(OrderType::Buy) => expected.bids.push((row.price, row.quantity)),
+                    Some(OrderType::Sell) => expected.asks.push((row.price, row.quantity)),
+                    None => panic!("{} 缺少买卖方向", location),
+                }
+                continue;
+            }
+            other => panic!("{} 未知的行类型 {}", location, other),
+        };
+
+        let outputs = harness.execute(command);
+
+        // 新订单或改价后的订单号：挂单时来自确认回报，完全成交时来自成交回报中的主动方订单号
+        let mut assigned = outputs.iter().find_map(|output| match output {
+            EngineOutput::Confirmation(confirmation) if confirmation.user_id == row.user => Some(confirmation.order_id),
+            _ => None,
+        });
+        if assigned.is_none() && row.kind == "order" {
+            assigned = outputs.iter().find_map(|output| match (output, row.side) {
+                (EngineOutput::Trade(trade), Some(OrderType::Buy)) => Some(trade.buyer_order_id),
+                (EngineOutput::Trade(trade), Some(OrderType::Sell)) => Some(trade.seller_order_id),
+                _ => None,
+            });
+        }
+        if let Some(id) = assigned {
+            order_ids.insert(row.reference.clone(), id);
+            refs.insert(id, row.reference.clone());
+        }
+
+        for output in outputs {
+            if let EngineOutput::Trade(trade) = output {
+                trades.push(trade);
+            }
+        }
+    }
+
+    let alias = |id: u64| refs.get(&id).cloned().unwrap_or_else(|| format!("#{}", id));
+    let (bids, asks) = harness.finish();
+    let actual = Outcome {
+        trades: trades
+            .iter()
+            .map(|trade| (alias(trade.buyer_order_id), alias(trade.seller_order_id), trade.matched_price, trade.matched_quantity))
+            .collect(),
+        bids,
+        asks,
+    };
+    (actual, expected)
+}
+
+fn scenario_files() -> Vec<PathBuf> {
+    let dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests").join("scenarios");
+    let mut files: Vec<PathBuf> = std::fs::read_dir(dir)
+        .expect("无法读取场景目录")
+        .map(|entry| entry.unwrap().path())
+        .filter(|path| path.extension().is_some_and(|ext| ext == "csv"))
+        .collect();
+    files.sort();
+    files
+}
+
+#[test]
+fn test_matching_scenarios() {
+    let files = scenario_files();
+    assert!(!files.is_empty(), "没有找到任何场景文件");
+
+    let mut failures = Vec::new();
+    for path in &files {
+        let (actual, expected) = run_scenario(path);
+        if actual != expected {
+            failures.push(format!("{}\n  期望: {:?}\n  实际: {:?}", path.display(), expected, actual));
+        }
+    }
+    assert!(failures.is_empty(), "{} 个场景未通过:\n{}", failures.len(), failures.join("\n"));
+}
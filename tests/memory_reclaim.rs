@@ -0,0 +1,98 @@
+use matching_engine::book_analysis::LEVEL_BYTES;
+use matching_engine::engine::{ControlCommand, EngineCommand, MatchingEngine, ReclaimPolicy};
+use matching_engine::orderbook::{MemoryUsage, NodeHandle, OrderBook, OrderNode};
+use matching_engine::protocol::{CancelOrderRequest, NewOrderRequest, OrderType};
+use tokio::sync::mpsc;
+
+fn order(user_id: u64, order_type: OrderType, price: u64, quantity: u64) -> NewOrderRequest {
+    NewOrderRequest {
+        user_id,
+        symbol: "BTC/USD".to_string(),
+        order_type,
+        price,
+        quantity,
+    }
+}
+
+#[test]
+fn test_compact_preserves_priority() {
+    let mut book = OrderBook::new();
+    for i in 0..10 {
+        book.match_order(order(i, OrderType::Sell, 100 + i % 2, 1)).unwrap();
+    }
+    for order_id in [1, 3, 4, 8] {
+        book.cancel_order(order_id).unwrap();
+    }
+    assert_eq!((book.pool_slots(), book.free_slots()), (10, 4));
+
+    assert_eq!(book.compact(), 4);
+    assert_eq!((book.pool_slots(), book.free_slots(), book.order_count()), (6, 0, 6));
+
+    // 压缩后订单号、数量和时间优先级保持不变
+    let (trades, _) = book.match_order(order(99, OrderType::Buy, 101, 6)).unwrap();
+    let sellers: Vec<u64> = trades.iter().map(|t| t.seller_order_id).collect();
+    assert_eq!(sellers, vec![5, 7, 9, 2, 6, 10]);
+    assert_eq!(book.order_count(), 0);
+
+    // 压缩后仍可正常挂单和撤单
+    let confirmation = book.match_order(order(1, OrderType::Buy, 90, 5)).unwrap().1.unwrap();
+    assert!(book.cancel_order(confirmation.order_id).is_ok());
+}
+
+#[test]
+fn test_engine_reclaims_pool() {
+    let (commands, command_receiver) = mpsc::unbounded_channel();
+    let (output_sender, _outputs) = mpsc::unbounded_channel();
+    let mut engine = MatchingEngine::new(command_receiver, output_sender)
+        .with_reclaim_policy(ReclaimPolicy { min_free_slots: 8, max_free_percent: 50 });
+    let metrics = engine.metrics();
+    let engine_thread = std::thread::spawn(move || engine.run());
+
+    for i in 0..16 {
+        commands.send(EngineCommand::NewOrder(order(1, OrderType::Buy, 100 + i, 1))).unwrap();
+    }
+    for order_id in 1..=8 {
+        let cancel = CancelOrderRequest { user_id: 1, symbol: "BTC/USD".to_string(), order_id };
+        commands.send(EngineCommand::CancelOrder(cancel)).unwrap();
+    }
+    commands.send(EngineCommand::Control(ControlCommand::Drain)).unwrap();
+    engine_thread.join().unwrap();
+
+    let snapshot = metrics.snapshot();
+    assert_eq!(snapshot.pool_compactions, 1);
+    assert_eq!(snapshot.pool_slots_reclaimed, 8);
+    // 压缩后节点池只剩存活的挂单
+    let symbol = &metrics.symbol_snapshots()["BTC/USD"];
+    assert_eq!((symbol.pool_slots, symbol.resting_orders), (8, 8));
+}
+
+#[test]
+fn test_node_links_use_compact_handles() {
+    // 链表指针使用 u32 下标，节点正好占一条 64 字节的缓存行
+    assert_eq!(std::mem::size_of::<OrderNode>(), 64);
+}
+
+#[test]
+fn test_memory_usage_counts_pool_capacity() {
+    let mut book = OrderBook::new();
+    book.match_order(order(1, OrderType::Buy, 99, 1)).unwrap();
+    book.match_order(order(2, OrderType::Sell, 101, 1)).unwrap();
+    book.match_order(order(3, OrderType::Sell, 101, 1)).unwrap();
+    book.cancel_order(1).unwrap();
+
+    let usage = book.memory_usage();
+    assert_eq!((usage.bid_levels, usage.ask_levels, usage.resting_orders, usage.pool_slots), (0, 1, 2, 3));
+    // 预分配的节点池按容量计入
+    assert!(usage.pool_capacity >= 1_000_000);
+    let expected = usage.pool_capacity * std::mem::size_of::<OrderNode>()
+        + 2 * std::mem::size_of::<(u64, NodeHandle)>()
+        + LEVEL_BYTES;
+    assert_eq!(usage.bytes, expected);
+
+    // 压缩释放多余容量后占用随之下降
+    book.compact();
+    let compacted = book.memory_usage();
+    assert!(compacted.bytes < usage.bytes);
+    let expected = MemoryUsage { ask_levels: 1, resting_orders: 2, pool_slots: 2, ..Default::default() };
+    assert_eq!(MemoryUsage { pool_capacity: 0, bytes: 0, ..compacted }, expected);
+}
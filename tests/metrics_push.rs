@@ -0,0 +1,32 @@
+use matching_engine::metrics::{DropPolicy, PushBuffer};
+use std::io;
+
+#[test]
+fn test_push_buffer_drop_policies() {
+    let mut buffer = PushBuffer::new(2, DropPolicy::DropOldest);
+    for line in ["a", "b", "c"] {
+        buffer.push(line.to_string());
+    }
+    let mut sent = Vec::new();
+    buffer.flush(|line| {
+        sent.push(line.to_string());
+        Ok(())
+    });
+    assert_eq!(sent, vec!["b", "c"]);
+    assert_eq!(buffer.dropped(), 1);
+
+    let mut buffer = PushBuffer::new(2, DropPolicy::DropNewest);
+    for line in ["a", "b", "c"] {
+        buffer.push(line.to_string());
+    }
+    // 发送失败时数据保留在缓冲区中
+    buffer.flush(|_| Err(io::Error::other("unreachable")));
+    assert_eq!(buffer.len(), 2);
+    let mut sent = Vec::new();
+    buffer.flush(|line| {
+        sent.push(line.to_string());
+        Ok(())
+    });
+    assert_eq!(sent, vec!["a", "b"]);
+    assert!(buffer.is_empty());
+}
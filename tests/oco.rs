@@ -0,0 +1,127 @@
+use matching_engine::engine::{EngineCommand, EngineOutput};
+use matching_engine::protocol::{
+    CancelOrderRequest, CancelStatus, NewOrderRequest, OcoOrderRequest, OrderType, RejectReason,
+};
+use matching_engine::testing::Simulation;
+
+fn new_order(user_id: u64, symbol: &str, order_type: OrderType, price: u64, quantity: u64) -> NewOrderRequest {
+    NewOrderRequest { user_id, symbol: symbol.to_string(), order_type, price, quantity }
+}
+
+fn order(user_id: u64, symbol: &str, order_type: OrderType, price: u64, quantity: u64) -> EngineCommand {
+    EngineCommand::NewOrder(new_order(user_id, symbol, order_type, price, quantity))
+}
+
+fn oco(first: NewOrderRequest, second: NewOrderRequest) -> EngineCommand {
+    EngineCommand::OcoOrder(OcoOrderRequest { link_id: 7, first, second })
+}
+
+fn confirmations(outputs: &[EngineOutput]) -> Vec<u64> {
+    outputs
+        .iter()
+        .filter_map(|output| match output {
+            EngineOutput::Confirmation(confirmation) => Some(confirmation.order_id),
+            _ => None,
+        })
+        .collect()
+}
+
+// 用户 9 的执行回报：(订单号, 成交数量, 关联 ID)
+fn reports(outputs: &[EngineOutput]) -> Vec<(u64, u64, Option<u64>)> {
+    outputs
+        .iter()
+        .filter_map(|output| match output {
+            EngineOutput::ExecutionReport(report) if report.user_id == 9 => {
+                Some((report.order_id, report.last_quantity, report.link_id))
+            }
+            _ => None,
+        })
+        .collect()
+}
+
+fn cancel_acks(outputs: &[EngineOutput]) -> Vec<(u64, u64, u64, CancelStatus)> {
+    outputs
+        .iter()
+        .filter_map(|output| match output {
+            EngineOutput::CancelAck(ack) => Some((ack.user_id, ack.order_id, ack.cancelled_quantity, ack.status)),
+            _ => None,
+        })
+        .collect()
+}
+
+fn rejects(outputs: &[EngineOutput]) -> Vec<(String, RejectReason)> {
+    outputs
+        .iter()
+        .filter_map(|output| match output {
+            EngineOutput::Reject(reject) => Some((reject.symbol.clone(), reject.reason)),
+            _ => None,
+        })
+        .collect()
+}
+
+#[test]
+fn test_fill_of_resting_leg_cancels_the_other() {
+    let mut sim = Simulation::new();
+    let outputs = sim.execute(oco(new_order(9, "BTC", OrderType::Buy, 95, 2), new_order(9, "ETH", OrderType::Sell, 30, 4)));
+    let ids = confirmations(&outputs);
+    assert_eq!(ids.len(), 2);
+    let (buy_id, sell_id) = (ids[0], ids[1]);
+
+    // 买单部分成交：回报带关联 ID，卖单被撤销
+    let outputs = sim.execute(order(1, "BTC", OrderType::Sell, 95, 1));
+    assert_eq!(reports(&outputs), vec![(buy_id, 1, Some(7))]);
+    assert_eq!(cancel_acks(&outputs), vec![(9, sell_id, 4, CancelStatus::LinkedOrderFilled)]);
+
+    // 买单剩余部分的成交仍然带关联 ID，不再有撤单
+    let outputs = sim.execute(order(1, "BTC", OrderType::Sell, 95, 1));
+    assert_eq!(reports(&outputs), vec![(buy_id, 1, Some(7))]);
+    assert!(cancel_acks(&outputs).is_empty());
+
+    // 被自动撤销的订单再次撤单得到幂等回报
+    let cancel = CancelOrderRequest { user_id: 9, symbol: "ETH".to_string(), order_id: sell_id };
+    let outputs = sim.execute(EngineCommand::CancelOrder(cancel));
+    assert_eq!(cancel_acks(&outputs), vec![(9, sell_id, 0, CancelStatus::AlreadyCancelled)]);
+}
+
+#[test]
+fn test_immediate_fill_of_first_leg_skips_second() {
+    let mut sim = Simulation::new();
+    sim.execute(order(1, "BTC", OrderType::Sell, 100, 1));
+    let outputs = sim.execute(oco(new_order(9, "BTC", OrderType::Buy, 100, 1), new_order(9, "BTC", OrderType::Sell, 120, 1)));
+    assert_eq!(reports(&outputs).iter().map(|report| report.2).collect::<Vec<_>>(), vec![Some(7)]);
+    assert!(confirmations(&outputs).is_empty());
+    assert_eq!(rejects(&outputs), vec![("BTC".to_string(), RejectReason::LinkedOrderFilled)]);
+}
+
+#[test]
+fn test_immediate_fill_of_second_leg_cancels_first() {
+    let mut sim = Simulation::new();
+    sim.execute(order(1, "ETH", OrderType::Sell, 30, 5));
+    let outputs = sim.execute(oco(new_order(9, "BTC", OrderType::Buy, 90, 1), new_order(9, "ETH", OrderType::Buy, 30, 2)));
+    let first_id = confirmations(&outputs)[0];
+    assert_eq!(reports(&outputs).iter().map(|report| (report.1, report.2)).collect::<Vec<_>>(), vec![(2, Some(7))]);
+    assert_eq!(cancel_acks(&outputs), vec![(9, first_id, 1, CancelStatus::LinkedOrderFilled)]);
+}
+
+#[test]
+fn test_invalid_leg_rejects_pair() {
+    let mut sim = Simulation::new();
+    let outputs = sim.execute(oco(new_order(9, "BTC", OrderType::Buy, 90, 1), new_order(9, "ETH", OrderType::Sell, 0, 1)));
+    assert_eq!(rejects(&outputs), vec![("ETH".to_string(), RejectReason::InvalidPrice)]);
+    assert!(confirmations(&outputs).is_empty());
+    let outputs = sim.execute(oco(new_order(9, "BTC", OrderType::Buy, 90, 1), new_order(8, "ETH", OrderType::Sell, 30, 1)));
+    assert_eq!(rejects(&outputs), vec![("ETH".to_string(), RejectReason::UserMismatch)]);
+}
+
+#[test]
+fn test_cancelling_one_leg_unlinks_the_other() {
+    let mut sim = Simulation::new();
+    let outputs = sim.execute(oco(new_order(9, "BTC", OrderType::Buy, 95, 1), new_order(9, "ETH", OrderType::Sell, 30, 1)));
+    let ids = confirmations(&outputs);
+    let cancel = CancelOrderRequest { user_id: 9, symbol: "BTC".to_string(), order_id: ids[0] };
+    sim.execute(EngineCommand::CancelOrder(cancel));
+
+    let outputs = sim.execute(order(1, "ETH", OrderType::Buy, 30, 1));
+    assert_eq!(reports(&outputs), vec![(ids[1], 1, Some(7))]);
+    assert!(cancel_acks(&outputs).is_empty());
+}
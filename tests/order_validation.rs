@@ -0,0 +1,104 @@
+use matching_engine::engine::{ControlCommand, EngineCommand, EngineOutput, MatchingEngine};
+use matching_engine::error::EngineError;
+use matching_engine::orderbook::OrderBook;
+use matching_engine::protocol::{AmendOrderRequest, NewOrderRequest, OrderType, RejectReason};
+use tokio::sync::mpsc;
+
+fn order(price: u64, quantity: u64) -> NewOrderRequest {
+    NewOrderRequest {
+        user_id: 1,
+        symbol: "ES".to_string(),
+        order_type: OrderType::Buy,
+        price,
+        quantity,
+    }
+}
+
+#[test]
+fn test_orderbook_validation() {
+    let mut book = OrderBook::new();
+    book.set_tick_size(25);
+
+    assert_eq!(
+        book.match_order(order(0, 1)).unwrap_err(),
+        EngineError::InvalidPrice { price: 0, tick_size: 25 }
+    );
+    assert_eq!(book.match_order(order(100, 0)).unwrap_err(), EngineError::InvalidQuantity);
+    assert_eq!(
+        book.match_order(order(110, 1)).unwrap_err(),
+        EngineError::InvalidPrice { price: 110, tick_size: 25 }
+    );
+    assert_eq!(book.order_count(), 0);
+    assert!(book.match_order(order(125, 1)).is_ok());
+    assert_eq!(book.order_count(), 1);
+
+    assert_eq!(book.reduce_order(1, 2).unwrap_err(), EngineError::InvalidQuantity);
+    assert_eq!(book.cancel_order(1).map(|node| node.quantity), Ok(1));
+    assert_eq!(book.cancel_order(1).map(|node| node.quantity), Err(EngineError::OrderNotFound(1)));
+}
+
+#[test]
+fn test_engine_rejects_invalid_orders() {
+    let (commands, command_receiver) = mpsc::unbounded_channel();
+    let (output_sender, mut outputs) = mpsc::unbounded_channel();
+    let engine_thread = std::thread::spawn(move || {
+        MatchingEngine::new(command_receiver, output_sender).with_tick_size("ES", 25).run();
+    });
+
+    commands.send(EngineCommand::NewOrder(order(110, 1))).unwrap();
+    commands.send(EngineCommand::NewOrder(order(100, 0))).unwrap();
+    commands.send(EngineCommand::NewOrder(order(100, 5))).unwrap();
+    // 改到无效价格被拒绝，原订单保持不变
+    commands
+        .send(EngineCommand::AmendOrder(AmendOrderRequest {
+            user_id: 1,
+            symbol: "ES".to_string(),
+            order_id: 1,
+            new_price: 101,
+            new_quantity: 5,
+        }))
+        .unwrap();
+    commands.send(EngineCommand::Control(ControlCommand::Drain)).unwrap();
+    engine_thread.join().unwrap();
+
+    let mut results = Vec::new();
+    while let Ok(output) = outputs.try_recv() {
+        results.push(output);
+    }
+    assert!(matches!(&results[0], EngineOutput::Reject(r) if r.reason == RejectReason::InvalidPrice));
+    assert!(matches!(&results[1], EngineOutput::Reject(r) if r.reason == RejectReason::InvalidQuantity));
+    assert!(matches!(&results[2], EngineOutput::Confirmation(c) if c.order_id == 1));
+    assert!(matches!(&results[3], EngineOutput::Reject(r) if r.reason == RejectReason::InvalidPrice));
+    assert_eq!(results.len(), 4);
+}
+
+#[test]
+fn test_amend_by_other_user_is_rejected() {
+    let (commands, command_receiver) = mpsc::unbounded_channel();
+    let (output_sender, mut outputs) = mpsc::unbounded_channel();
+    let engine_thread = std::thread::spawn(move || {
+        MatchingEngine::new(command_receiver, output_sender).with_tick_size("ES", 25).run();
+    });
+    let amend = |user_id, new_price, new_quantity| {
+        EngineCommand::AmendOrder(AmendOrderRequest { user_id, symbol: "ES".to_string(), order_id: 1, new_price, new_quantity })
+    };
+
+    commands.send(EngineCommand::NewOrder(order(100, 5))).unwrap();
+    // 原地减量和撤单重下两种改单都要求是订单的所有者
+    commands.send(amend(2, 100, 3)).unwrap();
+    commands.send(amend(2, 125, 5)).unwrap();
+    commands.send(amend(1, 100, 3)).unwrap();
+    commands.send(EngineCommand::Control(ControlCommand::Drain)).unwrap();
+    engine_thread.join().unwrap();
+
+    let mut results = Vec::new();
+    while let Ok(output) = outputs.try_recv() {
+        results.push(output);
+    }
+    assert!(matches!(&results[0], EngineOutput::Confirmation(c) if c.order_id == 1));
+    for result in &results[1..3] {
+        assert!(matches!(result, EngineOutput::Reject(r) if r.user_id == 2 && r.reason == RejectReason::NotOrderOwner));
+    }
+    assert!(matches!(&results[3], EngineOutput::Confirmation(c) if c.order_id == 1 && c.user_id == 1));
+    assert_eq!(results.len(), 4);
+}
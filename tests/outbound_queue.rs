@@ -0,0 +1,122 @@
+use bytes::Bytes;
+use matching_engine::engine::EngineOutput;
+use matching_engine::metrics::OutboundSnapshot;
+use matching_engine::network::{self, OutboundConfig, OutboundMessage, OutboundQueue, OverflowPolicy, PushOutcome};
+use matching_engine::protocol::{
+    CancelAck, CancelStatus, ImpliedQuote, MarkPrice, OrderConfirmation, OrderReject, RejectReason,
+};
+
+fn message(tag: u8, essential: bool) -> OutboundMessage {
+    OutboundMessage { payload: Bytes::from(vec![tag]), essential, trace: None }
+}
+
+fn drain(queue: &mut OutboundQueue) -> Vec<u8> {
+    std::iter::from_fn(|| queue.pop()).map(|message| message.payload[0]).collect()
+}
+
+fn config(overflow: OverflowPolicy) -> OutboundConfig {
+    OutboundConfig { capacity: 2, overflow, max_essential_backlog: 3 }
+}
+
+#[test]
+fn test_drop_oldest_keeps_essential_messages() {
+    let mut queue = OutboundQueue::new(config(OverflowPolicy::DropOldest));
+    assert_eq!(queue.push(message(1, false)), PushOutcome::Queued);
+    assert_eq!(queue.push(message(2, true)), PushOutcome::Queued);
+    assert_eq!(queue.push(message(3, false)), PushOutcome::Queued);
+    // 行情消息已满，丢弃最旧的行情消息 1，执行回报 2 保留
+    assert_eq!(queue.push(message(4, false)), PushOutcome::Dropped);
+    assert_eq!(queue.dropped(), 1);
+    assert_eq!(drain(&mut queue), vec![2, 3, 4]);
+    assert!(queue.is_empty());
+}
+
+#[test]
+fn test_drop_newest() {
+    let mut queue = OutboundQueue::new(config(OverflowPolicy::DropNewest));
+    queue.push(message(1, false));
+    queue.push(message(2, false));
+    assert_eq!(queue.push(message(3, false)), PushOutcome::Dropped);
+    // 关键消息不受行情容量限制
+    assert_eq!(queue.push(message(4, true)), PushOutcome::Queued);
+    assert_eq!(drain(&mut queue), vec![1, 2, 4]);
+}
+
+#[test]
+fn test_disconnect_policies() {
+    let mut queue = OutboundQueue::new(config(OverflowPolicy::Disconnect));
+    queue.push(message(1, false));
+    queue.push(message(2, false));
+    assert_eq!(queue.push(message(3, false)), PushOutcome::Disconnect);
+
+    // 关键消息积压超过上限时，无论行情策略如何都只能断开
+    let mut queue = OutboundQueue::new(config(OverflowPolicy::DropOldest));
+    for tag in 0..3 {
+        assert_eq!(queue.push(message(tag, true)), PushOutcome::Queued);
+    }
+    assert_eq!(queue.push(message(3, true)), PushOutcome::Disconnect);
+    assert_eq!(queue.len(), 3);
+}
+
+fn tagged(tag: u8, output: &EngineOutput) -> OutboundMessage {
+    message(tag, network::is_essential(output))
+}
+
+#[test]
+fn test_private_replies_survive_overflow() {
+    let confirmation = EngineOutput::Confirmation(OrderConfirmation { order_id: 1, user_id: 7 });
+    let reject = EngineOutput::Response {
+        request_id: 9,
+        connection_id: 1,
+        output: Box::new(EngineOutput::Reject(OrderReject {
+            user_id: 7,
+            symbol: "BTC".to_string(),
+            reason: RejectReason::InvalidPrice,
+        })),
+    };
+    let cancel_ack = EngineOutput::CancelAck(CancelAck {
+        user_id: 7,
+        symbol: "BTC".to_string(),
+        order_id: 1,
+        cancelled_quantity: 10,
+        status: CancelStatus::Cancelled,
+    });
+    let mark_price =
+        EngineOutput::MarkPrice(MarkPrice { symbol: "BTC".to_string(), mark_price: 100, index_price: 100, timestamp: 0 });
+    let implied_quote = EngineOutput::ImpliedQuote(ImpliedQuote {
+        spread: "BTC-CAL".to_string(),
+        bid_price: None,
+        bid_quantity: 0,
+        ask_price: None,
+        ask_quantity: 0,
+    });
+
+    for overflow in [OverflowPolicy::DropOldest, OverflowPolicy::DropNewest] {
+        let mut queue = OutboundQueue::new(config(overflow));
+        assert_eq!(queue.push(tagged(1, &mark_price)), PushOutcome::Queued);
+        assert_eq!(queue.push(tagged(2, &confirmation)), PushOutcome::Queued);
+        assert_eq!(queue.push(tagged(3, &implied_quote)), PushOutcome::Queued);
+        // 公共行情已占满容量，之后的回报和应答照样入队，新的行情被丢弃
+        assert_eq!(queue.push(tagged(4, &reject)), PushOutcome::Queued);
+        assert_eq!(queue.push(tagged(5, &mark_price)), PushOutcome::Dropped);
+        assert_eq!(queue.push(tagged(6, &cancel_ack)), PushOutcome::Queued);
+        let delivered = drain(&mut queue);
+        for reply in [2, 4, 6] {
+            assert!(delivered.contains(&reply), "{:?} 下回报 {} 被丢弃: {:?}", overflow, reply, delivered);
+        }
+        assert_eq!(queue.dropped(), 1);
+    }
+}
+
+#[test]
+fn test_outbound_statsd_lines() {
+    let snapshot = OutboundSnapshot { queue_depth: 5, market_data_dropped: 2, slow_consumer_disconnects: 1 };
+    assert_eq!(
+        snapshot.to_statsd_lines("me"),
+        vec![
+            "me.outbound_queue_depth:5|g",
+            "me.outbound_market_data_dropped:2|g",
+            "me.outbound_slow_consumer_disconnects:1|g",
+        ]
+    );
+}
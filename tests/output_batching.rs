@@ -0,0 +1,106 @@
+use matching_engine::engine::{ControlCommand, EngineCommand, EngineOutput, MatchingEngine, OutputBatching};
+use matching_engine::harness::TestServer;
+use matching_engine::protocol::{ClientMessage, NewOrderRequest, OrderType, ServerMessage};
+use matching_engine::session::SessionConfig;
+use tokio::sync::mpsc;
+
+fn new_order(user_id: u64, order_type: OrderType, price: u64, quantity: u64) -> NewOrderRequest {
+    NewOrderRequest { user_id, symbol: "BTC/USD".to_string(), order_type, price, quantity }
+}
+
+// 5 笔卖单挂在不同价格，一笔买单扫过全部价格
+fn sweep() -> Vec<EngineCommand> {
+    let mut commands: Vec<EngineCommand> =
+        (0..5).map(|index| EngineCommand::NewOrder(new_order(1, OrderType::Sell, 100 + index, 2))).collect();
+    commands.push(EngineCommand::NewOrder(new_order(2, OrderType::Buy, 110, 12)));
+    commands
+}
+
+// 在引擎线程上运行全部命令，返回通道中收到的输出
+fn run(commands: Vec<EngineCommand>, batching: Option<OutputBatching>) -> Vec<EngineOutput> {
+    let (command_sender, command_receiver) = mpsc::unbounded_channel();
+    let (output_sender, mut outputs) = mpsc::unbounded_channel();
+    let mut engine = MatchingEngine::new(command_receiver, output_sender);
+    if let Some(batching) = batching {
+        engine = engine.with_output_batching(batching);
+    }
+    for command in commands {
+        command_sender.send(command).unwrap();
+    }
+    command_sender.send(EngineCommand::Control(ControlCommand::Drain)).unwrap();
+    std::thread::spawn(move || engine.run()).join().unwrap();
+    let mut received = Vec::new();
+    while let Ok(output) = outputs.try_recv() {
+        received.push(output);
+    }
+    received
+}
+
+// 成交的时间戳取自时钟，比较时略去
+fn describe(output: &EngineOutput) -> String {
+    match output {
+        EngineOutput::Trade(trade) => format!("trade {} {}@{}", trade.trade_id, trade.matched_quantity, trade.matched_price),
+        output => format!("{:?}", output),
+    }
+}
+
+fn flatten(outputs: &[EngineOutput]) -> Vec<String> {
+    let mut flat = Vec::new();
+    for output in outputs {
+        match output {
+            EngineOutput::Batch(batch) => flat.extend(batch.iter().map(describe)),
+            output => flat.push(describe(output)),
+        }
+    }
+    flat
+}
+
+#[test]
+fn test_each_command_sends_one_batch() {
+    let unbatched = run(sweep(), None);
+    let batched = run(sweep(), Some(OutputBatching::default()));
+    // 顺序与逐条发送时相同
+    assert_eq!(flatten(&batched), flatten(&unbatched));
+    // 挂单只有一条确认，不包成批；扫单的 5 笔成交和新挂单的确认合成一批
+    assert_eq!(batched.len(), 6);
+    assert!(batched[..5].iter().all(|output| matches!(output, EngineOutput::Confirmation(_))));
+    let EngineOutput::Batch(batch) = &batched[5] else {
+        panic!("期望收到批量输出: {:?}", batched[5]);
+    };
+    assert_eq!(batch.iter().filter(|output| matches!(output, EngineOutput::Trade(_))).count(), 5);
+    assert!(matches!(batch.last(), Some(EngineOutput::Confirmation(_))));
+}
+
+#[test]
+fn test_backlog_is_batched_until_idle_up_to_max() {
+    let backlog = || (0..20).flat_map(|_| sweep()).collect::<Vec<_>>();
+    let unbatched = run(backlog(), None);
+    let batched = run(backlog(), Some(OutputBatching { max_outputs: 16, until_idle: true }));
+    assert_eq!(flatten(&batched), flatten(&unbatched));
+    // 命令全部积压在队列中，输出只在缓冲满和队列排空时发送
+    assert_eq!(batched.len(), unbatched.len().div_ceil(16));
+    assert!(batched.iter().all(|output| matches!(output, EngineOutput::Batch(batch) if batch.len() <= 16)));
+}
+
+#[tokio::test]
+async fn test_clients_receive_batched_outputs_individually() {
+    let batching = OutputBatching::default();
+    let server = TestServer::start_with(SessionConfig::default(), move |engine| engine.with_output_batching(batching)).await;
+    let mut client = server.connect().await;
+    for command in sweep() {
+        let EngineCommand::NewOrder(request) = command else { unreachable!() };
+        client.send(ClientMessage::NewOrder(request)).await;
+    }
+    let mut prices = Vec::new();
+    while prices.len() < 5 {
+        let price = client
+            .expect(|message| match message {
+                ServerMessage::Trade(trade) => Some(trade.matched_price),
+                _ => None,
+            })
+            .await;
+        prices.push(price);
+    }
+    assert_eq!(prices, [100, 101, 102, 103, 104]);
+    server.shutdown().await;
+}
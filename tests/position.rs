@@ -0,0 +1,100 @@
+use matching_engine::engine::{BlockTradeRules, EngineCommand, EngineOutput, MatchingEngine};
+use matching_engine::position::{PositionLimits, PositionTracker};
+use matching_engine::protocol::{
+    AmendOrderRequest, BlockTradeRequest, NewOrderRequest, OrderType, RejectReason, TradeNotification,
+};
+use tokio::sync::mpsc;
+
+fn trade(buyer_user_id: u64, seller_user_id: u64, quantity: u64) -> TradeNotification {
+    TradeNotification {
+        trade_id: 1,
+        symbol: "BTC/USD".into(),
+        matched_price: 50000,
+        matched_quantity: quantity,
+        buyer_user_id,
+        buyer_order_id: 1,
+        seller_user_id,
+        seller_order_id: 2,
+        timestamp: 0,
+        is_block_trade: false,
+    }
+}
+
+#[test]
+fn test_positions_and_limits() {
+    let limits = PositionLimits { default_limit: Some(10), ..Default::default() };
+    let mut tracker = PositionTracker::new(limits);
+
+    tracker.apply_trade(&trade(1, 2, 8));
+    assert_eq!(tracker.position(1, "BTC/USD"), 8);
+    assert_eq!(tracker.position(2, "BTC/USD"), -8);
+    assert_eq!(tracker.position(1, "ETH/USD"), 0);
+
+    // 多头 8，再买 3 会超过限额 10，卖出则是减仓
+    assert!(!tracker.check_order(1, "BTC/USD", OrderType::Buy, 3, (0, 0)));
+    assert!(tracker.check_order(1, "BTC/USD", OrderType::Buy, 2, (0, 0)));
+    assert!(tracker.check_order(1, "BTC/USD", OrderType::Sell, 18, (0, 0)));
+    assert!(!tracker.check_order(1, "BTC/USD", OrderType::Sell, 19, (0, 0)));
+    // 同方向的挂单计入敞口，反方向的不计入
+    assert!(!tracker.check_order(1, "BTC/USD", OrderType::Buy, 1, (2, 0)));
+    assert!(tracker.check_order(1, "BTC/USD", OrderType::Buy, 1, (1, 18)));
+    assert!(!tracker.check_order(1, "BTC/USD", OrderType::Sell, 1, (0, 18)));
+    // 超过限额的空头仍然可以买入减仓
+    tracker.set_position(2, "BTC/USD", -30);
+    assert!(tracker.check_order(2, "BTC/USD", OrderType::Buy, 5, (0, 0)));
+}
+
+// 引擎的持仓限额检查计入用户已有的挂单，并同样适用于大宗交易
+#[test]
+fn test_engine_limits_open_orders_and_block_trades() {
+    let (_commands, command_receiver) = mpsc::unbounded_channel();
+    let (output_sender, mut outputs) = mpsc::unbounded_channel();
+    let limits = PositionLimits { default_limit: Some(10), ..Default::default() };
+    let rules = BlockTradeRules { min_quantity: 1, max_price_deviation_bps: 1_000 };
+    let mut engine =
+        MatchingEngine::new(command_receiver, output_sender).with_position_limits(limits).with_block_trade_rules(rules);
+    let order = |user_id, order_type, price, quantity| {
+        EngineCommand::NewOrder(NewOrderRequest { user_id, symbol: "BTC/USD".to_string(), order_type, price, quantity })
+    };
+    let block_trade = |buyer_user_id, seller_user_id, quantity| {
+        EngineCommand::BlockTrade(BlockTradeRequest {
+            symbol: "BTC/USD".to_string(),
+            price: 100,
+            quantity,
+            buyer_user_id,
+            seller_user_id,
+        })
+    };
+    let mut rejects = || {
+        let mut reasons = Vec::new();
+        while let Ok(output) = outputs.try_recv() {
+            if let EngineOutput::Reject(reject) = output {
+                reasons.push(reject.reason);
+            }
+        }
+        reasons
+    };
+
+    engine.handle_command(order(1, OrderType::Buy, 90, 6));
+    engine.handle_command(order(1, OrderType::Buy, 91, 4));
+    assert!(rejects().is_empty());
+    // 两笔挂单已占满限额
+    engine.handle_command(order(1, OrderType::Buy, 92, 1));
+    engine.handle_command(block_trade(1, 2, 1));
+    assert_eq!(rejects(), vec![RejectReason::PositionLimitExceeded; 2]);
+    // 改单只把原订单之外的数量计入敞口
+    engine.handle_command(EngineCommand::AmendOrder(AmendOrderRequest {
+        user_id: 1,
+        symbol: "BTC/USD".to_string(),
+        order_id: 2,
+        new_price: 92,
+        new_quantity: 4,
+    }));
+    assert!(rejects().is_empty());
+    // 反方向的挂单不计入，卖方超出限额时同样拒绝
+    engine.handle_command(order(1, OrderType::Sell, 200, 10));
+    engine.handle_command(block_trade(3, 2, 10));
+    assert!(rejects().is_empty());
+    engine.handle_command(block_trade(4, 2, 1));
+    assert_eq!(rejects(), vec![RejectReason::PositionLimitExceeded]);
+}
@@ -0,0 +1,60 @@
+use matching_engine::instruments::InstrumentMaster;
+use matching_engine::price::{Price, PriceError, PriceScales};
+
+#[test]
+fn test_parse_and_display() {
+    assert_eq!(Price::parse("50123.45"), Ok(Price::new(5_012_345, 2)));
+    assert_eq!(Price::parse("0.0001"), Ok(Price::new(1, 4)));
+    assert_eq!(Price::parse("42"), Ok(Price::new(42, 0)));
+    assert_eq!("7.50".parse::<Price>(), Ok(Price::new(750, 2)));
+    for text in ["", ".5", "5.", "-1", "1e3", "1.2.3", " 1", "1,5"] {
+        assert!(matches!(Price::parse(text), Err(PriceError::Malformed(_))), "{:?}", text);
+    }
+    assert!(matches!(Price::parse("18446744073709551616"), Err(PriceError::Overflow(_))));
+    assert!(matches!(Price::parse("0.0000000000000000001"), Err(PriceError::TooPrecise { .. })));
+
+    assert_eq!(Price::new(5_012_345, 2).to_string(), "50123.45");
+    assert_eq!(Price::new(5, 3).to_string(), "0.005");
+    assert_eq!(Price::new(750, 2).to_string(), "7.50");
+    assert_eq!(Price::new(42, 0).to_string(), "42");
+}
+
+#[test]
+fn test_rescale_is_exact() {
+    let price = Price::parse("50123.45").unwrap();
+    assert_eq!(price.to_ticks(4), Ok(501_234_500));
+    assert_eq!(price.to_ticks(2), Ok(5_012_345));
+    // 截去非零的小数位会丢失精度
+    assert!(matches!(price.to_ticks(1), Err(PriceError::TooPrecise { .. })));
+    assert_eq!(Price::parse("50123.40").unwrap().to_ticks(1), Ok(501_234));
+    assert!(matches!(Price::new(u64::MAX, 0).to_ticks(1), Err(PriceError::Overflow(_))));
+    assert!(matches!(Price::new(1, 0).to_ticks(19), Err(PriceError::TooPrecise { .. })));
+}
+
+#[test]
+fn test_json_representation() {
+    let price: Price = serde_json::from_str(r#""0.25""#).unwrap();
+    assert_eq!(price, Price::new(25, 2));
+    let price: Price = serde_json::from_str("300").unwrap();
+    assert_eq!(price, Price::new(300, 0));
+    assert_eq!(serde_json::to_string(&Price::new(25, 2)).unwrap(), r#""0.25""#);
+    // 带小数点的 JSON 数字会经过 f64，不接受
+    assert!(serde_json::from_str::<Price>("0.25").is_err());
+    assert!(serde_json::from_str::<Price>("-3").is_err());
+}
+
+#[test]
+fn test_scales_from_reference_data() {
+    let master = InstrumentMaster::parse_json(
+        r#"{"instruments": [{"symbol": "BTC/USD", "tick_size": 50, "price_exponent": 2}, {"symbol": "ETH/USD"}]}"#,
+    )
+    .unwrap();
+    let scales = master.price_scales();
+    assert_eq!((scales.exponent("BTC/USD"), scales.exponent("ETH/USD"), scales.exponent("SOL/USD")), (2, 0, 0));
+    assert_eq!(scales.to_ticks("BTC/USD", Price::parse("50123.5").unwrap()), Ok(5_012_350));
+    assert_eq!(scales.to_price("BTC/USD", 5_012_350).to_string(), "50123.50");
+    assert_eq!(PriceScales::default().to_price("BTC/USD", 7), Price::new(7, 0));
+
+    let invalid = r#"{"instruments": [{"symbol": "A", "price_exponent": 19}]}"#;
+    assert!(InstrumentMaster::parse_json(invalid).is_err());
+}
@@ -0,0 +1,167 @@
+use matching_engine::engine::{EngineCommand, EngineOutput, MatchingEngine};
+use matching_engine::priority_lanes::{LaneWeights, PriorityLanes};
+use matching_engine::protocol::{CancelOrderRequest, CancelStatus, NewOrderRequest, OrderType};
+use tokio::sync::mpsc;
+
+fn new_order(user_id: u64, price: u64) -> EngineCommand {
+    EngineCommand::NewOrder(NewOrderRequest {
+        user_id,
+        symbol: "BTC/USD".to_string(),
+        order_type: OrderType::Buy,
+        price,
+        quantity: 1,
+    })
+}
+
+fn cancel(order_id: u64) -> EngineCommand {
+    EngineCommand::CancelOrder(CancelOrderRequest { user_id: 1, symbol: "BTC/USD".to_string(), order_id })
+}
+
+// 命令类型和用户，例如新订单 "N2"、撤单 "C1"
+fn describe(command: &EngineCommand) -> String {
+    let kind = if PriorityLanes::is_priority(command) { 'C' } else { 'N' };
+    format!("{}{}", kind, command.user_id().unwrap())
+}
+
+// 依次取出全部命令，新订单记为 'N'，撤单记为 'C'
+fn drain(lanes: &mut PriorityLanes) -> String {
+    std::iter::from_fn(|| lanes.pop())
+        .map(|command| if PriorityLanes::is_priority(&command) { 'C' } else { 'N' })
+        .collect()
+}
+
+#[test]
+fn test_weighted_lanes_without_starvation() {
+    let mut lanes = PriorityLanes::new(LaneWeights { priority: 2, normal: 1 });
+    for index in 0..4 {
+        lanes.push(new_order(2, 100 + index));
+    }
+    for order_id in 0..6 {
+        lanes.push(cancel(order_id));
+    }
+    assert_eq!((lanes.len(), lanes.priority_len()), (10, 6));
+    // 撤单再多，每轮也有一条新订单得到处理；一条车道空了之后直接处理另一条
+    assert_eq!(drain(&mut lanes), "CCNCCNCCNN");
+    assert!(lanes.is_empty());
+
+    // 同一车道内保持到达顺序
+    for price in [101, 102, 103] {
+        lanes.push(new_order(2, price));
+    }
+    let prices: Vec<u64> = std::iter::from_fn(|| lanes.pop())
+        .map(|command| match command {
+            EngineCommand::NewOrder(request) => request.price,
+            _ => unreachable!(),
+        })
+        .collect();
+    assert_eq!(prices, [101, 102, 103]);
+}
+
+#[test]
+fn test_cancel_never_overtakes_own_session() {
+    let mut lanes = PriorityLanes::new(LaneWeights::default());
+    lanes.push(new_order(2, 100));
+    lanes.push(new_order(2, 101));
+    lanes.push(new_order(1, 102));
+    lanes.push(EngineCommand::CancelUserOrders(1));
+    lanes.push(EngineCommand::CancelUserOrders(3));
+    lanes.push(EngineCommand::CancelUserOrders(1));
+    // 用户 3 的撤单越过所有新订单；用户 1 的两条撤单等到它自己的新订单处理之后，彼此保持顺序
+    let order: Vec<String> = std::iter::from_fn(|| lanes.pop()).map(|command| describe(&command)).collect();
+    assert_eq!(order, ["C3", "N2", "N2", "N1", "C1", "C1"]);
+}
+
+#[test]
+fn test_blocked_cancels_resume_in_arrival_order() {
+    let mut lanes = PriorityLanes::new(LaneWeights::default());
+    lanes.push(new_order(1, 100));
+    lanes.push(EngineCommand::CancelUserOrders(1));
+    lanes.push(EngineCommand::CancelUserOrders(1));
+    lanes.push(new_order(2, 101));
+    lanes.push(EngineCommand::CancelUserOrders(3));
+    lanes.push(EngineCommand::CancelUserOrders(4));
+    assert_eq!((lanes.len(), lanes.priority_len()), (6, 4));
+    // 用户 1 的撤单在它的新订单出队后才可以处理，之后仍排在普通车道剩余的新订单之前
+    let order: Vec<String> = std::iter::from_fn(|| lanes.pop()).map(|command| describe(&command)).collect();
+    assert_eq!(order, ["C3", "C4", "N1", "C1", "C1", "N2"]);
+    assert_eq!((lanes.len(), lanes.priority_len()), (0, 0));
+}
+
+#[test]
+fn test_parse_lane_weights() {
+    assert_eq!(LaneWeights::parse("8:1"), Ok(LaneWeights { priority: 8, normal: 1 }));
+    assert!(LaneWeights::parse("8").is_err());
+    assert!(LaneWeights::parse("8:0").is_err());
+}
+
+// 先挂一条订单，再在同一批命令中灌入大量新订单和一条撤单，返回撤单回报之前收到的挂单确认数
+fn confirmations_before_cancel(lanes: Option<LaneWeights>) -> usize {
+    const FLOOD: u64 = 5_000;
+    let (command_sender, command_receiver) = mpsc::unbounded_channel();
+    let (output_sender, mut output_receiver) = mpsc::unbounded_channel();
+    let mut engine = MatchingEngine::new(command_receiver, output_sender);
+    if let Some(weights) = lanes {
+        engine = engine.with_priority_lanes(weights);
+    }
+    engine.handle_command(new_order(1, 100));
+    let Ok(EngineOutput::Confirmation(confirmation)) = output_receiver.try_recv() else {
+        panic!("期望收到挂单确认");
+    };
+    while output_receiver.try_recv().is_ok() {}
+
+    for index in 0..FLOOD {
+        command_sender.send(new_order(2 + index % 10, 90 - index % 50)).unwrap();
+    }
+    command_sender.send(cancel(confirmation.order_id)).unwrap();
+    drop(command_sender);
+    std::thread::spawn(move || engine.run()).join().unwrap();
+
+    let mut confirmations = 0;
+    while let Ok(output) = output_receiver.try_recv() {
+        match output {
+            EngineOutput::Confirmation(_) => confirmations += 1,
+            EngineOutput::CancelAck(ack) => {
+                assert_eq!(ack.status, CancelStatus::Cancelled);
+                return confirmations;
+            }
+            _ => {}
+        }
+    }
+    panic!("没有收到撤单回报");
+}
+
+#[test]
+fn test_cancel_overtakes_new_order_flood() {
+    assert_eq!(confirmations_before_cancel(None), 5_000);
+    // 撤单在第一轮就得到处理
+    assert_eq!(confirmations_before_cancel(Some(LaneWeights::default())), 0);
+}
+
+#[test]
+fn test_cancel_all_after_own_order_under_flood() {
+    let (command_sender, command_receiver) = mpsc::unbounded_channel();
+    let (output_sender, mut output_receiver) = mpsc::unbounded_channel();
+    let mut engine = MatchingEngine::new(command_receiver, output_sender).with_priority_lanes(LaneWeights::default());
+    for index in 0..5_000 {
+        command_sender.send(new_order(2 + index % 10, 90 - index % 50)).unwrap();
+    }
+    // 用户 1 下单后立即撤销全部挂单：撤单不能越过自己的订单，否则订单会留在订单簿中
+    command_sender.send(new_order(1, 100)).unwrap();
+    command_sender.send(EngineCommand::CancelUserOrders(1)).unwrap();
+    drop(command_sender);
+    let engine = std::thread::spawn(move || {
+        engine.run();
+        engine
+    })
+    .join()
+    .unwrap();
+
+    let mut cancelled = Vec::new();
+    while let Ok(output) = output_receiver.try_recv() {
+        if let EngineOutput::CancelAck(ack) = output {
+            cancelled.push(ack.user_id);
+        }
+    }
+    assert_eq!(cancelled, [1]);
+    assert!(engine.checkpoint().orders.iter().all(|order| order.user_id != 1));
+}
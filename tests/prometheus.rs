@@ -0,0 +1,121 @@
+use matching_engine::engine::{ControlCommand, EngineCommand, MatchingEngine};
+use matching_engine::metrics::{self, EngineMetrics, OutboundMetrics};
+use matching_engine::protocol::{CancelOrderRequest, NewOrderRequest, OrderType};
+use std::io::{Read, Write};
+use std::net::{TcpListener, TcpStream};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::mpsc;
+
+fn order(user_id: u64, symbol: &str, order_type: OrderType, price: u64, quantity: u64) -> EngineCommand {
+    EngineCommand::NewOrder(NewOrderRequest { user_id, symbol: symbol.to_string(), order_type, price, quantity })
+}
+
+// 运行一组命令直到引擎退出，返回引擎的指标
+fn run(commands: Vec<EngineCommand>) -> Arc<EngineMetrics> {
+    let (command_sender, command_receiver) = mpsc::unbounded_channel();
+    let (output_sender, _output_receiver) = mpsc::unbounded_channel();
+    let mut engine = MatchingEngine::new(command_receiver, output_sender);
+    let metrics = engine.metrics();
+    for command in commands {
+        command_sender.send(command).unwrap();
+    }
+    command_sender.send(EngineCommand::Control(ControlCommand::Drain)).unwrap();
+    std::thread::spawn(move || engine.run()).join().unwrap();
+    metrics
+}
+
+fn scenario() -> Arc<EngineMetrics> {
+    run(vec![
+        order(1, "BTC", OrderType::Sell, 100, 5),
+        order(2, "BTC", OrderType::Buy, 100, 2),
+        order(3, "BTC", OrderType::Buy, 90, 1),
+        // 数量为 0 的订单被拒绝
+        order(3, "BTC", OrderType::Buy, 90, 0),
+        EngineCommand::CancelOrder(CancelOrderRequest { user_id: 3, symbol: "BTC".to_string(), order_id: 3 }),
+        // 重复撤单不计入撤单数
+        EngineCommand::CancelOrder(CancelOrderRequest { user_id: 3, symbol: "BTC".to_string(), order_id: 3 }),
+        order(4, "ETH", OrderType::Buy, 10, 1),
+    ])
+}
+
+#[test]
+fn test_per_symbol_counters() {
+    let symbols = scenario().symbol_snapshots();
+    assert_eq!(symbols.keys().collect::<Vec<_>>(), vec!["BTC", "ETH"]);
+    let btc = &symbols["BTC"];
+    assert_eq!((btc.orders, btc.trades, btc.cancels, btc.rejects), (4, 1, 1, 1));
+    // 被拒绝的订单不计入撮合耗时
+    assert_eq!(btc.latency_count(), 3);
+    assert_eq!(symbols["ETH"].orders, 1);
+}
+
+#[test]
+fn test_prometheus_text_format() {
+    let engine_metrics = scenario();
+    let text = metrics::render_prometheus(&engine_metrics, &OutboundMetrics::new());
+    assert!(text.contains("# TYPE matching_engine_match_latency_seconds histogram\n"));
+    assert!(text.contains("matching_engine_trades_executed_total 1\n"));
+    assert!(text.contains("matching_engine_symbol_orders_total{symbol=\"BTC\"} 4\n"));
+    assert!(text.contains("matching_engine_symbol_cancels_total{symbol=\"BTC\"} 1\n"));
+    assert!(text.contains("matching_engine_symbol_rejects_total{symbol=\"BTC\"} 1\n"));
+    // BTC 的节点池有两个槽位，撤单后只剩一笔挂单
+    assert!(text.contains("# TYPE matching_engine_symbol_pool_slots gauge\n"));
+    assert!(text.contains("matching_engine_symbol_pool_slots{symbol=\"BTC\"} 2\n"));
+    assert!(text.contains("matching_engine_symbol_resting_orders{symbol=\"BTC\"} 1\n"));
+    assert!(text.contains("# TYPE matching_engine_book_memory_bytes gauge\n"));
+    // BTC 和 ETH 各驻留一次
+    assert!(text.contains("matching_engine_symbol_pool_size 2\n"));
+    assert!(text.contains("matching_engine_symbol_pool_misses_total 2\n"));
+    let symbols = engine_metrics.symbol_snapshots();
+    assert!(symbols["BTC"].memory_bytes > 0);
+    let memory = format!("matching_engine_symbol_memory_bytes{{symbol=\"BTC\"}} {}\n", symbols["BTC"].memory_bytes);
+    assert!(text.contains(&memory));
+    let total = symbols["BTC"].memory_bytes + symbols["ETH"].memory_bytes;
+    assert!(text.contains(&format!("matching_engine_book_memory_bytes {}\n", total)));
+    assert!(text.contains("matching_engine_match_latency_seconds_bucket{symbol=\"BTC\",le=\"+Inf\"} 3\n"));
+    assert!(text.contains("matching_engine_match_latency_seconds_count{symbol=\"ETH\"} 1\n"));
+
+    // 直方图的桶是累积的
+    let buckets: Vec<u64> = text
+        .lines()
+        .filter(|line| line.starts_with("matching_engine_match_latency_seconds_bucket{symbol=\"BTC\""))
+        .map(|line| line.rsplit(' ').next().unwrap().parse().unwrap())
+        .collect();
+    assert_eq!(buckets.len(), metrics::LATENCY_BUCKETS_NS.len() + 1);
+    assert!(buckets.windows(2).all(|pair| pair[0] <= pair[1]));
+}
+
+#[test]
+fn test_label_values_are_escaped() {
+    let metrics = EngineMetrics::new();
+    metrics.symbol("A\"B\\C").orders.fetch_add(1, std::sync::atomic::Ordering::Relaxed);
+    let text = metrics::render_prometheus(&metrics, &OutboundMetrics::new());
+    assert!(text.contains("matching_engine_symbol_orders_total{symbol=\"A\\\"B\\\\C\"} 1\n"));
+}
+
+fn http_get(addr: std::net::SocketAddr, path: &str) -> String {
+    let mut stream = TcpStream::connect(addr).unwrap();
+    stream.set_read_timeout(Some(Duration::from_secs(5))).unwrap();
+    write!(stream, "GET {} HTTP/1.1\r\nHost: localhost\r\n\r\n", path).unwrap();
+    let mut response = String::new();
+    stream.read_to_string(&mut response).unwrap();
+    response
+}
+
+#[test]
+fn test_scrape_endpoint() {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap();
+    metrics::spawn_prometheus_exporter(listener, scenario(), Arc::new(OutboundMetrics::new()), None);
+
+    let response = http_get(addr, "/metrics");
+    assert!(response.starts_with("HTTP/1.1 200 OK\r\n"));
+    assert!(response.contains("Content-Type: text/plain; version=0.0.4\r\n"));
+    let (_, body) = response.split_once("\r\n\r\n").unwrap();
+    assert!(body.contains("matching_engine_symbol_trades_total{symbol=\"BTC\"} 1\n"));
+
+    assert!(http_get(addr, "/").starts_with("HTTP/1.1 404 Not Found\r\n"));
+    // 没有配置健康检查时不提供探针
+    assert!(http_get(addr, "/healthz").starts_with("HTTP/1.1 404 Not Found\r\n"));
+}
@@ -0,0 +1,224 @@
+use matching_engine::codec::{BincodeCodec, Codec, CodecError};
+use matching_engine::protobuf::{pb, ProtobufCodec};
+use matching_engine::protocol::{
+    BasketLeg, BasketOrderRequest, Candle, ClientMessage, ExecutionReport, ImpliedQuote, LogonRequest, MarkPrice,
+    MarketDataSnapshot, NewOrderRequest, OcoOrderRequest, OrderReject, OrderStatus, OrderType, RejectReason, ServerMessage,
+    SpreadOrderRequest, TimeInForce, TimedOrderRequest, TradeNotification,
+};
+use prost::Message;
+
+// 协议类型没有实现 PartialEq，用 Debug 输出比较
+fn assert_same<T: std::fmt::Debug>(left: &T, right: &T) {
+    assert_eq!(format!("{:?}", left), format!("{:?}", right));
+}
+
+fn client_messages() -> Vec<ClientMessage> {
+    vec![
+        ClientMessage::NewOrder(NewOrderRequest {
+            user_id: 7,
+            symbol: "BTC/USD".to_string(),
+            order_type: OrderType::Sell,
+            price: 50_000,
+            quantity: 3,
+        }),
+        ClientMessage::Logon(LogonRequest {
+            api_key: "desk-1".to_string(),
+            timestamp_ms: 1_700_000_000_000,
+            signature: vec![1, 2, 3, 255],
+        }),
+        ClientMessage::Heartbeat,
+        ClientMessage::Request { request_id: 42, message: Box::new(ClientMessage::Heartbeat) },
+        ClientMessage::SpreadOrder(SpreadOrderRequest {
+            user_id: 7,
+            spread: "CL-JUN-SEP".to_string(),
+            order_type: OrderType::Buy,
+            price: -25,
+            quantity: 2,
+        }),
+        ClientMessage::BasketOrder(BasketOrderRequest {
+            user_id: 7,
+            legs: vec![
+                BasketLeg { symbol: "BTC/USD".to_string(), order_type: OrderType::Buy, price: 100, quantity: 1 },
+                BasketLeg { symbol: "ETH/USD".to_string(), order_type: OrderType::Sell, price: 30, quantity: 5 },
+            ],
+        }),
+        ClientMessage::OcoOrder(OcoOrderRequest {
+            link_id: 11,
+            first: NewOrderRequest {
+                user_id: 7,
+                symbol: "BTC/USD".to_string(),
+                order_type: OrderType::Sell,
+                price: 110,
+                quantity: 1,
+            },
+            second: NewOrderRequest {
+                user_id: 7,
+                symbol: "BTC/USD".to_string(),
+                order_type: OrderType::Buy,
+                price: 90,
+                quantity: 1,
+            },
+        }),
+        ClientMessage::TimedOrder(TimedOrderRequest {
+            order: NewOrderRequest {
+                user_id: 7,
+                symbol: "BTC/USD".to_string(),
+                order_type: OrderType::Buy,
+                price: 95,
+                quantity: 2,
+            },
+            time_in_force: TimeInForce::GoodTillDate { expire_at_ms: 1_700_000_060_000 },
+        }),
+        ClientMessage::ReduceOnlyOrder(NewOrderRequest {
+            user_id: 7,
+            symbol: "BTC/USD".to_string(),
+            order_type: OrderType::Sell,
+            price: 105,
+            quantity: 3,
+        }),
+    ]
+}
+
+fn server_messages() -> Vec<ServerMessage> {
+    vec![
+        ServerMessage::Trade(TradeNotification {
+            trade_id: 9,
+            symbol: "ETH/USD".into(),
+            matched_price: 3_000,
+            matched_quantity: 2,
+            buyer_user_id: 1,
+            buyer_order_id: 11,
+            seller_user_id: 2,
+            seller_order_id: 12,
+            timestamp: 1_700_000_000_000_000_000,
+            is_block_trade: true,
+        }),
+        ServerMessage::Reject(OrderReject {
+            user_id: 4,
+            symbol: "BTC/USD".to_string(),
+            reason: RejectReason::UserMismatch,
+        }),
+        ServerMessage::ExecutionReport(ExecutionReport {
+            user_id: 1,
+            symbol: "BTC/USD".to_string(),
+            order_id: 5,
+            order_type: OrderType::Buy,
+            status: OrderStatus::PartiallyFilled,
+            trade_id: 2,
+            last_price: 100,
+            last_quantity: 4,
+            cumulative_quantity: 6,
+            leaves_quantity: 4,
+            link_id: Some(77),
+            received_at_ns: Some(1_700_000_000_123_456_789),
+        }),
+        ServerMessage::MarketData(MarketDataSnapshot {
+            user_id: 1,
+            symbol: "BTC/USD".to_string(),
+            last_price: Some(101),
+            high: Some(105),
+            low: None,
+            volume: 10,
+            trade_count: 3,
+            turnover: u64::MAX as u128 * 4,
+            vwap: Some(101.5),
+            candles: vec![Candle { start: 60, open: 100, high: 105, low: 99, close: 101, volume: 10 }],
+        }),
+        ServerMessage::Heartbeat,
+        ServerMessage::Shutdown,
+        ServerMessage::MarkPrice(MarkPrice {
+            symbol: "BTC/USD".to_string(),
+            mark_price: 50_010,
+            index_price: 50_000,
+            timestamp: 7,
+        }),
+        ServerMessage::ImpliedQuote(ImpliedQuote {
+            spread: "CL-JUN-SEP".to_string(),
+            bid_price: Some(-30),
+            bid_quantity: 4,
+            ask_price: None,
+            ask_quantity: 0,
+        }),
+        ServerMessage::Response {
+            request_id: 42,
+            message: Box::new(ServerMessage::Reject(OrderReject {
+                user_id: 4,
+                symbol: "BTC/USD".to_string(),
+                reason: RejectReason::Throttled,
+            })),
+        },
+    ]
+}
+
+#[test]
+fn test_protobuf_round_trip() {
+    let codec = ProtobufCodec;
+    for message in client_messages() {
+        let bytes = codec.encode_client(&message).unwrap();
+        assert_same(&codec.decode_client(&bytes).unwrap(), &message);
+    }
+    for message in server_messages() {
+        let bytes = codec.encode_server(&message).unwrap();
+        assert_same(&codec.decode_server(&bytes).unwrap(), &message);
+    }
+}
+
+#[test]
+fn test_bincode_codec_round_trip() {
+    let codec = BincodeCodec;
+    for message in client_messages() {
+        let bytes = codec.encode_client(&message).unwrap();
+        assert_same(&codec.decode_client(&bytes).unwrap(), &message);
+    }
+    for message in server_messages() {
+        let bytes = codec.encode_server(&message).unwrap();
+        assert_same(&codec.decode_server(&bytes).unwrap(), &message);
+    }
+}
+
+#[test]
+fn test_wire_format_matches_schema() {
+    // 按 .proto 中的字段号手工构造，确保 Rust 侧与发布的 schema 一致
+    let envelope = pb::ClientEnvelope {
+        message: Some(pb::client_envelope::Message::NewOrder(pb::NewOrderRequest {
+            user_id: 7,
+            symbol: "BTC/USD".to_string(),
+            side: pb::OrderSide::Buy as i32,
+            price: 100,
+            quantity: 1,
+        })),
+        request_id: 0,
+    };
+    let bytes = envelope.encode_to_vec();
+    // 字段 1（new_order）、wire type 2
+    assert_eq!(bytes[0], 0x0a);
+
+    match ProtobufCodec.decode_client(&bytes).unwrap() {
+        ClientMessage::NewOrder(request) => {
+            assert!(matches!(request.order_type, OrderType::Buy));
+            assert_eq!((request.user_id, request.price, request.quantity), (7, 100, 1));
+        }
+        other => panic!("unexpected message: {:?}", other),
+    }
+}
+
+#[test]
+fn test_rejects_unspecified_enum_and_empty_envelope() {
+    let envelope = pb::ClientEnvelope {
+        message: Some(pb::client_envelope::Message::NewOrder(pb::NewOrderRequest {
+            user_id: 7,
+            symbol: "BTC/USD".to_string(),
+            side: pb::OrderSide::Unspecified as i32,
+            price: 100,
+            quantity: 1,
+        })),
+        request_id: 0,
+    };
+    let result = ProtobufCodec.decode_client(&envelope.encode_to_vec());
+    assert!(matches!(result, Err(CodecError::Decode(_))));
+
+    let result = ProtobufCodec.decode_server(&pb::ServerEnvelope { message: None, request_id: 0, seq: 0 }.encode_to_vec());
+    assert!(matches!(result, Err(CodecError::Decode(_))));
+
+    assert!(ProtobufCodec.decode_client(&[0xff, 0xff]).is_err());
+}
@@ -0,0 +1,34 @@
+use matching_engine::rate_limiter::{RateLimitConfig, RateLimiter};
+use std::time::{Duration, Instant};
+
+#[test]
+fn test_token_bucket_burst_and_refill() {
+    let mut limiter = RateLimiter::new(RateLimitConfig { orders_per_second: 10, burst: 3 });
+    let start = Instant::now();
+
+    // 突发额度内全部放行，超出后被限流
+    for _ in 0..3 {
+        assert!(limiter.try_acquire_at(1, start));
+    }
+    assert!(!limiter.try_acquire_at(1, start));
+
+    // 其他用户有独立的令牌桶
+    assert!(limiter.try_acquire_at(2, start));
+
+    // 100ms 后补充一个令牌
+    let later = start + Duration::from_millis(100);
+    assert!(limiter.try_acquire_at(1, later));
+    assert!(!limiter.try_acquire_at(1, later));
+}
+
+#[test]
+fn test_acquire_many_is_all_or_nothing() {
+    let mut limiter = RateLimiter::new(RateLimitConfig { orders_per_second: 10, burst: 3 });
+    let start = Instant::now();
+
+    assert!(limiter.try_acquire_many_at(1, 2, start));
+    // 只剩一个令牌：请求两个被拒绝，剩余的令牌不受影响
+    assert!(!limiter.try_acquire_many_at(1, 2, start));
+    assert!(limiter.try_acquire_at(1, start));
+    assert!(!limiter.try_acquire_at(1, start));
+}
@@ -0,0 +1,408 @@
+use matching_engine::circuit_breaker::{BreachPolicy, PriceBand};
+use matching_engine::engine::{ControlCommand, EngineCommand, EngineOutput, MatchingEngine};
+use matching_engine::feature_flags::Feature;
+use matching_engine::protocol::{
+    CancelOrderRequest, NewOrderRequest, OcoOrderRequest, OrderStatus, OrderType, RejectReason, TimeInForce,
+    TimedOrderRequest,
+};
+use matching_engine::rate_limiter::RateLimitConfig;
+use matching_engine::recovery::{self, RecoveryConfig, RecoveryError, Snapshot, COMMAND_LOG_FILE};
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use std::collections::HashSet;
+use std::io::{BufRead, BufReader, Write};
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use std::time::Duration;
+use tokio::sync::mpsc::{self, UnboundedReceiver};
+
+// 设置时本进程作为被杀掉的子进程运行
+const CHILD_DIR_ENV: &str = "MATCHING_ENGINE_RECOVERY_TEST_CHILD";
+const BURST: usize = 200_000;
+
+fn temp_dir(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("recovery-{}-{}", name, std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+    dir
+}
+
+// 确定性的随机订单流：价格互相交叉，夹杂撤单
+fn burst(len: usize) -> Vec<EngineCommand> {
+    let mut rng = StdRng::seed_from_u64(42);
+    (0..len)
+        .map(|index| {
+            let user_id = rng.gen_range(1..=20);
+            if index % 10 == 9 {
+                return EngineCommand::CancelOrder(CancelOrderRequest {
+                    user_id,
+                    symbol: "BTC/USD".to_string(),
+                    order_id: rng.gen_range(1..=index as u64),
+                });
+            }
+            EngineCommand::NewOrder(NewOrderRequest {
+                user_id,
+                symbol: "BTC/USD".to_string(),
+                order_type: if rng.gen_bool(0.5) { OrderType::Buy } else { OrderType::Sell },
+                price: rng.gen_range(95..=105),
+                quantity: rng.gen_range(1..=5),
+            })
+        })
+        .collect()
+}
+
+fn new_engine() -> (MatchingEngine, UnboundedReceiver<EngineOutput>) {
+    let (_command_sender, command_receiver) = mpsc::unbounded_channel();
+    let (output_sender, output_receiver) = mpsc::unbounded_channel();
+    (MatchingEngine::new(command_receiver, output_sender), output_receiver)
+}
+
+// 子进程：从空目录启动，逐条输出确认的订单号和成交，直到被父进程杀掉
+fn run_child(dir: &str) {
+    let (command_sender, command_receiver) = mpsc::unbounded_channel();
+    let (output_sender, mut output_receiver) = mpsc::unbounded_channel();
+    let engine = MatchingEngine::new(command_receiver, output_sender);
+    let (mut engine, _) = recovery::recover(&RecoveryConfig::new(dir), engine).unwrap();
+    std::thread::spawn(move || engine.run());
+    std::thread::spawn(move || {
+        for (index, command) in burst(BURST).into_iter().enumerate() {
+            command_sender.send(command).unwrap();
+            // 放慢发送，保证父进程在订单流中途杀掉子进程
+            if index % 1_000 == 999 {
+                std::thread::sleep(Duration::from_millis(1));
+            }
+        }
+    });
+    while let Some(output) = output_receiver.blocking_recv() {
+        // 每行单独加锁：引擎线程也会向标准输出打印日志，一直持有锁会让它阻塞
+        let mut stdout = std::io::stdout().lock();
+        let _ = match output {
+            EngineOutput::Confirmation(confirmation) => writeln!(stdout, "accepted {}", confirmation.order_id),
+            EngineOutput::Trade(trade) => {
+                writeln!(stdout, "trade {} {} {}", trade.trade_id, trade.buyer_order_id, trade.seller_order_id)
+            }
+            _ => continue,
+        };
+        let _ = stdout.flush();
+    }
+}
+
+// 订单号（挂单确认和成交双方）和 (成交号, 买方订单号, 卖方订单号)
+#[derive(Default)]
+struct Observed {
+    accepted: HashSet<u64>,
+    trades: Vec<(u64, u64, u64)>,
+}
+
+impl Observed {
+    // 子进程的输出中还有测试框架和引擎自己的日志，只解析约定的两种行
+    fn record_line(&mut self, line: &str) {
+        let numbers = |fields: &str| -> Vec<u64> { fields.split(' ').map(|field| field.parse().unwrap()).collect() };
+        if let Some(fields) = line.strip_prefix("accepted ") {
+            self.accepted.insert(numbers(fields)[0]);
+        } else if let Some(fields) = line.strip_prefix("trade ") {
+            let fields = numbers(fields);
+            self.record_trade(fields[0], fields[1], fields[2]);
+        }
+    }
+
+    fn record_trade(&mut self, trade_id: u64, buyer_order_id: u64, seller_order_id: u64) {
+        self.accepted.extend([buyer_order_id, seller_order_id]);
+        self.trades.push((trade_id, buyer_order_id, seller_order_id));
+    }
+
+    fn drain(&mut self, outputs: &mut UnboundedReceiver<EngineOutput>) {
+        while let Ok(output) = outputs.try_recv() {
+            match output {
+                EngineOutput::Confirmation(confirmation) => {
+                    self.accepted.insert(confirmation.order_id);
+                }
+                EngineOutput::Trade(trade) => {
+                    self.record_trade(trade.trade_id, trade.buyer_order_id, trade.seller_order_id)
+                }
+                _ => {}
+            }
+        }
+    }
+}
+
+#[test]
+fn test_kill_mid_burst_loses_no_accepted_order() {
+    if let Ok(dir) = std::env::var(CHILD_DIR_ENV) {
+        run_child(&dir);
+        return;
+    }
+    let dir = temp_dir("kill");
+    let mut child = Command::new(std::env::current_exe().unwrap())
+        .args(["--exact", "test_kill_mid_burst_loses_no_accepted_order", "--nocapture", "--test-threads=1"])
+        .env(CHILD_DIR_ENV, &dir)
+        .stdout(Stdio::piped())
+        .spawn()
+        .unwrap();
+    let mut lines = BufReader::new(child.stdout.take().unwrap()).lines();
+    let mut before_crash = Observed::default();
+    while before_crash.accepted.len() < 3_000 {
+        before_crash.record_line(&lines.next().expect("子进程提前退出").unwrap());
+    }
+    child.kill().unwrap();
+    child.wait().unwrap();
+    // 杀掉之前已经写入管道的输出同样已经发出
+    for line in lines {
+        before_crash.record_line(&line.unwrap());
+    }
+
+    let (engine, mut outputs) = new_engine();
+    let (mut engine, report) = recovery::recover(&RecoveryConfig::new(&dir), engine).unwrap();
+    assert!(report.replayed < BURST, "子进程应在订单流中途被杀掉");
+    assert_eq!(report.snapshot_seq, None);
+    let mut recovered = Observed::default();
+    recovered.drain(&mut outputs);
+
+    // 已确认的订单都能恢复，崩溃前的成交以相同的成交号重现，没有重复
+    let lost: Vec<&u64> = before_crash.accepted.difference(&recovered.accepted).collect();
+    assert!(lost.is_empty(), "丢失的订单: {:?}", lost);
+    assert_eq!(recovered.trades[..before_crash.trades.len()], before_crash.trades[..]);
+    let trade_ids: HashSet<u64> = recovered.trades.iter().map(|trade| trade.0).collect();
+    assert_eq!(trade_ids.len(), recovered.trades.len());
+
+    // 恢复后新的成交号不与崩溃前的重复
+    let max_trade_id = trade_ids.iter().max().copied().unwrap();
+    for order_type in [OrderType::Buy, OrderType::Sell] {
+        let price = if order_type == OrderType::Buy { 1_000 } else { 1 };
+        let request =
+            NewOrderRequest { user_id: 99, symbol: "BTC/USD".to_string(), order_type, price, quantity: 1_000_000 };
+        engine.handle_command(EngineCommand::NewOrder(request));
+    }
+    let mut after = Observed::default();
+    after.drain(&mut outputs);
+    assert!(!after.trades.is_empty());
+    assert!(after.trades.iter().all(|&(trade_id, _, _)| trade_id > max_trade_id));
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn test_snapshot_and_log_restore_state() {
+    let dir = temp_dir("snapshot");
+    let config = RecoveryConfig::new(&dir);
+    let commands = burst(2_000);
+    let (engine, _outputs) = new_engine();
+    let (mut engine, _) = recovery::recover(&config, engine).unwrap();
+    let mut commands = commands.into_iter();
+    for command in commands.by_ref().take(1_000) {
+        engine.handle_command(command);
+    }
+    let (reply, snapshot) = std::sync::mpsc::channel();
+    engine.handle_command(EngineCommand::Control(ControlCommand::Checkpoint(reply)));
+    let snapshot = snapshot.recv().unwrap();
+    assert_eq!(snapshot.seq, 1_000);
+    assert!(!snapshot.orders.is_empty() && !snapshot.positions.is_empty());
+    recovery::write_snapshot(&dir, &snapshot).unwrap();
+    for command in commands {
+        engine.handle_command(command);
+    }
+    let expected = engine.checkpoint();
+    drop(engine);
+
+    let (engine, _outputs) = new_engine();
+    let (engine, report) = recovery::recover(&config, engine).unwrap();
+    assert_eq!((report.snapshot_seq, report.replayed, report.last_seq), (Some(1_000), 1_000, 2_000));
+    // 快照之前的成交原样恢复，日终时一起结算；重放产生的成交时间戳取自系统时钟，与记录时不同
+    let recovered = engine.checkpoint();
+    assert!(!snapshot.trade_log.is_empty());
+    assert_eq!(recovered.trade_log[..snapshot.trade_log.len()], snapshot.trade_log[..]);
+    assert_eq!(without_timestamps(recovered), without_timestamps(expected));
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+fn without_timestamps(mut snapshot: Snapshot) -> Snapshot {
+    for trade in &mut snapshot.trade_log {
+        trade.timestamp = 0;
+    }
+    snapshot
+}
+
+fn write_log(dir: &Path, count: usize) {
+    let (engine, _outputs) = new_engine();
+    let (mut engine, _) = recovery::recover(&RecoveryConfig::new(dir), engine).unwrap();
+    for command in burst(count) {
+        engine.handle_command(command);
+    }
+}
+
+#[test]
+fn test_refuses_to_start_on_gap() {
+    let dir = temp_dir("gap");
+    write_log(&dir, 5);
+    let path = dir.join(COMMAND_LOG_FILE);
+    let content = std::fs::read_to_string(&path).unwrap();
+    let without_third: Vec<&str> = content.lines().enumerate().filter(|&(index, _)| index != 2).map(|(_, line)| line).collect();
+    std::fs::write(&path, without_third.join("\n") + "\n").unwrap();
+
+    let (engine, _outputs) = new_engine();
+    match recovery::recover(&RecoveryConfig::new(&dir), engine) {
+        Err(RecoveryError::Gap { expected: 3, found: 4 }) => {}
+        other => panic!("应拒绝启动: {:?}", other.map(|(_, report)| report)),
+    }
+
+    let config = RecoveryConfig { allow_gaps: true, ..RecoveryConfig::new(&dir) };
+    let (engine, _outputs) = new_engine();
+    let (_, report) = recovery::recover(&config, engine).unwrap();
+    assert_eq!((report.replayed, report.last_seq, report.gaps), (4, 5, vec![(3, 4)]));
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn test_truncates_torn_record() {
+    let dir = temp_dir("torn");
+    write_log(&dir, 3);
+    let path = dir.join(COMMAND_LOG_FILE);
+    let torn = "{\"seq\":4,\"command\":{\"NewOr";
+    std::fs::OpenOptions::new().append(true).open(&path).unwrap().write_all(torn.as_bytes()).unwrap();
+
+    let (engine, _outputs) = new_engine();
+    let (mut engine, report) = recovery::recover(&RecoveryConfig::new(&dir), engine).unwrap();
+    assert_eq!((report.replayed, report.last_seq, report.truncated_bytes), (3, 3, torn.len() as u64));
+    // 续写的记录紧接在最后一条完整记录之后
+    engine.handle_command(burst(1).remove(0));
+    drop(engine);
+    let (engine, _outputs) = new_engine();
+    let (_, report) = recovery::recover(&RecoveryConfig::new(&dir), engine).unwrap();
+    assert_eq!((report.replayed, report.last_seq, report.truncated_bytes), (4, 4, 0));
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+fn order(user_id: u64, symbol: &str, order_type: OrderType, price: u64, quantity: u64) -> NewOrderRequest {
+    NewOrderRequest { user_id, symbol: symbol.to_string(), order_type, price, quantity }
+}
+
+#[test]
+fn test_snapshot_and_log_restore_order_attributes_and_control_state() {
+    let dir = temp_dir("attributes");
+    let config = RecoveryConfig::new(&dir);
+    let (engine, _outputs) = new_engine();
+    let (mut engine, _) = recovery::recover(&config, engine).unwrap();
+    let band = PriceBand { reference_price: 100, limit_up_bps: 1_000, limit_down_bps: 1_000, policy: BreachPolicy::Reject };
+    let before_snapshot = vec![
+        EngineCommand::Control(ControlCommand::SetPriceBand("BTC/USD".to_string(), band)),
+        EngineCommand::Control(ControlCommand::AdvanceTime(1_000)),
+        EngineCommand::NewOrder(order(1, "BTC/USD", OrderType::Buy, 100, 5)),
+        EngineCommand::NewOrder(order(2, "BTC/USD", OrderType::Sell, 100, 5)),
+        EngineCommand::OcoOrder(OcoOrderRequest {
+            link_id: 7,
+            first: order(3, "BTC/USD", OrderType::Buy, 95, 1),
+            second: order(3, "ETH/USD", OrderType::Sell, 60, 1),
+        }),
+        EngineCommand::TimedOrder(TimedOrderRequest {
+            order: order(4, "BTC/USD", OrderType::Sell, 108, 2),
+            time_in_force: TimeInForce::GoodTillDate { expire_at_ms: 5_000 },
+        }),
+        EngineCommand::ReduceOnlyOrder(order(1, "BTC/USD", OrderType::Sell, 109, 3)),
+        EngineCommand::Control(ControlCommand::Halt("ETH/USD".to_string())),
+    ];
+    for command in before_snapshot {
+        engine.handle_command(command);
+    }
+    let snapshot = engine.checkpoint();
+    assert_eq!(snapshot.clock_ms, 1_000);
+    assert_eq!(snapshot.good_till_date.len(), 1);
+    assert_eq!(snapshot.oco_links.len(), 2);
+    assert_eq!(snapshot.reduce_only.len(), 1);
+    recovery::write_snapshot(&dir, &snapshot).unwrap();
+    let after_snapshot = vec![
+        EngineCommand::Control(ControlCommand::StartAuction("BTC/USD".to_string())),
+        EngineCommand::NewOrder(order(5, "BTC/USD", OrderType::Buy, 96, 1)),
+        EngineCommand::Control(ControlCommand::EndAuction("BTC/USD".to_string())),
+        EngineCommand::Control(ControlCommand::EndOfDay(std::sync::mpsc::channel().0)),
+        EngineCommand::Control(ControlCommand::SetFeature {
+            feature: Feature::ProRataAllocation,
+            symbol: Some("BTC/USD".to_string()),
+            enabled: false,
+        }),
+    ];
+    for command in after_snapshot {
+        engine.handle_command(command);
+    }
+    let expected = engine.checkpoint();
+    drop(engine);
+
+    let (engine, mut outputs) = new_engine();
+    let (mut engine, report) = recovery::recover(&config, engine).unwrap();
+    assert_eq!((report.snapshot_seq, report.replayed), (Some(8), 5));
+    assert_eq!(engine.checkpoint(), expected);
+    while outputs.try_recv().is_ok() {}
+    // 恢复的 GTD 定时器照常到期
+    engine.handle_command(EngineCommand::Control(ControlCommand::AdvanceTime(6_000)));
+    let mut expired = false;
+    while let Ok(output) = outputs.try_recv() {
+        expired |=
+            matches!(output, EngineOutput::ExecutionReport(report) if report.user_id == 4 && report.status == OrderStatus::Expired);
+    }
+    assert!(expired);
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn test_clock_ticks_logged_only_when_needed() {
+    let dir = temp_dir("clock");
+    let config = RecoveryConfig::new(&dir);
+    let (engine, _outputs) = new_engine();
+    let (mut engine, _) = recovery::recover(&config, engine).unwrap();
+    let gtd = |user_id, price, expire_at_ms| {
+        EngineCommand::TimedOrder(TimedOrderRequest {
+            order: order(user_id, "BTC/USD", OrderType::Sell, price, 1),
+            time_in_force: TimeInForce::GoodTillDate { expire_at_ms },
+        })
+    };
+    // 没有定时器到期的节拍不写日志
+    for now_ms in (10..=1_000).step_by(10) {
+        engine.handle_command(EngineCommand::Control(ControlCommand::AdvanceTime(now_ms)));
+    }
+    assert!(recovery::read_command_log(&dir).unwrap().is_empty());
+    // GTD 订单之前补写时钟，重放时已过期的订单同样被拒绝
+    engine.handle_command(gtd(1, 101, 500));
+    engine.handle_command(gtd(2, 102, 1_500));
+    engine.handle_command(gtd(3, 103, 1_500));
+    for now_ms in (1_010..=2_000).step_by(10) {
+        engine.handle_command(EngineCommand::Control(ControlCommand::AdvanceTime(now_ms)));
+    }
+    let commands: Vec<_> = recovery::read_command_log(&dir).unwrap().into_iter().map(|record| record.command).collect();
+    assert_eq!(commands.len(), 5);
+    assert!(matches!(commands[0], recovery::LoggedCommand::AdvanceTime(1_000)));
+    assert!(matches!(commands[4], recovery::LoggedCommand::AdvanceTime(1_500)));
+    let expected = engine.checkpoint();
+    assert!(expected.orders.is_empty());
+    drop(engine);
+
+    let (engine, _outputs) = new_engine();
+    let (engine, report) = recovery::recover(&config, engine).unwrap();
+    assert_eq!(report.replayed, 5);
+    assert_eq!(Snapshot { clock_ms: 2_000, ..engine.checkpoint() }, expected);
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn test_throttled_orders_are_not_logged_or_rethrottled() {
+    let dir = temp_dir("throttle");
+    let config = RecoveryConfig::new(&dir);
+    let limit = RateLimitConfig { orders_per_second: 1, burst: 3 };
+    let (engine, mut outputs) = new_engine();
+    let (mut engine, _) = recovery::recover(&config, engine.with_rate_limit(limit)).unwrap();
+    for price in 100..106 {
+        engine.handle_command(EngineCommand::NewOrder(order(1, "BTC/USD", OrderType::Buy, price, 1)));
+    }
+    let mut throttled = 0;
+    while let Ok(output) = outputs.try_recv() {
+        throttled += matches!(output, EngineOutput::Reject(reject) if reject.reason == RejectReason::Throttled) as usize;
+    }
+    assert_eq!(throttled, 3);
+    assert_eq!(recovery::read_command_log(&dir).unwrap().len(), 3);
+    let expected = engine.checkpoint();
+    drop(engine);
+
+    // 重放时一次送入全部命令，限流器的额度对重放不起作用
+    let limit = RateLimitConfig { orders_per_second: 1, burst: 1 };
+    let (engine, _outputs) = new_engine();
+    let (engine, report) = recovery::recover(&config, engine.with_rate_limit(limit)).unwrap();
+    assert_eq!(report.replayed, 3);
+    assert_eq!(engine.checkpoint(), expected);
+    std::fs::remove_dir_all(&dir).unwrap();
+}
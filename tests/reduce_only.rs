@@ -0,0 +1,139 @@
+use matching_engine::engine::{EngineCommand, EngineOutput};
+use matching_engine::protocol::{NewOrderRequest, OrderStatus, OrderType, RejectReason};
+use matching_engine::testing::Simulation;
+
+fn new_order(user_id: u64, order_type: OrderType, price: u64, quantity: u64) -> NewOrderRequest {
+    NewOrderRequest { user_id, symbol: "BTC".to_string(), order_type, price, quantity }
+}
+
+fn order(user_id: u64, order_type: OrderType, price: u64, quantity: u64) -> EngineCommand {
+    EngineCommand::NewOrder(new_order(user_id, order_type, price, quantity))
+}
+
+fn reduce_only(order_type: OrderType, price: u64, quantity: u64) -> EngineCommand {
+    EngineCommand::ReduceOnlyOrder(new_order(9, order_type, price, quantity))
+}
+
+fn confirmation(outputs: &[EngineOutput]) -> u64 {
+    outputs
+        .iter()
+        .find_map(|output| match output {
+            EngineOutput::Confirmation(confirmation) => Some(confirmation.order_id),
+            _ => None,
+        })
+        .expect("期望收到订单确认")
+}
+
+// 缩减回报：(订单号, 缩减后的剩余数量)
+fn restated(outputs: &[EngineOutput]) -> Vec<(u64, u64)> {
+    outputs
+        .iter()
+        .filter_map(|output| match output {
+            EngineOutput::ExecutionReport(report) if report.status == OrderStatus::Restated => {
+                Some((report.order_id, report.leaves_quantity))
+            }
+            _ => None,
+        })
+        .collect()
+}
+
+fn reject(outputs: &[EngineOutput]) -> Option<RejectReason> {
+    outputs.iter().find_map(|output| match output {
+        EngineOutput::Reject(reject) => Some(reject.reason),
+        _ => None,
+    })
+}
+
+fn ask_quantity(sim: &mut Simulation, price: u64) -> u64 {
+    let depth = sim.depth("BTC").expect("期望收到深度快照");
+    depth.asks.iter().find(|level| level.price == price).map_or(0, |level| level.quantity)
+}
+
+// 用户 9 以 100 买入 quantity 手建立多头
+fn long(quantity: u64) -> Simulation {
+    let mut sim = Simulation::new();
+    sim.execute(order(1, OrderType::Sell, 100, quantity));
+    sim.execute(order(9, OrderType::Buy, 100, quantity));
+    sim
+}
+
+#[test]
+fn test_rejected_without_opposite_position() {
+    let mut sim = Simulation::new();
+    let outputs = sim.execute(reduce_only(OrderType::Sell, 110, 1));
+    assert_eq!(reject(&outputs), Some(RejectReason::ReduceOnlyExceedsPosition));
+
+    // 多头只能用卖单减仓
+    let mut sim = long(2);
+    let outputs = sim.execute(reduce_only(OrderType::Buy, 90, 1));
+    assert_eq!(reject(&outputs), Some(RejectReason::ReduceOnlyExceedsPosition));
+}
+
+#[test]
+fn test_quantity_capped_at_entry() {
+    let mut sim = long(5);
+    let outputs = sim.execute(reduce_only(OrderType::Sell, 110, 8));
+    assert!(reject(&outputs).is_none());
+    assert_eq!(ask_quantity(&mut sim, 110), 5);
+    // 反向持仓已被挂单占满
+    let outputs = sim.execute(reduce_only(OrderType::Sell, 120, 1));
+    assert_eq!(reject(&outputs), Some(RejectReason::ReduceOnlyExceedsPosition));
+
+    // 主动成交的只减仓订单同样不会开出反向仓位
+    let mut sim = long(3);
+    sim.execute(order(2, OrderType::Buy, 95, 10));
+    let outputs = sim.execute(reduce_only(OrderType::Sell, 95, 10));
+    let filled: u64 = outputs
+        .iter()
+        .filter_map(|output| match output {
+            EngineOutput::Trade(trade) => Some(trade.matched_quantity),
+            _ => None,
+        })
+        .sum();
+    assert_eq!(filled, 3);
+    assert!(!outputs.iter().any(|output| matches!(output, EngineOutput::Confirmation(_))));
+}
+
+#[test]
+fn test_trimmed_as_position_shrinks() {
+    let mut sim = long(5);
+    let first = confirmation(&sim.execute(reduce_only(OrderType::Sell, 110, 3)));
+    let second = confirmation(&sim.execute(reduce_only(OrderType::Sell, 120, 2)));
+
+    // 用普通卖单平掉 2 手：后下的订单被截到 0 并撤销
+    sim.execute(order(2, OrderType::Buy, 100, 3));
+    let outputs = sim.execute(order(9, OrderType::Sell, 100, 2));
+    assert_eq!(restated(&outputs), vec![(second, 0)]);
+    assert_eq!(ask_quantity(&mut sim, 120), 0);
+
+    // 再平掉 1 手：先下的订单缩减为 2 手，保持原有优先级
+    let outputs = sim.execute(order(9, OrderType::Sell, 100, 1));
+    assert_eq!(restated(&outputs), vec![(first, 2)]);
+    assert_eq!(ask_quantity(&mut sim, 110), 2);
+    let ids: Vec<u64> = sim.resting_orders().filter(|order| order.user_id == 9).map(|order| order.order_id).collect();
+    assert_eq!(ids, vec![first]);
+}
+
+#[test]
+fn test_own_fill_does_not_trim_others() {
+    let mut sim = long(5);
+    sim.execute(reduce_only(OrderType::Sell, 110, 3));
+    sim.execute(reduce_only(OrderType::Sell, 120, 2));
+    let outputs = sim.execute(order(2, OrderType::Buy, 110, 3));
+    assert!(restated(&outputs).is_empty());
+    assert_eq!(ask_quantity(&mut sim, 120), 2);
+}
+
+#[test]
+fn test_cancelled_when_position_flips() {
+    let mut sim = long(2);
+    let order_id = confirmation(&sim.execute(reduce_only(OrderType::Sell, 110, 2)));
+    sim.execute(order(2, OrderType::Buy, 100, 4));
+    let outputs = sim.execute(order(9, OrderType::Sell, 100, 4));
+    assert_eq!(restated(&outputs), vec![(order_id, 0)]);
+    assert_eq!(ask_quantity(&mut sim, 110), 0);
+
+    // 转为空头后可以用买单减仓
+    let outputs = sim.execute(reduce_only(OrderType::Buy, 90, 5));
+    assert!(reject(&outputs).is_none());
+}
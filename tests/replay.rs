@@ -0,0 +1,72 @@
+use matching_engine::replay::{replay, Journal, JournalEvent, ReplayConfig};
+use std::time::{Duration, Instant};
+
+const JOURNAL: &str = "\
+# 只有一笔卖单，回放不会产生记录中的成交
+1000,order,BTC/USD,1,sell,100,5
+1000,trade,BTC/USD,0,0,0,0
+";
+
+fn recorded_journal() -> String {
+    [
+        "# 引擎从空状态开始记录",
+        "0,order,BTC/USD,1,sell,100,5",
+        "1000000,order,BTC/USD,2,buy,100,2",
+        "1000000,trade,BTC/USD,2,1,100,2",
+        "2000000,amend,BTC/USD,1,1,101,3",
+        "3000000,order,ETH/USD,3,buy,20,1",
+        "4000000,cancel,ETH/USD,3,4",
+        "5000000,order,BTC/USD,2,buy,101,1",
+        "5000000,trade,BTC/USD,5,3,101,1",
+    ]
+    .join("\n")
+}
+
+#[test]
+fn test_parse_and_round_trip() {
+    let journal = Journal::parse(&recorded_journal()).unwrap();
+    assert_eq!(journal.entries.len(), 8);
+    assert!(matches!(&journal.entries[3].event, JournalEvent::Amend(amend) if amend.new_price == 101));
+    assert_eq!(journal.trades().len(), 2);
+
+    let reparsed = Journal::parse(&journal.to_csv()).unwrap();
+    assert_eq!(reparsed.to_csv(), journal.to_csv());
+
+    assert!(Journal::parse("x,order,BTC/USD,1,buy,1,1").is_err());
+    assert!(Journal::parse("1,order,BTC/USD,1,hold,1,1").is_err());
+    assert!(Journal::parse("1,cancel,BTC/USD,1").is_err());
+    assert!(Journal::parse("1,quote,BTC/USD,1,1,1,1").is_err());
+}
+
+#[test]
+fn test_replay_matches_recording() {
+    let journal = Journal::parse(&recorded_journal()).unwrap();
+    let report = replay(&journal, ReplayConfig::default());
+    assert_eq!(report.commands, 6);
+    assert!(report.is_consistent(), "{:?}", report.mismatches);
+    assert_eq!(report.trades, journal.trades());
+}
+
+#[test]
+fn test_replay_detects_divergence() {
+    let journal = Journal::parse(JOURNAL).unwrap();
+    let report = replay(&journal, ReplayConfig::default());
+    // 记录中有一笔成交，回放没有成交
+    assert!(!report.is_consistent());
+    assert_eq!(report.mismatches.len(), 1);
+
+    let altered = recorded_journal().replace("5000000,trade,BTC/USD,5,3,101,1", "5000000,trade,BTC/USD,5,3,100,1");
+    let report = replay(&Journal::parse(&altered).unwrap(), ReplayConfig::default());
+    assert_eq!(report.mismatches.len(), 1);
+    assert!(report.mismatches[0].contains("第 2 笔"));
+}
+
+#[test]
+fn test_replay_respects_speed() {
+    let journal = Journal::parse(&recorded_journal()).unwrap();
+    let start = Instant::now();
+    // 记录跨度 5ms，两倍速约 2.5ms
+    let report = replay(&journal, ReplayConfig { speed: 2.0 });
+    assert!(start.elapsed() >= Duration::from_micros(2_500));
+    assert!(report.is_consistent());
+}
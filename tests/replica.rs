@@ -0,0 +1,118 @@
+use matching_engine::engine::{ControlCommand, EngineCommand, MatchingEngine};
+use matching_engine::orderbook::BookEvent;
+use matching_engine::protocol::{AmendOrderRequest, CancelOrderRequest, NewOrderRequest, OrderType};
+use matching_engine::replica::{self, L3Message, ReplicaBooks};
+use std::collections::BTreeMap;
+use std::sync::mpsc as std_mpsc;
+use tokio::sync::mpsc;
+
+const SYMBOLS: [&str; 2] = ["BTC/USD", "ETH/USD"];
+
+fn new_engine() -> MatchingEngine {
+    let (_commands, command_receiver) = mpsc::unbounded_channel();
+    let (output_sender, _outputs) = mpsc::unbounded_channel();
+    MatchingEngine::new(command_receiver, output_sender)
+}
+
+// 新订单、撤单和改单混合的订单流，包括部分成交和完全成交
+fn command(index: u64) -> EngineCommand {
+    let symbol = SYMBOLS[(index % 2) as usize].to_string();
+    match index % 9 {
+        7 => EngineCommand::CancelOrder(CancelOrderRequest { user_id: index % 5 + 1, symbol, order_id: index / 2 + 1 }),
+        8 => EngineCommand::AmendOrder(AmendOrderRequest {
+            user_id: index % 5 + 1,
+            symbol,
+            order_id: index / 3 + 1,
+            new_price: 100 + index % 4,
+            new_quantity: 1,
+        }),
+        _ => EngineCommand::NewOrder(NewOrderRequest {
+            user_id: index % 5 + 1,
+            symbol,
+            order_type: if index % 4 < 2 { OrderType::Buy } else { OrderType::Sell },
+            price: 98 + index % 7,
+            quantity: index % 5 + 1,
+        }),
+    }
+}
+
+// 引擎检查点中的挂单，按订单号索引到 (合约, 剩余数量)
+fn engine_orders(engine: &MatchingEngine) -> BTreeMap<u64, (String, u64)> {
+    engine.checkpoint().orders.into_iter().map(|order| (order.order_id, (order.symbol, order.quantity))).collect()
+}
+
+fn replica_orders(books: &ReplicaBooks) -> BTreeMap<u64, (String, u64)> {
+    let mut orders = BTreeMap::new();
+    for symbol in books.symbols() {
+        for node in books.book(&symbol).unwrap().queued_orders() {
+            orders.insert(node.order_id, (symbol.clone(), node.quantity));
+        }
+    }
+    orders
+}
+
+#[test]
+fn test_replica_tracks_engine_books() {
+    let (feed, receiver) = std_mpsc::channel();
+    let (books, replica_thread) = replica::spawn_replica(receiver);
+    let mut engine = new_engine().with_l3_feed(feed, 100);
+    for index in 0..3_000 {
+        engine.handle_command(command(index));
+    }
+    // 合约到期时撤销的挂单同样出现在流中
+    engine.handle_command(EngineCommand::Control(ControlCommand::Expire("ETH/USD".to_string())));
+    let expected = engine_orders(&engine);
+    drop(engine);
+    replica_thread.join().unwrap();
+
+    let books = books.read();
+    assert!(books.mismatches().is_empty(), "{:?}", books.mismatches());
+    assert!(books.checksums_verified() >= 60);
+    assert!(!expected.is_empty());
+    assert!(expected.values().all(|(symbol, _)| symbol == "BTC/USD"));
+    assert_eq!(replica_orders(&books), expected);
+    assert_eq!(books.book("ETH/USD").unwrap().order_count(), 0);
+}
+
+#[test]
+fn test_feed_attached_later_starts_from_current_books() {
+    let mut engine = new_engine();
+    for index in 0..500 {
+        engine.handle_command(command(index));
+    }
+    let (feed, receiver) = std_mpsc::channel();
+    let mut engine = engine.with_l3_feed(feed, 1);
+    for index in 500..600 {
+        engine.handle_command(command(index));
+    }
+    let mut books = ReplicaBooks::new();
+    for message in receiver.try_iter() {
+        books.apply(message);
+    }
+    assert!(books.mismatches().is_empty(), "{:?}", books.mismatches());
+    assert_eq!(replica_orders(&books), engine_orders(&engine));
+    // 校验和覆盖队列顺序：挂单按原有的时间优先顺序重建
+    assert_eq!(books.checksums_verified(), 101 * 2);
+}
+
+#[test]
+fn test_lost_event_is_reported() {
+    let (feed, receiver) = std_mpsc::channel();
+    let mut engine = new_engine().with_l3_feed(feed, 10);
+    for index in 0..200 {
+        engine.handle_command(command(index));
+    }
+    let mut books = ReplicaBooks::new();
+    let mut dropped = false;
+    for message in receiver.try_iter() {
+        // 丢掉第一条新挂单事件
+        if !dropped && matches!(message, L3Message::Event { event: BookEvent::Added { .. }, .. }) {
+            dropped = true;
+            continue;
+        }
+        books.apply(message);
+    }
+    assert!(books.mismatches()[0].starts_with("消息序号不连续"), "{:?}", books.mismatches());
+    // 之后的校验和暴露出副本的状态已经偏离
+    assert!(books.mismatches().iter().any(|mismatch| mismatch.contains("校验和不一致")));
+}
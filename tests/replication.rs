@@ -0,0 +1,152 @@
+use matching_engine::engine::{EngineCommand, EngineOutput, MatchingEngine};
+use matching_engine::protocol::{CancelOrderRequest, NewOrderRequest, OrderType};
+use matching_engine::recovery::{self, RecoveryConfig, Snapshot};
+use matching_engine::replication::{self, ReplicationConfig, StandbyConfig};
+use std::net::TcpListener;
+use std::path::PathBuf;
+use std::thread;
+use std::time::{Duration, Instant};
+use tokio::sync::mpsc::{self, UnboundedReceiver};
+
+fn temp_dir(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("replication-{}-{}", name, std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+    dir
+}
+
+fn new_engine() -> (MatchingEngine, UnboundedReceiver<EngineOutput>) {
+    let (_command_sender, command_receiver) = mpsc::unbounded_channel();
+    let (output_sender, output_receiver) = mpsc::unbounded_channel();
+    (MatchingEngine::new(command_receiver, output_sender), output_receiver)
+}
+
+// 价格交替交叉的订单流，夹杂撤单
+fn command(index: u64) -> EngineCommand {
+    if index % 7 == 6 {
+        return EngineCommand::CancelOrder(CancelOrderRequest {
+            user_id: index % 5 + 1,
+            symbol: "BTC/USD".to_string(),
+            order_id: index / 2 + 1,
+        });
+    }
+    EngineCommand::NewOrder(NewOrderRequest {
+        user_id: index % 5 + 1,
+        symbol: "BTC/USD".to_string(),
+        order_type: if index.is_multiple_of(2) { OrderType::Buy } else { OrderType::Sell },
+        price: 100 + index % 3,
+        quantity: index % 4 + 1,
+    })
+}
+
+// 引擎状态；成交时间戳取自各自的系统时钟，比较时忽略
+fn state(engine: &MatchingEngine) -> Snapshot {
+    let mut snapshot = engine.checkpoint();
+    for trade in &mut snapshot.trade_log {
+        trade.timestamp = 0;
+    }
+    snapshot
+}
+
+fn standby_config(primary: std::net::SocketAddr, dir: PathBuf) -> StandbyConfig {
+    StandbyConfig { heartbeat_interval: Duration::from_millis(20), missed_heartbeats: 5, ..StandbyConfig::new(primary, dir) }
+}
+
+#[test]
+fn test_standby_follows_primary_and_promotes() {
+    let (primary_dir, standby_dir) = (temp_dir("primary"), temp_dir("standby"));
+    let (engine, _outputs) = new_engine();
+    let (mut primary, _) = recovery::recover(&RecoveryConfig::new(&primary_dir), engine).unwrap();
+    // 备机连接前已有的记录在订阅时补齐
+    for index in 0..300 {
+        primary.handle_command(command(index));
+    }
+
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let config = ReplicationConfig { dir: primary_dir.clone(), heartbeat_interval: Duration::from_millis(20) };
+    let server = replication::spawn_replication_server(listener, config).unwrap();
+    let standby = standby_config(server.addr(), standby_dir.clone());
+    let standby = thread::spawn(move || {
+        let (engine, mut outputs) = new_engine();
+        let (engine, report) = replication::run_standby(&standby, engine, &mut outputs).unwrap();
+        (state(&engine), report)
+    });
+
+    for index in 300..1_000 {
+        primary.handle_command(command(index));
+    }
+    // 心跳期间备机不会提升；等备机写完全部记录再停止主机，负载较高时备机可能还在追赶
+    thread::sleep(Duration::from_millis(300));
+    let deadline = Instant::now() + Duration::from_secs(10);
+    while recovery::read_command_log(&standby_dir).map_or(0, |records| records.len()) < 1_000 && Instant::now() < deadline {
+        thread::sleep(Duration::from_millis(10));
+    }
+    assert!(!standby.is_finished());
+
+    // 主机停止复制（模拟崩溃），备机错过心跳后提升，状态与主机完全一致
+    let stopped = Instant::now();
+    server.stop();
+    let (promoted, report) = standby.join().unwrap();
+    // 超时从最后一次收到心跳算起，最后一次心跳最多早于停止一个心跳间隔
+    assert!(stopped.elapsed() >= Duration::from_millis(100 - 20));
+    assert_eq!((report.applied, report.last_seq, report.primary_last_seq), (1_000, 1_000, 1_000));
+    assert_eq!(promoted, state(&primary));
+    assert!(!promoted.orders.is_empty());
+
+    // 提升后的备机从自己的目录恢复出相同的状态
+    let (engine, _outputs) = new_engine();
+    let (recovered, recovery_report) = recovery::recover(&RecoveryConfig::new(&standby_dir), engine).unwrap();
+    assert_eq!(recovery_report.last_seq, 1_000);
+    assert_eq!(state(&recovered), promoted);
+    for dir in [primary_dir, standby_dir] {
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+}
+
+#[test]
+fn test_standby_resumes_from_own_log() {
+    let (primary_dir, standby_dir) = (temp_dir("resume-primary"), temp_dir("resume-standby"));
+    let (engine, _outputs) = new_engine();
+    let (mut primary, _) = recovery::recover(&RecoveryConfig::new(&primary_dir), engine).unwrap();
+    for index in 0..200 {
+        primary.handle_command(command(index));
+    }
+    // 备机此前已经应用了前 150 条
+    let (engine, _outputs) = new_engine();
+    let (mut standby_engine, _) = recovery::recover(&RecoveryConfig::new(&standby_dir), engine).unwrap();
+    for index in 0..150 {
+        standby_engine.handle_command(command(index));
+    }
+    drop(standby_engine);
+
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let config = ReplicationConfig { dir: primary_dir.clone(), heartbeat_interval: Duration::from_millis(20) };
+    let server = replication::spawn_replication_server(listener, config).unwrap();
+    let standby = standby_config(server.addr(), standby_dir.clone());
+    let standby = thread::spawn(move || {
+        let (engine, mut outputs) = new_engine();
+        let (engine, report) = replication::run_standby(&standby, engine, &mut outputs).unwrap();
+        (state(&engine), report)
+    });
+    thread::sleep(Duration::from_millis(200));
+    server.stop();
+    let (promoted, report) = standby.join().unwrap();
+    assert_eq!((report.recovered.replayed, report.applied, report.last_seq), (150, 50, 200));
+    assert_eq!(promoted, state(&primary));
+    for dir in [primary_dir, standby_dir] {
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+}
+
+#[test]
+fn test_standby_promotes_when_primary_unreachable() {
+    let dir = temp_dir("unreachable");
+    // 绑定后立即释放，连接被拒绝
+    let addr = TcpListener::bind("127.0.0.1:0").unwrap().local_addr().unwrap();
+    let config = standby_config(addr, dir.clone());
+    let (engine, mut outputs) = new_engine();
+    let started = Instant::now();
+    let (_, report) = replication::run_standby(&config, engine, &mut outputs).unwrap();
+    assert!(started.elapsed() >= Duration::from_millis(100));
+    assert_eq!((report.applied, report.last_seq), (0, 0));
+    std::fs::remove_dir_all(&dir).unwrap();
+}
@@ -0,0 +1,188 @@
+use bincode::config;
+use futures::{SinkExt, StreamExt};
+use matching_engine::engine::{ControlCommand, EngineCommand, EngineOutput, MatchingEngine};
+use matching_engine::network;
+use matching_engine::protocol::{
+    CancelOrderRequest, CancelStatus, ClientMessage, NewOrderRequest, OrderStatus, OrderType, PositionQuery,
+    ServerMessage,
+};
+use std::collections::HashMap;
+use std::time::Duration;
+use tokio::net::{TcpListener, TcpStream};
+use tokio::sync::mpsc;
+use tokio_util::codec::{Framed, LengthDelimitedCodec};
+
+fn order(user_id: u64, order_type: OrderType, price: u64, quantity: u64) -> NewOrderRequest {
+    NewOrderRequest { user_id, symbol: "BTC/USD".to_string(), order_type, price, quantity }
+}
+
+fn tagged(request_id: u64, command: EngineCommand) -> EngineCommand {
+    EngineCommand::Request { request_id, connection_id: 1, command: Box::new(command) }
+}
+
+fn run_engine(commands: Vec<EngineCommand>) -> Vec<EngineOutput> {
+    let (command_sender, command_receiver) = mpsc::unbounded_channel();
+    let (output_sender, mut output_receiver) = mpsc::unbounded_channel();
+    let engine = std::thread::spawn(move || MatchingEngine::new(command_receiver, output_sender).run());
+    for command in commands {
+        command_sender.send(command).unwrap();
+    }
+    command_sender.send(EngineCommand::Control(ControlCommand::Drain)).unwrap();
+    engine.join().unwrap();
+    let mut outputs = Vec::new();
+    while let Ok(output) = output_receiver.try_recv() {
+        outputs.push(output);
+    }
+    outputs
+}
+
+#[test]
+fn test_engine_echoes_request_id_on_responses() {
+    let outputs = run_engine(vec![
+        tagged(10, EngineCommand::NewOrder(order(1, OrderType::Sell, 100, 5))),
+        // 未带请求 ID 的命令照常应答
+        EngineCommand::NewOrder(order(2, OrderType::Sell, 101, 5)),
+        tagged(11, EngineCommand::NewOrder(order(3, OrderType::Buy, 100, 2))),
+        tagged(12, EngineCommand::CancelOrder(CancelOrderRequest {
+            user_id: 2,
+            symbol: "BTC/USD".to_string(),
+            order_id: 2,
+        })),
+        tagged(13, EngineCommand::NewOrder(order(3, OrderType::Buy, 0, 1))),
+    ]);
+
+    let mut outputs = outputs.into_iter();
+    let Some(EngineOutput::Response { request_id: 10, output, .. }) = outputs.next() else {
+        panic!("期望收到回显请求 ID 的挂单确认");
+    };
+    assert!(matches!(*output, EngineOutput::Confirmation(ref c) if c.order_id == 1));
+    assert!(matches!(outputs.next(), Some(EngineOutput::Confirmation(c)) if c.order_id == 2));
+
+    // 成交通知是公共消息；只有主动方（买方）的执行回报属于请求 11
+    assert!(matches!(outputs.next(), Some(EngineOutput::Trade(_))));
+    let reports: Vec<EngineOutput> = outputs.by_ref().take(2).collect();
+    let mut taker_reports = 0;
+    for report in reports {
+        match report {
+            EngineOutput::Response { request_id: 11, output, .. } => {
+                let EngineOutput::ExecutionReport(report) = *output else {
+                    panic!("期望收到执行回报");
+                };
+                assert_eq!((report.user_id, report.status), (3, OrderStatus::Filled));
+                taker_reports += 1;
+            }
+            EngineOutput::ExecutionReport(report) => assert_eq!(report.user_id, 1),
+            other => panic!("unexpected output: {:?}", other),
+        }
+    }
+    assert_eq!(taker_reports, 1);
+
+    let Some(EngineOutput::Response { request_id: 12, output, .. }) = outputs.next() else {
+        panic!("期望收到回显请求 ID 的撤单回报");
+    };
+    assert!(matches!(*output, EngineOutput::CancelAck(ref ack) if ack.status == CancelStatus::Cancelled));
+    let Some(EngineOutput::Response { request_id: 13, output, .. }) = outputs.next() else {
+        panic!("期望收到回显请求 ID 的拒绝回报");
+    };
+    assert!(matches!(*output, EngineOutput::Reject(_)));
+    assert!(outputs.next().is_none());
+}
+
+#[tokio::test]
+async fn test_pipelined_requests_are_correlated_over_tcp() {
+    let (command_sender, command_receiver) = mpsc::unbounded_channel();
+    let (output_sender, output_receiver) = mpsc::unbounded_channel();
+    std::thread::spawn(move || MatchingEngine::new(command_receiver, output_sender).run());
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    tokio::spawn(network::serve(listener, command_sender, output_receiver));
+
+    let mut client = Framed::new(TcpStream::connect(addr).await.unwrap(), LengthDelimitedCodec::new());
+    // 不等待应答，连续发送多个请求
+    for request_id in 1..=5u64 {
+        let message = ClientMessage::Request {
+            request_id: request_id * 100,
+            message: Box::new(ClientMessage::NewOrder(order(request_id, OrderType::Buy, 100 - request_id, 1))),
+        };
+        client.send(bincode::encode_to_vec(&message, config::standard()).unwrap().into()).await.unwrap();
+    }
+
+    let mut confirmed = HashMap::new();
+    while confirmed.len() < 5 {
+        let frame = tokio::time::timeout(Duration::from_secs(5), client.next()).await.expect("等待应答超时");
+        let (message, _): (ServerMessage, usize) =
+            bincode::decode_from_slice(&frame.unwrap().unwrap(), config::standard()).unwrap();
+        if let ServerMessage::Response { request_id, message } = message {
+            let ServerMessage::Confirmation(confirmation) = *message else {
+                panic!("期望收到挂单确认");
+            };
+            confirmed.insert(request_id, confirmation.user_id);
+        }
+    }
+    for request_id in 1..=5u64 {
+        assert_eq!(confirmed[&(request_id * 100)], request_id);
+    }
+}
+
+async fn send_request(client: &mut Framed<TcpStream, LengthDelimitedCodec>, request_id: u64, message: ClientMessage) {
+    let message = ClientMessage::Request { request_id, message: Box::new(message) };
+    client.send(bincode::encode_to_vec(&message, config::standard()).unwrap().into()).await.unwrap();
+}
+
+// 读取消息直到收到给定请求 ID 的应答，返回应答之前收到的所有消息和应答本身
+async fn receive_until(client: &mut Framed<TcpStream, LengthDelimitedCodec>, request_id: u64) -> Vec<ServerMessage> {
+    let mut received = Vec::new();
+    loop {
+        let frame = tokio::time::timeout(Duration::from_secs(5), client.next()).await.expect("等待应答超时");
+        let (message, _): (ServerMessage, usize) =
+            bincode::decode_from_slice(&frame.unwrap().unwrap(), config::standard()).unwrap();
+        let done = matches!(message, ServerMessage::Response { request_id: id, .. } if id == request_id);
+        received.push(message);
+        if done {
+            return received;
+        }
+    }
+}
+
+#[tokio::test]
+async fn test_responses_go_only_to_the_requesting_connection() {
+    let (command_sender, command_receiver) = mpsc::unbounded_channel();
+    let (output_sender, output_receiver) = mpsc::unbounded_channel();
+    std::thread::spawn(move || MatchingEngine::new(command_receiver, output_sender).run());
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    tokio::spawn(network::serve(listener, command_sender, output_receiver));
+
+    let mut first = Framed::new(TcpStream::connect(addr).await.unwrap(), LengthDelimitedCodec::new());
+    let mut second = Framed::new(TcpStream::connect(addr).await.unwrap(), LengthDelimitedCodec::new());
+    // 两个连接使用相同的请求 ID，价格不交叉
+    send_request(&mut first, 1, ClientMessage::NewOrder(order(1, OrderType::Buy, 90, 1))).await;
+    send_request(&mut second, 1, ClientMessage::NewOrder(order(2, OrderType::Sell, 110, 1))).await;
+    let mut phase_one = vec![receive_until(&mut first, 1).await, receive_until(&mut second, 1).await];
+
+    // 第一轮的输出都已分发之后再发第二轮，任何串到另一个连接的消息都会排在第二轮应答之前
+    for (client, user_id) in [(&mut first, 1), (&mut second, 2)] {
+        let query = PositionQuery { user_id, symbol: "BTC/USD".to_string() };
+        send_request(client, 2, ClientMessage::QueryPosition(query)).await;
+    }
+    phase_one[0].extend(receive_until(&mut first, 2).await);
+    phase_one[1].extend(receive_until(&mut second, 2).await);
+
+    for (received, user_id) in phase_one.into_iter().zip([1, 2]) {
+        let mut confirmations = 0;
+        for message in received {
+            let ServerMessage::Response { message, .. } = message else {
+                panic!("用户 {} 的连接收到了其他消息: {:?}", user_id, message);
+            };
+            match *message {
+                ServerMessage::Confirmation(confirmation) => {
+                    assert_eq!(confirmation.user_id, user_id);
+                    confirmations += 1;
+                }
+                ServerMessage::Position(report) => assert_eq!(report.user_id, user_id),
+                other => panic!("unexpected response: {:?}", other),
+            }
+        }
+        assert_eq!(confirmations, 1);
+    }
+}
@@ -0,0 +1,139 @@
+use bincode::config;
+use futures::{SinkExt, StreamExt};
+use matching_engine::engine::{EngineCommand, EngineOutput, MatchingEngine};
+use matching_engine::network;
+use matching_engine::protocol::{ClientMessage, LogonRequest, NewOrderRequest, OrderType, ServerMessage};
+use matching_engine::rx_timestamp::{unix_nanos, TimestampedStream};
+use matching_engine::session::{sign_logon, ApiCredential, SessionConfig};
+use matching_engine::testing::Simulation;
+use std::collections::HashMap;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream};
+use tokio::sync::mpsc;
+use tokio_util::codec::{Framed, LengthDelimitedCodec};
+
+fn order(user_id: u64, order_type: OrderType, price: u64, quantity: u64) -> NewOrderRequest {
+    NewOrderRequest { user_id, symbol: "BTC/USD".to_string(), order_type, price, quantity }
+}
+
+#[test]
+fn test_received_timestamp_on_taker_reports() {
+    let mut simulation = Simulation::new();
+    simulation.execute(EngineCommand::NewOrder(order(1, OrderType::Sell, 100, 5)));
+
+    let received_at_ns = unix_nanos();
+    let command = EngineCommand::Request {
+        request_id: 7,
+        connection_id: 1,
+        command: Box::new(EngineCommand::NewOrder(order(2, OrderType::Buy, 100, 2))),
+    };
+    let outputs = simulation.execute(EngineCommand::Received { received_at_ns, command: Box::new(command) });
+    let mut reports = Vec::new();
+    for output in outputs {
+        let output = match output {
+            EngineOutput::Response { request_id: 7, output, .. } => *output,
+            output => output,
+        };
+        if let EngineOutput::ExecutionReport(report) = output {
+            reports.push((report.user_id, report.received_at_ns));
+        }
+    }
+    // 只有主动方的回报携带接收时间戳，并且仍然回显请求 ID
+    reports.sort();
+    assert_eq!(reports, vec![(1, None), (2, Some(received_at_ns))]);
+
+    // 不带时间戳的命令不受上一条命令影响
+    let outputs = simulation.execute(EngineCommand::NewOrder(order(3, OrderType::Buy, 100, 1)));
+    assert!(outputs.iter().all(|output| !matches!(output, EngineOutput::ExecutionReport(report) if report.received_at_ns.is_some())));
+}
+
+#[test]
+fn test_wire_latency_recorded() {
+    let (_command_sender, command_receiver) = mpsc::unbounded_channel();
+    let (output_sender, _output_receiver) = mpsc::unbounded_channel();
+    let mut engine = MatchingEngine::new(command_receiver, output_sender);
+    let metrics = engine.metrics();
+    let received_at_ns = unix_nanos() - 3_000_000;
+    let command = EngineCommand::NewOrder(order(1, OrderType::Buy, 100, 1));
+    engine.handle_command(EngineCommand::Received { received_at_ns, command: Box::new(command) });
+    assert_eq!(metrics.wire_latency.count(), 1);
+    assert!(metrics.wire_latency.sum_ns() >= 3_000_000);
+
+    let prometheus = matching_engine::metrics::render_prometheus(&metrics, &Default::default());
+    assert!(prometheus.contains("matching_engine_wire_to_match_seconds_count 1"));
+    assert!(prometheus.contains("matching_engine_wire_to_match_seconds_bucket{le=\"0.0025\"} 0"));
+    assert!(prometheus.contains("matching_engine_wire_to_match_seconds_bucket{le=\"0.01\"} 1"));
+}
+
+#[cfg(target_os = "linux")]
+#[tokio::test]
+async fn test_loopback_software_timestamp() {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let mut client = TcpStream::connect(listener.local_addr().unwrap()).await.unwrap();
+    let mut server = TimestampedStream::new(listener.accept().await.unwrap().0, true);
+    assert!(server.capturing());
+    assert_eq!(server.last_timestamp(), None);
+
+    let before = unix_nanos();
+    client.write_all(b"ping").await.unwrap();
+    let mut buf = [0u8; 4];
+    server.read_exact(&mut buf).await.unwrap();
+    assert_eq!(&buf, b"ping");
+    let timestamp = server.last_timestamp().expect("回环接口应有软件接收时间戳");
+    assert!(timestamp >= before && timestamp <= unix_nanos(), "{} 不在 {} 之后", timestamp, before);
+}
+
+#[cfg(target_os = "linux")]
+#[tokio::test]
+async fn test_execution_report_carries_wire_timestamp() {
+    let (command_sender, command_receiver) = mpsc::unbounded_channel::<EngineCommand>();
+    let (output_sender, output_receiver) = mpsc::unbounded_channel::<EngineOutput>();
+    let engine = MatchingEngine::new(command_receiver, output_sender);
+    let metrics = engine.metrics();
+    std::thread::spawn(move || {
+        let mut engine = engine;
+        engine.run();
+    });
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    let mut credentials = HashMap::new();
+    credentials.insert("key-1".to_string(), ApiCredential { user_id: 1, secret: b"secret".to_vec() });
+    let sessions = SessionConfig { rx_timestamps: true, require_logon: false, ..SessionConfig::with_credentials(credentials) };
+    tokio::spawn(network::serve_with_sessions(listener, command_sender, output_receiver, sessions));
+
+    let config = config::standard();
+    let mut framed = Framed::new(TcpStream::connect(addr).await.unwrap(), LengthDelimitedCodec::new());
+    // 以挂单方登录才能收到挂单方的回报；不要求登录，代用户 2 提交的主动方订单的回报直接发给本连接
+    let timestamp_ms = SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_millis() as u64;
+    let signature = sign_logon(b"secret", "key-1", timestamp_ms);
+    let logon = ClientMessage::Logon(LogonRequest { api_key: "key-1".to_string(), timestamp_ms, signature });
+    framed.send(bincode::encode_to_vec(logon, config).unwrap().into()).await.unwrap();
+    let frame = tokio::time::timeout(Duration::from_secs(5), framed.next()).await.unwrap().unwrap().unwrap();
+    assert!(matches!(bincode::decode_from_slice(&frame, config).unwrap().0, ServerMessage::Logon(_)));
+    let before = unix_nanos();
+    for message in [order(1, OrderType::Sell, 100, 1), order(2, OrderType::Buy, 100, 1)] {
+        let payload = bincode::encode_to_vec(ClientMessage::NewOrder(message), config).unwrap();
+        framed.send(payload.into()).await.unwrap();
+    }
+    // 成交的两份回报；卖单挂单时作为主动方的回报也带时间戳
+    let mut fills = Vec::new();
+    while fills.len() < 2 {
+        let frame = tokio::time::timeout(Duration::from_secs(5), framed.next()).await.unwrap().unwrap().unwrap();
+        if let (ServerMessage::ExecutionReport(report), _) = bincode::decode_from_slice(&frame, config).unwrap() {
+            if report.last_quantity > 0 {
+                fills.push(report);
+            }
+        }
+    }
+    let maker = fills.iter().find(|report| report.user_id == 1).unwrap();
+    let taker = fills.iter().find(|report| report.user_id == 2).unwrap();
+    assert_eq!(maker.received_at_ns, None);
+    assert!(taker.received_at_ns.is_some_and(|received| received >= before), "{:?} {}", taker.received_at_ns, before);
+    // 引擎在发出回报之后才记录耗时
+    let deadline = tokio::time::Instant::now() + Duration::from_secs(5);
+    while metrics.wire_latency.count() < 2 && tokio::time::Instant::now() < deadline {
+        tokio::time::sleep(Duration::from_millis(1)).await;
+    }
+    assert_eq!(metrics.wire_latency.count(), 2);
+}
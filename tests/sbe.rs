@@ -0,0 +1,79 @@
+use matching_engine::protocol::{NewOrderRequest, OrderType, TradeNotification};
+use matching_engine::sbe::{self, SbeError, SbeNewOrder, SbeTrade, Symbol, HEADER_LEN, SYMBOL_LEN};
+
+fn new_order() -> NewOrderRequest {
+    NewOrderRequest {
+        user_id: 42,
+        symbol: "BTC/USD".to_string(),
+        order_type: OrderType::Sell,
+        price: 50_000,
+        quantity: 7,
+    }
+}
+
+#[test]
+fn test_new_order_round_trip() {
+    let order = SbeNewOrder::from_request(&new_order()).unwrap();
+    let mut buf = [0u8; 128];
+    let len = order.encode(&mut buf).unwrap();
+    assert_eq!(len, SbeNewOrder::ENCODED_LEN);
+    assert_eq!(sbe::peek_template_id(&buf).unwrap(), sbe::NEW_ORDER_TEMPLATE_ID);
+
+    let decoded = SbeNewOrder::decode(&buf[..len]).unwrap();
+    assert_eq!(decoded, order);
+    assert_eq!(decoded.symbol.as_str(), "BTC/USD");
+    let request = decoded.to_request();
+    assert_eq!((request.user_id, request.symbol.as_str(), request.price), (42, "BTC/USD", 50_000));
+}
+
+#[test]
+fn test_trade_round_trip() {
+    let trade = TradeNotification {
+        trade_id: 9,
+        symbol: "ETH/USD".into(),
+        matched_price: 3_000,
+        matched_quantity: 2,
+        buyer_user_id: 1,
+        buyer_order_id: 11,
+        seller_user_id: 2,
+        seller_order_id: 12,
+        timestamp: 1_700_000_000_000_000_000,
+        is_block_trade: true,
+    };
+    let encoded = SbeTrade::from_notification(&trade).unwrap();
+    let mut buf = vec![0u8; SbeTrade::ENCODED_LEN];
+    encoded.encode(&mut buf).unwrap();
+    let decoded = SbeTrade::decode(&buf).unwrap().to_notification();
+    assert_eq!(decoded.trade_id, 9);
+    assert_eq!(&*decoded.symbol, "ETH/USD");
+    assert_eq!(decoded.timestamp, trade.timestamp);
+    assert!(decoded.is_block_trade);
+}
+
+#[test]
+fn test_decode_rejects_malformed_input() {
+    let mut buf = [0u8; SbeNewOrder::ENCODED_LEN];
+    SbeNewOrder::from_request(&new_order()).unwrap().encode(&mut buf).unwrap();
+
+    assert_eq!(
+        SbeNewOrder::decode(&buf[..20]),
+        Err(SbeError::BufferTooShort { needed: SbeNewOrder::ENCODED_LEN, available: 20 })
+    );
+    assert_eq!(SbeTrade::decode(&buf), Err(SbeError::UnexpectedTemplate(sbe::NEW_ORDER_TEMPLATE_ID)));
+
+    // 买卖方向字段位于 user_id 和合约代码之后
+    let mut bad_side = buf;
+    bad_side[HEADER_LEN + 8 + SYMBOL_LEN] = 9;
+    assert_eq!(SbeNewOrder::decode(&bad_side), Err(SbeError::InvalidSide(9)));
+
+    let mut bad_version = buf;
+    bad_version[6] = 2;
+    assert!(matches!(SbeNewOrder::decode(&bad_version), Err(SbeError::SchemaMismatch { .. })));
+
+    let mut long_buf = [0u8; SbeNewOrder::ENCODED_LEN];
+    assert_eq!(SbeNewOrder::from_request(&NewOrderRequest {
+        symbol: "X".repeat(SYMBOL_LEN + 1),
+        ..new_order()
+    }).map(|order| order.encode(&mut long_buf)), Err(SbeError::InvalidSymbol));
+    assert_eq!(Symbol::new("A\0B"), Err(SbeError::InvalidSymbol));
+}
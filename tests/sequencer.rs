@@ -0,0 +1,56 @@
+use matching_engine::id::IdGenerator;
+use matching_engine::orderbook::OrderBook;
+use matching_engine::protocol::{NewOrderRequest, OrderType};
+use matching_engine::sequencer::Sequencer;
+use std::sync::Arc;
+
+fn order(symbol: &str, order_type: OrderType, quantity: u64) -> NewOrderRequest {
+    NewOrderRequest {
+        user_id: 1,
+        symbol: symbol.to_string(),
+        order_type,
+        price: 100,
+        quantity,
+    }
+}
+
+#[test]
+fn test_book_trades_carry_sequenced_ids() {
+    let sequencer = Arc::new(Sequencer::new(Arc::new(IdGenerator::sequential())));
+    let mut btc = OrderBook::new();
+    let mut eth = OrderBook::new();
+    btc.set_sequencer(sequencer.clone());
+    eth.set_sequencer(sequencer);
+
+    btc.match_order(order("BTC/USD", OrderType::Sell, 5)).unwrap();
+    eth.match_order(order("ETH/USD", OrderType::Sell, 5)).unwrap();
+    let (btc_trades, _) = btc.match_order(order("BTC/USD", OrderType::Buy, 5)).unwrap();
+    let (eth_trades, _) = eth.match_order(order("ETH/USD", OrderType::Buy, 5)).unwrap();
+
+    // 不经过引擎直接撮合的成交也带有有效的成交号和时间戳，且在两个订单簿之间不重复
+    assert_eq!(btc_trades[0].trade_id, 1);
+    assert_eq!(eth_trades[0].trade_id, 2);
+    assert!(btc_trades[0].timestamp > 0);
+    assert!(eth_trades[0].timestamp >= btc_trades[0].timestamp);
+}
+
+#[test]
+fn test_timestamp_and_sequence_strictly_increase() {
+    let sequencer = Arc::new(Sequencer::new(Arc::new(IdGenerator::sequential())));
+    let handles: Vec<_> = (0..4)
+        .map(|_| {
+            let sequencer = sequencer.clone();
+            std::thread::spawn(move || (0..10_000).map(|_| sequencer.next_trade()).collect::<Vec<_>>())
+        })
+        .collect();
+    let mut events: Vec<(u64, u64)> = handles.into_iter().flat_map(|handle| handle.join().unwrap()).collect();
+
+    // 按成交号排序后时间戳严格递增
+    events.sort_unstable();
+    for pair in events.windows(2) {
+        let ((id_a, ts_a), (id_b, ts_b)) = (pair[0], pair[1]);
+        assert!(id_a < id_b);
+        assert!(ts_a < ts_b);
+    }
+    assert!(sequencer.next_timestamp() > events.last().unwrap().1);
+}
@@ -0,0 +1,210 @@
+use bincode::config;
+use futures::{SinkExt, StreamExt};
+use matching_engine::engine::{EngineCommand, EngineOutput, MatchingEngine};
+use matching_engine::network;
+use matching_engine::protocol::{
+    ClientMessage, LogonRequest, LogonStatus, NewOrderRequest, OcoOrderRequest, OrderType, PositionQuery, RejectReason,
+    ServerMessage,
+};
+use matching_engine::session::{sign_logon, ApiCredential, Session, SessionConfig};
+use std::collections::HashMap;
+use std::time::Duration;
+use tokio::net::{TcpListener, TcpStream};
+use tokio::sync::mpsc;
+use tokio_util::codec::{Framed, LengthDelimitedCodec};
+
+const SECRET: &[u8] = b"s3cret";
+
+fn sessions() -> SessionConfig {
+    let mut credentials = HashMap::new();
+    credentials.insert("key-101".to_string(), ApiCredential { user_id: 101, secret: SECRET.to_vec() });
+    SessionConfig::with_credentials(credentials)
+}
+
+fn logon(timestamp_ms: u64, secret: &[u8]) -> LogonRequest {
+    LogonRequest {
+        api_key: "key-101".to_string(),
+        timestamp_ms,
+        signature: sign_logon(secret, "key-101", timestamp_ms),
+    }
+}
+
+fn new_order(user_id: u64) -> NewOrderRequest {
+    NewOrderRequest {
+        user_id,
+        symbol: "BTC/USD".to_string(),
+        order_type: OrderType::Buy,
+        price: 100,
+        quantity: 1,
+    }
+}
+
+#[test]
+fn test_logon_validation() {
+    let config = sessions();
+    let now = 1_700_000_000_000;
+    let mut session = Session::new();
+
+    assert_eq!(session.logon_at(&config, &logon(now, b"wrong"), now), LogonStatus::InvalidSignature);
+    assert_eq!(session.logon_at(&config, &logon(now - 60_000, SECRET), now), LogonStatus::StaleTimestamp);
+    let unknown = LogonRequest { api_key: "other".to_string(), ..logon(now, SECRET) };
+    assert_eq!(session.logon_at(&config, &unknown, now), LogonStatus::UnknownApiKey);
+    assert_eq!(session.user_id(), None);
+
+    assert_eq!(session.logon_at(&config, &logon(now, SECRET), now), LogonStatus::Accepted);
+    assert_eq!(session.user_id(), Some(101));
+    assert_eq!(session.logon_at(&config, &logon(now, SECRET), now), LogonStatus::AlreadyLoggedOn);
+}
+
+#[test]
+fn test_authorize_binds_user() {
+    let config = sessions();
+    let mut session = Session::new();
+    let order = ClientMessage::NewOrder(new_order(101));
+    assert_eq!(session.authorize(&config, &order), Err(RejectReason::Unauthenticated));
+    // 未启用登录校验时不做限制
+    assert_eq!(session.authorize(&SessionConfig::default(), &order), Ok(()));
+
+    let now = 1_700_000_000_000;
+    session.logon_at(&config, &logon(now, SECRET), now);
+    assert_eq!(session.authorize(&config, &order), Ok(()));
+    let other = ClientMessage::NewOrder(new_order(102));
+    assert_eq!(session.authorize(&config, &other), Err(RejectReason::UserMismatch));
+}
+
+#[test]
+fn test_authorize_checks_oco_legs_in_every_mode() {
+    let oco = |first, second| ClientMessage::OcoOrder(OcoOrderRequest { link_id: 1, first: new_order(first), second: new_order(second) });
+    let mut session = Session::new();
+    // 未要求登录时两条腿也必须属于同一用户
+    let open = SessionConfig::default();
+    assert_eq!(session.authorize(&open, &oco(101, 101)), Ok(()));
+    assert_eq!(session.authorize(&open, &oco(101, 102)), Err(RejectReason::UserMismatch));
+
+    // 登录后两条腿都必须是会话用户，即使不要求登录
+    let config = sessions();
+    let now = 1_700_000_000_000;
+    session.logon_at(&config, &logon(now, SECRET), now);
+    for config in [&open, &config] {
+        assert_eq!(session.authorize(config, &oco(101, 101)), Ok(()));
+        assert_eq!(session.authorize(config, &oco(102, 102)), Err(RejectReason::UserMismatch));
+        assert_eq!(session.authorize(config, &oco(101, 102)), Err(RejectReason::UserMismatch));
+    }
+}
+
+async fn start_server(config: SessionConfig) -> std::net::SocketAddr {
+    let (command_sender, command_receiver) = mpsc::unbounded_channel::<EngineCommand>();
+    let (output_sender, output_receiver) = mpsc::unbounded_channel::<EngineOutput>();
+    std::thread::spawn(move || {
+        MatchingEngine::new(command_receiver, output_sender).run();
+    });
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    tokio::spawn(network::serve_with_sessions(listener, command_sender, output_receiver, config));
+    addr
+}
+
+async fn request(framed: &mut Framed<TcpStream, LengthDelimitedCodec>, message: ClientMessage) -> ServerMessage {
+    let config = config::standard();
+    framed.send(bincode::encode_to_vec(message, config).unwrap().into()).await.unwrap();
+    let frame = framed.next().await.unwrap().unwrap();
+    bincode::decode_from_slice(&frame, config).unwrap().0
+}
+
+#[tokio::test]
+async fn test_orders_require_logon() {
+    let addr = start_server(sessions()).await;
+    let mut framed = Framed::new(TcpStream::connect(addr).await.unwrap(), LengthDelimitedCodec::new());
+
+    let reply = request(&mut framed, ClientMessage::NewOrder(new_order(101))).await;
+    let ServerMessage::Reject(reject) = reply else {
+        panic!("期望未登录的订单被拒绝, 实际收到: {:?}", reply);
+    };
+    assert_eq!(reject.reason, RejectReason::Unauthenticated);
+
+    let now = std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH).unwrap().as_millis() as u64;
+    let reply = request(&mut framed, ClientMessage::Logon(logon(now, SECRET))).await;
+    let ServerMessage::Logon(response) = reply else {
+        panic!("期望收到登录回报, 实际收到: {:?}", reply);
+    };
+    assert_eq!((response.status, response.user_id), (LogonStatus::Accepted, 101));
+
+    let reply = request(&mut framed, ClientMessage::NewOrder(new_order(101))).await;
+    assert!(matches!(reply, ServerMessage::Confirmation(_)), "实际收到: {:?}", reply);
+}
+
+#[tokio::test]
+async fn test_missed_heartbeats_close_connection_and_cancel_orders() {
+    let config = SessionConfig {
+        require_logon: false,
+        heartbeat_interval: Some(Duration::from_millis(100)),
+        max_missed_heartbeats: 3,
+        ..sessions()
+    };
+    let addr = start_server(config).await;
+    let mut idle = Framed::new(TcpStream::connect(addr).await.unwrap(), LengthDelimitedCodec::new());
+    let mut watcher = Framed::new(TcpStream::connect(addr).await.unwrap(), LengthDelimitedCodec::new());
+
+    // 同一用户的另一个连接持续发送心跳保持在线，并等待断线撤单的回报；
+    // 不要求登录时也只有登录的连接接收该用户的私有回报
+    let now = std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH).unwrap().as_millis() as u64;
+    let reply = request(&mut watcher, ClientMessage::Logon(logon(now, SECRET))).await;
+    assert!(matches!(reply, ServerMessage::Logon(ref response) if response.status == LogonStatus::Accepted), "实际收到: {:?}", reply);
+    let cancel_ack = tokio::spawn(async move {
+        let config = config::standard();
+        let mut ticker = tokio::time::interval(Duration::from_millis(50));
+        loop {
+            tokio::select! {
+                _ = ticker.tick() => {
+                    watcher.send(bincode::encode_to_vec(ClientMessage::Heartbeat, config).unwrap().into()).await.unwrap();
+                }
+                frame = watcher.next() => {
+                    if let (ServerMessage::CancelAck(ack), _) = bincode::decode_from_slice(&frame.unwrap().unwrap(), config).unwrap() {
+                        return ack;
+                    }
+                }
+            }
+        }
+    });
+
+    let reply = request(&mut idle, ClientMessage::NewOrder(new_order(101))).await;
+    let ServerMessage::Confirmation(confirmation) = reply else {
+        panic!("期望收到挂单确认, 实际收到: {:?}", reply);
+    };
+
+    // 空闲连接只收到服务器心跳，错过 3 次心跳后被断开
+    let mut heartbeats = 0;
+    let closed = tokio::time::timeout(Duration::from_secs(5), async {
+        while let Some(frame) = idle.next().await {
+            let (message, _): (ServerMessage, usize) = bincode::decode_from_slice(&frame.unwrap(), config::standard()).unwrap();
+            assert!(matches!(message, ServerMessage::Heartbeat), "实际收到: {:?}", message);
+            heartbeats += 1;
+        }
+    });
+    closed.await.expect("连接应因错过心跳被关闭");
+    assert!(heartbeats >= 2);
+
+    let ack = tokio::time::timeout(Duration::from_secs(5), cancel_ack)
+        .await
+        .expect("期望收到断线撤单回报")
+        .unwrap();
+    assert_eq!((ack.user_id, ack.order_id, ack.cancelled_quantity), (101, confirmation.order_id, 1));
+}
+
+// 不要求登录时，用户 ID 由客户端自报：未登录连接只收到自己命令的应答，自报他人的用户 ID 不会收到他人的回报
+#[tokio::test]
+async fn test_anonymous_connection_receives_only_its_own_replies() {
+    let addr = start_server(SessionConfig::default()).await;
+    let mut owner = Framed::new(TcpStream::connect(addr).await.unwrap(), LengthDelimitedCodec::new());
+    let mut snooper = Framed::new(TcpStream::connect(addr).await.unwrap(), LengthDelimitedCodec::new());
+    let query = || ClientMessage::QueryPosition(PositionQuery { user_id: 101, symbol: "BTC/USD".to_string() });
+
+    let reply = request(&mut snooper, query()).await;
+    assert!(matches!(reply, ServerMessage::Position(ref report) if report.user_id == 101), "实际收到: {:?}", reply);
+    let reply = request(&mut owner, ClientMessage::NewOrder(new_order(101))).await;
+    assert!(matches!(reply, ServerMessage::Confirmation(_)), "实际收到: {:?}", reply);
+
+    // 挂单确认只发给下单的连接，另一个连接下一条收到的是自己查询的应答
+    let reply = request(&mut snooper, query()).await;
+    assert!(matches!(reply, ServerMessage::Position(_)), "实际收到: {:?}", reply);
+}
@@ -0,0 +1,69 @@
+use matching_engine::engine::{ControlCommand, EngineCommand, MatchingEngine};
+use matching_engine::protocol::{NewOrderRequest, OrderType};
+use matching_engine::settlement::{run_end_of_day, sha256_hex, until_cutoff, SettlementConfig, SettlementManifest};
+use std::time::Duration;
+use tokio::sync::mpsc;
+
+fn order(user_id: u64, order_type: OrderType, price: u64, quantity: u64) -> EngineCommand {
+    EngineCommand::NewOrder(NewOrderRequest {
+        user_id,
+        symbol: "BTC/USD".to_string(),
+        order_type,
+        price,
+        quantity,
+    })
+}
+
+#[test]
+fn test_end_of_day_settlement() {
+    let dir = std::env::temp_dir().join(format!("settlement-test-{}", std::process::id()));
+    let config = SettlementConfig { dir: dir.clone(), fee_bps: 10 };
+
+    let (commands, command_receiver) = mpsc::unbounded_channel();
+    let (output_sender, _outputs) = mpsc::unbounded_channel();
+    let engine_thread = std::thread::spawn(move || {
+        MatchingEngine::new(command_receiver, output_sender).run();
+    });
+
+    commands.send(order(1, OrderType::Sell, 1000, 10)).unwrap();
+    commands.send(order(2, OrderType::Buy, 1000, 4)).unwrap();
+    commands.send(order(3, OrderType::Buy, 1000, 6)).unwrap();
+
+    let manifest = run_end_of_day(&commands, "20240102", &config).unwrap();
+    assert_eq!(manifest.trades, 2);
+    assert_eq!(manifest.legs, 4);
+    assert_eq!(manifest.total_quantity, 10);
+    // 每条腿收取成交金额的 0.1%：(4000 + 6000) * 0.001 * 2
+    assert_eq!(manifest.total_fees, 20);
+
+    let csv = std::fs::read(dir.join(&manifest.file)).unwrap();
+    assert_eq!(sha256_hex(&csv), manifest.sha256);
+    let csv = String::from_utf8(csv).unwrap();
+    assert_eq!(csv.lines().count(), 5);
+    assert!(csv.lines().nth(1).unwrap().starts_with("20240102,1,BTC/USD,BUY,2,"));
+
+    let written: SettlementManifest =
+        serde_json::from_slice(&std::fs::read(dir.join("settlement_20240102.manifest.json")).unwrap()).unwrap();
+    assert_eq!(written, manifest);
+
+    // 日终取走成交后，下一个交易日从空记录开始
+    let next_day = run_end_of_day(&commands, "20240103", &config).unwrap();
+    assert_eq!(next_day.trades, 0);
+
+    commands.send(EngineCommand::Control(ControlCommand::Drain)).unwrap();
+    engine_thread.join().unwrap();
+    std::fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn test_until_cutoff() {
+    let day = 86_400;
+    let at = |days: u64, secs: u64| Duration::from_secs(days * day + secs);
+    let cutoff = Duration::from_secs(17 * 3_600);
+    assert_eq!(until_cutoff(at(100, 16 * 3_600), cutoff), Duration::from_secs(3_600));
+    // 恰好在日终时刻或已过日终时刻时等到第二天
+    assert_eq!(until_cutoff(at(100, 17 * 3_600), cutoff), Duration::from_secs(day));
+    assert_eq!(until_cutoff(at(100, 18 * 3_600), cutoff), Duration::from_secs(23 * 3_600));
+    let just_after_midnight = at(100, 0) + Duration::from_millis(500);
+    assert_eq!(until_cutoff(just_after_midnight, Duration::ZERO), Duration::from_secs(day) - Duration::from_millis(500));
+}
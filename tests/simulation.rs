@@ -0,0 +1,55 @@
+use matching_engine::book_analysis;
+use matching_engine::engine::{EngineCommand, EngineOutput};
+use matching_engine::protocol::{CancelOrderRequest, NewOrderRequest, OrderType};
+use matching_engine::testing::{self, RandomFlow, RandomFlowConfig, Simulation};
+
+fn order(user_id: u64, order_type: OrderType, price: u64, quantity: u64) -> EngineCommand {
+    EngineCommand::NewOrder(NewOrderRequest { user_id, symbol: "SIM".to_string(), order_type, price, quantity })
+}
+
+#[test]
+fn same_seed_produces_identical_transcript() {
+    let mut first = testing::run_random(7, 300, RandomFlowConfig::default());
+    let mut second = testing::run_random(7, 300, RandomFlowConfig::default());
+    assert_eq!(first.finish(), second.finish());
+
+    let mut other = testing::run_random(8, 300, RandomFlowConfig::default());
+    assert_ne!(first.transcript(), other.finish());
+}
+
+#[test]
+fn random_flow_never_leaves_book_crossed() {
+    let mut simulation = Simulation::new();
+    let mut flow = RandomFlow::new(11, RandomFlowConfig::default());
+    let mut trades = 0;
+    for step in 0..500 {
+        let command = flow.next_command(&simulation);
+        trades += simulation.execute(command).iter().filter(|o| matches!(o, EngineOutput::Trade(_))).count();
+        if let Some(snapshot) = simulation.depth("SIM") {
+            assert!(!book_analysis::is_crossed(&snapshot), "第 {} 步后订单簿交叉", step + 1);
+            assert_eq!(snapshot.resting_orders as usize, simulation.resting_orders().count());
+        }
+    }
+    assert!(trades > 0);
+}
+
+#[test]
+fn scripted_scenario_matches_golden() {
+    let mut simulation = Simulation::new();
+    simulation.execute(order(1, OrderType::Sell, 101, 5));
+    simulation.execute(order(2, OrderType::Sell, 100, 3));
+    simulation.execute(order(3, OrderType::Buy, 99, 4));
+    simulation.execute(order(4, OrderType::Buy, 101, 6));
+    simulation.execute(EngineCommand::CancelOrder(CancelOrderRequest {
+        user_id: 3,
+        symbol: "SIM".to_string(),
+        order_id: 3,
+    }));
+    testing::assert_golden("tests/golden/scripted_scenario.txt", simulation.finish());
+}
+
+#[test]
+fn random_flow_matches_golden() {
+    let mut simulation = testing::run_random(42, 40, RandomFlowConfig::default());
+    testing::assert_golden("tests/golden/random_flow_seed42.txt", simulation.finish());
+}
@@ -0,0 +1,150 @@
+use matching_engine::engine::{EngineCommand, EngineOutput};
+use matching_engine::instruments::InstrumentMaster;
+use matching_engine::protocol::{
+    DepthLevel, ImpliedQuote, NewOrderRequest, OrderType, PositionQuery, RejectReason, SpreadOrderRequest,
+};
+use matching_engine::spread::{implied_quote, plan_fills, LegFill};
+use matching_engine::testing::Simulation;
+
+const INSTRUMENTS: &str = r#"{
+    "instruments": [
+        {"symbol": "CL-JUN"},
+        {"symbol": "CL-SEP"},
+        {"symbol": "CL-JUN-SEP", "spread_legs": ["CL-JUN", "CL-SEP"]}
+    ]
+}"#;
+
+fn level(price: u64, quantity: u64) -> DepthLevel {
+    DepthLevel { price, quantity, order_count: 1 }
+}
+
+fn order(user_id: u64, symbol: &str, order_type: OrderType, price: u64, quantity: u64) -> EngineCommand {
+    EngineCommand::NewOrder(NewOrderRequest { user_id, symbol: symbol.to_string(), order_type, price, quantity })
+}
+
+fn spread_order(order_type: OrderType, price: i64, quantity: u64) -> EngineCommand {
+    EngineCommand::SpreadOrder(SpreadOrderRequest {
+        user_id: 9,
+        spread: "CL-JUN-SEP".to_string(),
+        order_type,
+        price,
+        quantity,
+    })
+}
+
+fn simulation() -> Simulation {
+    let master = InstrumentMaster::parse_json(INSTRUMENTS).unwrap();
+    Simulation::with_engine(|engine| master.configure(engine))
+}
+
+fn position(sim: &mut Simulation, symbol: &str) -> i64 {
+    let query = PositionQuery { user_id: 9, symbol: symbol.to_string() };
+    match sim.execute(EngineCommand::QueryPosition(query)).pop() {
+        Some(EngineOutput::Position(report)) => report.net_position,
+        other => panic!("期望收到持仓回报: {:?}", other),
+    }
+}
+
+fn implied_quotes(outputs: &[EngineOutput]) -> Vec<ImpliedQuote> {
+    outputs
+        .iter()
+        .filter_map(|output| match output {
+            EngineOutput::ImpliedQuote(quote) => Some(quote.clone()),
+            _ => None,
+        })
+        .collect()
+}
+
+#[test]
+fn test_plan_fills_stops_at_limit() {
+    // 买入价差：吃 leg1 卖盘、leg2 买盘，价差依次为 5、6、7
+    let leg1_asks = [level(105, 2), level(106, 3)];
+    let leg2_bids = [level(100, 1), level(99, 5)];
+    let fills = plan_fills(OrderType::Buy, 6, 10, &leg1_asks, &leg2_bids);
+    assert_eq!(
+        fills,
+        vec![
+            LegFill { leg1_price: 105, leg2_price: 100, quantity: 1 },
+            LegFill { leg1_price: 105, leg2_price: 99, quantity: 1 },
+        ]
+    );
+    assert_eq!(plan_fills(OrderType::Buy, 10, 3, &leg1_asks, &leg2_bids).iter().map(|f| f.quantity).sum::<u64>(), 3);
+
+    // 卖出价差可以为负：吃 leg1 买盘、leg2 卖盘
+    let fills = plan_fills(OrderType::Sell, -3, 4, &[level(97, 4)], &[level(99, 2), level(101, 2)]);
+    assert_eq!(fills, vec![LegFill { leg1_price: 97, leg2_price: 99, quantity: 2 }]);
+    assert_eq!(fills[0].spread_price(), -2);
+}
+
+#[test]
+fn test_implied_quote_needs_both_legs() {
+    let quote = implied_quote("S", (&[level(104, 3)], &[level(106, 1)]), (&[], &[level(101, 2)]));
+    assert_eq!((quote.bid_price, quote.bid_quantity), (Some(3), 2));
+    assert_eq!((quote.ask_price, quote.ask_quantity), (None, 0));
+}
+
+#[test]
+fn test_spread_order_executes_both_legs_atomically() {
+    let mut sim = simulation();
+    sim.execute(order(1, "CL-JUN", OrderType::Sell, 105, 2));
+    sim.execute(order(1, "CL-JUN", OrderType::Sell, 106, 3));
+    let outputs = sim.execute(order(2, "CL-SEP", OrderType::Buy, 99, 5));
+    assert_eq!(implied_quotes(&outputs)[0].ask_price, Some(6));
+    // leg2 买一变化时广播新的隐含报价：卖价 = leg1 卖一 105 − leg2 买一 100
+    let outputs = sim.execute(order(2, "CL-SEP", OrderType::Buy, 100, 1));
+    let quotes = implied_quotes(&outputs);
+    assert_eq!(quotes.len(), 1);
+    assert_eq!((quotes[0].ask_price, quotes[0].ask_quantity), (Some(5), 1));
+    assert_eq!((quotes[0].bid_price, quotes[0].bid_quantity), (None, 0));
+    // leg1 没有买盘，隐含买价不变，不重复广播
+    assert!(implied_quotes(&sim.execute(order(3, "CL-SEP", OrderType::Sell, 103, 1))).is_empty());
+
+    // 价差 5 和 6 的组合在限价内，价差 7 的不成交，剩余数量撤销
+    let outputs = sim.execute(spread_order(OrderType::Buy, 6, 10));
+    let trades: Vec<(String, u64, u64)> = outputs
+        .iter()
+        .filter_map(|output| match output {
+            EngineOutput::Trade(trade) => Some((trade.symbol.to_string(), trade.matched_price, trade.matched_quantity)),
+            _ => None,
+        })
+        .collect();
+    assert_eq!(
+        trades,
+        vec![
+            ("CL-JUN".to_string(), 105, 1),
+            ("CL-SEP".to_string(), 100, 1),
+            ("CL-JUN".to_string(), 105, 1),
+            ("CL-SEP".to_string(), 99, 1),
+        ]
+    );
+    assert!(!outputs.iter().any(|output| matches!(output, EngineOutput::Confirmation(_))));
+    assert_eq!((position(&mut sim, "CL-JUN"), position(&mut sim, "CL-SEP")), (2, -2));
+
+    // 隐含卖价变为 leg1 卖一 106 − leg2 买一 99
+    let quotes = implied_quotes(&outputs);
+    assert_eq!((quotes.last().unwrap().ask_price, quotes.last().unwrap().ask_quantity), (Some(7), 3));
+}
+
+#[test]
+fn test_spread_order_rejects() {
+    let mut sim = simulation();
+    sim.execute(order(1, "CL-JUN", OrderType::Sell, 105, 2));
+    sim.execute(order(2, "CL-SEP", OrderType::Buy, 100, 2));
+
+    let reject = |outputs: Vec<EngineOutput>| match outputs.as_slice() {
+        [EngineOutput::Reject(reject)] => reject.reason,
+        other => panic!("期望收到拒绝: {:?}", other),
+    };
+    // 限价内没有隐含流动性时整单拒绝，两腿都不成交
+    assert_eq!(reject(sim.execute(spread_order(OrderType::Buy, 4, 1))), RejectReason::NoImpliedLiquidity);
+    assert_eq!(reject(sim.execute(spread_order(OrderType::Buy, 6, 0))), RejectReason::InvalidQuantity);
+    let unknown = EngineCommand::SpreadOrder(SpreadOrderRequest {
+        user_id: 9,
+        spread: "CL-JUN-DEC".to_string(),
+        order_type: OrderType::Buy,
+        price: 5,
+        quantity: 1,
+    });
+    assert_eq!(reject(sim.execute(unknown)), RejectReason::UnknownSpread);
+    assert_eq!((position(&mut sim, "CL-JUN"), position(&mut sim, "CL-SEP")), (0, 0));
+}
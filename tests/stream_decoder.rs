@@ -0,0 +1,90 @@
+use bytes::BytesMut;
+use matching_engine::codec::{encode_frame, BincodeCodec, Codec, CodecError, ServerStreamDecoder};
+use matching_engine::protobuf::ProtobufCodec;
+use matching_engine::protocol::{OrderConfirmation, ServerMessage};
+use tokio_util::codec::{Encoder, LengthDelimitedCodec};
+
+fn confirmation(order_id: u64) -> ServerMessage {
+    ServerMessage::Confirmation(OrderConfirmation { order_id, user_id: 1 })
+}
+
+fn order_ids(messages: impl Iterator<Item = Result<ServerMessage, CodecError>>) -> Vec<u64> {
+    messages
+        .map(|message| match message.unwrap() {
+            ServerMessage::Confirmation(confirmation) => confirmation.order_id,
+            other => panic!("unexpected message: {:?}", other),
+        })
+        .collect()
+}
+
+fn stream(codec: &impl Codec, order_ids: &[u64]) -> BytesMut {
+    let mut bytes = BytesMut::new();
+    for &order_id in order_ids {
+        encode_frame(&codec.encode_server(&confirmation(order_id)).unwrap(), &mut bytes);
+    }
+    bytes
+}
+
+#[test]
+fn test_frame_split_across_reads() {
+    let bytes = stream(&BincodeCodec, &[1, 2, 3]);
+    let mut decoder = ServerStreamDecoder::new(BincodeCodec);
+    let mut decoded = Vec::new();
+    // 每次只喂 1 个字节，模拟最坏情况的 TCP 分片
+    for byte in bytes.iter() {
+        decoder.extend(std::slice::from_ref(byte));
+        decoded.extend(order_ids(decoder.messages()));
+    }
+    assert_eq!(decoded, vec![1, 2, 3]);
+    assert_eq!(decoder.buffered_len(), 0);
+}
+
+#[test]
+fn test_multiple_frames_in_one_read() {
+    let mut bytes = stream(&ProtobufCodec, &[10, 11, 12]);
+    // 末尾再附上半帧
+    let tail = stream(&ProtobufCodec, &[13]);
+    bytes.extend_from_slice(&tail[..tail.len() - 1]);
+
+    let mut decoder = ServerStreamDecoder::new(ProtobufCodec);
+    decoder.extend(&bytes);
+    assert_eq!(order_ids(decoder.messages()), vec![10, 11, 12]);
+    assert_eq!(decoder.buffered_len(), tail.len() - 1);
+
+    decoder.extend(&tail[tail.len() - 1..]);
+    assert_eq!(order_ids(decoder.messages()), vec![13]);
+}
+
+#[test]
+fn test_compatible_with_length_delimited_codec() {
+    let mut bytes = BytesMut::new();
+    let mut framing = LengthDelimitedCodec::new();
+    for order_id in [5, 6] {
+        let payload = BincodeCodec.encode_server(&confirmation(order_id)).unwrap();
+        framing.encode(payload.into(), &mut bytes).unwrap();
+    }
+    let mut decoder = ServerStreamDecoder::new(BincodeCodec);
+    decoder.extend(&bytes);
+    assert_eq!(order_ids(decoder.messages()), vec![5, 6]);
+}
+
+#[test]
+fn test_corrupt_frame_does_not_desync_stream() {
+    let mut bytes = BytesMut::new();
+    encode_frame(&[0xff, 0xff, 0xff], &mut bytes);
+    bytes.extend_from_slice(&stream(&BincodeCodec, &[7]));
+
+    let mut decoder = ServerStreamDecoder::new(BincodeCodec);
+    decoder.extend(&bytes);
+    assert!(matches!(decoder.next_message(), Some(Err(CodecError::Decode(_)))));
+    assert_eq!(order_ids(decoder.messages()), vec![7]);
+}
+
+#[test]
+fn test_oversized_frame_rejected() {
+    let mut decoder = ServerStreamDecoder::new(BincodeCodec).with_max_frame_len(16);
+    decoder.extend(&1_000u32.to_be_bytes());
+    assert!(matches!(decoder.next_message(), Some(Err(CodecError::FrameTooLong(1_000)))));
+    assert_eq!(decoder.buffered_len(), 0);
+    assert!(decoder.next_message().is_none());
+}
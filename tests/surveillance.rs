@@ -0,0 +1,149 @@
+use matching_engine::engine::{ControlCommand, EngineCommand, MatchingEngine};
+use matching_engine::metrics::EngineMetrics;
+use matching_engine::protocol::{CancelOrderRequest, NewOrderRequest, OrderType};
+use matching_engine::surveillance::{
+    self, Detector, MarketEvent, MeterKind, QuoteStuffingDetector, SelfCrossDetector, Surveillance, SurveillanceRule,
+    TradeMeter, WashTradeDetector,
+};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::mpsc;
+
+fn volume_rule(threshold: f64) -> SurveillanceRule {
+    SurveillanceRule {
+        name: "btc-volume".to_string(),
+        symbol: "BTC/USD".to_string(),
+        kind: MeterKind::VolumePerSec,
+        window: Duration::from_secs(1),
+        threshold,
+    }
+}
+
+#[test]
+fn test_meter_window_expires_old_buckets() {
+    let meter = TradeMeter::default();
+    meter.record_at(10_000, 5);
+    meter.record_at(10_050, 5);
+    meter.record_at(10_450, 20);
+
+    let reading = meter.reading_at(10_450, Duration::from_secs(1));
+    assert_eq!(reading.trades_per_sec, 3.0);
+    assert_eq!(reading.volume_per_sec, 30.0);
+
+    // 200 毫秒窗口只包含最近两个桶
+    let reading = meter.reading_at(10_450, Duration::from_millis(200));
+    assert_eq!(reading.volume_per_sec, 100.0);
+
+    // 1 秒后早期的成交移出窗口；复用的桶会先清零
+    meter.record_at(11_000, 1);
+    let reading = meter.reading_at(11_000, Duration::from_secs(1));
+    assert_eq!(reading.trades_per_sec, 2.0);
+    assert_eq!(reading.volume_per_sec, 21.0);
+}
+
+#[test]
+fn test_rule_alerts_once_per_breach() {
+    let surveillance = Surveillance::new();
+    surveillance.add_rule(volume_rule(50.0));
+    let meter = surveillance.meter("BTC/USD");
+
+    meter.record_at(1_000, 40);
+    assert!(surveillance.evaluate_at(1_000).is_empty());
+
+    meter.record_at(1_100, 20);
+    let alerts = surveillance.evaluate_at(1_100);
+    assert_eq!(alerts.len(), 1);
+    assert_eq!(alerts[0].rule, "btc-volume");
+    assert_eq!(alerts[0].observed, 60.0);
+
+    // 持续超限不重复告警，回落后再次超限才会告警
+    assert!(surveillance.evaluate_at(1_200).is_empty());
+    assert!(surveillance.evaluate_at(2_500).is_empty());
+    meter.record_at(2_600, 100);
+    assert_eq!(surveillance.evaluate_at(2_600).len(), 1);
+
+    surveillance.remove_rule("btc-volume");
+    assert!(surveillance.rules().is_empty());
+}
+
+#[test]
+fn test_engine_meters_trades_and_registers_rules() {
+    let (commands, command_receiver) = mpsc::unbounded_channel();
+    let (output_sender, _outputs) = mpsc::unbounded_channel();
+    let engine = MatchingEngine::new(command_receiver, output_sender);
+    let surveillance = engine.surveillance();
+    let engine_thread = std::thread::spawn(move || {
+        let mut engine = engine;
+        engine.run();
+    });
+
+    commands.send(EngineCommand::Control(ControlCommand::AddSurveillanceRule(volume_rule(5.0)))).unwrap();
+    for order_type in [OrderType::Sell, OrderType::Buy] {
+        commands.send(EngineCommand::NewOrder(NewOrderRequest {
+            user_id: 1,
+            symbol: "BTC/USD".to_string(),
+            order_type,
+            price: 100,
+            quantity: 10,
+        })).unwrap();
+    }
+    commands.send(EngineCommand::Control(ControlCommand::Drain)).unwrap();
+    engine_thread.join().unwrap();
+
+    let reading = surveillance.reading("BTC/USD", Duration::from_secs(1)).unwrap();
+    assert_eq!(reading.trades_per_sec, 1.0);
+    assert_eq!(reading.volume_per_sec, 10.0);
+    assert_eq!(surveillance.rules(), vec![volume_rule(5.0)]);
+    assert_eq!(surveillance.evaluate().len(), 1);
+}
+
+fn order(user_id: u64, order_type: OrderType, price: u64) -> EngineCommand {
+    EngineCommand::NewOrder(NewOrderRequest { user_id, symbol: "BTC/USD".to_string(), order_type, price, quantity: 10 })
+}
+
+#[test]
+fn test_engine_tap_feeds_wash_and_self_cross_detectors() {
+    let (_commands, command_receiver) = mpsc::unbounded_channel();
+    let (output_sender, _outputs) = mpsc::unbounded_channel();
+    let metrics = Arc::new(EngineMetrics::new());
+    let owners = WashTradeDetector::parse_owners("1:100,2:100").unwrap();
+    let detectors: Vec<Box<dyn Detector>> = vec![Box::new(WashTradeDetector::new(owners)), Box::new(SelfCrossDetector)];
+    let (alert_sender, alerts) = std::sync::mpsc::channel();
+    let (tap, detector_thread) = surveillance::spawn_market_detectors(detectors, metrics.clone(), alert_sender);
+    let mut engine = MatchingEngine::new(command_receiver, output_sender).with_surveillance_tap(tap);
+
+    // 用户 1 和 2 属于同一实益拥有人；用户 3 与自己的挂单成交；用户 4 与用户 1 的成交是正常成交
+    engine.handle_command(order(1, OrderType::Sell, 100));
+    engine.handle_command(order(2, OrderType::Buy, 100));
+    engine.handle_command(order(3, OrderType::Sell, 101));
+    engine.handle_command(order(3, OrderType::Buy, 101));
+    engine.handle_command(order(1, OrderType::Sell, 102));
+    engine.handle_command(order(4, OrderType::Buy, 102));
+    drop(engine);
+    detector_thread.join().unwrap();
+
+    let alerts: Vec<_> = alerts.try_iter().map(|alert| (alert.detector, alert.user_ids)).collect();
+    assert_eq!(alerts, [("wash_trade", vec![2, 1]), ("self_cross", vec![3])]);
+    assert_eq!(metrics.snapshot().surveillance_alerts, 2);
+}
+
+#[test]
+fn test_quote_stuffing_alerts_once_per_burst() {
+    let mut detector = QuoteStuffingDetector::new(3, Duration::from_secs(1));
+    let cancel = MarketEvent::from_command(&EngineCommand::CancelOrder(CancelOrderRequest {
+        user_id: 7,
+        symbol: "BTC/USD".to_string(),
+        order_id: 1,
+    }))
+    .remove(0);
+    let observe = |detector: &mut QuoteStuffingDetector, times: &[u64]| {
+        times.iter().filter(|&&now_ms| detector.observe(&cancel, now_ms).is_some()).count()
+    };
+    // 第 4 条消息超限，之后持续超限不重复告警
+    assert_eq!(observe(&mut detector, &[0, 100, 200, 300, 400, 500]), 1);
+    // 窗口滑过后回落到上限以内，新的突发再次告警
+    assert_eq!(observe(&mut detector, &[2_000, 2_010, 2_020, 2_030]), 1);
+    // 其他用户单独计数
+    let other = MarketEvent::from_command(&order(8, OrderType::Buy, 100)).remove(0);
+    assert!(detector.observe(&other, 2_040).is_none());
+}
@@ -0,0 +1,121 @@
+use matching_engine::engine::{ControlCommand, EngineCommand, EngineOutput};
+use matching_engine::protocol::{BlockTradeRequest, NewOrderRequest, OrderType, RejectReason, TradeNotification};
+use matching_engine::symbols::{SymbolPool, SymbolPoolStats};
+use matching_engine::testing::Simulation;
+use std::sync::{mpsc, Arc};
+
+fn order(user_id: u64, symbol: &str, order_type: OrderType, price: u64, quantity: u64) -> EngineCommand {
+    EngineCommand::NewOrder(NewOrderRequest { user_id, symbol: symbol.to_string(), order_type, price, quantity })
+}
+
+fn trades(outputs: Vec<EngineOutput>) -> Vec<TradeNotification> {
+    outputs
+        .into_iter()
+        .filter_map(|output| match output {
+            EngineOutput::Trade(trade) => Some(trade),
+            _ => None,
+        })
+        .collect()
+}
+
+#[test]
+fn test_pool_assigns_dense_ids() {
+    let mut pool = SymbolPool::new();
+    assert!(pool.is_empty());
+    assert_eq!(pool.intern("BTC/USD"), Some(0));
+    assert_eq!(pool.intern("ETH/USD"), Some(1));
+    assert_eq!(pool.intern("BTC/USD"), Some(0));
+    assert_eq!(pool.len(), 2);
+    assert_eq!((pool.id("ETH/USD"), pool.id("SOL/USD")), (Some(1), None));
+    assert_eq!(pool.name(1).map(|name| &**name), Some("ETH/USD"));
+    assert!(pool.name(2).is_none());
+    // 同一合约名只驻留一份
+    assert!(Arc::ptr_eq(&pool.intern_name("BTC/USD").unwrap(), pool.name(0).unwrap()));
+    assert_eq!(pool.iter().collect::<Vec<_>>(), vec![(0, "BTC/USD"), (1, "ETH/USD")]);
+    let stats = pool.stats();
+    assert_eq!((stats.size, stats.hits, stats.misses, stats.evictions), (2, 2, 2, 0));
+}
+
+#[test]
+fn test_full_pool_evicts_least_recently_used_idle_symbol() {
+    let mut pool = SymbolPool::new();
+    pool.set_capacity_limit(Some(2));
+    let btc = pool.intern_name("BTC").unwrap();
+    pool.intern("ETH");
+    pool.intern("SOL");
+    // BTC 仍被引用，回收的是空闲的 ETH，ID 被 SOL 复用
+    assert_eq!((pool.id("BTC"), pool.id("ETH"), pool.id("SOL")), (Some(0), None, Some(1)));
+
+    pool.intern("XRP");
+    assert_eq!((pool.id("SOL"), pool.id("XRP")), (None, Some(1)));
+    // 在用的合约最近是否使用无关紧要，但空闲合约中回收最久未使用的
+    drop(btc);
+    pool.intern("XRP");
+    pool.intern("ADA");
+    assert_eq!((pool.id("BTC"), pool.id("XRP"), pool.id("ADA")), (None, Some(1), Some(0)));
+
+    // 全部在用时无法驻留新合约
+    let _held: Vec<Arc<str>> = pool.iter().map(|(id, _)| pool.name(id).unwrap().clone()).collect();
+    assert_eq!(pool.intern("DOT"), None);
+    assert_eq!(
+        pool.stats(),
+        SymbolPoolStats { size: 2, capacity_limit: Some(2), hits: 1, misses: 6, evictions: 3 }
+    );
+}
+
+#[test]
+fn test_shrink_releases_idle_symbols() {
+    let mut pool = SymbolPool::new();
+    let held = pool.intern_name("A").unwrap();
+    for symbol in ["B", "C", "D"] {
+        pool.intern(symbol);
+    }
+    assert_eq!(pool.shrink(), 3);
+    assert_eq!(pool.iter().collect::<Vec<_>>(), vec![(0, "A")]);
+    // 末尾回收的 ID 被截掉，新合约从 1 开始编号
+    assert_eq!(pool.intern("E"), Some(1));
+    drop(held);
+    assert_eq!(pool.shrink(), 2);
+    assert!(pool.is_empty());
+}
+
+#[test]
+fn test_trades_share_interned_symbol() {
+    let mut sim = Simulation::new();
+    sim.execute(order(1, "BTC", OrderType::Sell, 100, 1));
+    sim.execute(order(2, "BTC", OrderType::Sell, 101, 1));
+    let sweep = trades(sim.execute(order(3, "BTC", OrderType::Buy, 101, 2)));
+    assert_eq!(sweep.len(), 2);
+    assert_eq!(&*sweep[0].symbol, "BTC");
+    assert!(Arc::ptr_eq(&sweep[0].symbol, &sweep[1].symbol));
+
+    // 大宗交易同样使用驻留的合约名
+    let block = BlockTradeRequest {
+        buyer_user_id: 4,
+        seller_user_id: 5,
+        symbol: "BTC".to_string(),
+        price: 101,
+        quantity: 1_000,
+    };
+    let block = trades(sim.execute(EngineCommand::BlockTrade(block)));
+    assert!(Arc::ptr_eq(&block[0].symbol, &sweep[0].symbol));
+}
+
+#[test]
+fn test_engine_rejects_symbols_beyond_limit() {
+    let mut sim = Simulation::with_engine(|engine| engine.with_symbol_limit(1));
+    sim.execute(order(1, "BTC", OrderType::Sell, 100, 1));
+    let outputs = sim.execute(order(1, "ETH", OrderType::Sell, 100, 1));
+    assert!(matches!(
+        outputs.as_slice(),
+        [EngineOutput::Reject(reject)] if reject.reason == RejectReason::SymbolLimitReached
+    ));
+
+    // BTC 到期后订单簿被移除，它的名字可以回收给新合约
+    sim.execute(EngineCommand::Control(ControlCommand::Expire("BTC".to_string())));
+    let (reply, evicted) = mpsc::channel();
+    sim.execute(EngineCommand::Control(ControlCommand::ShrinkSymbolPool(reply)));
+    assert_eq!(evicted.recv().unwrap(), 1);
+    let outputs = sim.execute(order(1, "ETH", OrderType::Sell, 100, 1));
+    assert!(outputs.iter().any(|output| matches!(output, EngineOutput::Confirmation(_))));
+}
@@ -0,0 +1,127 @@
+use matching_engine::engine::{EngineCommand, EngineOutput, MatchingEngine};
+use matching_engine::protocol::{CancelOrderRequest, NewOrderRequest, OrderType};
+use matching_engine::recovery::{self, RecoveryConfig, OUTPUT_LOG_FILE};
+use matching_engine::verify::{self, VerifyConfig};
+use std::path::{Path, PathBuf};
+use tokio::sync::mpsc::{self, UnboundedReceiver};
+
+fn temp_dir(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("verify-{}-{}", name, std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+    dir
+}
+
+fn new_engine() -> (MatchingEngine, UnboundedReceiver<EngineOutput>) {
+    let (_command_sender, command_receiver) = mpsc::unbounded_channel();
+    let (output_sender, output_receiver) = mpsc::unbounded_channel();
+    (MatchingEngine::new(command_receiver, output_sender), output_receiver)
+}
+
+fn command(index: u64) -> EngineCommand {
+    if index % 7 == 6 {
+        return EngineCommand::CancelOrder(CancelOrderRequest {
+            user_id: index % 5 + 1,
+            symbol: "BTC/USD".to_string(),
+            order_id: index / 2 + 1,
+        });
+    }
+    EngineCommand::NewOrder(NewOrderRequest {
+        user_id: index % 5 + 1,
+        symbol: "BTC/USD".to_string(),
+        order_type: if index.is_multiple_of(2) { OrderType::Buy } else { OrderType::Sell },
+        price: 100 + index % 3,
+        quantity: index % 4 + 1,
+    })
+}
+
+// 开启输出日志运行一段订单流，可以在已有的目录上继续
+fn record(dir: &Path, commands: std::ops::Range<u64>, logical_clock: bool) {
+    let (mut engine, _outputs) = new_engine();
+    if logical_clock {
+        engine = engine.with_logical_clock();
+    }
+    let config = RecoveryConfig { record_outputs: true, ..RecoveryConfig::new(dir) };
+    let (mut engine, _) = recovery::recover(&config, engine).unwrap();
+    for index in commands {
+        engine.handle_command(command(index));
+    }
+}
+
+#[test]
+fn test_rerun_matches_recorded_outputs() {
+    let dir = temp_dir("consistent");
+    record(&dir, 0..1_000, false);
+    // 重启后继续记录，恢复时重放的命令不会重复写入输出日志
+    record(&dir, 1_000..2_000, false);
+
+    let report = verify::verify(&dir, VerifyConfig::default()).unwrap();
+    assert!(report.is_consistent(), "{:?}", report.mismatches);
+    assert_eq!((report.commands, report.verified, report.unrecorded), (2_000, 2_000, 0));
+    assert!(report.trades > 0);
+
+    // 系统时钟的时间戳每次运行都不同，要求时间戳一致时全部报告为差异
+    let report = verify::verify(&dir, VerifyConfig { logical_clock: true }).unwrap();
+    assert!(!report.is_consistent());
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn test_logical_clock_timestamps_are_reproduced() {
+    let dir = temp_dir("logical");
+    record(&dir, 0..1_000, true);
+    let report = verify::verify(&dir, VerifyConfig { logical_clock: true }).unwrap();
+    assert!(report.is_consistent(), "{:?}", report.mismatches);
+    assert_eq!(report.verified, 1_000);
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn test_reports_diverging_trade() {
+    let dir = temp_dir("diverge");
+    record(&dir, 0..200, false);
+    // 篡改第一笔有成交的输出记录中的成交价
+    let path = dir.join(OUTPUT_LOG_FILE);
+    let content = std::fs::read_to_string(&path).unwrap();
+    let tampered_line = content.lines().position(|line| line.contains("\"matched_price\":")).unwrap();
+    let lines: Vec<String> = content
+        .lines()
+        .enumerate()
+        .map(|(index, line)| {
+            if index == tampered_line {
+                line.replacen("\"matched_price\":", "\"matched_price\":9", 1)
+            } else {
+                line.to_string()
+            }
+        })
+        .collect();
+    std::fs::write(&path, lines.join("\n") + "\n").unwrap();
+
+    let report = verify::verify(&dir, VerifyConfig::default()).unwrap();
+    assert_eq!(report.mismatches.len(), 1);
+    assert!(report.mismatches[0].starts_with(&format!("命令 #{}:", tampered_line + 1)), "{}", report.mismatches[0]);
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn test_commands_without_output_record_are_not_mismatches() {
+    let dir = temp_dir("unrecorded");
+    // 开启输出日志之前的命令
+    let (engine, _outputs) = new_engine();
+    let (mut engine, _) = recovery::recover(&RecoveryConfig::new(&dir), engine).unwrap();
+    for index in 0..100 {
+        engine.handle_command(command(index));
+    }
+    drop(engine);
+    record(&dir, 100..300, false);
+    // 模拟命令已写入、输出还没写出时崩溃
+    let path = dir.join(OUTPUT_LOG_FILE);
+    let content = std::fs::read_to_string(&path).unwrap();
+    let mut lines: Vec<&str> = content.lines().collect();
+    lines.pop();
+    std::fs::write(&path, lines.join("\n") + "\n").unwrap();
+
+    let report = verify::verify(&dir, VerifyConfig::default()).unwrap();
+    assert!(report.is_consistent(), "{:?}", report.mismatches);
+    assert_eq!((report.commands, report.verified, report.unrecorded), (300, 199, 101));
+    std::fs::remove_dir_all(&dir).unwrap();
+}
@@ -0,0 +1,121 @@
+use matching_engine::protocol::{
+    CancelAck, CancelStatus, ClientMessage, ExecutionReport, OrderConfirmation, OrderReject, OrderStatus, OrderType,
+    RejectReason, ServerMessage,
+};
+use matching_engine::workload::{WorkloadGenerator, WorkloadProfile};
+use std::time::{Duration, Instant};
+
+#[test]
+fn test_parse_profile() {
+    let profile =
+        WorkloadProfile::parse(["connections=4", "rate=250.5", "symbols=BTC/USD,ETH/USD", "cancel=0.2", "replace=0.1"])
+            .unwrap();
+    assert_eq!(profile.connections, 4);
+    assert_eq!(profile.rate, 250.5);
+    assert_eq!(profile.symbols, vec!["BTC/USD", "ETH/USD"]);
+    assert_eq!((profile.cancel_ratio, profile.replace_ratio), (0.2, 0.1));
+    assert_eq!(profile.mid_price, WorkloadProfile::default().mid_price);
+
+    assert!(WorkloadProfile::parse(["connections"]).is_err());
+    assert!(WorkloadProfile::parse(["unknown=1"]).is_err());
+    assert!(WorkloadProfile::parse(["cancel=0.7", "replace=0.5"]).is_err());
+    assert!(WorkloadProfile::parse(["mid=5", "spread=10"]).is_err());
+}
+
+#[test]
+fn test_poisson_arrivals_match_rate() {
+    let profile = WorkloadProfile { rate: 1_000.0, ..WorkloadProfile::default() };
+    let mut generator = WorkloadGenerator::new(profile, 1);
+    let samples = 20_000;
+    let total: Duration = (0..samples).map(|_| generator.next_delay()).sum();
+    let mean = total.as_secs_f64() / samples as f64;
+    // 指数分布均值为 1/rate
+    assert!((mean - 0.001).abs() < 0.0001, "mean interval {}", mean);
+
+    let mut unlimited = WorkloadGenerator::new(WorkloadProfile::default(), 1);
+    assert_eq!(unlimited.next_delay(), Duration::ZERO);
+}
+
+#[test]
+fn test_quotes_stay_around_mid() {
+    let profile = WorkloadProfile { volatility: 0, spread: 5, mid_price: 1_000, ..WorkloadProfile::default() };
+    let mut generator = WorkloadGenerator::new(profile, 1);
+    for _ in 0..1_000 {
+        let ClientMessage::NewOrder(order) = generator.next_message(Instant::now()) else {
+            panic!("没有挂单时只会下单");
+        };
+        match order.order_type {
+            OrderType::Buy => assert!((995..=1_000).contains(&order.price)),
+            OrderType::Sell => assert!((1_000..=1_005).contains(&order.price)),
+        }
+        assert!((1..=5).contains(&order.quantity));
+    }
+}
+
+#[test]
+fn test_tracks_acks_and_latency() {
+    let profile = WorkloadProfile { cancel_ratio: 1.0, ..WorkloadProfile::default() };
+    let mut generator = WorkloadGenerator::new(profile, 7);
+    let start = Instant::now();
+
+    assert!(matches!(generator.next_message(start), ClientMessage::NewOrder(_)));
+    assert!(matches!(generator.next_message(start), ClientMessage::NewOrder(_)));
+    // 其他用户的回报不影响本连接
+    let other = ServerMessage::Confirmation(OrderConfirmation { order_id: 99, user_id: 8 });
+    assert_eq!(generator.on_server_message(&other, start), None);
+
+    let later = start + Duration::from_micros(50);
+    let confirmation = ServerMessage::Confirmation(OrderConfirmation { order_id: 1, user_id: 7 });
+    assert_eq!(generator.on_server_message(&confirmation, later), Some(Duration::from_micros(50)));
+    let reject = ServerMessage::Reject(OrderReject {
+        user_id: 7,
+        symbol: "BTC/USD".to_string(),
+        reason: RejectReason::PriceBandBreach,
+    });
+    assert_eq!(generator.on_server_message(&reject, later), Some(Duration::from_micros(50)));
+    assert_eq!(generator.open_orders(), 1);
+
+    // 有挂单且撤单比例为 1 时撤销该挂单
+    let ClientMessage::CancelOrder(cancel) = generator.next_message(later) else {
+        panic!("应当撤单");
+    };
+    assert_eq!(cancel.order_id, 1);
+    assert_eq!(generator.open_orders(), 0);
+    let ack = ServerMessage::CancelAck(CancelAck {
+        user_id: 7,
+        symbol: cancel.symbol,
+        order_id: 1,
+        cancelled_quantity: 1,
+        status: CancelStatus::Cancelled,
+    });
+    assert_eq!(generator.on_server_message(&ack, later + Duration::from_micros(10)), Some(Duration::from_micros(10)));
+}
+
+#[test]
+fn test_filled_orders_are_forgotten() {
+    let profile = WorkloadProfile { replace_ratio: 1.0, ..WorkloadProfile::default() };
+    let mut generator = WorkloadGenerator::new(profile, 3);
+    let now = Instant::now();
+    generator.next_message(now);
+    generator.on_server_message(&ServerMessage::Confirmation(OrderConfirmation { order_id: 5, user_id: 3 }), now);
+    assert_eq!(generator.open_orders(), 1);
+
+    let report = ExecutionReport {
+        user_id: 3,
+        symbol: "BTC/USD".to_string(),
+        order_id: 5,
+        order_type: OrderType::Buy,
+        status: OrderStatus::Filled,
+        trade_id: 1,
+        last_price: 50_000,
+        last_quantity: 1,
+        cumulative_quantity: 1,
+        leaves_quantity: 0,
+        link_id: None,
+        received_at_ns: None,
+    };
+    generator.on_server_message(&ServerMessage::ExecutionReport(report), now);
+    assert_eq!(generator.open_orders(), 0);
+    // 没有挂单时改单比例不起作用
+    assert!(matches!(generator.next_message(now), ClientMessage::NewOrder(_)));
+}
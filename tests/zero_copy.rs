@@ -0,0 +1,70 @@
+use matching_engine::protocol::{NewOrderRequest, OrderType};
+use matching_engine::sbe::{NewOrderView, SbeError, SbeNewOrder, HEADER_LEN};
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::cell::Cell;
+
+// 统计当前线程的堆分配次数
+struct CountingAllocator;
+
+thread_local! {
+    static ALLOCATIONS: Cell<usize> = const { Cell::new(0) };
+}
+
+unsafe impl GlobalAlloc for CountingAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        ALLOCATIONS.with(|count| count.set(count.get() + 1));
+        System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout)
+    }
+}
+
+#[global_allocator]
+static GLOBAL: CountingAllocator = CountingAllocator;
+
+fn allocations() -> usize {
+    ALLOCATIONS.with(Cell::get)
+}
+
+fn encoded_order() -> [u8; SbeNewOrder::ENCODED_LEN] {
+    let request = NewOrderRequest {
+        user_id: 7,
+        symbol: "BTC/USD".to_string(),
+        order_type: OrderType::Buy,
+        price: 50_000,
+        quantity: 3,
+    };
+    let mut buf = [0u8; SbeNewOrder::ENCODED_LEN];
+    SbeNewOrder::from_request(&request).unwrap().encode(&mut buf).unwrap();
+    buf
+}
+
+#[test]
+fn test_view_reads_fields_without_allocating() {
+    let buf = encoded_order();
+
+    let before = allocations();
+    let view = NewOrderView::new(&buf).unwrap();
+    let fields = (view.user_id(), view.symbol(), view.order_type(), view.price(), view.quantity());
+    let owned = SbeNewOrder::decode(&buf).unwrap();
+    assert_eq!(allocations(), before, "解码不应产生堆分配");
+
+    assert_eq!(fields, (7, "BTC/USD", OrderType::Buy, 50_000, 3));
+    assert_eq!(owned.symbol.as_str(), view.symbol());
+    assert_eq!(view.to_request().symbol, "BTC/USD");
+}
+
+#[test]
+fn test_view_validates_on_construction() {
+    let mut buf = encoded_order();
+    buf[HEADER_LEN + 8 + 16] = 5;
+    assert_eq!(NewOrderView::new(&buf).unwrap_err(), SbeError::InvalidSide(5));
+
+    let mut buf = encoded_order();
+    buf[HEADER_LEN + 8] = 0xff;
+    assert_eq!(NewOrderView::new(&buf).unwrap_err(), SbeError::InvalidSymbol);
+
+    assert!(matches!(NewOrderView::new(&encoded_order()[..10]), Err(SbeError::BufferTooShort { .. })));
+}